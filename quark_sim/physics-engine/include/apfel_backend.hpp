@@ -90,6 +90,12 @@ namespace neuronswquarks::apfel_backend
 
   [[nodiscard]] nlohmann::json success_response(StructureFunctionResult const& result);
 
+  // A handshake request, {"schema_version": 1, "type": "handshake"}, asks only
+  // for the protocol and library versions; no structure function is computed.
+  [[nodiscard]] bool is_handshake_request(nlohmann::json const& input);
+
+  [[nodiscard]] nlohmann::json handshake_response(nlohmann::json const& input);
+
   [[nodiscard]] nlohmann::json error_response(std::string const& code,
                                               std::string const& message,
                                               std::string const& hint);
@@ -8,6 +8,10 @@ namespace neuronswquarks::pythia_dis_generator
 {
   inline constexpr int SchemaVersion = 1;
 
+  // Values of DisEventRequest::process: gamma*/Z exchange or W exchange.
+  inline constexpr char const* NeutralCurrentProcess = "neutral_current_dis";
+  inline constexpr char const* ChargedCurrentProcess = "charged_current_dis";
+
   struct DisEventRequest
   {
     int         schema_version = SchemaVersion;
@@ -26,6 +30,8 @@ namespace neuronswquarks::pythia_dis_generator
     int         pdf_member = 0;
     bool        parton_shower = true;
     bool        hadronization = true;
+    // Raw PYTHIA setting lines, applied after the generator defaults.
+    std::vector<std::string> pythia_settings;
   };
 
   struct EventObservables
@@ -59,6 +65,8 @@ namespace neuronswquarks::pythia_dis_generator
     double max_momentum_mismatch_gev = 0.0;
     double max_energy_mismatch_gev = 0.0;
     double momentum_conservation_tolerance_gev = 1.0e-3;
+    double cross_section_pb = 0.0;
+    double cross_section_error_pb = 0.0;
   };
 
   class GeneratorError : public std::runtime_error
@@ -79,8 +87,24 @@ namespace neuronswquarks::pythia_dis_generator
 
   [[nodiscard]] DisEventRequest request_from_json(nlohmann::json const& input);
 
+  // Stops the event loop of a running generator after the current event. The
+  // run then writes its files for the events accepted so far. Safe to call
+  // from signal handlers and other threads.
+  void request_cancel() noexcept;
+
+  // Writes its output files and, on stderr, one JSON progress line at most
+  // every ProgressIntervalSeconds and a final one; see docs/pythia8_dis_generation.md.
   void run_generator(DisEventRequest const& request, std::string const& output_dir);
 
+  inline constexpr double ProgressIntervalSeconds = 0.25;
+
+  // A handshake request, {"schema_version": 1, "type": "handshake"}, asks only
+  // for the protocol and library versions; no event is generated and the
+  // output directory is not touched.
+  [[nodiscard]] bool is_handshake_request(nlohmann::json const& input);
+
+  [[nodiscard]] nlohmann::json handshake_response(nlohmann::json const& input);
+
   [[nodiscard]] nlohmann::json error_response(std::string const& code,
                                               std::string const& message,
                                               std::string const& hint);
@@ -553,6 +553,25 @@ namespace neuronswquarks::apfel_backend
               {"pdf_size", result.metadata.pdf_size}}}};
   }
 
+  bool is_handshake_request(nlohmann::json const& input)
+  {
+    return input.is_object() && input.value("type", "") == "handshake";
+  }
+
+  nlohmann::json handshake_response(nlohmann::json const& input)
+  {
+    if (required_integer(input, "schema_version") != SchemaVersion)
+      invalid_request("unsupported_schema_version",
+                      "Only schema_version 1 is supported.",
+                      "Set schema_version to 1.");
+    return {{"schema_version", SchemaVersion},
+            {"success", true},
+            {"type", "handshake"},
+            {"backend", "apfel"},
+            {"apfelxx_version", VERSION},
+            {"lhapdf_version", LHAPDF::version()}};
+  }
+
   nlohmann::json error_response(std::string const& code,
                                 std::string const& message,
                                 std::string const& hint)
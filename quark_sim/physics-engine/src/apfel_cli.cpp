@@ -22,6 +22,11 @@ int main()
                            2};
 
       const nlohmann::json document = nlohmann::json::parse(input);
+      if (is_handshake_request(document))
+        {
+          std::cout << handshake_response(document).dump() << '\n';
+          return 0;
+        }
       const StructureFunctionRequest request = request_from_json(document);
       const StructureFunctionResult result = evaluate(request);
       std::cout << success_response(result).dump() << '\n';
@@ -1,9 +1,10 @@
 #include "pythia_dis_generator.hpp"
 #include <nlohmann/json.hpp>
+#include <csignal>
 #include <iostream>
-#include <iterator>
 #include <string>
 #include <fstream>
+#include <thread>
 #include <sys/stat.h>
 
 #ifdef _WIN32
@@ -11,6 +12,30 @@
 #define mkdir(dir, mode) _mkdir(dir)
 #endif
 
+namespace
+{
+  extern "C" void cancel_on_signal(int)
+  {
+    neuronswquarks::pythia_dis_generator::request_cancel();
+  }
+
+  // After the request, stdin carries control messages, one JSON object per
+  // line. {"type": "cancel"} stops generation with a valid partial run; end of
+  // input is not a cancellation, so `pythia_dis_cli <dir> < request.json` works.
+  void read_control_messages()
+  {
+    std::string line;
+    while (std::getline(std::cin, line))
+      {
+        const auto message = nlohmann::json::parse(line, nullptr, false);
+        if (message.is_object() && message.value("type", "") == "cancel")
+          {
+            neuronswquarks::pythia_dis_generator::request_cancel();
+          }
+      }
+  }
+}
+
 int main(int argc, char* argv[])
 {
   using namespace neuronswquarks::pythia_dis_generator;
@@ -22,19 +47,31 @@ int main(int argc, char* argv[])
     }
   std::string output_dir = argv[1];
 
-  const std::string input{std::istreambuf_iterator<char>{std::cin},
-                          std::istreambuf_iterator<char>{}};
+  std::signal(SIGINT, cancel_on_signal);
+  std::signal(SIGTERM, cancel_on_signal);
+#ifdef SIGPIPE
+  // A front end that went away must not stop the run before its files are written.
+  std::signal(SIGPIPE, SIG_IGN);
+#endif
 
   try
     {
-      if (input.empty())
+      if ((std::cin >> std::ws).peek() == std::char_traits<char>::eof())
         throw GeneratorError{"invalid_json",
                              "No JSON request was provided on stdin.",
                              "Pipe one schema-version 1 JSON object to pythia_dis_cli.",
                              2};
 
-      const nlohmann::json document = nlohmann::json::parse(input);
+      // Reads exactly one JSON value and leaves the rest of stdin to the control messages.
+      nlohmann::json document;
+      std::cin >> document;
+      if (is_handshake_request(document))
+        {
+          std::cout << handshake_response(document).dump() << '\n';
+          return 0;
+        }
       const DisEventRequest request = request_from_json(document);
+      std::thread{read_control_messages}.detach();
 
       // Create output directory if it doesn't exist
       #ifdef _WIN32
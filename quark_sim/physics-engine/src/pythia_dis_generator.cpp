@@ -13,6 +13,9 @@
 #include <chrono>
 #include <algorithm>
 #include <stdexcept>
+#include <atomic>
+#include <tuple>
+#include <utility>
 
 #ifndef PYTHIA8_VERSION_STR
 #define PYTHIA8_VERSION_STR "unknown"
@@ -36,6 +39,66 @@
 
 namespace neuronswquarks::pythia_dis_generator
 {
+  namespace
+  {
+    std::atomic<bool> cancel_flag{false};
+
+    // Cross section of the accepted events in pb: PYTHIA's estimate for every
+    // generated event scaled by the fraction of attempts that were accepted,
+    // with the binomial error of that fraction added to PYTHIA's.
+    std::pair<double, double> accepted_cross_section(Pythia8::Pythia& pythia, int accepted, int attempted)
+    {
+      const double acceptance = attempted > 0 ? static_cast<double>(accepted) / attempted : 0.0;
+      const double sigma_gen_pb = pythia.info.sigmaGen() * 1.0e9;
+      const double sigma_err_pb = pythia.info.sigmaErr() * 1.0e9;
+      const double cross_section = sigma_gen_pb * acceptance;
+      if (accepted == 0 || sigma_gen_pb <= 0.0)
+        {
+          return {cross_section, 0.0};
+        }
+      const double relative_sigma = sigma_err_pb / sigma_gen_pb;
+      const double relative_acceptance = std::sqrt((1.0 - acceptance) / accepted);
+      return {cross_section,
+              cross_section * std::sqrt(relative_sigma * relative_sigma + relative_acceptance * relative_acceptance)};
+    }
+
+    template <typename SettingMap>
+    void add_changed_settings(SettingMap const& settings, nlohmann::json& changed)
+    {
+      for (auto const& entry : settings)
+        {
+          if (entry.second.valNow != entry.second.valDefault)
+            {
+              changed[entry.second.name] = entry.second.valNow;
+            }
+        }
+    }
+
+    // Every setting whose value differs from PYTHIA's default, by name. The
+    // seed is left out; metadata.json records it as random_seed, and chunks of
+    // a parallel run differ only there.
+    nlohmann::json changed_settings(Pythia8::Settings& settings)
+    {
+      nlohmann::json changed = nlohmann::json::object();
+      add_changed_settings(settings.getFlagMap(""), changed);
+      add_changed_settings(settings.getModeMap(""), changed);
+      add_changed_settings(settings.getParmMap(""), changed);
+      add_changed_settings(settings.getWordMap(""), changed);
+      add_changed_settings(settings.getFVecMap(""), changed);
+      add_changed_settings(settings.getMVecMap(""), changed);
+      add_changed_settings(settings.getPVecMap(""), changed);
+      add_changed_settings(settings.getWVecMap(""), changed);
+      changed.erase("Random:seed");
+      changed.erase("Random:setSeed");
+      return changed;
+    }
+  }
+
+  void request_cancel() noexcept
+  {
+    cancel_flag.store(true);
+  }
+
   DisEventRequest request_from_json(nlohmann::json const& input)
   {
     DisEventRequest req;
@@ -53,11 +116,11 @@ namespace neuronswquarks::pythia_dis_generator
       {
         req.process = input.at("process").get<std::string>();
       }
-    if (req.process != "neutral_current_dis")
+    if (req.process != NeutralCurrentProcess && req.process != ChargedCurrentProcess)
       {
         throw GeneratorError("invalid_process",
                              "Unsupported process: " + req.process,
-                             "Only neutral_current_dis is supported.", 2);
+                             "Use neutral_current_dis or charged_current_dis.", 2);
       }
     if (input.contains("electron_energy_gev"))
       {
@@ -115,6 +178,10 @@ namespace neuronswquarks::pythia_dis_generator
       {
         req.hadronization = input.at("hadronization").get<bool>();
       }
+    if (input.contains("pythia_settings"))
+      {
+        req.pythia_settings = input.at("pythia_settings").get<std::vector<std::string>>();
+      }
 
     // High level kinematic validation
     if (req.electron_energy_gev <= 0.0 || req.proton_energy_gev <= 0.0)
@@ -151,12 +218,13 @@ namespace neuronswquarks::pythia_dis_generator
     return req;
   }
 
-  // Tracer to identify final-state scattered electron descended from incoming electron
-  static int find_scattered_electron(const Pythia8::Event& event, int electron_beam_idx)
+  // Tracer to identify the final-state scattered lepton (an electron, or the
+  // neutrino of charged-current DIS) descended from the incoming electron
+  static int find_scattered_lepton(const Pythia8::Event& event, int electron_beam_idx, int lepton_id)
   {
     for (int i = 0; i < event.size(); ++i)
       {
-        if (event[i].id() == 11 && event[i].isFinal())
+        if (event[i].id() == lepton_id && event[i].isFinal())
           {
             int current = i;
             while (current > 0)
@@ -169,10 +237,10 @@ namespace neuronswquarks::pythia_dis_generator
               }
           }
       }
-    // Fallback: search for any final-state electron
+    // Fallback: search for any final-state lepton of that kind
     for (int i = 0; i < event.size(); ++i)
       {
-        if (event[i].id() == 11 && event[i].isFinal())
+        if (event[i].id() == lepton_id && event[i].isFinal())
           {
             return i;
           }
@@ -191,8 +259,12 @@ namespace neuronswquarks::pythia_dis_generator
     pythia.readString("Beams:eA = " + std::to_string(request.electron_energy_gev));
     pythia.readString("Beams:eB = " + std::to_string(request.proton_energy_gev));
 
-    // Process selection: t-channel electroweak boson exchange (neutral current DIS)
-    pythia.readString("WeakBosonExchange:ff2ff(t:gmZ) = on");
+    // Process selection: t-channel electroweak boson exchange, gamma*/Z for
+    // neutral-current and W for charged-current DIS
+    const bool charged_current = request.process == ChargedCurrentProcess;
+    pythia.readString(charged_current ? "WeakBosonExchange:ff2ff(t:W) = on"
+                                      : "WeakBosonExchange:ff2ff(t:gmZ) = on");
+    const int scattered_lepton_id = charged_current ? 12 : 11;
 
     // Phase space cuts
     pythia.readString("PhaseSpace:Q2Min = " + std::to_string(request.q2_min_gev2));
@@ -245,6 +317,17 @@ namespace neuronswquarks::pythia_dis_generator
         pythia.readString("HadronLevel:all = on");
       }
 
+    // User settings come last so they override the defaults above.
+    for (std::string const& setting : request.pythia_settings)
+      {
+        if (!pythia.readString(setting, false))
+          {
+            throw GeneratorError("unknown_pythia_setting",
+                                 "Unknown PYTHIA setting: " + setting,
+                                 "Check the setting name against the PYTHIA 8 manual.", 2);
+          }
+      }
+
     // Mute detailed process listings to keep logs readable unless there's an error
     pythia.readString("Init:showChangedSettings = on");
     pythia.readString("Init:showAllSettings = off");
@@ -290,8 +373,43 @@ namespace neuronswquarks::pythia_dis_generator
     int accepted_count = 0;
     int attempted_count = 0;
 
-    while (accepted_count < request.number_of_events)
+    // Progress protocol: one JSON object per line on stderr, read by the Rust front end.
+    using Clock = std::chrono::steady_clock;
+    const auto started = Clock::now();
+    auto last_progress = started;
+    auto emit_progress = [&](std::string const& status)
+      {
+        const double elapsed = std::chrono::duration<double>(Clock::now() - started).count();
+        const auto [cross_section, cross_section_error] = accepted_cross_section(pythia, accepted_count, attempted_count);
+        nlohmann::json progress;
+        progress["type"] = "progress";
+        progress["status"] = status;
+        progress["requested_events"] = request.number_of_events;
+        progress["attempted_events"] = attempted_count;
+        progress["accepted_events"] = accepted_count;
+        progress["failed_events"] = stats.failed_events;
+        progress["vetoed_events"] = stats.vetoed_cuts_events + stats.vetoed_conservation_events;
+        progress["cross_section_pb"] = cross_section;
+        progress["cross_section_error_pb"] = cross_section_error;
+        progress["elapsed_seconds"] = elapsed;
+        if (status == "running" && accepted_count > 0)
+          {
+            progress["eta_seconds"] = elapsed * (request.number_of_events - accepted_count) / accepted_count;
+          }
+        else
+          {
+            progress["eta_seconds"] = nullptr;
+          }
+        std::cerr << progress.dump() + "\n" << std::flush;
+      };
+
+    while (accepted_count < request.number_of_events && !cancel_flag.load())
       {
+        if (Clock::now() - last_progress >= std::chrono::duration<double>(ProgressIntervalSeconds))
+          {
+            last_progress = Clock::now();
+            emit_progress("running");
+          }
         attempted_count++;
         stats.attempted_events = attempted_count;
 
@@ -317,12 +435,13 @@ namespace neuronswquarks::pythia_dis_generator
         const auto& e_beam = pythia.event[electron_beam_idx];
         const auto& p_beam = pythia.event[proton_beam_idx];
 
-        // 2. Identify the scattered electron in the final state
-        int scattered_idx = find_scattered_electron(pythia.event, electron_beam_idx);
+        // 2. Identify the scattered lepton in the final state
+        int scattered_idx = find_scattered_lepton(pythia.event, electron_beam_idx, scattered_lepton_id);
         if (scattered_idx == -1)
           {
             stats.failed_events++;
-            failure_reasons.push_back("scattered_electron_not_found");
+            failure_reasons.push_back(charged_current ? "scattered_neutrino_not_found"
+                                                      : "scattered_electron_not_found");
             continue;
           }
         const auto& e_scattered = pythia.event[scattered_idx];
@@ -463,12 +582,17 @@ namespace neuronswquarks::pythia_dis_generator
 
         accepted_count++;
       }
+    const bool cancelled = accepted_count < request.number_of_events;
 
     csv_file.close();
+    ascii_io.close();
     pythia.stat();
 
     stats.accepted_events = accepted_count;
 
+    std::tie(stats.cross_section_pb, stats.cross_section_error_pb)
+      = accepted_cross_section(pythia, accepted_count, attempted_count);
+
     // Write metadata.json
     std::string metadata_filename = output_dir + "/metadata.json";
     std::ofstream metadata_file(metadata_filename);
@@ -498,6 +622,9 @@ namespace neuronswquarks::pythia_dis_generator
         meta["build_timestamp"] = BUILD_TIMESTAMP_STR;
         meta["parton_shower_state"] = request.parton_shower;
         meta["hadronization_state"] = request.hadronization;
+        meta["process"] = request.process;
+        meta["pythia_user_settings"] = request.pythia_settings;
+        meta["pythia_settings"] = changed_settings(pythia.settings);
         metadata_file << meta.dump(2) << "\n";
         metadata_file.close();
       }
@@ -509,6 +636,7 @@ namespace neuronswquarks::pythia_dis_generator
       {
         nlohmann::json summary;
         summary["success"] = true;
+        summary["cancelled"] = cancelled;
         summary["requested_events"] = stats.requested_events;
         summary["attempted_events"] = stats.attempted_events;
         summary["accepted_events"] = stats.accepted_events;
@@ -519,9 +647,39 @@ namespace neuronswquarks::pythia_dis_generator
         summary["max_energy_mismatch_gev"] = stats.max_energy_mismatch_gev;
         summary["momentum_conservation_tolerance_gev"] = stats.momentum_conservation_tolerance_gev;
         summary["failure_reasons"] = failure_reasons;
+        summary["cross_section_pb"] = stats.cross_section_pb;
+        summary["cross_section_error_pb"] = stats.cross_section_error_pb;
         summary_file << summary.dump(2) << "\n";
         summary_file.close();
       }
+
+    // Last, so the run directory is complete when the front end sees it.
+    emit_progress(cancelled ? "cancelled" : "completed");
+  }
+
+  bool is_handshake_request(nlohmann::json const& input)
+  {
+    return input.is_object() && input.value("type", "") == "handshake";
+  }
+
+  nlohmann::json handshake_response(nlohmann::json const& input)
+  {
+    const int schema_version = input.value("schema_version", SchemaVersion);
+    if (schema_version != SchemaVersion)
+      {
+        throw GeneratorError("invalid_schema_version",
+                             "Unsupported schema version: " + std::to_string(schema_version),
+                             "Use schema version 1.", 2);
+      }
+    nlohmann::json response;
+    response["schema_version"] = SchemaVersion;
+    response["success"] = true;
+    response["type"] = "handshake";
+    response["backend"] = "pythia";
+    response["pythia_version"] = PYTHIA8_VERSION_STR;
+    response["hepmc3_version"] = HEPMC3_VERSION_STR;
+    response["lhapdf_version"] = LHAPDF_VERSION_STR;
+    return response;
   }
 
   nlohmann::json error_response(std::string const& code,
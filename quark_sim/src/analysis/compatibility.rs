@@ -0,0 +1,294 @@
+//! Statistical compatibility of two weighted distributions.
+//!
+//! [`chi_square_test`] compares the shapes of two histograms with the same
+//! binning; [`ks_test`] compares the unbinned weighted samples with the
+//! two-sample Kolmogorov-Smirnov statistic. Weighted samples enter both tests
+//! through `Σw²`, so the effective number of entries of a sample is
+//! `(Σw)² / Σw²`.
+
+use serde::{Deserialize, Serialize};
+
+use super::Histo1D;
+
+/// Iterations of the series and continued fraction of the incomplete gamma
+/// function and terms of the Kolmogorov series.
+const MAX_ITERATIONS: usize = 500;
+const RELATIVE_PRECISION: f64 = 1.0e-15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChiSquareTest {
+    pub chi_square: f64,
+    /// Compared bins minus one for the common normalisation.
+    pub degrees_of_freedom: usize,
+    pub p_value: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KsTest {
+    /// Largest distance between the two weighted cumulative distributions.
+    pub statistic: f64,
+    /// `n₁ n₂ / (n₁ + n₂)` of the effective entries of both samples.
+    pub effective_entries: f64,
+    pub p_value: f64,
+}
+
+/// χ² between the unit-normalised shapes of two histograms,
+/// `Σ (p₁ - p₂)² / (σ₁² + σ₂²)` over the bins where either is filled.
+///
+/// `None` when the binnings differ, either histogram is empty or fewer than
+/// two bins are filled.
+#[must_use]
+pub fn chi_square_test(reference: &Histo1D, other: &Histo1D) -> Option<ChiSquareTest> {
+    if reference.edges() != other.edges() {
+        return None;
+    }
+    let (reference_sum, other_sum) = (reference.integral(), other.integral());
+    if reference_sum <= 0.0 || other_sum <= 0.0 {
+        return None;
+    }
+    let mut chi_square = 0.0;
+    let mut compared = 0_usize;
+    for (first, second) in reference.bins().iter().zip(other.bins()) {
+        let variance =
+            first.sumw2 / (reference_sum * reference_sum) + second.sumw2 / (other_sum * other_sum);
+        if variance <= 0.0 {
+            continue;
+        }
+        let difference = first.sumw / reference_sum - second.sumw / other_sum;
+        chi_square += difference * difference / variance;
+        compared += 1;
+    }
+    let degrees_of_freedom = compared.checked_sub(1).filter(|&ndf| ndf > 0)?;
+    Some(ChiSquareTest {
+        chi_square,
+        degrees_of_freedom,
+        p_value: chi_square_p_value(chi_square, degrees_of_freedom),
+    })
+}
+
+/// Two-sample Kolmogorov-Smirnov test of weighted `(value, weight)` samples.
+/// Entries with a non-finite value or a non-positive weight are ignored.
+///
+/// `None` when either sample has no usable entries.
+#[must_use]
+pub fn ks_test(reference: &[(f64, f64)], other: &[(f64, f64)]) -> Option<KsTest> {
+    let (first, first_entries) = sorted_sample(reference)?;
+    let (second, second_entries) = sorted_sample(other)?;
+    let first_sum: f64 = first.iter().map(|&(_, weight)| weight).sum();
+    let second_sum: f64 = second.iter().map(|&(_, weight)| weight).sum();
+
+    let (mut i, mut j) = (0, 0);
+    let (mut first_cdf, mut second_cdf) = (0.0_f64, 0.0_f64);
+    let mut statistic = 0.0_f64;
+    while i < first.len() && j < second.len() {
+        let value = first[i].0.min(second[j].0);
+        while i < first.len() && first[i].0 <= value {
+            first_cdf += first[i].1 / first_sum;
+            i += 1;
+        }
+        while j < second.len() && second[j].0 <= value {
+            second_cdf += second[j].1 / second_sum;
+            j += 1;
+        }
+        statistic = statistic.max((first_cdf - second_cdf).abs());
+    }
+
+    let effective_entries = first_entries * second_entries / (first_entries + second_entries);
+    let root = effective_entries.sqrt();
+    Some(KsTest {
+        statistic,
+        effective_entries,
+        p_value: kolmogorov_probability((root + 0.12 + 0.11 / root) * statistic),
+    })
+}
+
+/// Usable entries sorted by value, with their effective number of entries.
+fn sorted_sample(sample: &[(f64, f64)]) -> Option<(Vec<(f64, f64)>, f64)> {
+    let mut entries: Vec<(f64, f64)> = sample
+        .iter()
+        .copied()
+        .filter(|&(value, weight)| value.is_finite() && weight.is_finite() && weight > 0.0)
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+    entries.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let sumw: f64 = entries.iter().map(|&(_, weight)| weight).sum();
+    let sumw2: f64 = entries.iter().map(|&(_, weight)| weight * weight).sum();
+    Some((entries, sumw * sumw / sumw2))
+}
+
+/// Probability of a χ² at least as large for `degrees_of_freedom`, the
+/// regularised upper incomplete gamma function `Q(ndf / 2, χ² / 2)`.
+#[must_use]
+pub fn chi_square_p_value(chi_square: f64, degrees_of_freedom: usize) -> f64 {
+    if degrees_of_freedom == 0 || chi_square.is_nan() {
+        return f64::NAN;
+    }
+    upper_regularized_gamma(degrees_of_freedom as f64 / 2.0, chi_square.max(0.0) / 2.0)
+}
+
+/// `Q_KS(λ) = 2 Σ (-1)^(j-1) exp(-2 j² λ²)`, the asymptotic probability of a
+/// scaled Kolmogorov-Smirnov distance of at least `λ`.
+#[must_use]
+pub fn kolmogorov_probability(lambda: f64) -> f64 {
+    // The alternating series converges too slowly below ~0.2, where Q is 1
+    // to double precision anyway.
+    if lambda < 0.2 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for j in 1..=MAX_ITERATIONS {
+        let term = sign * (-2.0 * (j * j) as f64 * lambda * lambda).exp();
+        sum += term;
+        if term.abs() <= RELATIVE_PRECISION * sum.abs() {
+            break;
+        }
+        sign = -sign;
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+fn upper_regularized_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    if x < a + 1.0 {
+        return 1.0 - lower_gamma_series(a, x);
+    }
+    upper_gamma_continued_fraction(a, x)
+}
+
+/// `P(a, x)` from its power series.
+fn lower_gamma_series(a: f64, x: f64) -> f64 {
+    let mut denominator = a;
+    let mut term = 1.0 / a;
+    let mut sum = term;
+    for _ in 0..MAX_ITERATIONS {
+        denominator += 1.0;
+        term *= x / denominator;
+        sum += term;
+        if term.abs() < sum.abs() * RELATIVE_PRECISION {
+            break;
+        }
+    }
+    sum * (-x + a * x.ln() - ln_gamma(a)).exp()
+}
+
+/// `Q(a, x)` from its continued fraction, evaluated with the modified Lentz
+/// method.
+fn upper_gamma_continued_fraction(a: f64, x: f64) -> f64 {
+    const TINY: f64 = 1.0e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut fraction = d;
+    for index in 1..=MAX_ITERATIONS {
+        let an = -(index as f64) * (index as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        fraction *= delta;
+        if (delta - 1.0).abs() < RELATIVE_PRECISION {
+            break;
+        }
+    }
+    (-x + a * x.ln() - ln_gamma(a)).exp() * fraction
+}
+
+/// `ln Γ(x)` for `x > 0` from the Lanczos approximation (g = 7, n = 9).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection formula.
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (index, coefficient)| {
+            sum + coefficient / (x + index as f64 + 1.0)
+        });
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::linear_edges;
+
+    #[test]
+    fn p_values_match_tabulated_distributions() {
+        assert!((ln_gamma(5.0) - 24.0_f64.ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-12);
+        // χ² quantiles at p = 0.05 for 1, 10 and 50 degrees of freedom.
+        assert!((chi_square_p_value(3.841_459, 1) - 0.05).abs() < 1e-6);
+        assert!((chi_square_p_value(18.307_038, 10) - 0.05).abs() < 1e-6);
+        assert!((chi_square_p_value(67.504_807, 50) - 0.05).abs() < 1e-6);
+        assert!((chi_square_p_value(2.0, 2) - (-1.0_f64).exp()).abs() < 1e-12);
+        // The 5% critical value of the Kolmogorov distribution.
+        assert!((kolmogorov_probability(1.358_099) - 0.05).abs() < 1e-5);
+        assert_eq!(kolmogorov_probability(0.0), 1.0);
+    }
+
+    #[test]
+    fn identical_samples_are_compatible_and_shifted_ones_are_not() {
+        let sample: Vec<(f64, f64)> = (0..1_000)
+            .map(|index| ((index as f64 + 0.5) / 1_000.0, 1.0))
+            .collect();
+        let shifted: Vec<(f64, f64)> = sample
+            .iter()
+            .map(|&(value, weight)| ((value + 0.2).min(1.0), weight))
+            .collect();
+
+        let same = ks_test(&sample, &sample).unwrap();
+        assert_eq!(same.statistic, 0.0);
+        assert_eq!(same.p_value, 1.0);
+        assert!((same.effective_entries - 500.0).abs() < 1e-9);
+        let different = ks_test(&sample, &shifted).unwrap();
+        assert!((different.statistic - 0.2).abs() < 2e-3);
+        assert!(different.p_value < 1e-6);
+        assert!(ks_test(&sample, &[(f64::NAN, 1.0), (0.5, 0.0)]).is_none());
+
+        let histogram = |values: &[(f64, f64)]| {
+            let mut histogram = Histo1D::new("/X", "x", linear_edges(10, 0.0, 1.0)).unwrap();
+            for &(value, weight) in values {
+                histogram.fill(value, weight);
+            }
+            histogram
+        };
+        let mut scaled = histogram(&sample);
+        scaled.scale(3.0);
+        let same = chi_square_test(&histogram(&sample), &scaled).unwrap();
+        assert!(same.chi_square.abs() < 1e-12);
+        assert_eq!(same.degrees_of_freedom, 9);
+        assert!((same.p_value - 1.0).abs() < 1e-12);
+        let different = chi_square_test(&histogram(&sample), &histogram(&shifted)).unwrap();
+        assert!(different.p_value < 1e-6);
+
+        let other_binning = Histo1D::new("/X", "x", linear_edges(5, 0.0, 1.0)).unwrap();
+        assert!(chi_square_test(&histogram(&sample), &other_binning).is_none());
+    }
+}
@@ -0,0 +1,772 @@
+//! Fast parametric detector simulation.
+//!
+//! A [`DetectorCard`] describes the response of an idealised HERA-like
+//! detector: energy and angular resolutions for the scattered electron, a
+//! calorimeter response for everything else, polar-angle acceptance, holes
+//! around the beam pipe and efficiency maps. A [`Detector`] applies the card
+//! to the truth-level projections of an [`AnalysisEvent`] with a seeded RNG,
+//! so a run is reproducible, and returns a [`DetectorEvent`] of smeared
+//! four-vectors. [`DetectorEvent::reconstruct`] turns them into `x`, `Q²`
+//! and `y` with the electron, Jacquet–Blondel or Σ method.
+//!
+//! Polar angles follow the crate's laboratory frame: `θ` is measured from the
+//! electron beam along `+z`, and the proton travels towards `θ = 180°`.
+
+use std::error::Error;
+use std::f64::consts::{PI, TAU};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::physics::{compute_dis_kinematics, FourVector, ELECTRON_MASS_GEV};
+
+use super::projections::{AnalysisEvent, Beams};
+
+/// Detector card schema understood by this version.
+pub const DETECTOR_CARD_SCHEMA_VERSION: u32 = 1;
+
+const NEUTRINO_PDG_IDS: [i32; 3] = [12, 14, 16];
+
+/// Calorimeter energy resolution
+/// `σ_E = sqrt(a² E + b² E² + c²)`, i.e. `σ_E/E = a/√E ⊕ b ⊕ c/E`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnergyResolution {
+    /// Stochastic term `a` in `sqrt(GeV)`.
+    pub stochastic: f64,
+    /// Constant term `b`.
+    #[serde(default)]
+    pub constant: f64,
+    /// Noise term `c` in GeV.
+    #[serde(default)]
+    pub noise_gev: f64,
+}
+
+impl EnergyResolution {
+    /// Absolute resolution `σ_E` in GeV at `energy`.
+    #[must_use]
+    pub fn sigma(&self, energy: f64) -> f64 {
+        let energy = energy.max(0.0);
+        (self.stochastic.powi(2) * energy
+            + (self.constant * energy).powi(2)
+            + self.noise_gev.powi(2))
+        .sqrt()
+    }
+}
+
+/// Polar-angle range and energy threshold of a subdetector.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Acceptance {
+    pub theta_min_deg: f64,
+    pub theta_max_deg: f64,
+    /// Minimum measured energy in GeV.
+    #[serde(default)]
+    pub energy_min_gev: f64,
+}
+
+impl Default for Acceptance {
+    fn default() -> Self {
+        Self {
+            theta_min_deg: 0.0,
+            theta_max_deg: 180.0,
+            energy_min_gev: 0.0,
+        }
+    }
+}
+
+/// Uninstrumented cones around the beam pipe. Particles inside them are lost.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BeamPipe {
+    /// Half-opening of the hole around the electron direction, `θ` below it.
+    pub electron_side_hole_deg: f64,
+    /// Half-opening of the hole around the proton direction, `θ` above
+    /// `180°` minus it.
+    pub proton_side_hole_deg: f64,
+}
+
+/// Reconstruction efficiency in bins of true polar angle and energy.
+///
+/// `values[i][j]` applies to `theta_edges_deg[i..=i + 1]` and
+/// `energy_edges_gev[j..=j + 1]`. Particles outside the edges take the
+/// nearest bin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EfficiencyMap {
+    pub theta_edges_deg: Vec<f64>,
+    pub energy_edges_gev: Vec<f64>,
+    pub values: Vec<Vec<f64>>,
+}
+
+/// A flat efficiency or an [`EfficiencyMap`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Efficiency {
+    Constant(f64),
+    Map(EfficiencyMap),
+}
+
+impl Default for Efficiency {
+    fn default() -> Self {
+        Self::Constant(1.0)
+    }
+}
+
+impl Efficiency {
+    #[must_use]
+    pub fn at(&self, theta_deg: f64, energy: f64) -> f64 {
+        match self {
+            Self::Constant(value) => *value,
+            Self::Map(map) => {
+                let bin = |edges: &[f64], value: f64| {
+                    edges[1..edges.len() - 1].partition_point(|&edge| edge <= value)
+                };
+                map.values[bin(&map.theta_edges_deg, theta_deg)][bin(&map.energy_edges_gev, energy)]
+            }
+        }
+    }
+
+    fn validate(&self, field: &str) -> Result<(), DetectorError> {
+        let check_value = |value: f64| {
+            if (0.0..=1.0).contains(&value) {
+                Ok(())
+            } else {
+                Err(invalid(format!(
+                    "{field} values must lie in [0, 1], got {value}"
+                )))
+            }
+        };
+        match self {
+            Self::Constant(value) => check_value(*value),
+            Self::Map(map) => {
+                for (name, edges) in [
+                    ("theta_edges_deg", &map.theta_edges_deg),
+                    ("energy_edges_gev", &map.energy_edges_gev),
+                ] {
+                    if edges.len() < 2
+                        || edges.iter().any(|edge| !edge.is_finite())
+                        || edges.windows(2).any(|pair| pair[0] >= pair[1])
+                    {
+                        return Err(invalid(format!(
+                            "{field}.{name} needs at least two finite, increasing edges"
+                        )));
+                    }
+                }
+                let rows = map.theta_edges_deg.len() - 1;
+                let columns = map.energy_edges_gev.len() - 1;
+                if map.values.len() != rows || map.values.iter().any(|row| row.len() != columns) {
+                    return Err(invalid(format!(
+                        "{field}.values must have {rows} rows of {columns} values"
+                    )));
+                }
+                map.values
+                    .iter()
+                    .flatten()
+                    .try_for_each(|&value| check_value(value))
+            }
+        }
+    }
+}
+
+/// Response to the scattered electron.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ElectronResponse {
+    pub energy_resolution: EnergyResolution,
+    /// Multiplies the true energy before smearing.
+    #[serde(default = "unit_scale")]
+    pub energy_scale: f64,
+    pub theta_resolution_mrad: f64,
+    pub phi_resolution_mrad: f64,
+    #[serde(default)]
+    pub acceptance: Acceptance,
+    #[serde(default)]
+    pub efficiency: Efficiency,
+}
+
+/// Calorimeter response to every other visible final-state particle.
+/// Neutrinos are never seen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HadronResponse {
+    pub energy_resolution: EnergyResolution,
+    /// Mean measured over true energy, below one for a non-compensating
+    /// calorimeter.
+    #[serde(default = "unit_scale")]
+    pub response: f64,
+    pub theta_resolution_mrad: f64,
+    pub phi_resolution_mrad: f64,
+    #[serde(default)]
+    pub acceptance: Acceptance,
+    #[serde(default)]
+    pub efficiency: Efficiency,
+}
+
+fn unit_scale() -> f64 {
+    1.0
+}
+
+/// JSON detector card.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DetectorCard {
+    pub schema_version: u32,
+    #[serde(default)]
+    pub name: String,
+    /// Seed of the smearing RNG.
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub beam_pipe: BeamPipe,
+    pub electron: ElectronResponse,
+    pub hadrons: HadronResponse,
+}
+
+impl DetectorCard {
+    pub fn from_file(path: &Path) -> Result<Self, DetectorError> {
+        let text = fs::read_to_string(path).map_err(|source| DetectorError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let card: Self = serde_json::from_str(&text).map_err(|source| DetectorError::Parse {
+            path: path.to_path_buf(),
+            message: source.to_string(),
+        })?;
+        card.validate()?;
+        Ok(card)
+    }
+
+    pub fn validate(&self) -> Result<(), DetectorError> {
+        if self.schema_version != DETECTOR_CARD_SCHEMA_VERSION {
+            return Err(invalid(format!(
+                "schema_version {} is not supported; expected {DETECTOR_CARD_SCHEMA_VERSION}",
+                self.schema_version
+            )));
+        }
+        let non_negative = |field: &str, value: f64| {
+            if value.is_finite() && value >= 0.0 {
+                Ok(())
+            } else {
+                Err(invalid(format!(
+                    "{field} must be finite and non-negative, got {value}"
+                )))
+            }
+        };
+        let positive = |field: &str, value: f64| {
+            if value.is_finite() && value > 0.0 {
+                Ok(())
+            } else {
+                Err(invalid(format!(
+                    "{field} must be finite and positive, got {value}"
+                )))
+            }
+        };
+        let acceptance = |field: &str, acceptance: &Acceptance| {
+            if !(0.0 <= acceptance.theta_min_deg
+                && acceptance.theta_min_deg < acceptance.theta_max_deg
+                && acceptance.theta_max_deg <= 180.0)
+            {
+                return Err(invalid(format!(
+                    "{field}.acceptance needs 0 <= theta_min_deg < theta_max_deg <= 180"
+                )));
+            }
+            non_negative(
+                &format!("{field}.acceptance.energy_min_gev"),
+                acceptance.energy_min_gev,
+            )
+        };
+        for (field, resolution, theta, phi) in [
+            (
+                "electron",
+                self.electron.energy_resolution,
+                self.electron.theta_resolution_mrad,
+                self.electron.phi_resolution_mrad,
+            ),
+            (
+                "hadrons",
+                self.hadrons.energy_resolution,
+                self.hadrons.theta_resolution_mrad,
+                self.hadrons.phi_resolution_mrad,
+            ),
+        ] {
+            non_negative(
+                &format!("{field}.energy_resolution.stochastic"),
+                resolution.stochastic,
+            )?;
+            non_negative(
+                &format!("{field}.energy_resolution.constant"),
+                resolution.constant,
+            )?;
+            non_negative(
+                &format!("{field}.energy_resolution.noise_gev"),
+                resolution.noise_gev,
+            )?;
+            non_negative(&format!("{field}.theta_resolution_mrad"), theta)?;
+            non_negative(&format!("{field}.phi_resolution_mrad"), phi)?;
+        }
+        positive("electron.energy_scale", self.electron.energy_scale)?;
+        positive("hadrons.response", self.hadrons.response)?;
+        acceptance("electron", &self.electron.acceptance)?;
+        acceptance("hadrons", &self.hadrons.acceptance)?;
+        self.electron.efficiency.validate("electron.efficiency")?;
+        self.hadrons.efficiency.validate("hadrons.efficiency")?;
+        for (field, hole) in [
+            (
+                "beam_pipe.electron_side_hole_deg",
+                self.beam_pipe.electron_side_hole_deg,
+            ),
+            (
+                "beam_pipe.proton_side_hole_deg",
+                self.beam_pipe.proton_side_hole_deg,
+            ),
+        ] {
+            if !(0.0..90.0).contains(&hole) {
+                return Err(invalid(format!("{field} must lie in [0, 90), got {hole}")));
+            }
+        }
+        Ok(())
+    }
+
+    fn in_beam_pipe(&self, theta_deg: f64) -> bool {
+        theta_deg < self.beam_pipe.electron_side_hole_deg
+            || theta_deg > 180.0 - self.beam_pipe.proton_side_hole_deg
+    }
+}
+
+/// Failures loading a detector card or reconstructing an event.
+#[derive(Debug)]
+pub enum DetectorError {
+    Io { path: PathBuf, source: io::Error },
+    Parse { path: PathBuf, message: String },
+    InvalidCard { message: String },
+    UnknownReconstructionMethod { value: String },
+}
+
+fn invalid(message: String) -> DetectorError {
+    DetectorError::InvalidCard { message }
+}
+
+impl fmt::Display for DetectorError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(formatter, "{}: {source}", path.display()),
+            Self::Parse { path, message } => {
+                write!(
+                    formatter,
+                    "invalid detector card {}: {message}",
+                    path.display()
+                )
+            }
+            Self::InvalidCard { message } => write!(formatter, "invalid detector card: {message}"),
+            Self::UnknownReconstructionMethod { value } => write!(
+                formatter,
+                "unknown reconstruction method '{value}'; expected electron, jacquet_blondel or sigma"
+            ),
+        }
+    }
+}
+
+impl Error for DetectorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Smeared, detector-level view of one event, momenta in GeV.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorEvent {
+    /// The measured scattered electron, if it was inside the acceptance and
+    /// reconstructed.
+    pub electron: Option<FourVector>,
+    /// Calorimeter clusters of the other visible particles, massless.
+    pub hadrons: Vec<FourVector>,
+}
+
+/// Inclusive DIS reconstruction methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconstructionMethod {
+    /// Scattered electron only.
+    Electron,
+    /// Hadronic final state only.
+    JacquetBlondel,
+    /// `y` from the hadronic `Σ(E + p_z)`, `Q²` from the electron.
+    Sigma,
+}
+
+impl ReconstructionMethod {
+    pub const ALL: [Self; 3] = [Self::Electron, Self::JacquetBlondel, Self::Sigma];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Electron => "electron",
+            Self::JacquetBlondel => "jacquet_blondel",
+            Self::Sigma => "sigma",
+        }
+    }
+}
+
+impl fmt::Display for ReconstructionMethod {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ReconstructionMethod {
+    type Err = DetectorError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().replace('-', "_").as_str() {
+            "electron" | "e" => Ok(Self::Electron),
+            "jacquet_blondel" | "jb" => Ok(Self::JacquetBlondel),
+            "sigma" => Ok(Self::Sigma),
+            _ => Err(DetectorError::UnknownReconstructionMethod {
+                value: value.to_owned(),
+            }),
+        }
+    }
+}
+
+/// Reconstructed inclusive invariants.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Reconstructed {
+    pub x: f64,
+    pub q2: f64,
+    pub y: f64,
+}
+
+impl DetectorEvent {
+    /// Reconstruct `x`, `Q²` and `y` with `method`. `None` when the needed
+    /// objects were not measured or the result is unphysical.
+    ///
+    /// With the electron beam along `+z`, the hadronic method uses
+    /// `δ_h = Σ_h (E + p_z)`: `y_JB = δ_h / 2E_e` and
+    /// `Q²_JB = p_T,h² / (1 - y_JB)`. The Σ method uses
+    /// `y_Σ = δ_h / (δ_h + E'(1 + cos θ))` and `Q²_Σ = p_T,e'² / (1 - y_Σ)`.
+    /// In both, `x = Q² / (2 k·P y)`.
+    #[must_use]
+    pub fn reconstruct(
+        &self,
+        method: ReconstructionMethod,
+        beams: &Beams,
+    ) -> Option<Reconstructed> {
+        if method == ReconstructionMethod::Electron {
+            let kinematics =
+                compute_dis_kinematics(beams.proton, beams.lepton, self.electron?).ok()?;
+            return Some(Reconstructed {
+                x: kinematics.x,
+                q2: kinematics.q2,
+                y: kinematics.y,
+            });
+        }
+
+        let delta_h = self
+            .hadrons
+            .iter()
+            .map(|hadron| hadron.e + hadron.pz)
+            .sum::<f64>();
+        let (y, q2) = match method {
+            ReconstructionMethod::JacquetBlondel => {
+                let (px, py) = self.hadrons.iter().fold((0.0, 0.0), |(px, py), hadron| {
+                    (px + hadron.px, py + hadron.py)
+                });
+                let y = delta_h / (2.0 * beams.lepton.e);
+                (y, (px * px + py * py) / (1.0 - y))
+            }
+            ReconstructionMethod::Sigma | ReconstructionMethod::Electron => {
+                let electron = self.electron?;
+                let y = delta_h / (delta_h + electron.e + electron.pz);
+                (y, electron.transverse_momentum().powi(2) / (1.0 - y))
+            }
+        };
+        let x = q2 / (2.0 * beams.lepton.dot(beams.proton) * y);
+        (y > 0.0 && y < 1.0 && q2 > 0.0 && x > 0.0 && x < 1.0).then_some(Reconstructed { x, q2, y })
+    }
+}
+
+/// A detector card with its RNG.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    card: DetectorCard,
+    rng: StdRng,
+}
+
+impl Detector {
+    /// Validate `card` and seed the RNG from it.
+    pub fn new(card: DetectorCard) -> Result<Self, DetectorError> {
+        card.validate()?;
+        Ok(Self {
+            rng: StdRng::seed_from_u64(card.seed),
+            card,
+        })
+    }
+
+    #[must_use]
+    pub const fn card(&self) -> &DetectorCard {
+        &self.card
+    }
+
+    /// Smear the truth-level scattered electron and hadronic final state.
+    pub fn simulate(&mut self, event: &AnalysisEvent<'_>) -> DetectorEvent {
+        let electron = event
+            .scattered_electron()
+            .ok()
+            .and_then(|electron| self.measure_electron(electron.momentum));
+        let hadrons = event
+            .hadronic_final_state()
+            .particles
+            .iter()
+            .filter(|(_, pdg_id, _)| !NEUTRINO_PDG_IDS.contains(&pdg_id.abs()))
+            .filter_map(|&(_, _, momentum)| self.measure_hadron(momentum))
+            .collect();
+        DetectorEvent { electron, hadrons }
+    }
+
+    fn measure_electron(&mut self, truth: FourVector) -> Option<FourVector> {
+        let response = self.card.electron.clone();
+        let (theta, phi) = self.accept(truth, &response.acceptance, &response.efficiency)?;
+        let energy = response.energy_scale * truth.e
+            + response.energy_resolution.sigma(truth.e) * self.normal();
+        if energy <= ELECTRON_MASS_GEV.max(response.acceptance.energy_min_gev) {
+            return None;
+        }
+        let (theta, phi) = self.smear_direction(
+            theta,
+            phi,
+            response.theta_resolution_mrad,
+            response.phi_resolution_mrad,
+        );
+        let momentum = (energy * energy - ELECTRON_MASS_GEV * ELECTRON_MASS_GEV).sqrt();
+        Some(from_polar(energy, momentum, theta, phi))
+    }
+
+    fn measure_hadron(&mut self, truth: FourVector) -> Option<FourVector> {
+        let response = self.card.hadrons.clone();
+        let (theta, phi) = self.accept(truth, &response.acceptance, &response.efficiency)?;
+        let energy =
+            response.response * truth.e + response.energy_resolution.sigma(truth.e) * self.normal();
+        if energy <= response.acceptance.energy_min_gev.max(0.0) {
+            return None;
+        }
+        let (theta, phi) = self.smear_direction(
+            theta,
+            phi,
+            response.theta_resolution_mrad,
+            response.phi_resolution_mrad,
+        );
+        Some(from_polar(energy, energy, theta, phi))
+    }
+
+    /// True `(θ, φ)` in radians of a particle that is inside the acceptance
+    /// and passes the efficiency, else `None`.
+    fn accept(
+        &mut self,
+        truth: FourVector,
+        acceptance: &Acceptance,
+        efficiency: &Efficiency,
+    ) -> Option<(f64, f64)> {
+        let momentum = truth.spatial_momentum();
+        if momentum == 0.0 {
+            return None;
+        }
+        let theta = (truth.pz / momentum).clamp(-1.0, 1.0).acos();
+        let theta_deg = theta.to_degrees();
+        if self.card.in_beam_pipe(theta_deg)
+            || theta_deg < acceptance.theta_min_deg
+            || theta_deg > acceptance.theta_max_deg
+            || self.rng.gen::<f64>() >= efficiency.at(theta_deg, truth.e)
+        {
+            return None;
+        }
+        Some((theta, truth.phi()))
+    }
+
+    fn smear_direction(
+        &mut self,
+        theta: f64,
+        phi: f64,
+        theta_mrad: f64,
+        phi_mrad: f64,
+    ) -> (f64, f64) {
+        let mut theta = theta + 1.0e-3 * theta_mrad * self.normal();
+        let mut phi = phi + 1.0e-3 * phi_mrad * self.normal();
+        // Smearing through the beam axis continues on the opposite side.
+        if theta < 0.0 {
+            theta = -theta;
+            phi += PI;
+        } else if theta > PI {
+            theta = TAU - theta;
+            phi += PI;
+        }
+        (theta, phi.rem_euclid(TAU))
+    }
+
+    /// Standard normal deviate by the Box–Muller transform.
+    fn normal(&mut self) -> f64 {
+        let uniform = 1.0 - self.rng.gen::<f64>();
+        (-2.0 * uniform.ln()).sqrt() * (TAU * self.rng.gen::<f64>()).cos()
+    }
+}
+
+fn from_polar(energy: f64, momentum: f64, theta: f64, phi: f64) -> FourVector {
+    let (sin_theta, cos_theta) = theta.sin_cos();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+    FourVector::new(
+        energy,
+        momentum * sin_theta * cos_phi,
+        momentum * sin_theta * sin_phi,
+        momentum * cos_theta,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hepmc3::{GenEvent, GenParticle};
+    use crate::physics::{collider_beams, scattered_electron};
+
+    const HERA_LIKE_CARD: &str = include_str!("../../data/detector/hera_like.json");
+
+    /// Quark-parton-model event: the struck quark `xP + q` and a remnant
+    /// `(1 - x)P`, plus an invisible neutrino.
+    fn event() -> GenEvent {
+        let beams = collider_beams(27.5, 920.0).unwrap();
+        let electron = scattered_electron(25.0, 30.0).unwrap();
+        let kinematics = compute_dis_kinematics(beams.proton, beams.electron, electron).unwrap();
+        let scale = |factor: f64, p: FourVector| {
+            FourVector::new(factor * p.e, factor * p.px, factor * p.py, factor * p.pz)
+        };
+        let quark = scale(kinematics.x, beams.proton) + kinematics.q;
+        let remnant = scale(1.0 - kinematics.x, beams.proton);
+        let particle = |id, pdg_id, momentum, status| GenParticle {
+            id,
+            pdg_id,
+            momentum,
+            mass: 0.0,
+            status,
+            production_vertex: (status == 1).then_some(-1),
+            end_vertex: None,
+        };
+        GenEvent {
+            weights: vec![1.0],
+            particles: vec![
+                particle(1, 11, beams.electron, 4),
+                particle(2, 2212, beams.proton, 4),
+                particle(3, 11, electron, 1),
+                particle(4, 211, quark, 1),
+                particle(5, 2101, remnant, 1),
+                particle(6, 12, FourVector::new(1.0, 1.0, 0.0, 0.0), 1),
+            ],
+            ..GenEvent::default()
+        }
+    }
+
+    fn perfect_card() -> DetectorCard {
+        let mut card: DetectorCard = serde_json::from_str(HERA_LIKE_CARD).unwrap();
+        card.beam_pipe = BeamPipe::default();
+        for resolution in [
+            &mut card.electron.energy_resolution,
+            &mut card.hadrons.energy_resolution,
+        ] {
+            *resolution = EnergyResolution::default();
+        }
+        card.electron.theta_resolution_mrad = 0.0;
+        card.electron.phi_resolution_mrad = 0.0;
+        card.electron.acceptance = Acceptance::default();
+        card.electron.efficiency = Efficiency::default();
+        card.hadrons = HadronResponse {
+            energy_resolution: EnergyResolution::default(),
+            response: 1.0,
+            theta_resolution_mrad: 0.0,
+            phi_resolution_mrad: 0.0,
+            acceptance: Acceptance::default(),
+            efficiency: Efficiency::default(),
+        };
+        card
+    }
+
+    #[test]
+    fn a_perfect_detector_reproduces_the_truth_with_every_method() {
+        let event = event();
+        let view = AnalysisEvent::new(&event);
+        let beams = view.beams().unwrap();
+        let truth = *view.dis_kinematics().unwrap();
+        let measured = Detector::new(perfect_card()).unwrap().simulate(&view);
+
+        // The neutrino is invisible.
+        assert_eq!(measured.hadrons.len(), 2);
+        for method in ReconstructionMethod::ALL {
+            let reconstructed = measured.reconstruct(method, beams).unwrap();
+            assert!(
+                (reconstructed.x / truth.x - 1.0).abs() < 1e-3,
+                "{method}: {reconstructed:?} vs {truth:?}"
+            );
+            assert!((reconstructed.q2 / truth.q2 - 1.0).abs() < 1e-3, "{method}");
+            assert!((reconstructed.y / truth.y - 1.0).abs() < 1e-3, "{method}");
+        }
+    }
+
+    #[test]
+    fn smearing_is_seeded_and_applies_acceptance_and_holes() {
+        let card: DetectorCard = serde_json::from_str(HERA_LIKE_CARD).unwrap();
+        card.validate().unwrap();
+        let event = event();
+        let view = AnalysisEvent::new(&event);
+
+        let first = Detector::new(card.clone()).unwrap().simulate(&view);
+        let second = Detector::new(card.clone()).unwrap().simulate(&view);
+        assert_eq!(first, second);
+        let electron = first.electron.unwrap();
+        assert!((electron.mass_squared().sqrt() - ELECTRON_MASS_GEV).abs() < 1e-6);
+        assert!((electron.e - 25.0).abs() < 5.0 && electron.e != 25.0);
+        // The remnant disappears down the proton-side beam pipe.
+        assert!(first.hadrons.len() <= 1);
+
+        let mut other_seed = card;
+        other_seed.seed += 1;
+        assert_ne!(Detector::new(other_seed).unwrap().simulate(&view), first);
+    }
+
+    #[test]
+    fn rejects_inconsistent_cards() {
+        let mut card = perfect_card();
+        card.electron.efficiency = Efficiency::Map(EfficiencyMap {
+            theta_edges_deg: vec![0.0, 90.0, 180.0],
+            energy_edges_gev: vec![0.0, 1000.0],
+            values: vec![vec![0.9]],
+        });
+        assert!(matches!(
+            card.validate(),
+            Err(DetectorError::InvalidCard { .. })
+        ));
+
+        card.electron.efficiency = Efficiency::Map(EfficiencyMap {
+            theta_edges_deg: vec![0.0, 90.0, 180.0],
+            energy_edges_gev: vec![0.0, 10.0, 1000.0],
+            values: vec![vec![0.5, 0.6], vec![0.7, 0.8]],
+        });
+        card.validate().unwrap();
+        assert_eq!(card.electron.efficiency.at(100.0, 5.0), 0.7);
+        assert_eq!(card.electron.efficiency.at(-5.0, 5000.0), 0.6);
+
+        card.hadrons.acceptance.theta_min_deg = 170.0;
+        card.hadrons.acceptance.theta_max_deg = 10.0;
+        assert!(card.validate().is_err());
+        assert!(
+            serde_json::from_str::<DetectorCard>(r#"{"schema_version": 1, "typo": 0}"#).is_err()
+        );
+        assert!("sigma".parse::<ReconstructionMethod>().is_ok());
+        assert!("double_angle".parse::<ReconstructionMethod>().is_err());
+    }
+}
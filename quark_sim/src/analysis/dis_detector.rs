@@ -0,0 +1,146 @@
+//! Detector-level inclusive DIS distributions.
+
+use crate::hepmc3::GenRunInfo;
+
+use super::detector::ReconstructionMethod;
+use super::histogram::{linear_edges, log_edges, Histo1D, Histogram, HistogramError};
+use super::projections::AnalysisEvent;
+use super::{Analysis, RunStatistics};
+
+/// Reconstructed `Q²`, `x` and `y` with each [`ReconstructionMethod`], and
+/// the relative resolution `(X_rec - X_true) / X_true` of `x` and `Q²`
+/// against the electron-method truth.
+///
+/// Needs a detector card; events without a detector-level view are skipped,
+/// so without one every histogram stays empty.
+#[derive(Debug, Default)]
+pub struct DisDetector {
+    histograms: Option<Vec<Booked>>,
+}
+
+#[derive(Debug)]
+struct Booked {
+    method: ReconstructionMethod,
+    q2: Histo1D,
+    x: Histo1D,
+    y: Histo1D,
+    q2_resolution: Histo1D,
+    x_resolution: Histo1D,
+}
+
+impl DisDetector {
+    pub const NAME: &'static str = "DIS_DETECTOR";
+
+    fn path(method: ReconstructionMethod, name: &str) -> String {
+        format!("/{}/{name}_{method}", Self::NAME)
+    }
+
+    fn book(method: ReconstructionMethod) -> Result<Booked, HistogramError> {
+        Ok(Booked {
+            method,
+            q2: Histo1D::new(
+                Self::path(method, "Q2"),
+                format!("{method} method Q^2 [GeV^2]"),
+                log_edges(40, 1.0, 1.0e5),
+            )?,
+            x: Histo1D::new(
+                Self::path(method, "x"),
+                format!("{method} method x_Bj"),
+                log_edges(40, 1.0e-5, 1.0),
+            )?,
+            y: Histo1D::new(
+                Self::path(method, "y"),
+                format!("{method} method y"),
+                linear_edges(20, 0.0, 1.0),
+            )?,
+            q2_resolution: Histo1D::new(
+                Self::path(method, "Q2_resolution"),
+                format!("{method} method (Q^2_rec - Q^2_true) / Q^2_true"),
+                linear_edges(50, -1.0, 1.0),
+            )?,
+            x_resolution: Histo1D::new(
+                Self::path(method, "x_resolution"),
+                format!("{method} method (x_rec - x_true) / x_true"),
+                linear_edges(50, -1.0, 1.0),
+            )?,
+        })
+    }
+}
+
+impl Analysis for DisDetector {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn init(&mut self, _run_info: &GenRunInfo) -> Result<(), HistogramError> {
+        self.histograms = Some(
+            ReconstructionMethod::ALL
+                .into_iter()
+                .map(Self::book)
+                .collect::<Result<_, _>>()?,
+        );
+        Ok(())
+    }
+
+    fn analyze(&mut self, event: &AnalysisEvent<'_>) {
+        let (Some(histograms), Some(measured), Ok(beams)) =
+            (self.histograms.as_mut(), event.detector(), event.beams())
+        else {
+            return;
+        };
+        let truth = event.dis_kinematics().ok();
+        let weight = event.weight();
+        for booked in histograms {
+            let Some(reconstructed) = measured.reconstruct(booked.method, beams) else {
+                continue;
+            };
+            booked.q2.fill(reconstructed.q2, weight);
+            booked.x.fill(reconstructed.x, weight);
+            booked.y.fill(reconstructed.y, weight);
+            if let Some(truth) = truth {
+                booked
+                    .q2_resolution
+                    .fill(reconstructed.q2 / truth.q2 - 1.0, weight);
+                booked
+                    .x_resolution
+                    .fill(reconstructed.x / truth.x - 1.0, weight);
+            }
+        }
+    }
+
+    fn finalize(&mut self, run: &RunStatistics) {
+        let (Some(histograms), Some(factor)) = (self.histograms.as_mut(), run.scale_factor())
+        else {
+            return;
+        };
+        for booked in histograms {
+            for histogram in [
+                &mut booked.q2,
+                &mut booked.x,
+                &mut booked.y,
+                &mut booked.q2_resolution,
+                &mut booked.x_resolution,
+            ] {
+                histogram.scale(factor);
+            }
+        }
+    }
+
+    fn histograms(&self) -> Vec<Histogram<'_>> {
+        let Some(histograms) = &self.histograms else {
+            return Vec::new();
+        };
+        histograms
+            .iter()
+            .flat_map(|booked| {
+                [
+                    Histogram::OneD(&booked.q2),
+                    Histogram::OneD(&booked.x),
+                    Histogram::OneD(&booked.y),
+                    Histogram::OneD(&booked.q2_resolution),
+                    Histogram::OneD(&booked.x_resolution),
+                ]
+            })
+            .collect()
+    }
+}
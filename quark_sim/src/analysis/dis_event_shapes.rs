@@ -0,0 +1,93 @@
+//! Event shapes in the Breit current hemisphere.
+
+use crate::hepmc3::GenRunInfo;
+
+use super::histogram::{linear_edges, Histo1D, Histogram, HistogramError};
+use super::projections::AnalysisEvent;
+use super::{Analysis, RunStatistics};
+
+/// Distributions of `τ_Q`, `τ_T`, the broadening `B`, the jet mass `ρ` and
+/// the C-parameter of the current hemisphere, for events whose current
+/// hemisphere holds more than `Q/10` of energy.
+#[derive(Debug, Default)]
+pub struct DisEventShapes {
+    histograms: Option<Booked>,
+}
+
+#[derive(Debug)]
+struct Booked {
+    tau_q: Histo1D,
+    tau_t: Histo1D,
+    broadening: Histo1D,
+    jet_mass: Histo1D,
+    c_parameter: Histo1D,
+}
+
+impl DisEventShapes {
+    pub const NAME: &'static str = "DIS_EVENT_SHAPES";
+
+    fn path(name: &str) -> String {
+        format!("/{}/{name}", Self::NAME)
+    }
+}
+
+impl Analysis for DisEventShapes {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn init(&mut self, _run_info: &GenRunInfo) -> Result<(), HistogramError> {
+        self.histograms = Some(Booked {
+            tau_q: Histo1D::new(Self::path("tau_Q"), "tau_Q", linear_edges(20, 0.0, 1.0))?,
+            tau_t: Histo1D::new(Self::path("tau_T"), "tau_T", linear_edges(20, 0.0, 0.5))?,
+            broadening: Histo1D::new(Self::path("B"), "B", linear_edges(20, 0.0, 0.5))?,
+            jet_mass: Histo1D::new(Self::path("rho"), "rho", linear_edges(20, 0.0, 0.3))?,
+            c_parameter: Histo1D::new(Self::path("C"), "C", linear_edges(20, 0.0, 1.0))?,
+        });
+        Ok(())
+    }
+
+    fn analyze(&mut self, event: &AnalysisEvent<'_>) {
+        let Some(histograms) = self.histograms.as_mut() else {
+            return;
+        };
+        let Ok(shapes) = event.event_shapes() else {
+            return;
+        };
+        let weight = event.weight();
+        histograms.tau_q.fill(shapes.tau_q, weight);
+        histograms.tau_t.fill(shapes.tau_t, weight);
+        histograms.broadening.fill(shapes.broadening, weight);
+        histograms.jet_mass.fill(shapes.jet_mass, weight);
+        histograms.c_parameter.fill(shapes.c_parameter, weight);
+    }
+
+    fn finalize(&mut self, run: &RunStatistics) {
+        let (Some(histograms), Some(factor)) = (self.histograms.as_mut(), run.scale_factor())
+        else {
+            return;
+        };
+        for histogram in [
+            &mut histograms.tau_q,
+            &mut histograms.tau_t,
+            &mut histograms.broadening,
+            &mut histograms.jet_mass,
+            &mut histograms.c_parameter,
+        ] {
+            histogram.scale(factor);
+        }
+    }
+
+    fn histograms(&self) -> Vec<Histogram<'_>> {
+        let Some(histograms) = &self.histograms else {
+            return Vec::new();
+        };
+        vec![
+            Histogram::OneD(&histograms.tau_q),
+            Histogram::OneD(&histograms.tau_t),
+            Histogram::OneD(&histograms.broadening),
+            Histogram::OneD(&histograms.jet_mass),
+            Histogram::OneD(&histograms.c_parameter),
+        ]
+    }
+}
@@ -0,0 +1,123 @@
+//! Inclusive neutral-current DIS distributions.
+
+use crate::hepmc3::GenRunInfo;
+
+use super::histogram::{linear_edges, log_edges, Histo1D, Histo2D, Histogram, HistogramError};
+use super::projections::AnalysisEvent;
+use super::{Analysis, RunStatistics};
+
+/// Electron-method `Q²`, `x`, `y` and `W²` spectra, the scattered-electron
+/// energy, the hadronic multiplicity and the `(x, Q²)` plane.
+///
+/// With a known cross section the histograms are in pb, so bin heights are
+/// `dσ/dX`; otherwise they hold summed event weights.
+#[derive(Debug, Default)]
+pub struct DisInclusive {
+    histograms: Option<Booked>,
+}
+
+#[derive(Debug)]
+struct Booked {
+    q2: Histo1D,
+    x: Histo1D,
+    y: Histo1D,
+    w2: Histo1D,
+    electron_energy: Histo1D,
+    multiplicity: Histo1D,
+    x_q2: Histo2D,
+}
+
+impl DisInclusive {
+    pub const NAME: &'static str = "DIS_INCLUSIVE";
+
+    fn path(name: &str) -> String {
+        format!("/{}/{name}", Self::NAME)
+    }
+}
+
+impl Analysis for DisInclusive {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn init(&mut self, _run_info: &GenRunInfo) -> Result<(), HistogramError> {
+        self.histograms = Some(Booked {
+            q2: Histo1D::new(Self::path("Q2"), "Q^2 [GeV^2]", log_edges(40, 1.0, 1.0e5))?,
+            x: Histo1D::new(Self::path("x"), "x_Bj", log_edges(40, 1.0e-5, 1.0))?,
+            y: Histo1D::new(Self::path("y"), "y", linear_edges(20, 0.0, 1.0))?,
+            w2: Histo1D::new(Self::path("W2"), "W^2 [GeV^2]", log_edges(40, 1.0, 1.0e6))?,
+            electron_energy: Histo1D::new(
+                Self::path("E_electron"),
+                "E'_e [GeV]",
+                linear_edges(50, 0.0, 100.0),
+            )?,
+            multiplicity: Histo1D::new(
+                Self::path("N_hadronic"),
+                "hadronic final-state multiplicity",
+                linear_edges(60, -0.5, 299.5),
+            )?,
+            x_q2: Histo2D::new(
+                Self::path("x_Q2"),
+                "x_Bj vs Q^2 [GeV^2]",
+                log_edges(20, 1.0e-5, 1.0),
+                log_edges(20, 1.0, 1.0e5),
+            )?,
+        });
+        Ok(())
+    }
+
+    fn analyze(&mut self, event: &AnalysisEvent<'_>) {
+        let Some(histograms) = self.histograms.as_mut() else {
+            return;
+        };
+        let Ok(kinematics) = event.dis_kinematics() else {
+            return;
+        };
+        let Ok(electron) = event.scattered_electron() else {
+            return;
+        };
+        let weight = event.weight();
+        histograms.q2.fill(kinematics.q2, weight);
+        histograms.x.fill(kinematics.x, weight);
+        histograms.y.fill(kinematics.y, weight);
+        histograms.w2.fill(kinematics.w2, weight);
+        histograms.electron_energy.fill(electron.momentum.e, weight);
+        histograms
+            .multiplicity
+            .fill(event.hadronic_final_state().particles.len() as f64, weight);
+        histograms.x_q2.fill(kinematics.x, kinematics.q2, weight);
+    }
+
+    fn finalize(&mut self, run: &RunStatistics) {
+        let (Some(histograms), Some(factor)) = (self.histograms.as_mut(), run.scale_factor())
+        else {
+            return;
+        };
+        for histogram in [
+            &mut histograms.q2,
+            &mut histograms.x,
+            &mut histograms.y,
+            &mut histograms.w2,
+            &mut histograms.electron_energy,
+            &mut histograms.multiplicity,
+        ] {
+            histogram.scale(factor);
+        }
+        histograms.x_q2.scale(factor);
+    }
+
+    fn histograms(&self) -> Vec<Histogram<'_>> {
+        let Some(histograms) = &self.histograms else {
+            return Vec::new();
+        };
+        vec![
+            Histogram::OneD(&histograms.q2),
+            Histogram::OneD(&histograms.x),
+            Histogram::OneD(&histograms.y),
+            Histogram::OneD(&histograms.w2),
+            Histogram::OneD(&histograms.electron_energy),
+            Histogram::OneD(&histograms.multiplicity),
+            Histogram::TwoD(&histograms.x_q2),
+        ]
+    }
+}
@@ -0,0 +1,144 @@
+//! Inclusive and dijet production in the Breit frame.
+
+use crate::hepmc3::GenRunInfo;
+use crate::physics::{
+    cluster_breit_frame, BreitJetCuts, JetAlgorithm, JetDefinition, RecombinationScheme,
+};
+
+use super::histogram::{linear_edges, Histo1D, Histogram, HistogramError};
+use super::projections::AnalysisEvent;
+use super::{Analysis, RunStatistics};
+
+/// Breit-frame `E_T` bin edges in GeV.
+const ET_EDGES: [f64; 7] = [5.0, 7.0, 11.0, 18.0, 30.0, 50.0, 100.0];
+const ET_MIN_GEV: f64 = 5.0;
+/// Laboratory pseudorapidity range with the proton along `+z`.
+const LAB_ETA_RANGE: (f64, f64) = (-1.0, 2.5);
+
+/// Jets of the HERA inclusive-jet measurements: longitudinally invariant kT
+/// with `R = 1` and `E_T` recombination in the Breit frame, with
+/// `E_T > 5 GeV` and `-1 < η_lab < 2.5`.
+///
+/// Laboratory pseudorapidities are filled with the proton along `+z`, as
+/// H1 and ZEUS quote them, which is the opposite sign to this crate's event
+/// records.
+#[derive(Debug, Default)]
+pub struct DisJets {
+    histograms: Option<Booked>,
+}
+
+#[derive(Debug)]
+struct Booked {
+    inclusive_et: Histo1D,
+    inclusive_eta: Histo1D,
+    multiplicity: Histo1D,
+    dijet_mean_et: Histo1D,
+}
+
+impl DisJets {
+    pub const NAME: &'static str = "DIS_JETS";
+
+    fn path(name: &str) -> String {
+        format!("/{}/{name}", Self::NAME)
+    }
+
+    fn definition() -> JetDefinition {
+        let mut definition = JetDefinition::new(JetAlgorithm::Kt, 1.0);
+        definition.recombination = RecombinationScheme::Et;
+        definition
+    }
+
+    fn cuts() -> BreitJetCuts {
+        BreitJetCuts {
+            et_min: ET_MIN_GEV,
+            breit_eta: None,
+            lab_eta: Some((-LAB_ETA_RANGE.1, -LAB_ETA_RANGE.0)),
+        }
+    }
+}
+
+impl Analysis for DisJets {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn init(&mut self, _run_info: &GenRunInfo) -> Result<(), HistogramError> {
+        self.histograms = Some(Booked {
+            inclusive_et: Histo1D::new(
+                Self::path("ET_Breit"),
+                "inclusive jet E_T^Breit [GeV]",
+                ET_EDGES.to_vec(),
+            )?,
+            inclusive_eta: Histo1D::new(
+                Self::path("eta_lab"),
+                "inclusive jet eta_lab (proton direction +z)",
+                linear_edges(14, LAB_ETA_RANGE.0, LAB_ETA_RANGE.1),
+            )?,
+            multiplicity: Histo1D::new(
+                Self::path("N_jets"),
+                "jet multiplicity",
+                linear_edges(10, -0.5, 9.5),
+            )?,
+            dijet_mean_et: Histo1D::new(
+                Self::path("ET_mean_dijet"),
+                "mean E_T^Breit of the two leading jets [GeV]",
+                ET_EDGES.to_vec(),
+            )?,
+        });
+        Ok(())
+    }
+
+    fn analyze(&mut self, event: &AnalysisEvent<'_>) {
+        let Some(histograms) = self.histograms.as_mut() else {
+            return;
+        };
+        let Ok(frame) = event.breit_frame() else {
+            return;
+        };
+        let hadrons = event.hadronic_final_state().momenta().collect::<Vec<_>>();
+        let Ok(jets) = cluster_breit_frame(&hadrons, frame, &Self::definition(), &Self::cuts())
+        else {
+            return;
+        };
+        let weight = event.weight();
+        for jet in &jets {
+            histograms.inclusive_et.fill(jet.jet.et(), weight);
+            histograms
+                .inclusive_eta
+                .fill(-jet.lab_momentum.pseudorapidity(), weight);
+        }
+        histograms.multiplicity.fill(jets.len() as f64, weight);
+        if let [first, second, ..] = jets.as_slice() {
+            histograms
+                .dijet_mean_et
+                .fill(0.5 * (first.jet.et() + second.jet.et()), weight);
+        }
+    }
+
+    fn finalize(&mut self, run: &RunStatistics) {
+        let (Some(histograms), Some(factor)) = (self.histograms.as_mut(), run.scale_factor())
+        else {
+            return;
+        };
+        for histogram in [
+            &mut histograms.inclusive_et,
+            &mut histograms.inclusive_eta,
+            &mut histograms.multiplicity,
+            &mut histograms.dijet_mean_et,
+        ] {
+            histogram.scale(factor);
+        }
+    }
+
+    fn histograms(&self) -> Vec<Histogram<'_>> {
+        let Some(histograms) = &self.histograms else {
+            return Vec::new();
+        };
+        vec![
+            Histogram::OneD(&histograms.inclusive_et),
+            Histogram::OneD(&histograms.inclusive_eta),
+            Histogram::OneD(&histograms.multiplicity),
+            Histogram::OneD(&histograms.dijet_mean_et),
+        ]
+    }
+}
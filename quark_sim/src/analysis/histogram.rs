@@ -0,0 +1,657 @@
+//! Weighted 1D and 2D histograms with YODA-compatible bin statistics.
+//!
+//! Every bin keeps the sums `Σw`, `Σw²` and the weighted first and second
+//! moments of its fill values, exactly as YODA's `Dbn1D`/`Dbn2D` do. Bin
+//! heights are `Σw / width` with statistical error `√Σw² / width`, so scaling
+//! by a factor `f` multiplies `Σw` by `f` and `Σw²` by `f²` and the relative
+//! errors are unchanged.
+
+use std::fmt::{self, Write as _};
+
+/// Weighted fill statistics of one 1D bin.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Dbn1D {
+    pub sumw: f64,
+    pub sumw2: f64,
+    pub sumwx: f64,
+    pub sumwx2: f64,
+    pub entries: f64,
+}
+
+impl Dbn1D {
+    pub fn fill(&mut self, x: f64, weight: f64) {
+        self.sumw += weight;
+        self.sumw2 += weight * weight;
+        self.sumwx += weight * x;
+        self.sumwx2 += weight * x * x;
+        self.entries += 1.0;
+    }
+
+    pub fn scale_weights(&mut self, factor: f64) {
+        self.sumw *= factor;
+        self.sumw2 *= factor * factor;
+        self.sumwx *= factor;
+        self.sumwx2 *= factor;
+    }
+
+    /// Weighted mean of the fill values, `None` for an empty bin.
+    #[must_use]
+    pub fn mean(&self) -> Option<f64> {
+        (self.sumw != 0.0).then(|| self.sumwx / self.sumw)
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.sumw += other.sumw;
+        self.sumw2 += other.sumw2;
+        self.sumwx += other.sumwx;
+        self.sumwx2 += other.sumwx2;
+        self.entries += other.entries;
+    }
+}
+
+/// Weighted fill statistics of one 2D bin.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Dbn2D {
+    pub sumw: f64,
+    pub sumw2: f64,
+    pub sumwx: f64,
+    pub sumwx2: f64,
+    pub sumwy: f64,
+    pub sumwy2: f64,
+    pub sumwxy: f64,
+    pub entries: f64,
+}
+
+impl Dbn2D {
+    pub fn fill(&mut self, x: f64, y: f64, weight: f64) {
+        self.sumw += weight;
+        self.sumw2 += weight * weight;
+        self.sumwx += weight * x;
+        self.sumwx2 += weight * x * x;
+        self.sumwy += weight * y;
+        self.sumwy2 += weight * y * y;
+        self.sumwxy += weight * x * y;
+        self.entries += 1.0;
+    }
+
+    pub fn scale_weights(&mut self, factor: f64) {
+        self.sumw *= factor;
+        self.sumw2 *= factor * factor;
+        self.sumwx *= factor;
+        self.sumwx2 *= factor;
+        self.sumwy *= factor;
+        self.sumwy2 *= factor;
+        self.sumwxy *= factor;
+    }
+}
+
+/// Invalid histogram binning.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistogramError {
+    TooFewEdges { path: String },
+    UnsortedEdges { path: String },
+}
+
+impl fmt::Display for HistogramError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooFewEdges { path } => {
+                write!(formatter, "histogram {path} needs at least two bin edges")
+            }
+            Self::UnsortedEdges { path } => write!(
+                formatter,
+                "histogram {path} bin edges must be finite and strictly increasing"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HistogramError {}
+
+/// `count` equal-width bin edges spanning `[low, high]`.
+#[must_use]
+pub fn linear_edges(count: usize, low: f64, high: f64) -> Vec<f64> {
+    (0..=count)
+        .map(|index| low + (high - low) * index as f64 / count as f64)
+        .collect()
+}
+
+/// `count` bins of equal width in `ln x` spanning `[low, high]`, with `low > 0`.
+#[must_use]
+pub fn log_edges(count: usize, low: f64, high: f64) -> Vec<f64> {
+    let (low, high) = (low.ln(), high.ln());
+    linear_edges(count, low, high)
+        .into_iter()
+        .map(f64::exp)
+        .collect()
+}
+
+fn validate_edges(path: &str, edges: &[f64]) -> Result<(), HistogramError> {
+    if edges.len() < 2 {
+        return Err(HistogramError::TooFewEdges {
+            path: path.to_owned(),
+        });
+    }
+    if edges.iter().any(|edge| !edge.is_finite()) || edges.windows(2).any(|pair| pair[1] <= pair[0])
+    {
+        return Err(HistogramError::UnsortedEdges {
+            path: path.to_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Index of the bin containing `value`, or `Err(true)` above the last edge and
+/// `Err(false)` below the first. Bins are closed below and open above.
+fn locate(edges: &[f64], value: f64) -> Result<usize, bool> {
+    if value.is_nan() {
+        return Err(true);
+    }
+    if value < edges[0] {
+        return Err(false);
+    }
+    match edges.partition_point(|&edge| edge <= value) {
+        index if index >= edges.len() => Err(true),
+        index => Ok(index - 1),
+    }
+}
+
+/// One-dimensional weighted histogram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histo1D {
+    /// YODA object path, for example `/DIS_INCLUSIVE/Q2`.
+    pub path: String,
+    pub title: String,
+    edges: Vec<f64>,
+    bins: Vec<Dbn1D>,
+    underflow: Dbn1D,
+    overflow: Dbn1D,
+    /// Accumulated scale factor, reported as YODA's `ScaledBy`.
+    scaled_by: f64,
+}
+
+impl Histo1D {
+    pub fn new(
+        path: impl Into<String>,
+        title: impl Into<String>,
+        edges: Vec<f64>,
+    ) -> Result<Self, HistogramError> {
+        let path = path.into();
+        validate_edges(&path, &edges)?;
+        Ok(Self {
+            path,
+            title: title.into(),
+            bins: vec![Dbn1D::default(); edges.len() - 1],
+            edges,
+            underflow: Dbn1D::default(),
+            overflow: Dbn1D::default(),
+            scaled_by: 1.0,
+        })
+    }
+
+    pub fn fill(&mut self, x: f64, weight: f64) {
+        match locate(&self.edges, x) {
+            Ok(bin) => self.bins[bin].fill(x, weight),
+            Err(false) => self.underflow.fill(x, weight),
+            Err(true) => self.overflow.fill(x, weight),
+        }
+    }
+
+    #[must_use]
+    pub fn edges(&self) -> &[f64] {
+        &self.edges
+    }
+
+    #[must_use]
+    pub fn bins(&self) -> &[Dbn1D] {
+        &self.bins
+    }
+
+    #[must_use]
+    pub const fn underflow(&self) -> &Dbn1D {
+        &self.underflow
+    }
+
+    #[must_use]
+    pub const fn overflow(&self) -> &Dbn1D {
+        &self.overflow
+    }
+
+    #[must_use]
+    pub const fn scaled_by(&self) -> f64 {
+        self.scaled_by
+    }
+
+    /// Statistics of all fills, including under- and overflow.
+    #[must_use]
+    pub fn total(&self) -> Dbn1D {
+        let mut total = self.underflow;
+        total.add(&self.overflow);
+        for bin in &self.bins {
+            total.add(bin);
+        }
+        total
+    }
+
+    /// `Σw` of the in-range bins.
+    #[must_use]
+    pub fn integral(&self) -> f64 {
+        self.bins.iter().map(|bin| bin.sumw).sum()
+    }
+
+    /// Height `Σw / width` of bin `index`.
+    #[must_use]
+    pub fn height(&self, index: usize) -> f64 {
+        self.bins[index].sumw / self.width(index)
+    }
+
+    /// Statistical error `√Σw² / width` of bin `index`.
+    #[must_use]
+    pub fn height_error(&self, index: usize) -> f64 {
+        self.bins[index].sumw2.sqrt() / self.width(index)
+    }
+
+    #[must_use]
+    pub fn width(&self, index: usize) -> f64 {
+        self.edges[index + 1] - self.edges[index]
+    }
+
+    /// Multiply all weights by `factor`, propagating `Σw²` quadratically.
+    pub fn scale(&mut self, factor: f64) {
+        for bin in self
+            .bins
+            .iter_mut()
+            .chain([&mut self.underflow, &mut self.overflow])
+        {
+            bin.scale_weights(factor);
+        }
+        self.scaled_by *= factor;
+    }
+
+    /// Scale so the in-range integral equals `area`. Empty histograms are
+    /// left unchanged.
+    pub fn normalize(&mut self, area: f64) {
+        let integral = self.integral();
+        if integral != 0.0 {
+            self.scale(area / integral);
+        }
+    }
+}
+
+/// Two-dimensional weighted histogram on a rectangular grid.
+///
+/// Fills outside the grid are kept in a single outflow accumulator so the
+/// total statistics remain exact; YODA does not persist 2D outflows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histo2D {
+    pub path: String,
+    pub title: String,
+    x_edges: Vec<f64>,
+    y_edges: Vec<f64>,
+    /// Row-major over `x`, i.e. bin `(i, j)` is at `i * ny + j`.
+    bins: Vec<Dbn2D>,
+    outflow: Dbn2D,
+    scaled_by: f64,
+}
+
+impl Histo2D {
+    pub fn new(
+        path: impl Into<String>,
+        title: impl Into<String>,
+        x_edges: Vec<f64>,
+        y_edges: Vec<f64>,
+    ) -> Result<Self, HistogramError> {
+        let path = path.into();
+        validate_edges(&path, &x_edges)?;
+        validate_edges(&path, &y_edges)?;
+        Ok(Self {
+            path,
+            title: title.into(),
+            bins: vec![Dbn2D::default(); (x_edges.len() - 1) * (y_edges.len() - 1)],
+            x_edges,
+            y_edges,
+            outflow: Dbn2D::default(),
+            scaled_by: 1.0,
+        })
+    }
+
+    pub fn fill(&mut self, x: f64, y: f64, weight: f64) {
+        match (locate(&self.x_edges, x), locate(&self.y_edges, y)) {
+            (Ok(i), Ok(j)) => {
+                let index = i * (self.y_edges.len() - 1) + j;
+                self.bins[index].fill(x, y, weight);
+            }
+            _ => self.outflow.fill(x, y, weight),
+        }
+    }
+
+    #[must_use]
+    pub fn x_edges(&self) -> &[f64] {
+        &self.x_edges
+    }
+
+    #[must_use]
+    pub fn y_edges(&self) -> &[f64] {
+        &self.y_edges
+    }
+
+    #[must_use]
+    pub const fn outflow(&self) -> &Dbn2D {
+        &self.outflow
+    }
+
+    #[must_use]
+    pub const fn scaled_by(&self) -> f64 {
+        self.scaled_by
+    }
+
+    /// Statistics of bin `(i, j)`.
+    #[must_use]
+    pub fn bin(&self, i: usize, j: usize) -> &Dbn2D {
+        &self.bins[i * (self.y_edges.len() - 1) + j]
+    }
+
+    #[must_use]
+    pub fn total(&self) -> Dbn2D {
+        let mut total = self.outflow;
+        for bin in &self.bins {
+            total.sumw += bin.sumw;
+            total.sumw2 += bin.sumw2;
+            total.sumwx += bin.sumwx;
+            total.sumwx2 += bin.sumwx2;
+            total.sumwy += bin.sumwy;
+            total.sumwy2 += bin.sumwy2;
+            total.sumwxy += bin.sumwxy;
+            total.entries += bin.entries;
+        }
+        total
+    }
+
+    /// `Σw / area` of bin `(i, j)`.
+    #[must_use]
+    pub fn height(&self, i: usize, j: usize) -> f64 {
+        self.bin(i, j).sumw / self.area(i, j)
+    }
+
+    #[must_use]
+    pub fn height_error(&self, i: usize, j: usize) -> f64 {
+        self.bin(i, j).sumw2.sqrt() / self.area(i, j)
+    }
+
+    #[must_use]
+    pub fn area(&self, i: usize, j: usize) -> f64 {
+        (self.x_edges[i + 1] - self.x_edges[i]) * (self.y_edges[j + 1] - self.y_edges[j])
+    }
+
+    pub fn scale(&mut self, factor: f64) {
+        for bin in self.bins.iter_mut().chain([&mut self.outflow]) {
+            bin.scale_weights(factor);
+        }
+        self.scaled_by *= factor;
+    }
+}
+
+/// A histogram owned by an analysis, borrowed for output.
+#[derive(Debug, Clone, Copy)]
+pub enum Histogram<'a> {
+    OneD(&'a Histo1D),
+    TwoD(&'a Histo2D),
+}
+
+impl Histogram<'_> {
+    #[must_use]
+    pub fn path(&self) -> &str {
+        match self {
+            Self::OneD(histogram) => &histogram.path,
+            Self::TwoD(histogram) => &histogram.path,
+        }
+    }
+}
+
+/// Serialize histograms in the YODA 1 text format read by `rivet-mkhtml`
+/// and `yoda2root`.
+#[must_use]
+pub fn to_yoda(histograms: &[Histogram<'_>]) -> String {
+    let mut output = String::new();
+    for histogram in histograms {
+        match histogram {
+            Histogram::OneD(histogram) => write_yoda_1d(&mut output, histogram),
+            Histogram::TwoD(histogram) => write_yoda_2d(&mut output, histogram),
+        }
+        output.push('\n');
+    }
+    output
+}
+
+fn write_yoda_1d(output: &mut String, histogram: &Histo1D) {
+    let total = histogram.total();
+    let area = histogram.integral();
+    let _ = writeln!(output, "BEGIN YODA_HISTO1D_V2 {}", histogram.path);
+    let _ = writeln!(output, "Path: {}", histogram.path);
+    let _ = writeln!(output, "ScaledBy: {:e}", histogram.scaled_by);
+    let _ = writeln!(output, "Title: {}", histogram.title);
+    let _ = writeln!(output, "Type: Histo1D");
+    let _ = writeln!(output, "---");
+    let _ = writeln!(output, "# Mean: {:e}", total.mean().unwrap_or(f64::NAN));
+    let _ = writeln!(output, "# Area: {area:e}");
+    let _ = writeln!(
+        output,
+        "# ID\t ID\t sumw\t sumw2\t sumwx\t sumwx2\t numEntries"
+    );
+    for (label, dbn) in [
+        ("Total   \tTotal   ", &total),
+        ("Underflow\tUnderflow", histogram.underflow()),
+        ("Overflow\tOverflow", histogram.overflow()),
+    ] {
+        let _ = writeln!(
+            output,
+            "{label}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}",
+            dbn.sumw, dbn.sumw2, dbn.sumwx, dbn.sumwx2, dbn.entries
+        );
+    }
+    let _ = writeln!(
+        output,
+        "# xlow\t xhigh\t sumw\t sumw2\t sumwx\t sumwx2\t numEntries"
+    );
+    for (index, dbn) in histogram.bins().iter().enumerate() {
+        let _ = writeln!(
+            output,
+            "{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}",
+            histogram.edges[index],
+            histogram.edges[index + 1],
+            dbn.sumw,
+            dbn.sumw2,
+            dbn.sumwx,
+            dbn.sumwx2,
+            dbn.entries
+        );
+    }
+    let _ = writeln!(output, "END YODA_HISTO1D_V2");
+}
+
+fn write_yoda_2d(output: &mut String, histogram: &Histo2D) {
+    let total = histogram.total();
+    let (mean_x, mean_y) = if total.sumw == 0.0 {
+        (f64::NAN, f64::NAN)
+    } else {
+        (total.sumwx / total.sumw, total.sumwy / total.sumw)
+    };
+    let volume: f64 = histogram.bins.iter().map(|bin| bin.sumw).sum();
+    let _ = writeln!(output, "BEGIN YODA_HISTO2D_V2 {}", histogram.path);
+    let _ = writeln!(output, "Path: {}", histogram.path);
+    let _ = writeln!(output, "ScaledBy: {:e}", histogram.scaled_by);
+    let _ = writeln!(output, "Title: {}", histogram.title);
+    let _ = writeln!(output, "Type: Histo2D");
+    let _ = writeln!(output, "---");
+    let _ = writeln!(output, "# Mean: ({mean_x:e}, {mean_y:e})");
+    let _ = writeln!(output, "# Volume: {volume:e}");
+    let _ = writeln!(
+        output,
+        "# ID\t ID\t sumw\t sumw2\t sumwx\t sumwx2\t sumwy\t sumwy2\t sumwxy\t numEntries"
+    );
+    let _ = writeln!(
+        output,
+        "Total   \tTotal   \t{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}",
+        total.sumw,
+        total.sumw2,
+        total.sumwx,
+        total.sumwx2,
+        total.sumwy,
+        total.sumwy2,
+        total.sumwxy,
+        total.entries
+    );
+    let _ = writeln!(
+        output,
+        "# 2D outflow persistency not currently supported until API is stable"
+    );
+    let _ = writeln!(
+        output,
+        "# xlow\t xhigh\t ylow\t yhigh\t sumw\t sumw2\t sumwx\t sumwx2\t sumwy\t sumwy2\t sumwxy\t numEntries"
+    );
+    let ny = histogram.y_edges.len() - 1;
+    for (index, dbn) in histogram.bins.iter().enumerate() {
+        let (i, j) = (index / ny, index % ny);
+        let _ = writeln!(
+            output,
+            "{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}\t{:e}",
+            histogram.x_edges[i],
+            histogram.x_edges[i + 1],
+            histogram.y_edges[j],
+            histogram.y_edges[j + 1],
+            dbn.sumw,
+            dbn.sumw2,
+            dbn.sumwx,
+            dbn.sumwx2,
+            dbn.sumwy,
+            dbn.sumwy2,
+            dbn.sumwxy,
+            dbn.entries
+        );
+    }
+    let _ = writeln!(output, "END YODA_HISTO2D_V2");
+}
+
+/// One row per bin, with heights and errors already divided by the bin width
+/// (1D) or area (2D). 1D rows leave the `y` columns empty.
+#[must_use]
+pub fn to_csv(histograms: &[Histogram<'_>]) -> String {
+    let mut output =
+        String::from("histogram,xlow,xhigh,ylow,yhigh,value,error,sumw,sumw2,entries\n");
+    for histogram in histograms {
+        match histogram {
+            Histogram::OneD(histogram) => {
+                for (index, dbn) in histogram.bins().iter().enumerate() {
+                    let _ = writeln!(
+                        output,
+                        "{},{},{},,,{},{},{},{},{}",
+                        histogram.path,
+                        histogram.edges[index],
+                        histogram.edges[index + 1],
+                        histogram.height(index),
+                        histogram.height_error(index),
+                        dbn.sumw,
+                        dbn.sumw2,
+                        dbn.entries
+                    );
+                }
+            }
+            Histogram::TwoD(histogram) => {
+                for i in 0..histogram.x_edges.len() - 1 {
+                    for j in 0..histogram.y_edges.len() - 1 {
+                        let dbn = histogram.bin(i, j);
+                        let _ = writeln!(
+                            output,
+                            "{},{},{},{},{},{},{},{},{},{}",
+                            histogram.path,
+                            histogram.x_edges[i],
+                            histogram.x_edges[i + 1],
+                            histogram.y_edges[j],
+                            histogram.y_edges[j + 1],
+                            histogram.height(i, j),
+                            histogram.height_error(i, j),
+                            dbn.sumw,
+                            dbn.sumw2,
+                            dbn.entries
+                        );
+                    }
+                }
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_bins_and_outflows_and_propagates_errors_through_scaling() {
+        let mut histogram = Histo1D::new("/TEST/h", "h", linear_edges(4, 0.0, 2.0)).unwrap();
+        histogram.fill(0.1, 2.0);
+        histogram.fill(0.2, 1.0);
+        histogram.fill(1.0, 3.0);
+        histogram.fill(2.0, 1.0);
+        histogram.fill(-1.0, 0.5);
+
+        assert_eq!(histogram.bins()[0].sumw, 3.0);
+        assert_eq!(histogram.bins()[0].sumw2, 5.0);
+        assert_eq!(histogram.bins()[2].entries, 1.0);
+        assert_eq!(histogram.overflow().sumw, 1.0);
+        assert_eq!(histogram.underflow().sumw, 0.5);
+        assert_eq!(histogram.total().sumw, 7.5);
+        assert_eq!(histogram.height(0), 6.0);
+
+        let relative_error = histogram.height_error(0) / histogram.height(0);
+        histogram.scale(0.25);
+        assert_eq!(histogram.bins()[0].sumw, 0.75);
+        assert_eq!(histogram.bins()[0].sumw2, 5.0 / 16.0);
+        assert!((histogram.height_error(0) / histogram.height(0) - relative_error).abs() < 1e-15);
+        assert_eq!(histogram.scaled_by(), 0.25);
+
+        histogram.normalize(1.0);
+        assert!((histogram.integral() - 1.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn two_dimensional_bins_are_row_major_and_written_as_yoda() {
+        let mut histogram = Histo2D::new(
+            "/TEST/h2",
+            "h2",
+            linear_edges(2, 0.0, 2.0),
+            log_edges(2, 1.0, 100.0),
+        )
+        .unwrap();
+        histogram.fill(1.5, 5.0, 2.0);
+        histogram.fill(0.5, 50.0, 1.0);
+        histogram.fill(3.0, 5.0, 1.0);
+
+        assert_eq!(histogram.bin(1, 0).sumw, 2.0);
+        assert_eq!(histogram.bin(0, 1).sumw, 1.0);
+        assert_eq!(histogram.outflow().sumw, 1.0);
+        assert_eq!(histogram.total().entries, 3.0);
+
+        let yoda = to_yoda(&[Histogram::TwoD(&histogram)]);
+        assert!(yoda.starts_with("BEGIN YODA_HISTO2D_V2 /TEST/h2\n"));
+        assert_eq!(
+            yoda.lines()
+                .filter(|line| line.starts_with(|c: char| c.is_ascii_digit()))
+                .count(),
+            4
+        );
+        assert!(yoda.trim_end().ends_with("END YODA_HISTO2D_V2"));
+
+        let csv = to_csv(&[Histogram::TwoD(&histogram)]);
+        assert_eq!(csv.lines().count(), 5);
+    }
+
+    #[test]
+    fn rejects_unsorted_edges() {
+        assert!(matches!(
+            Histo1D::new("/TEST/h", "", vec![0.0, 1.0, 1.0]),
+            Err(HistogramError::UnsortedEdges { .. })
+        ));
+        assert!(Histo1D::new("/TEST/h", "", vec![0.0]).is_err());
+    }
+}
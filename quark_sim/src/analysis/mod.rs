@@ -0,0 +1,529 @@
+//! Rivet-style analysis of HepMC3 event files.
+//!
+//! An [`Analysis`] books its histograms in [`Analysis::init`], fills them
+//! from each event in [`Analysis::analyze`] and normalises them in
+//! [`Analysis::finalize`]. Events reach analyses as [`AnalysisEvent`]s, whose
+//! projections (beams, scattered electron, hadronic final state, DIS
+//! invariants, Breit frame and event shapes) are computed once and shared.
+//! [`run_analyses`] streams any plain or gzipped event file through a set of
+//! analyses, and [`write_results`] saves every histogram as YODA and CSV.
+//! With a [`DetectorCard`] in the options each event is also passed through
+//! the parametric [`Detector`], and analyses read the smeared objects from
+//! [`AnalysisEvent::detector`]. [`reweight_events`] copies an event file with
+//! extra weights for other PDF sets and members.
+//!
+//! Histograms are filled with the nominal event weight. At the end of the run
+//! [`RunStatistics::scale_factor`] converts summed weights to pb using the
+//! generator cross section, taken from the run's `summary.json` or, failing
+//! that, from the last event's `GenCrossSection` attribute.
+
+mod compatibility;
+mod detector;
+mod dis_detector;
+mod dis_event_shapes;
+mod dis_inclusive;
+mod dis_jets;
+mod histogram;
+mod projections;
+mod reweighting;
+mod run_comparison;
+mod unfolding;
+
+pub use compatibility::{
+    chi_square_p_value, chi_square_test, kolmogorov_probability, ks_test, ChiSquareTest, KsTest,
+};
+pub use detector::{
+    Acceptance, BeamPipe, Detector, DetectorCard, DetectorError, DetectorEvent, Efficiency,
+    EfficiencyMap, ElectronResponse, EnergyResolution, HadronResponse, Reconstructed,
+    ReconstructionMethod, DETECTOR_CARD_SCHEMA_VERSION,
+};
+pub use dis_detector::DisDetector;
+pub use dis_event_shapes::DisEventShapes;
+pub use dis_inclusive::DisInclusive;
+pub use dis_jets::DisJets;
+pub use histogram::{
+    linear_edges, log_edges, to_csv, to_yoda, Dbn1D, Dbn2D, Histo1D, Histo2D, Histogram,
+    HistogramError,
+};
+pub use projections::{
+    AnalysisEvent, Beams, HadronicFinalState, ProjectionError, ScatteredElectron,
+};
+pub use reweighting::{
+    reweight_events, ReweightedMember, ReweightedSet, ReweightingRun, ReweightingSummary,
+    ReweightingTarget,
+};
+pub use run_comparison::{
+    compare_runs, ComparedRun, CompatibilityTest, DistributionComparison, Observable,
+    ObservableSample, RunComparison, RunComparisonError, RunDistribution, RunSummary,
+    ValueDifference, INCLUSIVE_OBSERVABLES_FILE_NAME,
+};
+pub use unfolding::{
+    fill_measured, fill_response, KinematicBin, KinematicBinning, ResponseMatrix, Spectrum,
+    UnfoldedBin, UnfoldingError, UnfoldingMetadata, UnfoldingMethod, UnfoldingReport,
+    UnfoldingResult, DEFAULT_BAYES_ITERATIONS,
+};
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::hepmc3::{GenEvent, GenRunInfo, Hepmc3Error, Hepmc3Reader, Hepmc3WriteError};
+use crate::physics::PdfReweightingError;
+
+/// Names accepted by [`builtin_analysis`].
+pub const BUILTIN_ANALYSES: &[&str] = &[
+    DisInclusive::NAME,
+    DisJets::NAME,
+    DisEventShapes::NAME,
+    DisDetector::NAME,
+];
+
+/// File names written by [`write_results`].
+pub const YODA_FILE_NAME: &str = "analysis.yoda";
+pub const CSV_FILE_NAME: &str = "analysis.csv";
+
+/// A user analysis run over a stream of events.
+pub trait Analysis {
+    /// Upper-case analysis name, also the prefix of its histogram paths.
+    fn name(&self) -> &str;
+
+    /// Book histograms. Called once, after the run header has been read.
+    fn init(&mut self, run_info: &GenRunInfo) -> Result<(), HistogramError>;
+
+    /// Fill histograms from one event. Events whose projections fail are
+    /// normally skipped.
+    fn analyze(&mut self, event: &AnalysisEvent<'_>);
+
+    /// Normalise histograms once all events have been seen.
+    fn finalize(&mut self, run: &RunStatistics);
+
+    /// Histograms to write, in output order.
+    fn histograms(&self) -> Vec<Histogram<'_>>;
+}
+
+/// Total generator cross section with its statistical error.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CrossSection {
+    pub value_pb: f64,
+    pub error_pb: f64,
+}
+
+impl CrossSection {
+    /// Read `cross_section_pb` and `cross_section_error_pb` from a run's
+    /// `summary.json`. Summaries without a cross section give `None`.
+    pub fn from_summary(path: &Path) -> Result<Option<Self>, AnalysisError> {
+        let text = fs::read_to_string(path).map_err(|source| AnalysisError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let summary: serde_json::Value =
+            serde_json::from_str(&text).map_err(|source| AnalysisError::Summary {
+                path: path.to_path_buf(),
+                message: source.to_string(),
+            })?;
+        let Some(value_pb) = summary.get("cross_section_pb").and_then(|v| v.as_f64()) else {
+            return Ok(None);
+        };
+        let error_pb = summary
+            .get("cross_section_error_pb")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0);
+        if !value_pb.is_finite() || value_pb < 0.0 {
+            return Err(AnalysisError::Summary {
+                path: path.to_path_buf(),
+                message: format!(
+                    "cross_section_pb must be finite and non-negative, got {value_pb}"
+                ),
+            });
+        }
+        Ok(Some(Self { value_pb, error_pb }))
+    }
+
+    /// Parse the HepMC3 `GenCrossSection` event attribute, whose first two
+    /// fields are the cross section and its error in pb.
+    #[must_use]
+    pub fn from_event(event: &GenEvent) -> Option<Self> {
+        let mut fields = event
+            .attribute(0, "GenCrossSection")?
+            .split_whitespace()
+            .map(str::parse::<f64>);
+        let value_pb = fields.next()?.ok()?;
+        let error_pb = fields.next().and_then(Result::ok).unwrap_or(0.0);
+        (value_pb.is_finite() && value_pb >= 0.0).then_some(Self { value_pb, error_pb })
+    }
+}
+
+/// Event count, weight sums and cross section of an analysis run.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct RunStatistics {
+    pub events: usize,
+    pub sum_of_weights: f64,
+    pub sum_of_weights_squared: f64,
+    pub cross_section: Option<CrossSection>,
+}
+
+impl RunStatistics {
+    /// Factor converting summed event weights to pb, `σ / Σw`, when the
+    /// cross section is known and some weight was seen.
+    #[must_use]
+    pub fn scale_factor(&self) -> Option<f64> {
+        let cross_section = self.cross_section?;
+        (self.sum_of_weights != 0.0).then(|| cross_section.value_pb / self.sum_of_weights)
+    }
+}
+
+/// Settings of [`run_analyses`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AnalysisOptions {
+    /// Stop after this many events.
+    pub max_events: Option<usize>,
+    /// Cross section overriding the one found in the event file.
+    pub cross_section: Option<CrossSection>,
+    /// Simulate this detector for every event.
+    pub detector: Option<DetectorCard>,
+}
+
+/// Failures while running analyses or writing their output.
+#[derive(Debug)]
+pub enum AnalysisError {
+    UnknownAnalysis { name: String },
+    Histogram(HistogramError),
+    Detector(DetectorError),
+    Read(Hepmc3Error),
+    Write(Hepmc3WriteError),
+    Reweighting(PdfReweightingError),
+    Io { path: PathBuf, source: io::Error },
+    Summary { path: PathBuf, message: String },
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownAnalysis { name } => write!(
+                formatter,
+                "unknown analysis '{name}'; available analyses: {}",
+                BUILTIN_ANALYSES.join(", ")
+            ),
+            Self::Histogram(source) => write!(formatter, "{source}"),
+            Self::Detector(source) => write!(formatter, "{source}"),
+            Self::Read(source) => write!(formatter, "{source}"),
+            Self::Write(source) => write!(formatter, "{source}"),
+            Self::Reweighting(source) => write!(formatter, "{source}"),
+            Self::Io { path, source } => write!(formatter, "{}: {source}", path.display()),
+            Self::Summary { path, message } => {
+                write!(
+                    formatter,
+                    "invalid run summary {}: {message}",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl Error for AnalysisError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Histogram(source) => Some(source),
+            Self::Detector(source) => Some(source),
+            Self::Read(source) => Some(source),
+            Self::Write(source) => Some(source),
+            Self::Reweighting(source) => Some(source),
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<HistogramError> for AnalysisError {
+    fn from(source: HistogramError) -> Self {
+        Self::Histogram(source)
+    }
+}
+
+impl From<DetectorError> for AnalysisError {
+    fn from(source: DetectorError) -> Self {
+        Self::Detector(source)
+    }
+}
+
+impl From<Hepmc3Error> for AnalysisError {
+    fn from(source: Hepmc3Error) -> Self {
+        Self::Read(source)
+    }
+}
+
+impl From<Hepmc3WriteError> for AnalysisError {
+    fn from(source: Hepmc3WriteError) -> Self {
+        Self::Write(source)
+    }
+}
+
+impl From<PdfReweightingError> for AnalysisError {
+    fn from(source: PdfReweightingError) -> Self {
+        Self::Reweighting(source)
+    }
+}
+
+/// Construct a built-in analysis by name, ignoring case.
+pub fn builtin_analysis(name: &str) -> Result<Box<dyn Analysis>, AnalysisError> {
+    match name.to_ascii_uppercase().as_str() {
+        DisInclusive::NAME => Ok(Box::new(DisInclusive::default())),
+        DisJets::NAME => Ok(Box::new(DisJets::default())),
+        DisEventShapes::NAME => Ok(Box::new(DisEventShapes::default())),
+        DisDetector::NAME => Ok(Box::new(DisDetector::default())),
+        _ => Err(AnalysisError::UnknownAnalysis {
+            name: name.to_owned(),
+        }),
+    }
+}
+
+/// Stream events from `reader` through every analysis: `init`, then
+/// `analyze` for each event, then `finalize`. A detector card in `options` is
+/// validated before the first event is read.
+pub fn run_analyses<R: BufRead>(
+    mut reader: Hepmc3Reader<R>,
+    analyses: &mut [Box<dyn Analysis>],
+    options: &AnalysisOptions,
+) -> Result<RunStatistics, AnalysisError> {
+    let mut detector = options.detector.clone().map(Detector::new).transpose()?;
+    let run_info = reader.read_run_info()?.clone();
+    for analysis in analyses.iter_mut() {
+        analysis.init(&run_info)?;
+    }
+
+    let mut run = RunStatistics::default();
+    let mut event_cross_section = None;
+    let limit = options.max_events.unwrap_or(usize::MAX);
+    for event in reader.by_ref().take(limit) {
+        let event = event?;
+        let mut view = AnalysisEvent::new(&event);
+        if let Some(detector) = detector.as_mut() {
+            let measured = detector.simulate(&view);
+            view = view.with_detector(measured);
+        }
+        run.events += 1;
+        run.sum_of_weights += view.weight();
+        run.sum_of_weights_squared += view.weight().powi(2);
+        for analysis in analyses.iter_mut() {
+            analysis.analyze(&view);
+        }
+        if let Some(cross_section) = CrossSection::from_event(&event) {
+            event_cross_section = Some(cross_section);
+        }
+    }
+
+    run.cross_section = options.cross_section.or(event_cross_section);
+    for analysis in analyses.iter_mut() {
+        analysis.finalize(&run);
+    }
+    Ok(run)
+}
+
+/// Write all histograms to `analysis.yoda` and `analysis.csv` in `directory`.
+pub fn write_results(
+    directory: &Path,
+    analyses: &[Box<dyn Analysis>],
+) -> Result<(PathBuf, PathBuf), AnalysisError> {
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| AnalysisError::Io { path, source }
+    };
+    fs::create_dir_all(directory).map_err(io_error(directory))?;
+    let histograms: Vec<Histogram<'_>> = analyses
+        .iter()
+        .flat_map(|analysis| analysis.histograms())
+        .collect();
+
+    let yoda_path = directory.join(YODA_FILE_NAME);
+    fs::write(&yoda_path, to_yoda(&histograms)).map_err(io_error(&yoda_path))?;
+    let csv_path = directory.join(CSV_FILE_NAME);
+    fs::write(&csv_path, to_csv(&histograms)).map_err(io_error(&csv_path))?;
+    Ok((yoda_path, csv_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hepmc3::{write_file, GenParticle, GenVertex};
+    use crate::physics::{collider_beams, scattered_electron, FourVector};
+
+    fn event(number: i64, weight: f64, theta_deg: f64) -> GenEvent {
+        let beams = collider_beams(27.5, 920.0).unwrap();
+        let electron = scattered_electron(20.0, theta_deg).unwrap();
+        let hadron = beams.electron + beams.proton - electron;
+        let particle = |id, pdg_id, momentum: FourVector, status, production_vertex| GenParticle {
+            id,
+            pdg_id,
+            momentum,
+            mass: 0.0,
+            status,
+            production_vertex,
+            end_vertex: (status == 4).then_some(-1),
+        };
+        GenEvent {
+            event_number: number,
+            weights: vec![weight],
+            particles: vec![
+                particle(1, 11, beams.electron, 4, None),
+                particle(2, 2212, beams.proton, 4, None),
+                particle(3, 11, electron, 1, Some(-1)),
+                particle(4, 92, hadron, 1, Some(-1)),
+            ],
+            vertices: vec![GenVertex {
+                id: -1,
+                status: 0,
+                position: None,
+                incoming: vec![1, 2],
+                outgoing: vec![3, 4],
+            }],
+            attributes: vec![crate::hepmc3::Attribute {
+                target: 0,
+                name: "GenCrossSection".to_owned(),
+                value: "120.0 2.0 1 1".to_owned(),
+            }],
+            ..GenEvent::default()
+        }
+    }
+
+    #[test]
+    fn runs_builtin_analyses_and_normalises_to_the_cross_section() {
+        let directory =
+            std::env::temp_dir().join(format!("quark_sim_analysis_run_{}", std::process::id()));
+        let events = [event(1, 1.0, 160.0), event(2, 3.0, 170.0)];
+        let events_path = directory.join("events.hepmc3");
+        fs::create_dir_all(&directory).unwrap();
+        write_file(&events_path, &GenRunInfo::default(), &events).unwrap();
+
+        let mut analyses = vec![
+            builtin_analysis("dis_inclusive").unwrap(),
+            builtin_analysis("dis_jets").unwrap(),
+        ];
+        let run = run_analyses(
+            Hepmc3Reader::open(&events_path).unwrap(),
+            &mut analyses,
+            &AnalysisOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(run.events, 2);
+        assert_eq!(run.sum_of_weights, 4.0);
+        assert_eq!(run.cross_section.unwrap().value_pb, 120.0);
+        assert_eq!(run.scale_factor(), Some(30.0));
+
+        let histograms = analyses[0].histograms();
+        let Histogram::OneD(q2) = histograms[0] else {
+            panic!("first histogram should be Q²");
+        };
+        assert_eq!(q2.path, "/DIS_INCLUSIVE/Q2");
+        assert!((q2.total().sumw - 120.0).abs() < 1e-9);
+        assert!((q2.total().sumw2 - 900.0 * 10.0).abs() < 1e-6);
+
+        // The single recoiling hadron is back-to-back with the boson in the
+        // Breit frame, so it has no E_T and every event has zero jets.
+        let Histogram::OneD(jets) = analyses[1].histograms()[2] else {
+            panic!("third jet histogram should be the multiplicity");
+        };
+        assert_eq!(jets.path, "/DIS_JETS/N_jets");
+        assert!((jets.bins()[0].sumw - 120.0).abs() < 1e-9);
+
+        let (yoda, csv) = write_results(&directory, &analyses).unwrap();
+        assert!(fs::read_to_string(yoda)
+            .unwrap()
+            .contains("BEGIN YODA_HISTO1D_V2 /DIS_INCLUSIVE/Q2"));
+        assert!(fs::read_to_string(csv)
+            .unwrap()
+            .starts_with("histogram,xlow,xhigh"));
+        let _ = fs::remove_dir_all(directory);
+    }
+
+    #[test]
+    fn detector_cards_feed_smeared_events_to_dis_detector() {
+        let directory = std::env::temp_dir().join(format!(
+            "quark_sim_analysis_detector_{}",
+            std::process::id()
+        ));
+        let events_path = directory.join("events.hepmc3");
+        fs::create_dir_all(&directory).unwrap();
+        write_file(
+            &events_path,
+            &GenRunInfo::default(),
+            &[event(1, 1.0, 160.0), event(2, 1.0, 170.0)],
+        )
+        .unwrap();
+        let card: DetectorCard =
+            serde_json::from_str(include_str!("../../data/detector/hera_like.json")).unwrap();
+
+        let run = |detector: Option<DetectorCard>| {
+            let mut analyses = vec![builtin_analysis(DisDetector::NAME).unwrap()];
+            run_analyses(
+                Hepmc3Reader::open(&events_path).unwrap(),
+                &mut analyses,
+                &AnalysisOptions {
+                    cross_section: Some(CrossSection {
+                        value_pb: 1.0,
+                        error_pb: 0.0,
+                    }),
+                    detector,
+                    ..AnalysisOptions::default()
+                },
+            )
+            .unwrap();
+            let Histogram::OneD(q2) = analyses[0].histograms()[0] else {
+                panic!("first histogram should be the electron-method Q²");
+            };
+            assert_eq!(q2.path, "/DIS_DETECTOR/Q2_electron");
+            q2.total().sumw
+        };
+        assert_eq!(run(None), 0.0);
+        assert!(run(Some(card.clone())) > 0.0);
+
+        let mut broken = card;
+        broken.schema_version = 2;
+        let mut analyses = vec![builtin_analysis("dis_inclusive").unwrap()];
+        assert!(matches!(
+            run_analyses(
+                Hepmc3Reader::open(&events_path).unwrap(),
+                &mut analyses,
+                &AnalysisOptions {
+                    detector: Some(broken),
+                    ..AnalysisOptions::default()
+                },
+            ),
+            Err(AnalysisError::Detector(DetectorError::InvalidCard { .. }))
+        ));
+        let _ = fs::remove_dir_all(directory);
+    }
+
+    #[test]
+    fn reads_the_cross_section_from_a_run_summary() {
+        let directory =
+            std::env::temp_dir().join(format!("quark_sim_analysis_summary_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let summary = directory.join("summary.json");
+        fs::write(
+            &summary,
+            r#"{"success": true, "cross_section_pb": 42.5, "cross_section_error_pb": 0.5}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            CrossSection::from_summary(&summary).unwrap(),
+            Some(CrossSection {
+                value_pb: 42.5,
+                error_pb: 0.5
+            })
+        );
+
+        fs::write(&summary, r#"{"success": true, "accepted_events": 10}"#).unwrap();
+        assert_eq!(CrossSection::from_summary(&summary).unwrap(), None);
+        assert!(matches!(
+            builtin_analysis("NOT_AN_ANALYSIS"),
+            Err(AnalysisError::UnknownAnalysis { .. })
+        ));
+        let _ = fs::remove_dir_all(directory);
+    }
+}
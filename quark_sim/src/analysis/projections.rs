@@ -0,0 +1,369 @@
+//! Event projections shared by all analyses.
+//!
+//! A projection extracts one physics object from a [`GenEvent`]: the beams,
+//! the scattered electron, the hadronic final state, the inclusive DIS
+//! invariants, the Breit frame or the current-hemisphere event shapes. [`AnalysisEvent`] computes each projection at most once per
+//! event, so several analyses run over the same file share the work.
+//!
+//! Momenta are converted to GeV whatever the event's units. The beams and the
+//! scattered electron are put exactly on their mass shell, recomputing the
+//! energy from the three-momentum, because [`compute_dis_kinematics`]
+//! validates the mass shell more tightly than the precision of many files.
+
+use std::cell::OnceCell;
+use std::error::Error;
+use std::fmt;
+
+use crate::hepmc3::{GenEvent, GenParticle};
+use crate::physics::{
+    compute_dis_kinematics, current_hemisphere_event_shapes, BreitFrame, BreitFrameError, DisError,
+    DisKinematics, EventShapeError, EventShapes, FourVector, ELECTRON_MASS_GEV, PROTON_MASS_GEV,
+};
+
+use super::detector::DetectorEvent;
+
+const BEAM_STATUS: i32 = 4;
+const ELECTRON_PDG_ID: i32 = 11;
+const PROTON_PDG_ID: i32 = 2212;
+
+/// Reasons a projection cannot be built for an event. Analyses normally skip
+/// such events.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectionError {
+    MissingBeam { particle: &'static str },
+    MissingScatteredElectron,
+    Kinematics(DisError),
+    BreitFrame(BreitFrameError),
+    EventShapes(EventShapeError),
+}
+
+impl fmt::Display for ProjectionError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingBeam { particle } => {
+                write!(formatter, "event has no incoming {particle} beam")
+            }
+            Self::MissingScatteredElectron => {
+                write!(formatter, "event has no final-state scattered electron")
+            }
+            Self::Kinematics(source) => write!(formatter, "invalid DIS kinematics: {source}"),
+            Self::BreitFrame(source) => write!(formatter, "no Breit frame: {source}"),
+            Self::EventShapes(source) => write!(formatter, "no event shapes: {source}"),
+        }
+    }
+}
+
+impl Error for ProjectionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Kinematics(source) => Some(source),
+            Self::BreitFrame(source) => Some(source),
+            Self::EventShapes(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Incoming electron (or positron) and proton.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Beams {
+    pub lepton_pdg_id: i32,
+    pub lepton: FourVector,
+    pub proton: FourVector,
+}
+
+impl Beams {
+    /// Beam particles have status 4. Files without status codes fall back to
+    /// particles that have no production vertex.
+    pub fn project(event: &GenEvent) -> Result<Self, ProjectionError> {
+        let find = |pdg: fn(i32) -> bool| {
+            event
+                .particles
+                .iter()
+                .find(|particle| particle.status == BEAM_STATUS && pdg(particle.pdg_id))
+                .or_else(|| {
+                    event.particles.iter().find(|particle| {
+                        particle.production_vertex.is_none() && pdg(particle.pdg_id)
+                    })
+                })
+        };
+        let lepton =
+            find(|pdg| pdg.abs() == ELECTRON_PDG_ID).ok_or(ProjectionError::MissingBeam {
+                particle: "electron",
+            })?;
+        let proton = find(|pdg| pdg == PROTON_PDG_ID)
+            .ok_or(ProjectionError::MissingBeam { particle: "proton" })?;
+        Ok(Self {
+            lepton_pdg_id: lepton.pdg_id,
+            lepton: on_shell(momentum_gev(event, lepton), ELECTRON_MASS_GEV),
+            proton: on_shell(momentum_gev(event, proton), PROTON_MASS_GEV),
+        })
+    }
+}
+
+/// The final-state lepton of the beam flavour with the highest energy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScatteredElectron {
+    /// HepMC3 id of the selected particle.
+    pub particle_id: i32,
+    pub momentum: FourVector,
+}
+
+impl ScatteredElectron {
+    pub fn project(event: &GenEvent, beams: &Beams) -> Result<Self, ProjectionError> {
+        let particle = event
+            .final_state()
+            .filter(|particle| particle.pdg_id == beams.lepton_pdg_id)
+            .max_by(|a, b| a.momentum.e.total_cmp(&b.momentum.e))
+            .ok_or(ProjectionError::MissingScatteredElectron)?;
+        Ok(Self {
+            particle_id: particle.id,
+            momentum: on_shell(momentum_gev(event, particle), ELECTRON_MASS_GEV),
+        })
+    }
+}
+
+/// Final-state particles other than the scattered electron.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HadronicFinalState {
+    /// `(HepMC3 id, PDG id, momentum)` of each particle.
+    pub particles: Vec<(i32, i32, FourVector)>,
+}
+
+impl HadronicFinalState {
+    #[must_use]
+    pub fn project(event: &GenEvent, scattered: Option<&ScatteredElectron>) -> Self {
+        let excluded = scattered.map(|electron| electron.particle_id);
+        Self {
+            particles: event
+                .final_state()
+                .filter(|particle| Some(particle.id) != excluded)
+                .map(|particle| (particle.id, particle.pdg_id, momentum_gev(event, particle)))
+                .collect(),
+        }
+    }
+
+    pub fn momenta(&self) -> impl Iterator<Item = FourVector> + '_ {
+        self.particles.iter().map(|&(_, _, momentum)| momentum)
+    }
+
+    /// Four-momentum sum of the hadronic final state.
+    #[must_use]
+    pub fn total(&self) -> FourVector {
+        self.momenta()
+            .fold(FourVector::new(0.0, 0.0, 0.0, 0.0), |sum, momentum| {
+                sum + momentum
+            })
+    }
+}
+
+/// One event as seen by analyses, with lazily computed projections.
+#[derive(Debug)]
+pub struct AnalysisEvent<'a> {
+    event: &'a GenEvent,
+    weight: f64,
+    beams: OnceCell<Result<Beams, ProjectionError>>,
+    scattered_electron: OnceCell<Result<ScatteredElectron, ProjectionError>>,
+    hadronic_final_state: OnceCell<HadronicFinalState>,
+    kinematics: OnceCell<Result<DisKinematics, ProjectionError>>,
+    breit_frame: OnceCell<Result<BreitFrame, ProjectionError>>,
+    event_shapes: OnceCell<Result<EventShapes, ProjectionError>>,
+    detector: Option<DetectorEvent>,
+}
+
+impl<'a> AnalysisEvent<'a> {
+    #[must_use]
+    pub fn new(event: &'a GenEvent) -> Self {
+        Self {
+            event,
+            weight: event.weight(),
+            beams: OnceCell::new(),
+            scattered_electron: OnceCell::new(),
+            hadronic_final_state: OnceCell::new(),
+            kinematics: OnceCell::new(),
+            breit_frame: OnceCell::new(),
+            event_shapes: OnceCell::new(),
+            detector: None,
+        }
+    }
+
+    /// Attach the detector-level view of this event.
+    #[must_use]
+    pub fn with_detector(mut self, detector: DetectorEvent) -> Self {
+        self.detector = Some(detector);
+        self
+    }
+
+    #[must_use]
+    pub const fn event(&self) -> &'a GenEvent {
+        self.event
+    }
+
+    /// Nominal event weight used for filling.
+    #[must_use]
+    pub const fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    pub fn beams(&self) -> Result<&Beams, ProjectionError> {
+        self.beams
+            .get_or_init(|| Beams::project(self.event))
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    pub fn scattered_electron(&self) -> Result<&ScatteredElectron, ProjectionError> {
+        self.scattered_electron
+            .get_or_init(|| {
+                let beams = self.beams()?;
+                ScatteredElectron::project(self.event, beams)
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// Final state without the scattered electron. When no electron is found
+    /// the whole final state is returned.
+    pub fn hadronic_final_state(&self) -> &HadronicFinalState {
+        self.hadronic_final_state
+            .get_or_init(|| HadronicFinalState::project(self.event, self.scattered_electron().ok()))
+    }
+
+    /// Smeared objects, when the run simulates a detector.
+    #[must_use]
+    pub const fn detector(&self) -> Option<&DetectorEvent> {
+        self.detector.as_ref()
+    }
+
+    /// Electron-method DIS invariants from the beams and scattered electron.
+    pub fn dis_kinematics(&self) -> Result<&DisKinematics, ProjectionError> {
+        self.kinematics
+            .get_or_init(|| {
+                let beams = self.beams()?;
+                let electron = self.scattered_electron()?;
+                compute_dis_kinematics(beams.proton, beams.lepton, electron.momentum)
+                    .map_err(ProjectionError::Kinematics)
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// Breit frame built from the proton beam and the electron-method `q`.
+    pub fn breit_frame(&self) -> Result<&BreitFrame, ProjectionError> {
+        self.breit_frame
+            .get_or_init(|| {
+                let beams = self.beams()?;
+                let kinematics = self.dis_kinematics()?;
+                BreitFrame::from_kinematics(beams.proton, kinematics)
+                    .map_err(ProjectionError::BreitFrame)
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// Event shapes of the hadronic final state in the Breit current
+    /// hemisphere.
+    pub fn event_shapes(&self) -> Result<&EventShapes, ProjectionError> {
+        self.event_shapes
+            .get_or_init(|| {
+                let frame = self.breit_frame()?;
+                let hadrons = self.hadronic_final_state().momenta().collect::<Vec<_>>();
+                current_hemisphere_event_shapes(&hadrons, frame)
+                    .map_err(ProjectionError::EventShapes)
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+}
+
+fn momentum_gev(event: &GenEvent, particle: &GenParticle) -> FourVector {
+    let factor = event.units.momentum.to_gev();
+    let momentum = particle.momentum;
+    FourVector::new(
+        factor * momentum.e,
+        factor * momentum.px,
+        factor * momentum.py,
+        factor * momentum.pz,
+    )
+}
+
+fn on_shell(momentum: FourVector, mass: f64) -> FourVector {
+    let energy = (momentum.spatial_momentum().powi(2) + mass * mass).sqrt();
+    FourVector::new(energy, momentum.px, momentum.py, momentum.pz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hepmc3::parse_ascii;
+
+    const EVENT: &str = "HepMC::Version 3.02.06
+HepMC::Asciiv3-START_EVENT_LISTING
+E 1 1 6
+U MEV MM
+W 2.0
+P 1 0 11 0 0 27500 27500 0.511 4
+P 2 0 2212 0 0 -920000 920000.478 938.272 4
+V -1 0 [1,2]
+P 3 -1 11 5000 0 20000 20620.4 0.511 1
+P 4 -1 211 -3000 1000 -400000 400012.6 139.57 1
+P 5 -1 11 100 0 100 141.42 0.511 1
+P 6 -1 22 0 0 1 1 0 1
+HepMC::Asciiv3-END_EVENT_LISTING
+";
+
+    #[test]
+    fn projects_the_highest_energy_electron_and_the_hadronic_final_state() {
+        let file = parse_ascii(EVENT).unwrap();
+        let event = AnalysisEvent::new(&file.events[0]);
+
+        assert_eq!(event.weight(), 2.0);
+        let beams = event.beams().unwrap();
+        assert!((beams.lepton.e - 27.5).abs() < 1e-6);
+        assert!((beams.proton.mass_squared().sqrt() - PROTON_MASS_GEV).abs() < 1e-6);
+
+        let electron = event.scattered_electron().unwrap();
+        assert_eq!(electron.particle_id, 3);
+        assert!((electron.momentum.px - 5.0).abs() < 1e-12);
+
+        let hadrons = event.hadronic_final_state();
+        let ids: Vec<i32> = hadrons.particles.iter().map(|&(id, _, _)| id).collect();
+        assert_eq!(ids, [4, 5, 6]);
+
+        let kinematics = event.dis_kinematics().unwrap();
+        assert!(kinematics.q2 > 0.0);
+        assert!(kinematics.x > 0.0 && kinematics.x < 1.0);
+
+        let breit = event.breit_frame().unwrap();
+        let boson = breit.to_breit(kinematics.q);
+        assert!(boson.e.abs() < 1e-9 * breit.q());
+        assert!((boson.pz + breit.q()).abs() < 1e-9 * breit.q());
+        // The only hard hadron goes forward into the target hemisphere.
+        assert!(matches!(
+            event.event_shapes(),
+            Err(ProjectionError::EventShapes(_))
+        ));
+    }
+
+    #[test]
+    fn reports_missing_beams() {
+        let file = parse_ascii(
+            "HepMC::Version 3.02.06
+HepMC::Asciiv3-START_EVENT_LISTING
+E 1 0 1
+P 1 0 211 0 0 1 2 0.13957 1
+HepMC::Asciiv3-END_EVENT_LISTING
+",
+        )
+        .unwrap();
+        let event = AnalysisEvent::new(&file.events[0]);
+
+        assert_eq!(
+            event.dis_kinematics(),
+            Err(ProjectionError::MissingBeam {
+                particle: "electron"
+            })
+        );
+        assert_eq!(event.hadronic_final_state().particles.len(), 1);
+    }
+}
@@ -0,0 +1,330 @@
+//! PDF reweighting of HepMC3 event files.
+//!
+//! [`reweight_events`] copies an event file, appending one weight per target
+//! PDF member to every event, and sums the weights on the way.
+//! [`ReweightingSummary`] turns the sums into reweighted cross sections and,
+//! for complete sets, into PDF uncertainties.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use super::{AnalysisError, CrossSection, RunStatistics};
+use crate::hepmc3::{Hepmc3Reader, Hepmc3Writer};
+use crate::physics::{
+    pdf_weight_name, PdfErrorSet, PdfReweighter, PdfReweightingError, PdfUncertainty,
+};
+
+/// Name given to the nominal weight of files that declare no weight names.
+const NOMINAL_WEIGHT_NAME: &str = "Weight";
+
+/// Members of one PDF set that a sample is reweighted to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReweightingTarget {
+    pub set_name: String,
+    pub members: Vec<i32>,
+    /// Error prescription of the set, when `members` covers all of it.
+    pub errors: Option<PdfErrorSet>,
+}
+
+impl ReweightingTarget {
+    /// Weight names of the members, as given to the [`PdfReweighter`].
+    pub fn weight_names(&self) -> impl Iterator<Item = String> + '_ {
+        self.members
+            .iter()
+            .map(|&member| pdf_weight_name(&self.set_name, member))
+    }
+}
+
+/// Nominal statistics of a reweighted sample and the summed weight of each
+/// added member, in [`PdfReweighter::weight_names`] order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ReweightingRun {
+    pub nominal: RunStatistics,
+    pub sums_of_weights: Vec<f64>,
+}
+
+/// Copy events from `reader` to `output` with the reweighter's weights
+/// appended, and return the output with the weight sums. The cross section
+/// of the run is taken from the last event's `GenCrossSection`.
+pub fn reweight_events<R: BufRead, W: Write>(
+    mut reader: Hepmc3Reader<R>,
+    output: W,
+    reweighter: &PdfReweighter,
+    max_events: Option<usize>,
+) -> Result<(W, ReweightingRun), AnalysisError> {
+    let mut run_info = reader.read_run_info()?.clone();
+    let unnamed = run_info.weight_names.is_empty();
+    if unnamed {
+        run_info.weight_names.push(NOMINAL_WEIGHT_NAME.to_owned());
+    }
+    for name in reweighter.weight_names() {
+        if run_info.weight_index(name).is_some() {
+            return Err(PdfReweightingError::DuplicateWeightName {
+                name: name.to_owned(),
+            }
+            .into());
+        }
+        run_info.weight_names.push(name.to_owned());
+    }
+    let mut writer = Hepmc3Writer::new(output, run_info);
+
+    let mut run = ReweightingRun {
+        sums_of_weights: vec![0.0; reweighter.len()],
+        ..ReweightingRun::default()
+    };
+    let nominal_weights = writer.run_info().weight_names.len() - reweighter.len();
+    for event in reader.by_ref().take(max_events.unwrap_or(usize::MAX)) {
+        let mut event = event?;
+        if unnamed && event.weights.is_empty() {
+            event.weights.push(1.0);
+        }
+        reweighter.reweight(&mut event)?;
+        writer.write_event(&event)?;
+
+        let weight = event.weight();
+        run.nominal.events += 1;
+        run.nominal.sum_of_weights += weight;
+        run.nominal.sum_of_weights_squared += weight * weight;
+        for (sum, added) in run
+            .sums_of_weights
+            .iter_mut()
+            .zip(&event.weights[nominal_weights..])
+        {
+            *sum += added;
+        }
+        if let Some(cross_section) = CrossSection::from_event(&event) {
+            run.nominal.cross_section = Some(cross_section);
+        }
+    }
+    Ok((writer.finish()?, run))
+}
+
+/// Reweighted result of one PDF member.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReweightedMember {
+    pub weight_name: String,
+    pub set_name: String,
+    pub member: i32,
+    pub sum_of_weights: f64,
+    /// Summed member weight over summed nominal weight.
+    pub ratio: f64,
+    /// Nominal cross section times `ratio`, when the former is known.
+    pub cross_section_pb: Option<f64>,
+}
+
+/// PDF uncertainty of a complete set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReweightedSet {
+    pub set_name: String,
+    pub errors: PdfErrorSet,
+    /// Uncertainty of the ratio to the nominal sample.
+    pub ratio: PdfUncertainty,
+    pub cross_section_pb: Option<PdfUncertainty>,
+}
+
+/// Reweighted cross sections of every member and PDF uncertainties of every
+/// complete set, written as `reweighting_summary.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReweightingSummary {
+    /// PDF the sample was generated with, as `SET/MEMBER`.
+    pub nominal_pdf: String,
+    pub events: usize,
+    pub nominal_sum_of_weights: f64,
+    pub nominal_cross_section: Option<CrossSection>,
+    pub members: Vec<ReweightedMember>,
+    pub sets: Vec<ReweightedSet>,
+}
+
+impl ReweightingSummary {
+    /// Combine the weight sums of `run`, which must follow the member order
+    /// of `targets`.
+    #[must_use]
+    pub fn new(
+        nominal_pdf: impl Into<String>,
+        targets: &[ReweightingTarget],
+        run: &ReweightingRun,
+    ) -> Self {
+        let nominal_sum = run.nominal.sum_of_weights;
+        let cross_section = run.nominal.cross_section;
+        let members: Vec<ReweightedMember> = targets
+            .iter()
+            .flat_map(|target| target.members.iter().map(move |&member| (target, member)))
+            .zip(&run.sums_of_weights)
+            .map(|((target, member), &sum_of_weights)| {
+                let ratio = sum_of_weights / nominal_sum;
+                ReweightedMember {
+                    weight_name: pdf_weight_name(&target.set_name, member),
+                    set_name: target.set_name.clone(),
+                    member,
+                    sum_of_weights,
+                    ratio,
+                    cross_section_pb: cross_section.map(|value| value.value_pb * ratio),
+                }
+            })
+            .collect();
+
+        let sets = targets
+            .iter()
+            .filter_map(|target| {
+                let errors = target.errors?;
+                let ratios: HashMap<i32, f64> = members
+                    .iter()
+                    .filter(|member| member.set_name == target.set_name)
+                    .map(|member| (member.member, member.ratio))
+                    .collect();
+                let values = (0..=errors.error_members)
+                    .map(|member| ratios.get(&i32::try_from(member).ok()?).copied())
+                    .collect::<Option<Vec<f64>>>()?;
+                let ratio = errors.uncertainty(&values)?;
+                Some(ReweightedSet {
+                    set_name: target.set_name.clone(),
+                    errors,
+                    ratio,
+                    cross_section_pb: cross_section.map(|value| PdfUncertainty {
+                        central: value.value_pb * ratio.central,
+                        plus: value.value_pb * ratio.plus,
+                        minus: value.value_pb * ratio.minus,
+                    }),
+                })
+            })
+            .collect();
+
+        Self {
+            nominal_pdf: nominal_pdf.into(),
+            events: run.nominal.events,
+            nominal_sum_of_weights: nominal_sum,
+            nominal_cross_section: cross_section,
+            members,
+            sets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hepmc3::{Attribute, GenEvent, GenRunInfo};
+    use crate::physics::{
+        HardProcess, PartonDensities, PdfError, PdfErrorType, PdfProvider, GEN_PDF_INFO_ATTRIBUTE,
+    };
+
+    /// The same density `scale` for every flavour.
+    struct FlatPdf(f64);
+
+    impl PdfProvider for FlatPdf {
+        fn parton_densities(&self, x: f64, q2: f64) -> Result<PartonDensities, PdfError> {
+            let value = self.0;
+            Ok(PartonDensities {
+                x,
+                q2,
+                gluon: value,
+                up: value,
+                anti_up: value,
+                down: value,
+                anti_down: value,
+                strange: value,
+                anti_strange: value,
+                charm: value,
+                anti_charm: value,
+                bottom: value,
+                anti_bottom: value,
+            })
+        }
+    }
+
+    fn events_file() -> String {
+        let hard = HardProcess {
+            x: 0.01,
+            q2: 100.0,
+            flavour: 2,
+        };
+        let events: Vec<GenEvent> = (1..=4)
+            .map(|number| GenEvent {
+                event_number: number,
+                weights: vec![2.0],
+                attributes: vec![
+                    Attribute {
+                        target: 0,
+                        name: "GenCrossSection".to_owned(),
+                        value: format!("{} 1.0", 100 + number),
+                    },
+                    Attribute {
+                        target: 0,
+                        name: GEN_PDF_INFO_ATTRIBUTE.to_owned(),
+                        value: hard.gen_pdf_info(11, 0.5),
+                    },
+                ],
+                ..GenEvent::default()
+            })
+            .collect();
+        let mut writer = Hepmc3Writer::new(Vec::new(), GenRunInfo::default());
+        for event in &events {
+            writer.write_event(event).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn reweighted_files_carry_named_member_weights_and_set_uncertainties() {
+        let target = ReweightingTarget {
+            set_name: "Flat".to_owned(),
+            members: vec![0, 1, 2],
+            errors: Some(PdfErrorSet {
+                error_type: PdfErrorType::SymmetricHessian,
+                error_members: 2,
+            }),
+        };
+        let mut reweighter = PdfReweighter::new(Box::new(FlatPdf(1.0)));
+        for (name, scale) in target.weight_names().zip([1.0, 1.3, 0.6]) {
+            reweighter
+                .add_member(name, Box::new(FlatPdf(scale)))
+                .unwrap();
+        }
+
+        let input = events_file();
+        let (output, run) = reweight_events(
+            Hepmc3Reader::new(input.as_bytes()),
+            Vec::new(),
+            &reweighter,
+            Some(3),
+        )
+        .unwrap();
+        assert_eq!(run.nominal.events, 3);
+        assert_eq!(run.nominal.sum_of_weights, 6.0);
+        assert_eq!(
+            run.nominal.cross_section.map(|value| value.value_pb),
+            Some(103.0)
+        );
+
+        let mut written = Hepmc3Reader::new(output.as_slice());
+        assert_eq!(
+            written.read_run_info().unwrap().weight_names,
+            ["Weight", "Flat/0", "Flat/1", "Flat/2"]
+        );
+        let first = written.next().unwrap().unwrap();
+        assert_eq!(first.named_weight(written.run_info(), "Flat/1"), Some(2.6));
+
+        let summary = ReweightingSummary::new("Nominal/0", &[target], &run);
+        assert_eq!(summary.members.len(), 3);
+        assert!((summary.members[2].ratio - 0.6).abs() < 1e-12);
+        let set = &summary.sets[0];
+        assert!((set.ratio.plus - 0.5).abs() < 1e-12);
+        let cross_section = set.cross_section_pb.unwrap();
+        assert!((cross_section.central - 103.0).abs() < 1e-9);
+        assert!((cross_section.minus - 51.5).abs() < 1e-9);
+
+        assert!(matches!(
+            reweight_events(
+                Hepmc3Reader::new(output.as_slice()),
+                Vec::new(),
+                &reweighter,
+                None
+            ),
+            Err(AnalysisError::Reweighting(
+                PdfReweightingError::DuplicateWeightName { .. }
+            ))
+        ));
+    }
+}
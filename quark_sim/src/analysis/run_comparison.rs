@@ -0,0 +1,725 @@
+//! Side-by-side comparison of event-generation and validation runs.
+//!
+//! A [`ComparedRun`] is read from a run directory: its configuration from
+//! `config.json` and `run_card.json`, its `summary.json` and, for event
+//! generation, the weighted `(Q², x, y, W²)` of every event from
+//! `inclusive_observables.csv`. [`compare_runs`] tabulates the configuration
+//! and summary values that differ between runs and histograms the
+//! observables on a common binning. Histograms are normalised to unit area,
+//! so they compare shapes; every run is tested against the first run with
+//! events by a χ² of the binned shapes and an unbinned Kolmogorov-Smirnov
+//! test.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{chi_square_test, ks_test, linear_edges, log_edges, ChiSquareTest, Histo1D, KsTest};
+
+pub const RUN_CONFIG_FILE_NAME: &str = "config.json";
+pub const RUN_CARD_FILE_NAME: &str = "run_card.json";
+pub const RUN_SUMMARY_FILE_NAME: &str = "summary.json";
+pub const INCLUSIVE_OBSERVABLES_FILE_NAME: &str = "inclusive_observables.csv";
+
+/// Run-card options that only say where a run was written.
+const IGNORED_RUN_CARD_OPTIONS: [&str; 2] = ["output", "config"];
+
+/// An inclusive observable of `inclusive_observables.csv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Observable {
+    Q2,
+    X,
+    Y,
+    W2,
+}
+
+impl Observable {
+    pub const ALL: [Self; 4] = [Self::Q2, Self::X, Self::Y, Self::W2];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Q2 => "q2",
+            Self::X => "x",
+            Self::Y => "y",
+            Self::W2 => "w2",
+        }
+    }
+
+    /// Column of the observable in `inclusive_observables.csv`.
+    #[must_use]
+    pub const fn column(self) -> &'static str {
+        match self {
+            Self::Q2 => "Q2",
+            Self::X => "x",
+            Self::Y => "y",
+            Self::W2 => "W2",
+        }
+    }
+
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Q2 => "Q² [GeV²]",
+            Self::X => "x",
+            Self::Y => "y",
+            Self::W2 => "W² [GeV²]",
+        }
+    }
+
+    /// Whether the observable is binned logarithmically.
+    #[must_use]
+    pub const fn logarithmic(self) -> bool {
+        !matches!(self, Self::Y)
+    }
+}
+
+impl fmt::Display for Observable {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub enum RunComparisonError {
+    TooFewRuns {
+        runs: usize,
+    },
+    NotADirectory {
+        path: PathBuf,
+    },
+    EmptyRun {
+        path: PathBuf,
+    },
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    Csv {
+        path: PathBuf,
+        source: csv::Error,
+    },
+    MissingColumn {
+        path: PathBuf,
+        column: &'static str,
+    },
+}
+
+impl fmt::Display for RunComparisonError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooFewRuns { runs } => write!(
+                formatter,
+                "a run comparison needs at least two runs, got {runs}"
+            ),
+            Self::NotADirectory { path } => {
+                write!(formatter, "run directory '{}' not found", path.display())
+            }
+            Self::EmptyRun { path } => write!(
+                formatter,
+                "run directory '{}' has none of {RUN_CONFIG_FILE_NAME}, {RUN_CARD_FILE_NAME}, \
+                 {RUN_SUMMARY_FILE_NAME} or {INCLUSIVE_OBSERVABLES_FILE_NAME}",
+                path.display()
+            ),
+            Self::Io { path, source } => {
+                write!(formatter, "failed to read '{}': {source}", path.display())
+            }
+            Self::Json { path, source } => {
+                write!(formatter, "invalid JSON in '{}': {source}", path.display())
+            }
+            Self::Csv { path, source } => {
+                write!(formatter, "invalid CSV in '{}': {source}", path.display())
+            }
+            Self::MissingColumn { path, column } => {
+                write!(formatter, "'{}' has no {column} column", path.display())
+            }
+        }
+    }
+}
+
+impl Error for RunComparisonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            Self::Csv { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Weighted values of each observable, one entry per event.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObservableSample {
+    values: BTreeMap<Observable, Vec<(f64, f64)>>,
+}
+
+impl ObservableSample {
+    /// `(value, weight)` of every event with a finite value.
+    #[must_use]
+    pub fn values(&self, observable: Observable) -> &[(f64, f64)] {
+        self.values.get(&observable).map_or(&[], Vec::as_slice)
+    }
+
+    #[must_use]
+    pub fn events(&self) -> usize {
+        self.values.values().map(Vec::len).max().unwrap_or(0)
+    }
+}
+
+/// Everything read from one run directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparedRun {
+    pub label: String,
+    pub directory: PathBuf,
+    /// Flattened `config.json` (`config.*`) and run-card (`run_card.*`)
+    /// values.
+    pub configuration: BTreeMap<String, Value>,
+    /// Flattened `summary.json` values.
+    pub summary: BTreeMap<String, Value>,
+    pub observables: Option<ObservableSample>,
+}
+
+impl ComparedRun {
+    /// Read a run directory. `summary.json` is taken from the directory or,
+    /// as `validate-hera` writes it, from its only subdirectory that has one.
+    pub fn load(directory: &Path, label: String) -> Result<Self, RunComparisonError> {
+        if !directory.is_dir() {
+            return Err(RunComparisonError::NotADirectory {
+                path: directory.to_owned(),
+            });
+        }
+        let mut configuration = BTreeMap::new();
+        if let Some(config) = read_json(&directory.join(RUN_CONFIG_FILE_NAME))? {
+            flatten("config", &config, &mut configuration);
+        }
+        if let Some(mut card) = read_json(&directory.join(RUN_CARD_FILE_NAME))? {
+            if let Some(options) = card.get_mut("options").and_then(Value::as_object_mut) {
+                for option in IGNORED_RUN_CARD_OPTIONS {
+                    options.remove(option);
+                }
+            }
+            flatten("run_card", &card, &mut configuration);
+        }
+        let mut summary = BTreeMap::new();
+        if let Some(value) = read_json(&summary_path(directory)?)? {
+            flatten("", &value, &mut summary);
+        }
+        let observables_path = directory.join(INCLUSIVE_OBSERVABLES_FILE_NAME);
+        let observables = if observables_path.is_file() {
+            Some(read_observables(&observables_path)?)
+        } else {
+            None
+        };
+        if configuration.is_empty() && summary.is_empty() && observables.is_none() {
+            return Err(RunComparisonError::EmptyRun {
+                path: directory.to_owned(),
+            });
+        }
+        Ok(Self {
+            label,
+            directory: directory.to_owned(),
+            configuration,
+            summary,
+            observables,
+        })
+    }
+}
+
+/// One configuration or summary key across all runs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueDifference {
+    pub key: String,
+    /// The value of every run, in run order; `None` where a run lacks it.
+    pub values: Vec<Option<Value>>,
+}
+
+/// Histogram of one observable for one run, normalised to unit area.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunDistribution {
+    pub label: String,
+    pub events: usize,
+    pub density: Vec<f64>,
+    pub error: Vec<f64>,
+    /// Ratio to the reference run and its statistical error, `None` where
+    /// the reference bin is empty.
+    pub ratio: Vec<Option<f64>>,
+    pub ratio_error: Vec<Option<f64>>,
+}
+
+/// Compatibility of one run's distribution with the reference run's.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompatibilityTest {
+    pub observable: Observable,
+    pub label: String,
+    pub reference: String,
+    pub chi_square: Option<ChiSquareTest>,
+    pub kolmogorov_smirnov: Option<KsTest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DistributionComparison {
+    pub observable: Observable,
+    pub edges: Vec<f64>,
+    pub runs: Vec<RunDistribution>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    pub label: String,
+    pub directory: PathBuf,
+    pub events: Option<usize>,
+}
+
+/// The result of [`compare_runs`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunComparison {
+    pub runs: Vec<RunSummary>,
+    /// Label of the run the distributions are compared with.
+    pub reference: Option<String>,
+    /// Configuration keys whose values differ between runs.
+    pub configuration_differences: Vec<ValueDifference>,
+    /// Summary keys whose values differ between runs.
+    pub summary_differences: Vec<ValueDifference>,
+    pub distributions: Vec<DistributionComparison>,
+    pub tests: Vec<CompatibilityTest>,
+}
+
+/// Compare at least two runs, histogramming the observables of the runs that
+/// have events into `bins` bins spanning all of them.
+pub fn compare_runs(
+    runs: &[ComparedRun],
+    bins: usize,
+) -> Result<RunComparison, RunComparisonError> {
+    if runs.len() < 2 {
+        return Err(RunComparisonError::TooFewRuns { runs: runs.len() });
+    }
+    let with_events: Vec<(&ComparedRun, &ObservableSample)> = runs
+        .iter()
+        .filter_map(|run| run.observables.as_ref().map(|sample| (run, sample)))
+        .collect();
+
+    let mut distributions = Vec::new();
+    let mut tests = Vec::new();
+    if with_events.len() >= 2 {
+        for observable in Observable::ALL {
+            let Some(edges) = common_edges(
+                observable,
+                bins.max(1),
+                with_events.iter().map(|(_, sample)| sample),
+            ) else {
+                continue;
+            };
+            let histograms: Vec<Histo1D> = with_events
+                .iter()
+                .map(|(run, sample)| {
+                    let mut histogram = Histo1D::new(
+                        format!("/{}/{observable}", run.label),
+                        observable.label(),
+                        edges.clone(),
+                    )
+                    .expect("common edges are increasing");
+                    for &(value, weight) in sample.values(observable) {
+                        histogram.fill(value, weight);
+                    }
+                    histogram
+                })
+                .collect();
+            let (reference_run, reference_sample) = with_events[0];
+            for ((run, sample), histogram) in with_events.iter().zip(&histograms).skip(1) {
+                tests.push(CompatibilityTest {
+                    observable,
+                    label: run.label.clone(),
+                    reference: reference_run.label.clone(),
+                    chi_square: chi_square_test(&histograms[0], histogram),
+                    kolmogorov_smirnov: ks_test(
+                        reference_sample.values(observable),
+                        sample.values(observable),
+                    ),
+                });
+            }
+            distributions.push(DistributionComparison {
+                observable,
+                runs: with_events
+                    .iter()
+                    .zip(&histograms)
+                    .map(|((run, sample), histogram)| {
+                        run_distribution(
+                            run,
+                            sample.values(observable).len(),
+                            histogram,
+                            &histograms[0],
+                        )
+                    })
+                    .collect(),
+                edges,
+            });
+        }
+    }
+
+    Ok(RunComparison {
+        runs: runs
+            .iter()
+            .map(|run| RunSummary {
+                label: run.label.clone(),
+                directory: run.directory.clone(),
+                events: run.observables.as_ref().map(ObservableSample::events),
+            })
+            .collect(),
+        reference: (with_events.len() >= 2).then(|| with_events[0].0.label.clone()),
+        configuration_differences: differences(runs, |run| &run.configuration),
+        summary_differences: differences(runs, |run| &run.summary),
+        distributions,
+        tests,
+    })
+}
+
+fn run_distribution(
+    run: &ComparedRun,
+    events: usize,
+    histogram: &Histo1D,
+    reference: &Histo1D,
+) -> RunDistribution {
+    let normalized = |histogram: &Histo1D| {
+        let mut histogram = histogram.clone();
+        histogram.normalize(1.0);
+        let bins = histogram.bins().len();
+        (0..bins)
+            .map(|index| (histogram.height(index), histogram.height_error(index)))
+            .collect::<Vec<_>>()
+    };
+    let values = normalized(histogram);
+    let reference = normalized(reference);
+    let (ratio, ratio_error) = values
+        .iter()
+        .zip(&reference)
+        .map(|(&(value, error), &(reference, _))| {
+            if reference > 0.0 {
+                (Some(value / reference), Some(error / reference))
+            } else {
+                (None, None)
+            }
+        })
+        .unzip();
+    RunDistribution {
+        label: run.label.clone(),
+        events,
+        density: values.iter().map(|&(value, _)| value).collect(),
+        error: values.iter().map(|&(_, error)| error).collect(),
+        ratio,
+        ratio_error,
+    }
+}
+
+/// Bin edges spanning the values of every sample, or `None` when no sample
+/// has a usable value.
+fn common_edges<'a>(
+    observable: Observable,
+    bins: usize,
+    samples: impl Iterator<Item = &'a &'a ObservableSample>,
+) -> Option<Vec<f64>> {
+    let (mut low, mut high) = (f64::INFINITY, f64::NEG_INFINITY);
+    for sample in samples {
+        for &(value, _) in sample.values(observable) {
+            if observable.logarithmic() && value <= 0.0 {
+                continue;
+            }
+            low = low.min(value);
+            high = high.max(value);
+        }
+    }
+    if !low.is_finite() || !high.is_finite() {
+        return None;
+    }
+    let edges = if observable.logarithmic() {
+        // Widen by a tiny factor so the largest value lies inside the last
+        // bin, which is open above.
+        let (low, high) = if high > low {
+            (low, high * (1.0 + 1e-9))
+        } else {
+            (low / 2.0, high * 2.0)
+        };
+        log_edges(bins, low, high)
+    } else {
+        let (low, high) = if high > low {
+            (low, high + 1e-9 * (high - low))
+        } else {
+            (low - 0.5, high + 0.5)
+        };
+        linear_edges(bins, low, high)
+    };
+    Some(edges)
+}
+
+/// Keys whose value is not the same in every run.
+fn differences(
+    runs: &[ComparedRun],
+    values: impl Fn(&ComparedRun) -> &BTreeMap<String, Value>,
+) -> Vec<ValueDifference> {
+    let keys: BTreeSet<&String> = runs.iter().flat_map(|run| values(run).keys()).collect();
+    keys.into_iter()
+        .filter_map(|key| {
+            let row: Vec<Option<Value>> = runs
+                .iter()
+                .map(|run| values(run).get(key).cloned())
+                .collect();
+            row.iter()
+                .any(|value| value != &row[0])
+                .then(|| ValueDifference {
+                    key: key.clone(),
+                    values: row,
+                })
+        })
+        .collect()
+}
+
+/// Flatten nested objects into dotted keys; arrays are kept as values.
+fn flatten(prefix: &str, value: &Value, output: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&key, value, output);
+            }
+        }
+        value => {
+            output.insert(prefix.to_owned(), value.clone());
+        }
+    }
+}
+
+fn read_json(path: &Path) -> Result<Option<Value>, RunComparisonError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(source) if source.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => {
+            return Err(RunComparisonError::Io {
+                path: path.to_owned(),
+                source,
+            })
+        }
+    };
+    serde_json::from_str(&text)
+        .map(Some)
+        .map_err(|source| RunComparisonError::Json {
+            path: path.to_owned(),
+            source,
+        })
+}
+
+fn summary_path(directory: &Path) -> Result<PathBuf, RunComparisonError> {
+    let path = directory.join(RUN_SUMMARY_FILE_NAME);
+    if path.is_file() {
+        return Ok(path);
+    }
+    let entries = fs::read_dir(directory).map_err(|source| RunComparisonError::Io {
+        path: directory.to_owned(),
+        source,
+    })?;
+    let mut candidates = entries
+        .filter_map(|entry| {
+            entry
+                .ok()
+                .map(|entry| entry.path().join(RUN_SUMMARY_FILE_NAME))
+        })
+        .filter(|path| path.is_file());
+    match (candidates.next(), candidates.next()) {
+        (Some(found), None) => Ok(found),
+        _ => Ok(path),
+    }
+}
+
+/// Read the observables and `event_weight` of every event; events without a
+/// weight column count with weight one.
+fn read_observables(path: &Path) -> Result<ObservableSample, RunComparisonError> {
+    let csv_error = |source| RunComparisonError::Csv {
+        path: path.to_owned(),
+        source,
+    };
+    let mut reader = csv::Reader::from_path(path).map_err(csv_error)?;
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let column = |name: &str| headers.iter().position(|header| header.trim() == name);
+    let weight_column = column("event_weight");
+    let columns = Observable::ALL
+        .iter()
+        .map(|&observable| {
+            column(observable.column())
+                .map(|index| (observable, index))
+                .ok_or(RunComparisonError::MissingColumn {
+                    path: path.to_owned(),
+                    column: observable.column(),
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut sample = ObservableSample::default();
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        let number = |index: usize| {
+            record
+                .get(index)
+                .and_then(|field| field.trim().parse::<f64>().ok())
+                .filter(|value| value.is_finite())
+        };
+        let weight = weight_column.map_or(Some(1.0), number);
+        let Some(weight) = weight else {
+            continue;
+        };
+        for &(observable, index) in &columns {
+            if let Some(value) = number(index) {
+                sample
+                    .values
+                    .entry(observable)
+                    .or_default()
+                    .push((value, weight));
+            }
+        }
+    }
+    Ok(sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_run(directory: &Path, pdf_set: &str, events: &[(f64, f64, f64)]) {
+        fs::create_dir_all(directory).unwrap();
+        fs::write(
+            directory.join(RUN_CONFIG_FILE_NAME),
+            format!(r#"{{"pdf_set": "{pdf_set}", "parton_shower": true, "x_min": 0.001}}"#),
+        )
+        .unwrap();
+        fs::write(
+            directory.join(RUN_CARD_FILE_NAME),
+            format!(
+                r#"{{"schema_version": 1, "command": "generate-dis-events",
+                    "options": {{"pdf-set": "{pdf_set}", "output": "{}"}}}}"#,
+                directory.display()
+            ),
+        )
+        .unwrap();
+        fs::write(
+            directory.join(RUN_SUMMARY_FILE_NAME),
+            format!(
+                r#"{{"success": true, "accepted_events": {}}}"#,
+                events.len()
+            ),
+        )
+        .unwrap();
+        let mut csv = String::from("event_number,event_weight,Q2,x,y,W2\n");
+        for (index, &(q2, x, y)) in events.iter().enumerate() {
+            let w2 = q2 * (1.0 - x) / x;
+            csv.push_str(&format!("{index},1.0,{q2},{x},{y},{w2}\n"));
+        }
+        fs::write(directory.join(INCLUSIVE_OBSERVABLES_FILE_NAME), csv).unwrap();
+    }
+
+    #[test]
+    fn runs_differ_in_configuration_and_shape() {
+        let base =
+            std::env::temp_dir().join(format!("quark_sim_run_comparison_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let events = |shift: f64| -> Vec<(f64, f64, f64)> {
+            (0..400)
+                .map(|index| {
+                    let u = (index as f64 + 0.5) / 400.0;
+                    (
+                        10.0 * 100.0_f64.powf(u),
+                        0.001 + 0.1 * u,
+                        (0.1 + 0.5 * u + shift).min(0.95),
+                    )
+                })
+                .collect()
+        };
+        write_run(&base.join("a"), "CT18LO", &events(0.0));
+        write_run(&base.join("b"), "CT18LO", &events(0.0));
+        write_run(&base.join("c"), "NNPDF31_lo", &events(0.2));
+        fs::create_dir_all(base.join("hera/HERA_NC_318")).unwrap();
+        fs::write(
+            base.join("hera/HERA_NC_318").join(RUN_SUMMARY_FILE_NAME),
+            r#"{"chi_square": 12.5}"#,
+        )
+        .unwrap();
+
+        let runs: Vec<ComparedRun> = ["a", "b", "c", "hera"]
+            .iter()
+            .map(|name| ComparedRun::load(&base.join(name), (*name).to_owned()).unwrap())
+            .collect();
+        assert!(runs[3].observables.is_none());
+        assert_eq!(runs[3].summary["chi_square"], 12.5);
+        assert!(!runs[0]
+            .configuration
+            .contains_key("run_card.options.output"));
+
+        let comparison = compare_runs(&runs, 10).unwrap();
+        assert_eq!(comparison.reference.as_deref(), Some("a"));
+        let keys: Vec<&str> = comparison
+            .configuration_differences
+            .iter()
+            .map(|difference| difference.key.as_str())
+            .collect();
+        assert!(keys.contains(&"config.pdf_set"));
+        assert!(keys.contains(&"run_card.options.pdf-set"));
+        assert!(!keys.iter().any(|key| key.ends_with("output")));
+        assert_eq!(comparison.distributions.len(), 4);
+        let y = &comparison.distributions[2];
+        assert_eq!(y.observable, Observable::Y);
+        assert_eq!(y.edges.len(), 11);
+        assert_eq!(y.runs.len(), 3);
+        let area: f64 = y.runs[0]
+            .density
+            .iter()
+            .zip(y.edges.windows(2))
+            .map(|(density, edges)| density * (edges[1] - edges[0]))
+            .sum();
+        assert!((area - 1.0).abs() < 1e-12);
+        assert!(y.runs[1]
+            .ratio
+            .iter()
+            .flatten()
+            .all(|ratio| (ratio - 1.0).abs() < 1e-12));
+
+        let test = |label: &str, observable| {
+            comparison
+                .tests
+                .iter()
+                .find(|test| test.label == label && test.observable == observable)
+                .unwrap()
+        };
+        assert_eq!(test("b", Observable::Y).chi_square.unwrap().chi_square, 0.0);
+        assert!(
+            test("b", Observable::Q2)
+                .kolmogorov_smirnov
+                .unwrap()
+                .p_value
+                > 0.99
+        );
+        assert!(test("c", Observable::Y).kolmogorov_smirnov.unwrap().p_value < 1e-6);
+        assert!(test("c", Observable::Y).chi_square.unwrap().p_value < 1e-6);
+
+        assert!(matches!(
+            compare_runs(&runs[..1], 10),
+            Err(RunComparisonError::TooFewRuns { runs: 1 })
+        ));
+        assert!(matches!(
+            ComparedRun::load(&base.join("missing"), "missing".to_owned()),
+            Err(RunComparisonError::NotADirectory { .. })
+        ));
+        let _ = fs::remove_dir_all(base);
+    }
+}
@@ -0,0 +1,1006 @@
+//! Response matrices and unfolding of inclusive DIS spectra.
+//!
+//! A [`ResponseMatrix`] is filled from Monte Carlo events that carry both the
+//! generated `(x, Q²)` and the `(x, Q²)` reconstructed after the parametric
+//! [`Detector`]. It gives the purity, stability and efficiency of each bin and
+//! unfolds a measured [`Spectrum`] back to the generator level with one of
+//! three [`UnfoldingMethod`]s:
+//!
+//! - bin-by-bin correction factors `C_i = N_gen,i / N_rec,i`,
+//! - inversion of the response matrix,
+//! - iterative Bayesian unfolding (D'Agostini), starting from the Monte Carlo
+//!   truth as prior.
+//!
+//! The covariance of the measured spectrum, diagonal with the summed squared
+//! weights, is propagated through each method. For the iterative method the
+//! dependence of each iteration on the previous one is included, following
+//! Adye's error propagation. The statistical uncertainty of the response
+//! matrix itself is not propagated.
+//!
+//! Truth and reconstructed spectra share one [`KinematicBinning`]. Events
+//! reconstructed inside the binning but generated outside it are fakes; the
+//! inversion and iterative methods subtract their fraction from the measured
+//! spectrum before unfolding.
+
+use std::error::Error;
+use std::fmt;
+use std::io::BufRead;
+
+use serde::{Deserialize, Serialize};
+
+use crate::hepmc3::Hepmc3Reader;
+use crate::physics::{exact_inelasticity, leptonic_y_plus, DEFAULT_FIXED_ALPHA, GEV_MINUS_2_TO_PB};
+
+use super::detector::{Detector, ReconstructionMethod};
+use super::projections::AnalysisEvent;
+use super::{AnalysisError, CrossSection, RunStatistics};
+
+/// Iterations of the Bayesian method when none are requested.
+pub const DEFAULT_BAYES_ITERATIONS: usize = 4;
+
+/// Failures building a response matrix or unfolding with it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnfoldingError {
+    InvalidBinning { axis: &'static str, message: String },
+    DimensionMismatch { expected: usize, actual: usize },
+    InvalidMeasurement { bin: usize, value: f64 },
+    SingularResponse { bin: usize },
+    InvalidIterations,
+}
+
+impl fmt::Display for UnfoldingError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidBinning { axis, message } => {
+                write!(formatter, "invalid {axis} binning: {message}")
+            }
+            Self::DimensionMismatch { expected, actual } => write!(
+                formatter,
+                "measured spectrum has {actual} bins but the response matrix has {expected}"
+            ),
+            Self::InvalidMeasurement { bin, value } => write!(
+                formatter,
+                "measured bin {bin} must be finite and non-negative, got {value}"
+            ),
+            Self::SingularResponse { bin } => write!(
+                formatter,
+                "the response matrix is singular at bin {bin}; merge sparsely populated bins or use the iterative method"
+            ),
+            Self::InvalidIterations => {
+                write!(formatter, "iterative unfolding needs at least one iteration")
+            }
+        }
+    }
+}
+
+impl Error for UnfoldingError {}
+
+/// Rectangular bins in `x` and `Q²`. Bin `i` has `Q²` index `i / n_x` and
+/// `x` index `i % n_x`, so bins are ordered by `Q²` and then by `x` like the
+/// HERA cross-section tables.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KinematicBinning {
+    pub x_edges: Vec<f64>,
+    /// `Q²` edges in GeV².
+    pub q2_edges: Vec<f64>,
+}
+
+/// Limits and logarithmic centre of one bin.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KinematicBin {
+    pub x_low: f64,
+    pub x_high: f64,
+    pub q2_low: f64,
+    pub q2_high: f64,
+}
+
+impl KinematicBin {
+    /// Geometric mean of the `x` limits.
+    #[must_use]
+    pub fn x_centre(&self) -> f64 {
+        (self.x_low * self.x_high).sqrt()
+    }
+
+    /// Geometric mean of the `Q²` limits.
+    #[must_use]
+    pub fn q2_centre(&self) -> f64 {
+        (self.q2_low * self.q2_high).sqrt()
+    }
+}
+
+impl KinematicBinning {
+    pub fn new(x_edges: Vec<f64>, q2_edges: Vec<f64>) -> Result<Self, UnfoldingError> {
+        let binning = Self { x_edges, q2_edges };
+        binning.validate()?;
+        Ok(binning)
+    }
+
+    pub fn validate(&self) -> Result<(), UnfoldingError> {
+        for (axis, edges, upper) in [
+            ("x", &self.x_edges, Some(1.0)),
+            ("Q²", &self.q2_edges, None),
+        ] {
+            let invalid = |message: &str| UnfoldingError::InvalidBinning {
+                axis,
+                message: message.to_owned(),
+            };
+            if edges.len() < 2 {
+                return Err(invalid("at least two edges are required"));
+            }
+            if edges.iter().any(|edge| !edge.is_finite() || *edge <= 0.0) {
+                return Err(invalid("edges must be finite and positive"));
+            }
+            if edges.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err(invalid("edges must be strictly increasing"));
+            }
+            if upper.is_some_and(|upper| edges[edges.len() - 1] > upper) {
+                return Err(invalid("edges must not exceed 1"));
+            }
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        (self.x_edges.len() - 1) * (self.q2_edges.len() - 1)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bin containing `(x, Q²)`, each lower edge included.
+    #[must_use]
+    pub fn find(&self, x: f64, q2: f64) -> Option<usize> {
+        let index = |edges: &[f64], value: f64| {
+            (value >= edges[0] && value < edges[edges.len() - 1])
+                .then(|| edges.partition_point(|&edge| edge <= value) - 1)
+        };
+        let ix = index(&self.x_edges, x)?;
+        let iq2 = index(&self.q2_edges, q2)?;
+        Some(iq2 * (self.x_edges.len() - 1) + ix)
+    }
+
+    #[must_use]
+    pub fn bin(&self, index: usize) -> KinematicBin {
+        let nx = self.x_edges.len() - 1;
+        let (iq2, ix) = (index / nx, index % nx);
+        KinematicBin {
+            x_low: self.x_edges[ix],
+            x_high: self.x_edges[ix + 1],
+            q2_low: self.q2_edges[iq2],
+            q2_high: self.q2_edges[iq2 + 1],
+        }
+    }
+}
+
+/// Summed weights and squared weights per bin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Spectrum {
+    pub values: Vec<f64>,
+    pub variances: Vec<f64>,
+}
+
+impl Spectrum {
+    #[must_use]
+    pub fn zeros(bins: usize) -> Self {
+        Self {
+            values: vec![0.0; bins],
+            variances: vec![0.0; bins],
+        }
+    }
+
+    /// Unit-weight counts with Poisson variances.
+    #[must_use]
+    pub fn poisson(values: Vec<f64>) -> Self {
+        Self {
+            variances: values.clone(),
+            values,
+        }
+    }
+
+    pub fn fill(&mut self, bin: usize, weight: f64) {
+        self.values[bin] += weight;
+        self.variances[bin] += weight * weight;
+    }
+}
+
+/// Migrations between generated and reconstructed bins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseMatrix {
+    pub binning: KinematicBinning,
+    /// `migrations[t][r]`: weight generated in bin `t` and reconstructed in
+    /// bin `r`.
+    pub migrations: Vec<Vec<f64>>,
+    /// Weight generated in each bin, reconstructed or not.
+    pub generated: Vec<f64>,
+    /// Weight reconstructed in each bin but generated outside the binning.
+    pub fakes: Vec<f64>,
+    /// Squared centre-of-mass energy of the beams, from the first event with
+    /// beams.
+    pub s_gev2: Option<f64>,
+}
+
+impl ResponseMatrix {
+    #[must_use]
+    pub fn new(binning: KinematicBinning) -> Self {
+        let bins = binning.len();
+        Self {
+            binning,
+            migrations: vec![vec![0.0; bins]; bins],
+            generated: vec![0.0; bins],
+            fakes: vec![0.0; bins],
+            s_gev2: None,
+        }
+    }
+
+    /// Record one event from its generated and reconstructed `(x, Q²)`.
+    pub fn fill(
+        &mut self,
+        truth: Option<(f64, f64)>,
+        reconstructed: Option<(f64, f64)>,
+        weight: f64,
+    ) {
+        let truth = truth.and_then(|(x, q2)| self.binning.find(x, q2));
+        let reconstructed = reconstructed.and_then(|(x, q2)| self.binning.find(x, q2));
+        match (truth, reconstructed) {
+            (Some(t), Some(r)) => {
+                self.generated[t] += weight;
+                self.migrations[t][r] += weight;
+            }
+            (Some(t), None) => self.generated[t] += weight,
+            (None, Some(r)) => self.fakes[r] += weight,
+            (None, None) => {}
+        }
+    }
+
+    /// Weight reconstructed in each bin, fakes included.
+    #[must_use]
+    pub fn reconstructed(&self) -> Vec<f64> {
+        (0..self.binning.len())
+            .map(|r| self.fakes[r] + self.migrations.iter().map(|row| row[r]).sum::<f64>())
+            .collect()
+    }
+
+    /// Fraction of the events reconstructed in each bin that were also
+    /// generated in it.
+    #[must_use]
+    pub fn purity(&self) -> Vec<Option<f64>> {
+        self.reconstructed()
+            .into_iter()
+            .enumerate()
+            .map(|(i, reconstructed)| ratio(self.migrations[i][i], reconstructed))
+            .collect()
+    }
+
+    /// Fraction of the events generated in each bin and reconstructed in any
+    /// bin that were reconstructed in the same bin.
+    #[must_use]
+    pub fn stability(&self) -> Vec<Option<f64>> {
+        self.migrations
+            .iter()
+            .enumerate()
+            .map(|(i, row)| ratio(row[i], row.iter().sum()))
+            .collect()
+    }
+
+    /// Fraction of the events generated in each bin that were reconstructed
+    /// in any bin.
+    #[must_use]
+    pub fn efficiency(&self) -> Vec<Option<f64>> {
+        self.migrations
+            .iter()
+            .zip(&self.generated)
+            .map(|(row, &generated)| ratio(row.iter().sum(), generated))
+            .collect()
+    }
+
+    /// Bin-by-bin correction factors `N_gen / N_rec`.
+    #[must_use]
+    pub fn correction_factors(&self) -> Vec<Option<f64>> {
+        self.generated
+            .iter()
+            .zip(self.reconstructed())
+            .map(|(&generated, reconstructed)| ratio(generated, reconstructed))
+            .collect()
+    }
+
+    /// `probabilities[r][t]`: probability that an event generated in bin `t`
+    /// is reconstructed in bin `r`.
+    #[must_use]
+    pub fn probabilities(&self) -> Vec<Vec<f64>> {
+        let bins = self.binning.len();
+        (0..bins)
+            .map(|r| {
+                (0..bins)
+                    .map(|t| ratio(self.migrations[t][r], self.generated[t]).unwrap_or(0.0))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Unfold `measured` to the generator level.
+    pub fn unfold(
+        &self,
+        measured: &Spectrum,
+        method: UnfoldingMethod,
+    ) -> Result<UnfoldingResult, UnfoldingError> {
+        let bins = self.binning.len();
+        for length in [measured.values.len(), measured.variances.len()] {
+            if length != bins {
+                return Err(UnfoldingError::DimensionMismatch {
+                    expected: bins,
+                    actual: length,
+                });
+            }
+        }
+        for (bin, &value) in measured
+            .values
+            .iter()
+            .chain(&measured.variances)
+            .enumerate()
+        {
+            if !value.is_finite() || value < 0.0 {
+                return Err(UnfoldingError::InvalidMeasurement {
+                    bin: bin % bins,
+                    value,
+                });
+            }
+        }
+
+        // Jacobian of the unfolded spectrum with respect to the measured one.
+        let jacobian = match method {
+            UnfoldingMethod::BinByBin => {
+                let mut jacobian = vec![vec![0.0; bins]; bins];
+                for (i, factor) in self.correction_factors().into_iter().enumerate() {
+                    jacobian[i][i] = factor.unwrap_or(0.0);
+                }
+                jacobian
+            }
+            UnfoldingMethod::MatrixInversion => {
+                let inverse = invert(self.probabilities())?;
+                with_fake_subtraction(inverse, &self.purity_of_fakes())
+            }
+            UnfoldingMethod::IterativeBayes { iterations } => {
+                if iterations == 0 {
+                    return Err(UnfoldingError::InvalidIterations);
+                }
+                let signal = self.purity_of_fakes();
+                let corrected = measured
+                    .values
+                    .iter()
+                    .zip(&signal)
+                    .map(|(value, fraction)| value * fraction)
+                    .collect::<Vec<_>>();
+                let jacobian = self.bayes_jacobian(&corrected, iterations);
+                with_fake_subtraction(jacobian, &signal)
+            }
+        };
+        let values = jacobian
+            .iter()
+            .map(|row| dot(row, &measured.values))
+            .collect();
+        let covariance = jacobian
+            .iter()
+            .map(|row_i| {
+                jacobian
+                    .iter()
+                    .map(|row_j| {
+                        row_i
+                            .iter()
+                            .zip(row_j)
+                            .zip(&measured.variances)
+                            .map(|((a, b), variance)| a * b * variance)
+                            .sum()
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(UnfoldingResult {
+            method,
+            values,
+            covariance,
+        })
+    }
+
+    /// `1 - fakes / reconstructed` per bin, the fraction of a measured bin
+    /// that migrates from inside the binning.
+    fn purity_of_fakes(&self) -> Vec<f64> {
+        self.fakes
+            .iter()
+            .zip(self.reconstructed())
+            .map(|(&fakes, reconstructed)| 1.0 - ratio(fakes, reconstructed).unwrap_or(0.0))
+            .collect()
+    }
+
+    /// Iterate D'Agostini's Bayes step from the Monte Carlo truth and return
+    /// `∂u/∂n`. The unfolded spectrum is the Jacobian applied to `n`: each
+    /// step is invariant under scaling the prior, so `u(n)` is homogeneous of
+    /// degree one and `u = (∂u/∂n) n`.
+    fn bayes_jacobian(&self, measured: &[f64], iterations: usize) -> Vec<Vec<f64>> {
+        let bins = self.binning.len();
+        let response = self.probabilities();
+        let efficiency = self
+            .efficiency()
+            .into_iter()
+            .map(|efficiency| efficiency.unwrap_or(0.0))
+            .collect::<Vec<_>>();
+        let mut prior = self.generated.clone();
+        let mut jacobian = vec![vec![0.0; bins]; bins];
+
+        for _ in 0..iterations {
+            // D_r = Σ_t R_rt n0_t and M_tr = R_rt n0_t / (ε_t D_r).
+            let denominators = response
+                .iter()
+                .map(|row| dot(row, &prior))
+                .collect::<Vec<_>>();
+            let unfolding_matrix = (0..bins)
+                .map(|t| {
+                    (0..bins)
+                        .map(|r| {
+                            if efficiency[t] > 0.0 && denominators[r] > 0.0 {
+                                response[r][t] * prior[t] / (efficiency[t] * denominators[r])
+                            } else {
+                                0.0
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let unfolded = unfolding_matrix
+                .iter()
+                .map(|row| dot(row, measured))
+                .collect::<Vec<_>>();
+
+            // Adye: ∂u_t/∂n_j = M_tj + (u_t/n0_t) ∂n0_t/∂n_j
+            //   - Σ_l Σ_r n_r M_tr (R_rl / D_r) ∂n0_l/∂n_j,
+            // with u_t/n0_t = Σ_r n_r R_rt / (ε_t D_r) to avoid n0_t = 0.
+            let ratio_to_prior = (0..bins)
+                .map(|t| {
+                    if efficiency[t] > 0.0 {
+                        (0..bins)
+                            .filter(|&r| denominators[r] > 0.0)
+                            .map(|r| measured[r] * response[r][t] / denominators[r])
+                            .sum::<f64>()
+                            / efficiency[t]
+                    } else {
+                        0.0
+                    }
+                })
+                .collect::<Vec<_>>();
+            let coupling = (0..bins)
+                .map(|t| {
+                    (0..bins)
+                        .map(|l| {
+                            (0..bins)
+                                .filter(|&r| denominators[r] > 0.0)
+                                .map(|r| {
+                                    measured[r] * unfolding_matrix[t][r] * response[r][l]
+                                        / denominators[r]
+                                })
+                                .sum::<f64>()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            jacobian = (0..bins)
+                .map(|t| {
+                    (0..bins)
+                        .map(|j| {
+                            unfolding_matrix[t][j] + ratio_to_prior[t] * jacobian[t][j]
+                                - (0..bins)
+                                    .map(|l| coupling[t][l] * jacobian[l][j])
+                                    .sum::<f64>()
+                        })
+                        .collect()
+                })
+                .collect();
+            prior = unfolded;
+        }
+        jacobian
+    }
+}
+
+/// How [`ResponseMatrix::unfold`] inverts the detector response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum UnfoldingMethod {
+    BinByBin,
+    MatrixInversion,
+    IterativeBayes { iterations: usize },
+}
+
+impl fmt::Display for UnfoldingMethod {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BinByBin => formatter.write_str("bin-by-bin"),
+            Self::MatrixInversion => formatter.write_str("matrix inversion"),
+            Self::IterativeBayes { iterations } => {
+                write!(formatter, "iterative Bayesian, {iterations} iterations")
+            }
+        }
+    }
+}
+
+/// Unfolded generator-level spectrum with its covariance, in the weight
+/// units of the measured spectrum.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnfoldingResult {
+    pub method: UnfoldingMethod,
+    pub values: Vec<f64>,
+    pub covariance: Vec<Vec<f64>>,
+}
+
+impl UnfoldingResult {
+    #[must_use]
+    pub fn errors(&self) -> Vec<f64> {
+        (0..self.values.len())
+            .map(|i| self.covariance[i][i].max(0.0).sqrt())
+            .collect()
+    }
+}
+
+/// Fill `response` from Monte Carlo events, simulating each with `detector`
+/// and reconstructing it with `method`. The truth is the electron-method
+/// kinematics of the generated event.
+pub fn fill_response<R: BufRead>(
+    reader: Hepmc3Reader<R>,
+    response: &mut ResponseMatrix,
+    detector: &mut Detector,
+    method: ReconstructionMethod,
+    max_events: Option<usize>,
+) -> Result<RunStatistics, AnalysisError> {
+    for_each_event(reader, max_events, |view| {
+        let Ok(beams) = view.beams() else {
+            return;
+        };
+        if response.s_gev2.is_none() {
+            response.s_gev2 = Some((beams.lepton + beams.proton).mass_squared());
+        }
+        let truth = view
+            .dis_kinematics()
+            .ok()
+            .map(|kinematics| (kinematics.x, kinematics.q2));
+        let reconstructed = detector
+            .simulate(view)
+            .reconstruct(method, beams)
+            .map(|reconstructed| (reconstructed.x, reconstructed.q2));
+        response.fill(truth, reconstructed, view.weight());
+    })
+}
+
+/// Detector-level spectrum of events simulated with `detector` and
+/// reconstructed with `method`, the pseudo-data to unfold.
+pub fn fill_measured<R: BufRead>(
+    reader: Hepmc3Reader<R>,
+    binning: &KinematicBinning,
+    detector: &mut Detector,
+    method: ReconstructionMethod,
+    max_events: Option<usize>,
+) -> Result<(Spectrum, RunStatistics), AnalysisError> {
+    let mut spectrum = Spectrum::zeros(binning.len());
+    let run = for_each_event(reader, max_events, |view| {
+        let Ok(beams) = view.beams() else {
+            return;
+        };
+        if let Some(bin) = detector
+            .simulate(view)
+            .reconstruct(method, beams)
+            .and_then(|reconstructed| binning.find(reconstructed.x, reconstructed.q2))
+        {
+            spectrum.fill(bin, view.weight());
+        }
+    })?;
+    Ok((spectrum, run))
+}
+
+fn for_each_event<R: BufRead>(
+    mut reader: Hepmc3Reader<R>,
+    max_events: Option<usize>,
+    mut visit: impl FnMut(&AnalysisEvent<'_>),
+) -> Result<RunStatistics, AnalysisError> {
+    reader.read_run_info()?;
+    let mut run = RunStatistics::default();
+    for event in reader.by_ref().take(max_events.unwrap_or(usize::MAX)) {
+        let event = event?;
+        let view = AnalysisEvent::new(&event);
+        run.events += 1;
+        run.sum_of_weights += view.weight();
+        run.sum_of_weights_squared += view.weight().powi(2);
+        visit(&view);
+        if let Some(cross_section) = CrossSection::from_event(&event) {
+            run.cross_section = Some(cross_section);
+        }
+    }
+    Ok(run)
+}
+
+/// Unfolding inputs and settings recorded with the results.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnfoldingMetadata {
+    pub method: UnfoldingMethod,
+    pub reconstruction: ReconstructionMethod,
+    pub detector: String,
+    pub response_seed: u64,
+    pub data_seed: u64,
+    pub response_events: usize,
+    pub data_events: usize,
+    /// Cross section of the data sample, converting unfolded weights to pb.
+    pub data_cross_section: Option<CrossSection>,
+    pub s_gev2: Option<f64>,
+}
+
+/// One row of the unfolded table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnfoldedBin {
+    pub bin: usize,
+    pub x_low: f64,
+    pub x_high: f64,
+    pub q2_low: f64,
+    pub q2_high: f64,
+    pub x_centre: f64,
+    pub q2_centre: f64,
+    pub generated_mc: f64,
+    pub reconstructed_mc: f64,
+    pub measured: f64,
+    pub measured_error: f64,
+    pub purity: Option<f64>,
+    pub stability: Option<f64>,
+    pub efficiency: Option<f64>,
+    pub correction_factor: Option<f64>,
+    pub unfolded: f64,
+    pub unfolded_error: f64,
+    /// Unfolded cross section in the bin, in pb.
+    pub sigma_pb: Option<f64>,
+    pub sigma_error_pb: Option<f64>,
+    /// Bin-averaged reduced cross section quoted at the bin centre.
+    pub reduced_cross_section: Option<f64>,
+    pub reduced_cross_section_error: Option<f64>,
+}
+
+/// Per-bin table, response probabilities and covariance of one unfolding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnfoldingReport {
+    pub metadata: UnfoldingMetadata,
+    pub binning: KinematicBinning,
+    pub bins: Vec<UnfoldedBin>,
+    /// `response_probabilities[r][t]`, see [`ResponseMatrix::probabilities`].
+    pub response_probabilities: Vec<Vec<f64>>,
+    /// Covariance of the unfolded values, in the measured weight units.
+    pub covariance: Vec<Vec<f64>>,
+}
+
+impl UnfoldingReport {
+    /// Tabulate an unfolding. With a data cross section the unfolded weights
+    /// are converted to pb with `σ / Σw` of the data sample and, where the
+    /// bin centre is physical, to the reduced cross section
+    /// `σ_r = x Q⁴ / (2π α² Y₊) · σ_bin / (Δx ΔQ²)`. That is the bin average
+    /// of `d²σ/dx dQ²` quoted at the centre, without a bin-centre correction.
+    #[must_use]
+    pub fn new(
+        metadata: UnfoldingMetadata,
+        response: &ResponseMatrix,
+        measured: &Spectrum,
+        data_run: &RunStatistics,
+        result: &UnfoldingResult,
+    ) -> Self {
+        let purity = response.purity();
+        let stability = response.stability();
+        let efficiency = response.efficiency();
+        let correction_factors = response.correction_factors();
+        let reconstructed = response.reconstructed();
+        let errors = result.errors();
+        let scale = data_run.scale_factor();
+        let bins = (0..response.binning.len())
+            .map(|index| {
+                let bin = response.binning.bin(index);
+                let sigma_pb = scale.map(|scale| scale * result.values[index]);
+                let sigma_error_pb = scale.map(|scale| scale.abs() * errors[index]);
+                let reduced = metadata
+                    .s_gev2
+                    .and_then(|s| reduced_cross_section_factor(&bin, s));
+                UnfoldedBin {
+                    bin: index,
+                    x_low: bin.x_low,
+                    x_high: bin.x_high,
+                    q2_low: bin.q2_low,
+                    q2_high: bin.q2_high,
+                    x_centre: bin.x_centre(),
+                    q2_centre: bin.q2_centre(),
+                    generated_mc: response.generated[index],
+                    reconstructed_mc: reconstructed[index],
+                    measured: measured.values[index],
+                    measured_error: measured.variances[index].sqrt(),
+                    purity: purity[index],
+                    stability: stability[index],
+                    efficiency: efficiency[index],
+                    correction_factor: correction_factors[index],
+                    unfolded: result.values[index],
+                    unfolded_error: errors[index],
+                    sigma_pb,
+                    sigma_error_pb,
+                    reduced_cross_section: sigma_pb.zip(reduced).map(|(sigma, f)| sigma * f),
+                    reduced_cross_section_error: sigma_error_pb
+                        .zip(reduced)
+                        .map(|(error, f)| error * f),
+                }
+            })
+            .collect();
+        Self {
+            metadata,
+            binning: response.binning.clone(),
+            bins,
+            response_probabilities: response.probabilities(),
+            covariance: result.covariance.clone(),
+        }
+    }
+}
+
+/// Factor taking a bin cross section in pb to the reduced cross section at
+/// the bin centre, or `None` when the centre is outside `0 < y < 1`.
+fn reduced_cross_section_factor(bin: &KinematicBin, s: f64) -> Option<f64> {
+    let (x, q2) = (bin.x_centre(), bin.q2_centre());
+    let y = exact_inelasticity(x, q2, s).ok()?;
+    let y_plus = leptonic_y_plus(y).ok()?;
+    let area = (bin.x_high - bin.x_low) * (bin.q2_high - bin.q2_low);
+    Some(
+        x * q2 * q2
+            / (2.0 * std::f64::consts::PI * DEFAULT_FIXED_ALPHA.powi(2) * y_plus)
+            / (area * GEV_MINUS_2_TO_PB),
+    )
+}
+
+fn ratio(numerator: f64, denominator: f64) -> Option<f64> {
+    (denominator != 0.0).then(|| numerator / denominator)
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// Multiply the columns of `matrix` by the signal fraction of each measured
+/// bin, so the Jacobian acts on the measured rather than the fake-subtracted
+/// spectrum.
+fn with_fake_subtraction(mut matrix: Vec<Vec<f64>>, signal: &[f64]) -> Vec<Vec<f64>> {
+    for row in &mut matrix {
+        for (value, fraction) in row.iter_mut().zip(signal) {
+            *value *= fraction;
+        }
+    }
+    matrix
+}
+
+/// Gauss–Jordan inversion with partial pivoting.
+fn invert(mut matrix: Vec<Vec<f64>>) -> Result<Vec<Vec<f64>>, UnfoldingError> {
+    let size = matrix.len();
+    let scale = matrix
+        .iter()
+        .flatten()
+        .fold(0.0_f64, |largest, value| largest.max(value.abs()));
+    let mut inverse = (0..size)
+        .map(|i| (0..size).map(|j| f64::from(u8::from(i == j))).collect())
+        .collect::<Vec<Vec<f64>>>();
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))
+            .unwrap_or(column);
+        if matrix[pivot][column].abs() <= 1e-12 * scale || scale == 0.0 {
+            return Err(UnfoldingError::SingularResponse { bin: column });
+        }
+        matrix.swap(column, pivot);
+        inverse.swap(column, pivot);
+        let diagonal = matrix[column][column];
+        for j in 0..size {
+            matrix[column][j] /= diagonal;
+            inverse[column][j] /= diagonal;
+        }
+        for row in 0..size {
+            if row == column {
+                continue;
+            }
+            let factor = matrix[row][column];
+            if factor == 0.0 {
+                continue;
+            }
+            for j in 0..size {
+                matrix[row][j] -= factor * matrix[column][j];
+                inverse[row][j] -= factor * inverse[column][j];
+            }
+        }
+    }
+    Ok(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {expected:.10e}, got {actual:.10e}"
+        );
+    }
+
+    /// Three `x` bins in one `Q²` bin with neighbour migrations, fakes and
+    /// an inefficiency.
+    fn response() -> ResponseMatrix {
+        let binning =
+            KinematicBinning::new(vec![1e-3, 1e-2, 1e-1, 1.0], vec![10.0, 100.0]).unwrap();
+        let mut response = ResponseMatrix::new(binning);
+        let centres = [3e-3, 3e-2, 3e-1];
+        let truth = [1000.0, 600.0, 200.0];
+        for (t, &count) in truth.iter().enumerate() {
+            let reco = |r: usize| Some((centres[r], 30.0));
+            let neighbours = [t > 0, t < 2];
+            let diagonal = 0.9 - 0.1 * neighbours.iter().filter(|&&n| n).count() as f64;
+            response.fill(Some((centres[t], 30.0)), reco(t), diagonal * count);
+            if t > 0 {
+                response.fill(Some((centres[t], 30.0)), reco(t - 1), 0.1 * count);
+            }
+            if t < 2 {
+                response.fill(Some((centres[t], 30.0)), reco(t + 1), 0.1 * count);
+            }
+            response.fill(Some((centres[t], 30.0)), None, 0.1 * count);
+        }
+        response.fill(Some((3e-2, 5.0)), Some((3e-2, 30.0)), 20.0);
+        response
+    }
+
+    #[test]
+    fn bins_are_ordered_by_q2_then_x() {
+        let binning = KinematicBinning::new(vec![0.01, 0.1, 1.0], vec![1.0, 10.0, 100.0]).unwrap();
+        assert_eq!(binning.len(), 4);
+        assert_eq!(binning.find(0.05, 5.0), Some(0));
+        assert_eq!(binning.find(0.5, 5.0), Some(1));
+        assert_eq!(binning.find(0.05, 50.0), Some(2));
+        assert_eq!(binning.find(0.1, 10.0), Some(3));
+        assert_eq!(binning.find(1.0, 50.0), None);
+        assert_eq!(binning.bin(3).q2_low, 10.0);
+        assert!(KinematicBinning::new(vec![0.1, 0.01], vec![1.0, 2.0]).is_err());
+        assert!(KinematicBinning::new(vec![0.1, 2.0], vec![1.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn purity_stability_and_efficiency_follow_the_migrations() {
+        let response = response();
+        assert_eq!(response.generated, vec![1000.0, 600.0, 200.0]);
+        assert_eq!(response.reconstructed(), vec![860.0, 560.0, 220.0]);
+        assert_close(response.purity()[0].unwrap(), 800.0 / 860.0, 1e-12);
+        assert_close(response.purity()[1].unwrap(), 420.0 / 560.0, 1e-12);
+        assert_close(response.stability()[0].unwrap(), 800.0 / 900.0, 1e-12);
+        assert_close(response.stability()[1].unwrap(), 420.0 / 540.0, 1e-12);
+        assert_close(response.efficiency()[2].unwrap(), 0.9, 1e-12);
+        assert_close(
+            response.correction_factors()[1].unwrap(),
+            600.0 / 560.0,
+            1e-12,
+        );
+        let probabilities = response.probabilities();
+        assert_close(probabilities[0][1], 0.1, 1e-12);
+        assert_close(probabilities.iter().map(|row| row[1]).sum(), 0.9, 1e-12);
+    }
+
+    #[test]
+    fn every_method_recovers_the_truth_of_the_monte_carlo_itself() {
+        let response = response();
+        let measured = Spectrum::poisson(response.reconstructed());
+        for method in [
+            UnfoldingMethod::BinByBin,
+            UnfoldingMethod::MatrixInversion,
+            UnfoldingMethod::IterativeBayes { iterations: 1 },
+        ] {
+            let result = response.unfold(&measured, method).unwrap();
+            for (unfolded, generated) in result.values.iter().zip(&response.generated) {
+                assert_close(*unfolded, *generated, 1e-9 * generated);
+            }
+        }
+    }
+
+    #[test]
+    fn matrix_inversion_and_bayes_unfold_a_different_truth() {
+        let response = response();
+        let truth = [500.0, 900.0, 300.0];
+        let probabilities = response.probabilities();
+        let expected = probabilities
+            .iter()
+            .map(|row| dot(row, &truth))
+            .collect::<Vec<_>>();
+        let measured = Spectrum::poisson(expected.clone());
+        // Without fakes in this pseudo-data, undo the fake subtraction.
+        let mut response_without_fakes = response.clone();
+        response_without_fakes.fakes = vec![0.0; 3];
+
+        let inverted = response_without_fakes
+            .unfold(&measured, UnfoldingMethod::MatrixInversion)
+            .unwrap();
+        for (unfolded, truth) in inverted.values.iter().zip(truth) {
+            assert_close(*unfolded, truth, 1e-9 * truth);
+        }
+
+        let bayes = |iterations| {
+            let result = response_without_fakes
+                .unfold(&measured, UnfoldingMethod::IterativeBayes { iterations })
+                .unwrap();
+            result
+                .values
+                .iter()
+                .zip(truth)
+                .map(|(unfolded, truth)| (unfolded - truth).abs())
+                .sum::<f64>()
+        };
+        assert!(bayes(50) < 0.1 * bayes(1));
+        assert!(bayes(200) < 1.0);
+
+        // Bin-by-bin factors only reproduce the Monte Carlo shape.
+        let bin_by_bin = response_without_fakes
+            .unfold(&measured, UnfoldingMethod::BinByBin)
+            .unwrap();
+        assert!((bin_by_bin.values[0] - truth[0]).abs() > 10.0);
+    }
+
+    #[test]
+    fn bayes_covariance_matches_finite_differences() {
+        let response = response();
+        let measured = Spectrum::poisson(vec![700.0, 650.0, 260.0]);
+        let method = UnfoldingMethod::IterativeBayes { iterations: 3 };
+        let result = response.unfold(&measured, method).unwrap();
+
+        // Numerical Jacobian of the full non-linear unfolding.
+        let step = 1e-3;
+        let jacobian = (0..3)
+            .map(|j| {
+                let mut up = measured.clone();
+                up.values[j] += step;
+                let mut down = measured.clone();
+                down.values[j] -= step;
+                let up = response.unfold(&up, method).unwrap().values;
+                let down = response.unfold(&down, method).unwrap().values;
+                (0..3)
+                    .map(|i| (up[i] - down[i]) / (2.0 * step))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        for i in 0..3 {
+            for k in 0..3 {
+                let expected = (0..3)
+                    .map(|j| jacobian[j][i] * jacobian[j][k] * measured.variances[j])
+                    .sum::<f64>();
+                assert_close(
+                    result.covariance[i][k],
+                    expected,
+                    1e-5 * expected.abs().max(1.0),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_singular_responses_and_mismatched_spectra() {
+        let binning = KinematicBinning::new(vec![0.01, 0.1, 1.0], vec![1.0, 10.0]).unwrap();
+        let mut response = ResponseMatrix::new(binning);
+        response.fill(Some((0.05, 5.0)), Some((0.05, 5.0)), 1.0);
+        assert_eq!(
+            response.unfold(
+                &Spectrum::poisson(vec![1.0, 1.0]),
+                UnfoldingMethod::MatrixInversion
+            ),
+            Err(UnfoldingError::SingularResponse { bin: 1 })
+        );
+        assert_eq!(
+            response.unfold(&Spectrum::poisson(vec![1.0]), UnfoldingMethod::BinByBin),
+            Err(UnfoldingError::DimensionMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            response.unfold(
+                &Spectrum::poisson(vec![1.0, 1.0]),
+                UnfoldingMethod::IterativeBayes { iterations: 0 }
+            ),
+            Err(UnfoldingError::InvalidIterations)
+        );
+    }
+}
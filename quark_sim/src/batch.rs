@@ -0,0 +1,557 @@
+//! Batch evaluation of inclusive observables over many `(x, Q²)` points.
+//!
+//! Points come from a CSV or JSON file ([`read_points`]) or from the product
+//! of two logarithmic grids ([`grid_points`]). [`BatchWriter`] streams one
+//! row per point as a formatted table, CSV or JSON Lines. A point whose
+//! evaluation fails gets a row with its error message instead of values, so
+//! one bad point does not abort the batch.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Column names a points file must provide.
+pub const X_COLUMN: &str = "x";
+pub const Q2_COLUMN: &str = "q2";
+
+/// Minimum width of a table column.
+const TABLE_WIDTH: usize = 14;
+
+/// One kinematic point of a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KinematicPoint {
+    pub x: f64,
+    pub q2: f64,
+}
+
+#[derive(Debug)]
+pub enum BatchError {
+    InvalidGrid {
+        text: String,
+        reason: String,
+    },
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Csv {
+        path: PathBuf,
+        source: csv::Error,
+    },
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    MissingColumn {
+        path: PathBuf,
+        column: &'static str,
+    },
+    InvalidPoint {
+        path: PathBuf,
+        row: usize,
+        reason: String,
+    },
+    UnsupportedFormat {
+        path: PathBuf,
+    },
+    NoPoints {
+        path: PathBuf,
+    },
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidGrid { text, reason } => {
+                write!(formatter, "invalid grid '{text}': {reason}")
+            }
+            Self::Io { path, source } => {
+                write!(formatter, "failed to read '{}': {source}", path.display())
+            }
+            Self::Csv { path, source } => {
+                write!(formatter, "invalid CSV in '{}': {source}", path.display())
+            }
+            Self::Json { path, source } => {
+                write!(formatter, "invalid JSON in '{}': {source}", path.display())
+            }
+            Self::MissingColumn { path, column } => write!(
+                formatter,
+                "points file '{}' has no '{column}' column",
+                path.display()
+            ),
+            Self::InvalidPoint { path, row, reason } => write!(
+                formatter,
+                "point {row} of '{}' is invalid: {reason}",
+                path.display()
+            ),
+            Self::UnsupportedFormat { path } => write!(
+                formatter,
+                "points file '{}' must end in .csv or .json",
+                path.display()
+            ),
+            Self::NoPoints { path } => {
+                write!(
+                    formatter,
+                    "points file '{}' contains no points",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl Error for BatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Csv { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// `points` values from `min` to `max`, evenly spaced in `ln`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogGrid {
+    pub min: f64,
+    pub max: f64,
+    pub points: usize,
+}
+
+impl LogGrid {
+    #[must_use]
+    pub fn values(&self) -> Vec<f64> {
+        if self.points == 1 {
+            return vec![self.min];
+        }
+        let step = (self.max / self.min).ln() / (self.points - 1) as f64;
+        (0..self.points)
+            .map(|index| match index {
+                0 => self.min,
+                index if index + 1 == self.points => self.max,
+                index => self.min * (step * index as f64).exp(),
+            })
+            .collect()
+    }
+}
+
+impl FromStr for LogGrid {
+    type Err = BatchError;
+
+    /// Parse `MIN:MAX:POINTS`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| BatchError::InvalidGrid {
+            text: text.to_owned(),
+            reason: reason.to_owned(),
+        };
+        let fields: Vec<&str> = text.split(':').map(str::trim).collect();
+        let [min, max, points] = fields[..] else {
+            return Err(invalid("expected MIN:MAX:POINTS"));
+        };
+        let bound = |value: &str| {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite() && *value > 0.0)
+                .ok_or_else(|| invalid("bounds must be positive finite numbers"))
+        };
+        let grid = Self {
+            min: bound(min)?,
+            max: bound(max)?,
+            points: points
+                .parse::<usize>()
+                .ok()
+                .filter(|points| *points > 0)
+                .ok_or_else(|| invalid("the number of points must be a positive integer"))?,
+        };
+        if grid.min > grid.max || (grid.min == grid.max && grid.points > 1) {
+            return Err(invalid("MIN must be less than MAX"));
+        }
+        Ok(grid)
+    }
+}
+
+/// Every combination of the two grids, `Q²` outermost like the HERA tables.
+#[must_use]
+pub fn grid_points(x: &LogGrid, q2: &LogGrid) -> Vec<KinematicPoint> {
+    let x_values = x.values();
+    q2.values()
+        .into_iter()
+        .flat_map(|q2| x_values.iter().map(move |&x| KinematicPoint { x, q2 }))
+        .collect()
+}
+
+/// Read points from a CSV file with `x` and `q2` columns, or from a JSON
+/// array of `{"x": …, "q2": …}` objects. Other columns and fields are
+/// ignored.
+pub fn read_points(path: &Path) -> Result<Vec<KinematicPoint>, BatchError> {
+    let file = File::open(path).map_err(|source| BatchError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let points = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => read_csv_points(path, file)?,
+        Some("json") => serde_json::from_reader::<_, Vec<KinematicPoint>>(BufReader::new(file))
+            .map_err(|source| BatchError::Json {
+                path: path.to_owned(),
+                source,
+            })?,
+        _ => {
+            return Err(BatchError::UnsupportedFormat {
+                path: path.to_owned(),
+            })
+        }
+    };
+    if points.is_empty() {
+        return Err(BatchError::NoPoints {
+            path: path.to_owned(),
+        });
+    }
+    for (index, point) in points.iter().enumerate() {
+        if !point.x.is_finite() || !point.q2.is_finite() {
+            return Err(BatchError::InvalidPoint {
+                path: path.to_owned(),
+                row: index + 1,
+                reason: "x and q2 must be finite".to_owned(),
+            });
+        }
+    }
+    Ok(points)
+}
+
+fn read_csv_points(path: &Path, file: File) -> Result<Vec<KinematicPoint>, BatchError> {
+    let csv_error = |source| BatchError::Csv {
+        path: path.to_owned(),
+        source,
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(file);
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let column = |name: &'static str| {
+        headers
+            .iter()
+            .position(|header| header == name)
+            .ok_or_else(|| BatchError::MissingColumn {
+                path: path.to_owned(),
+                column: name,
+            })
+    };
+    let (x_column, q2_column) = (column(X_COLUMN)?, column(Q2_COLUMN)?);
+
+    reader
+        .records()
+        .enumerate()
+        .map(|(index, record)| {
+            let record = record.map_err(csv_error)?;
+            let value = |column: usize, name: &str| {
+                let text = record.get(column).unwrap_or_default();
+                text.parse::<f64>().map_err(|_| BatchError::InvalidPoint {
+                    path: path.to_owned(),
+                    row: index + 1,
+                    reason: format!("invalid {name} '{text}'"),
+                })
+            };
+            Ok(KinematicPoint {
+                x: value(x_column, X_COLUMN)?,
+                q2: value(q2_column, Q2_COLUMN)?,
+            })
+        })
+        .collect()
+}
+
+/// Output format of a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchFormat {
+    #[default]
+    Table,
+    Csv,
+    JsonLines,
+}
+
+impl BatchFormat {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Table => "table",
+            Self::Csv => "csv",
+            Self::JsonLines => "jsonl",
+        }
+    }
+}
+
+impl fmt::Display for BatchFormat {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl FromStr for BatchFormat {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "table" => Ok(Self::Table),
+            "csv" => Ok(Self::Csv),
+            "jsonl" => Ok(Self::JsonLines),
+            _ => Err(format!(
+                "unknown batch format '{text}'; use table, csv or jsonl"
+            )),
+        }
+    }
+}
+
+/// Number of points written by a [`BatchWriter`] and how many failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BatchSummary {
+    pub points: usize,
+    pub failures: usize,
+}
+
+/// Streams one row per point. Every row starts with the point's index, `x`
+/// and `q2`, followed by the value columns or, for failed points, the error.
+/// Missing values are empty in CSV, `null` in JSON Lines and `-` in tables.
+pub struct BatchWriter<W: Write> {
+    output: W,
+    format: BatchFormat,
+    columns: Vec<&'static str>,
+    summary: BatchSummary,
+}
+
+impl<W: Write> BatchWriter<W> {
+    /// Write the header for the value `columns`.
+    pub fn new(mut output: W, format: BatchFormat, columns: &[&'static str]) -> io::Result<Self> {
+        match format {
+            BatchFormat::Table => {
+                let mut line = format!("{:>6}", "index");
+                for name in [X_COLUMN, Q2_COLUMN].iter().chain(columns) {
+                    line.push_str(&format!(" {name:>width$}", width = table_width(name)));
+                }
+                writeln!(output, "{line}")?;
+            }
+            BatchFormat::Csv => {
+                let header: Vec<&str> = ["index", X_COLUMN, Q2_COLUMN]
+                    .into_iter()
+                    .chain(columns.iter().copied())
+                    .chain(["error"])
+                    .collect();
+                writeln!(output, "{}", header.join(","))?;
+            }
+            BatchFormat::JsonLines => {}
+        }
+        output.flush()?;
+        Ok(Self {
+            output,
+            format,
+            columns: columns.to_vec(),
+            summary: BatchSummary::default(),
+        })
+    }
+
+    /// Write the row of one point and flush it.
+    pub fn write(
+        &mut self,
+        point: KinematicPoint,
+        values: Result<&[Option<f64>], &str>,
+    ) -> io::Result<()> {
+        let index = self.summary.points;
+        self.summary.points += 1;
+        if values.is_err() {
+            self.summary.failures += 1;
+        }
+        if let Ok(values) = values {
+            debug_assert_eq!(values.len(), self.columns.len());
+        }
+
+        let line = match self.format {
+            BatchFormat::Table => {
+                let mut line = format!(
+                    "{index:>6} {:>TABLE_WIDTH$.6e} {:>TABLE_WIDTH$.6e}",
+                    point.x, point.q2
+                );
+                match values {
+                    Ok(values) => {
+                        for (name, value) in self.columns.iter().zip(values) {
+                            let width = table_width(name);
+                            match value {
+                                Some(value) => line.push_str(&format!(" {value:>width$.6e}")),
+                                None => line.push_str(&format!(" {:>width$}", "-")),
+                            }
+                        }
+                    }
+                    Err(error) => line.push_str(&format!(" error: {error}")),
+                }
+                line
+            }
+            BatchFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(Vec::new());
+                let mut record = vec![index.to_string(), point.x.to_string(), point.q2.to_string()];
+                match values {
+                    Ok(values) => {
+                        record.extend(
+                            values.iter().map(|value| {
+                                value.map(|value| value.to_string()).unwrap_or_default()
+                            }),
+                        );
+                        record.push(String::new());
+                    }
+                    Err(error) => {
+                        record.extend(self.columns.iter().map(|_| String::new()));
+                        record.push(error.to_owned());
+                    }
+                }
+                writer.write_record(&record).map_err(io::Error::from)?;
+                let bytes = writer
+                    .into_inner()
+                    .map_err(|error| io::Error::other(error.to_string()))?;
+                String::from_utf8_lossy(&bytes).trim_end().to_owned()
+            }
+            BatchFormat::JsonLines => {
+                let mut fields = vec![
+                    format!("\"index\":{index}"),
+                    format!("\"x\":{}", json_number(Some(point.x))),
+                    format!("\"q2\":{}", json_number(Some(point.q2))),
+                ];
+                match values {
+                    Ok(values) => fields.extend(
+                        self.columns
+                            .iter()
+                            .zip(values)
+                            .map(|(name, value)| format!("\"{name}\":{}", json_number(*value))),
+                    ),
+                    Err(error) => {
+                        fields.push(format!("\"error\":{}", serde_json::Value::from(error)))
+                    }
+                }
+                format!("{{{}}}", fields.join(","))
+            }
+        };
+        writeln!(self.output, "{line}")?;
+        self.output.flush()
+    }
+
+    /// Return the output and the number of points written.
+    pub fn finish(mut self) -> io::Result<(W, BatchSummary)> {
+        self.output.flush()?;
+        Ok((self.output, self.summary))
+    }
+}
+
+fn table_width(column: &str) -> usize {
+    column.len().max(TABLE_WIDTH)
+}
+
+fn json_number(value: Option<f64>) -> serde_json::Value {
+    value
+        .and_then(serde_json::Number::from_f64)
+        .map_or(serde_json::Value::Null, serde_json::Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_grids_include_both_ends_and_reject_bad_ranges() {
+        let grid: LogGrid = "1e-4:1e-1:4".parse().unwrap();
+        let values = grid.values();
+        assert_eq!(values.len(), 4);
+        assert_eq!((values[0], values[3]), (1e-4, 1e-1));
+        assert!((values[1] - 1e-3).abs() < 1e-15);
+        assert_eq!("5:5:1".parse::<LogGrid>().unwrap().values(), [5.0]);
+
+        let points = grid_points(&"0.01:0.1:2".parse().unwrap(), &"10:100:3".parse().unwrap());
+        assert_eq!(points.len(), 6);
+        assert_eq!(points[1], KinematicPoint { x: 0.1, q2: 10.0 });
+        assert_eq!(points[2].q2, points[3].q2);
+
+        for text in ["1:10", "0:10:3", "10:1:3", "1:10:0", "1:1:2", "a:10:3"] {
+            assert!(
+                text.parse::<LogGrid>().is_err(),
+                "{text} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn points_files_are_read_from_csv_and_json() {
+        let directory =
+            std::env::temp_dir().join(format!("quark_sim_batch_points_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let csv = directory.join("points.csv");
+        std::fs::write(&csv, "label,q2,x\nlow, 10, 0.001\nhigh,100,0.1\n").unwrap();
+        assert_eq!(
+            read_points(&csv).unwrap(),
+            [
+                KinematicPoint { x: 0.001, q2: 10.0 },
+                KinematicPoint { x: 0.1, q2: 100.0 }
+            ]
+        );
+        let json = directory.join("points.json");
+        std::fs::write(&json, r#"[{"x": 0.01, "q2": 20, "note": "bin 1"}]"#).unwrap();
+        assert_eq!(
+            read_points(&json).unwrap(),
+            [KinematicPoint { x: 0.01, q2: 20.0 }]
+        );
+
+        std::fs::write(&csv, "x,Q2\n0.1,10\n").unwrap();
+        assert!(matches!(
+            read_points(&csv),
+            Err(BatchError::MissingColumn { column: "q2", .. })
+        ));
+        std::fs::write(&csv, "x,q2\n0.1,ten\n").unwrap();
+        assert!(matches!(
+            read_points(&csv),
+            Err(BatchError::InvalidPoint { row: 1, .. })
+        ));
+        std::fs::write(&json, "[]").unwrap();
+        assert!(matches!(
+            read_points(&json),
+            Err(BatchError::NoPoints { .. })
+        ));
+        let _ = std::fs::remove_dir_all(directory);
+    }
+
+    #[test]
+    fn failed_points_are_reported_inline_in_every_format() {
+        let point = KinematicPoint { x: 0.01, q2: 100.0 };
+        let write = |format| {
+            let mut writer = BatchWriter::new(Vec::new(), format, &["f2", "fl"]).unwrap();
+            writer.write(point, Ok(&[Some(1.25), None])).unwrap();
+            writer.write(point, Err("y, is unphysical")).unwrap();
+            let (output, summary) = writer.finish().unwrap();
+            assert_eq!(
+                summary,
+                BatchSummary {
+                    points: 2,
+                    failures: 1
+                }
+            );
+            String::from_utf8(output).unwrap()
+        };
+
+        assert_eq!(
+            write(BatchFormat::Csv),
+            "index,x,q2,f2,fl,error\n0,0.01,100,1.25,,\n1,0.01,100,,,\"y, is unphysical\"\n"
+        );
+        assert_eq!(
+            write(BatchFormat::JsonLines),
+            "{\"index\":0,\"x\":0.01,\"q2\":100.0,\"f2\":1.25,\"fl\":null}\n\
+             {\"index\":1,\"x\":0.01,\"q2\":100.0,\"error\":\"y, is unphysical\"}\n"
+        );
+        let table = write(BatchFormat::Table);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].trim_start().starts_with("index"));
+        assert!(lines[1].ends_with("1.250000e0              -"));
+        assert!(lines[2].ends_with("error: y, is unphysical"));
+    }
+}
@@ -0,0 +1,45 @@
+//! SHA-256 digests of run artifacts and PDF grids.
+//!
+//! Run manifests record the digest of every file they describe so that
+//! `verify-run` can tell a modified or truncated artifact from an intact one.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+/// Lower-case hexadecimal SHA-256 of `data`.
+#[must_use]
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+/// Lower-case hexadecimal SHA-256 and size in bytes of a file, streamed so
+/// event files of any size can be hashed.
+pub fn sha256_file(path: &Path) -> io::Result<(String, u64)> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let bytes = io::copy(&mut file, &mut hasher)?;
+    Ok((to_hex(&hasher.finalize()), bytes))
+}
+
+fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_hashed_like_their_contents() {
+        let path =
+            std::env::temp_dir().join(format!("quark_sim_checksum_{}.txt", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+        let (digest, bytes) = sha256_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(digest, sha256_hex(b"abc"));
+        assert_eq!(bytes, 3);
+    }
+}
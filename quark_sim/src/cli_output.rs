@@ -0,0 +1,710 @@
+//! Output format and structured failures of the command line.
+//!
+//! `--format text|json|csv` is a global option. `dis-kinematics`,
+//! `dis-cross-section` and `structure-functions` print their results in it.
+//! Every command reports a failure as `Error: …` in text mode and as one JSON
+//! error object on standard error otherwise. The exit status separates
+//! invalid input, an unavailable backend and physics-domain errors; see
+//! `docs/cli_output.md`.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use quark_sim::analysis::RunComparisonError;
+use quark_sim::batch::{BatchError, BatchFormat};
+use quark_sim::pdf_artifacts::PdfArtifactError;
+use quark_sim::physics::structure_function_provider::StructureFunctionProviderError;
+use quark_sim::physics::{CrossSectionError, DisError, PdfError, StructureFunctionError};
+use quark_sim::pythia_settings::PythiaSettingsError;
+use quark_sim::run_comparison_artifacts::RunComparisonArtifactError;
+
+use crate::gui::state::{GuiError, GuiErrorCategory};
+use crate::manifest::ManifestError;
+use crate::run_card::RunCardError;
+
+/// Exit status of a failure not covered by the other codes.
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit status of an invalid option, input file or run card.
+pub const EXIT_INVALID_INPUT: i32 = 2;
+/// Exit status when a PDF set, a surrogate model or a backend binary is missing.
+pub const EXIT_BACKEND_UNAVAILABLE: i32 = 3;
+/// Exit status of a point outside the physical or supported domain.
+pub const EXIT_PHYSICS_DOMAIN: i32 = 4;
+
+const FORMAT_FLAG: &str = "--format";
+
+/// Global output format selected with `--format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    /// Remove `--format <FORMAT>` from anywhere in `args`.
+    pub fn from_arguments(mut args: Vec<String>) -> Result<(Option<Self>, Vec<String>), CliError> {
+        let Some(index) = args.iter().position(|arg| arg == FORMAT_FLAG) else {
+            return Ok((None, args));
+        };
+        let invalid = |message: String| {
+            CliError::new(
+                "invalid_option",
+                GuiErrorCategory::InvalidInput,
+                Some("format"),
+                message,
+            )
+        };
+        let value = args
+            .get(index + 1)
+            .filter(|value| !value.starts_with("--"))
+            .ok_or_else(|| invalid(format!("{FORMAT_FLAG} requires a value")))?;
+        let format = value.parse().map_err(invalid)?;
+        args.drain(index..index + 2);
+        if args.iter().any(|arg| arg == FORMAT_FLAG) {
+            return Err(invalid(format!("duplicate option: {FORMAT_FLAG}")));
+        }
+        Ok((Some(format), args))
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(format!(
+                "invalid value for {FORMAT_FLAG}: {text}; use text, json or csv"
+            )),
+        }
+    }
+}
+
+impl From<OutputFormat> for BatchFormat {
+    /// A batch writes one JSON object per point in JSON mode.
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Text => Self::Table,
+            OutputFormat::Json => Self::JsonLines,
+            OutputFormat::Csv => Self::Csv,
+        }
+    }
+}
+
+/// Print one flat record as a JSON object or as a CSV header and row. Keys
+/// keep the given order.
+pub fn print_record(format: OutputFormat, fields: &[(&str, Value)]) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    match format {
+        OutputFormat::Text | OutputFormat::Json => {
+            let members: Vec<String> = fields
+                .iter()
+                .map(|(name, value)| format!("{}:{value}", Value::from(*name)))
+                .collect();
+            writeln!(stdout, "{{{}}}", members.join(","))
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(stdout);
+            writer.write_record(fields.iter().map(|(name, _)| *name))?;
+            writer.write_record(fields.iter().map(|(_, value)| match value {
+                Value::Null => String::new(),
+                Value::String(text) => text.clone(),
+                value => value.to_string(),
+            }))?;
+            writer.flush()
+        }
+    }
+}
+
+/// A failed command with a stable `code`, the GUI error `category` and the
+/// option that caused it, named like a run-card option.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CliError {
+    pub code: &'static str,
+    pub category: GuiErrorCategory,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(
+        code: &'static str,
+        category: GuiErrorCategory,
+        field: Option<&str>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            code,
+            category,
+            field: field.map(str::to_owned),
+            message: message.into(),
+        }
+    }
+
+    /// Whether the error came from parsing the command line.
+    pub fn is_usage(&self) -> bool {
+        matches!(
+            self.code,
+            "missing_option" | "unknown_option" | "unknown_command" | "invalid_option"
+        )
+    }
+
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        match self.category {
+            GuiErrorCategory::InvalidInput
+            | GuiErrorCategory::UnsupportedOrder
+            | GuiErrorCategory::FileNotFound
+            | GuiErrorCategory::ParseError => EXIT_INVALID_INPUT,
+            GuiErrorCategory::LhapdfSetNotInstalled
+            | GuiErrorCategory::ApfelBackendMissing
+            | GuiErrorCategory::PythiaBackendMissing
+            | GuiErrorCategory::SurrogateModelMissing => EXIT_BACKEND_UNAVAILABLE,
+            GuiErrorCategory::InvalidKinematics => EXIT_PHYSICS_DOMAIN,
+            GuiErrorCategory::InvalidOutputSchema
+            | GuiErrorCategory::WslgUnavailable
+            | GuiErrorCategory::ProcessFailed
+            | GuiErrorCategory::Unknown => EXIT_FAILURE,
+        }
+    }
+
+    /// The JSON error object printed in the json and csv formats.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "error": {
+                "code": self.code,
+                "category": self.category,
+                "field": self.field,
+                "message": self.message,
+                "suggestion": GuiError::new(self.category.clone(), "").suggestion,
+                "exit_code": self.exit_code(),
+            }
+        })
+    }
+
+    /// Print the error on standard error in `format`.
+    pub fn report(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Text => eprintln!("Error: {}", self.message),
+            OutputFormat::Json | OutputFormat::Csv => eprintln!("{}", self.to_json()),
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.message)
+    }
+}
+
+impl Error for CliError {}
+
+/// Settings rejected before a PYTHIA run starts.
+impl From<PythiaSettingsError> for CliError {
+    fn from(error: PythiaSettingsError) -> Self {
+        let message = error.to_string();
+        match error {
+            PythiaSettingsError::Io { .. } => Self::new(
+                "pythia_cmnd_unreadable",
+                GuiErrorCategory::FileNotFound,
+                Some("pythia-cmnd"),
+                message,
+            ),
+            PythiaSettingsError::Malformed { .. } => Self::new(
+                "malformed_pythia_setting",
+                GuiErrorCategory::InvalidInput,
+                Some("pythia-setting"),
+                message,
+            ),
+            PythiaSettingsError::Reserved { .. } => Self::new(
+                "reserved_pythia_setting",
+                GuiErrorCategory::InvalidInput,
+                Some("pythia-setting"),
+                message,
+            ),
+        }
+    }
+}
+
+/// Failures of commands that still report plain messages.
+impl From<candle_core::Error> for CliError {
+    fn from(error: candle_core::Error) -> Self {
+        Self::new(
+            "command_failed",
+            GuiErrorCategory::ProcessFailed,
+            None,
+            error.to_string(),
+        )
+    }
+}
+
+/// Failures writing the result, such as a closed pipe.
+impl From<io::Error> for CliError {
+    fn from(error: io::Error) -> Self {
+        Self::new(
+            "output_failed",
+            GuiErrorCategory::ProcessFailed,
+            None,
+            format!("failed to write the output: {error}"),
+        )
+    }
+}
+
+impl From<RunCardError> for CliError {
+    fn from(error: RunCardError) -> Self {
+        let message = error.to_string();
+        match &error {
+            RunCardError::Io { .. } => Self::new(
+                "run_card_unreadable",
+                GuiErrorCategory::FileNotFound,
+                Some("config"),
+                message,
+            ),
+            RunCardError::InvalidOption { name, .. } => Self::new(
+                "invalid_run_card",
+                GuiErrorCategory::ParseError,
+                Some(name),
+                message,
+            ),
+            _ => Self::new(
+                "invalid_run_card",
+                GuiErrorCategory::ParseError,
+                Some("config"),
+                message,
+            ),
+        }
+    }
+}
+
+impl From<ManifestError> for CliError {
+    fn from(error: ManifestError) -> Self {
+        let message = error.to_string();
+        match &error {
+            ManifestError::Io { source, .. } => Self::new(
+                "manifest_unreadable",
+                if source.kind() == io::ErrorKind::NotFound {
+                    GuiErrorCategory::FileNotFound
+                } else {
+                    GuiErrorCategory::InvalidInput
+                },
+                Some("run"),
+                message,
+            ),
+            ManifestError::Json { .. } | ManifestError::UnsupportedSchemaVersion { .. } => {
+                Self::new(
+                    "invalid_manifest",
+                    GuiErrorCategory::ParseError,
+                    Some("run"),
+                    message,
+                )
+            }
+        }
+    }
+}
+
+impl From<RunComparisonError> for CliError {
+    fn from(error: RunComparisonError) -> Self {
+        let message = error.to_string();
+        match error {
+            RunComparisonError::TooFewRuns { .. } => Self::new(
+                "missing_option",
+                GuiErrorCategory::InvalidInput,
+                Some("run"),
+                message,
+            ),
+            RunComparisonError::NotADirectory { .. } | RunComparisonError::Io { .. } => Self::new(
+                "run_unreadable",
+                GuiErrorCategory::FileNotFound,
+                Some("run"),
+                message,
+            ),
+            RunComparisonError::EmptyRun { .. }
+            | RunComparisonError::Json { .. }
+            | RunComparisonError::Csv { .. }
+            | RunComparisonError::MissingColumn { .. } => Self::new(
+                "invalid_run",
+                GuiErrorCategory::ParseError,
+                Some("run"),
+                message,
+            ),
+        }
+    }
+}
+
+impl From<RunComparisonArtifactError> for CliError {
+    fn from(error: RunComparisonArtifactError) -> Self {
+        let message = error.to_string();
+        match error {
+            RunComparisonArtifactError::Plot { .. } => Self::new(
+                "plot_failed",
+                GuiErrorCategory::ProcessFailed,
+                None,
+                message,
+            ),
+            _ => Self::new(
+                "output_unwritable",
+                GuiErrorCategory::InvalidInput,
+                Some("output"),
+                message,
+            ),
+        }
+    }
+}
+
+impl From<BatchError> for CliError {
+    fn from(error: BatchError) -> Self {
+        let (code, category) = match error {
+            BatchError::Io { .. } => ("points_file_unreadable", GuiErrorCategory::FileNotFound),
+            _ => ("invalid_points_file", GuiErrorCategory::ParseError),
+        };
+        Self::new(code, category, Some("points"), error.to_string())
+    }
+}
+
+impl From<PdfArtifactError> for CliError {
+    fn from(error: PdfArtifactError) -> Self {
+        let message = error.to_string();
+        match error {
+            PdfArtifactError::ArtifactExists { .. } | PdfArtifactError::Io { .. } => Self::new(
+                "output_unwritable",
+                GuiErrorCategory::InvalidInput,
+                Some("output"),
+                message,
+            ),
+            PdfArtifactError::NoCurves | PdfArtifactError::Plot { .. } => Self::new(
+                "plot_failed",
+                GuiErrorCategory::ProcessFailed,
+                None,
+                message,
+            ),
+        }
+    }
+}
+
+impl From<DisError> for CliError {
+    fn from(error: DisError) -> Self {
+        let (code, field) = match &error {
+            DisError::NonFiniteInput { quantity, .. } => ("non_finite_input", dis_option(quantity)),
+            DisError::InvalidFourVector { vector, .. } => {
+                ("invalid_four_vector", dis_option(vector))
+            }
+            DisError::NonPositiveEnergy { particle, .. } => {
+                ("non_positive_energy", dis_option(particle))
+            }
+            DisError::EnergyBelowRestMass { particle, .. } => {
+                ("energy_below_rest_mass", dis_option(particle))
+            }
+            DisError::OffMassShell { particle, .. } => ("off_mass_shell", dis_option(particle)),
+            DisError::InvalidScatteringAngle { .. } => {
+                ("invalid_scattering_angle", Some("theta-deg"))
+            }
+            DisError::NonFiniteResult { .. } => ("non_finite_result", None),
+            DisError::InvalidDenominator { .. } => ("invalid_denominator", None),
+            DisError::NonPositiveQ2 { .. } => ("non_positive_q2", Some("q2")),
+            DisError::UnphysicalS { .. } => ("unphysical_s", None),
+            DisError::InvalidBjorkenX { .. } => ("invalid_bjorken_x", Some("x")),
+            DisError::InvalidInelasticity { .. } => ("invalid_inelasticity", None),
+            DisError::UnphysicalW2 { .. } => ("unphysical_w2", None),
+            DisError::InvalidCutValue { .. } => ("invalid_cut_value", None),
+            DisError::InvalidCutRange { .. } => ("invalid_cut_range", None),
+        };
+        Self::new(
+            code,
+            GuiErrorCategory::InvalidKinematics,
+            field,
+            error.to_string(),
+        )
+    }
+}
+
+/// The `dis-kinematics` option behind a particle or quantity of [`DisError`].
+fn dis_option(name: &str) -> Option<&'static str> {
+    match name {
+        "incoming electron" => Some("electron-energy"),
+        "incoming proton" => Some("proton-energy"),
+        "scattered electron" => Some("scattered-electron-energy"),
+        "scattering angle" => Some("theta-deg"),
+        _ => None,
+    }
+}
+
+impl From<CrossSectionError> for CliError {
+    fn from(error: CrossSectionError) -> Self {
+        let (code, field) = match &error {
+            CrossSectionError::InvalidBjorkenX { .. } => ("invalid_bjorken_x", Some("x")),
+            CrossSectionError::NonPositiveQ2 { .. } => ("non_positive_q2", Some("q2")),
+            CrossSectionError::UnphysicalS { .. } => ("unphysical_s", None),
+            CrossSectionError::InvalidInelasticity { .. } => ("invalid_inelasticity", None),
+            CrossSectionError::InvalidDenominator { .. } => ("invalid_denominator", None),
+            CrossSectionError::InvalidStructureFunction { .. } => {
+                ("invalid_structure_function", None)
+            }
+            CrossSectionError::NegativeCrossSectionFactor { .. } => {
+                ("negative_cross_section_factor", None)
+            }
+            CrossSectionError::NonFiniteResult { .. } => ("non_finite_result", None),
+            CrossSectionError::Coupling(_) => {
+                return Self::new(
+                    "coupling_failed",
+                    GuiErrorCategory::Unknown,
+                    None,
+                    error.to_string(),
+                )
+            }
+        };
+        Self::new(
+            code,
+            GuiErrorCategory::InvalidKinematics,
+            field,
+            error.to_string(),
+        )
+    }
+}
+
+impl From<PdfError> for CliError {
+    fn from(error: PdfError) -> Self {
+        let message = error.to_string();
+        match &error {
+            PdfError::EmptySetName => Self::new(
+                "empty_pdf_set",
+                GuiErrorCategory::InvalidInput,
+                Some("pdf-set"),
+                message,
+            ),
+            PdfError::InvalidMember { .. } => Self::new(
+                "invalid_pdf_member",
+                GuiErrorCategory::InvalidInput,
+                Some("pdf-member"),
+                message,
+            ),
+            PdfError::SetUnavailable { .. } => Self::new(
+                "pdf_set_unavailable",
+                GuiErrorCategory::LhapdfSetNotInstalled,
+                Some("pdf-set"),
+                message,
+            ),
+            PdfError::MissingSetMetadata { .. } | PdfError::InvalidSetMetadata { .. } => Self::new(
+                "pdf_set_metadata",
+                GuiErrorCategory::LhapdfSetNotInstalled,
+                Some("pdf-set"),
+                message,
+            ),
+            PdfError::MemberOutOfRange { .. } | PdfError::MemberUnavailable { .. } => Self::new(
+                "pdf_member_unavailable",
+                GuiErrorCategory::InvalidInput,
+                Some("pdf-member"),
+                message,
+            ),
+            PdfError::InvalidInput { name, .. } => Self::new(
+                "invalid_pdf_input",
+                GuiErrorCategory::InvalidKinematics,
+                Some(name),
+                message,
+            ),
+            PdfError::XOutsideGrid { .. } => Self::new(
+                "x_outside_pdf_grid",
+                GuiErrorCategory::InvalidKinematics,
+                Some("x"),
+                message,
+            ),
+            PdfError::Q2OutsideGrid { .. } => Self::new(
+                "q2_outside_pdf_grid",
+                GuiErrorCategory::InvalidKinematics,
+                Some("q2"),
+                message,
+            ),
+            PdfError::BackendEvaluationFailed { .. } | PdfError::NonFiniteDensity { .. } => {
+                Self::new(
+                    "pdf_evaluation_failed",
+                    GuiErrorCategory::Unknown,
+                    None,
+                    message,
+                )
+            }
+        }
+    }
+}
+
+impl From<StructureFunctionError> for CliError {
+    fn from(error: StructureFunctionError) -> Self {
+        let message = error.to_string();
+        match error {
+            StructureFunctionError::InvalidBjorkenX { .. } => Self::new(
+                "invalid_bjorken_x",
+                GuiErrorCategory::InvalidKinematics,
+                Some("x"),
+                message,
+            ),
+            StructureFunctionError::NonPositiveQ2 { .. } => Self::new(
+                "non_positive_q2",
+                GuiErrorCategory::InvalidKinematics,
+                Some("q2"),
+                message,
+            ),
+            StructureFunctionError::Pdf(source) => Self::from(source),
+            StructureFunctionError::NonFiniteDensity { .. }
+            | StructureFunctionError::MismatchedPdfKinematics { .. }
+            | StructureFunctionError::NonFiniteResult { .. } => Self::new(
+                "pdf_evaluation_failed",
+                GuiErrorCategory::Unknown,
+                None,
+                message,
+            ),
+        }
+    }
+}
+
+impl From<StructureFunctionProviderError> for CliError {
+    fn from(error: StructureFunctionProviderError) -> Self {
+        use StructureFunctionProviderError as ProviderError;
+
+        let message = error.to_string();
+        let option = |field: &str| field.replace('_', "-");
+        match error {
+            ProviderError::InvalidRequest { field, .. } => {
+                let category = if matches!(field, "x" | "q2") {
+                    GuiErrorCategory::InvalidKinematics
+                } else {
+                    GuiErrorCategory::InvalidInput
+                };
+                Self::new("invalid_request", category, Some(&option(field)), message)
+            }
+            ProviderError::UnsupportedOrder { .. } => Self::new(
+                "unsupported_order",
+                GuiErrorCategory::UnsupportedOrder,
+                Some("order"),
+                message,
+            ),
+            ProviderError::UnsupportedScale { .. } => Self::new(
+                "unsupported_scale",
+                GuiErrorCategory::InvalidInput,
+                Some("mu-f-over-q"),
+                message,
+            ),
+            ProviderError::ConfigurationMismatch { field, .. } => Self::new(
+                "configuration_mismatch",
+                GuiErrorCategory::InvalidInput,
+                Some(&option(field)),
+                message,
+            ),
+            ProviderError::LoEvaluation(source) => Self::from(source),
+            ProviderError::BackendUnavailable { .. } => Self::new(
+                "backend_unavailable",
+                GuiErrorCategory::ApfelBackendMissing,
+                Some("backend"),
+                message,
+            ),
+            ProviderError::OutOfDomain { .. } => Self::new(
+                "outside_backend_domain",
+                GuiErrorCategory::InvalidKinematics,
+                None,
+                message,
+            ),
+            ProviderError::UncertaintyTooLarge { .. } => Self::new(
+                "uncertainty_too_large",
+                GuiErrorCategory::InvalidKinematics,
+                Some("max-uncertainty"),
+                message,
+            ),
+            ProviderError::BackendRejected { .. } => Self::new(
+                "backend_rejected",
+                GuiErrorCategory::ProcessFailed,
+                None,
+                message,
+            ),
+            ProviderError::NonFiniteResult { .. } => Self::new(
+                "non_finite_result",
+                GuiErrorCategory::Unknown,
+                None,
+                message,
+            ),
+            ProviderError::BackendIo { .. }
+            | ProviderError::BackendExited { .. }
+            | ProviderError::RequestSerialization { .. }
+            | ProviderError::ResponseDeserialization { .. }
+            | ProviderError::SchemaMismatch { .. }
+            | ProviderError::InvalidResponse { .. }
+            | ProviderError::MetadataMismatch { .. }
+            | ProviderError::EvaluationFailed(_) => Self::new(
+                "backend_failed",
+                GuiErrorCategory::ProcessFailed,
+                None,
+                message,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| (*arg).to_owned()).collect()
+    }
+
+    #[test]
+    fn the_global_format_is_taken_from_anywhere_on_the_command_line() {
+        assert_eq!(
+            OutputFormat::from_arguments(strings(&["dis-kinematics", "--format", "json", "--x"]))
+                .unwrap(),
+            (
+                Some(OutputFormat::Json),
+                strings(&["dis-kinematics", "--x"])
+            )
+        );
+        assert_eq!(
+            OutputFormat::from_arguments(strings(&["--help"])).unwrap(),
+            (None, strings(&["--help"]))
+        );
+        for args in [
+            &["--format"][..],
+            &["--format", "xml"],
+            &["--format", "csv", "--format", "json"],
+        ] {
+            let error = OutputFormat::from_arguments(strings(args)).unwrap_err();
+            assert_eq!(error.field.as_deref(), Some("format"));
+            assert_eq!(error.exit_code(), EXIT_INVALID_INPUT);
+        }
+    }
+
+    #[test]
+    fn errors_carry_a_stable_code_category_and_field() {
+        let physics = CliError::from(DisError::NonPositiveEnergy {
+            particle: "incoming electron",
+            energy: -1.0,
+        });
+        assert_eq!(physics.exit_code(), EXIT_PHYSICS_DOMAIN);
+        assert_eq!(
+            physics.to_json()["error"],
+            serde_json::json!({
+                "code": "non_positive_energy",
+                "category": "invalid_kinematics",
+                "field": "electron-energy",
+                "message": physics.message,
+                "suggestion": GuiError::new(GuiErrorCategory::InvalidKinematics, "").suggestion,
+                "exit_code": 4,
+            })
+        );
+
+        let missing = CliError::from(PdfError::SetUnavailable {
+            set_name: "CT18LO".to_owned(),
+            message: "not installed".to_owned(),
+        });
+        assert_eq!(missing.category, GuiErrorCategory::LhapdfSetNotInstalled);
+        assert_eq!(missing.exit_code(), EXIT_BACKEND_UNAVAILABLE);
+        assert_eq!(
+            CliError::from(candle_core::Error::Msg("failed".to_owned())).exit_code(),
+            EXIT_FAILURE
+        );
+    }
+}
@@ -0,0 +1,787 @@
+//! Environment diagnostics of the `doctor` subcommand.
+//!
+//! Every check only inspects its dependency: the LHAPDF data path and the
+//! installed sets, the APFEL++ and PYTHIA 8 backend executables, the surrogate
+//! model directory and the Python environment of the validation scripts. The
+//! backends are sent a handshake request, which they answer with their
+//! protocol schema version and library versions, so no physics is computed
+//! and no run directory is written.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use quark_sim::physics::structure_function_provider::STRUCTURE_FUNCTION_SCHEMA_VERSION;
+use quark_sim::physics::surrogate::SurrogateProvider;
+use quark_sim::physics::DEFAULT_APFEL_BACKEND_PATH;
+
+use crate::gui::state::{GuiError, GuiErrorCategory};
+
+/// Default PYTHIA 8 backend location when commands are run from the crate root.
+pub const DEFAULT_PYTHIA_BACKEND_PATH: &str = "physics-engine/build/pythia_dis_cli";
+/// Default surrogate model directory.
+pub const DEFAULT_MODEL_DIR: &str = "models/surrogate_v1";
+/// Default virtual environment of the Python validation scripts.
+pub const DEFAULT_VENV_DIR: &str = "analysis/venv";
+/// Packages the validation scripts import.
+pub const REQUIREMENTS_PATH: &str = "analysis/requirements.txt";
+
+/// Schema version of the PYTHIA 8 backend protocol, spoken by `pythia_dis_cli`.
+pub const PYTHIA_SCHEMA_VERSION: i32 = 1;
+
+/// `type` of the handshake request and of its reply.
+const HANDSHAKE_TYPE: &str = "handshake";
+
+/// A native backend executable and the protocol it speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Apfel,
+    Pythia,
+}
+
+impl Backend {
+    const fn schema_version(self) -> i64 {
+        match self {
+            Self::Apfel => STRUCTURE_FUNCTION_SCHEMA_VERSION as i64,
+            Self::Pythia => PYTHIA_SCHEMA_VERSION as i64,
+        }
+    }
+}
+
+/// Locations checked by `doctor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoctorOptions {
+    pub apfel_backend: PathBuf,
+    pub pythia_backend: PathBuf,
+    pub model_dir: PathBuf,
+    pub venv: PathBuf,
+}
+
+impl Default for DoctorOptions {
+    /// The locations the other subcommands use: `APFEL_BACKEND_BIN`,
+    /// `PYTHIA_BACKEND_BIN` and `VIRTUAL_ENV` replace the built-in paths.
+    fn default() -> Self {
+        let from_environment = |name: &str, default: &str| {
+            env::var_os(name)
+                .filter(|value| !value.is_empty())
+                .map_or_else(|| PathBuf::from(default), PathBuf::from)
+        };
+        Self {
+            apfel_backend: from_environment("APFEL_BACKEND_BIN", DEFAULT_APFEL_BACKEND_PATH),
+            pythia_backend: from_environment("PYTHIA_BACKEND_BIN", DEFAULT_PYTHIA_BACKEND_PATH),
+            model_dir: PathBuf::from(DEFAULT_MODEL_DIR),
+            venv: from_environment("VIRTUAL_ENV", DEFAULT_VENV_DIR),
+        }
+    }
+}
+
+/// Build provenance recorded by `build.rs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildProvenance {
+    pub version: String,
+    pub git_hash: String,
+    pub git_dirty: bool,
+    pub rustc_version: String,
+    pub os_arch: String,
+}
+
+impl BuildProvenance {
+    #[must_use]
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            git_hash: option_env!("GIT_HASH").unwrap_or("unknown").to_owned(),
+            git_dirty: option_env!("GIT_DIRTY") == Some("true"),
+            rustc_version: option_env!("RUSTC_VERSION").unwrap_or("unknown").to_owned(),
+            os_arch: option_env!("OS_ARCH").unwrap_or("unknown").to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl CheckStatus {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Warning => "warning",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Outcome of one check. A failure carries the GUI error category of the
+/// dependency, which also selects the exit status.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<GuiErrorCategory>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            category: None,
+            suggestion: None,
+        }
+    }
+
+    fn warning(name: &'static str, detail: impl Into<String>, suggestion: &str) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warning,
+            detail: detail.into(),
+            category: None,
+            suggestion: Some(suggestion.to_owned()),
+        }
+    }
+
+    fn failed(name: &'static str, category: GuiErrorCategory, detail: impl Into<String>) -> Self {
+        let suggestion = GuiError::new(category.clone(), "").suggestion;
+        Self {
+            name,
+            status: CheckStatus::Failed,
+            detail: detail.into(),
+            category: Some(category),
+            suggestion: Some(suggestion),
+        }
+    }
+
+    fn with_suggestion(mut self, suggestion: &str) -> Self {
+        self.suggestion = Some(suggestion.to_owned());
+        self
+    }
+}
+
+/// One directory LHAPDF searches for sets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LhapdfDataPath {
+    pub path: PathBuf,
+    pub exists: bool,
+}
+
+/// An installed set. `member_count` is `None` when the `.info` file does not
+/// declare a readable `NumMembers`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstalledPdfSet {
+    pub name: String,
+    pub member_count: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LhapdfReport {
+    pub data_paths: Vec<LhapdfDataPath>,
+    pub sets: Vec<InstalledPdfSet>,
+}
+
+impl LhapdfReport {
+    /// Find the LHAPDF data directories and the sets installed in them.
+    #[must_use]
+    pub fn scan() -> Self {
+        let data_paths = lhapdf_data_paths();
+        let sets = installed_sets(
+            data_paths
+                .iter()
+                .filter(|data_path| data_path.exists)
+                .map(|data_path| data_path.path.as_path()),
+        );
+        Self { data_paths, sets }
+    }
+}
+
+/// Everything `doctor` prints.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DoctorReport {
+    pub ok: bool,
+    pub build: BuildProvenance,
+    pub lhapdf: LhapdfReport,
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    /// Run every check.
+    #[must_use]
+    pub fn collect(options: &DoctorOptions) -> Self {
+        let lhapdf = LhapdfReport::scan();
+        let checks = vec![
+            check_lhapdf(&lhapdf),
+            check_apfel_backend(&options.apfel_backend),
+            check_pythia_backend(&options.pythia_backend),
+            check_surrogate_model(&options.model_dir),
+            check_python_environment(&options.venv),
+        ];
+        Self {
+            ok: checks
+                .iter()
+                .all(|check| check.status != CheckStatus::Failed),
+            build: BuildProvenance::current(),
+            lhapdf,
+            checks,
+        }
+    }
+
+    #[must_use]
+    pub fn failures(&self) -> Vec<&Check> {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Failed)
+            .collect()
+    }
+
+    /// The report as aligned text with a suggestion under every problem.
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        let build = &self.build;
+        let mut text = String::from("Build\n");
+        let dirty = if build.git_dirty { " (dirty)" } else { "" };
+        let _ = writeln!(text, "  version    {}", build.version);
+        let _ = writeln!(text, "  git hash   {}{dirty}", build.git_hash);
+        let _ = writeln!(text, "  rustc      {}", build.rustc_version);
+        let _ = writeln!(text, "  platform   {}", build.os_arch);
+
+        text.push_str("\nLHAPDF\n");
+        if self.lhapdf.data_paths.is_empty() {
+            text.push_str("  data path  (none)\n");
+        }
+        for data_path in &self.lhapdf.data_paths {
+            let missing = if data_path.exists { "" } else { " (missing)" };
+            let _ = writeln!(text, "  data path  {}{missing}", data_path.path.display());
+        }
+        for set in &self.lhapdf.sets {
+            let members = set
+                .member_count
+                .map_or_else(|| "unknown".to_owned(), |count| count.to_string());
+            let _ = writeln!(text, "  {:<24} NumMembers {members}", set.name);
+        }
+
+        text.push_str("\nChecks\n");
+        for check in &self.checks {
+            let _ = writeln!(
+                text,
+                "  {:<8} {:<16} {}",
+                check.status.as_str(),
+                check.name,
+                check.detail
+            );
+            if let Some(suggestion) = check
+                .suggestion
+                .as_ref()
+                .filter(|_| check.status != CheckStatus::Ok)
+            {
+                let _ = writeln!(text, "  {:<8} {:<16} {suggestion}", "", "");
+            }
+        }
+        text
+    }
+
+    /// One CSV row per check.
+    pub fn write_csv(&self, output: impl Write) -> io::Result<()> {
+        let mut writer = csv::Writer::from_writer(output);
+        writer.write_record(["check", "status", "detail", "suggestion"])?;
+        for check in &self.checks {
+            writer.write_record([
+                check.name,
+                check.status.as_str(),
+                &check.detail,
+                check.suggestion.as_deref().unwrap_or(""),
+            ])?;
+        }
+        writer.flush()
+    }
+}
+
+/// LHAPDF search directories in its own order: `LHAPDF_DATA_PATH` or
+/// `LHAPATH`, then the data directory of the installation.
+fn lhapdf_data_paths() -> Vec<LhapdfDataPath> {
+    let mut paths: Vec<PathBuf> = env::var_os("LHAPDF_DATA_PATH")
+        .or_else(|| env::var_os("LHAPATH"))
+        .map(|value| env::split_paths(&value).collect())
+        .unwrap_or_default();
+    let installation = Command::new("lhapdf-config")
+        .arg("--datadir")
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+        .filter(|directory| !directory.is_empty());
+    paths.extend(installation.map(PathBuf::from));
+
+    let mut data_paths: Vec<LhapdfDataPath> = Vec::new();
+    for path in paths {
+        if path.as_os_str().is_empty() || data_paths.iter().any(|known| known.path == path) {
+            continue;
+        }
+        data_paths.push(LhapdfDataPath {
+            exists: path.is_dir(),
+            path,
+        });
+    }
+    data_paths
+}
+
+/// Sets in the given directories, sorted by name. A set installed twice is
+/// reported from the first directory, the one LHAPDF loads.
+fn installed_sets<'a>(directories: impl IntoIterator<Item = &'a Path>) -> Vec<InstalledPdfSet> {
+    let mut sets: Vec<InstalledPdfSet> = Vec::new();
+    for directory in directories {
+        let Ok(entries) = fs::read_dir(directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let info_path = entry.path().join(format!("{name}.info"));
+            if !info_path.is_file() || sets.iter().any(|set| set.name == name) {
+                continue;
+            }
+            let member_count = fs::read_to_string(&info_path)
+                .ok()
+                .and_then(|info| info_member_count(&info));
+            sets.push(InstalledPdfSet { name, member_count });
+        }
+    }
+    sets.sort_by(|left, right| left.name.cmp(&right.name));
+    sets
+}
+
+/// `NumMembers` of an LHAPDF `.info` file.
+fn info_member_count(info: &str) -> Option<usize> {
+    info.lines()
+        .find_map(|line| line.trim().strip_prefix("NumMembers:"))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+fn check_lhapdf(report: &LhapdfReport) -> Check {
+    const NAME: &str = "lhapdf";
+    let category = GuiErrorCategory::LhapdfSetNotInstalled;
+    let existing = report
+        .data_paths
+        .iter()
+        .filter(|data_path| data_path.exists)
+        .count();
+    if existing == 0 {
+        return Check::failed(NAME, category, "no LHAPDF data directory found")
+            .with_suggestion("Run: source scripts/lhapdf_env.sh, or set LHAPDF_DATA_PATH");
+    }
+    if report.sets.is_empty() {
+        return Check::failed(NAME, category, "no PDF set is installed");
+    }
+    Check::ok(
+        NAME,
+        format!(
+            "installed sets: {}, data directories: {existing}",
+            report.sets.len()
+        ),
+    )
+}
+
+fn check_apfel_backend(executable: &Path) -> Check {
+    check_backend(
+        "apfel_backend",
+        GuiErrorCategory::ApfelBackendMissing,
+        Backend::Apfel,
+        executable,
+    )
+}
+
+fn check_pythia_backend(executable: &Path) -> Check {
+    check_backend(
+        "pythia_backend",
+        GuiErrorCategory::PythiaBackendMissing,
+        Backend::Pythia,
+        executable,
+    )
+}
+
+fn check_backend(
+    name: &'static str,
+    category: GuiErrorCategory,
+    backend: Backend,
+    executable: &Path,
+) -> Check {
+    if !executable.is_file() {
+        return Check::failed(
+            name,
+            category,
+            format!("executable not found: {}", executable.display()),
+        );
+    }
+    match handshake(backend, executable) {
+        Ok(versions) => {
+            let mut detail = format!(
+                "{} speaks schema v{}",
+                executable.display(),
+                backend.schema_version()
+            );
+            for (key, version) in &versions {
+                let _ = write!(detail, ", {key} {version}");
+            }
+            Check::ok(name, detail)
+        }
+        Err(message) => Check::failed(name, category, message),
+    }
+}
+
+fn check_surrogate_model(model_dir: &Path) -> Check {
+    const NAME: &str = "surrogate_model";
+    match SurrogateProvider::load(model_dir) {
+        Ok(provider) => {
+            let config = provider.config();
+            Check::ok(
+                NAME,
+                format!(
+                    "{}: {} {} member {}, {} networks, test max relative error {:.3}",
+                    model_dir.display(),
+                    config.order,
+                    config.pdf_set,
+                    config.pdf_member,
+                    config.ensemble_size,
+                    config.test_max_rel_error
+                ),
+            )
+        }
+        Err(error) => Check::failed(
+            NAME,
+            GuiErrorCategory::SurrogateModelMissing,
+            error.to_string(),
+        ),
+    }
+}
+
+/// The validation scripts run `python3`. Without the virtual environment
+/// that is the interpreter on `PATH`, which is only a warning as long as it
+/// imports the required packages.
+fn check_python_environment(venv: &Path) -> Check {
+    const NAME: &str = "python_venv";
+    let category = GuiErrorCategory::ProcessFailed;
+    let install = format!(
+        "Run: python3 -m venv {0} && {0}/bin/pip install -r {REQUIREMENTS_PATH}",
+        venv.display()
+    );
+    let venv_python = venv.join(if cfg!(windows) {
+        "Scripts/python.exe"
+    } else {
+        "bin/python"
+    });
+    let (python, in_venv) = if venv_python.is_file() {
+        (venv_python, true)
+    } else {
+        (PathBuf::from("python3"), false)
+    };
+
+    let version = Command::new(&python)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            let text = if output.stdout.is_empty() {
+                output.stderr
+            } else {
+                output.stdout
+            };
+            String::from_utf8_lossy(&text).trim().to_owned()
+        });
+    let Some(version) = version else {
+        return Check::failed(NAME, category, format!("cannot run {}", python.display()))
+            .with_suggestion(&install);
+    };
+
+    let modules = fs::read_to_string(REQUIREMENTS_PATH)
+        .map(|requirements| required_modules(&requirements))
+        .unwrap_or_default();
+    if !modules.is_empty() {
+        let imports = Command::new(&python)
+            .args(["-c", &format!("import {}", modules.join(", "))])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if !imports.is_ok_and(|status| status.success()) {
+            return Check::failed(
+                NAME,
+                category,
+                format!(
+                    "{version} at {} cannot import {}",
+                    python.display(),
+                    modules.join(", ")
+                ),
+            )
+            .with_suggestion(&install);
+        }
+    }
+
+    if in_venv {
+        Check::ok(NAME, format!("{version} in {}", venv.display()))
+    } else {
+        Check::warning(
+            NAME,
+            format!(
+                "no virtual environment at {}; using {version} from PATH",
+                venv.display()
+            ),
+            &install,
+        )
+    }
+}
+
+/// Import names of the packages in a `requirements.txt`.
+fn required_modules(requirements: &str) -> Vec<String> {
+    requirements
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('-'))
+        .filter_map(|line| {
+            let end = line
+                .find(|character: char| {
+                    !(character.is_ascii_alphanumeric() || "-_.".contains(character))
+                })
+                .unwrap_or(line.len());
+            let name = line[..end].to_ascii_lowercase().replace('-', "_");
+            (!name.is_empty()).then_some(name)
+        })
+        .collect()
+}
+
+/// Send `backend` its handshake request and return the library versions it
+/// reports, keyed as in its metadata, e.g. `pythia_version`.
+pub fn handshake(backend: Backend, executable: &Path) -> Result<BTreeMap<String, String>, String> {
+    let request = json!({
+        "schema_version": backend.schema_version(),
+        "type": HANDSHAKE_TYPE,
+    });
+    // `pythia_dis_cli` requires an output directory argument. The handshake
+    // names one that does not exist, so nothing can be written there.
+    let arguments = match backend {
+        Backend::Apfel => Vec::new(),
+        Backend::Pythia => vec![env::temp_dir()
+            .join(format!("quark_sim_handshake_{}", std::process::id()))
+            .join("run")
+            .into_os_string()],
+    };
+    let reply = exchange_handshake(executable, &arguments, &request)?;
+    interpret_handshake(&reply, backend.schema_version())
+}
+
+/// Send `request` and return the last JSON line the backend writes to stdout.
+fn exchange_handshake(
+    executable: &Path,
+    arguments: &[OsString],
+    request: &Value,
+) -> Result<Value, String> {
+    let mut child = Command::new(executable)
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("cannot start {}: {error}", executable.display()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A backend that exits before reading still gets its reply decoded.
+        let _ = writeln!(stdin, "{request}");
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|error| format!("{} did not finish: {error}", executable.display()))?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str::<Value>(line.trim()).ok())
+        .filter(Value::is_object)
+        .ok_or_else(|| {
+            format!(
+                "{} gave no JSON reply to the handshake (exit status {})",
+                executable.display(),
+                output
+                    .status
+                    .code()
+                    .map_or_else(|| "none".to_owned(), |code| code.to_string())
+            )
+        })
+}
+
+/// A backend speaking `schema_version` answers the handshake with that
+/// version and the `*_version` strings of its libraries.
+fn interpret_handshake(
+    reply: &Value,
+    schema_version: i64,
+) -> Result<BTreeMap<String, String>, String> {
+    if reply["success"] != Value::Bool(true) {
+        let error = &reply["error"];
+        return Err(match error["code"].as_str() {
+            Some(code) => format!(
+                "backend rejected the schema-v{schema_version} handshake: {code}: {}",
+                error["message"].as_str().unwrap_or("")
+            ),
+            None => "backend reply is neither a handshake nor an error".to_owned(),
+        });
+    }
+    match &reply["schema_version"] {
+        Value::Number(version) if version.as_i64() == Some(schema_version) => {}
+        Value::Null => return Err("backend handshake has no schema_version".to_owned()),
+        version => {
+            return Err(format!(
+                "backend replied with schema version {version}, expected {schema_version}"
+            ))
+        }
+    }
+    if reply["type"] != HANDSHAKE_TYPE {
+        return Err("backend reply is not a handshake".to_owned());
+    }
+    Ok(reply
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(key, _)| key.ends_with("_version") && *key != "schema_version")
+        .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_owned())))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("quark_sim_doctor_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn handshake_accepts_only_a_schema_v1_reply() {
+        let dir = scratch_dir("handshake");
+        let backend = |name: &str, reply: &str| {
+            let script = dir.join(name);
+            fs::write(
+                &script,
+                format!("cat > /dev/null\necho '{reply}'\nexit 0\n"),
+            )
+            .unwrap();
+            let request = json!({"schema_version": 1, "type": HANDSHAKE_TYPE});
+            exchange_handshake(Path::new("/bin/sh"), &[script.into_os_string()], &request)
+                .and_then(|reply| interpret_handshake(&reply, 1))
+        };
+
+        let speaking = r#"{"schema_version":1,"success":true,"type":"handshake","backend":"pythia","pythia_version":"8.312","lhapdf_version":"6.5.4"}"#;
+        let versions = backend("speaking.sh", speaking).unwrap();
+        assert_eq!(
+            versions.into_iter().collect::<Vec<_>>(),
+            [
+                ("lhapdf_version".to_owned(), "6.5.4".to_owned()),
+                ("pythia_version".to_owned(), "8.312".to_owned())
+            ]
+        );
+        let newer = r#"{"schema_version":2,"success":true,"type":"handshake"}"#;
+        assert!(backend("newer.sh", newer)
+            .unwrap_err()
+            .contains("schema version 2, expected 1"));
+        let outdated = r#"{"success":false,"error":{"code":"invalid_process","message":"m"}}"#;
+        assert!(backend("outdated.sh", outdated)
+            .unwrap_err()
+            .contains("invalid_process"));
+        assert!(backend("silent.sh", "not json")
+            .unwrap_err()
+            .contains("no JSON reply"));
+
+        let missing = check_apfel_backend(&dir.join("apfel_cli"));
+        assert_eq!(missing.status, CheckStatus::Failed);
+        assert_eq!(
+            missing.category,
+            Some(GuiErrorCategory::ApfelBackendMissing)
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn installed_sets_are_listed_with_member_counts() {
+        let first = scratch_dir("sets_first");
+        let second = scratch_dir("sets_second");
+        for (directory, name, info) in [
+            (&first, "CT18LO", "SetDesc: CT18 LO\nNumMembers: 1\n"),
+            (&first, "broken", "SetDesc: no member count\n"),
+            (&second, "CT18LO", "NumMembers: 59\n"),
+            (&second, "NNPDF40_nnlo_as_01180", "NumMembers: 101\n"),
+        ] {
+            fs::create_dir_all(directory.join(name)).unwrap();
+            fs::write(directory.join(name).join(format!("{name}.info")), info).unwrap();
+        }
+        fs::create_dir_all(first.join("not_a_set")).unwrap();
+
+        let sets = installed_sets([first.as_path(), second.as_path()]);
+        let summary: Vec<_> = sets
+            .iter()
+            .map(|set| (set.name.as_str(), set.member_count))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("CT18LO", Some(1)),
+                ("NNPDF40_nnlo_as_01180", Some(101)),
+                ("broken", None)
+            ]
+        );
+        assert_eq!(
+            required_modules("numpy>=1.26.0\n# plots\nscikit-learn[extra] ; python_version>'3'\n"),
+            ["numpy", "scikit_learn"]
+        );
+        fs::remove_dir_all(first).unwrap();
+        fs::remove_dir_all(second).unwrap();
+    }
+
+    #[test]
+    fn text_report_shows_suggestions_for_problems_only() {
+        let report = DoctorReport {
+            ok: false,
+            build: BuildProvenance::current(),
+            lhapdf: LhapdfReport {
+                data_paths: vec![LhapdfDataPath {
+                    path: PathBuf::from("/opt/lhapdf"),
+                    exists: true,
+                }],
+                sets: vec![InstalledPdfSet {
+                    name: "CT18LO".to_owned(),
+                    member_count: Some(1),
+                }],
+            },
+            checks: vec![
+                check_lhapdf(&LhapdfReport {
+                    data_paths: Vec::new(),
+                    sets: Vec::new(),
+                }),
+                Check::ok("surrogate_model", "models/surrogate_v1"),
+            ],
+        };
+
+        let text = report.to_text();
+        assert!(text.contains("  data path  /opt/lhapdf\n"));
+        assert!(text.contains("CT18LO                   NumMembers 1"));
+        assert!(text.contains("failed   lhapdf           no LHAPDF data directory found"));
+        assert!(text.contains("source scripts/lhapdf_env.sh"));
+        assert_eq!(text.matches("Run:").count(), 1);
+        assert_eq!(report.failures().len(), 1);
+    }
+}
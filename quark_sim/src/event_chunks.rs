@@ -0,0 +1,681 @@
+//! Event generation split into independent chunks and merged back into one
+//! run directory.
+//!
+//! `generate-dis-events --jobs N` runs N generator processes concurrently,
+//! each in its own directory below `chunks/` with a share of the requested
+//! events and a seed derived from the run seed. [`merge_chunks`] then writes
+//! the layout of a single run: the HepMC3 events and the
+//! `inclusive_observables.csv` rows of every chunk in chunk order with
+//! consecutive event numbers, a combined `summary.json` and `metadata.json`,
+//! and the chunk logs one after the other in `generator.log`.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::hepmc3::{Hepmc3Error, Hepmc3Reader, Hepmc3WriteError, Hepmc3Writer};
+
+pub const CHUNKS_DIRECTORY_NAME: &str = "chunks";
+pub const EVENTS_FILE_NAME: &str = "events.hepmc3";
+pub const OBSERVABLES_FILE_NAME: &str = "inclusive_observables.csv";
+pub const SUMMARY_FILE_NAME: &str = "summary.json";
+pub const METADATA_FILE_NAME: &str = "metadata.json";
+pub const GENERATOR_LOG_FILE_NAME: &str = "generator.log";
+
+/// Largest seed PYTHIA accepts in `Random:seed`.
+pub const MAX_GENERATOR_SEED: i32 = 900_000_000;
+
+/// One chunk of a split run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GenerationChunk {
+    pub index: usize,
+    #[serde(skip)]
+    pub directory: PathBuf,
+    pub events: usize,
+    pub seed: i32,
+    /// Backend launches needed, including the successful one.
+    pub attempts: usize,
+}
+
+/// `chunks/chunk_<index>` below `run_directory`.
+#[must_use]
+pub fn chunk_directory(run_directory: &Path, index: usize) -> PathBuf {
+    run_directory
+        .join(CHUNKS_DIRECTORY_NAME)
+        .join(format!("chunk_{index:03}"))
+}
+
+/// Split `total` events into at most `jobs` chunks whose sizes differ by at
+/// most one, largest first. No chunk is empty, so fewer than `jobs` chunks
+/// are returned when `total < jobs`.
+#[must_use]
+pub fn split_events(total: usize, jobs: usize) -> Vec<usize> {
+    let chunks = jobs.min(total);
+    (0..chunks)
+        .map(|index| total / chunks + usize::from(index < total % chunks))
+        .collect()
+}
+
+/// Distinct generator seeds for `count` chunks, derived from the run seed
+/// with SplitMix64 so that the same seed and chunk count repeat a run while
+/// runs with neighbouring seeds share no chunk seed.
+#[must_use]
+pub fn chunk_seeds(seed: i32, count: usize) -> Vec<i32> {
+    let modulus = u64::from(MAX_GENERATOR_SEED.unsigned_abs()) + 1;
+    let mut used = BTreeSet::new();
+    (0..count)
+        .map(|index| {
+            let mut state = (u64::from(seed.unsigned_abs()) << 32) | index as u64;
+            loop {
+                state = splitmix64(state);
+                let candidate = (state % modulus) as i32;
+                if used.insert(candidate) {
+                    return candidate;
+                }
+            }
+        })
+        .collect()
+}
+
+fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[derive(Debug)]
+pub enum ChunkMergeError {
+    NoChunks,
+    Io {
+        operation: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    Hepmc3 {
+        path: PathBuf,
+        source: Hepmc3Error,
+    },
+    Hepmc3Write {
+        path: PathBuf,
+        source: Hepmc3WriteError,
+    },
+    Csv {
+        path: PathBuf,
+        source: csv::Error,
+    },
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// A chunk's output does not fit the first chunk's.
+    Inconsistent {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl fmt::Display for ChunkMergeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoChunks => write!(formatter, "there are no chunks to merge"),
+            Self::Io {
+                operation,
+                path,
+                source,
+            } => write!(
+                formatter,
+                "failed while {operation} '{}': {source}",
+                path.display()
+            ),
+            Self::Hepmc3 { path, source } => write!(
+                formatter,
+                "failed to read chunk events '{}': {source}",
+                path.display()
+            ),
+            Self::Hepmc3Write { path, source } => write!(
+                formatter,
+                "failed to write merged events '{}': {source}",
+                path.display()
+            ),
+            Self::Csv { path, source } => {
+                write!(
+                    formatter,
+                    "failed to merge CSV '{}': {source}",
+                    path.display()
+                )
+            }
+            Self::Json { path, source } => {
+                write!(
+                    formatter,
+                    "failed to merge JSON '{}': {source}",
+                    path.display()
+                )
+            }
+            Self::Inconsistent { path, message } => write!(
+                formatter,
+                "cannot merge chunk output '{}': {message}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for ChunkMergeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::NoChunks | Self::Inconsistent { .. } => None,
+            Self::Io { source, .. } => Some(source),
+            Self::Hepmc3 { source, .. } => Some(source),
+            Self::Hepmc3Write { source, .. } => Some(source),
+            Self::Csv { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Merge the outputs of finished `chunks` into `run_directory` and return the
+/// combined summary. `seed` is the run seed the chunk seeds were derived from.
+pub fn merge_chunks(
+    run_directory: &Path,
+    chunks: &[GenerationChunk],
+    seed: i32,
+) -> Result<Value, ChunkMergeError> {
+    if chunks.is_empty() {
+        return Err(ChunkMergeError::NoChunks);
+    }
+    merge_events(run_directory, chunks)?;
+    merge_observables(run_directory, chunks)?;
+    merge_logs(run_directory, chunks)?;
+
+    let summaries = chunks
+        .iter()
+        .map(|chunk| read_json(&chunk.directory.join(SUMMARY_FILE_NAME)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let summary = merge_summaries(&summaries, chunks);
+    write_json(&run_directory.join(SUMMARY_FILE_NAME), &summary)?;
+
+    let first_metadata = chunks[0].directory.join(METADATA_FILE_NAME);
+    if first_metadata.is_file() {
+        let metadata = chunks
+            .iter()
+            .map(|chunk| read_json(&chunk.directory.join(METADATA_FILE_NAME)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let metadata = merge_metadata(&metadata, chunks, seed);
+        write_json(&run_directory.join(METADATA_FILE_NAME), &metadata)?;
+    }
+    Ok(summary)
+}
+
+/// Combine chunk summaries into the summary of the whole run.
+///
+/// Event counters and `sum_of_weights` are added, `max_*` values take the
+/// maximum, `failure_reasons` lists are concatenated (or their counts added)
+/// and `success` requires every chunk to succeed, while one cancelled chunk
+/// makes the run `cancelled`. Each chunk's
+/// `cross_section_pb` is the mean weight of its attempts, so the run's is the
+/// average weighted by `attempted_events`, with errors added in quadrature
+/// under the same weights. Other values are taken from the first chunk, and
+/// `jobs` and `chunks` record how the run was split.
+#[must_use]
+pub fn merge_summaries(summaries: &[Value], chunks: &[GenerationChunk]) -> Value {
+    let objects: Vec<&Map<String, Value>> = summaries.iter().filter_map(Value::as_object).collect();
+    let Some(first) = objects.first() else {
+        return Value::Null;
+    };
+    let values = |key| field_values(&objects, key);
+
+    let mut merged = Map::new();
+    for (key, value) in first.iter() {
+        let merged_value = match key.as_str() {
+            "success" => Value::Bool(values(key).all(|value| value == Some(&Value::Bool(true)))),
+            "cancelled" => Value::Bool(values(key).any(|value| value == Some(&Value::Bool(true)))),
+            "failure_reasons" => merge_failure_reasons(values(key)),
+            "cross_section_pb" | "cross_section_error_pb" => continue,
+            "sum_of_weights" => sum_floats(values(key)).map_or(Value::Null, Value::from),
+            key if key.starts_with("max_") => values(key)
+                .filter_map(|value| value?.as_f64())
+                .reduce(f64::max)
+                .map_or(Value::Null, Value::from),
+            _ => match sum_integers(values(key)) {
+                Some(total) if value.is_u64() => Value::from(total),
+                _ => value.clone(),
+            },
+        };
+        merged.insert(key.clone(), merged_value);
+    }
+
+    if let Some((cross_section, error)) = combined_cross_section(&objects) {
+        merged.insert("cross_section_pb".to_owned(), Value::from(cross_section));
+        merged.insert("cross_section_error_pb".to_owned(), Value::from(error));
+    }
+    merged.insert("jobs".to_owned(), Value::from(chunks.len()));
+    let chunk_records = chunks
+        .iter()
+        .zip(&objects)
+        .map(|(chunk, summary)| {
+            let mut record = serde_json::to_value(chunk).unwrap_or(Value::Null);
+            if let Some(record) = record.as_object_mut() {
+                for key in [
+                    "accepted_events",
+                    "cross_section_pb",
+                    "cross_section_error_pb",
+                ] {
+                    if let Some(value) = summary.get(key) {
+                        record.insert(key.to_owned(), value.clone());
+                    }
+                }
+            }
+            record
+        })
+        .collect();
+    merged.insert("chunks".to_owned(), Value::Array(chunk_records));
+    Value::Object(merged)
+}
+
+/// Combine chunk `metadata.json` files: the event counts are added, the
+/// run seed replaces the chunk seed and `chunk_seeds` lists every chunk's.
+#[must_use]
+pub fn merge_metadata(metadata: &[Value], chunks: &[GenerationChunk], seed: i32) -> Value {
+    let Some(Value::Object(first)) = metadata.first() else {
+        return Value::Null;
+    };
+    let mut merged = first.clone();
+    for key in [
+        "requested_event_count",
+        "accepted_event_count",
+        "failed_event_count",
+    ] {
+        if let Some(total) = sum_integers(metadata.iter().map(|value| value.get(key))) {
+            merged.insert(key.to_owned(), Value::from(total));
+        }
+    }
+    merged.insert("random_seed".to_owned(), Value::from(seed));
+    merged.insert(
+        "chunk_seeds".to_owned(),
+        chunks.iter().map(|chunk| chunk.seed).collect(),
+    );
+    Value::Object(merged)
+}
+
+fn field_values<'a>(
+    objects: &'a [&'a Map<String, Value>],
+    key: &'a str,
+) -> impl Iterator<Item = Option<&'a Value>> + 'a {
+    objects.iter().map(move |object| object.get(key))
+}
+
+fn sum_integers<'a>(values: impl Iterator<Item = Option<&'a Value>>) -> Option<u64> {
+    values.map(|value| value?.as_u64()).sum()
+}
+
+fn sum_floats<'a>(values: impl Iterator<Item = Option<&'a Value>>) -> Option<f64> {
+    values.map(|value| value?.as_f64()).sum()
+}
+
+fn merge_failure_reasons<'a>(values: impl Iterator<Item = Option<&'a Value>>) -> Value {
+    let values: Vec<&Value> = values.flatten().collect();
+    if values.iter().all(|value| value.is_array()) {
+        return values
+            .iter()
+            .filter_map(|value| value.as_array())
+            .flatten()
+            .cloned()
+            .collect();
+    }
+    let mut counts = Map::new();
+    for (reason, count) in values
+        .iter()
+        .filter_map(|value| value.as_object())
+        .flatten()
+    {
+        let total =
+            counts.get(reason).and_then(Value::as_u64).unwrap_or(0) + count.as_u64().unwrap_or(0);
+        counts.insert(reason.clone(), Value::from(total));
+    }
+    Value::Object(counts)
+}
+
+fn combined_cross_section(summaries: &[&Map<String, Value>]) -> Option<(f64, f64)> {
+    let mut attempts = 0.0;
+    let mut weighted = 0.0;
+    let mut variance = 0.0;
+    for summary in summaries {
+        let chunk_attempts = summary.get("attempted_events")?.as_f64()?;
+        let cross_section = summary.get("cross_section_pb")?.as_f64()?;
+        let error = summary
+            .get("cross_section_error_pb")
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0);
+        attempts += chunk_attempts;
+        weighted += chunk_attempts * cross_section;
+        variance += (chunk_attempts * error).powi(2);
+    }
+    (attempts > 0.0).then(|| (weighted / attempts, variance.sqrt() / attempts))
+}
+
+fn merge_events(run_directory: &Path, chunks: &[GenerationChunk]) -> Result<(), ChunkMergeError> {
+    let output = run_directory.join(EVENTS_FILE_NAME);
+    let mut readers = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        let path = chunk.directory.join(EVENTS_FILE_NAME);
+        let mut reader = Hepmc3Reader::open(&path).map_err(|source| ChunkMergeError::Hepmc3 {
+            path: path.clone(),
+            source,
+        })?;
+        reader
+            .read_run_info()
+            .map_err(|source| ChunkMergeError::Hepmc3 {
+                path: path.clone(),
+                source,
+            })?;
+        readers.push((path, reader));
+    }
+
+    let run_info = readers[0].1.run_info().clone();
+    let mut writer = Hepmc3Writer::create(&output, run_info.clone()).map_err(|source| {
+        ChunkMergeError::Hepmc3Write {
+            path: output.clone(),
+            source,
+        }
+    })?;
+    let mut next_event_number = None;
+    for (path, reader) in readers {
+        if reader.run_info().weight_names != run_info.weight_names {
+            return Err(ChunkMergeError::Inconsistent {
+                path,
+                message: "its weight names differ from the first chunk's".to_owned(),
+            });
+        }
+        for event in reader {
+            let mut event = event.map_err(|source| ChunkMergeError::Hepmc3 {
+                path: path.clone(),
+                source,
+            })?;
+            let number = *next_event_number.get_or_insert(event.event_number);
+            event.event_number = number;
+            next_event_number = Some(number + 1);
+            writer
+                .write_event(&event)
+                .map_err(|source| ChunkMergeError::Hepmc3Write {
+                    path: output.clone(),
+                    source,
+                })?;
+        }
+    }
+    writer
+        .close()
+        .map_err(|source| ChunkMergeError::Hepmc3Write {
+            path: output,
+            source,
+        })
+}
+
+fn merge_observables(
+    run_directory: &Path,
+    chunks: &[GenerationChunk],
+) -> Result<(), ChunkMergeError> {
+    let output = run_directory.join(OBSERVABLES_FILE_NAME);
+    let csv_error = |path: &Path| {
+        let path = path.to_owned();
+        move |source| ChunkMergeError::Csv { path, source }
+    };
+    let mut writer = csv::Writer::from_path(&output).map_err(csv_error(&output))?;
+    let mut header: Option<csv::StringRecord> = None;
+    let mut next_event_number: Option<i64> = None;
+    for chunk in chunks {
+        let path = chunk.directory.join(OBSERVABLES_FILE_NAME);
+        let mut reader = csv::Reader::from_path(&path).map_err(csv_error(&path))?;
+        let chunk_header = reader.headers().map_err(csv_error(&path))?.clone();
+        match &header {
+            Some(header) if *header != chunk_header => {
+                return Err(ChunkMergeError::Inconsistent {
+                    path,
+                    message: "its columns differ from the first chunk's".to_owned(),
+                });
+            }
+            Some(_) => {}
+            None => {
+                writer
+                    .write_record(&chunk_header)
+                    .map_err(csv_error(&output))?;
+                header = Some(chunk_header.clone());
+            }
+        }
+        let event_column = chunk_header.iter().position(|name| name == "event_number");
+        for record in reader.records() {
+            let record = record.map_err(csv_error(&path))?;
+            let Some(column) = event_column else {
+                writer.write_record(&record).map_err(csv_error(&output))?;
+                continue;
+            };
+            let number = match next_event_number {
+                Some(number) => number,
+                None => record[column].trim().parse().unwrap_or(0),
+            };
+            next_event_number = Some(number + 1);
+            let number = number.to_string();
+            let renumbered: csv::StringRecord = record
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    if index == column {
+                        number.as_str()
+                    } else {
+                        field
+                    }
+                })
+                .collect();
+            writer
+                .write_record(&renumbered)
+                .map_err(csv_error(&output))?;
+        }
+    }
+    writer.flush().map_err(|source| ChunkMergeError::Io {
+        operation: "writing CSV",
+        path: output,
+        source,
+    })
+}
+
+fn merge_logs(run_directory: &Path, chunks: &[GenerationChunk]) -> Result<(), ChunkMergeError> {
+    let output = run_directory.join(GENERATOR_LOG_FILE_NAME);
+    let io_error = |operation, path: &Path| {
+        let path = path.to_owned();
+        move |source| ChunkMergeError::Io {
+            operation,
+            path,
+            source,
+        }
+    };
+    let mut log = fs::File::create(&output).map_err(io_error("creating", &output))?;
+    for chunk in chunks {
+        let path = chunk.directory.join(GENERATOR_LOG_FILE_NAME);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(source) => return Err(io_error("reading", &path)(source)),
+        };
+        writeln!(
+            log,
+            "==> chunk {} (seed {}, {} events, attempt {}) <==",
+            chunk.index, chunk.seed, chunk.events, chunk.attempts
+        )
+        .and_then(|()| log.write_all(text.as_bytes()))
+        .and_then(|()| {
+            if text.ends_with('\n') || text.is_empty() {
+                Ok(())
+            } else {
+                writeln!(log)
+            }
+        })
+        .map_err(io_error("writing", &output))?;
+    }
+    Ok(())
+}
+
+fn read_json(path: &Path) -> Result<Value, ChunkMergeError> {
+    let text = fs::read_to_string(path).map_err(|source| ChunkMergeError::Io {
+        operation: "reading",
+        path: path.to_owned(),
+        source,
+    })?;
+    serde_json::from_str(&text).map_err(|source| ChunkMergeError::Json {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+fn write_json(path: &Path, value: &Value) -> Result<(), ChunkMergeError> {
+    let mut text = serde_json::to_string_pretty(value).map_err(|source| ChunkMergeError::Json {
+        path: path.to_owned(),
+        source,
+    })?;
+    text.push('\n');
+    fs::write(path, text).map_err(|source| ChunkMergeError::Io {
+        operation: "writing",
+        path: path.to_owned(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hepmc3::{GenEvent, GenRunInfo};
+    use serde_json::json;
+
+    #[test]
+    fn events_and_seeds_are_split_reproducibly() {
+        assert_eq!(split_events(10, 4), [3, 3, 2, 2]);
+        assert_eq!(split_events(2, 4), [1, 1]);
+        assert_eq!(split_events(8, 1), [8]);
+
+        let seeds = chunk_seeds(12345, 16);
+        assert_eq!(seeds, chunk_seeds(12345, 16));
+        assert_eq!(seeds[..4], chunk_seeds(12345, 4)[..]);
+        assert_eq!(seeds.iter().collect::<BTreeSet<_>>().len(), 16);
+        assert!(seeds
+            .iter()
+            .all(|seed| (0..=MAX_GENERATOR_SEED).contains(seed)));
+        assert!(chunk_seeds(12346, 16)
+            .iter()
+            .all(|seed| !seeds.contains(seed)));
+    }
+
+    #[test]
+    fn merges_chunk_outputs_into_one_run() {
+        let base =
+            std::env::temp_dir().join(format!("quark_sim_event_chunks_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let run_info = GenRunInfo {
+            weight_names: vec!["Weight".to_owned()],
+            ..GenRunInfo::default()
+        };
+        let chunks: Vec<GenerationChunk> = [(3, 1000, 2.0), (2, 500, 4.0)]
+            .into_iter()
+            .zip(chunk_seeds(7, 2))
+            .enumerate()
+            .map(|(index, ((events, attempted, cross_section), seed))| {
+                let directory = chunk_directory(&base, index);
+                fs::create_dir_all(&directory).unwrap();
+                let mut writer =
+                    Hepmc3Writer::create(&directory.join(EVENTS_FILE_NAME), run_info.clone())
+                        .unwrap();
+                let mut csv = String::from("event_number,event_weight,Q2\n");
+                for number in 0..events {
+                    writer
+                        .write_event(&GenEvent {
+                            event_number: number,
+                            weights: vec![1.0],
+                            ..GenEvent::default()
+                        })
+                        .unwrap();
+                    csv.push_str(&format!("{number},1,{}\n", 10 * (index + 1)));
+                }
+                writer.close().unwrap();
+                fs::write(directory.join(OBSERVABLES_FILE_NAME), csv).unwrap();
+                fs::write(
+                    directory.join(GENERATOR_LOG_FILE_NAME),
+                    format!(" PYTHIA log of chunk {index}\n"),
+                )
+                .unwrap();
+                let summary = json!({
+                    "success": true,
+                    "cancelled": index == 1,
+                    "requested_events": events,
+                    "attempted_events": attempted,
+                    "accepted_events": events,
+                    "max_energy_mismatch_gev": 0.1 * (index + 1) as f64,
+                    "momentum_conservation_tolerance_gev": 0.001,
+                    "failure_reasons": [format!("reason_{index}")],
+                    "cross_section_pb": cross_section,
+                    "cross_section_error_pb": 0.1,
+                });
+                fs::write(directory.join(SUMMARY_FILE_NAME), summary.to_string()).unwrap();
+                let metadata = json!({"accepted_event_count": events, "random_seed": seed});
+                fs::write(directory.join(METADATA_FILE_NAME), metadata.to_string()).unwrap();
+                GenerationChunk {
+                    index,
+                    directory,
+                    events: events as usize,
+                    seed,
+                    attempts: index + 1,
+                }
+            })
+            .collect();
+
+        let summary = merge_chunks(&base, &chunks, 7).unwrap();
+        assert_eq!(summary["success"], true);
+        assert_eq!(summary["cancelled"], true);
+        assert_eq!(summary["accepted_events"], 5);
+        assert_eq!(summary["attempted_events"], 1500);
+        assert_eq!(summary["max_energy_mismatch_gev"], 0.2);
+        assert_eq!(summary["momentum_conservation_tolerance_gev"], 0.001);
+        assert_eq!(summary["failure_reasons"], json!(["reason_0", "reason_1"]));
+        let cross_section = summary["cross_section_pb"].as_f64().unwrap();
+        assert!((cross_section - 8.0 / 3.0).abs() < 1e-12);
+        let error = summary["cross_section_error_pb"].as_f64().unwrap();
+        let expected_error = 0.1 * (1000.0_f64.powi(2) + 500.0_f64.powi(2)).sqrt() / 1500.0;
+        assert!((error - expected_error).abs() < 1e-12);
+        assert_eq!(summary["jobs"], 2);
+        assert_eq!(summary["chunks"][1]["attempts"], 2);
+        assert_eq!(summary["chunks"][1]["cross_section_pb"], 4.0);
+        assert_eq!(read_json(&base.join(SUMMARY_FILE_NAME)).unwrap(), summary);
+
+        let metadata = read_json(&base.join(METADATA_FILE_NAME)).unwrap();
+        assert_eq!(metadata["accepted_event_count"], 5);
+        assert_eq!(metadata["random_seed"], 7);
+        assert_eq!(metadata["chunk_seeds"], json!(chunk_seeds(7, 2)));
+
+        let numbers: Vec<i64> = Hepmc3Reader::open(&base.join(EVENTS_FILE_NAME))
+            .unwrap()
+            .map(|event| event.unwrap().event_number)
+            .collect();
+        assert_eq!(numbers, [0, 1, 2, 3, 4]);
+        assert_eq!(
+            fs::read_to_string(base.join(OBSERVABLES_FILE_NAME)).unwrap(),
+            "event_number,event_weight,Q2\n0,1,10\n1,1,10\n2,1,10\n3,1,20\n4,1,20\n"
+        );
+        let log = fs::read_to_string(base.join(GENERATOR_LOG_FILE_NAME)).unwrap();
+        assert!(log.contains("==> chunk 1 (seed"));
+        assert!(log.ends_with(" PYTHIA log of chunk 1\n"));
+
+        fs::write(
+            chunks[1].directory.join(OBSERVABLES_FILE_NAME),
+            "event_number,Q2\n0,20\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            merge_chunks(&base, &chunks, 7),
+            Err(ChunkMergeError::Inconsistent { .. })
+        ));
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,287 @@
+//! Progress and control protocol between `generate-dis-events` and its
+//! generator backends.
+//!
+//! A backend writes one JSON object per line on standard error:
+//!
+//! ```text
+//! {"type":"progress","status":"running","requested_events":10000,"attempted_events":5321,
+//!  "accepted_events":4200,"failed_events":3,"vetoed_events":1118,"cross_section_pb":1234.5,
+//!  "cross_section_error_pb":12.1,"elapsed_seconds":41.7,"eta_seconds":57.6}
+//! ```
+//!
+//! `status` is `running` while events are generated and `completed` or
+//! `cancelled` in the last line, which follows the run files. Other lines on
+//! standard error are diagnostics. Control messages go the other way, one JSON
+//! object per line on the backend's standard input after the request:
+//! [`CANCEL_MESSAGE`] stops generation after the current event, and the
+//! backend then writes a valid run for the events accepted so far.
+//!
+//! `generate-dis-events --progress json` prints the same lines on standard
+//! output, combined over the chunks of a parallel run, and accepts
+//! [`CANCEL_MESSAGE`] on its own standard input.
+
+use serde::{Deserialize, Serialize};
+
+/// Control message that cancels a running generator.
+pub const CANCEL_MESSAGE: &str = r#"{"type":"cancel"}"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationStatus {
+    Running,
+    Completed,
+    Cancelled,
+}
+
+/// One progress report of a generator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorProgress {
+    pub status: GenerationStatus,
+    pub requested_events: usize,
+    pub attempted_events: usize,
+    pub accepted_events: usize,
+    pub failed_events: usize,
+    /// Events vetoed by the cuts or the momentum-conservation check.
+    pub vetoed_events: usize,
+    /// Running estimate of the cross section of the accepted events.
+    #[serde(default)]
+    pub cross_section_pb: Option<f64>,
+    #[serde(default)]
+    pub cross_section_error_pb: Option<f64>,
+    pub elapsed_seconds: f64,
+    /// Remaining time at the rate so far; `None` before the first accepted
+    /// event and once generation has stopped.
+    #[serde(default)]
+    pub eta_seconds: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ProtocolMessage {
+    Progress(GeneratorProgress),
+    Cancel,
+}
+
+/// The progress report on `line`, or `None` for any other line.
+#[must_use]
+pub fn parse_progress_line(line: &str) -> Option<GeneratorProgress> {
+    match serde_json::from_str(line.trim()) {
+        Ok(ProtocolMessage::Progress(progress)) => Some(progress),
+        _ => None,
+    }
+}
+
+/// Whether `line` is a [`CANCEL_MESSAGE`], whatever its spacing.
+#[must_use]
+pub fn is_cancel_message(line: &str) -> bool {
+    matches!(
+        serde_json::from_str(line.trim()),
+        Ok(ProtocolMessage::Cancel)
+    )
+}
+
+impl GeneratorProgress {
+    /// Combine the latest reports of the chunks of a parallel run of
+    /// `requested_events`; `None` marks a chunk that has not reported yet.
+    ///
+    /// Counts are added and the cross sections averaged with the chunks'
+    /// attempted events as weights, as in the merged `summary.json`. The run
+    /// has completed or been cancelled only once every chunk has.
+    #[must_use]
+    pub fn combine(requested_events: usize, chunks: &[Option<Self>]) -> Self {
+        let reports: Vec<&Self> = chunks.iter().flatten().collect();
+        let stopped = reports.len() == chunks.len()
+            && reports
+                .iter()
+                .all(|report| report.status != GenerationStatus::Running);
+        let status = if !stopped {
+            GenerationStatus::Running
+        } else if reports
+            .iter()
+            .any(|report| report.status == GenerationStatus::Cancelled)
+        {
+            GenerationStatus::Cancelled
+        } else {
+            GenerationStatus::Completed
+        };
+
+        let sum = |count: fn(&Self) -> usize| reports.iter().map(|report| count(report)).sum();
+        let (mut attempts, mut weighted, mut variance) = (0.0, 0.0, 0.0);
+        for report in &reports {
+            if let Some(cross_section) = report.cross_section_pb {
+                let report_attempts = report.attempted_events as f64;
+                let error = report.cross_section_error_pb.unwrap_or(0.0);
+                attempts += report_attempts;
+                weighted += report_attempts * cross_section;
+                variance += (report_attempts * error).powi(2);
+            }
+        }
+        let mut combined = Self {
+            status,
+            requested_events,
+            attempted_events: sum(|report| report.attempted_events),
+            accepted_events: sum(|report| report.accepted_events),
+            failed_events: sum(|report| report.failed_events),
+            vetoed_events: sum(|report| report.vetoed_events),
+            cross_section_pb: (attempts > 0.0).then(|| weighted / attempts),
+            cross_section_error_pb: (attempts > 0.0).then(|| variance.sqrt() / attempts),
+            elapsed_seconds: reports
+                .iter()
+                .map(|report| report.elapsed_seconds)
+                .fold(0.0, f64::max),
+            eta_seconds: None,
+        };
+        combined.eta_seconds = combined.estimated_remaining_seconds();
+        combined
+    }
+
+    /// Fraction of the requested events accepted so far.
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        if self.requested_events == 0 {
+            return 1.0;
+        }
+        (self.accepted_events as f64 / self.requested_events as f64).min(1.0)
+    }
+
+    fn estimated_remaining_seconds(&self) -> Option<f64> {
+        (self.status == GenerationStatus::Running && self.accepted_events > 0).then(|| {
+            let remaining = self.requested_events.saturating_sub(self.accepted_events);
+            self.elapsed_seconds * remaining as f64 / self.accepted_events as f64
+        })
+    }
+
+    /// The report as one protocol line, without a line break.
+    #[must_use]
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(&ProtocolMessage::Progress(self.clone()))
+            .unwrap_or_else(|_| String::new())
+    }
+
+    /// A one-line progress bar `width` characters wide followed by the
+    /// counts, the cross-section estimate and the remaining time.
+    #[must_use]
+    pub fn render_bar(&self, width: usize) -> String {
+        let filled = (self.fraction() * width as f64).round() as usize;
+        let mut line = format!(
+            "[{}{}] {:3.0}% {}/{} accepted, {} failed, {} vetoed",
+            "#".repeat(filled),
+            "-".repeat(width - filled.min(width)),
+            100.0 * self.fraction(),
+            self.accepted_events,
+            self.requested_events,
+            self.failed_events,
+            self.vetoed_events
+        );
+        if let (Some(cross_section), Some(error)) =
+            (self.cross_section_pb, self.cross_section_error_pb)
+        {
+            line.push_str(&format!(", σ = {cross_section:.4e} ± {error:.1e} pb"));
+        }
+        match (self.status, self.eta_seconds) {
+            (GenerationStatus::Running, Some(eta)) => {
+                line.push_str(&format!(", ETA {}", format_duration(eta)));
+            }
+            (GenerationStatus::Running, None) => {}
+            (GenerationStatus::Completed, _) => line.push_str(", completed"),
+            (GenerationStatus::Cancelled, _) => line.push_str(", cancelled"),
+        }
+        line
+    }
+}
+
+/// `1h 02m`, `3m 05s` or `12s`.
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.0).round() as u64;
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, seconds) => format!("{seconds}s"),
+        (0, minutes, seconds) => format!("{minutes}m {seconds:02}s"),
+        (hours, minutes, _) => format!("{hours}h {minutes:02}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(status: GenerationStatus, accepted: usize, attempted: usize) -> GeneratorProgress {
+        GeneratorProgress {
+            status,
+            requested_events: 100,
+            attempted_events: attempted,
+            accepted_events: accepted,
+            failed_events: 1,
+            vetoed_events: attempted - accepted - 1,
+            cross_section_pb: Some(if attempted > 100 { 4.0 } else { 2.0 }),
+            cross_section_error_pb: Some(0.1),
+            elapsed_seconds: 10.0,
+            eta_seconds: None,
+        }
+    }
+
+    #[test]
+    fn protocol_lines_round_trip_and_other_lines_are_ignored() {
+        let progress = report(GenerationStatus::Running, 50, 80);
+        let line = progress.to_json_line();
+        assert!(line.starts_with(r#"{"type":"progress","status":"running""#));
+        assert_eq!(parse_progress_line(&line), Some(progress));
+        assert_eq!(
+            parse_progress_line(
+                r#"{"type": "progress", "status": "completed", "requested_events": 2,
+                    "attempted_events": 3, "accepted_events": 2, "failed_events": 0,
+                    "vetoed_events": 1, "elapsed_seconds": 0.5, "eta_seconds": null}"#
+                    .replace('\n', " ")
+                    .as_str()
+            )
+            .map(|progress| (progress.status, progress.cross_section_pb)),
+            Some((GenerationStatus::Completed, None))
+        );
+        assert_eq!(parse_progress_line(" PYTHIA Warning in SpaceShower"), None);
+        assert_eq!(
+            parse_progress_line(r#"{"success":false,"error":{"code":"io_error"}}"#),
+            None
+        );
+        assert!(is_cancel_message(CANCEL_MESSAGE));
+        assert!(is_cancel_message(" { \"type\" : \"cancel\" }\n"));
+        assert!(!is_cancel_message("cancel"));
+    }
+
+    #[test]
+    fn chunk_reports_combine_into_the_run_progress() {
+        let running = report(GenerationStatus::Running, 20, 50);
+        let completed = report(GenerationStatus::Completed, 100, 150);
+        let combined = GeneratorProgress::combine(
+            300,
+            &[Some(running.clone()), Some(completed.clone()), None],
+        );
+        assert_eq!(combined.status, GenerationStatus::Running);
+        assert_eq!(
+            (
+                combined.accepted_events,
+                combined.attempted_events,
+                combined.failed_events
+            ),
+            (120, 200, 2)
+        );
+        assert!(
+            (combined.cross_section_pb.unwrap() - (50.0 * 2.0 + 150.0 * 4.0) / 200.0).abs() < 1e-12
+        );
+        assert!((combined.eta_seconds.unwrap() - 10.0 * 180.0 / 120.0).abs() < 1e-12);
+        assert!((combined.fraction() - 0.4).abs() < 1e-12);
+        let bar = combined.render_bar(10);
+        assert!(bar.starts_with("[####------]  40% 120/300 accepted, 2 failed, 78 vetoed, σ = "));
+        assert!(bar.ends_with(", ETA 15s"));
+
+        let cancelled = report(GenerationStatus::Cancelled, 20, 50);
+        let stopped = GeneratorProgress::combine(200, &[Some(cancelled), Some(completed.clone())]);
+        assert_eq!(stopped.status, GenerationStatus::Cancelled);
+        assert_eq!(stopped.eta_seconds, None);
+        assert!(stopped.render_bar(10).ends_with(", cancelled"));
+        let done = GeneratorProgress::combine(200, &[Some(completed.clone()), Some(completed)]);
+        assert_eq!(done.status, GenerationStatus::Completed);
+
+        assert_eq!(format_duration(12.4), "12s");
+        assert_eq!(format_duration(185.0), "3m 05s");
+        assert_eq!(format_duration(3720.0), "1h 02m");
+    }
+}
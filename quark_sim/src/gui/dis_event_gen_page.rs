@@ -2,11 +2,17 @@
 //!
 //! Launches PYTHIA event generation in a worker thread and displays progress,
 //! accepted/failed events, output location, logs, and summary statistics.
+//! Progress comes from the `generate-dis-events --progress json` lines, and
+//! cancelling asks the generator to keep the events accepted so far.
 
 use eframe::egui;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
+use quark_sim::generator_progress::{
+    parse_progress_line, GenerationStatus, GeneratorProgress, CANCEL_MESSAGE,
+};
+
 use super::state::{
     BackendProcess, DisConfig, EventGenSummary, GuiError, GuiErrorCategory, ProcessStatus,
 };
@@ -23,8 +29,8 @@ pub struct EventGenPageState {
     pub process: BackendProcess,
     pub worker: Option<WorkerHandle>,
     pub summary: Option<EventGenSummary>,
-    pub accepted_count: usize,
-    pub failed_count: usize,
+    /// Latest progress report of the generator.
+    pub progress: Option<GeneratorProgress>,
     pub view_mode: EventGenView,
 }
 
@@ -34,8 +40,7 @@ impl Default for EventGenPageState {
             process: BackendProcess::default(),
             worker: None,
             summary: None,
-            accepted_count: 0,
-            failed_count: 0,
+            progress: None,
             view_mode: EventGenView::Visualized,
         }
     }
@@ -53,34 +58,38 @@ pub fn render_event_gen_page(
     ui.separator();
 
     // Poll worker
-    if let Some(ref handle) = state.worker {
-        for msg in handle.drain() {
-            match msg {
-                WorkerMessage::StdoutLine(line) => {
-                    // Try to parse progress info from stdout
-                    parse_event_progress(&line, state);
-                    state.process.stdout_lines.push(line);
-                }
-                WorkerMessage::StderrLine(line) => {
-                    state.process.stderr_lines.push(line);
-                }
-                WorkerMessage::Progress(text) => {
-                    state.process.progress_text = text;
-                }
-                WorkerMessage::Completed(code) => {
-                    state.process.exit_code = Some(code);
-                    state.process.status = ProcessStatus::Completed;
-                    parse_event_summary(state, config);
-                }
-                WorkerMessage::Failed(msg) => {
+    let messages = state.worker.as_ref().map(WorkerHandle::drain);
+    for msg in messages.into_iter().flatten() {
+        match msg {
+            WorkerMessage::StdoutLine(line) => record_stdout_line(state, line),
+            WorkerMessage::StderrLine(line) => {
+                state.process.stderr_lines.push(line);
+            }
+            WorkerMessage::Progress(text) => {
+                state.process.progress_text = text;
+            }
+            WorkerMessage::Completed(code) => {
+                state.worker = None;
+                state.process.exit_code = Some(code);
+                state.process.status = ProcessStatus::Completed;
+                parse_event_summary(state, config);
+            }
+            WorkerMessage::Failed(msg) => {
+                state.worker = None;
+                if state.process.status == ProcessStatus::Cancelled {
+                    // A cancelled run exits with an error after writing the partial run.
+                    if generator_status(state) == Some(GenerationStatus::Cancelled) {
+                        parse_event_summary(state, config);
+                    }
+                } else {
                     state.process.status = ProcessStatus::Failed;
                     errors.push(GuiError::new(GuiErrorCategory::ProcessFailed, msg));
                 }
             }
         }
-        if state.process.status == ProcessStatus::Running {
-            ctx.request_repaint();
-        }
+    }
+    if state.worker.is_some() {
+        ctx.request_repaint();
     }
 
     // Configuration summary
@@ -132,16 +141,13 @@ pub fn render_event_gen_page(
             ui.separator();
             ui.spinner();
             ui.label(&state.process.progress_text);
-            let progress = if config.event_count > 0 {
-                state.accepted_count as f32 / config.event_count as f32
-            } else {
-                0.0
-            };
-            ui.add(egui::ProgressBar::new(progress.min(1.0)).show_percentage());
-            ui.label(format!(
-                "Accepted: {} | Failed: {}",
-                state.accepted_count, state.failed_count
-            ));
+            render_generator_progress(state.progress.as_ref(), ui);
+        }
+        ProcessStatus::Cancelled if state.worker.is_some() => {
+            ui.separator();
+            ui.spinner();
+            ui.label("Cancelling: the generator is writing the events accepted so far...");
+            render_generator_progress(state.progress.as_ref(), ui);
         }
         ProcessStatus::Completed => {
             ui.separator();
@@ -157,6 +163,9 @@ pub fn render_event_gen_page(
         ProcessStatus::Cancelled => {
             ui.separator();
             ui.colored_label(egui::Color32::YELLOW, "⚠ Generation cancelled");
+            if state.summary.is_some() {
+                ui.label("The events accepted before cancelling were kept as a complete run.");
+            }
         }
         ProcessStatus::Idle => {}
     }
@@ -256,44 +265,70 @@ fn start_event_generation(
     state.process.reset();
     state.process.status = ProcessStatus::Running;
     state.summary = None;
-    state.accepted_count = 0;
-    state.failed_count = 0;
+    state.progress = None;
 
-    let args = super::state::build_event_generation_command(config);
+    let mut args = super::state::build_event_generation_command(config);
+    args.extend(["--progress".to_string(), "json".to_string()]);
     let cancel_flag = Arc::clone(&state.process.cancel_flag);
 
     let exe = std::env::current_exe()
         .map(|p| p.to_string_lossy().to_string())
         .unwrap_or_else(|_| "quark_sim".to_string());
 
-    state.worker = Some(worker::spawn_subprocess(&exe, &args, cancel_flag));
+    state.worker = Some(worker::spawn_subprocess_with_cancel_message(
+        &exe,
+        &args,
+        CANCEL_MESSAGE,
+        cancel_flag,
+    ));
 }
 
-/// Try to parse event counts from a stdout line.
-fn parse_event_progress(line: &str, state: &mut EventGenPageState) {
-    // Look for patterns like "Events generated: 100" or "Accepted: 100 Failed: 5"
-    if let Some(rest) = line.strip_prefix("Events generated:") {
-        if let Ok(n) = rest.trim().parse::<usize>() {
-            state.accepted_count = n;
-        }
+/// Keep a progress report as the latest progress and any other stdout line
+/// in the log.
+pub(super) fn record_stdout_line(state: &mut EventGenPageState, line: String) {
+    match parse_progress_line(&line) {
+        Some(progress) => state.progress = Some(progress),
+        None => state.process.stdout_lines.push(line),
     }
-    if line.contains("accepted") || line.contains("Accepted") {
-        // Try to extract number after "accepted" or "Accepted"
-        for word in line.split_whitespace() {
-            if let Ok(n) = word.parse::<usize>() {
-                state.accepted_count = n;
-                break;
-            }
-        }
+}
+
+fn generator_status(state: &EventGenPageState) -> Option<GenerationStatus> {
+    state.progress.as_ref().map(|progress| progress.status)
+}
+
+/// Progress bar, event counts, cross-section estimate and remaining time.
+fn render_generator_progress(progress: Option<&GeneratorProgress>, ui: &mut egui::Ui) {
+    let Some(progress) = progress else {
+        ui.label("Waiting for the first progress report...");
+        return;
+    };
+    ui.add(egui::ProgressBar::new(progress.fraction() as f32).show_percentage());
+    ui.label(format!(
+        "Accepted: {} / {} | Failed: {} | Vetoed: {}",
+        progress.accepted_events,
+        progress.requested_events,
+        progress.failed_events,
+        progress.vetoed_events
+    ));
+    if let (Some(cross_section), Some(error)) =
+        (progress.cross_section_pb, progress.cross_section_error_pb)
+    {
+        ui.label(format!(
+            "Cross section: {cross_section:.4e} ± {error:.1e} pb"
+        ));
+    }
+    if let Some(eta) = progress.eta_seconds {
+        ui.label(format!("Remaining: about {eta:.0} s"));
     }
 }
 
 /// Create a summary after completion.
 fn parse_event_summary(state: &mut EventGenPageState, config: &DisConfig) {
+    let progress = state.progress.as_ref();
     state.summary = Some(EventGenSummary {
         total_events: config.event_count,
-        accepted_events: state.accepted_count,
-        failed_events: state.failed_count,
+        accepted_events: progress.map_or(0, |progress| progress.accepted_events),
+        failed_events: progress.map_or(0, |progress| progress.failed_events),
         output_path: config.output_directory.clone(),
         hepmc3_file: Some(format!("{}/events.hepmc3", config.output_directory)),
         config_file: Some(format!("{}/config.json", config.output_directory)),
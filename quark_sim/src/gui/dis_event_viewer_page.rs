@@ -1,17 +1,22 @@
 //! HepMC3 event viewer page.
 //!
 //! Reads HepMC3 event files and displays event number, particles, PDG IDs,
-//! status, four-momenta, vertices, parent/child relationships, and supports
-//! final-state filtering.
+//! status, four-momenta, vertices, parent/child relationships and the Breit
+//! current-hemisphere event shapes, and supports final-state filtering.
 
 use eframe::egui;
-use std::fs;
+use std::io::BufRead;
 use std::path::Path;
 
 use egui_plot::{Bar, BarChart, Legend, Plot};
+use quark_sim::analysis::AnalysisEvent;
+use quark_sim::hepmc3::{self, EventIndex, GenEvent, Hepmc3Error, Hepmc3Reader};
 
 use super::state::{GuiError, GuiErrorCategory, HepMC3Event, HepMC3Particle, HepMC3Vertex};
 
+/// Maximum number of events held in memory by the viewer at once.
+pub const EVENT_PAGE_SIZE: usize = 200;
+
 #[derive(PartialEq, Clone, Copy)]
 pub enum EventView {
     Visualized,
@@ -22,6 +27,8 @@ pub enum EventView {
 /// State for the event viewer page.
 pub struct EventViewerPageState {
     pub file_path: String,
+    /// Position in the file of the first loaded event.
+    pub first_event: usize,
     pub events: Vec<HepMC3Event>,
     pub selected_event: usize,
     pub show_final_state_only: bool,
@@ -34,6 +41,7 @@ impl Default for EventViewerPageState {
     fn default() -> Self {
         Self {
             file_path: "outputs/dis_run/events.hepmc3".to_string(),
+            first_event: 0,
             events: Vec::new(),
             selected_event: 0,
             show_final_state_only: false,
@@ -57,9 +65,22 @@ pub fn render_event_viewer_page(
     ui.horizontal(|ui| {
         ui.label("HepMC3 file:");
         ui.text_edit_singleline(&mut state.file_path);
+        ui.label("From event:");
+        ui.add(egui::DragValue::new(&mut state.first_event));
         if ui.button("📂 Load").clicked() {
             load_hepmc3_file(state, errors);
         }
+        if state.loaded && ui.button("Next page ▶").clicked() {
+            state.first_event += EVENT_PAGE_SIZE;
+            load_hepmc3_file(state, errors);
+        }
+        if ui
+            .button("🗂 Build index")
+            .on_hover_text("Record event offsets so later loads seek directly to an event")
+            .clicked()
+        {
+            build_event_index(&state.file_path, errors);
+        }
     });
 
     if !state.loaded {
@@ -73,7 +94,11 @@ pub fn render_event_viewer_page(
     }
 
     ui.separator();
-    ui.label(format!("Total events loaded: {}", state.events.len()));
+    ui.label(format!(
+        "Events {}–{} loaded (at most {EVENT_PAGE_SIZE} are held at once)",
+        state.first_event + 1,
+        state.first_event + state.events.len()
+    ));
 
     // Event selector
     ui.horizontal(|ui| {
@@ -110,6 +135,8 @@ pub fn render_event_viewer_page(
         event.event_number, event.weight
     ));
 
+    render_event_shapes(event, ui);
+
     // Particle table
     let pdg_filter_value: Option<i32> = if state.pdg_filter.trim().is_empty() {
         None
@@ -237,6 +264,39 @@ pub fn render_event_viewer_page(
     }
 }
 
+/// Event shapes of the Breit current hemisphere.
+fn render_event_shapes(event: &HepMC3Event, ui: &mut egui::Ui) {
+    ui.collapsing("📐 Event shapes (Breit current hemisphere)", |ui| {
+        let Some(shapes) = &event.event_shapes else {
+            ui.label(
+                "Not defined: the event needs beams, a scattered electron and more than Q/10 of energy in the current hemisphere.",
+            );
+            return;
+        };
+        egui::Grid::new("event_shape_grid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                for (name, value) in [
+                    ("Q [GeV]", shapes.q),
+                    ("E current [GeV]", shapes.current_hemisphere_energy),
+                    ("τ_Q", shapes.tau_q),
+                    ("τ_T", shapes.tau_t),
+                    ("B", shapes.broadening),
+                    ("ρ", shapes.jet_mass),
+                    ("C", shapes.c_parameter),
+                ] {
+                    ui.label(name);
+                    ui.label(format!("{value:.4}"));
+                    ui.end_row();
+                }
+                ui.label("Particles");
+                ui.label(format!("{}", shapes.multiplicity));
+                ui.end_row();
+            });
+    });
+}
+
 /// A simplified graphical event display showing particle flow.
 fn render_simple_event_display(
     event: &HepMC3Event,
@@ -289,7 +349,7 @@ fn render_simple_event_display(
     });
 }
 
-/// Load and parse a HepMC3 file.
+/// Load one page of events from a plain or gzip-compressed HepMC3 file.
 fn load_hepmc3_file(state: &mut EventViewerPageState, errors: &mut Vec<GuiError>) {
     let path = Path::new(&state.file_path);
     if !path.exists() {
@@ -301,122 +361,105 @@ fn load_hepmc3_file(state: &mut EventViewerPageState, errors: &mut Vec<GuiError>
         return;
     }
 
-    let content = match fs::read_to_string(path) {
-        Ok(c) => c,
+    match load_hepmc3_events(path, state.first_event, EVENT_PAGE_SIZE) {
+        Ok(events) => {
+            state.events = events;
+            state.selected_event = 0;
+            state.loaded = true;
+        }
         Err(e) => {
             errors.push(GuiError::new(
                 GuiErrorCategory::ParseError,
-                format!("Failed to read file: {e}"),
+                format!("Failed to read {}: {e}", state.file_path),
             ));
             state.loaded = false;
-            return;
         }
-    };
+    }
+}
 
-    state.events = parse_hepmc3(&content);
-    state.selected_event = 0;
-    state.loaded = true;
+/// Write `<file>.index.json` for an uncompressed or compressed event file.
+fn build_event_index(file_path: &str, errors: &mut Vec<GuiError>) {
+    let path = Path::new(file_path);
+    let result =
+        EventIndex::build(path).and_then(|index| index.save(&hepmc3::index_path(path)));
+    if let Err(e) = result {
+        errors.push(GuiError::new(
+            GuiErrorCategory::ParseError,
+            format!("Failed to index {file_path}: {e}"),
+        ));
+    }
 }
 
-/// Parse HepMC3 ASCII format into structured events.
+/// Stream at most `limit` events starting at event `first` of `path`.
 ///
-/// The HepMC3 ASCII format uses lines starting with:
-/// - `E` — event header
-/// - `V` — vertex
-/// - `P` — particle
-/// - `W` — weight
-pub fn parse_hepmc3(content: &str) -> Vec<HepMC3Event> {
-    let mut events = Vec::new();
-    let mut current_event: Option<HepMC3Event> = None;
-
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
+/// A current `<file>.index.json` next to the file is used to seek directly
+/// to `first`; otherwise the preceding events are skipped unparsed.
+pub fn load_hepmc3_events(
+    path: &Path,
+    first: usize,
+    limit: usize,
+) -> Result<Vec<HepMC3Event>, Hepmc3Error> {
+    let index = EventIndex::load(&hepmc3::index_path(path))
+        .ok()
+        .filter(|index| index.check_source(path).is_ok());
+    read_hepmc3_events(
+        Hepmc3Reader::open_at_event(path, first, index.as_ref())?,
+        limit,
+    )
+}
 
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.is_empty() {
-            continue;
-        }
+/// Read at most `limit` events from `reader` into viewer events.
+///
+/// Parsing is delegated to [`quark_sim::hepmc3`]; this only flattens the
+/// library's event graph into the table-friendly viewer structures.
+pub fn read_hepmc3_events<R: BufRead>(
+    reader: Hepmc3Reader<R>,
+    limit: usize,
+) -> Result<Vec<HepMC3Event>, Hepmc3Error> {
+    reader
+        .take(limit)
+        .map(|event| event.map(|event| viewer_event(&event)))
+        .collect()
+}
 
-        match parts[0] {
-            "E" => {
-                // Save previous event
-                if let Some(evt) = current_event.take() {
-                    events.push(evt);
+fn viewer_event(event: &GenEvent) -> HepMC3Event {
+    HepMC3Event {
+        event_number: event.event_number,
+        particles: event
+            .particles
+            .iter()
+            .map(|particle| HepMC3Particle {
+                index: particle.id as usize,
+                pdg_id: particle.pdg_id,
+                status: particle.status,
+                px: particle.momentum.px,
+                py: particle.momentum.py,
+                pz: particle.momentum.pz,
+                energy: particle.momentum.e,
+                mass: particle.mass,
+                production_vertex: particle.production_vertex,
+                end_vertex: particle.end_vertex,
+            })
+            .collect(),
+        vertices: event
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let position = vertex.position.unwrap_or_default();
+                HepMC3Vertex {
+                    id: vertex.id,
+                    x: position.x,
+                    y: position.y,
+                    z: position.z,
+                    t: position.t,
+                    incoming: vertex.incoming.iter().map(|&id| id as usize).collect(),
+                    outgoing: vertex.outgoing.iter().map(|&id| id as usize).collect(),
                 }
-                // Parse event header: E event_number num_vertices ...
-                let event_number = parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-                current_event = Some(HepMC3Event {
-                    event_number,
-                    particles: Vec::new(),
-                    vertices: Vec::new(),
-                    weight: 1.0,
-                });
-            }
-            "V" => {
-                if let Some(ref mut evt) = current_event {
-                    // V id status [x y z t]
-                    let id = parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-                    let status_or_x = parts.get(2).and_then(|s| s.parse::<f64>().ok()).unwrap_or(0.0);
-                    // Simplified: just store vertex ID
-                    evt.vertices.push(HepMC3Vertex {
-                        id,
-                        x: parts.get(3).and_then(|s| s.parse().ok()).unwrap_or(0.0),
-                        y: parts.get(4).and_then(|s| s.parse().ok()).unwrap_or(0.0),
-                        z: parts.get(5).and_then(|s| s.parse().ok()).unwrap_or(status_or_x),
-                        t: parts.get(6).and_then(|s| s.parse().ok()).unwrap_or(0.0),
-                        incoming: Vec::new(),
-                        outgoing: Vec::new(),
-                    });
-                }
-            }
-            "P" => {
-                if let Some(ref mut evt) = current_event {
-                    // P index pdg_id px py pz energy mass status
-                    let idx = evt.particles.len();
-                    let pdg_id = parts.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
-                    let px = parts.get(3).and_then(|s| s.parse().ok()).unwrap_or(0.0);
-                    let py = parts.get(4).and_then(|s| s.parse().ok()).unwrap_or(0.0);
-                    let pz = parts.get(5).and_then(|s| s.parse().ok()).unwrap_or(0.0);
-                    let energy = parts.get(6).and_then(|s| s.parse().ok()).unwrap_or(0.0);
-                    let mass = parts.get(7).and_then(|s| s.parse().ok()).unwrap_or(0.0);
-                    let status = parts.get(8).and_then(|s| s.parse().ok()).unwrap_or(0);
-
-                    evt.particles.push(HepMC3Particle {
-                        index: idx,
-                        pdg_id,
-                        status,
-                        px,
-                        py,
-                        pz,
-                        energy,
-                        mass,
-                        production_vertex: None,
-                        end_vertex: None,
-                    });
-                }
-            }
-            "W" => {
-                if let Some(ref mut evt) = current_event {
-                    if let Some(w) = parts.get(1).and_then(|s| s.parse().ok()) {
-                        evt.weight = w;
-                    }
-                }
-            }
-            _ => {
-                // HepMC3 header lines, attributes, etc. — skip.
-            }
-        }
-    }
-
-    // Push the last event
-    if let Some(evt) = current_event {
-        events.push(evt);
+            })
+            .collect(),
+        weight: event.weight(),
+        event_shapes: AnalysisEvent::new(event).event_shapes().ok().copied(),
     }
-
-    events
 }
 
 /// Filter events to final-state particles only.
@@ -76,10 +76,14 @@ pub fn render_inclusive_page(
                 }
                 WorkerMessage::Failed(msg) => {
                     state.process.status = ProcessStatus::Failed;
-                    errors.push(GuiError::new(
-                        GuiErrorCategory::ProcessFailed,
-                        msg,
-                    ));
+                    let error = state
+                        .process
+                        .stderr_lines
+                        .iter()
+                        .rev()
+                        .find_map(|line| GuiError::from_cli_error(line))
+                        .unwrap_or_else(|| GuiError::new(GuiErrorCategory::ProcessFailed, msg));
+                    errors.push(error);
                 }
             }
         }
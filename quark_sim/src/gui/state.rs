@@ -80,7 +80,11 @@ impl Default for UiState {
 // ---------------------------------------------------------------------------
 
 /// All user-configurable physics parameters for DIS analysis.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// This is also the `dis` section of a command-line run card, where omitted
+/// fields take the defaults below.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct DisConfig {
     pub electron_energy_gev: f64,
     pub proton_energy_gev: f64,
@@ -414,6 +418,9 @@ pub struct HepMC3Event {
     pub particles: Vec<HepMC3Particle>,
     pub vertices: Vec<HepMC3Vertex>,
     pub weight: f64,
+    /// Breit current-hemisphere event shapes, when the event defines them.
+    #[serde(default)]
+    pub event_shapes: Option<quark_sim::physics::EventShapes>,
 }
 
 // ---------------------------------------------------------------------------
@@ -485,12 +492,16 @@ pub fn is_schema_compatible(version: Option<i32>) -> bool {
 // Error state
 // ---------------------------------------------------------------------------
 
-/// Categorized error types for actionable messages.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Categorized error types for actionable messages. The CLI reports the same
+/// categories, in snake case, in its JSON error objects.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum GuiErrorCategory {
     LhapdfSetNotInstalled,
     ApfelBackendMissing,
     PythiaBackendMissing,
+    SurrogateModelMissing,
+    InvalidInput,
     InvalidKinematics,
     UnsupportedOrder,
     InvalidOutputSchema,
@@ -524,6 +535,13 @@ impl GuiError {
                 "Ensure PYTHIA 8 is installed and scripts/pythia_env.sh sets the correct paths"
                     .to_string()
             }
+            GuiErrorCategory::SurrogateModelMissing => {
+                "Train a surrogate with: quark_sim train-surrogate --output models/surrogate_v1"
+                    .to_string()
+            }
+            GuiErrorCategory::InvalidInput => {
+                "Check the value of the reported option".to_string()
+            }
             GuiErrorCategory::InvalidKinematics => {
                 "Check the kinematic ranges (x, Q², y, W²) in the configuration tab".to_string()
             }
@@ -553,6 +571,20 @@ impl GuiError {
             suggestion,
         }
     }
+
+    /// Read a JSON error object printed by a failed `quark_sim --format json`
+    /// subprocess.
+    #[must_use]
+    pub fn from_cli_error(line: &str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(line.trim()).ok()?;
+        let error = value.get("error")?;
+        let category = serde_json::from_value(error.get("category")?.clone()).ok()?;
+        let message = error.get("message")?.as_str()?;
+        Some(match error.get("field").and_then(|field| field.as_str()) {
+            Some(field) => Self::new(category, format!("--{field}: {message}")),
+            None => Self::new(category, message),
+        })
+    }
 }
 
 /// Application-wide error state.
@@ -621,6 +653,8 @@ pub fn build_structure_function_command(
         pdf_set.to_string(),
         "--pdf-member".to_string(),
         pdf_member.to_string(),
+        "--format".to_string(),
+        "json".to_string(),
     ];
     if (mu_f_over_q - 1.0).abs() > 1e-12 {
         args.push("--mu-f-over-q".to_string());
@@ -4,10 +4,12 @@
 //! command construction, schema loading, run-history parsing, process-state
 //! transitions, cancellation, error rendering, and event filtering.
 
-use super::dis_event_viewer_page::{filter_by_pdg, filter_final_state, parse_hepmc3};
+use super::dis_event_gen_page::{record_stdout_line, EventGenPageState};
+use super::dis_event_viewer_page::{filter_by_pdg, filter_final_state, read_hepmc3_events};
 use super::dis_run_history_page::scan_runs;
 use super::state::*;
 
+use quark_sim::hepmc3::{Hepmc3Error, Hepmc3Reader};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -234,6 +236,25 @@ fn event_generation_command_omits_seed_when_empty() {
     assert!(!args.contains(&"--seed".to_string()));
 }
 
+#[test]
+fn event_generation_page_keeps_progress_reports_out_of_the_log() {
+    let mut state = EventGenPageState::default();
+    record_stdout_line(&mut state, "Output directory: runs/dis_run_1".to_string());
+    record_stdout_line(
+        &mut state,
+        r#"{"type":"progress","status":"running","requested_events":100,"attempted_events":60,"accepted_events":40,"failed_events":2,"vetoed_events":18,"cross_section_pb":12.5,"cross_section_error_pb":0.5,"elapsed_seconds":4.0,"eta_seconds":6.0}"#
+            .to_string(),
+    );
+    assert_eq!(
+        state.process.stdout_lines,
+        ["Output directory: runs/dis_run_1"]
+    );
+    let progress = state.progress.expect("the progress line should be parsed");
+    assert_eq!((progress.accepted_events, progress.vetoed_events), (40, 18));
+    assert_eq!(progress.eta_seconds, Some(6.0));
+    assert!((progress.fraction() - 0.4).abs() < 1e-12);
+}
+
 #[test]
 fn validate_hera_command_has_all_required_flags() {
     let args = build_validate_hera_command(
@@ -374,6 +395,8 @@ fn error_categories_have_actionable_suggestions() {
         GuiErrorCategory::LhapdfSetNotInstalled,
         GuiErrorCategory::ApfelBackendMissing,
         GuiErrorCategory::PythiaBackendMissing,
+        GuiErrorCategory::SurrogateModelMissing,
+        GuiErrorCategory::InvalidInput,
         GuiErrorCategory::InvalidKinematics,
         GuiErrorCategory::UnsupportedOrder,
         GuiErrorCategory::InvalidOutputSchema,
@@ -394,6 +417,17 @@ fn error_categories_have_actionable_suggestions() {
     }
 }
 
+#[test]
+fn cli_error_objects_become_categorized_errors() {
+    let error = GuiError::from_cli_error(
+        r#"{"error":{"code":"pdf_set_unavailable","category":"lhapdf_set_not_installed","field":"pdf-set","message":"set CT18LO is not installed"}}"#,
+    )
+    .unwrap();
+    assert_eq!(error.category, GuiErrorCategory::LhapdfSetNotInstalled);
+    assert_eq!(error.message, "--pdf-set: set CT18LO is not installed");
+    assert!(GuiError::from_cli_error("Error: set CT18LO is not installed").is_none());
+}
+
 #[test]
 fn error_state_keeps_latest_error() {
     let mut state = ErrorState::default();
@@ -432,40 +466,97 @@ fn error_state_clear_removes_all() {
 // Event filtering
 // ---------------------------------------------------------------------------
 
+fn parse_hepmc3(input: &str) -> Result<Vec<HepMC3Event>, Hepmc3Error> {
+    read_hepmc3_events(Hepmc3Reader::new(input.as_bytes()), usize::MAX)
+}
+
 #[test]
 fn parse_hepmc3_empty_input_returns_no_events() {
-    let events = parse_hepmc3("");
+    let events = parse_hepmc3("").unwrap();
     assert!(events.is_empty());
 }
 
 #[test]
 fn parse_hepmc3_single_event() {
     let input = "\
-E 1 1
-V -1 0 0 0 0 0
-P 1 11 0.0 0.0 27.5 27.5 0.000511 4
-P 2 2212 0.0 0.0 -920.0 920.0 0.938 4
-P 3 11 5.0 3.0 20.0 21.0 0.000511 1
+E 1 1 3
 W 1.0
+P 1 0 11 0.0 0.0 27.5 27.5 0.000511 4
+P 2 0 2212 0.0 0.0 -920.0 920.0 0.938 4
+V -1 0 [1,2] @ 0 0 0 0
+P 3 -1 11 5.0 3.0 20.0 21.0 0.000511 1
 ";
-    let events = parse_hepmc3(input);
+    let events = parse_hepmc3(input).unwrap();
     assert_eq!(events.len(), 1);
     assert_eq!(events[0].event_number, 1);
     assert_eq!(events[0].particles.len(), 3);
     assert_eq!(events[0].vertices.len(), 1);
+    assert_eq!(events[0].vertices[0].incoming, vec![1, 2]);
+    assert_eq!(events[0].vertices[0].outgoing, vec![3]);
+    assert_eq!(events[0].particles[2].production_vertex, Some(-1));
+    assert_eq!(events[0].particles[0].end_vertex, Some(-1));
+    assert!(events[0].event_shapes.is_none());
+}
+
+#[test]
+fn viewer_events_carry_breit_current_hemisphere_event_shapes() {
+    use quark_sim::physics::{collider_beams, compute_dis_kinematics, scattered_electron};
+
+    let beams = collider_beams(27.5, 920.0).unwrap();
+    let electron = scattered_electron(20.0, 150.0).unwrap();
+    let kinematics = compute_dis_kinematics(beams.proton, beams.electron, electron).unwrap();
+    let x = kinematics.x;
+    let proton = beams.proton;
+    let quark = kinematics.q
+        + quark_sim::physics::FourVector::new(x * proton.e, 0.0, 0.0, x * proton.pz);
+    let line = |id: i32, pdg: i32, p: quark_sim::physics::FourVector, status: i32| {
+        format!(
+            "P {id} {} {pdg} {:.12} {:.12} {:.12} {:.12} 0 {status}\n",
+            if status == 4 { 0 } else { -1 },
+            p.px,
+            p.py,
+            p.pz,
+            p.e
+        )
+    };
+    let input = format!(
+        "E 1 1 4\nW 1.0\n{}{}V -1 0 [1,2]\n{}{}",
+        line(1, 11, beams.electron, 4),
+        line(2, 2212, beams.proton, 4),
+        line(3, 11, electron, 1),
+        line(4, 2, quark, 1),
+    );
+
+    let events = parse_hepmc3(&input).unwrap();
+    let shapes = events[0].event_shapes.expect("the struck quark is in the current hemisphere");
+    assert_eq!(shapes.multiplicity, 1);
+    assert!((shapes.q - kinematics.q2.sqrt()).abs() < 1e-6);
+    assert!(shapes.tau_t.abs() < 1e-9);
+    assert!(shapes.tau_q.abs() < 1e-3);
+    assert!(shapes.broadening < 1e-6);
+}
+
+#[test]
+fn parse_hepmc3_reports_malformed_numbers() {
+    let input = "\
+E 1 0 1
+P 1 0 11 0.0 0.0 oops 27.5 0.000511 4
+";
+    let error = parse_hepmc3(input).unwrap_err();
+    assert_eq!(error.line(), Some(2));
 }
 
 #[test]
 fn filter_final_state_returns_only_status_one() {
     let input = "\
-E 1 1
-P 1 11 0.0 0.0 27.5 27.5 0.000511 4
-P 2 2212 0.0 0.0 -920.0 920.0 0.938 4
-P 3 11 5.0 3.0 20.0 21.0 0.000511 1
-P 4 211 1.0 2.0 10.0 10.1 0.140 1
-P 5 22 0.1 0.2 0.5 0.55 0.0 1
+E 1 0 5
+P 1 0 11 0.0 0.0 27.5 27.5 0.000511 4
+P 2 0 2212 0.0 0.0 -920.0 920.0 0.938 4
+P 3 0 11 5.0 3.0 20.0 21.0 0.000511 1
+P 4 0 211 1.0 2.0 10.0 10.1 0.140 1
+P 5 0 22 0.1 0.2 0.5 0.55 0.0 1
 ";
-    let events = parse_hepmc3(input);
+    let events = parse_hepmc3(input).unwrap();
     let final_state = filter_final_state(&events[0]);
     assert_eq!(final_state.len(), 3);
     for p in &final_state {
@@ -476,12 +567,12 @@ P 5 22 0.1 0.2 0.5 0.55 0.0 1
 #[test]
 fn filter_by_pdg_returns_matching_particles() {
     let input = "\
-E 1 1
-P 1 11 0.0 0.0 27.5 27.5 0.000511 4
-P 2 11 5.0 3.0 20.0 21.0 0.000511 1
-P 3 22 0.1 0.2 0.5 0.55 0.0 1
+E 1 0 3
+P 1 0 11 0.0 0.0 27.5 27.5 0.000511 4
+P 2 0 11 5.0 3.0 20.0 21.0 0.000511 1
+P 3 0 22 0.1 0.2 0.5 0.55 0.0 1
 ";
-    let events = parse_hepmc3(input);
+    let events = parse_hepmc3(input).unwrap();
     let electrons = filter_by_pdg(&events[0], 11);
     assert_eq!(electrons.len(), 2);
     let photons = filter_by_pdg(&events[0], 22);
@@ -2,14 +2,20 @@
 //!
 //! This module provides a safe, non-blocking way to run external commands
 //! from the GUI without freezing the UI. It uses structured command arguments
-//! (no shell-string concatenation) and supports cancellation.
+//! (no shell-string concatenation) and supports cancellation, either by
+//! killing the process or by sending it a control message on stdin.
 
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
 use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long a process may take to stop after a cancel message before it is
+/// killed.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(30);
 
 // ---------------------------------------------------------------------------
 // Worker messages
@@ -99,7 +105,34 @@ pub fn spawn_subprocess(
     let flag = Arc::clone(&cancel_flag);
 
     let thread = thread::spawn(move || {
-        run_subprocess(&program, &args, &sender, &flag);
+        run_subprocess(&program, &args, None, &sender, &flag);
+    });
+
+    WorkerHandle {
+        receiver,
+        cancel_flag,
+        _thread: thread,
+    }
+}
+
+/// Like [`spawn_subprocess`], but cancellation writes `cancel_message` as one
+/// line to the process's stdin and lets it finish, so it can keep the work
+/// done so far. The process is killed if it has not exited after
+/// [`CANCEL_GRACE_PERIOD`].
+pub fn spawn_subprocess_with_cancel_message(
+    program: &str,
+    args: &[String],
+    cancel_message: &str,
+    cancel_flag: Arc<AtomicBool>,
+) -> WorkerHandle {
+    let (sender, receiver) = mpsc::channel();
+    let program = program.to_string();
+    let args: Vec<String> = args.to_vec();
+    let cancel_message = cancel_message.to_string();
+    let flag = Arc::clone(&cancel_flag);
+
+    let thread = thread::spawn(move || {
+        run_subprocess(&program, &args, Some(&cancel_message), &sender, &flag);
     });
 
     WorkerHandle {
@@ -113,14 +146,19 @@ pub fn spawn_subprocess(
 fn run_subprocess(
     program: &str,
     args: &[String],
+    cancel_message: Option<&str>,
     sender: &Sender<WorkerMessage>,
     cancel_flag: &Arc<AtomicBool>,
 ) {
-    let child_result = Command::new(program)
+    let mut command = Command::new(program);
+    command
         .args(args)
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn();
+        .stderr(Stdio::piped());
+    if cancel_message.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let child_result = command.spawn();
 
     let mut child: Child = match child_result {
         Ok(c) => c,
@@ -164,8 +202,19 @@ fn run_subprocess(
     });
 
     // Wait for the process, checking cancellation.
+    let mut control = child.stdin.take();
+    let mut cancel_sent: Option<Instant> = None;
     loop {
-        if cancel_flag.load(Ordering::Acquire) {
+        if let (Some(message), None) = (cancel_message, cancel_sent) {
+            if cancel_flag.load(Ordering::Acquire) {
+                if let Some(stdin) = control.as_mut() {
+                    let _ = writeln!(stdin, "{message}").and_then(|()| stdin.flush());
+                }
+                cancel_sent = Some(Instant::now());
+            }
+        }
+        let stop_waiting = cancel_sent.is_none_or(|sent| sent.elapsed() >= CANCEL_GRACE_PERIOD);
+        if stop_waiting && cancel_flag.load(Ordering::Acquire) {
             let _ = child.kill();
             let _ = child.wait();
             let _ = sender.send(WorkerMessage::Failed("Process cancelled by user".to_string()));
@@ -0,0 +1,353 @@
+//! HepMC3 event records and the `Asciiv3` text serialization.
+//!
+//! The data model mirrors the HepMC3 C++ library closely enough that files
+//! written by `HepMC3::WriterAscii` (and therefore by the PYTHIA backend) can
+//! be read without loss: particles and vertices form a graph through their
+//! production and end vertices, every event carries its units and a weight
+//! vector, and free-form attributes are kept as strings exactly as written.
+//! Files written here can in turn be read by HepMC3.
+//!
+//! Particle ids are positive and dense (`1..=n`); vertex ids are negative
+//! (`-1, -2, …`). Both follow the HepMC3 numbering so ids read from a file can
+//! be compared directly with attribute targets and vertex in-lists.
+
+mod reader;
+mod stream;
+mod writer;
+
+pub use reader::{parse_ascii, AsciiParser, Hepmc3Error};
+pub use stream::{index_path, EventIndex, Hepmc3Input, Hepmc3Reader, IndexEntry, INDEX_SUFFIX};
+pub use writer::{write_file, Hepmc3Output, Hepmc3WriteError, Hepmc3Writer, HEPMC_VERSION};
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::physics::FourVector;
+
+/// Momentum unit of an event record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MomentumUnit {
+    Mev,
+    #[default]
+    Gev,
+}
+
+impl MomentumUnit {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Mev => "MEV",
+            Self::Gev => "GEV",
+        }
+    }
+
+    /// Multiplicative factor converting this unit to GeV.
+    #[must_use]
+    pub const fn to_gev(self) -> f64 {
+        match self {
+            Self::Mev => 1e-3,
+            Self::Gev => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for MomentumUnit {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MomentumUnit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "MEV" => Ok(Self::Mev),
+            "GEV" => Ok(Self::Gev),
+            _ => Err(format!(
+                "unknown momentum unit '{value}'; expected MEV or GEV"
+            )),
+        }
+    }
+}
+
+/// Length unit of vertex positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthUnit {
+    #[default]
+    Mm,
+    Cm,
+}
+
+impl LengthUnit {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Mm => "MM",
+            Self::Cm => "CM",
+        }
+    }
+
+    /// Multiplicative factor converting this unit to millimetres.
+    #[must_use]
+    pub const fn to_mm(self) -> f64 {
+        match self {
+            Self::Mm => 1.0,
+            Self::Cm => 10.0,
+        }
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LengthUnit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "MM" => Ok(Self::Mm),
+            "CM" => Ok(Self::Cm),
+            _ => Err(format!("unknown length unit '{value}'; expected MM or CM")),
+        }
+    }
+}
+
+/// Units of one event, as given by its `U` record. HepMC3 defaults to GeV
+/// and millimetres when the record is absent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Units {
+    pub momentum: MomentumUnit,
+    pub length: LengthUnit,
+}
+
+/// Space-time position `(x, y, z, t)` of a vertex or of the event origin, in
+/// the event's length unit (`t` in length/c).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub t: f64,
+}
+
+impl Position {
+    #[must_use]
+    pub const fn new(x: f64, y: f64, z: f64, t: f64) -> Self {
+        Self { x, y, z, t }
+    }
+}
+
+/// Generator or tool that contributed to a run (`T` header record).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ToolInfo {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+}
+
+/// Run-level information shared by every event of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GenRunInfo {
+    /// Names of the entries of each event's weight vector, in order.
+    pub weight_names: Vec<String>,
+    pub tools: Vec<ToolInfo>,
+    /// Run attributes as `(name, value)` pairs in file order.
+    pub attributes: Vec<(String, String)>,
+}
+
+impl GenRunInfo {
+    /// Position of `name` in the weight vector.
+    #[must_use]
+    pub fn weight_index(&self, name: &str) -> Option<usize> {
+        self.weight_names
+            .iter()
+            .position(|candidate| candidate == name)
+    }
+
+    #[must_use]
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(candidate, _)| candidate == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A particle in the event graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenParticle {
+    pub id: i32,
+    pub pdg_id: i32,
+    pub momentum: FourVector,
+    /// Generated mass as stored in the record, which need not equal the
+    /// invariant mass of `momentum`.
+    pub mass: f64,
+    pub status: i32,
+    pub production_vertex: Option<i32>,
+    pub end_vertex: Option<i32>,
+}
+
+/// A vertex in the event graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenVertex {
+    pub id: i32,
+    pub status: i32,
+    /// `None` when the record carries no position, i.e. the vertex sits at
+    /// the event origin.
+    pub position: Option<Position>,
+    /// Ids of the particles ending at this vertex.
+    pub incoming: Vec<i32>,
+    /// Ids of the particles produced at this vertex.
+    pub outgoing: Vec<i32>,
+}
+
+/// A string attribute attached to the event (`target == 0`), a particle
+/// (`target > 0`) or a vertex (`target < 0`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    pub target: i32,
+    pub name: String,
+    pub value: String,
+}
+
+/// One event record.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GenEvent {
+    pub event_number: i64,
+    pub units: Units,
+    pub weights: Vec<f64>,
+    /// Event origin shift, when the record carries one.
+    pub position: Option<Position>,
+    /// Particles ordered by id, so `particles[i].id == i + 1`.
+    pub particles: Vec<GenParticle>,
+    /// Vertices ordered from `-1` downwards. Ids are normally dense, so
+    /// `vertices[i].id == -(i + 1)`, but hand-written records may skip some.
+    pub vertices: Vec<GenVertex>,
+    pub attributes: Vec<Attribute>,
+}
+
+impl GenEvent {
+    /// Nominal event weight: the first weight, or 1 for unweighted records.
+    #[must_use]
+    pub fn weight(&self) -> f64 {
+        self.weights.first().copied().unwrap_or(1.0)
+    }
+
+    /// Weight named `name` in the run's weight list.
+    #[must_use]
+    pub fn named_weight(&self, run_info: &GenRunInfo, name: &str) -> Option<f64> {
+        run_info
+            .weight_index(name)
+            .and_then(|index| self.weights.get(index).copied())
+    }
+
+    #[must_use]
+    pub fn particle(&self, id: i32) -> Option<&GenParticle> {
+        let index = usize::try_from(id).ok()?.checked_sub(1)?;
+        self.particles
+            .get(index)
+            .filter(|particle| particle.id == id)
+    }
+
+    #[must_use]
+    pub fn vertex(&self, id: i32) -> Option<&GenVertex> {
+        let index = usize::try_from(id.checked_neg()?).ok()?.checked_sub(1)?;
+        self.vertices
+            .get(index)
+            .filter(|vertex| vertex.id == id)
+            .or_else(|| self.vertices.iter().find(|vertex| vertex.id == id))
+    }
+
+    /// Value of attribute `name` on `target` (0 for the event itself).
+    #[must_use]
+    pub fn attribute(&self, target: i32, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.target == target && attribute.name == name)
+            .map(|attribute| attribute.value.as_str())
+    }
+
+    /// Particles entering the production vertex of `particle`.
+    #[must_use]
+    pub fn parents(&self, particle: &GenParticle) -> Vec<&GenParticle> {
+        self.linked_particles(particle.production_vertex, |vertex| &vertex.incoming)
+    }
+
+    /// Particles leaving the end vertex of `particle`.
+    #[must_use]
+    pub fn children(&self, particle: &GenParticle) -> Vec<&GenParticle> {
+        self.linked_particles(particle.end_vertex, |vertex| &vertex.outgoing)
+    }
+
+    /// Stable particles (status 1).
+    pub fn final_state(&self) -> impl Iterator<Item = &GenParticle> {
+        self.particles
+            .iter()
+            .filter(|particle| particle.status == 1)
+    }
+
+    fn linked_particles(
+        &self,
+        vertex: Option<i32>,
+        ids: impl Fn(&GenVertex) -> &Vec<i32>,
+    ) -> Vec<&GenParticle> {
+        vertex
+            .and_then(|id| self.vertex(id))
+            .map(|vertex| {
+                ids(vertex)
+                    .iter()
+                    .filter_map(|&id| self.particle(id))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A fully parsed `Asciiv3` file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Hepmc3File {
+    pub run_info: GenRunInfo,
+    pub events: Vec<GenEvent>,
+}
+
+/// Undo the `Asciiv3` string escaping used for attribute values and run-info
+/// records: `\|` encodes a newline and `\\` a backslash.
+#[must_use]
+pub fn unescape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            output.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('|') => output.push('\n'),
+            Some('\\') => output.push('\\'),
+            Some(other) => {
+                output.push('\\');
+                output.push(other);
+            }
+            None => output.push('\\'),
+        }
+    }
+    output
+}
+
+/// Inverse of [`unescape`].
+#[must_use]
+pub fn escape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\|"),
+            other => output.push(other),
+        }
+    }
+    output
+}
@@ -0,0 +1,924 @@
+//! Line-oriented parser for the HepMC3 `Asciiv3` format.
+//!
+//! Each record occupies one line and starts with a single-letter tag:
+//!
+//! - `W`, `T`, `A` before the first event carry run information: weight
+//!   names, tools and run attributes;
+//! - `E number n_vertices n_particles [@ x y z t]` opens an event;
+//! - `U momentum length` gives the event units;
+//! - `W w1 w2 …` gives the event weights;
+//! - `A target name value` attaches an attribute;
+//! - `V id status [in,list] [@ x y z t]` declares a vertex and the particles
+//!   entering it;
+//! - `P id parent pdg px py pz e m status` declares a particle. A negative
+//!   `parent` is its production vertex; a positive one is a single parent
+//!   particle whose (implicit, unwritten) end vertex produced it.
+//!
+//! Links are resolved when an event is complete, because vertex in-lists may
+//! refer to particles that appear later in the record.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{
+    unescape, Attribute, GenEvent, GenParticle, GenRunInfo, GenVertex, Hepmc3File, Position,
+    ToolInfo, Units,
+};
+use crate::physics::FourVector;
+
+const HEADER_PREFIX: &str = "HepMC::";
+const VERSION_HEADER: &str = "HepMC::Version";
+const LISTING_START: &str = "HepMC::Asciiv3-START_EVENT_LISTING";
+const LISTING_END: &str = "HepMC::Asciiv3-END_EVENT_LISTING";
+
+/// Failure while reading a HepMC3 record. Line numbers are 1-based.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Hepmc3Error {
+    UnsupportedFormat {
+        line: usize,
+        header: String,
+    },
+    UnknownRecord {
+        line: usize,
+        record: String,
+    },
+    RecordOutsideEvent {
+        line: usize,
+        record: char,
+    },
+    MissingField {
+        line: usize,
+        record: char,
+        field: &'static str,
+    },
+    InvalidNumber {
+        line: usize,
+        field: &'static str,
+        value: String,
+    },
+    InvalidUnit {
+        line: usize,
+        message: String,
+    },
+    MalformedRecord {
+        line: usize,
+        record: char,
+        reason: String,
+    },
+    UnexpectedParticleId {
+        line: usize,
+        expected: i32,
+        found: i32,
+    },
+    DuplicateVertex {
+        line: usize,
+        id: i32,
+    },
+    UnknownParticle {
+        line: usize,
+        id: i32,
+    },
+    UnknownVertex {
+        line: usize,
+        id: i32,
+    },
+    ConflictingEndVertex {
+        line: usize,
+        particle: i32,
+    },
+    WeightCountMismatch {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    CountMismatch {
+        line: usize,
+        record: &'static str,
+        declared: usize,
+        found: usize,
+    },
+    Io {
+        line: Option<usize>,
+        message: String,
+    },
+    InvalidIndex {
+        message: String,
+    },
+    IndexMismatch {
+        indexed_bytes: u64,
+        file_bytes: u64,
+    },
+    EventOutOfRange {
+        requested: usize,
+        available: usize,
+    },
+}
+
+impl Hepmc3Error {
+    /// Line of the record that caused the failure, when there is one.
+    #[must_use]
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Self::UnsupportedFormat { line, .. }
+            | Self::UnknownRecord { line, .. }
+            | Self::RecordOutsideEvent { line, .. }
+            | Self::MissingField { line, .. }
+            | Self::InvalidNumber { line, .. }
+            | Self::InvalidUnit { line, .. }
+            | Self::MalformedRecord { line, .. }
+            | Self::UnexpectedParticleId { line, .. }
+            | Self::DuplicateVertex { line, .. }
+            | Self::UnknownParticle { line, .. }
+            | Self::UnknownVertex { line, .. }
+            | Self::ConflictingEndVertex { line, .. }
+            | Self::WeightCountMismatch { line, .. }
+            | Self::CountMismatch { line, .. } => Some(*line),
+            Self::Io { line, .. } => *line,
+            Self::InvalidIndex { .. }
+            | Self::IndexMismatch { .. }
+            | Self::EventOutOfRange { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Hepmc3Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line() {
+            write!(formatter, "HepMC3 line {line}: ")?;
+        }
+        match self {
+            Self::UnsupportedFormat { header, .. } => write!(
+                formatter,
+                "unsupported event format '{header}'; only Asciiv3 is supported"
+            ),
+            Self::UnknownRecord { record, .. } => {
+                write!(formatter, "unknown record type '{record}'")
+            }
+            Self::RecordOutsideEvent { record, .. } => {
+                write!(formatter, "'{record}' record appears outside an event")
+            }
+            Self::MissingField { record, field, .. } => {
+                write!(formatter, "'{record}' record is missing field {field}")
+            }
+            Self::InvalidNumber { field, value, .. } => {
+                write!(formatter, "field {field} has invalid value '{value}'")
+            }
+            Self::InvalidUnit { message, .. } => formatter.write_str(message),
+            Self::MalformedRecord { record, reason, .. } => {
+                write!(formatter, "malformed '{record}' record: {reason}")
+            }
+            Self::UnexpectedParticleId {
+                expected, found, ..
+            } => write!(
+                formatter,
+                "particle id {found} is out of order; expected {expected}"
+            ),
+            Self::DuplicateVertex { id, .. } => write!(formatter, "vertex {id} is declared twice"),
+            Self::UnknownParticle { id, .. } => {
+                write!(formatter, "reference to undeclared particle {id}")
+            }
+            Self::UnknownVertex { id, .. } => {
+                write!(formatter, "reference to undeclared vertex {id}")
+            }
+            Self::ConflictingEndVertex { particle, .. } => {
+                write!(formatter, "particle {particle} enters more than one vertex")
+            }
+            Self::WeightCountMismatch {
+                expected, found, ..
+            } => write!(
+                formatter,
+                "event has {found} weights but the run declares {expected} weight names"
+            ),
+            Self::CountMismatch {
+                record,
+                declared,
+                found,
+                ..
+            } => write!(
+                formatter,
+                "event declares {declared} {record} but {found} were read"
+            ),
+            Self::Io { message, .. } => write!(formatter, "failed to read HepMC3 input: {message}"),
+            Self::InvalidIndex { message } => {
+                write!(formatter, "invalid HepMC3 event index: {message}")
+            }
+            Self::IndexMismatch {
+                indexed_bytes,
+                file_bytes,
+            } => write!(
+                formatter,
+                "HepMC3 event index was built for a {indexed_bytes}-byte file but the file has {file_bytes} bytes; rebuild the index"
+            ),
+            Self::EventOutOfRange {
+                requested,
+                available,
+            } => write!(
+                formatter,
+                "event {requested} requested but the file holds only {available} events"
+            ),
+        }
+    }
+}
+
+impl Error for Hepmc3Error {}
+
+/// Parse a complete `Asciiv3` document held in memory.
+pub fn parse_ascii(content: &str) -> Result<Hepmc3File, Hepmc3Error> {
+    let mut parser = AsciiParser::new();
+    let mut events = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if let Some(event) = parser.parse_line(index + 1, line)? {
+            events.push(event);
+        }
+    }
+    if let Some(event) = parser.finish()? {
+        events.push(event);
+    }
+    Ok(Hepmc3File {
+        run_info: parser.run_info,
+        events,
+    })
+}
+
+/// Incremental `Asciiv3` parser fed one line at a time.
+///
+/// An event is returned once the record that follows it (the next `E` line,
+/// the end-of-listing footer, or [`AsciiParser::finish`]) shows it is
+/// complete, so at most one event is held in memory.
+#[derive(Debug, Default)]
+pub struct AsciiParser {
+    run_info: GenRunInfo,
+    pending: Option<PendingEvent>,
+    seen_event: bool,
+}
+
+impl AsciiParser {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run information read so far. It is complete once the first event has
+    /// been returned.
+    #[must_use]
+    pub fn run_info(&self) -> &GenRunInfo {
+        &self.run_info
+    }
+
+    /// Parse line `line_number` and return the previous event if this line
+    /// closes it.
+    pub fn parse_line(
+        &mut self,
+        line_number: usize,
+        line: &str,
+    ) -> Result<Option<GenEvent>, Hepmc3Error> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        if line.starts_with(HEADER_PREFIX) {
+            return self.parse_header(line_number, line);
+        }
+
+        let (tag, rest) = split_token(line);
+        let mut characters = tag.chars();
+        let record = match (characters.next(), characters.next()) {
+            (Some(record), None) => record,
+            _ => {
+                return Err(Hepmc3Error::UnknownRecord {
+                    line: line_number,
+                    record: tag.to_owned(),
+                })
+            }
+        };
+
+        if record == 'E' {
+            let finished = self.finish()?;
+            self.pending = Some(PendingEvent::parse(line_number, rest)?);
+            self.seen_event = true;
+            return Ok(finished);
+        }
+
+        match self.pending.as_mut() {
+            Some(event) => {
+                event.parse_record(line_number, record, rest, &self.run_info)?;
+            }
+            None if !self.seen_event => self.parse_run_record(line_number, record, rest)?,
+            None => {
+                return Err(Hepmc3Error::RecordOutsideEvent {
+                    line: line_number,
+                    record,
+                })
+            }
+        }
+        Ok(None)
+    }
+
+    /// Close the event in progress, if any.
+    pub fn finish(&mut self) -> Result<Option<GenEvent>, Hepmc3Error> {
+        self.pending.take().map(PendingEvent::build).transpose()
+    }
+
+    pub(super) fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub(super) fn seen_event(&self) -> bool {
+        self.seen_event
+    }
+
+    /// Drop the event in progress and treat the event body that follows as
+    /// skipped rather than parsed.
+    pub(super) fn skip_event(&mut self) {
+        self.pending = None;
+        self.seen_event = true;
+    }
+
+    fn parse_header(
+        &mut self,
+        line_number: usize,
+        line: &str,
+    ) -> Result<Option<GenEvent>, Hepmc3Error> {
+        if line.starts_with(VERSION_HEADER) || line == LISTING_START {
+            Ok(None)
+        } else if line == LISTING_END {
+            self.finish()
+        } else {
+            Err(Hepmc3Error::UnsupportedFormat {
+                line: line_number,
+                header: line.to_owned(),
+            })
+        }
+    }
+
+    fn parse_run_record(
+        &mut self,
+        line_number: usize,
+        record: char,
+        rest: &str,
+    ) -> Result<(), Hepmc3Error> {
+        match record {
+            'W' => {
+                self.run_info.weight_names = unescape(rest.trim())
+                    .split('\n')
+                    .map(str::to_owned)
+                    .collect();
+            }
+            'T' => {
+                let text = unescape(rest.trim());
+                let mut parts = text.splitn(3, '\n').map(str::to_owned);
+                self.run_info.tools.push(ToolInfo {
+                    name: parts.next().unwrap_or_default(),
+                    version: parts.next().unwrap_or_default(),
+                    description: parts.next().unwrap_or_default(),
+                });
+            }
+            'A' => {
+                let (name, value) = split_token(rest);
+                if name.is_empty() {
+                    return Err(Hepmc3Error::MissingField {
+                        line: line_number,
+                        record,
+                        field: "name",
+                    });
+                }
+                self.run_info
+                    .attributes
+                    .push((name.to_owned(), unescape(value.trim_start())));
+            }
+            _ => {
+                return Err(Hepmc3Error::RecordOutsideEvent {
+                    line: line_number,
+                    record,
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct RawParticle {
+    line: usize,
+    parent: i32,
+    particle: GenParticle,
+}
+
+#[derive(Debug)]
+struct RawVertex {
+    line: usize,
+    vertex: GenVertex,
+}
+
+#[derive(Debug)]
+struct PendingEvent {
+    line: usize,
+    declared_vertices: usize,
+    declared_particles: usize,
+    event: GenEvent,
+    particles: Vec<RawParticle>,
+    vertices: Vec<RawVertex>,
+}
+
+impl PendingEvent {
+    fn parse(line: usize, rest: &str) -> Result<Self, Hepmc3Error> {
+        let mut fields = Fields::new(line, 'E', rest);
+        let event_number = fields.number("event_number")?;
+        let declared_vertices = fields.number("n_vertices")?;
+        let declared_particles = fields.number("n_particles")?;
+        let position = fields.optional_position()?;
+        fields.finish()?;
+        Ok(Self {
+            line,
+            declared_vertices,
+            declared_particles,
+            event: GenEvent {
+                event_number,
+                position,
+                ..GenEvent::default()
+            },
+            particles: Vec::new(),
+            vertices: Vec::new(),
+        })
+    }
+
+    fn parse_record(
+        &mut self,
+        line: usize,
+        record: char,
+        rest: &str,
+        run_info: &GenRunInfo,
+    ) -> Result<(), Hepmc3Error> {
+        let mut fields = Fields::new(line, record, rest);
+        match record {
+            'U' => {
+                let momentum = fields.text("momentum_unit")?;
+                let length = fields.text("length_unit")?;
+                fields.finish()?;
+                let invalid = |message| Hepmc3Error::InvalidUnit { line, message };
+                self.event.units = Units {
+                    momentum: momentum.parse().map_err(invalid)?,
+                    length: length.parse().map_err(invalid)?,
+                };
+            }
+            'W' => {
+                let mut weights = vec![fields.number("weight")?];
+                while !fields.is_empty() {
+                    weights.push(fields.number("weight")?);
+                }
+                if !run_info.weight_names.is_empty() && weights.len() != run_info.weight_names.len()
+                {
+                    return Err(Hepmc3Error::WeightCountMismatch {
+                        line,
+                        expected: run_info.weight_names.len(),
+                        found: weights.len(),
+                    });
+                }
+                self.event.weights = weights;
+            }
+            'A' => {
+                let target = fields.number("target")?;
+                let name = fields.text("name")?.to_owned();
+                self.event.attributes.push(Attribute {
+                    target,
+                    name,
+                    value: unescape(fields.remainder()),
+                });
+            }
+            'V' => self.parse_vertex(fields)?,
+            'P' => self.parse_particle(fields)?,
+            _ => {
+                return Err(Hepmc3Error::UnknownRecord {
+                    line,
+                    record: record.to_string(),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_vertex(&mut self, mut fields: Fields<'_>) -> Result<(), Hepmc3Error> {
+        let line = fields.line;
+        let id: i32 = fields.number("id")?;
+        if id >= 0 {
+            return Err(fields.malformed(format!("vertex id {id} must be negative")));
+        }
+        if self.vertices.iter().any(|raw| raw.vertex.id == id) {
+            return Err(Hepmc3Error::DuplicateVertex { line, id });
+        }
+        let status = fields.number("status")?;
+        let incoming = fields.optional_id_list()?;
+        let position = fields.optional_position()?;
+        fields.finish()?;
+        self.vertices.push(RawVertex {
+            line,
+            vertex: GenVertex {
+                id,
+                status,
+                position,
+                incoming,
+                outgoing: Vec::new(),
+            },
+        });
+        Ok(())
+    }
+
+    fn parse_particle(&mut self, mut fields: Fields<'_>) -> Result<(), Hepmc3Error> {
+        let line = fields.line;
+        let id: i32 = fields.number("id")?;
+        let expected = self.particles.len() as i32 + 1;
+        if id != expected {
+            return Err(Hepmc3Error::UnexpectedParticleId {
+                line,
+                expected,
+                found: id,
+            });
+        }
+        let parent = fields.number("parent")?;
+        let pdg_id = fields.number("pdg_id")?;
+        let px = fields.number("px")?;
+        let py = fields.number("py")?;
+        let pz = fields.number("pz")?;
+        let e = fields.number("e")?;
+        let mass = fields.number("mass")?;
+        let status = fields.number("status")?;
+        fields.finish()?;
+        self.particles.push(RawParticle {
+            line,
+            parent,
+            particle: GenParticle {
+                id,
+                pdg_id,
+                momentum: FourVector::new(e, px, py, pz),
+                mass,
+                status,
+                production_vertex: None,
+                end_vertex: None,
+            },
+        });
+        Ok(())
+    }
+
+    /// Resolve vertex in-lists and particle parents into the event graph.
+    fn build(self) -> Result<GenEvent, Hepmc3Error> {
+        let Self {
+            line: event_line,
+            declared_vertices,
+            declared_particles,
+            mut event,
+            particles,
+            vertices,
+        } = self;
+
+        let mut parents = Vec::with_capacity(particles.len());
+        let mut particle_lines = Vec::with_capacity(particles.len());
+        event.particles = particles
+            .into_iter()
+            .map(|raw| {
+                parents.push(raw.parent);
+                particle_lines.push(raw.line);
+                raw.particle
+            })
+            .collect();
+
+        let mut graph: Vec<GenVertex> = Vec::with_capacity(vertices.len());
+        for RawVertex { line, vertex } in vertices {
+            for &id in &vertex.incoming {
+                let particle = particle_mut(&mut event.particles, id)
+                    .ok_or(Hepmc3Error::UnknownParticle { line, id })?;
+                if particle.end_vertex.is_some() {
+                    return Err(Hepmc3Error::ConflictingEndVertex { line, particle: id });
+                }
+                particle.end_vertex = Some(vertex.id);
+            }
+            graph.push(vertex);
+        }
+
+        // A positive parent names a particle whose end vertex was not written
+        // out; recreate it under the lowest vertex id the record left free.
+        let used: HashSet<i32> = graph.iter().map(|vertex| vertex.id).collect();
+        let mut free_ids = (1..).map(|n: i32| -n).filter(|id| !used.contains(id));
+        for (index, &parent) in parents.iter().enumerate() {
+            if parent <= 0 {
+                continue;
+            }
+            let line = particle_lines[index];
+            if parent == event.particles[index].id {
+                return Err(Hepmc3Error::UnknownParticle { line, id: parent });
+            }
+            let parent_particle = particle_mut(&mut event.particles, parent)
+                .ok_or(Hepmc3Error::UnknownParticle { line, id: parent })?;
+            if parent_particle.end_vertex.is_none() {
+                let id = free_ids.next().unwrap_or(i32::MIN);
+                parent_particle.end_vertex = Some(id);
+                graph.push(GenVertex {
+                    id,
+                    status: 0,
+                    position: None,
+                    incoming: vec![parent],
+                    outgoing: Vec::new(),
+                });
+            }
+        }
+
+        let index_of: HashMap<i32, usize> = graph
+            .iter()
+            .enumerate()
+            .map(|(index, vertex)| (vertex.id, index))
+            .collect();
+        for (index, &parent) in parents.iter().enumerate() {
+            let vertex_id = match parent {
+                0 => continue,
+                id if id < 0 => id,
+                id => event.particles[id as usize - 1]
+                    .end_vertex
+                    .expect("parent end vertex assigned above"),
+            };
+            let vertex_index = *index_of.get(&vertex_id).ok_or(Hepmc3Error::UnknownVertex {
+                line: particle_lines[index],
+                id: vertex_id,
+            })?;
+            let particle = &mut event.particles[index];
+            particle.production_vertex = Some(vertex_id);
+            graph[vertex_index].outgoing.push(particle.id);
+        }
+
+        graph.sort_by_key(|vertex| std::cmp::Reverse(vertex.id));
+        event.vertices = graph;
+
+        for (record, declared, found) in [
+            ("vertices", declared_vertices, event.vertices.len()),
+            ("particles", declared_particles, event.particles.len()),
+        ] {
+            if declared != found {
+                return Err(Hepmc3Error::CountMismatch {
+                    line: event_line,
+                    record,
+                    declared,
+                    found,
+                });
+            }
+        }
+        Ok(event)
+    }
+}
+
+fn particle_mut(particles: &mut [GenParticle], id: i32) -> Option<&mut GenParticle> {
+    let index = usize::try_from(id).ok()?.checked_sub(1)?;
+    particles.get_mut(index)
+}
+
+/// Split off the first whitespace-delimited token.
+fn split_token(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    match text.find(char::is_whitespace) {
+        Some(end) => (&text[..end], &text[end..]),
+        None => (text, ""),
+    }
+}
+
+/// Cursor over the fields of one record.
+struct Fields<'a> {
+    line: usize,
+    record: char,
+    rest: &'a str,
+}
+
+impl<'a> Fields<'a> {
+    fn new(line: usize, record: char, rest: &'a str) -> Self {
+        Self { line, record, rest }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rest.trim().is_empty()
+    }
+
+    fn text(&mut self, field: &'static str) -> Result<&'a str, Hepmc3Error> {
+        let (token, rest) = split_token(self.rest);
+        if token.is_empty() {
+            return Err(Hepmc3Error::MissingField {
+                line: self.line,
+                record: self.record,
+                field,
+            });
+        }
+        self.rest = rest;
+        Ok(token)
+    }
+
+    fn number<T: FromStr>(&mut self, field: &'static str) -> Result<T, Hepmc3Error> {
+        let token = self.text(field)?;
+        token.parse().map_err(|_| Hepmc3Error::InvalidNumber {
+            line: self.line,
+            field,
+            value: token.to_owned(),
+        })
+    }
+
+    /// Everything after the next separating space, unparsed.
+    fn remainder(&mut self) -> &'a str {
+        let rest = self.rest.strip_prefix(' ').unwrap_or(self.rest);
+        self.rest = "";
+        rest
+    }
+
+    /// A bracketed, comma-separated list such as `[1,2]`, if present.
+    fn optional_id_list(&mut self) -> Result<Vec<i32>, Hepmc3Error> {
+        let text = self.rest.trim_start();
+        let Some(list) = text.strip_prefix('[') else {
+            return Ok(Vec::new());
+        };
+        let Some(end) = list.find(']') else {
+            return Err(self.malformed("unterminated particle list".to_owned()));
+        };
+        self.rest = &list[end + 1..];
+        list[..end]
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                token.parse().map_err(|_| Hepmc3Error::InvalidNumber {
+                    line: self.line,
+                    field: "particle_list",
+                    value: token.to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// An `@ x y z t` position suffix, if present.
+    fn optional_position(&mut self) -> Result<Option<Position>, Hepmc3Error> {
+        let (marker, rest) = split_token(self.rest);
+        if marker != "@" {
+            return Ok(None);
+        }
+        self.rest = rest;
+        Ok(Some(Position::new(
+            self.number("x")?,
+            self.number("y")?,
+            self.number("z")?,
+            self.number("t")?,
+        )))
+    }
+
+    fn finish(&self) -> Result<(), Hepmc3Error> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self.malformed(format!("unexpected trailing fields '{}'", self.rest.trim())))
+        }
+    }
+
+    fn malformed(&self, reason: String) -> Hepmc3Error {
+        Hepmc3Error::MalformedRecord {
+            line: self.line,
+            record: self.record,
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hepmc3::{LengthUnit, MomentumUnit};
+
+    const PYTHIA_STYLE: &str = "\
+HepMC::Version 3.02.06
+HepMC::Asciiv3-START_EVENT_LISTING
+W Weight\\|MUR0.5_MUF1.0
+T Pythia8\\|8.310\\|Output from Pythia8
+A NLO_weight_name nominal
+E 7 2 5 @ 0 0 0 0
+U GEV CM
+W 1.5 0.75
+A 0 signal_process_id 211
+A 0 note first line\\|second \\\\ line
+P 1 0 11 0 0 27.5 27.5 0.000511 4
+P 2 0 2212 0 0 -920 920.0004783 0.938 4
+V -1 0 [1,2] @ 0.1 0.2 0.3 0.4
+P 3 -1 11 5 3 20 21 0.000511 1
+P 4 -1 1 -5 -3 -10 12 0.33 2
+P 5 4 211 -5 -3 -10 12 0.1396 1
+A 5 flow1 501
+HepMC::Asciiv3-END_EVENT_LISTING
+";
+
+    #[test]
+    fn reads_run_info_units_weights_and_attributes() {
+        let file = parse_ascii(PYTHIA_STYLE).unwrap();
+        assert_eq!(file.run_info.weight_names, ["Weight", "MUR0.5_MUF1.0"]);
+        assert_eq!(file.run_info.tools[0].name, "Pythia8");
+        assert_eq!(file.run_info.tools[0].description, "Output from Pythia8");
+        assert_eq!(file.run_info.attribute("NLO_weight_name"), Some("nominal"));
+
+        let event = &file.events[0];
+        assert_eq!(event.event_number, 7);
+        assert_eq!(event.units.momentum, MomentumUnit::Gev);
+        assert_eq!(event.units.length, LengthUnit::Cm);
+        assert_eq!(event.weight(), 1.5);
+        assert_eq!(
+            event.named_weight(&file.run_info, "MUR0.5_MUF1.0"),
+            Some(0.75)
+        );
+        assert_eq!(event.attribute(0, "signal_process_id"), Some("211"));
+        assert_eq!(
+            event.attribute(0, "note"),
+            Some("first line\nsecond \\ line")
+        );
+        assert_eq!(event.attribute(5, "flow1"), Some("501"));
+    }
+
+    #[test]
+    fn links_particles_through_explicit_and_implicit_vertices() {
+        let event = parse_ascii(PYTHIA_STYLE).unwrap().events.remove(0);
+        assert_eq!(event.vertices.len(), 2);
+
+        let hard = event.vertex(-1).unwrap();
+        assert_eq!(hard.incoming, [1, 2]);
+        assert_eq!(hard.outgoing, [3, 4]);
+        assert_eq!(hard.position, Some(Position::new(0.1, 0.2, 0.3, 0.4)));
+
+        let quark = event.particle(4).unwrap();
+        assert_eq!(quark.production_vertex, Some(-1));
+        assert_eq!(quark.end_vertex, Some(-2));
+        let implicit = event.vertex(-2).unwrap();
+        assert_eq!(implicit.incoming, [4]);
+        assert_eq!(implicit.outgoing, [5]);
+        assert_eq!(implicit.position, None);
+
+        let pion = event.particle(5).unwrap();
+        assert_eq!(pion.momentum.e, 12.0);
+        assert_eq!(event.parents(pion)[0].id, 4);
+        let beam = event.particle(1).unwrap();
+        assert_eq!(beam.production_vertex, None);
+        let children: Vec<i32> = event.children(beam).iter().map(|p| p.id).collect();
+        assert_eq!(children, [3, 4]);
+        assert_eq!(event.final_state().count(), 2);
+    }
+
+    #[test]
+    fn vertices_may_reference_later_particles() {
+        let input = "\
+E 1 2 4
+P 1 0 11 0 0 1 1 0 4
+V -2 0 [1,3]
+P 2 -2 22 0 0 1 1 0 2
+P 3 0 2212 0 0 -1 1 0.938 4
+P 4 2 11 0 0 1 1 0 1
+";
+        let event = parse_ascii(input).unwrap().events.remove(0);
+        let explicit = event.vertex(-2).unwrap();
+        assert_eq!(explicit.incoming, [1, 3]);
+        assert_eq!(explicit.outgoing, [2]);
+        assert_eq!(event.particle(3).unwrap().end_vertex, Some(-2));
+        // The implicit decay vertex of particle 2 takes the free id -1.
+        let implicit = event.vertex(-1).unwrap();
+        assert_eq!(implicit.incoming, [2]);
+        assert_eq!(implicit.outgoing, [4]);
+    }
+
+    #[test]
+    fn reports_malformed_input_with_line_numbers() {
+        let cases = [
+            ("E 1 0 1\nP 1 0 11 0 0 abc 1 0 1\n", 2, "pz"),
+            ("E 1 0 1\nP 1 0 11 0 0 1 1 0\n", 2, "status"),
+            (
+                "E 1 0 2\nP 1 0 11 0 0 1 1 0 1\nP 3 0 11 0 0 1 1 0 1\n",
+                3,
+                "",
+            ),
+            ("E 1 1 1\nP 1 -4 11 0 0 1 1 0 1\n", 2, ""),
+            ("E 1 0 0\nU GEV KM\n", 2, ""),
+            ("E 1 0 0\nV -1 0 [1\n", 2, ""),
+            ("P 1 0 11 0 0 1 1 0 1\n", 1, ""),
+            ("HepMC::IO_GenEvent-START_EVENT_LISTING\n", 1, ""),
+            ("E 1 0 0\nX 1\n", 2, ""),
+            ("E 1 0 2\nP 1 0 11 0 0 1 1 0 1\n", 1, ""),
+            (
+                "E 1 2 1\nP 1 0 11 0 0 1 1 0 1\nV -1 0 [1]\nV -2 0 [1]\n",
+                4,
+                "",
+            ),
+        ];
+        for (input, line, field) in cases {
+            let error = parse_ascii(input).unwrap_err();
+            assert_eq!(error.line(), Some(line), "{input:?}: {error}");
+            if !field.is_empty() {
+                assert!(error.to_string().contains(field), "{error}");
+            }
+        }
+
+        let weights = "W a\\|b\nE 1 0 0\nW 1 2 3\n";
+        assert!(matches!(
+            parse_ascii(weights).unwrap_err(),
+            Hepmc3Error::WeightCountMismatch {
+                line: 3,
+                expected: 2,
+                found: 3
+            }
+        ));
+    }
+}
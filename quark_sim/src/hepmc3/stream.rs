@@ -0,0 +1,464 @@
+//! Streaming access to large event files.
+//!
+//! [`Hepmc3Reader`] reads from any [`BufRead`] and yields one event at a
+//! time, so memory use is bounded by the largest event rather than by the
+//! file. [`Hepmc3Reader::open`] detects gzip compression from the file's
+//! magic bytes, and an [`EventIndex`] built once per file lets uncompressed
+//! files jump straight to event `N` instead of scanning from the start.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use flate2::bufread::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+
+use super::reader::{AsciiParser, Hepmc3Error};
+use super::{GenEvent, GenRunInfo};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// File-name suffix of the index written next to an event file.
+pub const INDEX_SUFFIX: &str = ".index.json";
+
+/// Event file opened for reading, decompressed on the fly when gzipped.
+pub enum Hepmc3Input {
+    Plain(BufReader<File>),
+    Gzip(BufReader<MultiGzDecoder<BufReader<File>>>),
+}
+
+impl Hepmc3Input {
+    pub fn open(path: &Path) -> Result<Self, Hepmc3Error> {
+        let file = File::open(path).map_err(|error| io_error(None, path, &error))?;
+        let mut reader = BufReader::new(file);
+        let compressed = reader
+            .fill_buf()
+            .map_err(|error| io_error(None, path, &error))?
+            .starts_with(&GZIP_MAGIC);
+        Ok(if compressed {
+            Self::Gzip(BufReader::new(MultiGzDecoder::new(reader)))
+        } else {
+            Self::Plain(reader)
+        })
+    }
+
+    #[must_use]
+    pub fn is_compressed(&self) -> bool {
+        matches!(self, Self::Gzip(_))
+    }
+}
+
+impl Read for Hepmc3Input {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(reader) => reader.read(buffer),
+            Self::Gzip(reader) => reader.read(buffer),
+        }
+    }
+}
+
+impl BufRead for Hepmc3Input {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Self::Plain(reader) => reader.fill_buf(),
+            Self::Gzip(reader) => reader.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        match self {
+            Self::Plain(reader) => reader.consume(amount),
+            Self::Gzip(reader) => reader.consume(amount),
+        }
+    }
+}
+
+impl Seek for Hepmc3Input {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::Plain(reader) => reader.seek(position),
+            Self::Gzip(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "compressed event files cannot seek",
+            )),
+        }
+    }
+}
+
+/// Iterator over the events of an `Asciiv3` stream.
+///
+/// The iterator stops after the first error, which it yields once.
+pub struct Hepmc3Reader<R> {
+    input: R,
+    parser: AsciiParser,
+    line: String,
+    line_number: usize,
+    done: bool,
+}
+
+impl Hepmc3Reader<Hepmc3Input> {
+    /// Open a plain or gzip-compressed event file.
+    pub fn open(path: &Path) -> Result<Self, Hepmc3Error> {
+        Ok(Self::new(Hepmc3Input::open(path)?))
+    }
+
+    /// Open `path` positioned at its `ordinal`-th event (0-based).
+    ///
+    /// With an index, uncompressed files seek directly to the event;
+    /// otherwise the preceding events are skipped without being parsed.
+    pub fn open_at_event(
+        path: &Path,
+        ordinal: usize,
+        index: Option<&EventIndex>,
+    ) -> Result<Self, Hepmc3Error> {
+        let mut reader = Self::open(path)?;
+        match index {
+            Some(index) if !reader.input.is_compressed() => {
+                index.check_source(path)?;
+                reader.seek_event(ordinal, index)?;
+            }
+            _ => {
+                let skipped = reader.skip_events(ordinal)?;
+                if !reader.parser.has_pending() {
+                    return Err(Hepmc3Error::EventOutOfRange {
+                        requested: ordinal,
+                        available: skipped,
+                    });
+                }
+            }
+        }
+        Ok(reader)
+    }
+}
+
+impl<R: BufRead> Hepmc3Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            parser: AsciiParser::new(),
+            line: String::new(),
+            line_number: 0,
+            done: false,
+        }
+    }
+
+    /// Run information read so far; see [`Hepmc3Reader::read_run_info`].
+    #[must_use]
+    pub fn run_info(&self) -> &GenRunInfo {
+        self.parser.run_info()
+    }
+
+    /// Read the file header up to the first event, so that the run
+    /// information is complete before any event is requested.
+    pub fn read_run_info(&mut self) -> Result<&GenRunInfo, Hepmc3Error> {
+        self.skip_events(0)?;
+        Ok(self.parser.run_info())
+    }
+
+    /// Skip the next `count` events without building them and return how
+    /// many were skipped, which is less than `count` only at end of input.
+    pub fn skip_events(&mut self, count: usize) -> Result<usize, Hepmc3Error> {
+        let mut skipped = 0;
+        if count > 0 && self.parser.has_pending() {
+            self.parser.skip_event();
+            skipped = 1;
+        }
+        loop {
+            if skipped == count && self.parser.has_pending() {
+                return Ok(skipped);
+            }
+            if !self.read_line()? {
+                return Ok(skipped);
+            }
+            if is_event_record(&self.line) {
+                if skipped < count {
+                    self.parser.skip_event();
+                    skipped += 1;
+                    continue;
+                }
+            } else if self.parser.seen_event() {
+                continue;
+            }
+            self.parser.parse_line(self.line_number, &self.line)?;
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Option<GenEvent>, Hepmc3Error> {
+        loop {
+            if !self.read_line()? {
+                return self.parser.finish();
+            }
+            if let Some(event) = self.parser.parse_line(self.line_number, &self.line)? {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> Result<bool, Hepmc3Error> {
+        self.line.clear();
+        let read = self
+            .input
+            .read_line(&mut self.line)
+            .map_err(|error| Hepmc3Error::Io {
+                line: Some(self.line_number + 1),
+                message: error.to_string(),
+            })?;
+        self.line_number += 1;
+        Ok(read > 0)
+    }
+}
+
+impl<R: BufRead + Seek> Hepmc3Reader<R> {
+    /// Reposition the reader at event `ordinal` of `index`.
+    pub fn seek_event(&mut self, ordinal: usize, index: &EventIndex) -> Result<(), Hepmc3Error> {
+        self.read_run_info()?;
+        let entry = index
+            .entries
+            .get(ordinal)
+            .ok_or(Hepmc3Error::EventOutOfRange {
+                requested: ordinal,
+                available: index.entries.len(),
+            })?;
+        self.input
+            .seek(SeekFrom::Start(entry.offset))
+            .map_err(|error| Hepmc3Error::Io {
+                line: Some(entry.line),
+                message: error.to_string(),
+            })?;
+        self.parser.skip_event();
+        self.line_number = entry.line - 1;
+        self.done = false;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for Hepmc3Reader<R> {
+    type Item = Result<GenEvent, Hepmc3Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Position of one event in the decompressed text of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub event_number: i64,
+    /// Byte offset of the event's `E` record.
+    pub offset: u64,
+    /// 1-based line number of the event's `E` record.
+    pub line: usize,
+}
+
+/// Event offsets of one file, used to seek to an event by position.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EventIndex {
+    /// Size of the indexed file on disk, used to detect a stale index.
+    pub source_bytes: u64,
+    pub entries: Vec<IndexEntry>,
+}
+
+impl EventIndex {
+    /// Scan `path` once and record where every event starts.
+    pub fn build(path: &Path) -> Result<Self, Hepmc3Error> {
+        let source_bytes = file_size(path)?;
+        let mut input = Hepmc3Input::open(path)?;
+        let mut entries = Vec::new();
+        let mut line = String::new();
+        let mut offset = 0_u64;
+        let mut line_number = 0;
+        loop {
+            line.clear();
+            let read = input
+                .read_line(&mut line)
+                .map_err(|error| io_error(Some(line_number + 1), path, &error))?;
+            if read == 0 {
+                break;
+            }
+            line_number += 1;
+            if is_event_record(&line) {
+                let value = line.split_whitespace().nth(1).unwrap_or_default();
+                let event_number = value.parse().map_err(|_| Hepmc3Error::InvalidNumber {
+                    line: line_number,
+                    field: "event_number",
+                    value: value.to_owned(),
+                })?;
+                entries.push(IndexEntry {
+                    event_number,
+                    offset,
+                    line: line_number,
+                });
+            }
+            offset += read as u64;
+        }
+        Ok(Self {
+            source_bytes,
+            entries,
+        })
+    }
+
+    /// Number of indexed events.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Hepmc3Error> {
+        let text = fs::read_to_string(path).map_err(|error| io_error(None, path, &error))?;
+        serde_json::from_str(&text).map_err(|error| Hepmc3Error::InvalidIndex {
+            message: format!("{}: {error}", path.display()),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Hepmc3Error> {
+        let text = serde_json::to_string(self).map_err(|error| Hepmc3Error::InvalidIndex {
+            message: error.to_string(),
+        })?;
+        fs::write(path, text).map_err(|error| io_error(None, path, &error))
+    }
+
+    /// Fail unless the index was built for the current contents of `path`.
+    pub fn check_source(&self, path: &Path) -> Result<(), Hepmc3Error> {
+        let file_bytes = file_size(path)?;
+        if file_bytes == self.source_bytes {
+            Ok(())
+        } else {
+            Err(Hepmc3Error::IndexMismatch {
+                indexed_bytes: self.source_bytes,
+                file_bytes,
+            })
+        }
+    }
+}
+
+/// Conventional index location for an event file: `<file>.index.json`.
+#[must_use]
+pub fn index_path(events: &Path) -> PathBuf {
+    let mut name = events.as_os_str().to_owned();
+    name.push(INDEX_SUFFIX);
+    PathBuf::from(name)
+}
+
+fn is_event_record(line: &str) -> bool {
+    line.split_whitespace().next() == Some("E")
+}
+
+fn file_size(path: &Path) -> Result<u64, Hepmc3Error> {
+    fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .map_err(|error| io_error(None, path, &error))
+}
+
+fn io_error(line: Option<usize>, path: &Path, error: &io::Error) -> Hepmc3Error {
+    Hepmc3Error::Io {
+        line,
+        message: format!("{}: {error}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn sample(events: usize) -> String {
+        let mut text = String::from(
+            "HepMC::Version 3.02.06\nHepMC::Asciiv3-START_EVENT_LISTING\nW nominal\\|alt\n",
+        );
+        for number in 0..events {
+            text.push_str(&format!(
+                "E {number} 1 3\nU GEV MM\nW {number} 1\n\
+                 P 1 0 11 0 0 1 1 0 4\nP 2 0 2212 0 0 -1 1 0.938 4\n\
+                 V -1 0 [1,2]\nP 3 -1 11 0 0 0 {number} 0 1\n"
+            ));
+        }
+        text.push_str("HepMC::Asciiv3-END_EVENT_LISTING\n");
+        text
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("quark_sim_hepmc3_{name}_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn yields_events_one_at_a_time_and_skips() {
+        let text = sample(5);
+        let mut reader = Hepmc3Reader::new(text.as_bytes());
+        assert_eq!(
+            reader.read_run_info().unwrap().weight_names,
+            ["nominal", "alt"]
+        );
+        let first = reader.next().unwrap().unwrap();
+        assert_eq!(first.event_number, 0);
+        assert_eq!(reader.skip_events(2).unwrap(), 2);
+        let numbers: Vec<i64> = reader.map(|event| event.unwrap().event_number).collect();
+        assert_eq!(numbers, [3, 4]);
+    }
+
+    #[test]
+    fn stops_after_the_first_error() {
+        let text = "E 1 0 1\nP 1 0 11 0 0 x 1 0 1\nE 2 0 0\n";
+        let mut reader = Hepmc3Reader::new(text.as_bytes());
+        assert_eq!(reader.next().unwrap().unwrap_err().line(), Some(2));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn seeks_through_an_index_and_reads_gzip() {
+        let dir = temp_dir("stream");
+        let plain = dir.join("events.hepmc3");
+        fs::write(&plain, sample(6)).unwrap();
+        let compressed = dir.join("events.hepmc3.gz");
+        let mut encoder = GzEncoder::new(File::create(&compressed).unwrap(), Compression::fast());
+        encoder.write_all(sample(6).as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let index = EventIndex::build(&plain).unwrap();
+        assert_eq!(index.len(), 6);
+        index.save(&index_path(&plain)).unwrap();
+        let index = EventIndex::load(&index_path(&plain)).unwrap();
+
+        for (path, index) in [(&plain, Some(&index)), (&compressed, None)] {
+            let mut reader = Hepmc3Reader::open_at_event(path, 4, index).unwrap();
+            assert_eq!(reader.run_info().weight_names.len(), 2);
+            let event = reader.next().unwrap().unwrap();
+            assert_eq!(event.event_number, 4);
+            assert_eq!(event.particles[2].momentum.e, 4.0);
+            assert_eq!(reader.count(), 1);
+        }
+
+        assert!(matches!(
+            Hepmc3Reader::open_at_event(&compressed, 6, None),
+            Err(Hepmc3Error::EventOutOfRange { available: 6, .. })
+        ));
+        fs::write(&plain, sample(2)).unwrap();
+        assert!(matches!(
+            Hepmc3Reader::open_at_event(&plain, 1, Some(&index)),
+            Err(Hepmc3Error::IndexMismatch { .. })
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
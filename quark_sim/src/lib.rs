@@ -1,3 +1,15 @@
 //! Reusable library components for the Cornell visualization and DIS tools.
 
+pub mod analysis;
+pub mod batch;
+pub mod checksum;
+pub mod event_chunks;
+pub mod generator_progress;
+pub mod hepmc3;
+pub mod pdf_artifacts;
 pub mod physics;
+pub mod pythia_settings;
+pub mod reweighting_artifacts;
+pub mod run_comparison_artifacts;
+pub mod surrogate_validation_artifacts;
+pub mod unfolding_artifacts;
@@ -1,6 +1,10 @@
+mod cli_output;
+mod doctor;
 mod gui;
+mod manifest;
 mod model;
 mod plotting;
+mod run_card;
 mod scattering;
 mod training;
 
@@ -10,13 +14,36 @@ use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use cli_output::{print_record, CliError, OutputFormat};
+use doctor::{
+    Backend, BuildProvenance, DoctorOptions, DoctorReport, LhapdfReport, PYTHIA_SCHEMA_VERSION,
+};
 use gui::launch_gui;
 use gui::legacy_cornell::{AppData, InteractiveContext};
+use gui::state::GuiErrorCategory;
+use manifest::{
+    compare_artifacts, verify_digests, ManifestRecorder, RunManifest, Tolerances, VerifyReport,
+    MANIFEST_FILENAME,
+};
 use plotting::plot_results;
+use quark_sim::batch::{grid_points, read_points, BatchWriter, KinematicPoint, LogGrid};
+use quark_sim::event_chunks::{
+    chunk_directory, chunk_seeds, merge_chunks, split_events, GenerationChunk,
+    CHUNKS_DIRECTORY_NAME, GENERATOR_LOG_FILE_NAME,
+};
+use quark_sim::generator_progress::{
+    is_cancel_message, parse_progress_line, GenerationStatus, GeneratorProgress, CANCEL_MESSAGE,
+};
+use quark_sim::pdf_artifacts::{pdf_curves, write_pdf_plot};
 use quark_sim::physics::{
     collider_beams, compute_dis_kinematics, evaluate_lo_structure_functions, exact_inelasticity,
-    lo_differential_cross_section, scattered_electron, FixedAlpha, LhapdfProvider,
+    lo_differential_cross_section, scattered_electron, validate_surrogate, DatasetSampler,
+    EventWeighting, FixedAlpha, LearningRateSchedule, LhapdfProvider, PdfErrorSet, PdfFlavour,
+    PdfProvider, PdfSetInfo, RefinementConfig, SamplingMethod, SurrogateActivation,
+    SurrogateTrainingConfig, SurrogateValidationConfig,
 };
+use quark_sim::pythia_settings::{check_setting, read_command_file};
+use run_card::RunCard;
 use scattering::{get_proton_quarks, plot_scattering, simulate_scattering, ScatteringParams};
 use training::{
     create_model_and_optimizer, generate_training_data, load_model_with_config,
@@ -60,6 +87,58 @@ Usage:
       Validate predictions and calculate theory uncertainties against HERA DIS measurements.
       Run `quark_sim theory-uncertainties --help` for the options.
 
+  quark_sim train-surrogate [OPTIONS]
+      Generate an APFEL++ dataset and train the structure-function surrogate.
+      Run `quark_sim train-surrogate --help` for the options.
+
+  quark_sim validate-surrogate [OPTIONS]
+      Measure surrogate accuracy against its source backend on held-out points.
+      Run `quark_sim validate-surrogate --help` for the options.
+
+  quark_sim analyze-events [OPTIONS]
+      Run Rivet-style analyses over a HepMC3 event file.
+      Run `quark_sim analyze-events --help` for the options.
+
+  quark_sim unfold [OPTIONS]
+      Unfold detector-level (x, Q²) spectra with a Monte Carlo response matrix.
+      Run `quark_sim unfold --help` for the options.
+
+  quark_sim reweight-events [OPTIONS]
+      Reweight a HepMC3 event sample to other PDF sets or members.
+      Run `quark_sim reweight-events --help` for the options.
+
+  quark_sim compare-runs --run <DIR> --run <DIR> [OPTIONS]
+      Diff the configurations of finished runs and compare their Q², x, y
+      and W² distributions with ratio panels and χ² and KS tests.
+      Run `quark_sim compare-runs --help` for the options.
+
+  quark_sim doctor [OPTIONS]
+      Check LHAPDF, the APFEL++ and PYTHIA 8 backends, the surrogate model and
+      the Python environment, and print the build provenance.
+      Run `quark_sim doctor --help` for the options.
+
+  quark_sim pdf <list|info|table|plot> [OPTIONS]
+      List installed LHAPDF sets, show their metadata, tabulate x f(x, Q²) or
+      plot flavour decompositions with PDF uncertainty bands.
+      Run `quark_sim pdf --help` for the options.
+
+  quark_sim verify-run --run <RUN_DIRECTORY> [OPTIONS]
+      Recheck the SHA-256 digests of a run's manifest.json and optionally
+      repeat the run and compare its artifacts within tolerances.
+      Run `quark_sim verify-run --help` for the options.
+
+  quark_sim [SUBCOMMAND] --config <CARD.json|CARD.toml> [OPTIONS]
+      Run a subcommand from a run card. Options on the command line replace
+      those of the card. Every run writes its resolved card as run_card.json
+      into its output directory; see docs/run_cards.md.
+
+  quark_sim [SUBCOMMAND] --format <text|json|csv> [OPTIONS]
+      Print the results of dis-kinematics, dis-cross-section,
+      structure-functions, doctor, pdf and verify-run as text, JSON or CSV. With json or csv, a failure of
+      any command is one JSON error object on standard error. The exit status
+      is 2 for invalid input, 3 for a missing PDF set, model or backend and 4
+      for a physics-domain error; see docs/cli_output.md.
+
   quark_sim -h | --help
       Show this help message without training or launching the GUI.
 
@@ -125,12 +204,25 @@ Required options:
   --pdf-member <INDEX>
       Non-negative LHAPDF member index.
 
+Batch options (replace --x and --q2):
+  --points <FILE>
+      CSV file with x and q2 columns, or a JSON array of objects with x and q2.
+
+  --x-grid <MIN:MAX:N>, --q2-grid <MIN:MAX:N>
+      Evaluate every combination of two logarithmically spaced grids.
+
+  --output <FILE>
+      Write the batch to a new file instead of standard output. The global
+      --format selects a table (text), CSV or JSON Lines (json).
+
 The calculation uses LHAPDF x f(x,Q²) values, a fixed α(0), F_L = 0, and
 xF₃ = 0. It prints d²σ/(dx dQ²) in GeV⁻⁴ and pb/GeV². Points with
-unphysical y or outside the selected PDF grid are rejected.
+unphysical y or outside the selected PDF grid are rejected; in a batch the
+rejection is reported in that point's row and the remaining points are still
+evaluated.
 ";
 
-const GENERATE_DIS_EVENTS_HELP: &str = "Generate Monte Carlo DIS events using the PYTHIA 8 backend
+const GENERATE_DIS_EVENTS_HELP: &str = "Generate Monte Carlo DIS events using the PYTHIA 8 backend or the native LO generator
 
 Usage:
   quark_sim generate-dis-events \
@@ -148,7 +240,15 @@ Usage:
       [--seed <SEED>] \
       [--pdf-member <INDEX>] \
       [--parton-shower <true|false>] \
-      [--hadronization <true|false>]
+      [--hadronization <true|false>] \
+      [--generator <pythia|native>] \
+      [--process <nc|cc>] \
+      [--pythia-setting <SETTING>]... \
+      [--pythia-cmnd <FILE.cmnd>] \
+      [--weighting <unweighted|weighted>] \
+      [--jobs <COUNT>] \
+      [--retries <COUNT>] \
+      [--progress <bar|json|none>]
 
 Required options:
   --electron-energy <GEV>   Incoming electron energy. Travels along +z.
@@ -160,8 +260,394 @@ Required options:
 
 Defaults:
   --q2-max 10000.0, --x-min 0.0001, --x-max 0.8, --y-min 0.01, --y-max 0.95,
-  --pdf-member 0, --parton-shower true, --hadronization true.
+  --pdf-member 0, --parton-shower true, --hadronization true,
+  --generator pythia, --process nc, --weighting unweighted, --jobs 1,
+  --retries 2, --progress bar.
   If --seed is omitted, a random seed is dynamically generated.
+
+--process selects neutral-current (nc, gamma*/Z exchange) or charged-current
+(cc, W exchange, with a neutrino as the scattered lepton) PYTHIA 8 DIS.
+--pythia-setting passes one PYTHIA setting such as
+\"MultipartonInteractions:pT0Ref = 2.3\" and may be repeated; --pythia-cmnd
+reads them from a .cmnd file, before the --pythia-setting ones. They override
+the generator defaults, and metadata.json records every setting that differs
+from PYTHIA's defaults. Beams:, Random:, PDF:pSet and Main:numberOfEvents are
+set by the options above and cannot be passed; unknown settings stop the
+backend with the unknown_pythia_setting error.
+
+--jobs splits the events into that many chunks, each generated by its own
+PYTHIA 8 process with a seed derived from --seed, and merges the chunks into
+one run directory. A failed chunk is relaunched with the same seed up to
+--retries times. The merged summary.json averages the chunk cross sections
+weighted by their attempted events.
+
+--progress bar draws the PYTHIA 8 progress (accepted, failed and vetoed events,
+the cross-section estimate and the remaining time) on standard output.
+--progress json prints it as JSON lines instead. Ctrl-C, SIGTERM or the
+control message {\"type\":\"cancel\"} on standard input stops generation and
+keeps the events accepted so far as a complete run, after which the command
+exits with an error. A second Ctrl-C stops it at once.
+
+The native generator samples the LO cross section with the LHAPDF
+structure functions and needs no external backend. It has no parton shower
+or hadronization, so those options are ignored, and it adds the total cross
+section to summary.json. --weighting applies to the native generator only,
+and its --seed must be non-negative.
+";
+
+const TRAIN_SURROGATE_HELP: &str = "Generate an APFEL++ dataset and train the structure-function surrogate
+
+Usage:
+  quark_sim train-surrogate \\
+      --pdf-set <NAME> \\
+      --output <DIRECTORY> \\
+      [--pdf-member <INDEX>] \\
+      [--order <LO|NLO|NNLO>] \\
+      [--sampler <grid|sobol|latin-hypercube>] \\
+      [--points <COUNT>] \\
+      [--x-points <COUNT>] [--q2-points <COUNT>] [--scale-points <COUNT>] \\
+      [--seed <SEED>] \\
+      [--training-config <FILE.json>] \\
+      [--hidden-layers <WIDTH,WIDTH,...>] \\
+      [--activation <relu|gelu|silu|tanh>] \\
+      [--learning-rate <RATE>] \\
+      [--lr-schedule <constant|step|cosine>] \\
+      [--lr-step-epochs <COUNT>] [--lr-decay-factor <FACTOR>] \\
+      [--min-learning-rate <RATE>] \\
+      [--epochs <COUNT>] \\
+      [--batch-size <COUNT|full>] \\
+      [--patience <EPOCHS|none>] \\
+      [--ensemble-size <COUNT>] \\
+      [--max-uncertainty <FRACTION>] \\
+      [--constrain-fl] [--zero-xf3] \\
+      [--q2-slope-weight <WEIGHT>] \\
+      [--target-rel-error <FRACTION>] \\
+      [--max-rounds <COUNT>] \\
+      [--refine-samples <COUNT>] \\
+      [--refine-points <COUNT>] \\
+      [--resume]
+
+The grid sampler is logarithmic in x, Q² and both scale ratios and uses the
+--x-points, --q2-points and --scale-points sizes. The sobol and
+latin-hypercube samplers draw --points points instead. The dataset is written
+to <DIRECTORY>/dataset.csv before training.
+
+--training-config reads network and optimiser options from a JSON object with
+the optional fields architecture ({\"hidden_layers\": [...], \"activation\": ...}),
+learning_rate, learning_rate_schedule ({\"kind\": \"step\", \"step_epochs\": ...,
+\"factor\": ...} or {\"kind\": \"cosine\", \"min_learning_rate\": ...}), epochs,
+batch_size, validation_interval, early_stopping_patience, seed, ensemble_size,
+max_relative_uncertainty and constraints ({\"fl_bounded_by_f2\": ...,
+\"zero_xf3\": ..., \"q2_slope_weight\": ...}). Omitted fields keep their defaults and
+command-line options take precedence.
+The resolved options are written to <DIRECTORY>/training_config.json, and the
+architecture is stored in model_config.json so the model loads with the
+matching network. --seed fixes the sampler, data split and weight
+initialisation.
+
+Physics constraints are recorded in model_config.json and applied whenever the
+model is evaluated. --constrain-fl predicts FL/F2 through a logistic function
+so 0 <= FL <= F2 everywhere. --zero-xf3 fixes xF3 to zero, as required for
+pure photon exchange. --q2-slope-weight adds a training penalty on the
+difference between the network's dlnF2/dlnQ2 and APFEL++'s, which is
+evaluated by a central difference in ln Q2 and stored in dataset.csv.
+
+--ensemble-size trains that many networks on bootstrap resamples of the
+training split. The surrogate then returns the ensemble mean and reports the
+standard deviation of F2, FL and xF3 as an \"uncertainty\" field. With
+--max-uncertainty, stored in model_config.json, evaluation fails instead of
+returning a prediction whose spread exceeds that fraction of F2.
+
+Any refinement option enables adaptive refinement: after each training round
+the surrogate is compared with APFEL++ on --refine-samples fresh Sobol points,
+up to --refine-points of the worst points are added to the dataset, and the
+model is retrained until the maximum F2 relative error is at most
+--target-rel-error or --max-rounds rounds have run. Each round is recorded in
+<DIRECTORY>/refinement_history.json. --resume continues from the dataset and
+history already in <DIRECTORY> instead of generating a new dataset.
+
+Defaults:
+  --pdf-member 0, --order NLO, --sampler grid, --x-points 5, --q2-points 5,
+  --scale-points 3, --points 2000, --seed 42, --hidden-layers 128,64,32,
+  --activation relu, --learning-rate 0.001, --lr-schedule constant (step:
+  every 500 epochs by 0.5; cosine: down to 1e-5), --epochs 2000,
+  --batch-size full, --patience 200, --ensemble-size 1, --target-rel-error 0.01,
+  --max-rounds 5, --refine-samples 500, --refine-points 50.
+";
+
+const VALIDATE_SURROGATE_HELP: &str = "Validate the structure-function surrogate against its source backend
+
+Usage:
+  quark_sim validate-surrogate \\
+      --output <DIRECTORY> \\
+      [--model-dir <DIRECTORY>] \\
+      [--samples <COUNT>] \\
+      [--sampling <sobol|latin-hypercube>] \\
+      [--seed <SEED>] \\
+      [--x-bins <COUNT>] \\
+      [--q2-bins <COUNT>] \\
+      [--worst <COUNT>]
+
+Points are sampled inside the x, Q², μF/Q and μR/Q domain stored in the
+model's model_config.json (logarithmically in x and Q²). The surrogate and the
+APFEL++ backend are evaluated at every point, and the output directory
+receives surrogate_points.csv, surrogate_heat_map.csv,
+surrogate_validation.json and surrogate_heat_map.svg. Existing artifacts are
+never overwritten.
+
+Defaults:
+  --model-dir models/surrogate_v1, --samples 2000, --sampling sobol,
+  --seed 42, --x-bins 8, --q2-bins 8, --worst 20.
+";
+
+const ANALYZE_EVENTS_HELP: &str = "Run Rivet-style analyses over a HepMC3 event file
+
+Usage:
+  quark_sim analyze-events \\
+      --input <EVENTS.hepmc3[.gz]> \\
+      --output <DIRECTORY> \\
+      [--analysis <NAME[,NAME...]>] \\
+      [--summary <summary.json>] \\
+      [--cross-section <PB>] \\
+      [--max-events <COUNT>] \\
+      [--detector <CARD.json>] \\
+      [--detector-seed <SEED>]
+
+Histograms are written to analysis.yoda and analysis.csv in the output
+directory. They are normalised to pb with the generator cross section, read
+from --cross-section, from --summary, from a summary.json next to the input
+file or from the events' GenCrossSection attribute, in that order. Without a
+cross section they hold summed event weights.
+
+--detector passes every event through the parametric detector described by a
+JSON detector card (see docs/detector_simulation.md and
+data/detector/hera_like.json); --detector-seed overrides the card's seed.
+
+Analyses:
+  DIS_INCLUSIVE   Q², x, y, W², scattered-electron energy, hadronic
+                  multiplicity and the (x, Q²) plane.
+  DIS_JETS        Breit-frame kT jets (R = 1, E_T > 5 GeV,
+                  -1 < η_lab < 2.5): inclusive E_T and η, jet multiplicity
+                  and the mean dijet E_T.
+  DIS_EVENT_SHAPES
+                  τ_Q, τ_T, broadening, jet mass and C-parameter of the
+                  Breit current hemisphere.
+  DIS_DETECTOR    Q², x and y from the electron, Jacquet-Blondel and Σ
+                  methods with their resolutions; needs --detector.
+
+Defaults:
+  --analysis DIS_INCLUSIVE, all events, no detector simulation.
+";
+
+const UNFOLD_HELP: &str = "Unfold detector-level (x, Q²) spectra with a Monte Carlo response matrix
+
+Usage:
+  quark_sim unfold \\
+      --response <MC.hepmc3[.gz]> \\
+      --data <DATA.hepmc3[.gz]> \\
+      --detector <CARD.json> \\
+      --output <DIRECTORY> \\
+      --x-bins <EDGE,EDGE,...> \\
+      --q2-bins <EDGE,EDGE,...> \\
+      [--method bayes|inversion|bin-by-bin] \\
+      [--iterations <COUNT>] \\
+      [--reconstruction electron|jacquet-blondel|sigma] \\
+      [--data-seed <SEED>] \\
+      [--data-summary <summary.json>] \\
+      [--cross-section <PB>] \\
+      [--max-events <COUNT>]
+
+Both event files are passed through the detector card. The response sample
+fills the response matrix from its generated and reconstructed (x, Q²); the
+data sample is reconstructed only and then unfolded. Using one file for both
+is a closure test. The data sample is smeared with --data-seed, by default
+the card's seed plus one, so its fluctuations differ from the response.
+
+Methods:
+  bayes         Iterative Bayesian (D'Agostini) unfolding from the Monte
+                Carlo prior, with full error propagation.
+  inversion     Inversion of the response matrix.
+  bin-by-bin    Correction factors N_gen / N_rec per bin.
+
+The data cross section converts unfolded weights to pb and to the reduced
+cross section at each bin centre. It is read from --cross-section, from
+--data-summary, from a summary.json next to the data file or from the events'
+GenCrossSection attribute, in that order.
+
+Writes unfolded_bins.csv, response_matrix.csv, unfolded_covariance.csv and
+unfolding.json to the output directory and refuses to overwrite them.
+
+Defaults:
+  --method bayes, --iterations 4, --reconstruction electron, all events.
+";
+
+const REWEIGHT_EVENTS_HELP: &str = "Reweight a HepMC3 event sample to other PDF sets or members
+
+Usage:
+  quark_sim reweight-events \\
+      --input <EVENTS.hepmc3[.gz]> \\
+      --output <DIRECTORY> \\
+      --pdf <SET[/MEMBER]> [--pdf <SET[/MEMBER]> ...] \\
+      [--nominal-pdf <SET[/MEMBER]>] \\
+      [--summary <summary.json>] \\
+      [--cross-section <PB>] \\
+      [--max-events <COUNT>]
+
+Every event needs a GenPdfInfo attribute giving the struck parton's flavour,
+momentum fraction x and factorization scale Q, as written by the PYTHIA
+backend and the native generator. Its weight under a target PDF f' is the
+nominal weight times f'(x, Q²) / f(x, Q²) for that parton, where f is the PDF
+the sample was generated with.
+
+--pdf SET adds every member of an installed LHAPDF set and, for hessian,
+symmhessian and replicas sets, its PDF uncertainty. --pdf SET/MEMBER adds one
+member. The option may be repeated. The nominal PDF is taken from
+--nominal-pdf or from the pdf_set and pdf_member of a metadata.json next to
+the input file.
+
+The output directory receives events.hepmc3 (gzipped when the input is), with
+one extra weight named SET/MEMBER per member, reweighted_cross_sections.csv
+and reweighting_summary.json. Existing files are never overwritten.
+Reweighted cross sections scale the nominal one, read from --cross-section,
+from --summary, from a summary.json next to the input file or from the
+events' GenCrossSection attribute, in that order.
+
+Defaults:
+  --nominal-pdf from metadata.json (member 0 if only a set is given), all
+  events.
+";
+
+const COMPARE_RUNS_HELP: &str = "Compare event-generation and validation runs
+
+Usage:
+  quark_sim compare-runs \\
+      --run <RUN_DIRECTORY> \\
+      --run <RUN_DIRECTORY> \\
+      [--run <RUN_DIRECTORY> ...] \\
+      --output <DIRECTORY> \\
+      [--label <NAME> ...] \\
+      [--bins <COUNT>]
+
+Each run directory is read for its config.json, run_card.json, summary.json
+(or the summary.json of its only subdirectory, as written by validate-hera)
+and, for event generation, inclusive_observables.csv. Configuration and
+summary values are flattened to dotted keys, and every key whose value is not
+the same in all runs is reported; the output and config options of run cards
+are ignored.
+
+The Q², x, y and W² of the runs with inclusive_observables.csv are
+histogrammed with their event weights on a common binning, logarithmic
+except for y, and normalised to unit area. The first of these runs is the
+reference: every other run is compared with it by a χ² of the binned shapes
+and an unbinned two-sample Kolmogorov-Smirnov test, each with its p-value.
+
+The output directory receives run_comparison.json, configuration_diff.csv,
+compatibility_tests.csv, distributions.csv and q2.svg, x.svg, y.svg and
+w2.svg, each with a ratio panel to the reference run, plus run_card.json and
+manifest.json. Existing files are never overwritten.
+
+Defaults:
+  --label the name of each run directory, --bins 40.
+";
+
+const DOCTOR_HELP: &str = "Check the external dependencies of the DIS commands
+
+Usage:
+  quark_sim doctor \\
+      [--apfel-backend <PATH>] \\
+      [--pythia-backend <PATH>] \\
+      [--model-dir <DIRECTORY>] \\
+      [--venv <DIRECTORY>]
+
+Reports the LHAPDF data path and every installed set with its number of
+members, and the git hash, dirty flag, rustc version and platform of this
+build. The APFEL++ and PYTHIA 8 backends are started with a schema-v1
+handshake request, which they reject without computing anything. The
+surrogate model is loaded from its directory, and the Python interpreter of
+the validation scripts must import the packages of analysis/requirements.txt.
+
+With --format json the report is one JSON object; with --format csv it is one
+row per check. The exit status is 0 when no check failed and otherwise that
+of the first failure, e.g. 3 for a missing backend; see docs/cli_output.md.
+
+Defaults:
+  --apfel-backend $APFEL_BACKEND_BIN or physics-engine/build/apfel_cli,
+  --pythia-backend $PYTHIA_BACKEND_BIN or physics-engine/build/pythia_dis_cli,
+  --model-dir models/surrogate_v1, --venv $VIRTUAL_ENV or analysis/venv.
+";
+
+const VERIFY_RUN_HELP: &str = "Check a run directory against its reproducibility manifest
+
+Usage:
+  quark_sim verify-run \\
+      --run <RUN_DIRECTORY> \\
+      [--rerun] \\
+      [--rerun-output <DIRECTORY>] \\
+      [--rel-tol <TOLERANCE>] \\
+      [--abs-tol <TOLERANCE>]
+
+generate-dis-events, validate-hera, theory-uncertainties, train-surrogate,
+validate-surrogate, analyze-events, unfold, reweight-events and compare-runs
+write manifest.json into their run directory. It records the resolved run card, the
+build, the APFEL++, LHAPDF, PYTHIA and HepMC3 versions, the digests of the
+input files and PDF sets, the seeds, the wall time and the size and SHA-256 of
+every artifact; see docs/reproducibility.md.
+
+verify-run reports each artifact, input file and PDF set as ok, modified or
+missing. With --rerun the recorded card is run again by this executable into
+--rerun-output, which must not exist, and every artifact is compared with its
+repetition: identical, within_tolerance when corresponding numbers a and b
+satisfy |a - b| <= abs-tol + rel-tol * max(|a|, |b|) and all other text
+matches, different, or missing. run_card.json, manifest.json and *.log files
+are not compared. Relative input paths of the card are resolved against the
+current directory.
+
+The exit status is 1 with code run_verification_failed when any check fails.
+
+Defaults:
+  --rerun-output <RUN_DIRECTORY>_rerun (implies --rerun), --rel-tol 1e-9,
+  --abs-tol 1e-12.
+";
+
+const PDF_HELP: &str = "Inspect, tabulate and plot installed LHAPDF sets
+
+Usage:
+  quark_sim pdf list
+  quark_sim pdf info --pdf-set <INSTALLED_SET> [--pdf-member <INDEX>]
+  quark_sim pdf table \\
+      --pdf-set <INSTALLED_SET> \\
+      [--pdf-member <INDEX>] \\
+      [--flavours <FLAVOUR,FLAVOUR,...>] \\
+      (--x <X> --q2 <GEV2> | --points <FILE> | --x-grid <MIN:MAX:N> --q2-grid <MIN:MAX:N>) \\
+      [--output <FILE>]
+  quark_sim pdf plot \\
+      --pdf-set <INSTALLED_SET> \\
+      --q2 <GEV2> \\
+      --output <FILE.svg> \\
+      [--pdf-member <INDEX>] \\
+      [--flavours <FLAVOUR,FLAVOUR,...>] \\
+      [--x-grid <MIN:MAX:N>] \\
+      [--uncertainty]
+
+list prints the sets found in the LHAPDF data path with their number of
+members. info prints OrderQCD, NumMembers, ErrorType and DataVersion of a set
+and the x range, Q range and flavours of one of its members.
+
+table prints x f(x, Q²) of each flavour at one point or, like
+dis-cross-section, at every point of a --points file or x/Q² grid, one column
+per flavour (xg, xu, xubar, ..., xuv, xdv).
+
+plot draws x f(x, Q²) against log x at one Q² into a new SVG file. With
+--uncertainty every member of the set is evaluated and the PDF uncertainty of
+a hessian, symmhessian or replicas set is drawn as a band around member 0.
+
+Flavours are g, d, u, s, c, b, dbar, ubar, sbar, cbar, bbar, the valence
+distributions uv = u - ubar and dv = d - dbar, or PDG ids (21 or 0 for the
+gluon). Flavours the set does not provide evaluate to zero.
+
+Defaults:
+  --pdf-member 0, --flavours uv,dv,ubar,dbar,s,g, plot --x-grid from the x
+  range of the set, limited to 1e-5 <= x <= 0.9, with 100 points.
 ";
 
 #[derive(Debug, PartialEq)]
@@ -176,7 +662,15 @@ enum Command {
     StructureFunctions(StructureFunctionsCliArgs),
     ValidateHera(ValidateHeraCliArgs),
     TheoryUncertainties(TheoryUncertaintiesCliArgs),
-    TrainSurrogate(TrainSurrogateCliArgs),
+    TrainSurrogate(TrainSurrogateCommand),
+    ValidateSurrogate(ValidateSurrogateCommand),
+    AnalyzeEvents(AnalyzeEventsCommand),
+    Unfold(UnfoldCommand),
+    ReweightEvents(ReweightEventsCommand),
+    CompareRuns(CompareRunsCommand),
+    Doctor(DoctorCommand),
+    Pdf(PdfCommand),
+    VerifyRun(VerifyRunCommand),
     Help,
 }
 
@@ -188,7 +682,7 @@ enum DisCommand {
 
 #[derive(Debug, PartialEq)]
 enum GenerateDisEventsCommand {
-    Calculate(GenerateDisEventsCliArgs),
+    Calculate(Box<GenerateDisEventsCliArgs>),
     Help,
 }
 
@@ -211,20 +705,88 @@ struct GenerateDisEventsCliArgs {
     pdf_member: i32,
     parton_shower: bool,
     hadronization: bool,
+    /// Raw PYTHIA settings, applied after the generator defaults.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pythia_settings: Vec<String>,
+    #[serde(skip)]
+    generator: DisEventGenerator,
+    #[serde(skip)]
+    weighting: EventWeighting,
+    #[serde(skip)]
+    jobs: usize,
+    #[serde(skip)]
+    retries: usize,
+    #[serde(skip)]
+    progress: ProgressOutput,
     #[serde(skip)]
     output: PathBuf,
 }
 
+/// Event generator behind `generate-dis-events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum DisEventGenerator {
+    #[default]
+    Pythia,
+    Native,
+}
+
+/// `process` of a neutral-current (γ*/Z exchange) DIS request.
+const NEUTRAL_CURRENT_PROCESS: &str = "neutral_current_dis";
+
+/// `process` of a charged-current (W exchange) DIS request.
+const CHARGED_CURRENT_PROCESS: &str = "charged_current_dis";
+
+/// How `generate-dis-events` reports the progress of the PYTHIA backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ProgressOutput {
+    #[default]
+    Bar,
+    Json,
+    None,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 struct StructureFunctionsCliArgs {
     backend: String,
-    x: f64,
-    q2: f64,
+    kinematics: KinematicInput,
     order: String,
     pdf_set: String,
     pdf_member: i32,
     mu_f_over_q: f64,
     mu_r_over_q: f64,
+    max_uncertainty: Option<f64>,
+}
+
+/// The `(x, Q²)` points evaluated by `dis-cross-section` and
+/// `structure-functions`.
+#[derive(Debug, Clone, PartialEq)]
+enum KinematicInput {
+    Point { x: f64, q2: f64 },
+    Batch(BatchCliArgs),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct BatchCliArgs {
+    points: BatchPoints,
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BatchPoints {
+    File(PathBuf),
+    Grid { x: LogGrid, q2: LogGrid },
+}
+
+/// Kinematic options shared by `dis-cross-section` and `structure-functions`,
+/// collected before deciding between a single point and a batch.
+#[derive(Debug, Default)]
+struct KinematicOptions {
+    x: Option<f64>,
+    q2: Option<f64>,
+    points: Option<PathBuf>,
+    x_grid: Option<LogGrid>,
+    q2_grid: Option<LogGrid>,
+    output: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -243,8 +805,7 @@ enum CrossSectionCommand {
 
 #[derive(Debug, Clone, PartialEq)]
 struct CrossSectionCliArgs {
-    x: f64,
-    q2: f64,
+    kinematics: KinematicInput,
     electron_energy: f64,
     proton_energy: f64,
     pdf_set: String,
@@ -273,21 +834,199 @@ struct TheoryUncertaintiesCliArgs {
     output: PathBuf,
 }
 
+#[derive(Debug, PartialEq)]
+enum TrainSurrogateCommand {
+    Calculate(Box<TrainSurrogateCliArgs>),
+    Help,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 struct TrainSurrogateCliArgs {
     pdf_set: String,
     pdf_member: i32,
     order: String,
     output: PathBuf,
+    sampler: DatasetSampler,
+    training: SurrogateTrainingConfig,
+    refinement: Option<RefinementConfig>,
+    resume: bool,
 }
 
-fn main() -> Result<()> {
-    let command = parse_command(std::env::args().skip(1)).map_err(|message| {
-        eprintln!("Error: {message}\n\n{HELP}");
-        Error::Msg(message)
-    })?;
+#[derive(Debug, PartialEq)]
+enum ValidateSurrogateCommand {
+    Calculate(ValidateSurrogateCliArgs),
+    Help,
+}
 
-    match command {
+#[derive(Debug, Clone, PartialEq)]
+struct ValidateSurrogateCliArgs {
+    model_dir: PathBuf,
+    output: PathBuf,
+    validation: SurrogateValidationConfig,
+}
+
+#[derive(Debug, PartialEq)]
+enum AnalyzeEventsCommand {
+    Calculate(AnalyzeEventsCliArgs),
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AnalyzeEventsCliArgs {
+    input: PathBuf,
+    output: PathBuf,
+    analyses: Vec<String>,
+    summary: Option<PathBuf>,
+    cross_section_pb: Option<f64>,
+    max_events: Option<usize>,
+    detector: Option<PathBuf>,
+    detector_seed: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+enum UnfoldCommand {
+    Calculate(Box<UnfoldCliArgs>),
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct UnfoldCliArgs {
+    response: PathBuf,
+    data: PathBuf,
+    detector: PathBuf,
+    output: PathBuf,
+    binning: quark_sim::analysis::KinematicBinning,
+    method: quark_sim::analysis::UnfoldingMethod,
+    reconstruction: quark_sim::analysis::ReconstructionMethod,
+    data_seed: Option<u64>,
+    data_summary: Option<PathBuf>,
+    cross_section_pb: Option<f64>,
+    max_events: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ReweightEventsCommand {
+    Calculate(ReweightEventsCliArgs),
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+enum CompareRunsCommand {
+    Compare(CompareRunsCliArgs),
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct CompareRunsCliArgs {
+    runs: Vec<PathBuf>,
+    labels: Vec<String>,
+    output: PathBuf,
+    bins: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum DoctorCommand {
+    Run(DoctorOptions),
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+enum VerifyRunCommand {
+    Verify(VerifyRunCliArgs),
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct VerifyRunCliArgs {
+    run: PathBuf,
+    /// Where to repeat the run, with `--rerun`.
+    rerun: Option<PathBuf>,
+    tolerances: Tolerances,
+}
+
+#[derive(Debug, PartialEq)]
+enum PdfCommand {
+    List,
+    Info(PdfInfoCliArgs),
+    Table(PdfTableCliArgs),
+    Plot(PdfPlotCliArgs),
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PdfInfoCliArgs {
+    pdf_set: String,
+    pdf_member: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PdfTableCliArgs {
+    pdf_set: String,
+    pdf_member: i32,
+    flavours: Vec<PdfFlavour>,
+    kinematics: KinematicInput,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PdfPlotCliArgs {
+    pdf_set: String,
+    pdf_member: i32,
+    flavours: Vec<PdfFlavour>,
+    q2: f64,
+    x_grid: Option<LogGrid>,
+    uncertainty: bool,
+    output: PathBuf,
+}
+
+/// An LHAPDF set, optionally restricted to one member.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PdfSelection {
+    set_name: String,
+    member: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ReweightEventsCliArgs {
+    input: PathBuf,
+    output: PathBuf,
+    pdfs: Vec<PdfSelection>,
+    nominal_pdf: Option<PdfSelection>,
+    summary: Option<PathBuf>,
+    cross_section_pb: Option<f64>,
+    max_events: Option<usize>,
+}
+
+fn main() {
+    let (format, args) = match OutputFormat::from_arguments(std::env::args().skip(1).collect()) {
+        Ok(parsed) => parsed,
+        Err(error) => exit_with(&error, OutputFormat::Text),
+    };
+    let output_format = format.unwrap_or_default();
+    let (command, card) = match parse_invocation(args) {
+        Ok(parsed) => parsed,
+        Err(error) => exit_with(&error, output_format),
+    };
+    if let Err(error) = run_command(command, card, format) {
+        exit_with(&error, output_format);
+    }
+}
+
+/// Report `error` and exit with its status. Command-line mistakes are
+/// followed by the usage text in text mode.
+fn exit_with(error: &CliError, format: OutputFormat) -> ! {
+    error.report(format);
+    if format == OutputFormat::Text && error.is_usage() {
+        eprintln!("\n{HELP}");
+    }
+    std::process::exit(error.exit_code())
+}
+
+fn run_command(
+    command: Command,
+    card: RunCard,
+    format: Option<OutputFormat>,
+) -> std::result::Result<(), CliError> {
+    let result = match command {
         Command::LaunchGui => gui::launch_dis_gui("QuarkSim"),
         Command::Train => run_training(),
         Command::LoadSession(session_file) => {
@@ -300,49 +1039,228 @@ fn main() -> Result<()> {
                 return Err(Error::Msg(format!(
                     "model configuration not found: {} (normalization values are required)",
                     config_path.display()
-                )));
+                ))
+                .into());
             }
             run_with_pretrained_model(&model_path, &config_path)
         }
-        Command::DisKinematics(DisCommand::Calculate(arguments)) => run_dis_kinematics(arguments),
+        Command::DisKinematics(DisCommand::Calculate(arguments)) => {
+            return run_dis_kinematics(arguments, format.unwrap_or_default())
+        }
         Command::DisKinematics(DisCommand::Help) => {
             print!("{DIS_HELP}");
             Ok(())
         }
         Command::DisCrossSection(CrossSectionCommand::Calculate(arguments)) => {
-            run_dis_cross_section(arguments)
+            return run_dis_cross_section(arguments, format.unwrap_or_default())
         }
         Command::DisCrossSection(CrossSectionCommand::Help) => {
             print!("{CROSS_SECTION_HELP}");
             Ok(())
         }
         Command::GenerateDisEvents(GenerateDisEventsCommand::Calculate(arguments)) => {
-            run_generate_dis_events(arguments)
+            return run_generate_dis_events(*arguments, card)
         }
         Command::GenerateDisEvents(GenerateDisEventsCommand::Help) => {
             print!("{GENERATE_DIS_EVENTS_HELP}");
             Ok(())
         }
         Command::StructureFunctions(arguments) => {
-            run_structure_functions(arguments)
-        }
-        Command::ValidateHera(arguments) => {
-            run_validate_hera(arguments)
+            return run_structure_functions(arguments, format)
         }
+        Command::ValidateHera(arguments) => return run_validate_hera(arguments, &card),
         Command::TheoryUncertainties(arguments) => {
-            run_theory_uncertainties(arguments)
+            return run_theory_uncertainties(arguments, &card)
+        }
+        Command::TrainSurrogate(TrainSurrogateCommand::Calculate(arguments)) => {
+            return run_train_surrogate(*arguments, &card)
+        }
+        Command::TrainSurrogate(TrainSurrogateCommand::Help) => {
+            print!("{TRAIN_SURROGATE_HELP}");
+            Ok(())
+        }
+        Command::ValidateSurrogate(ValidateSurrogateCommand::Calculate(arguments)) => {
+            return run_validate_surrogate(arguments, &card)
+        }
+        Command::ValidateSurrogate(ValidateSurrogateCommand::Help) => {
+            print!("{VALIDATE_SURROGATE_HELP}");
+            Ok(())
+        }
+        Command::AnalyzeEvents(AnalyzeEventsCommand::Calculate(arguments)) => {
+            return run_analyze_events(arguments, &card)
+        }
+        Command::AnalyzeEvents(AnalyzeEventsCommand::Help) => {
+            print!("{ANALYZE_EVENTS_HELP}");
+            Ok(())
         }
-        Command::TrainSurrogate(arguments) => {
-            run_train_surrogate(arguments)
+        Command::Unfold(UnfoldCommand::Calculate(arguments)) => {
+            return run_unfold(*arguments, &card)
+        }
+        Command::Unfold(UnfoldCommand::Help) => {
+            print!("{UNFOLD_HELP}");
+            Ok(())
+        }
+        Command::ReweightEvents(ReweightEventsCommand::Calculate(arguments)) => {
+            return run_reweight_events(arguments, &card)
+        }
+        Command::ReweightEvents(ReweightEventsCommand::Help) => {
+            print!("{REWEIGHT_EVENTS_HELP}");
+            Ok(())
+        }
+        Command::CompareRuns(CompareRunsCommand::Compare(arguments)) => {
+            return run_compare_runs(&arguments, &card)
+        }
+        Command::CompareRuns(CompareRunsCommand::Help) => {
+            print!("{COMPARE_RUNS_HELP}");
+            Ok(())
+        }
+        Command::Doctor(DoctorCommand::Run(options)) => {
+            return run_doctor(&options, format.unwrap_or_default())
+        }
+        Command::Doctor(DoctorCommand::Help) => {
+            print!("{DOCTOR_HELP}");
+            Ok(())
+        }
+        Command::Pdf(command) => return run_pdf(command, format.unwrap_or_default()),
+        Command::VerifyRun(VerifyRunCommand::Verify(arguments)) => {
+            return run_verify_run(&arguments, format.unwrap_or_default())
+        }
+        Command::VerifyRun(VerifyRunCommand::Help) => {
+            print!("{VERIFY_RUN_HELP}");
+            Ok(())
         }
         Command::Help => {
             print!("{HELP}");
             Ok(())
         }
+    };
+    result.map_err(CliError::from)
+}
+
+/// Parse a command line, resolving a `--config` run card first. The returned
+/// card holds every option of a subcommand run; it is empty for the legacy
+/// commands.
+fn parse_invocation(args: Vec<String>) -> std::result::Result<(Command, RunCard), CliError> {
+    if let Some(card) = RunCard::from_config_arguments(&args)? {
+        let command = parse_command(card.command_line())?;
+        return Ok((command, card));
+    }
+    let card = RunCard::from_command_line(&args).unwrap_or_default();
+    let command = parse_command(args)?;
+    Ok((command, card))
+}
+
+/// An invalid value of the option `field`, named as in a run card.
+fn invalid_option(field: &str, message: impl Into<String>) -> CliError {
+    CliError::new(
+        "invalid_option",
+        GuiErrorCategory::InvalidInput,
+        Some(field),
+        message,
+    )
+}
+
+/// An option `flag` that the subcommand does not take, or an argument that is
+/// not an option at all.
+fn unknown_option(flag: &str, message: impl Into<String>) -> CliError {
+    CliError::new(
+        "unknown_option",
+        GuiErrorCategory::InvalidInput,
+        flag.strip_prefix("--"),
+        message,
+    )
+}
+
+/// A required option `flag` that was not given.
+fn missing_option(flag: &str, message: impl Into<String>) -> CliError {
+    CliError::new(
+        "missing_option",
+        GuiErrorCategory::InvalidInput,
+        Some(option_field(flag)),
+        message,
+    )
+}
+
+/// The value of the required option `flag`.
+fn required_option<T>(value: Option<T>, flag: &str) -> std::result::Result<T, CliError> {
+    value.ok_or_else(|| missing_option(flag, format!("missing required option: {flag}")))
+}
+
+/// A subcommand run without any of its options; `usage` says which it needs.
+fn missing_options(usage: impl Into<String>) -> CliError {
+    CliError::new(
+        "missing_option",
+        GuiErrorCategory::InvalidInput,
+        None,
+        usage,
+    )
+}
+
+/// The run-card name of the command-line option `flag`: `pdf-set` for
+/// `--pdf-set`.
+fn option_field(flag: &str) -> &str {
+    flag.strip_prefix("--").unwrap_or(flag)
+}
+
+/// A detector card given with `--detector` that cannot be read.
+fn invalid_detector_card(error: impl std::fmt::Display) -> CliError {
+    CliError::new(
+        "invalid_detector_card",
+        GuiErrorCategory::ParseError,
+        Some("detector"),
+        error.to_string(),
+    )
+}
+
+/// A HepMC3 event file given with the option `field` that cannot be opened.
+fn events_unreadable(field: &str, error: &dyn std::fmt::Display) -> CliError {
+    CliError::new(
+        "events_unreadable",
+        GuiErrorCategory::FileNotFound,
+        Some(field),
+        error.to_string(),
+    )
+}
+
+/// Reject the events given with the option `field` when the `metadata.json`
+/// next to them records a charged-current run. Its final-state lepton is a
+/// neutrino, so the electron-based projections would skip every event. Events
+/// without readable metadata are accepted.
+fn reject_charged_current_events(field: &str, events: &Path) -> std::result::Result<(), CliError> {
+    let metadata = events.with_file_name("metadata.json");
+    let process = std::fs::read_to_string(&metadata)
+        .ok()
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+        .and_then(|metadata| metadata.get("process")?.as_str().map(str::to_owned));
+    if process.as_deref() == Some(CHARGED_CURRENT_PROCESS) {
+        return Err(CliError::new(
+            "unsupported_process",
+            GuiErrorCategory::InvalidInput,
+            Some(field),
+            format!(
+                "{} is from a charged-current run ({}); the analyses reconstruct events \
+                 from the scattered electron, which charged-current events do not have",
+                events.display(),
+                metadata.display()
+            ),
+        ));
     }
+    Ok(())
+}
+
+/// Record the resolved run card in a run's output directory.
+fn write_run_card(card: &RunCard, directory: &Path) -> Result<PathBuf> {
+    card.write(directory).map_err(|e| Error::Msg(e.to_string()))
+}
+
+/// Write the reproducibility manifest once every artifact of a run exists.
+fn write_run_manifest(manifest: ManifestRecorder, directory: &Path) -> Result<PathBuf> {
+    manifest
+        .finish(directory)
+        .map_err(|e| Error::Msg(e.to_string()))
 }
 
-fn parse_command(args: impl IntoIterator<Item = String>) -> std::result::Result<Command, String> {
+fn parse_command(args: impl IntoIterator<Item = String>) -> std::result::Result<Command, CliError> {
     let args: Vec<String> = args.into_iter().collect();
     match args.as_slice() {
         [] => Ok(Command::LaunchGui),
@@ -368,23 +1286,53 @@ fn parse_command(args: impl IntoIterator<Item = String>) -> std::result::Result<
         [subcommand, remaining @ ..] if subcommand == "train-surrogate" => {
             parse_train_surrogate_command(remaining).map(Command::TrainSurrogate)
         }
+        [subcommand, remaining @ ..] if subcommand == "validate-surrogate" => {
+            parse_validate_surrogate_command(remaining).map(Command::ValidateSurrogate)
+        }
+        [subcommand, remaining @ ..] if subcommand == "analyze-events" => {
+            parse_analyze_events_command(remaining).map(Command::AnalyzeEvents)
+        }
+        [subcommand, remaining @ ..] if subcommand == "unfold" => {
+            parse_unfold_command(remaining).map(Command::Unfold)
+        }
+        [subcommand, remaining @ ..] if subcommand == "reweight-events" => {
+            parse_reweight_events_command(remaining).map(Command::ReweightEvents)
+        }
+        [subcommand, remaining @ ..] if subcommand == "compare-runs" => {
+            parse_compare_runs_command(remaining).map(Command::CompareRuns)
+        }
+        [subcommand, remaining @ ..] if subcommand == "doctor" => {
+            parse_doctor_command(remaining).map(Command::Doctor)
+        }
+        [subcommand, remaining @ ..] if subcommand == "pdf" => {
+            parse_pdf_command(remaining).map(Command::Pdf)
+        }
+        [subcommand, remaining @ ..] if subcommand == "verify-run" => {
+            parse_verify_run_command(remaining).map(Command::VerifyRun)
+        }
         [flag, path] if flag == "--load" => Ok(Command::LoadSession(PathBuf::from(path))),
         [flag, path] if flag == "--load-model" => Ok(Command::LoadModel(PathBuf::from(path))),
-        [flag] if flag == "--load" || flag == "--load-model" => {
-            Err(format!("{flag} requires a file path"))
-        }
-        _ => Err(format!("unrecognized arguments: {}", args.join(" "))),
+        [flag] if flag == "--load" || flag == "--load-model" => Err(invalid_option(
+            option_field(flag),
+            format!("{flag} requires a file path"),
+        )),
+        _ => Err(CliError::new(
+            "unknown_command",
+            GuiErrorCategory::InvalidInput,
+            None,
+            format!("unrecognized arguments: {}", args.join(" ")),
+        )),
     }
 }
 
-fn parse_dis_command(args: &[String]) -> std::result::Result<DisCommand, String> {
+fn parse_dis_command(args: &[String]) -> std::result::Result<DisCommand, CliError> {
     if matches!(args, [flag] if flag == "-h" || flag == "--help") {
         return Ok(DisCommand::Help);
     }
     if args.is_empty() {
-        return Err(format!(
+        return Err(missing_options(format!(
             "dis-kinematics requires four options\n\n{DIS_HELP}"
-        ));
+        )));
     }
 
     let mut electron_energy = None;
@@ -401,22 +1349,40 @@ fn parse_dis_command(args: &[String]) -> std::result::Result<DisCommand, String>
             "--scattered-electron-energy" => &mut scattered_electron_energy,
             "--theta-deg" => &mut theta_deg,
             "-h" | "--help" => {
-                return Err("--help must be used by itself after dis-kinematics".to_string())
+                return Err(invalid_option(
+                    "help",
+                    "--help must be used by itself after dis-kinematics",
+                ))
+            }
+            _ => {
+                return Err(unknown_option(
+                    flag,
+                    format!("unknown dis-kinematics option: {flag}"),
+                ))
             }
-            _ => return Err(format!("unknown dis-kinematics option: {flag}")),
         };
+        let field = option_field(flag);
         let value_text = args
             .get(index + 1)
-            .ok_or_else(|| format!("{flag} requires a numeric value"))?;
+            .ok_or_else(|| invalid_option(field, format!("{flag} requires a numeric value")))?;
         if value_text.starts_with("--") {
-            return Err(format!("{flag} requires a numeric value"));
+            return Err(invalid_option(
+                field,
+                format!("{flag} requires a numeric value"),
+            ));
         }
-        let value = value_text
-            .parse::<f64>()
-            .map_err(|_| format!("invalid numeric value for {flag}: {value_text}"))?;
+        let value = value_text.parse::<f64>().map_err(|_| {
+            invalid_option(
+                field,
+                format!("invalid numeric value for {flag}: {value_text}"),
+            )
+        })?;
 
         if slot.replace(value).is_some() {
-            return Err(format!("duplicate dis-kinematics option: {flag}"));
+            return Err(invalid_option(
+                field,
+                format!("duplicate dis-kinematics option: {flag}"),
+            ));
         }
         index += 2;
     }
@@ -435,24 +1401,28 @@ fn parse_dis_command(args: &[String]) -> std::result::Result<DisCommand, String>
 fn required_dis_option(
     value: Option<f64>,
     option: &'static str,
-) -> std::result::Result<f64, String> {
-    value.ok_or_else(|| format!("missing required dis-kinematics option: {option}"))
+) -> std::result::Result<f64, CliError> {
+    value.ok_or_else(|| {
+        missing_option(
+            option,
+            format!("missing required dis-kinematics option: {option}"),
+        )
+    })
 }
 
 fn parse_cross_section_command(
     args: &[String],
-) -> std::result::Result<CrossSectionCommand, String> {
+) -> std::result::Result<CrossSectionCommand, CliError> {
     if matches!(args, [flag] if flag == "-h" || flag == "--help") {
         return Ok(CrossSectionCommand::Help);
     }
     if args.is_empty() {
-        return Err(format!(
+        return Err(missing_options(format!(
             "dis-cross-section requires six options\n\n{CROSS_SECTION_HELP}"
-        ));
+        )));
     }
 
-    let mut x = None;
-    let mut q2 = None;
+    let mut kinematics = KinematicOptions::default();
     let mut electron_energy = None;
     let mut proton_energy = None;
     let mut pdf_set = None;
@@ -462,24 +1432,63 @@ fn parse_cross_section_command(
     while index < args.len() {
         let flag = args[index].as_str();
         if flag == "-h" || flag == "--help" {
-            return Err("--help must be used by itself after dis-cross-section".to_string());
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after dis-cross-section",
+            ));
         }
         if !matches!(
             flag,
-            "--x" | "--q2" | "--electron-energy" | "--proton-energy" | "--pdf-set" | "--pdf-member"
+            "--x"
+                | "--q2"
+                | "--points"
+                | "--x-grid"
+                | "--q2-grid"
+                | "--output"
+                | "--electron-energy"
+                | "--proton-energy"
+                | "--pdf-set"
+                | "--pdf-member"
         ) {
-            return Err(format!("unknown dis-cross-section option: {flag}"));
+            return Err(unknown_option(
+                flag,
+                format!("unknown dis-cross-section option: {flag}"),
+            ));
         }
 
         let value_text = args
             .get(index + 1)
             .filter(|value| !value.starts_with("--"))
-            .ok_or_else(|| format!("{flag} requires a value"))?;
+            .ok_or_else(|| {
+                invalid_option(option_field(flag), format!("{flag} requires a value"))
+            })?;
 
         match flag {
-            "--x" => set_cross_option(&mut x, parse_finite_cross_number(flag, value_text)?, flag)?,
-            "--q2" => {
-                set_cross_option(&mut q2, parse_finite_cross_number(flag, value_text)?, flag)?
+            "--x" => set_cross_option(
+                &mut kinematics.x,
+                parse_finite_cross_number(flag, value_text)?,
+                flag,
+            )?,
+            "--q2" => set_cross_option(
+                &mut kinematics.q2,
+                parse_finite_cross_number(flag, value_text)?,
+                flag,
+            )?,
+            "--points" => {
+                set_cross_option(&mut kinematics.points, PathBuf::from(value_text), flag)?
+            }
+            "--x-grid" => set_cross_option(
+                &mut kinematics.x_grid,
+                parse_log_grid(flag, value_text)?,
+                flag,
+            )?,
+            "--q2-grid" => set_cross_option(
+                &mut kinematics.q2_grid,
+                parse_log_grid(flag, value_text)?,
+                flag,
+            )?,
+            "--output" => {
+                set_cross_option(&mut kinematics.output, PathBuf::from(value_text), flag)?
             }
             "--electron-energy" => set_cross_option(
                 &mut electron_energy,
@@ -494,18 +1503,12 @@ fn parse_cross_section_command(
             "--pdf-set" => {
                 let value = value_text.trim();
                 if value.is_empty() {
-                    return Err("--pdf-set must not be empty".to_string());
+                    return Err(invalid_option("pdf-set", "--pdf-set must not be empty"));
                 }
                 set_cross_option(&mut pdf_set, value.to_owned(), flag)?;
             }
             "--pdf-member" => {
-                let value = value_text.parse::<i32>().map_err(|_| {
-                    format!("invalid non-negative integer for {flag}: {value_text}")
-                })?;
-                if value < 0 {
-                    return Err(format!("{flag} must be non-negative, got {value}"));
-                }
-                set_cross_option(&mut pdf_member, value, flag)?;
+                set_cross_option(&mut pdf_member, parse_pdf_member(flag, value_text)?, flag)?;
             }
             _ => unreachable!("supported options were checked above"),
         }
@@ -513,8 +1516,12 @@ fn parse_cross_section_command(
     }
 
     Ok(CrossSectionCommand::Calculate(CrossSectionCliArgs {
-        x: required_cross_option(x, "--x")?,
-        q2: required_cross_option(q2, "--q2")?,
+        kinematics: kinematics.resolve(|option| {
+            missing_option(
+                option,
+                format!("missing required dis-cross-section option: {option}"),
+            )
+        })?,
         electron_energy: required_cross_option(electron_energy, "--electron-energy")?,
         proton_energy: required_cross_option(proton_energy, "--proton-energy")?,
         pdf_set: required_cross_option(pdf_set, "--pdf-set")?,
@@ -522,12 +1529,35 @@ fn parse_cross_section_command(
     }))
 }
 
-fn parse_finite_cross_number(flag: &str, value_text: &str) -> std::result::Result<f64, String> {
-    let value = value_text
-        .parse::<f64>()
-        .map_err(|_| format!("invalid numeric value for {flag}: {value_text}"))?;
+fn parse_finite_cross_number(flag: &str, value_text: &str) -> std::result::Result<f64, CliError> {
+    let value = value_text.parse::<f64>().map_err(|_| {
+        invalid_option(
+            option_field(flag),
+            format!("invalid numeric value for {flag}: {value_text}"),
+        )
+    })?;
     if !value.is_finite() {
-        return Err(format!("{flag} must be finite, got {value_text}"));
+        return Err(invalid_option(
+            option_field(flag),
+            format!("{flag} must be finite, got {value_text}"),
+        ));
+    }
+    Ok(value)
+}
+
+/// A PDF member given with `flag`, which must be a non-negative integer.
+fn parse_pdf_member(flag: &str, value_text: &str) -> std::result::Result<i32, CliError> {
+    let value = value_text.parse::<i32>().map_err(|_| {
+        invalid_option(
+            option_field(flag),
+            format!("invalid non-negative integer for {flag}: {value_text}"),
+        )
+    })?;
+    if value < 0 {
+        return Err(invalid_option(
+            option_field(flag),
+            format!("{flag} must be non-negative, got {value}"),
+        ));
     }
     Ok(value)
 }
@@ -536,26 +1566,108 @@ fn set_cross_option<T>(
     slot: &mut Option<T>,
     value: T,
     flag: &str,
-) -> std::result::Result<(), String> {
+) -> std::result::Result<(), CliError> {
     if slot.replace(value).is_some() {
-        Err(format!("duplicate dis-cross-section option: {flag}"))
+        Err(invalid_option(
+            option_field(flag),
+            format!("duplicate dis-cross-section option: {flag}"),
+        ))
     } else {
         Ok(())
     }
 }
 
-fn required_cross_option<T>(value: Option<T>, option: &str) -> std::result::Result<T, String> {
-    value.ok_or_else(|| format!("missing required dis-cross-section option: {option}"))
+fn required_cross_option<T>(value: Option<T>, option: &str) -> std::result::Result<T, CliError> {
+    value.ok_or_else(|| {
+        missing_option(
+            option,
+            format!("missing required dis-cross-section option: {option}"),
+        )
+    })
+}
+
+fn parse_log_grid(flag: &str, value_text: &str) -> std::result::Result<LogGrid, CliError> {
+    value_text.parse().map_err(|error| {
+        invalid_option(
+            option_field(flag),
+            format!("invalid value for {flag}: {error}"),
+        )
+    })
 }
 
-fn run_dis_kinematics(arguments: DisCliArgs) -> Result<()> {
-    let beams = collider_beams(arguments.electron_energy, arguments.proton_energy)
-        .map_err(|error| Error::Msg(error.to_string()))?;
-    let outgoing = scattered_electron(arguments.scattered_electron_energy, arguments.theta_deg)
-        .map_err(|error| Error::Msg(error.to_string()))?;
-    let event = compute_dis_kinematics(beams.proton, beams.electron, outgoing)
-        .map_err(|error| Error::Msg(error.to_string()))?;
+impl KinematicOptions {
+    /// A single point from `--x` and `--q2`, or a batch from `--points` or
+    /// the `--x-grid`/`--q2-grid` pair. `missing` formats the error for a
+    /// missing single-point option.
+    fn resolve(
+        self,
+        missing: impl Fn(&str) -> CliError,
+    ) -> std::result::Result<KinematicInput, CliError> {
+        let grid = self.x_grid.is_some() || self.q2_grid.is_some();
+        if self.points.is_some() && grid {
+            return Err(invalid_option(
+                "points",
+                "--points cannot be combined with --x-grid or --q2-grid",
+            ));
+        }
+        if self.points.is_none() && !grid {
+            if self.output.is_some() {
+                return Err(invalid_option(
+                    "output",
+                    "--output requires --points or --x-grid and --q2-grid",
+                ));
+            }
+            return Ok(KinematicInput::Point {
+                x: self.x.ok_or_else(|| missing("--x"))?,
+                q2: self.q2.ok_or_else(|| missing("--q2"))?,
+            });
+        }
+        if self.x.is_some() || self.q2.is_some() {
+            let field = if self.x.is_some() { "x" } else { "q2" };
+            return Err(invalid_option(
+                field,
+                "--x and --q2 cannot be combined with --points or a grid",
+            ));
+        }
+        let points = match (self.points, self.x_grid, self.q2_grid) {
+            (Some(path), _, _) => BatchPoints::File(path),
+            (None, Some(x), Some(q2)) => BatchPoints::Grid { x, q2 },
+            (None, x, _) => {
+                let absent = if x.is_none() { "--x-grid" } else { "--q2-grid" };
+                return Err(missing_option(
+                    absent,
+                    "--x-grid and --q2-grid must be given together",
+                ));
+            }
+        };
+        Ok(KinematicInput::Batch(BatchCliArgs {
+            points,
+            output: self.output,
+        }))
+    }
+}
 
+fn run_dis_kinematics(
+    arguments: DisCliArgs,
+    format: OutputFormat,
+) -> std::result::Result<(), CliError> {
+    let beams = collider_beams(arguments.electron_energy, arguments.proton_energy)?;
+    let outgoing = scattered_electron(arguments.scattered_electron_energy, arguments.theta_deg)?;
+    let event = compute_dis_kinematics(beams.proton, beams.electron, outgoing)?;
+
+    if format != OutputFormat::Text {
+        print_record(
+            format,
+            &[
+                ("s_gev2", event.s.into()),
+                ("q2_gev2", event.q2.into()),
+                ("x", event.x.into()),
+                ("y", event.y.into()),
+                ("w2_gev2", event.w2.into()),
+            ],
+        )?;
+        return Ok(());
+    }
     println!("Neutral-current inclusive e⁻p DIS kinematics");
     println!("s   = {:.12} GeV²", event.s);
     println!("Q²  = {:.12} GeV²", event.q2);
@@ -565,30 +1677,90 @@ fn run_dis_kinematics(arguments: DisCliArgs) -> Result<()> {
     Ok(())
 }
 
-fn run_dis_cross_section(arguments: CrossSectionCliArgs) -> Result<()> {
-    let beams = collider_beams(arguments.electron_energy, arguments.proton_energy)
-        .map_err(|error| Error::Msg(error.to_string()))?;
+fn run_dis_cross_section(
+    arguments: CrossSectionCliArgs,
+    format: OutputFormat,
+) -> std::result::Result<(), CliError> {
+    let beams = collider_beams(arguments.electron_energy, arguments.proton_energy)?;
     let s = (beams.proton + beams.electron).mass_squared();
 
+    let (x, q2) = match arguments.kinematics {
+        KinematicInput::Point { x, q2 } => (x, q2),
+        KinematicInput::Batch(batch) => {
+            let provider = LhapdfProvider::new(arguments.pdf_set, arguments.pdf_member)?;
+            return run_batch(&batch, format, &CROSS_SECTION_BATCH_COLUMNS, |point| {
+                exact_inelasticity(point.x, point.q2, s).map_err(|error| error.to_string())?;
+                let structure_functions =
+                    evaluate_lo_structure_functions(&provider, point.x, point.q2)
+                        .map_err(|error| error.to_string())?;
+                let result = lo_differential_cross_section(
+                    point.x,
+                    point.q2,
+                    s,
+                    structure_functions.f2,
+                    &FixedAlpha::default(),
+                )
+                .map_err(|error| error.to_string())?;
+                Ok(vec![
+                    Some(result.y),
+                    Some(result.f2),
+                    Some(result.fl),
+                    Some(result.xf3),
+                    Some(result.d2sigma_dx_dq2_gev_minus4),
+                    Some(result.d2sigma_dx_dq2_pb_per_gev2),
+                ])
+            });
+        }
+    };
+
     // Reject an unphysical beam/point combination before loading or querying a
     // potentially expensive native PDF grid.
-    exact_inelasticity(arguments.x, arguments.q2, s)
-        .map_err(|error| Error::Msg(error.to_string()))?;
-
-    let provider = LhapdfProvider::new(arguments.pdf_set, arguments.pdf_member)
-        .map_err(|error| Error::Msg(error.to_string()))?;
-    let structure_functions = evaluate_lo_structure_functions(&provider, arguments.x, arguments.q2)
-        .map_err(|error| Error::Msg(error.to_string()))?;
-    let result = lo_differential_cross_section(
-        arguments.x,
-        arguments.q2,
-        s,
-        structure_functions.f2,
-        &FixedAlpha::default(),
-    )
-    .map_err(|error| Error::Msg(error.to_string()))?;
+    exact_inelasticity(x, q2, s)?;
+
+    let provider = LhapdfProvider::new(arguments.pdf_set, arguments.pdf_member)?;
+    let structure_functions = evaluate_lo_structure_functions(&provider, x, q2)?;
+    let result =
+        lo_differential_cross_section(x, q2, s, structure_functions.f2, &FixedAlpha::default())?;
     let densities = structure_functions.densities;
 
+    if format != OutputFormat::Text {
+        print_record(
+            format,
+            &[
+                ("pdf_set", provider.set_name().into()),
+                ("pdf_member", provider.member().into()),
+                ("x", result.x.into()),
+                ("q2_gev2", result.q2.into()),
+                ("s_gev2", result.s.into()),
+                ("y", result.y.into()),
+                ("y_plus", result.y_plus.into()),
+                ("xg", densities.gluon.into()),
+                ("xu", densities.up.into()),
+                ("xubar", densities.anti_up.into()),
+                ("xd", densities.down.into()),
+                ("xdbar", densities.anti_down.into()),
+                ("xs", densities.strange.into()),
+                ("xsbar", densities.anti_strange.into()),
+                ("xc", densities.charm.into()),
+                ("xcbar", densities.anti_charm.into()),
+                ("xb", densities.bottom.into()),
+                ("xbbar", densities.anti_bottom.into()),
+                ("f2", result.f2.into()),
+                ("fl", result.fl.into()),
+                ("xf3", result.xf3.into()),
+                ("alpha", result.alpha.into()),
+                (
+                    "d2sigma_dx_dq2_gev_minus4",
+                    result.d2sigma_dx_dq2_gev_minus4.into(),
+                ),
+                (
+                    "d2sigma_dx_dq2_pb_per_gev2",
+                    result.d2sigma_dx_dq2_pb_per_gev2.into(),
+                ),
+            ],
+        )?;
+        return Ok(());
+    }
     println!("Leading-order electromagnetic neutral-current e⁻p DIS");
     println!(
         "PDF set/member: {}/{}",
@@ -627,6 +1799,71 @@ fn run_dis_cross_section(arguments: CrossSectionCliArgs) -> Result<()> {
     Ok(())
 }
 
+/// Value columns of a `dis-cross-section` batch.
+const CROSS_SECTION_BATCH_COLUMNS: [&str; 6] = [
+    "y",
+    "f2",
+    "fl",
+    "xf3",
+    "d2sigma_dx_dq2_gev_minus4",
+    "d2sigma_dx_dq2_pb_per_gev2",
+];
+
+/// Value columns of a `structure-functions` batch. The uncertainties are only
+/// filled by the surrogate ensemble.
+const STRUCTURE_FUNCTION_BATCH_COLUMNS: [&str; 6] = [
+    "f2",
+    "fl",
+    "xf3",
+    "f2_uncertainty",
+    "fl_uncertainty",
+    "xf3_uncertainty",
+];
+
+/// Evaluate every point of a batch with `evaluate` and stream one row per
+/// point. A failed point is reported in its row; only reading the points and
+/// writing the output end the batch early.
+fn run_batch(
+    batch: &BatchCliArgs,
+    format: OutputFormat,
+    columns: &[&'static str],
+    mut evaluate: impl FnMut(KinematicPoint) -> std::result::Result<Vec<Option<f64>>, String>,
+) -> std::result::Result<(), CliError> {
+    let points = match &batch.points {
+        BatchPoints::File(path) => read_points(path)?,
+        BatchPoints::Grid { x, q2 } => grid_points(x, q2),
+    };
+    let output: Box<dyn std::io::Write> = match &batch.output {
+        Some(path) => Box::new(
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .map_err(|error| {
+                    CliError::new(
+                        "output_unwritable",
+                        GuiErrorCategory::InvalidInput,
+                        Some("output"),
+                        format!("failed to create '{}': {error}", path.display()),
+                    )
+                })?,
+        ),
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    let mut writer = BatchWriter::new(output, format.into(), columns)?;
+    for point in points {
+        let values = evaluate(point);
+        writer.write(point, values.as_deref().map_err(String::as_str))?;
+    }
+    let (_, summary) = writer.finish()?;
+    eprintln!(
+        "Evaluated {} points, {} failed",
+        summary.points, summary.failures
+    );
+    Ok(())
+}
+
 fn model_config_path(model_path: &Path) -> Result<PathBuf> {
     let stem = model_path
         .file_stem()
@@ -800,14 +2037,14 @@ fn run_with_pretrained_model(model_path: &Path, config_path: &Path) -> Result<()
 
 fn parse_generate_dis_events_command(
     args: &[String],
-) -> std::result::Result<GenerateDisEventsCommand, String> {
+) -> std::result::Result<GenerateDisEventsCommand, CliError> {
     if matches!(args, [flag] if flag == "-h" || flag == "--help") {
         return Ok(GenerateDisEventsCommand::Help);
     }
     if args.is_empty() {
-        return Err(format!(
+        return Err(missing_options(format!(
             "generate-dis-events requires parameters\n\n{GENERATE_DIS_EVENTS_HELP}"
-        ));
+        )));
     }
 
     let mut electron_energy = None;
@@ -824,19 +2061,31 @@ fn parse_generate_dis_events_command(
     let mut pdf_member = Some(0);
     let mut parton_shower = Some(true);
     let mut hadronization = Some(true);
+    let mut generator = DisEventGenerator::Pythia;
+    let mut process = NEUTRAL_CURRENT_PROCESS;
+    let mut pythia_settings = Vec::new();
+    let mut pythia_cmnd = None;
+    let mut weighting = EventWeighting::Unweighted;
+    let mut jobs = 1;
+    let mut retries = DEFAULT_CHUNK_RETRIES;
+    let mut progress = ProgressOutput::Bar;
     let mut output = None;
 
     let mut index = 0;
     while index < args.len() {
         let flag = args[index].as_str();
         if flag == "-h" || flag == "--help" {
-            return Err("--help must be used by itself after generate-dis-events".to_string());
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after generate-dis-events",
+            ));
         }
 
+        let field = option_field(flag);
         let value_text = args
             .get(index + 1)
             .filter(|value| !value.starts_with("--"))
-            .ok_or_else(|| format!("{flag} requires a value"))?;
+            .ok_or_else(|| invalid_option(field, format!("{flag} requires a value")))?;
 
         match flag {
             "--electron-energy" => {
@@ -850,70 +2099,181 @@ fn parse_generate_dis_events_command(
             "--y-min" => y_min = Some(parse_finite_cross_number(flag, value_text)?),
             "--y-max" => y_max = Some(parse_finite_cross_number(flag, value_text)?),
             "--events" => {
-                let val = value_text
-                    .parse::<usize>()
-                    .map_err(|_| format!("invalid positive integer for {flag}: {value_text}"))?;
+                let val = value_text.parse::<usize>().map_err(|_| {
+                    invalid_option(
+                        field,
+                        format!("invalid positive integer for {flag}: {value_text}"),
+                    )
+                })?;
                 events = Some(val);
             }
             "--seed" => {
-                let val = value_text
-                    .parse::<i32>()
-                    .map_err(|_| format!("invalid integer for {flag}: {value_text}"))?;
+                let val = value_text.parse::<i32>().map_err(|_| {
+                    invalid_option(field, format!("invalid integer for {flag}: {value_text}"))
+                })?;
                 seed = Some(val);
             }
             "--pdf-set" => {
                 let val = value_text.trim();
                 if val.is_empty() {
-                    return Err("--pdf-set must not be empty".to_string());
+                    return Err(invalid_option(field, "--pdf-set must not be empty"));
                 }
                 pdf_set = Some(val.to_owned());
             }
-            "--pdf-member" => {
-                let val = value_text.parse::<i32>().map_err(|_| {
-                    format!("invalid non-negative integer for {flag}: {value_text}")
-                })?;
-                if val < 0 {
-                    return Err(format!("{flag} must be non-negative, got {val}"));
-                }
-                pdf_member = Some(val);
-            }
+            "--pdf-member" => pdf_member = Some(parse_pdf_member(flag, value_text)?),
             "--parton-shower" => {
-                let val = value_text
-                    .parse::<bool>()
-                    .map_err(|_| format!("invalid boolean for {flag}: {value_text}"))?;
+                let val = value_text.parse::<bool>().map_err(|_| {
+                    invalid_option(field, format!("invalid boolean for {flag}: {value_text}"))
+                })?;
                 parton_shower = Some(val);
             }
             "--hadronization" => {
-                let val = value_text
-                    .parse::<bool>()
-                    .map_err(|_| format!("invalid boolean for {flag}: {value_text}"))?;
+                let val = value_text.parse::<bool>().map_err(|_| {
+                    invalid_option(field, format!("invalid boolean for {flag}: {value_text}"))
+                })?;
                 hadronization = Some(val);
             }
+            "--generator" => {
+                generator = match value_text.as_str() {
+                    "pythia" => DisEventGenerator::Pythia,
+                    "native" => DisEventGenerator::Native,
+                    _ => {
+                        return Err(invalid_option(
+                            field,
+                            format!(
+                                "invalid value for {flag}: {value_text} \
+                                 (expected pythia or native)"
+                            ),
+                        ))
+                    }
+                };
+            }
+            "--process" => {
+                process = match value_text.as_str() {
+                    "nc" => NEUTRAL_CURRENT_PROCESS,
+                    "cc" => CHARGED_CURRENT_PROCESS,
+                    _ => {
+                        return Err(invalid_option(
+                            field,
+                            format!("invalid value for {flag}: {value_text} (expected nc or cc)"),
+                        ))
+                    }
+                };
+            }
+            "--pythia-setting" => {
+                check_setting(value_text)?;
+                pythia_settings.push(value_text.trim().to_owned());
+            }
+            "--pythia-cmnd" => pythia_cmnd = Some(PathBuf::from(value_text)),
+            "--weighting" => {
+                weighting = value_text
+                    .parse::<EventWeighting>()
+                    .map_err(|source| invalid_option(field, source.to_string()))?;
+            }
+            "--jobs" => {
+                jobs = value_text
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&val| val > 0)
+                    .ok_or_else(|| {
+                        invalid_option(
+                            field,
+                            format!("invalid positive integer for {flag}: {value_text}"),
+                        )
+                    })?;
+            }
+            "--retries" => {
+                retries = value_text.parse::<usize>().map_err(|_| {
+                    invalid_option(
+                        field,
+                        format!("invalid non-negative integer for {flag}: {value_text}"),
+                    )
+                })?;
+            }
+            "--progress" => {
+                progress = match value_text.as_str() {
+                    "bar" => ProgressOutput::Bar,
+                    "json" => ProgressOutput::Json,
+                    "none" => ProgressOutput::None,
+                    _ => {
+                        return Err(invalid_option(
+                            field,
+                            format!(
+                                "invalid value for {flag}: {value_text} \
+                                 (expected bar, json or none)"
+                            ),
+                        ))
+                    }
+                };
+            }
             "--output" => {
                 let val = value_text.trim();
                 if val.is_empty() {
-                    return Err("--output must not be empty".to_string());
+                    return Err(invalid_option(field, "--output must not be empty"));
                 }
                 output = Some(PathBuf::from(val));
             }
-            _ => return Err(format!("unknown generate-dis-events option: {flag}")),
+            _ => {
+                return Err(unknown_option(
+                    flag,
+                    format!("unknown generate-dis-events option: {flag}"),
+                ))
+            }
         }
         index += 2;
     }
 
-    let electron_energy =
-        electron_energy.ok_or_else(|| "missing required option: --electron-energy".to_string())?;
-    let proton_energy =
-        proton_energy.ok_or_else(|| "missing required option: --proton-energy".to_string())?;
-    let q2_min = q2_min.ok_or_else(|| "missing required option: --q2-min".to_string())?;
-    let events = events.ok_or_else(|| "missing required option: --events".to_string())?;
-    let pdf_set = pdf_set.ok_or_else(|| "missing required option: --pdf-set".to_string())?;
-    let output = output.ok_or_else(|| "missing required option: --output".to_string())?;
+    let electron_energy = required_option(electron_energy, "--electron-energy")?;
+    let proton_energy = required_option(proton_energy, "--proton-energy")?;
+    let q2_min = required_option(q2_min, "--q2-min")?;
+    let events = required_option(events, "--events")?;
+    let pdf_set = required_option(pdf_set, "--pdf-set")?;
+    let output = required_option(output, "--output")?;
+    if jobs > 1 && generator == DisEventGenerator::Native {
+        return Err(invalid_option("jobs", "--jobs requires --generator pythia"));
+    }
+    if generator == DisEventGenerator::Native {
+        if process != NEUTRAL_CURRENT_PROCESS {
+            return Err(invalid_option(
+                "process",
+                "--process cc requires --generator pythia",
+            ));
+        }
+        if !pythia_settings.is_empty() || pythia_cmnd.is_some() {
+            let field = if pythia_settings.is_empty() {
+                "pythia-cmnd"
+            } else {
+                "pythia-setting"
+            };
+            return Err(invalid_option(
+                field,
+                "--pythia-setting and --pythia-cmnd require --generator pythia",
+            ));
+        }
+        if seed.is_some_and(|seed| seed < 0) {
+            return Err(invalid_option(
+                "seed",
+                "--seed must be non-negative with --generator native",
+            ));
+        }
+    }
+    // Settings given one by one override those of the command file.
+    if let Some(path) = pythia_cmnd {
+        let mut settings = read_command_file(&path)?;
+        settings.append(&mut pythia_settings);
+        pythia_settings = settings;
+    }
+    if jobs > 1 && seed.is_some_and(|seed| seed < 0) {
+        return Err(invalid_option(
+            "seed",
+            "--seed must be non-negative with --jobs",
+        ));
+    }
 
-    Ok(GenerateDisEventsCommand::Calculate(
+    Ok(GenerateDisEventsCommand::Calculate(Box::new(
         GenerateDisEventsCliArgs {
-            schema_version: 1,
-            process: "neutral_current_dis".to_string(),
+            schema_version: PYTHIA_SCHEMA_VERSION,
+            process: process.to_string(),
             electron_energy_gev: electron_energy,
             proton_energy_gev: proton_energy,
             q2_min_gev2: q2_min,
@@ -928,34 +2288,56 @@ fn parse_generate_dis_events_command(
             pdf_member: pdf_member.unwrap_or(0),
             parton_shower: parton_shower.unwrap_or(true),
             hadronization: hadronization.unwrap_or(true),
+            pythia_settings,
+            generator,
+            weighting,
+            jobs,
+            retries,
+            progress,
             output,
         },
-    ))
+    )))
 }
 
-fn run_generate_dis_events(arguments: GenerateDisEventsCliArgs) -> Result<()> {
-    if arguments.electron_energy_gev <= 0.0 || arguments.proton_energy_gev <= 0.0 {
-        return Err(Error::Msg(
-            "incoming beam energies must be positive".to_string(),
+fn run_generate_dis_events(
+    mut arguments: GenerateDisEventsCliArgs,
+    mut card: RunCard,
+) -> std::result::Result<(), CliError> {
+    if arguments.electron_energy_gev <= 0.0 {
+        return Err(invalid_option(
+            "electron-energy",
+            "incoming beam energies must be positive",
+        ));
+    }
+    if arguments.proton_energy_gev <= 0.0 {
+        return Err(invalid_option(
+            "proton-energy",
+            "incoming beam energies must be positive",
         ));
     }
     if arguments.q2_min_gev2 <= 0.0 || arguments.q2_max_gev2 <= arguments.q2_min_gev2 {
-        return Err(Error::Msg(
-            "invalid Q2 cuts: q2-min must be positive and less than q2-max".to_string(),
+        return Err(invalid_option(
+            "q2-min",
+            "invalid Q2 cuts: q2-min must be positive and less than q2-max",
         ));
     }
     if arguments.x_min <= 0.0 || arguments.x_max <= arguments.x_min || arguments.x_max >= 1.0 {
-        return Err(Error::Msg(
-            "invalid x cuts: x-min must be positive and less than x-max".to_string(),
+        return Err(invalid_option(
+            "x-min",
+            "invalid x cuts: x-min must be positive and less than x-max",
         ));
     }
     if arguments.y_min <= 0.0 || arguments.y_max <= arguments.y_min || arguments.y_max >= 1.0 {
-        return Err(Error::Msg(
-            "invalid y cuts: y-min must be positive and less than y-max".to_string(),
+        return Err(invalid_option(
+            "y-min",
+            "invalid y cuts: y-min must be positive and less than y-max",
         ));
     }
     if arguments.number_of_events == 0 {
-        return Err(Error::Msg("number of events must be positive".to_string()));
+        return Err(invalid_option(
+            "events",
+            "number of events must be positive",
+        ));
     }
 
     let timestamp = Utc::now().format("%Y%m%d_%H%M%S").to_string();
@@ -964,6 +2346,24 @@ fn run_generate_dis_events(arguments: GenerateDisEventsCliArgs) -> Result<()> {
     std::fs::create_dir_all(&run_dir).map_err(Error::wrap)?;
     println!("Output directory: {}", run_dir.display());
 
+    // Draw the seed here, in PYTHIA's range, so the run card repeats the run.
+    let seed = *arguments
+        .random_seed
+        .get_or_insert_with(|| rand::Rng::gen_range(&mut rand::thread_rng(), 0..900_000_000));
+    card.set_option("seed", seed);
+    let mut manifest = ManifestRecorder::start(&card);
+    // A negative seed leaves the choice to PYTHIA; metadata.json records it.
+    if let Ok(seed) = u64::try_from(seed) {
+        manifest.record_seed("seed", seed);
+    }
+    write_run_card(&card, &run_dir)?;
+
+    if arguments.generator == DisEventGenerator::Native {
+        run_native_dis_generator(&arguments, &run_dir)?;
+        write_run_manifest(manifest, &run_dir)?;
+        return Ok(());
+    }
+
     let config_path = run_dir.join("config.json");
     let config_json = serde_json::to_string_pretty(&arguments)
         .map_err(|source| Error::Msg(format!("failed to serialize request: {source}")))?;
@@ -974,42 +2374,192 @@ fn run_generate_dis_events(arguments: GenerateDisEventsCliArgs) -> Result<()> {
         .unwrap_or_else(|_| PathBuf::from("physics-engine/build/pythia_dis_cli"));
 
     if !backend_bin.is_file() {
-        return Err(Error::Msg(format!(
-            "PYTHIA 8 backend executable not found: {} (run setup scripts and build first)",
-            backend_bin.display()
-        )));
+        return Err(CliError::new(
+            "pythia_backend_missing",
+            GuiErrorCategory::PythiaBackendMissing,
+            None,
+            format!(
+                "PYTHIA 8 backend executable not found: {} (run setup scripts and build first)",
+                backend_bin.display()
+            ),
+        ));
     }
+    manifest.record_backend(Backend::Pythia, &backend_bin);
 
-    let log_path = run_dir.join("generator.log");
-    let log_file = std::fs::File::create(&log_path).map_err(Error::wrap)?;
+    let summary = if arguments.jobs > 1 {
+        let mut chunks = plan_pythia_chunks(&arguments, &run_dir, &mut manifest);
+        println!(
+            "Launching {} PYTHIA 8 event generator backends...",
+            chunks.len()
+        );
+        run_pythia_backends(&backend_bin, &arguments, &mut chunks, arguments.retries)?;
+        println!("Merging {} chunks...", chunks.len());
+        let summary =
+            merge_chunks(&run_dir, &chunks, seed).map_err(|e| Error::Msg(e.to_string()))?;
+        std::fs::remove_dir_all(run_dir.join(CHUNKS_DIRECTORY_NAME)).map_err(Error::wrap)?;
+        summary
+    } else {
+        // The whole run is one chunk generated in place, without retries.
+        let mut chunks = [GenerationChunk {
+            index: 0,
+            directory: run_dir.clone(),
+            events: arguments.number_of_events,
+            seed,
+            attempts: 1,
+        }];
+        println!("Launching PYTHIA 8 event generator backend...");
+        run_pythia_backends(&backend_bin, &arguments, &mut chunks, 0)?
+    };
+    write_run_manifest(manifest, &run_dir)?;
 
-    println!("Launching PYTHIA 8 event generator backend...");
-    let mut child = std::process::Command::new(&backend_bin)
-        .arg(run_dir.to_string_lossy().as_ref())
-        .stdin(std::process::Stdio::piped())
-        .stdout(log_file.try_clone().map_err(Error::wrap)?)
-        .stderr(log_file)
-        .spawn()
-        .map_err(|err| Error::Msg(format!("failed to spawn backend process: {err}")))?;
+    let cancelled = summary
+        .get("cancelled")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if cancelled {
+        println!("DIS events generation was cancelled.");
+    } else {
+        println!("DIS events generation completed successfully.");
+    }
+    if let Some(accepted) = summary.get("accepted_events") {
+        println!("Accepted events: {}", accepted);
+    }
+    if let Some(failed) = summary.get("failed_events") {
+        println!("Failed events: {}", failed);
+    }
+    if let Some(vetoed) = summary.get("vetoed_cuts_events") {
+        println!("Vetoed by cuts: {}", vetoed);
+    }
+    if let (Some(cross_section), Some(error)) = (
+        summary.get("cross_section_pb").and_then(|v| v.as_f64()),
+        summary
+            .get("cross_section_error_pb")
+            .and_then(|v| v.as_f64()),
+    ) {
+        println!("Cross section: {cross_section:.6e} ± {error:.2e} pb");
+    }
+
+    if cancelled {
+        return Err(CliError::new(
+            "generation_cancelled",
+            GuiErrorCategory::ProcessFailed,
+            None,
+            format!(
+                "generation cancelled after {} of {} events; the partial run in {} is complete",
+                summary
+                    .get("accepted_events")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0),
+                arguments.number_of_events,
+                run_dir.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Failed launches of one chunk that `generate-dis-events --jobs` retries.
+const DEFAULT_CHUNK_RETRIES: usize = 2;
+
+/// Pause between checks of the running backends.
+const BACKEND_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Shortest interval between two progress bars or JSON progress lines.
+const PROGRESS_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
 
-    if let Some(mut stdin) = child.stdin.take() {
+/// Width of the progress bar, in characters.
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// A running PYTHIA backend: the process, the control channel on its
+/// standard input and the thread that reads its standard error.
+struct PythiaBackend {
+    child: std::process::Child,
+    control: std::process::ChildStdin,
+    reader: std::thread::JoinHandle<()>,
+}
+
+impl PythiaBackend {
+    /// Ask the backend to stop and write the events accepted so far.
+    fn cancel(&mut self) {
         use std::io::Write;
-        stdin
-            .write_all(config_json.as_bytes())
-            .map_err(Error::wrap)?;
+        // A backend that already exited has closed the pipe; nothing to cancel.
+        let _ = writeln!(self.control, "{CANCEL_MESSAGE}").and_then(|()| self.control.flush());
     }
 
-    let status = child
-        .wait()
-        .map_err(|err| Error::Msg(format!("failed to wait for backend: {err}")))?;
+    /// Wait for the last progress report of an exited backend.
+    fn finish(self) {
+        let _ = self.reader.join();
+    }
 
-    if !status.success() {
-        let code = status.code().unwrap_or(-1);
-        return Err(Error::Msg(format!(
-            "PYTHIA 8 backend failed with exit code: {code}"
-        )));
+    fn kill(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        self.finish();
     }
+}
+
+/// Start the PYTHIA backend on `directory` and send it the request. Progress
+/// reports on its standard error go to `report`; its other output goes to
+/// `generator.log` there.
+fn spawn_pythia_backend(
+    backend_bin: &Path,
+    directory: &Path,
+    config_json: &str,
+    report: impl Fn(GeneratorProgress) + Send + 'static,
+) -> Result<PythiaBackend> {
+    use std::io::{BufRead, Write};
+
+    // Both the backend and the reader thread append, so neither overwrites the other.
+    let log_path = directory.join(GENERATOR_LOG_FILE_NAME);
+    std::fs::File::create(&log_path).map_err(Error::wrap)?;
+    let log_file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&log_path)
+        .map_err(Error::wrap)?;
+    let mut log = log_file.try_clone().map_err(Error::wrap)?;
+
+    let mut child = std::process::Command::new(backend_bin)
+        .arg(directory.to_string_lossy().as_ref())
+        .stdin(std::process::Stdio::piped())
+        .stdout(log_file)
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|err| Error::Msg(format!("failed to spawn backend process: {err}")))?;
 
+    let (Some(mut control), Some(stderr)) = (child.stdin.take(), child.stderr.take()) else {
+        return Err(Error::Msg("backend process has no pipes".to_string()));
+    };
+    let reader = std::thread::spawn(move || {
+        let mut stderr = std::io::BufReader::new(stderr);
+        let mut line = Vec::new();
+        while stderr
+            .read_until(b'\n', &mut line)
+            .is_ok_and(|read| read > 0)
+        {
+            match parse_progress_line(&String::from_utf8_lossy(&line)) {
+                Some(progress) => report(progress),
+                None => {
+                    let _ = log.write_all(&line);
+                }
+            }
+            line.clear();
+        }
+    });
+    // The request is one JSON document; the pipe stays open for control messages.
+    control
+        .write_all(config_json.as_bytes())
+        .and_then(|()| control.write_all(b"\n"))
+        .map_err(Error::wrap)?;
+    Ok(PythiaBackend {
+        child,
+        control,
+        reader,
+    })
+}
+
+/// Check that a finished backend wrote every run file and reported success,
+/// and return its summary.
+fn check_pythia_outputs(run_dir: &Path) -> Result<serde_json::Value> {
     let expected_files = [
         "config.json",
         "metadata.json",
@@ -1041,24 +2591,392 @@ fn run_generate_dis_events(arguments: GenerateDisEventsCliArgs) -> Result<()> {
             "summary.json indicates generation failed".to_string(),
         ));
     }
+    Ok(summary)
+}
 
-    println!("DIS events generation completed successfully.");
-    if let Some(accepted) = summary.get("accepted_events") {
-        println!("Accepted events: {}", accepted);
+/// Exit code of a backend that rejected its request, for example for an
+/// unknown PYTHIA setting.
+const BACKEND_REQUEST_REJECTED: i32 = 2;
+
+/// A backend failure, described with the error the backend wrote to
+/// `summary.json` when there is one. A setting PYTHIA does not know is the
+/// user's input and fails like an invalid option.
+fn pythia_backend_failure(directory: &Path, code: i32) -> CliError {
+    let summary = std::fs::read_to_string(directory.join("summary.json"))
+        .ok()
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok());
+    let error = summary.as_ref().and_then(|summary| summary.get("error"));
+    let field = |name: &str| error?.get(name)?.as_str();
+    match (field("code"), field("message")) {
+        (Some("unknown_pythia_setting"), Some(message)) => CliError::new(
+            "unknown_pythia_setting",
+            GuiErrorCategory::InvalidInput,
+            Some("pythia-setting"),
+            message,
+        ),
+        (Some(name), Some(message)) => CliError::new(
+            "backend_failed",
+            GuiErrorCategory::ProcessFailed,
+            None,
+            format!("PYTHIA 8 backend failed with exit code: {code} ({name}: {message})"),
+        ),
+        _ => CliError::new(
+            "backend_failed",
+            GuiErrorCategory::ProcessFailed,
+            None,
+            format!("PYTHIA 8 backend failed with exit code: {code}"),
+        ),
     }
-    if let Some(failed) = summary.get("failed_events") {
-        println!("Failed events: {}", failed);
+}
+
+/// Split the run into `arguments.jobs` chunks below `chunks/` and record
+/// their seeds.
+fn plan_pythia_chunks(
+    arguments: &GenerateDisEventsCliArgs,
+    run_dir: &Path,
+    manifest: &mut ManifestRecorder,
+) -> Vec<GenerationChunk> {
+    let seed = arguments.random_seed.unwrap_or_default();
+    let sizes = split_events(arguments.number_of_events, arguments.jobs);
+    let chunks: Vec<GenerationChunk> = sizes
+        .iter()
+        .zip(chunk_seeds(seed, sizes.len()))
+        .enumerate()
+        .map(|(index, (&events, seed))| GenerationChunk {
+            index,
+            directory: chunk_directory(run_dir, index),
+            events,
+            seed,
+            attempts: 1,
+        })
+        .collect();
+    for chunk in &chunks {
+        manifest.record_seed(&format!("chunk_{:03}_seed", chunk.index), chunk.seed as u64);
     }
-    if let Some(vetoed) = summary.get("vetoed_cuts_events") {
-        println!("Vetoed by cuts: {}", vetoed);
+    chunks
+}
+
+/// Generate every chunk concurrently, relaunching a failed one up to
+/// `retries` times, while reporting their combined progress as
+/// `arguments.progress` asks. A cancel request, a control message on stdin
+/// or SIGINT/SIGTERM, is forwarded to every backend, which then writes a
+/// partial run. Returns the summary of the chunk that
+/// finished last, the run's own for a single chunk.
+fn run_pythia_backends(
+    backend_bin: &Path,
+    arguments: &GenerateDisEventsCliArgs,
+    chunks: &mut [GenerationChunk],
+    retries: usize,
+) -> std::result::Result<serde_json::Value, CliError> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let launch = |chunk: &GenerationChunk| {
+        let sender = sender.clone();
+        let (index, attempt) = (chunk.index, chunk.attempts);
+        launch_pythia_chunk(backend_bin, arguments, chunk, move |progress| {
+            let _ = sender.send((index, attempt, progress));
+        })
+    };
+    let cancel_requested = Arc::new(AtomicBool::new(false));
+    cancel_on_signals(&cancel_requested);
+    {
+        let cancel_requested = Arc::clone(&cancel_requested);
+        // Detached: it ends with the process, wherever stdin is.
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lines().map_while(std::io::Result::ok) {
+                if is_cancel_message(&line) {
+                    cancel_requested.store(true, Ordering::Relaxed);
+                }
+            }
+        });
+    }
+
+    let mut running = Vec::with_capacity(chunks.len());
+    for chunk in chunks.iter() {
+        running.push(Some(launch(chunk)?));
+    }
+    let mut latest: Vec<Option<GeneratorProgress>> = vec![None; chunks.len()];
+    let mut display = ProgressDisplay::new(arguments.progress);
+    let mut cancelling = false;
+    let mut summary = serde_json::Value::Null;
+    let mut failure = None;
+    while failure.is_none() && running.iter().any(Option::is_some) {
+        std::thread::sleep(BACKEND_POLL_INTERVAL);
+        if !cancelling && cancel_requested.load(Ordering::Relaxed) {
+            cancelling = true;
+            display.message("Cancelling; the generator keeps the events accepted so far.");
+            for backend in running.iter_mut().flatten() {
+                backend.cancel();
+            }
+        }
+
+        for (chunk, slot) in chunks.iter_mut().zip(&mut running) {
+            let Some(backend) = slot else {
+                continue;
+            };
+            let status = match backend.child.try_wait() {
+                Ok(None) => continue,
+                Ok(Some(status)) => status,
+                Err(err) => {
+                    failure = Some(CliError::from(Error::Msg(format!(
+                        "failed to wait for backend: {err}"
+                    ))));
+                    break;
+                }
+            };
+            if let Some(backend) = slot.take() {
+                backend.finish();
+            }
+            // A rejected request fails the same way on every attempt.
+            let rejected = status.code() == Some(BACKEND_REQUEST_REJECTED);
+            let outcome = if status.success() {
+                check_pythia_outputs(&chunk.directory).map_err(CliError::from)
+            } else {
+                Err(pythia_backend_failure(
+                    &chunk.directory,
+                    status.code().unwrap_or(-1),
+                ))
+            };
+            match outcome {
+                Ok(chunk_summary) => {
+                    if latest.len() > 1 {
+                        display.message(&format!(
+                            "Chunk {} finished: {} events (seed {}).",
+                            chunk.index,
+                            chunk_summary
+                                .get("accepted_events")
+                                .and_then(|v| v.as_u64())
+                                .unwrap_or(0),
+                            chunk.seed
+                        ));
+                    }
+                    summary = chunk_summary;
+                }
+                Err(error) if chunk.attempts <= retries && !cancelling && !rejected => {
+                    chunk.attempts += 1;
+                    display.message(&format!(
+                        "Chunk {} failed ({error}); retrying, attempt {} of {}.",
+                        chunk.index,
+                        chunk.attempts,
+                        retries + 1
+                    ));
+                    latest[chunk.index] = None;
+                    *slot = Some(launch(chunk)?);
+                }
+                Err(error) if latest.len() == 1 => failure = Some(error),
+                Err(error) => {
+                    failure = Some(CliError {
+                        message: format!(
+                            "chunk {} failed after {} attempts: {} (see {})",
+                            chunk.index,
+                            chunk.attempts,
+                            error.message,
+                            chunk.directory.join(GENERATOR_LOG_FILE_NAME).display()
+                        ),
+                        ..error
+                    });
+                    break;
+                }
+            }
+        }
+
+        let mut reported = false;
+        for (index, attempt, progress) in receiver.try_iter() {
+            if chunks[index].attempts == attempt {
+                latest[index] = Some(progress);
+                reported = true;
+            }
+        }
+        if reported {
+            display.update(&GeneratorProgress::combine(
+                arguments.number_of_events,
+                &latest,
+            ));
+        }
+    }
+    display.finish();
+
+    if let Some(failure) = failure {
+        for backend in running.into_iter().flatten() {
+            backend.kill();
+        }
+        return Err(failure);
+    }
+    Ok(summary)
+}
+
+/// Make SIGINT and SIGTERM request cancellation like a control message, so
+/// the run still merges its chunks and writes its manifest. The handlers stay
+/// for the rest of the command; a signal after a cancel request exits at once
+/// with the usual `128 + signal` status.
+fn cancel_on_signals(cancel_requested: &Arc<std::sync::atomic::AtomicBool>) {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+
+    for signal in [SIGINT, SIGTERM] {
+        // Registered first, so it sees the flag before this signal sets it.
+        let _ = signal_hook::flag::register_conditional_shutdown(
+            signal,
+            128 + signal,
+            Arc::clone(cancel_requested),
+        );
+        let _ = signal_hook::flag::register(signal, Arc::clone(cancel_requested));
+    }
+}
+
+/// Clear a relaunched chunk's directory, write its request there and start
+/// its backend. A run of one chunk gets its own `config.json` rewritten
+/// unchanged.
+fn launch_pythia_chunk(
+    backend_bin: &Path,
+    arguments: &GenerateDisEventsCliArgs,
+    chunk: &GenerationChunk,
+    report: impl Fn(GeneratorProgress) + Send + 'static,
+) -> Result<PythiaBackend> {
+    if chunk.attempts > 1 {
+        std::fs::remove_dir_all(&chunk.directory).map_err(Error::wrap)?;
+    }
+    std::fs::create_dir_all(&chunk.directory).map_err(Error::wrap)?;
+    let request = GenerateDisEventsCliArgs {
+        number_of_events: chunk.events,
+        random_seed: Some(chunk.seed),
+        ..arguments.clone()
+    };
+    let config_json = serde_json::to_string_pretty(&request)
+        .map_err(|source| Error::Msg(format!("failed to serialize request: {source}")))?;
+    std::fs::write(chunk.directory.join("config.json"), &config_json).map_err(Error::wrap)?;
+    spawn_pythia_backend(backend_bin, &chunk.directory, &config_json, report)
+}
+
+/// Progress of the PYTHIA backends on standard output.
+///
+/// A terminal gets one bar redrawn in place; other outputs get a line per
+/// tenth of the requested events so logs stay short. JSON lines are the
+/// protocol lines of [`GeneratorProgress`], combined over the chunks.
+struct ProgressDisplay {
+    output: ProgressOutput,
+    terminal: bool,
+    /// The bar is drawn without a line break after it.
+    drawn: bool,
+    reported_tenths: usize,
+    last_update: Option<std::time::Instant>,
+}
+
+impl ProgressDisplay {
+    fn new(output: ProgressOutput) -> Self {
+        use std::io::IsTerminal;
+
+        Self {
+            output,
+            terminal: std::io::stdout().is_terminal(),
+            drawn: false,
+            reported_tenths: 0,
+            last_update: None,
+        }
+    }
+
+    fn update(&mut self, progress: &GeneratorProgress) {
+        use std::io::Write;
+
+        let due = progress.status != GenerationStatus::Running
+            || self
+                .last_update
+                .is_none_or(|last| last.elapsed() >= PROGRESS_REFRESH_INTERVAL);
+        match self.output {
+            ProgressOutput::None => return,
+            ProgressOutput::Json if due => println!("{}", progress.to_json_line()),
+            ProgressOutput::Bar if self.terminal && due => {
+                print!("\r{}\x1b[K", progress.render_bar(PROGRESS_BAR_WIDTH));
+                self.drawn = true;
+            }
+            ProgressOutput::Bar if !self.terminal => {
+                let tenths = (progress.fraction() * 10.0) as usize;
+                if tenths <= self.reported_tenths && progress.status == GenerationStatus::Running {
+                    return;
+                }
+                self.reported_tenths = tenths;
+                println!("{}", progress.render_bar(PROGRESS_BAR_WIDTH));
+            }
+            _ => return,
+        }
+        self.last_update = Some(std::time::Instant::now());
+        let _ = std::io::stdout().flush();
     }
 
+    /// Print a line of text below the bar.
+    fn message(&mut self, text: &str) {
+        self.finish();
+        println!("{text}");
+    }
+
+    fn finish(&mut self) {
+        if std::mem::take(&mut self.drawn) {
+            println!();
+        }
+    }
+}
+
+fn run_native_dis_generator(
+    arguments: &GenerateDisEventsCliArgs,
+    run_dir: &Path,
+) -> std::result::Result<(), CliError> {
+    use quark_sim::physics::{
+        generate_dis_events, DisGeneratorConfig, LoPdfStructureFunctionProvider, PerturbativeOrder,
+    };
+
+    // run_generate_dis_events draws the seed when it is omitted, and the
+    // parser rejects a negative one.
+    let random_seed = arguments
+        .random_seed
+        .and_then(|seed| u64::try_from(seed).ok())
+        .ok_or_else(|| {
+            invalid_option(
+                "seed",
+                "--seed must be non-negative with --generator native",
+            )
+        })?;
+    let config = DisGeneratorConfig {
+        schema_version: arguments.schema_version,
+        process: arguments.process.clone(),
+        electron_energy_gev: arguments.electron_energy_gev,
+        proton_energy_gev: arguments.proton_energy_gev,
+        q2_min_gev2: arguments.q2_min_gev2,
+        q2_max_gev2: arguments.q2_max_gev2,
+        x_min: arguments.x_min,
+        x_max: arguments.x_max,
+        y_min: arguments.y_min,
+        y_max: arguments.y_max,
+        number_of_events: arguments.number_of_events,
+        random_seed,
+        pdf_set: arguments.pdf_set.clone(),
+        pdf_member: arguments.pdf_member,
+        order: PerturbativeOrder::Lo,
+        weighting: arguments.weighting,
+        ..DisGeneratorConfig::default()
+    };
+
+    let pdf = LhapdfProvider::new(&arguments.pdf_set, arguments.pdf_member)?;
+    let provider =
+        LoPdfStructureFunctionProvider::new(pdf, &arguments.pdf_set, arguments.pdf_member, 0, 0)?;
+
+    println!("Running native LO DIS event generator (seed {})...", config.random_seed);
+    let summary = generate_dis_events(&provider, Some(provider.pdf_provider()), &config, run_dir)
+        .map_err(|e| Error::Msg(e.to_string()))?;
+
+    println!("DIS events generation completed successfully.");
+    println!("Accepted events: {}", summary.accepted_events);
+    println!("Failed events: {}", summary.failed_events);
+    println!("Vetoed by cuts: {}", summary.vetoed_cuts_events);
+    println!(
+        "Cross section: {:.6e} ± {:.2e} pb",
+        summary.cross_section_pb, summary.cross_section_error_pb
+    );
     Ok(())
 }
 
-fn parse_validate_hera_command(args: &[String]) -> std::result::Result<ValidateHeraCliArgs, String> {
+fn parse_validate_hera_command(args: &[String]) -> std::result::Result<ValidateHeraCliArgs, CliError> {
     if matches!(args, [flag] if flag == "-h" || flag == "--help") {
-        return Err("validate-hera --dataset <DATASET_ID> --backend apfel --order NLO --pdf-set <SET> --output outputs/validation/".to_owned());
+        return Err(missing_options("validate-hera --dataset <DATASET_ID> --backend apfel --order NLO --pdf-set <SET> --output outputs/validation/"));
     }
     
     let mut dataset = None;
@@ -1072,19 +2990,19 @@ fn parse_validate_hera_command(args: &[String]) -> std::result::Result<ValidateH
     while index < args.len() {
         let flag = args[index].as_str();
         if flag == "-h" || flag == "--help" {
-            return Err("--help must be used by itself after validate-hera".to_string());
+            return Err(invalid_option("help", "--help must be used by itself after validate-hera"));
         }
         if !matches!(
             flag,
             "--dataset" | "--backend" | "--order" | "--pdf-set" | "--pdf-member" | "--output"
         ) {
-            return Err(format!("unknown validate-hera option: {flag}"));
+            return Err(unknown_option(flag, format!("unknown validate-hera option: {flag}")));
         }
         
         let value_text = args
             .get(index + 1)
             .filter(|value| !value.starts_with("--"))
-            .ok_or_else(|| format!("{flag} requires a value"))?;
+            .ok_or_else(|| invalid_option(option_field(flag), format!("{flag} requires a value")))?;
             
         match flag {
             "--dataset" => dataset = Some(value_text.clone()),
@@ -1092,9 +3010,12 @@ fn parse_validate_hera_command(args: &[String]) -> std::result::Result<ValidateH
             "--order" => order = Some(value_text.clone()),
             "--pdf-set" => pdf_set = Some(value_text.clone()),
             "--pdf-member" => {
-                let parsed = value_text
-                    .parse::<i32>()
-                    .map_err(|_| format!("invalid integer for --pdf-member: {value_text}"))?;
+                let parsed = value_text.parse::<i32>().map_err(|_| {
+                    invalid_option(
+                        "pdf-member",
+                        format!("invalid integer for --pdf-member: {value_text}"),
+                    )
+                })?;
                 pdf_member = Some(parsed);
             }
             "--output" => output = Some(PathBuf::from(value_text)),
@@ -1104,26 +3025,59 @@ fn parse_validate_hera_command(args: &[String]) -> std::result::Result<ValidateH
     }
     
     Ok(ValidateHeraCliArgs {
-        dataset: dataset.ok_or_else(|| "missing required option: --dataset".to_owned())?,
-        backend: backend.ok_or_else(|| "missing required option: --backend".to_owned())?,
-        order: order.ok_or_else(|| "missing required option: --order".to_owned())?,
-        pdf_set: pdf_set.ok_or_else(|| "missing required option: --pdf-set".to_owned())?,
+        dataset: required_option(dataset, "--dataset")?,
+        backend: required_option(backend, "--backend")?,
+        order: required_option(order, "--order")?,
+        pdf_set: required_option(pdf_set, "--pdf-set")?,
         pdf_member: pdf_member.unwrap_or(0),
-        output: output.ok_or_else(|| "missing required option: --output".to_owned())?,
+        output: required_option(output, "--output")?,
     })
 }
 
-fn run_validate_hera(arguments: ValidateHeraCliArgs) -> Result<()> {
-    if arguments.backend != "apfel" {
-        return Err(Error::Msg(format!("Unsupported backend: {}. Only 'apfel' is supported currently.", arguments.backend)));
+/// The backend and order the Python validation pipelines support.
+fn check_validation_backend(backend: &str, order: &str) -> std::result::Result<(), CliError> {
+    if backend != "apfel" {
+        return Err(CliError::new(
+            "unknown_backend",
+            GuiErrorCategory::InvalidInput,
+            Some("backend"),
+            format!("Unsupported backend: {backend}. Only 'apfel' is supported currently."),
+        ));
     }
-    if arguments.order != "LO" && arguments.order != "NLO" {
-        return Err(Error::Msg(format!("Unsupported order: {}. Only 'LO' or 'NLO' is supported.", arguments.order)));
+    if order != "LO" && order != "NLO" {
+        return Err(CliError::new(
+            "unsupported_order",
+            GuiErrorCategory::UnsupportedOrder,
+            Some("order"),
+            format!("Unsupported order: {order}. Only 'LO' or 'NLO' is supported."),
+        ));
     }
+    Ok(())
+}
+
+/// A Python validation script missing from `analysis/validation`.
+fn validation_script_missing(message: &str) -> CliError {
+    CliError::new(
+        "validation_script_missing",
+        GuiErrorCategory::ProcessFailed,
+        None,
+        message,
+    )
+}
+
+fn run_validate_hera(
+    arguments: ValidateHeraCliArgs,
+    card: &RunCard,
+) -> std::result::Result<(), CliError> {
+    let mut manifest = ManifestRecorder::start(card);
+    check_validation_backend(&arguments.backend, &arguments.order)?;
+    manifest.record_backend(Backend::Apfel, &DoctorOptions::default().apfel_backend);
     
     let compare_script = PathBuf::from("analysis/validation/compare.py");
     if !compare_script.is_file() {
-        return Err(Error::Msg("compare.py script not found under analysis/validation/compare.py".to_string()));
+        return Err(validation_script_missing(
+            "compare.py script not found under analysis/validation/compare.py",
+        ));
     }
     
     println!("Launching HERA validation pipeline via Python...");
@@ -1143,8 +3097,16 @@ fn run_validate_hera(arguments: ValidateHeraCliArgs) -> Result<()> {
     
     if !status.success() {
         let code = status.code().unwrap_or(-1);
-        return Err(Error::Msg(format!("Validation pipeline failed with exit code: {code}")));
+        return Err(CliError::new(
+            "backend_failed",
+            GuiErrorCategory::ProcessFailed,
+            None,
+            format!("Validation pipeline failed with exit code: {code}"),
+        ));
     }
+    std::fs::create_dir_all(&arguments.output).map_err(Error::wrap)?;
+    write_run_card(card, &arguments.output)?;
+    write_run_manifest(manifest, &arguments.output)?;
     
     let summary_path = arguments.output.join(&arguments.dataset).join("summary.json");
     if summary_path.is_file() {
@@ -1170,9 +3132,9 @@ fn run_validate_hera(arguments: ValidateHeraCliArgs) -> Result<()> {
     Ok(())
 }
 
-fn parse_theory_uncertainties_command(args: &[String]) -> std::result::Result<TheoryUncertaintiesCliArgs, String> {
+fn parse_theory_uncertainties_command(args: &[String]) -> std::result::Result<TheoryUncertaintiesCliArgs, CliError> {
     if matches!(args, [flag] if flag == "-h" || flag == "--help") {
-        return Err("theory-uncertainties --dataset <DATASET_ID> --backend apfel --order NLO --pdf-set <SET> [--pdf-uncertainty] [--scale-variations] --output outputs/uncertainties/".to_owned());
+        return Err(missing_options("theory-uncertainties --dataset <DATASET_ID> --backend apfel --order NLO --pdf-set <SET> [--pdf-uncertainty] [--scale-variations] --output outputs/uncertainties/"));
     }
     
     let mut dataset = None;
@@ -1188,7 +3150,10 @@ fn parse_theory_uncertainties_command(args: &[String]) -> std::result::Result<Th
     while index < args.len() {
         let flag = args[index].as_str();
         if flag == "-h" || flag == "--help" {
-            return Err("--help must be used by itself after theory-uncertainties".to_string());
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after theory-uncertainties",
+            ));
         }
         if flag == "--pdf-uncertainty" {
             pdf_uncertainty = true;
@@ -1204,13 +3169,16 @@ fn parse_theory_uncertainties_command(args: &[String]) -> std::result::Result<Th
             flag,
             "--dataset" | "--backend" | "--order" | "--pdf-set" | "--pdf-member" | "--output"
         ) {
-            return Err(format!("unknown theory-uncertainties option: {flag}"));
+            return Err(unknown_option(
+                flag,
+                format!("unknown theory-uncertainties option: {flag}"),
+            ));
         }
         
         let value_text = args
             .get(index + 1)
             .filter(|value| !value.starts_with("--"))
-            .ok_or_else(|| format!("{flag} requires a value"))?;
+            .ok_or_else(|| invalid_option(option_field(flag), format!("{flag} requires a value")))?;
             
         match flag {
             "--dataset" => dataset = Some(value_text.clone()),
@@ -1218,9 +3186,12 @@ fn parse_theory_uncertainties_command(args: &[String]) -> std::result::Result<Th
             "--order" => order = Some(value_text.clone()),
             "--pdf-set" => pdf_set = Some(value_text.clone()),
             "--pdf-member" => {
-                let parsed = value_text
-                    .parse::<i32>()
-                    .map_err(|_| format!("invalid integer for --pdf-member: {value_text}"))?;
+                let parsed = value_text.parse::<i32>().map_err(|_| {
+                    invalid_option(
+                        "pdf-member",
+                        format!("invalid integer for --pdf-member: {value_text}"),
+                    )
+                })?;
                 pdf_member = Some(parsed);
             }
             "--output" => output = Some(PathBuf::from(value_text)),
@@ -1230,28 +3201,30 @@ fn parse_theory_uncertainties_command(args: &[String]) -> std::result::Result<Th
     }
     
     Ok(TheoryUncertaintiesCliArgs {
-        dataset: dataset.ok_or_else(|| "missing required option: --dataset".to_owned())?,
-        backend: backend.ok_or_else(|| "missing required option: --backend".to_owned())?,
-        order: order.ok_or_else(|| "missing required option: --order".to_owned())?,
-        pdf_set: pdf_set.ok_or_else(|| "missing required option: --pdf-set".to_owned())?,
+        dataset: required_option(dataset, "--dataset")?,
+        backend: required_option(backend, "--backend")?,
+        order: required_option(order, "--order")?,
+        pdf_set: required_option(pdf_set, "--pdf-set")?,
         pdf_member: pdf_member.unwrap_or(0),
         pdf_uncertainty,
         scale_variations,
-        output: output.ok_or_else(|| "missing required option: --output".to_owned())?,
+        output: required_option(output, "--output")?,
     })
 }
 
-fn run_theory_uncertainties(arguments: TheoryUncertaintiesCliArgs) -> Result<()> {
-    if arguments.backend != "apfel" {
-        return Err(Error::Msg(format!("Unsupported backend: {}. Only 'apfel' is supported currently.", arguments.backend)));
-    }
-    if arguments.order != "LO" && arguments.order != "NLO" {
-        return Err(Error::Msg(format!("Unsupported order: {}. Only 'LO' or 'NLO' is supported.", arguments.order)));
-    }
+fn run_theory_uncertainties(
+    arguments: TheoryUncertaintiesCliArgs,
+    card: &RunCard,
+) -> std::result::Result<(), CliError> {
+    let mut manifest = ManifestRecorder::start(card);
+    check_validation_backend(&arguments.backend, &arguments.order)?;
+    manifest.record_backend(Backend::Apfel, &DoctorOptions::default().apfel_backend);
     
     let compare_script = PathBuf::from("analysis/validation/compare_uncertainty.py");
     if !compare_script.is_file() {
-        return Err(Error::Msg("compare_uncertainty.py script not found".to_string()));
+        return Err(validation_script_missing(
+            "compare_uncertainty.py script not found",
+        ));
     }
     
     println!("Launching theory uncertainties pipeline via Python...");
@@ -1278,8 +3251,16 @@ fn run_theory_uncertainties(arguments: TheoryUncertaintiesCliArgs) -> Result<()>
     
     if !status.success() {
         let code = status.code().unwrap_or(-1);
-        return Err(Error::Msg(format!("Theory uncertainties pipeline failed with exit code: {code}")));
+        return Err(CliError::new(
+            "backend_failed",
+            GuiErrorCategory::ProcessFailed,
+            None,
+            format!("Theory uncertainties pipeline failed with exit code: {code}"),
+        ));
     }
+    std::fs::create_dir_all(&arguments.output).map_err(Error::wrap)?;
+    write_run_card(card, &arguments.output)?;
+    write_run_manifest(manifest, &arguments.output)?;
     
     let summary_path = arguments.output.join(&arguments.dataset).join("summary.json");
     if summary_path.is_file() {
@@ -1303,52 +3284,93 @@ fn run_theory_uncertainties(arguments: TheoryUncertaintiesCliArgs) -> Result<()>
     
     Ok(())
 }
-fn parse_structure_functions_command(args: &[String]) -> std::result::Result<StructureFunctionsCliArgs, String> {
+fn parse_structure_functions_command(args: &[String]) -> std::result::Result<StructureFunctionsCliArgs, CliError> {
     let mut backend = None;
-    let mut x = None;
-    let mut q2 = None;
+    let mut kinematics = KinematicOptions::default();
     let mut order = None;
     let mut pdf_set = None;
     let mut pdf_member = None;
     let mut mu_f_over_q = 1.0;
     let mut mu_r_over_q = 1.0;
+    let mut max_uncertainty = None;
     let mut index = 0;
 
     while index < args.len() {
         let flag = args[index].as_str();
         let value_text = args
             .get(index + 1)
-            .ok_or_else(|| format!("{flag} requires a value"))?;
+            .ok_or_else(|| invalid_option(option_field(flag), format!("{flag} requires a value")))?;
 
         match flag {
             "--backend" => backend = Some(value_text.clone()),
-            "--x" => x = Some(parse_finite_cross_number("--x", value_text)?),
-            "--q2" => q2 = Some(parse_finite_cross_number("--q2", value_text)?),
+            "--x" => kinematics.x = Some(parse_finite_cross_number("--x", value_text)?),
+            "--q2" => kinematics.q2 = Some(parse_finite_cross_number("--q2", value_text)?),
+            "--points" => kinematics.points = Some(PathBuf::from(value_text)),
+            "--x-grid" => kinematics.x_grid = Some(parse_log_grid(flag, value_text)?),
+            "--q2-grid" => kinematics.q2_grid = Some(parse_log_grid(flag, value_text)?),
+            "--output" => kinematics.output = Some(PathBuf::from(value_text)),
             "--order" => order = Some(value_text.clone()),
             "--pdf-set" => pdf_set = Some(value_text.clone()),
             "--pdf-member" => {
-                pdf_member = Some(value_text.parse::<i32>().map_err(|_| format!("invalid integer for --pdf-member: {value_text}"))?);
+                pdf_member = Some(value_text.parse::<i32>().map_err(|_| {
+                    invalid_option(
+                        "pdf-member",
+                        format!("invalid integer for --pdf-member: {value_text}"),
+                    )
+                })?);
             }
             "--mu-f-over-q" => mu_f_over_q = parse_finite_cross_number("--mu-f-over-q", value_text)?,
             "--mu-r-over-q" => mu_r_over_q = parse_finite_cross_number("--mu-r-over-q", value_text)?,
-            _ => return Err(format!("unknown option: {flag}")),
+            "--max-uncertainty" => max_uncertainty = Some(parse_max_uncertainty(value_text)?),
+            _ => return Err(unknown_option(flag, format!("unknown option: {flag}"))),
         }
         index += 2;
     }
 
     Ok(StructureFunctionsCliArgs {
-        backend: backend.ok_or_else(|| "missing required option: --backend".to_owned())?,
-        x: x.ok_or_else(|| "missing required option: --x".to_owned())?,
-        q2: q2.ok_or_else(|| "missing required option: --q2".to_owned())?,
-        order: order.ok_or_else(|| "missing required option: --order".to_owned())?,
-        pdf_set: pdf_set.ok_or_else(|| "missing required option: --pdf-set".to_owned())?,
+        backend: required_option(backend, "--backend")?,
+        kinematics: kinematics.resolve(|option| {
+            missing_option(option, format!("missing required option: {option}"))
+        })?,
+        order: required_option(order, "--order")?,
+        pdf_set: required_option(pdf_set, "--pdf-set")?,
         pdf_member: pdf_member.unwrap_or(0),
         mu_f_over_q,
         mu_r_over_q,
+        max_uncertainty,
     })
 }
 
-fn run_structure_functions(args: StructureFunctionsCliArgs) -> Result<()> {
+fn parse_positive_number(flag: &str, value_text: &str) -> std::result::Result<f64, CliError> {
+    value_text
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value > 0.0)
+        .ok_or_else(|| {
+            invalid_option(
+                option_field(flag),
+                format!("invalid positive number for {flag}: {value_text}"),
+            )
+        })
+}
+
+fn parse_max_uncertainty(value_text: &str) -> std::result::Result<f64, CliError> {
+    value_text
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value > 0.0)
+        .ok_or_else(|| {
+            invalid_option(
+                "max-uncertainty",
+                format!("invalid positive number for --max-uncertainty: {value_text}"),
+            )
+        })
+}
+
+fn run_structure_functions(
+    args: StructureFunctionsCliArgs,
+    format: Option<OutputFormat>,
+) -> std::result::Result<(), CliError> {
     use quark_sim::physics::structure_function_provider::{
         StructureFunctionBackend, StructureFunctionProvider, StructureFunctionRequest,
         PerturbativeOrder, StructureFunctionProcess, DisProjectile, DisTarget,
@@ -1360,243 +3382,3574 @@ fn run_structure_functions(args: StructureFunctionsCliArgs) -> Result<()> {
     use std::str::FromStr;
 
     let order = PerturbativeOrder::from_str(&args.order).map_err(|_| {
-        Error::Msg(format!("Invalid perturbative order: {}", args.order))
+        CliError::new(
+            "unsupported_order",
+            GuiErrorCategory::UnsupportedOrder,
+            Some("order"),
+            format!("Invalid perturbative order: {}", args.order),
+        )
     })?;
 
-    let mut request = StructureFunctionRequest::electromagnetic_nc(
-        args.x,
-        args.q2,
-        order,
-        args.pdf_set.clone(),
-        args.pdf_member,
-    );
-    request.mu_f_over_q = args.mu_f_over_q;
-    request.mu_r_over_q = args.mu_r_over_q;
+    let request_at = |x, q2| {
+        let mut request = StructureFunctionRequest::electromagnetic_nc(
+            x,
+            q2,
+            order,
+            args.pdf_set.clone(),
+            args.pdf_member,
+        );
+        request.mu_f_over_q = args.mu_f_over_q;
+        request.mu_r_over_q = args.mu_r_over_q;
+        request
+    };
 
-    let result = match args.backend.as_str() {
-        "apfel" => {
-            let provider = ApfelStructureFunctionProvider::new("physics-engine/build/apfel_cli");
-            provider.evaluate(&request)
-        }
+    // One provider serves every point of a batch.
+    let provider: Box<dyn StructureFunctionProvider> = match args.backend.as_str() {
+        "apfel" => Box::new(ApfelStructureFunctionProvider::new(
+            "physics-engine/build/apfel_cli",
+        )),
         "surrogate" => {
             let dir = std::env::current_dir().unwrap().join("models/surrogate_v1");
-            let provider = SurrogateProvider::load(&dir)
-                .map_err(|e| Error::Msg(e.to_string()))?;
-            provider.evaluate(&request)
+            let mut provider = SurrogateProvider::load(&dir).map_err(|e| {
+                CliError::new(
+                    "surrogate_unavailable",
+                    GuiErrorCategory::SurrogateModelMissing,
+                    Some("backend"),
+                    e.to_string(),
+                )
+            })?;
+            if args.max_uncertainty.is_some() {
+                provider = provider.with_max_relative_uncertainty(args.max_uncertainty);
+            }
+            Box::new(provider)
         }
         "lo" => {
-            let pdf = LhapdfProvider::new(&args.pdf_set, args.pdf_member)
-                .map_err(|e| Error::Msg(e.to_string()))?;
-            let provider = LoPdfStructureFunctionProvider::new(pdf, &args.pdf_set, args.pdf_member, 0, 0)
-                .map_err(|e| Error::Msg(e.to_string()))?;
-            provider.evaluate(&request)
+            let pdf = LhapdfProvider::new(&args.pdf_set, args.pdf_member)?;
+            Box::new(LoPdfStructureFunctionProvider::new(
+                pdf,
+                &args.pdf_set,
+                args.pdf_member,
+                0,
+                0,
+            )?)
         }
-        other => return Err(Error::Msg(format!("Unsupported backend: {other}"))),
-    }.map_err(|e| Error::Msg(e.to_string()))?;
+        other => {
+            return Err(CliError::new(
+                "unknown_backend",
+                GuiErrorCategory::InvalidInput,
+                Some("backend"),
+                format!("Unsupported backend: {other}"),
+            ))
+        }
+    };
 
-    // Enrich with reproducibility metadata
+    let (x, q2) = match &args.kinematics {
+        KinematicInput::Point { x, q2 } => (*x, *q2),
+        KinematicInput::Batch(batch) => {
+            let format = format.unwrap_or_default();
+            return run_batch(batch, format, &STRUCTURE_FUNCTION_BATCH_COLUMNS, |point| {
+                let result = provider
+                    .evaluate(&request_at(point.x, point.q2))
+                    .map_err(|e| e.to_string())?;
+                let uncertainty = result.uncertainty.as_ref();
+                Ok(vec![
+                    Some(result.f2),
+                    Some(result.fl),
+                    Some(result.xf3),
+                    uncertainty.map(|uncertainty| uncertainty.f2),
+                    uncertainty.map(|uncertainty| uncertainty.fl),
+                    uncertainty.map(|uncertainty| uncertainty.xf3),
+                ])
+            });
+        }
+    };
+    let result = provider.evaluate(&request_at(x, q2))?;
+
+    // Enrich with the same build provenance as the run manifests
+    let build = BuildProvenance::current();
     let mut enriched_result = result;
-    enriched_result.metadata.os_arch = option_env!("OS_ARCH").map(String::from);
-    enriched_result.metadata.rust_version = option_env!("RUSTC_VERSION").map(String::from);
-    enriched_result.metadata.git_commit = option_env!("GIT_HASH").map(String::from);
-    if let Some(dirty_str) = option_env!("GIT_DIRTY") {
-        enriched_result.metadata.git_dirty = Some(dirty_str == "true");
+    enriched_result.metadata.os_arch = Some(build.os_arch);
+    enriched_result.metadata.rust_version = Some(build.rustc_version);
+    enriched_result.metadata.git_commit = Some(build.git_hash);
+    enriched_result.metadata.git_dirty = Some(build.git_dirty);
+
+    // A single point keeps printing the full JSON result unless another
+    // format is requested.
+    let metadata = &enriched_result.metadata;
+    match format.unwrap_or(OutputFormat::Json) {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string(&enriched_result).unwrap());
+        }
+        OutputFormat::Csv => {
+            let uncertainty = enriched_result.uncertainty.as_ref();
+            print_record(
+                OutputFormat::Csv,
+                &[
+                    ("backend", metadata.backend.to_string().into()),
+                    ("order", metadata.order.to_string().into()),
+                    ("pdf_set", metadata.pdf_set.clone().into()),
+                    ("pdf_member", metadata.pdf_member.into()),
+                    ("x", x.into()),
+                    ("q2", q2.into()),
+                    ("f2", enriched_result.f2.into()),
+                    ("fl", enriched_result.fl.into()),
+                    ("xf3", enriched_result.xf3.into()),
+                    ("f2_uncertainty", uncertainty.map(|u| u.f2).into()),
+                    ("fl_uncertainty", uncertainty.map(|u| u.fl).into()),
+                    ("xf3_uncertainty", uncertainty.map(|u| u.xf3).into()),
+                ],
+            )?;
+        }
+        OutputFormat::Text => {
+            println!(
+                "Structure functions ({} {}, PDF set/member {}/{})",
+                metadata.backend, metadata.order, metadata.pdf_set, metadata.pdf_member
+            );
+            println!("x    = {x:.12e}");
+            println!("Q²   = {q2:.12e} GeV²");
+            println!("F₂   = {:.12e}", enriched_result.f2);
+            println!("F_L  = {:.12e}", enriched_result.fl);
+            println!("xF₃  = {:.12e}", enriched_result.xf3);
+            if let Some(uncertainty) = &enriched_result.uncertainty {
+                println!(
+                    "Ensemble spread ({} members): σ(F₂) = {:.3e}, σ(F_L) = {:.3e}, σ(xF₃) = {:.3e}",
+                    uncertainty.members, uncertainty.f2, uncertainty.fl, uncertainty.xf3
+                );
+            }
+        }
     }
-
-    println!("{}", serde_json::to_string(&enriched_result).unwrap());
     Ok(())
 }
 
-fn parse_train_surrogate_command(args: &[String]) -> std::result::Result<TrainSurrogateCliArgs, String> {
+fn parse_train_surrogate_command(
+    args: &[String],
+) -> std::result::Result<TrainSurrogateCommand, CliError> {
+    if matches!(args, [flag] if flag == "-h" || flag == "--help") {
+        return Ok(TrainSurrogateCommand::Help);
+    }
+
     let mut pdf_set = None;
     let mut pdf_member = None;
     let mut order = None;
     let mut output = None;
+    let mut sampler_name = "grid".to_string();
+    let mut points = 2000;
+    let mut seed = None;
+    let mut grid = [5, 5, 3];
+    let mut training_config = None;
+    let mut hidden_layers = None;
+    let mut activation = None;
+    let mut learning_rate = None;
+    let mut lr_schedule = None;
+    let mut lr_step_epochs = None;
+    let mut lr_decay_factor = None;
+    let mut min_learning_rate = None;
+    let mut epochs = None;
+    let mut batch_size = None;
+    let mut patience = None;
+    let mut ensemble_size = None;
+    let mut max_uncertainty = None;
+    let mut constrain_fl = false;
+    let mut zero_xf3 = false;
+    let mut q2_slope_weight = None;
+    let mut refinement = RefinementConfig::default();
+    let mut refine = false;
+    let mut resume = false;
     let mut index = 0;
 
     while index < args.len() {
         let flag = args[index].as_str();
+        if flag == "-h" || flag == "--help" {
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after train-surrogate",
+            ));
+        }
+        if matches!(flag, "--resume" | "--constrain-fl" | "--zero-xf3") {
+            match flag {
+                "--resume" => resume = true,
+                "--constrain-fl" => constrain_fl = true,
+                _ => zero_xf3 = true,
+            }
+            index += 1;
+            continue;
+        }
         let value_text = args
             .get(index + 1)
-            .ok_or_else(|| format!("{flag} requires a value"))?;
+            .ok_or_else(|| invalid_option(option_field(flag), format!("{flag} requires a value")))?;
+        let parse_count = |value: &str| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|count| *count > 0)
+                .ok_or_else(|| {
+                    invalid_option(
+                        option_field(flag),
+                        format!("invalid positive integer for {flag}: {value}"),
+                    )
+                })
+        };
 
         match flag {
             "--pdf-set" => pdf_set = Some(value_text.clone()),
             "--pdf-member" => {
-                let parsed = value_text
-                    .parse::<i32>()
-                    .map_err(|_| format!("invalid integer for --pdf-member: {value_text}"))?;
+                let parsed = value_text.parse::<i32>().map_err(|_| {
+                    invalid_option(
+                        "pdf-member",
+                        format!("invalid integer for --pdf-member: {value_text}"),
+                    )
+                })?;
                 pdf_member = Some(parsed);
             }
             "--order" => order = Some(value_text.clone()),
             "--output" => output = Some(PathBuf::from(value_text)),
-            _ => return Err(format!("unknown option: {flag}")),
+            "--sampler" => sampler_name = value_text.clone(),
+            "--points" => points = parse_count(value_text)?,
+            "--x-points" => grid[0] = parse_count(value_text)?,
+            "--q2-points" => grid[1] = parse_count(value_text)?,
+            "--scale-points" => grid[2] = parse_count(value_text)?,
+            "--training-config" => training_config = Some(PathBuf::from(value_text)),
+            "--hidden-layers" => {
+                hidden_layers = Some(
+                    value_text
+                        .split(',')
+                        .map(|width| parse_count(width.trim()))
+                        .collect::<std::result::Result<Vec<_>, _>>()?,
+                );
+            }
+            "--activation" => {
+                activation = Some(
+                    value_text
+                        .parse::<SurrogateActivation>()
+                        .map_err(|error| invalid_option("activation", error))?,
+                );
+            }
+            "--learning-rate" => learning_rate = Some(parse_positive_number(flag, value_text)?),
+            "--lr-schedule" => lr_schedule = Some(value_text.to_ascii_lowercase()),
+            "--lr-step-epochs" => lr_step_epochs = Some(parse_count(value_text)?),
+            "--lr-decay-factor" => lr_decay_factor = Some(parse_positive_number(flag, value_text)?),
+            "--min-learning-rate" => {
+                min_learning_rate = Some(parse_positive_number(flag, value_text)?);
+            }
+            "--epochs" => epochs = Some(parse_count(value_text)?),
+            "--batch-size" => {
+                batch_size = Some(if value_text == "full" {
+                    None
+                } else {
+                    Some(parse_count(value_text)?)
+                });
+            }
+            "--patience" => {
+                patience = Some(if value_text == "none" {
+                    None
+                } else {
+                    Some(parse_count(value_text)?)
+                });
+            }
+            "--ensemble-size" => ensemble_size = Some(parse_count(value_text)?),
+            "--max-uncertainty" => max_uncertainty = Some(parse_max_uncertainty(value_text)?),
+            "--q2-slope-weight" => {
+                q2_slope_weight = Some(parse_positive_number(flag, value_text)?);
+            }
+            "--seed" => {
+                seed = Some(value_text.parse::<u64>().map_err(|_| {
+                    invalid_option(
+                        "seed",
+                        format!("invalid non-negative integer for {flag}: {value_text}"),
+                    )
+                })?);
+            }
+            "--target-rel-error" => {
+                refinement.target_f2_relative_error = value_text
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|value| value.is_finite() && *value > 0.0)
+                    .ok_or_else(|| {
+                        invalid_option(
+                            "target-rel-error",
+                            format!("invalid positive number for {flag}: {value_text}"),
+                        )
+                    })?;
+                refine = true;
+            }
+            "--max-rounds" => {
+                refinement.max_rounds = parse_count(value_text)?;
+                refine = true;
+            }
+            "--refine-samples" => {
+                refinement.validation_samples = parse_count(value_text)?;
+                refine = true;
+            }
+            "--refine-points" => {
+                refinement.points_per_round = parse_count(value_text)?;
+                refine = true;
+            }
+            _ => {
+                return Err(unknown_option(
+                    flag,
+                    format!("unknown train-surrogate option: {flag}"),
+                ))
+            }
         }
         index += 2;
     }
 
-    Ok(TrainSurrogateCliArgs {
-        pdf_set: pdf_set.ok_or_else(|| "missing required option: --pdf-set".to_owned())?,
+    // Command-line options override the training config file.
+    let mut training = match &training_config {
+        Some(path) => SurrogateTrainingConfig::load(path)
+            .map_err(|error| invalid_option("training-config", error.to_string()))?,
+        None => SurrogateTrainingConfig::default(),
+    };
+    if let Some(hidden_layers) = hidden_layers {
+        training.architecture.hidden_layers = hidden_layers;
+    }
+    if let Some(activation) = activation {
+        training.architecture.activation = activation;
+    }
+    if let Some(learning_rate) = learning_rate {
+        training.learning_rate = learning_rate;
+    }
+    if let Some(name) = lr_schedule {
+        training.learning_rate_schedule = match name.as_str() {
+            "constant" => LearningRateSchedule::Constant,
+            "step" => LearningRateSchedule::Step {
+                step_epochs: 500,
+                factor: 0.5,
+            },
+            "cosine" => LearningRateSchedule::Cosine {
+                min_learning_rate: 1e-5,
+            },
+            _ => {
+                return Err(invalid_option(
+                    "lr-schedule",
+                    format!(
+                        "unknown learning-rate schedule '{name}'; expected constant, step or cosine"
+                    ),
+                ))
+            }
+        };
+    }
+    match &mut training.learning_rate_schedule {
+        LearningRateSchedule::Step {
+            step_epochs,
+            factor,
+        } => {
+            *step_epochs = lr_step_epochs.unwrap_or(*step_epochs);
+            *factor = lr_decay_factor.unwrap_or(*factor);
+        }
+        LearningRateSchedule::Cosine {
+            min_learning_rate: minimum,
+        } => *minimum = min_learning_rate.unwrap_or(*minimum),
+        LearningRateSchedule::Constant => {}
+    }
+    if let Some(epochs) = epochs {
+        training.epochs = epochs;
+    }
+    if let Some(batch_size) = batch_size {
+        training.batch_size = batch_size;
+    }
+    if let Some(patience) = patience {
+        training.early_stopping_patience = patience;
+    }
+    if let Some(ensemble_size) = ensemble_size {
+        training.ensemble_size = ensemble_size;
+    }
+    if max_uncertainty.is_some() {
+        training.max_relative_uncertainty = max_uncertainty;
+    }
+    if let Some(seed) = seed {
+        training.seed = seed;
+    }
+    training.constraints.fl_bounded_by_f2 |= constrain_fl;
+    training.constraints.zero_xf3 |= zero_xf3;
+    if q2_slope_weight.is_some() {
+        training.constraints.q2_slope_weight = q2_slope_weight;
+    }
+    // The options were checked as they were parsed, so what is left comes from
+    // the training config file.
+    training
+        .validate()
+        .map_err(|error| invalid_option("training-config", error))?;
+    let seed = training.seed;
+
+    let sampler = if sampler_name.eq_ignore_ascii_case("grid") {
+        DatasetSampler::Grid {
+            x_points: grid[0],
+            q2_points: grid[1],
+            scale_points: grid[2],
+        }
+    } else {
+        match sampler_name
+            .parse::<SamplingMethod>()
+            .map_err(|_| {
+                invalid_option(
+                    "sampler",
+                    format!(
+                        "unknown sampler '{sampler_name}'; expected grid, sobol or latin-hypercube"
+                    ),
+                )
+            })?
+        {
+            SamplingMethod::Sobol => DatasetSampler::Sobol { points, seed },
+            SamplingMethod::LatinHypercube => DatasetSampler::LatinHypercube { points, seed },
+        }
+    };
+    refinement.seed = seed;
+
+    Ok(TrainSurrogateCommand::Calculate(Box::new(TrainSurrogateCliArgs {
+        pdf_set: required_option(pdf_set, "--pdf-set")?,
         pdf_member: pdf_member.unwrap_or(0),
         order: order.unwrap_or_else(|| "NLO".to_string()),
-        output: output.ok_or_else(|| "missing required option: --output".to_owned())?,
-    })
+        output: required_option(output, "--output")?,
+        sampler,
+        training,
+        refinement: refine.then_some(refinement),
+        resume,
+    })))
 }
 
-fn run_train_surrogate(arguments: TrainSurrogateCliArgs) -> Result<()> {
-    use quark_sim::physics::apfel::ApfelStructureFunctionProvider;
+fn run_train_surrogate(
+    arguments: TrainSurrogateCliArgs,
+    card: &RunCard,
+) -> std::result::Result<(), CliError> {
+    use quark_sim::physics::apfel::{ApfelStructureFunctionProvider, DEFAULT_APFEL_BACKEND_PATH};
     use quark_sim::physics::structure_function_provider::PerturbativeOrder;
-    use quark_sim::physics::surrogate_training::{generate_dataset, train_and_save_surrogate};
+    use quark_sim::physics::surrogate_training::{
+        attach_q2_slopes, generate_dataset, load_dataset, load_refinement_history,
+        refine_surrogate, save_dataset, train_and_save_surrogate, DatasetDomain, DATASET_FILENAME,
+    };
     use std::str::FromStr;
 
+    let mut manifest = ManifestRecorder::start(card);
+
     println!("Starting surrogate dataset generation and training...");
-    
+
     let order = PerturbativeOrder::from_str(&arguments.order).map_err(|_| {
-        Error::Msg(format!("Invalid perturbative order: {}", arguments.order))
+        CliError::new(
+            "unsupported_order",
+            GuiErrorCategory::UnsupportedOrder,
+            Some("order"),
+            format!("Invalid perturbative order: {}", arguments.order),
+        )
     })?;
 
-    let provider = ApfelStructureFunctionProvider::new("physics-engine/build/apfel_cli");
-    
-    let dataset = generate_dataset(&provider, &arguments.pdf_set, arguments.pdf_member, order)
+    let provider = ApfelStructureFunctionProvider::new(DEFAULT_APFEL_BACKEND_PATH);
+    let dataset_path = arguments.output.join(DATASET_FILENAME);
+
+    let dataset = if arguments.resume {
+        let dataset = load_dataset(&dataset_path).map_err(|e| {
+            Error::Msg(format!("cannot resume from {}: {e}", dataset_path.display()))
+        })?;
+        println!("Resuming with {} points from {}", dataset.len(), dataset_path.display());
+        dataset
+    } else {
+        let dataset = generate_dataset(
+            &provider,
+            &arguments.pdf_set,
+            arguments.pdf_member,
+            order,
+            &DatasetDomain::default(),
+            &arguments.sampler,
+        )
         .map_err(|e| Error::Msg(e.to_string()))?;
+        std::fs::create_dir_all(&arguments.output)?;
+        save_dataset(&dataset_path, &dataset).map_err(|e| Error::Msg(e.to_string()))?;
+        dataset
+    };
+
+    let dataset = if arguments.training.constraints.q2_slope_weight.is_some()
+        && dataset.iter().any(|point| point.df2_dlnq2.is_none())
+    {
+        println!("Evaluating APFEL++ Q2 slopes for the slope penalty...");
+        let dataset = attach_q2_slopes(
+            &provider,
+            dataset,
+            &arguments.pdf_set,
+            arguments.pdf_member,
+            order,
+        );
+        save_dataset(&dataset_path, &dataset).map_err(|e| Error::Msg(e.to_string()))?;
+        dataset
+    } else {
+        dataset
+    };
 
-    train_and_save_surrogate(
-        dataset,
-        &arguments.output,
-        arguments.pdf_set,
-        arguments.pdf_member,
-        order,
-    ).map_err(|e| Error::Msg(e.to_string()))?;
+    match arguments.refinement {
+        Some(refinement) => {
+            let history = if arguments.resume {
+                load_refinement_history(&arguments.output).map_err(|e| Error::Msg(e.to_string()))?
+            } else {
+                Vec::new()
+            };
+            refine_surrogate(
+                &provider,
+                dataset,
+                history,
+                &arguments.output,
+                arguments.pdf_set,
+                arguments.pdf_member,
+                order,
+                &arguments.training,
+                &refinement,
+            )
+            .map_err(|e| Error::Msg(e.to_string()))?;
+        }
+        None => {
+            train_and_save_surrogate(
+                dataset,
+                &arguments.output,
+                arguments.pdf_set,
+                arguments.pdf_member,
+                order,
+                &arguments.training,
+            )
+            .map_err(|e| Error::Msg(e.to_string()))?;
+        }
+    }
+    write_run_card(card, &arguments.output)?;
+    manifest.record_seed("seed", arguments.training.seed);
+    write_run_manifest(manifest, &arguments.output)?;
 
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn parse(args: &[&str]) -> std::result::Result<Command, String> {
-        parse_command(args.iter().map(|value| (*value).to_string()))
+fn parse_validate_surrogate_command(
+    args: &[String],
+) -> std::result::Result<ValidateSurrogateCommand, CliError> {
+    if matches!(args, [flag] if flag == "-h" || flag == "--help") {
+        return Ok(ValidateSurrogateCommand::Help);
     }
 
-    #[test]
-    fn help_is_explicit_and_does_not_select_training() {
-        assert_eq!(parse(&["--help"]), Ok(Command::Help));
-        assert_eq!(parse(&["-h"]), Ok(Command::Help));
-    }
+    let mut model_dir = PathBuf::from("models/surrogate_v1");
+    let mut output = None;
+    let mut validation = SurrogateValidationConfig::default();
+    let mut index = 0;
 
-    #[test]
-    fn malformed_commands_are_rejected() {
-        assert!(parse(&["--load"]).is_err());
-        assert!(parse(&["--load-model"]).is_err());
-        assert!(parse(&["--unknown"]).is_err());
-        assert!(parse(&["--help", "extra"]).is_err());
-        assert!(parse(&["dis-kinematics"]).is_err());
+    while index < args.len() {
+        let flag = args[index].as_str();
+        if flag == "-h" || flag == "--help" {
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after validate-surrogate",
+            ));
+        }
+        let value_text = args
+            .get(index + 1)
+            .filter(|value| !value.starts_with("--"))
+            .ok_or_else(|| invalid_option(option_field(flag), format!("{flag} requires a value")))?;
+        let parse_count = |value: &str| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|count| *count > 0)
+                .ok_or_else(|| {
+                    invalid_option(
+                        option_field(flag),
+                        format!("invalid positive integer for {flag}: {value}"),
+                    )
+                })
+        };
+
+        match flag {
+            "--model-dir" => model_dir = PathBuf::from(value_text),
+            "--output" => output = Some(PathBuf::from(value_text)),
+            "--samples" => validation.sample_count = parse_count(value_text)?,
+            "--sampling" => {
+                validation.sampling = value_text
+                    .parse::<SamplingMethod>()
+                    .map_err(|error| invalid_option("sampling", error.to_string()))?;
+            }
+            "--seed" => {
+                validation.seed = value_text.parse::<u64>().map_err(|_| {
+                    invalid_option(
+                        option_field(flag),
+                        format!("invalid non-negative integer for {flag}: {value_text}"),
+                    )
+                })?;
+            }
+            "--x-bins" => validation.x_bins = parse_count(value_text)?,
+            "--q2-bins" => validation.q2_bins = parse_count(value_text)?,
+            "--worst" => validation.worst_point_count = parse_count(value_text)?,
+            _ => {
+                return Err(unknown_option(
+                    flag,
+                    format!("unknown validate-surrogate option: {flag}"),
+                ))
+            }
+        }
+        index += 2;
     }
 
-    #[test]
-    fn dis_help_is_explicit_and_side_effect_free() {
-        assert_eq!(
-            parse(&["dis-kinematics", "--help"]),
-            Ok(Command::DisKinematics(DisCommand::Help))
-        );
+    Ok(ValidateSurrogateCommand::Calculate(ValidateSurrogateCliArgs {
+        model_dir,
+        output: required_option(output, "--output")?,
+        validation,
+    }))
+}
+
+fn run_validate_surrogate(
+    arguments: ValidateSurrogateCliArgs,
+    card: &RunCard,
+) -> std::result::Result<(), CliError> {
+    use quark_sim::physics::apfel::{ApfelStructureFunctionProvider, DEFAULT_APFEL_BACKEND_PATH};
+    use quark_sim::physics::surrogate::SurrogateProvider;
+    use quark_sim::surrogate_validation_artifacts::write_surrogate_validation_artifacts;
+
+    let mut manifest = ManifestRecorder::start(card);
+
+    let surrogate = SurrogateProvider::load(&arguments.model_dir).map_err(|e| {
+        CliError::new(
+            "surrogate_unavailable",
+            GuiErrorCategory::SurrogateModelMissing,
+            Some("model-dir"),
+            e.to_string(),
+        )
+    })?;
+    let model = surrogate.config().clone();
+    if model.source_backend != "apfel" {
+        return Err(CliError::new(
+            "unknown_backend",
+            GuiErrorCategory::InvalidInput,
+            Some("model-dir"),
+            format!(
+                "Unsupported surrogate source backend: {}. Only 'apfel' is supported currently.",
+                model.source_backend
+            ),
+        ));
     }
+    let reference = ApfelStructureFunctionProvider::new(DEFAULT_APFEL_BACKEND_PATH);
 
-    #[test]
-    fn dis_options_are_parsed_in_any_order() {
-        let command = parse(&[
-            "dis-kinematics",
-            "--theta-deg",
-            "20",
-            "--proton-energy",
-            "920",
-            "--electron-energy",
-            "27.5",
-            "--scattered-electron-energy",
-            "15",
-        ]);
+    println!(
+        "Validating surrogate {} against {} on {} {} points...",
+        arguments.model_dir.display(),
+        model.source_backend,
+        arguments.validation.sample_count,
+        arguments.validation.sampling
+    );
+    let report = validate_surrogate(&surrogate, &reference, &model, &arguments.validation)
+        .map_err(|e| Error::Msg(e.to_string()))?;
+    let paths = write_surrogate_validation_artifacts(&arguments.output, &report)
+        .map_err(|e| Error::Msg(e.to_string()))?;
+    write_run_card(card, &arguments.output)?;
+    manifest.record_seed("seed", arguments.validation.seed);
+    write_run_manifest(manifest, &arguments.output)?;
 
-        assert_eq!(
-            command,
-            Ok(Command::DisKinematics(DisCommand::Calculate(DisCliArgs {
-                electron_energy: 27.5,
-                proton_energy: 920.0,
-                scattered_electron_energy: 15.0,
-                theta_deg: 20.0,
-            })))
+    let percent = |value: Option<f64>| {
+        value.map_or_else(|| "n/a".to_string(), |value| format!("{:.3}%", value * 100.0))
+    };
+    println!("Evaluated points: {}", report.evaluated_points);
+    println!("Failed points:    {}", report.failures.len());
+    for (label, quantiles) in [
+        ("|ΔF₂|/F₂  ", &report.statistics.f2_relative_error),
+        ("|ΔF_L|/F₂ ", &report.statistics.fl_error_over_f2),
+        ("|ΔxF₃|/F₂ ", &report.statistics.xf3_error_over_f2),
+    ] {
+        println!(
+            "{label} median {}, p95 {}, max {}",
+            percent(quantiles.p50),
+            percent(quantiles.p95),
+            percent(quantiles.maximum)
         );
     }
+    println!(
+        "Stored test max relative error on F₂: {:.3}%",
+        f64::from(model.test_max_rel_error) * 100.0
+    );
+    println!("Artifacts written to {}", paths.json.display());
+    Ok(())
+}
 
-    #[test]
-    fn malformed_dis_options_are_rejected() {
-        assert!(parse(&[
-            "dis-kinematics",
-            "--electron-energy",
-            "27.5",
-            "--electron-energy",
-            "30",
-        ])
-        .is_err());
-        assert!(parse(&["dis-kinematics", "--electron-energy", "not-a-number",]).is_err());
-        assert!(parse(&["dis-kinematics", "--unknown", "1"]).is_err());
-        assert!(parse(&[
-            "dis-kinematics",
-            "--electron-energy",
-            "--proton-energy",
-            "920"
-        ])
-        .is_err());
+fn parse_analyze_events_command(
+    args: &[String],
+) -> std::result::Result<AnalyzeEventsCommand, CliError> {
+    if matches!(args, [flag] if flag == "-h" || flag == "--help") {
+        return Ok(AnalyzeEventsCommand::Help);
     }
 
-    #[test]
-    fn cross_section_help_is_explicit_and_side_effect_free() {
-        assert_eq!(
-            parse(&["dis-cross-section", "--help"]),
-            Ok(Command::DisCrossSection(CrossSectionCommand::Help))
-        );
-    }
+    let mut input = None;
+    let mut output = None;
+    let mut analyses = vec![quark_sim::analysis::DisInclusive::NAME.to_owned()];
+    let mut summary = None;
+    let mut cross_section_pb = None;
+    let mut max_events = None;
+    let mut detector = None;
+    let mut detector_seed = None;
+    let mut index = 0;
 
-    #[test]
-    fn cross_section_options_are_parsed_in_any_order() {
-        let command = parse(&[
-            "dis-cross-section",
-            "--pdf-member",
-            "0",
-            "--q2",
-            "100",
-            "--proton-energy",
-            "920",
-            "--pdf-set",
-            "CT18LO",
-            "--x",
-            "0.01",
-            "--electron-energy",
-            "27.5",
-        ]);
+    while index < args.len() {
+        let flag = args[index].as_str();
+        if flag == "-h" || flag == "--help" {
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after analyze-events",
+            ));
+        }
+        let value_text = args
+            .get(index + 1)
+            .filter(|value| !value.starts_with("--"))
+            .ok_or_else(|| {
+                invalid_option(option_field(flag), format!("{flag} requires a value"))
+            })?;
 
-        assert_eq!(
-            command,
-            Ok(Command::DisCrossSection(CrossSectionCommand::Calculate(
-                CrossSectionCliArgs {
-                    x: 0.01,
-                    q2: 100.0,
-                    electron_energy: 27.5,
-                    proton_energy: 920.0,
-                    pdf_set: "CT18LO".to_string(),
-                    pdf_member: 0,
+        match flag {
+            "--input" => input = Some(PathBuf::from(value_text)),
+            "--output" => output = Some(PathBuf::from(value_text)),
+            "--analysis" => {
+                analyses = value_text
+                    .split(',')
+                    .map(|name| name.trim().to_owned())
+                    .filter(|name| !name.is_empty())
+                    .collect();
+                if analyses.is_empty() {
+                    return Err(invalid_option(
+                        "analysis",
+                        "--analysis must name at least one analysis",
+                    ));
                 }
-            )))
-        );
+            }
+            "--summary" => summary = Some(PathBuf::from(value_text)),
+            "--cross-section" => {
+                let value = parse_finite_cross_number(flag, value_text)?;
+                if value < 0.0 {
+                    return Err(invalid_option(
+                        option_field(flag),
+                        format!("{flag} must be non-negative, got {value}"),
+                    ));
+                }
+                cross_section_pb = Some(value);
+            }
+            "--max-events" => {
+                max_events = Some(
+                    value_text
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|count| *count > 0)
+                        .ok_or_else(|| {
+                            invalid_option(
+                                option_field(flag),
+                                format!("invalid positive integer for {flag}: {value_text}"),
+                            )
+                        })?,
+                );
+            }
+            "--detector" => detector = Some(PathBuf::from(value_text)),
+            "--detector-seed" => {
+                detector_seed = Some(value_text.parse::<u64>().map_err(|_| {
+                    invalid_option(
+                        option_field(flag),
+                        format!("invalid non-negative integer for {flag}: {value_text}"),
+                    )
+                })?);
+            }
+            _ => {
+                return Err(unknown_option(
+                    flag,
+                    format!("unknown analyze-events option: {flag}"),
+                ))
+            }
+        }
+        index += 2;
     }
 
-    #[test]
-    fn malformed_cross_section_options_are_rejected() {
-        assert!(parse(&["dis-cross-section"]).is_err());
-        assert!(parse(&["dis-cross-section", "--unknown", "1"]).is_err());
-        assert!(parse(&["dis-cross-section", "--x", "NaN"]).is_err());
-        assert!(parse(&["dis-cross-section", "--pdf-member", "-1"]).is_err());
-        assert!(parse(&["dis-cross-section", "--x", "0.01", "--x", "0.02"]).is_err());
-        assert!(parse(&["dis-cross-section", "--q2", "--electron-energy", "27.5"]).is_err());
+    Ok(AnalyzeEventsCommand::Calculate(AnalyzeEventsCliArgs {
+        input: required_option(input, "--input")?,
+        output: required_option(output, "--output")?,
+        analyses,
+        summary,
+        cross_section_pb,
+        max_events,
+        detector,
+        detector_seed,
+    }))
+}
+
+fn run_analyze_events(
+    arguments: AnalyzeEventsCliArgs,
+    card: &RunCard,
+) -> std::result::Result<(), CliError> {
+    use quark_sim::analysis::{
+        builtin_analysis, run_analyses, write_results, AnalysisOptions, CrossSection,
+        DetectorCard, DisDetector,
+    };
+    use quark_sim::hepmc3::Hepmc3Reader;
+
+    let mut manifest = ManifestRecorder::start(card);
+    reject_charged_current_events("input", &arguments.input)?;
+
+    let mut analyses = arguments
+        .analyses
+        .iter()
+        .map(|name| builtin_analysis(name))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| invalid_option("analysis", e.to_string()))?;
+
+    let summary = arguments.summary.clone().or_else(|| {
+        let sibling = arguments.input.with_file_name("summary.json");
+        sibling.is_file().then_some(sibling)
+    });
+    let cross_section = match (arguments.cross_section_pb, &summary) {
+        (Some(value_pb), _) => Some(CrossSection {
+            value_pb,
+            error_pb: 0.0,
+        }),
+        (None, Some(path)) => {
+            CrossSection::from_summary(path).map_err(|e| Error::Msg(e.to_string()))?
+        }
+        (None, None) => None,
+    };
+
+    let detector = match &arguments.detector {
+        Some(path) => {
+            let mut card = DetectorCard::from_file(path).map_err(invalid_detector_card)?;
+            if let Some(seed) = arguments.detector_seed {
+                card.seed = seed;
+            }
+            Some(card)
+        }
+        None if arguments.detector_seed.is_some() => {
+            return Err(CliError::new(
+                "missing_option",
+                GuiErrorCategory::InvalidInput,
+                Some("detector"),
+                "--detector-seed requires --detector",
+            ));
+        }
+        None => None,
+    };
+    if detector.is_none() && analyses.iter().any(|analysis| analysis.name() == DisDetector::NAME) {
+        eprintln!("Warning: {} needs --detector; its histograms will be empty", DisDetector::NAME);
+    }
+
+    let reader =
+        Hepmc3Reader::open(&arguments.input).map_err(|e| events_unreadable("input", &e))?;
+    println!("Analysing {}...", arguments.input.display());
+    if let Some(card) = &detector {
+        println!("Detector:        {} (seed {})", card.name, card.seed);
+        manifest.record_seed("detector-seed", card.seed);
+    }
+    let run = run_analyses(
+        reader,
+        &mut analyses,
+        &AnalysisOptions {
+            max_events: arguments.max_events,
+            cross_section,
+            detector,
+        },
+    )
+    .map_err(|e| Error::Msg(e.to_string()))?;
+    let (yoda, csv) =
+        write_results(&arguments.output, &analyses).map_err(|e| Error::Msg(e.to_string()))?;
+    let run_card = write_run_card(card, &arguments.output)?;
+    let manifest = write_run_manifest(manifest, &arguments.output)?;
+
+    println!("Events analysed: {}", run.events);
+    println!("Sum of weights:  {:.6e}", run.sum_of_weights);
+    match run.cross_section {
+        Some(cross_section) => println!(
+            "Cross section:   {:.6e} ± {:.2e} pb",
+            cross_section.value_pb, cross_section.error_pb
+        ),
+        None => println!("Cross section:   unknown; histograms hold summed event weights"),
+    }
+    for path in [&yoda, &csv, &run_card, &manifest] {
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn parse_unfold_command(args: &[String]) -> std::result::Result<UnfoldCommand, CliError> {
+    use quark_sim::analysis::{
+        KinematicBinning, ReconstructionMethod, UnfoldingError, UnfoldingMethod,
+        DEFAULT_BAYES_ITERATIONS,
+    };
+
+    if matches!(args, [flag] if flag == "-h" || flag == "--help") {
+        return Ok(UnfoldCommand::Help);
+    }
+
+    let mut response = None;
+    let mut data = None;
+    let mut detector = None;
+    let mut output = None;
+    let mut x_edges = None;
+    let mut q2_edges = None;
+    let mut method = "bayes".to_owned();
+    let mut iterations = None;
+    let mut reconstruction = ReconstructionMethod::Electron;
+    let mut data_seed = None;
+    let mut data_summary = None;
+    let mut cross_section_pb = None;
+    let mut max_events = None;
+    let mut index = 0;
+
+    let parse_edges = |flag: &str, value_text: &str| {
+        value_text
+            .split(',')
+            .map(|edge| parse_finite_cross_number(flag, edge.trim()))
+            .collect::<std::result::Result<Vec<_>, _>>()
+    };
+    let parse_positive_count = |flag: &str, value_text: &str| {
+        value_text
+            .parse::<usize>()
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or_else(|| {
+                invalid_option(
+                    option_field(flag),
+                    format!("invalid positive integer for {flag}: {value_text}"),
+                )
+            })
+    };
+
+    while index < args.len() {
+        let flag = args[index].as_str();
+        if flag == "-h" || flag == "--help" {
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after unfold",
+            ));
+        }
+        let value_text = args
+            .get(index + 1)
+            .filter(|value| !value.starts_with("--"))
+            .ok_or_else(|| {
+                invalid_option(option_field(flag), format!("{flag} requires a value"))
+            })?;
+
+        match flag {
+            "--response" => response = Some(PathBuf::from(value_text)),
+            "--data" => data = Some(PathBuf::from(value_text)),
+            "--detector" => detector = Some(PathBuf::from(value_text)),
+            "--output" => output = Some(PathBuf::from(value_text)),
+            "--x-bins" => x_edges = Some(parse_edges(flag, value_text)?),
+            "--q2-bins" => q2_edges = Some(parse_edges(flag, value_text)?),
+            "--method" => method = value_text.to_ascii_lowercase(),
+            "--iterations" => iterations = Some(parse_positive_count(flag, value_text)?),
+            "--reconstruction" => {
+                reconstruction = value_text
+                    .parse::<ReconstructionMethod>()
+                    .map_err(|error| invalid_option("reconstruction", error.to_string()))?;
+            }
+            "--data-seed" => {
+                data_seed = Some(value_text.parse::<u64>().map_err(|_| {
+                    invalid_option(
+                        option_field(flag),
+                        format!("invalid non-negative integer for {flag}: {value_text}"),
+                    )
+                })?);
+            }
+            "--data-summary" => data_summary = Some(PathBuf::from(value_text)),
+            "--cross-section" => {
+                let value = parse_finite_cross_number(flag, value_text)?;
+                if value < 0.0 {
+                    return Err(invalid_option(
+                        option_field(flag),
+                        format!("{flag} must be non-negative, got {value}"),
+                    ));
+                }
+                cross_section_pb = Some(value);
+            }
+            "--max-events" => max_events = Some(parse_positive_count(flag, value_text)?),
+            _ => {
+                return Err(unknown_option(
+                    flag,
+                    format!("unknown unfold option: {flag}"),
+                ))
+            }
+        }
+        index += 2;
+    }
+
+    let method = match method.as_str() {
+        "bayes" => UnfoldingMethod::IterativeBayes {
+            iterations: iterations.unwrap_or(DEFAULT_BAYES_ITERATIONS),
+        },
+        "inversion" | "bin-by-bin" if iterations.is_some() => {
+            return Err(invalid_option(
+                "iterations",
+                "--iterations only applies to --method bayes",
+            ));
+        }
+        "inversion" => UnfoldingMethod::MatrixInversion,
+        "bin-by-bin" => UnfoldingMethod::BinByBin,
+        _ => {
+            return Err(invalid_option(
+                "method",
+                format!(
+                    "unknown unfolding method '{method}'; \
+                     expected bayes, inversion or bin-by-bin"
+                ),
+            ))
+        }
+    };
+    let binning = KinematicBinning::new(
+        required_option(x_edges, "--x-bins")?,
+        required_option(q2_edges, "--q2-bins")?,
+    )
+    .map_err(|error| {
+        let field = match error {
+            UnfoldingError::InvalidBinning { axis: "x", .. } => "x-bins",
+            _ => "q2-bins",
+        };
+        invalid_option(field, error.to_string())
+    })?;
+
+    Ok(UnfoldCommand::Calculate(Box::new(UnfoldCliArgs {
+        response: required_option(response, "--response")?,
+        data: required_option(data, "--data")?,
+        detector: required_option(detector, "--detector")?,
+        output: required_option(output, "--output")?,
+        binning,
+        method,
+        reconstruction,
+        data_seed,
+        data_summary,
+        cross_section_pb,
+        max_events,
+    })))
+}
+
+fn run_unfold(arguments: UnfoldCliArgs, run_card: &RunCard) -> std::result::Result<(), CliError> {
+    use quark_sim::analysis::{
+        fill_measured, fill_response, CrossSection, Detector, DetectorCard, ResponseMatrix,
+        UnfoldingMetadata, UnfoldingReport,
+    };
+    use quark_sim::hepmc3::Hepmc3Reader;
+    use quark_sim::unfolding_artifacts::write_unfolding_artifacts;
+
+    let mut manifest = ManifestRecorder::start(run_card);
+    reject_charged_current_events("response", &arguments.response)?;
+    reject_charged_current_events("data", &arguments.data)?;
+
+    let message = |error: &dyn std::fmt::Display| Error::Msg(error.to_string());
+    let card = DetectorCard::from_file(&arguments.detector).map_err(invalid_detector_card)?;
+    let mut data_card = card.clone();
+    data_card.seed = arguments.data_seed.unwrap_or(card.seed.wrapping_add(1));
+
+    println!(
+        "Filling the response matrix from {}...",
+        arguments.response.display()
+    );
+    let mut response = ResponseMatrix::new(arguments.binning.clone());
+    let mut detector = Detector::new(card.clone()).map_err(|e| message(&e))?;
+    let response_run = fill_response(
+        Hepmc3Reader::open(&arguments.response).map_err(|e| events_unreadable("response", &e))?,
+        &mut response,
+        &mut detector,
+        arguments.reconstruction,
+        arguments.max_events,
+    )
+    .map_err(|e| message(&e))?;
+
+    println!("Reconstructing {}...", arguments.data.display());
+    let mut detector = Detector::new(data_card.clone()).map_err(|e| message(&e))?;
+    let (measured, mut data_run) = fill_measured(
+        Hepmc3Reader::open(&arguments.data).map_err(|e| events_unreadable("data", &e))?,
+        &arguments.binning,
+        &mut detector,
+        arguments.reconstruction,
+        arguments.max_events,
+    )
+    .map_err(|e| message(&e))?;
+
+    let summary = arguments.data_summary.clone().or_else(|| {
+        let sibling = arguments.data.with_file_name("summary.json");
+        sibling.is_file().then_some(sibling)
+    });
+    let summary_cross_section = match (arguments.cross_section_pb, &summary) {
+        (Some(value_pb), _) => Some(CrossSection {
+            value_pb,
+            error_pb: 0.0,
+        }),
+        (None, Some(path)) => CrossSection::from_summary(path).map_err(|e| message(&e))?,
+        (None, None) => None,
+    };
+    data_run.cross_section = summary_cross_section.or(data_run.cross_section);
+
+    let result = response
+        .unfold(&measured, arguments.method)
+        .map_err(|e| message(&e))?;
+    let report = UnfoldingReport::new(
+        UnfoldingMetadata {
+            method: arguments.method,
+            reconstruction: arguments.reconstruction,
+            detector: card.name.clone(),
+            response_seed: card.seed,
+            data_seed: data_card.seed,
+            response_events: response_run.events,
+            data_events: data_run.events,
+            data_cross_section: data_run.cross_section,
+            s_gev2: response.s_gev2,
+        },
+        &response,
+        &measured,
+        &data_run,
+        &result,
+    );
+    let paths = write_unfolding_artifacts(&arguments.output, &report).map_err(|e| message(&e))?;
+    let run_card_path = write_run_card(run_card, &arguments.output)?;
+    manifest.record_seed("response-seed", card.seed);
+    manifest.record_seed("data-seed", data_card.seed);
+    let manifest_path = write_run_manifest(manifest, &arguments.output)?;
+
+    println!("Method:          {}", arguments.method);
+    println!("Reconstruction:  {}", arguments.reconstruction);
+    println!(
+        "Events:          {} response, {} data",
+        response_run.events, data_run.events
+    );
+    println!();
+    println!(
+        "{:>10} {:>10} {:>8} {:>9} {:>14} {:>12}",
+        "x", "Q² [GeV²]", "purity", "stability", "unfolded", "σ_r"
+    );
+    let format_option = |value: Option<f64>, precision: usize| {
+        value.map_or_else(|| "-".to_owned(), |value| format!("{value:.precision$}"))
+    };
+    for bin in &report.bins {
+        println!(
+            "{:>10.3e} {:>10.1} {:>8} {:>9} {:>14} {:>12}",
+            bin.x_centre,
+            bin.q2_centre,
+            format_option(bin.purity, 3),
+            format_option(bin.stability, 3),
+            format!("{:.4e}", bin.unfolded),
+            format_option(bin.reduced_cross_section, 4),
+        );
+    }
+    if data_run.cross_section.is_none() {
+        println!("Data cross section unknown; no σ or σ_r was calculated.");
+    }
+    println!();
+    for path in [
+        &paths.bins_csv,
+        &paths.response_csv,
+        &paths.covariance_csv,
+        &paths.json,
+        &run_card_path,
+        &manifest_path,
+    ] {
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn parse_reweight_events_command(
+    args: &[String],
+) -> std::result::Result<ReweightEventsCommand, CliError> {
+    if matches!(args, [flag] if flag == "-h" || flag == "--help") {
+        return Ok(ReweightEventsCommand::Help);
+    }
+
+    let mut input = None;
+    let mut output = None;
+    let mut pdfs = Vec::new();
+    let mut nominal_pdf = None;
+    let mut summary = None;
+    let mut cross_section_pb = None;
+    let mut max_events = None;
+    let mut index = 0;
+
+    while index < args.len() {
+        let flag = args[index].as_str();
+        if flag == "-h" || flag == "--help" {
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after reweight-events",
+            ));
+        }
+        let value_text = args
+            .get(index + 1)
+            .filter(|value| !value.starts_with("--"))
+            .ok_or_else(|| {
+                invalid_option(option_field(flag), format!("{flag} requires a value"))
+            })?;
+
+        match flag {
+            "--input" => input = Some(PathBuf::from(value_text)),
+            "--output" => output = Some(PathBuf::from(value_text)),
+            "--pdf" => {
+                let selection = parse_pdf_selection(flag, value_text)?;
+                if pdfs.contains(&selection) {
+                    return Err(invalid_option(
+                        option_field(flag),
+                        format!("{flag} {value_text} is given more than once"),
+                    ));
+                }
+                pdfs.push(selection);
+            }
+            "--nominal-pdf" => nominal_pdf = Some(parse_pdf_selection(flag, value_text)?),
+            "--summary" => summary = Some(PathBuf::from(value_text)),
+            "--cross-section" => {
+                let value = parse_finite_cross_number(flag, value_text)?;
+                if value < 0.0 {
+                    return Err(invalid_option(
+                        option_field(flag),
+                        format!("{flag} must be non-negative, got {value}"),
+                    ));
+                }
+                cross_section_pb = Some(value);
+            }
+            "--max-events" => {
+                max_events = Some(
+                    value_text
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|count| *count > 0)
+                        .ok_or_else(|| {
+                            invalid_option(
+                                option_field(flag),
+                                format!("invalid positive integer for {flag}: {value_text}"),
+                            )
+                        })?,
+                );
+            }
+            _ => {
+                return Err(unknown_option(
+                    flag,
+                    format!("unknown reweight-events option: {flag}"),
+                ))
+            }
+        }
+        index += 2;
+    }
+
+    if pdfs.is_empty() {
+        return Err(missing_option("--pdf", "missing required option: --pdf"));
+    }
+    Ok(ReweightEventsCommand::Calculate(ReweightEventsCliArgs {
+        input: required_option(input, "--input")?,
+        output: required_option(output, "--output")?,
+        pdfs,
+        nominal_pdf,
+        summary,
+        cross_section_pb,
+        max_events,
+    }))
+}
+
+/// Parse `SET` or `SET/MEMBER`.
+fn parse_pdf_selection(
+    flag: &str,
+    value_text: &str,
+) -> std::result::Result<PdfSelection, CliError> {
+    let (set_name, member) = match value_text.rsplit_once('/') {
+        Some((set_name, member_text)) => {
+            let member = member_text
+                .parse::<i32>()
+                .ok()
+                .filter(|member| *member >= 0)
+                .ok_or_else(|| {
+                    invalid_option(
+                        option_field(flag),
+                        format!(
+                            "invalid PDF member for {flag}: {value_text}; \
+                             expected SET or SET/MEMBER"
+                        ),
+                    )
+                })?;
+            (set_name, Some(member))
+        }
+        None => (value_text, None),
+    };
+    if set_name.trim().is_empty() {
+        return Err(invalid_option(
+            option_field(flag),
+            format!("{flag} requires a PDF set name"),
+        ));
+    }
+    Ok(PdfSelection {
+        set_name: set_name.trim().to_owned(),
+        member,
+    })
+}
+
+/// PDF set and member recorded in a run's `metadata.json`.
+fn nominal_pdf_from_metadata(path: &Path) -> Result<PdfSelection> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| Error::Msg(format!("{}: {e}", path.display())))?;
+    let metadata: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|e| Error::Msg(format!("invalid run metadata {}: {e}", path.display())))?;
+    let set_name = metadata
+        .get("pdf_set")
+        .and_then(|value| value.as_str())
+        .filter(|name| !name.is_empty() && *name != "default")
+        .ok_or_else(|| {
+            Error::Msg(format!(
+                "{} names no LHAPDF set; pass --nominal-pdf",
+                path.display()
+            ))
+        })?;
+    let member = metadata
+        .get("pdf_member")
+        .and_then(|value| value.as_i64())
+        .unwrap_or(0);
+    Ok(PdfSelection {
+        set_name: set_name.to_owned(),
+        member: Some(i32::try_from(member).map_err(|_| {
+            Error::Msg(format!("invalid pdf_member {member} in {}", path.display()))
+        })?),
+    })
+}
+
+fn run_reweight_events(
+    arguments: ReweightEventsCliArgs,
+    card: &RunCard,
+) -> std::result::Result<(), CliError> {
+    use quark_sim::analysis::{
+        reweight_events, CrossSection, ReweightingSummary, ReweightingTarget,
+    };
+    use quark_sim::hepmc3::{Hepmc3Output, Hepmc3Reader};
+    use quark_sim::physics::{pdf_weight_name, PdfErrorSet, PdfReweighter, PdfSetInfo};
+    use quark_sim::reweighting_artifacts::{
+        prepare_reweighting_artifacts, write_reweighting_summary,
+    };
+
+    let manifest = ManifestRecorder::start(card);
+
+    let message = |error: &dyn std::fmt::Display| Error::Msg(error.to_string());
+    let nominal = match arguments.nominal_pdf.clone() {
+        Some(selection) => selection,
+        None => {
+            let metadata = arguments.input.with_file_name("metadata.json");
+            if !metadata.is_file() {
+                return Err(CliError::new(
+                    "missing_option",
+                    GuiErrorCategory::InvalidInput,
+                    Some("nominal-pdf"),
+                    format!(
+                        "the nominal PDF is unknown: {} does not exist; pass --nominal-pdf",
+                        metadata.display()
+                    ),
+                ));
+            }
+            nominal_pdf_from_metadata(&metadata)?
+        }
+    };
+    let nominal_member = nominal.member.unwrap_or(0);
+    let nominal_name = pdf_weight_name(&nominal.set_name, nominal_member);
+
+    let mut reweighter = PdfReweighter::new(Box::new(
+        LhapdfProvider::new(&nominal.set_name, nominal_member)?,
+    ));
+    let mut targets = Vec::new();
+    for selection in &arguments.pdfs {
+        let target = match selection.member {
+            Some(member) => ReweightingTarget {
+                set_name: selection.set_name.clone(),
+                members: vec![member],
+                errors: None,
+            },
+            None => {
+                let info = PdfSetInfo::load(&selection.set_name)?;
+                let errors = PdfErrorSet::from_lhapdf(&info);
+                if errors.is_none() {
+                    eprintln!(
+                        "Warning: {} has no supported ErrorType; no PDF uncertainty is calculated",
+                        info.set_name
+                    );
+                }
+                ReweightingTarget {
+                    set_name: info.set_name,
+                    members: (0..info.member_count).map(|member| member as i32).collect(),
+                    errors,
+                }
+            }
+        };
+        println!(
+            "Loading {} member(s) of {}...",
+            target.members.len(),
+            target.set_name
+        );
+        for (&member, name) in target.members.iter().zip(target.weight_names()) {
+            let provider = LhapdfProvider::new(&target.set_name, member)?;
+            reweighter
+                .add_member(name, Box::new(provider))
+                .map_err(|e| message(&e))?;
+        }
+        targets.push(target);
+    }
+
+    let compressed = arguments
+        .input
+        .extension()
+        .is_some_and(|extension| extension == "gz");
+    let paths =
+        prepare_reweighting_artifacts(&arguments.output, compressed).map_err(|e| message(&e))?;
+    println!(
+        "Reweighting {} from {nominal_name} to {} weight(s)...",
+        arguments.input.display(),
+        reweighter.len()
+    );
+    let reweighted = Hepmc3Reader::open(&arguments.input)
+        .map_err(|e| events_unreadable("input", &e))
+        .and_then(|reader| {
+            let output = Hepmc3Output::create(&paths.events).map_err(|e| message(&e))?;
+            let (output, run) = reweight_events(reader, output, &reweighter, arguments.max_events)
+                .map_err(|e| message(&e))?;
+            output.close().map_err(|e| message(&e))?;
+            Ok::<_, CliError>(run)
+        });
+    let mut run = match reweighted {
+        Ok(run) => run,
+        Err(error) => {
+            let _ = std::fs::remove_file(&paths.events);
+            return Err(error);
+        }
+    };
+    if run.nominal.events == 0 {
+        return Err(CliError::new(
+            "invalid_run",
+            GuiErrorCategory::ParseError,
+            Some("input"),
+            format!("{} contains no events", arguments.input.display()),
+        ));
+    }
+
+    let summary_path = arguments.summary.clone().or_else(|| {
+        let sibling = arguments.input.with_file_name("summary.json");
+        sibling.is_file().then_some(sibling)
+    });
+    let summary_cross_section = match (arguments.cross_section_pb, &summary_path) {
+        (Some(value_pb), _) => Some(CrossSection {
+            value_pb,
+            error_pb: 0.0,
+        }),
+        (None, Some(path)) => CrossSection::from_summary(path).map_err(|e| message(&e))?,
+        (None, None) => None,
+    };
+    run.nominal.cross_section = summary_cross_section.or(run.nominal.cross_section);
+
+    let summary = ReweightingSummary::new(nominal_name.clone(), &targets, &run);
+    write_reweighting_summary(&paths, &summary).map_err(|e| message(&e))?;
+    let run_card = write_run_card(card, &arguments.output)?;
+    let manifest = write_run_manifest(manifest, &arguments.output)?;
+
+    println!("Nominal PDF:  {nominal_name}");
+    println!("Events:       {}", summary.events);
+    match summary.nominal_cross_section {
+        Some(cross_section) => println!(
+            "Nominal σ:    {:.6e} ± {:.2e} pb",
+            cross_section.value_pb, cross_section.error_pb
+        ),
+        None => println!("Nominal σ:    unknown; only weight ratios are reported"),
+    }
+    println!();
+    for member in summary.members.iter().filter(|member| {
+        summary
+            .sets
+            .iter()
+            .all(|set| set.set_name != member.set_name)
+    }) {
+        match member.cross_section_pb {
+            Some(value) => println!(
+                "{:<32} ratio {:.5}   σ = {value:.6e} pb",
+                member.weight_name, member.ratio
+            ),
+            None => println!("{:<32} ratio {:.5}", member.weight_name, member.ratio),
+        }
+    }
+    for set in &summary.sets {
+        println!(
+            "{:<32} ratio {:.5} +{:.5} -{:.5} ({}, {} error members)",
+            set.set_name,
+            set.ratio.central,
+            set.ratio.plus,
+            set.ratio.minus,
+            set.errors.error_type,
+            set.errors.error_members
+        );
+        if let Some(cross_section) = set.cross_section_pb {
+            println!(
+                "{:<32} σ = {:.6e} +{:.3e} -{:.3e} pb",
+                "", cross_section.central, cross_section.plus, cross_section.minus
+            );
+        }
+    }
+    println!();
+    for path in [&paths.events, &paths.csv, &paths.json, &run_card, &manifest] {
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// Histogram bins of `compare-runs` without `--bins`.
+const DEFAULT_COMPARISON_BINS: usize = 40;
+
+fn parse_compare_runs_command(
+    args: &[String],
+) -> std::result::Result<CompareRunsCommand, CliError> {
+    if matches!(args, [flag] if flag == "-h" || flag == "--help") {
+        return Ok(CompareRunsCommand::Help);
+    }
+
+    let mut runs = Vec::new();
+    let mut labels = Vec::new();
+    let mut output = None;
+    let mut bins = None;
+    let mut index = 0;
+
+    while index < args.len() {
+        let flag = args[index].as_str();
+        if flag == "-h" || flag == "--help" {
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after compare-runs",
+            ));
+        }
+        let value_text = args
+            .get(index + 1)
+            .filter(|value| !value.starts_with("--"))
+            .ok_or_else(|| {
+                invalid_option(option_field(flag), format!("{flag} requires a value"))
+            })?;
+
+        match flag {
+            "--run" => runs.push(PathBuf::from(value_text)),
+            "--label" => labels.push(value_text.clone()),
+            "--output" => output = Some(PathBuf::from(value_text)),
+            "--bins" => {
+                let value = value_text.parse::<usize>().map_err(|_| {
+                    invalid_option(
+                        option_field(flag),
+                        format!("invalid positive integer for {flag}: {value_text}"),
+                    )
+                })?;
+                if value == 0 {
+                    return Err(invalid_option(
+                        option_field(flag),
+                        format!("{flag} must be positive, got {value}"),
+                    ));
+                }
+                bins = Some(value);
+            }
+            _ => {
+                return Err(unknown_option(
+                    flag,
+                    format!("unknown compare-runs option: {flag}"),
+                ))
+            }
+        }
+        index += 2;
+    }
+
+    if runs.len() < 2 {
+        return Err(missing_option(
+            "--run",
+            format!(
+                "missing required compare-runs option: --run must be given at least twice, got {}",
+                runs.len()
+            ),
+        ));
+    }
+    if !labels.is_empty() && labels.len() != runs.len() {
+        return Err(invalid_option(
+            "label",
+            format!(
+                "--label must be given once per --run: {} labels for {} runs",
+                labels.len(),
+                runs.len()
+            ),
+        ));
+    }
+    let mut distinct = std::collections::BTreeSet::new();
+    if let Some(label) = labels.iter().find(|label| !distinct.insert(label.as_str())) {
+        return Err(invalid_option(
+            "label",
+            format!("--label {label} is given twice"),
+        ));
+    }
+
+    Ok(CompareRunsCommand::Compare(CompareRunsCliArgs {
+        runs,
+        labels,
+        output: output.ok_or_else(|| {
+            missing_option("--output", "missing required compare-runs option: --output")
+        })?,
+        bins: bins.unwrap_or(DEFAULT_COMPARISON_BINS),
+    }))
+}
+
+/// Load every run, compare them and write the artifact bundle.
+fn run_compare_runs(
+    arguments: &CompareRunsCliArgs,
+    card: &RunCard,
+) -> std::result::Result<(), CliError> {
+    use quark_sim::analysis::{compare_runs, ComparedRun};
+    use quark_sim::run_comparison_artifacts::write_run_comparison_artifacts;
+
+    let manifest = ManifestRecorder::start(card);
+
+    let labels = comparison_labels(arguments);
+    let runs = arguments
+        .runs
+        .iter()
+        .zip(labels)
+        .map(|(directory, label)| ComparedRun::load(directory, label))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let comparison = compare_runs(&runs, arguments.bins)?;
+    let paths = write_run_comparison_artifacts(&arguments.output, &comparison)?;
+    let run_card = write_run_card(card, &arguments.output)?;
+    let manifest = write_run_manifest(manifest, &arguments.output)?;
+
+    for run in &comparison.runs {
+        match run.events {
+            Some(events) => println!("{:<20} {events} events", run.label),
+            None => println!("{:<20} no inclusive_observables.csv", run.label),
+        }
+    }
+    println!();
+    if comparison.configuration_differences.is_empty() {
+        println!("The configurations do not differ.");
+    } else {
+        println!("Configuration differences:");
+        for difference in &comparison.configuration_differences {
+            let values: Vec<String> = difference
+                .values
+                .iter()
+                .map(|value| {
+                    value
+                        .as_ref()
+                        .map_or_else(|| "-".to_owned(), ToString::to_string)
+                })
+                .collect();
+            println!("  {:<40} {}", difference.key, values.join(" | "));
+        }
+    }
+    match &comparison.reference {
+        Some(reference) => {
+            println!();
+            println!("Compatibility with {reference} (p-values):");
+            for test in &comparison.tests {
+                let p_value = |value: Option<f64>| {
+                    value.map_or_else(|| "n/a".to_owned(), |value| format!("{value:.3e}"))
+                };
+                println!(
+                    "  {:<4} {:<20} χ² {}  KS {}",
+                    test.observable.as_str(),
+                    test.label,
+                    p_value(test.chi_square.map(|chi| chi.p_value)),
+                    p_value(test.kolmogorov_smirnov.map(|ks| ks.p_value)),
+                );
+            }
+        }
+        None => {
+            println!();
+            println!(
+                "Fewer than two runs have inclusive_observables.csv; no distributions compared."
+            );
+        }
+    }
+    println!();
+    for path in paths.iter().chain([run_card.as_path(), manifest.as_path()]) {
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// `--label` values, or the run directory names made unique by their
+/// position.
+fn comparison_labels(arguments: &CompareRunsCliArgs) -> Vec<String> {
+    if !arguments.labels.is_empty() {
+        return arguments.labels.clone();
+    }
+    let names: Vec<String> = arguments
+        .runs
+        .iter()
+        .map(|run| {
+            run.components().as_path().file_name().map_or_else(
+                || run.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            )
+        })
+        .collect();
+    names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            if names.iter().filter(|other| *other == name).count() > 1 {
+                format!("{name}_{}", index + 1)
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
+}
+
+fn parse_doctor_command(args: &[String]) -> std::result::Result<DoctorCommand, CliError> {
+    if matches!(args, [flag] if flag == "-h" || flag == "--help") {
+        return Ok(DoctorCommand::Help);
+    }
+
+    let mut options = DoctorOptions::default();
+    let mut index = 0;
+
+    while index < args.len() {
+        let flag = args[index].as_str();
+        if flag == "-h" || flag == "--help" {
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after doctor",
+            ));
+        }
+        let value_text = args
+            .get(index + 1)
+            .filter(|value| !value.starts_with("--"))
+            .ok_or_else(|| {
+                invalid_option(option_field(flag), format!("{flag} requires a value"))
+            })?;
+
+        let slot = match flag {
+            "--apfel-backend" => &mut options.apfel_backend,
+            "--pythia-backend" => &mut options.pythia_backend,
+            "--model-dir" => &mut options.model_dir,
+            "--venv" => &mut options.venv,
+            _ => {
+                return Err(unknown_option(
+                    flag,
+                    format!("unknown doctor option: {flag}"),
+                ))
+            }
+        };
+        *slot = PathBuf::from(value_text);
+        index += 2;
+    }
+
+    Ok(DoctorCommand::Run(options))
+}
+
+/// Print the environment report and fail with the first failed check.
+fn run_doctor(options: &DoctorOptions, format: OutputFormat) -> std::result::Result<(), CliError> {
+    let report = DoctorReport::collect(options);
+    match format {
+        OutputFormat::Text => print!("{}", report.to_text()),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string(&report).map_err(std::io::Error::other)?
+        ),
+        OutputFormat::Csv => report.write_csv(std::io::stdout().lock())?,
+    }
+
+    let failures = report.failures();
+    let Some(first) = failures.first() else {
+        return Ok(());
+    };
+    Err(CliError::new(
+        "environment_check_failed",
+        first.category.clone().unwrap_or(GuiErrorCategory::Unknown),
+        None,
+        format!(
+            "{} of {} environment checks failed, first {}: {}",
+            failures.len(),
+            report.checks.len(),
+            first.name,
+            first.detail
+        ),
+    ))
+}
+
+fn parse_verify_run_command(args: &[String]) -> std::result::Result<VerifyRunCommand, CliError> {
+    if matches!(args, [flag] if flag == "-h" || flag == "--help") {
+        return Ok(VerifyRunCommand::Help);
+    }
+
+    let mut run = None;
+    let mut rerun = false;
+    let mut rerun_output = None;
+    let mut relative = None;
+    let mut absolute = None;
+    let mut index = 0;
+
+    while index < args.len() {
+        let flag = args[index].as_str();
+        if flag == "-h" || flag == "--help" {
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after verify-run",
+            ));
+        }
+        if flag == "--rerun" {
+            if rerun {
+                return Err(invalid_option(
+                    "rerun",
+                    "duplicate verify-run option: --rerun",
+                ));
+            }
+            rerun = true;
+            index += 1;
+            continue;
+        }
+        let value_text = args
+            .get(index + 1)
+            .filter(|value| !value.starts_with("--"))
+            .ok_or_else(|| {
+                invalid_option(option_field(flag), format!("{flag} requires a value"))
+            })?;
+
+        match flag {
+            "--run" => set_verify_run_option(&mut run, PathBuf::from(value_text), flag)?,
+            "--rerun-output" => {
+                set_verify_run_option(&mut rerun_output, PathBuf::from(value_text), flag)?;
+            }
+            "--rel-tol" | "--abs-tol" => {
+                let value = parse_finite_cross_number(flag, value_text)?;
+                if value < 0.0 {
+                    return Err(invalid_option(
+                        option_field(flag),
+                        format!("{flag} must be non-negative, got {value}"),
+                    ));
+                }
+                let slot = if flag == "--rel-tol" {
+                    &mut relative
+                } else {
+                    &mut absolute
+                };
+                set_verify_run_option(slot, value, flag)?;
+            }
+            _ => {
+                return Err(unknown_option(
+                    flag,
+                    format!("unknown verify-run option: {flag}"),
+                ))
+            }
+        }
+        index += 2;
+    }
+
+    let run =
+        run.ok_or_else(|| missing_option("--run", "missing required verify-run option: --run"))?;
+    let rerun = rerun_output.or_else(|| {
+        rerun.then(|| {
+            let run = run.components().as_path().display().to_string();
+            PathBuf::from(format!("{run}_rerun"))
+        })
+    });
+    let defaults = Tolerances::default();
+    Ok(VerifyRunCommand::Verify(VerifyRunCliArgs {
+        run,
+        rerun,
+        tolerances: Tolerances {
+            relative: relative.unwrap_or(defaults.relative),
+            absolute: absolute.unwrap_or(defaults.absolute),
+        },
+    }))
+}
+
+fn set_verify_run_option<T>(
+    slot: &mut Option<T>,
+    value: T,
+    flag: &str,
+) -> std::result::Result<(), CliError> {
+    if slot.replace(value).is_some() {
+        Err(invalid_option(
+            option_field(flag),
+            format!("duplicate verify-run option: {flag}"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Recheck a run against its manifest, optionally repeating it, and fail when
+/// any artifact does not match.
+fn run_verify_run(
+    arguments: &VerifyRunCliArgs,
+    format: OutputFormat,
+) -> std::result::Result<(), CliError> {
+    let manifest = RunManifest::load(&arguments.run)?;
+    let digests = verify_digests(&arguments.run, &manifest);
+    let (rerun, comparisons) = match &arguments.rerun {
+        Some(output) => {
+            let directory = rerun_recorded_run(&manifest, output)?;
+            let repeat = RunManifest::load(&directory)?;
+            let comparisons = compare_artifacts(
+                &arguments.run,
+                &manifest,
+                &directory,
+                &repeat,
+                arguments.tolerances,
+            );
+            (Some(directory), comparisons)
+        }
+        None => (None, Vec::new()),
+    };
+
+    let report = VerifyReport::new(arguments.run.clone(), digests, rerun, comparisons);
+    match format {
+        OutputFormat::Text => print!("{}", report.to_text()),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string(&report).map_err(std::io::Error::other)?
+        ),
+        OutputFormat::Csv => report.write_csv(std::io::stdout().lock())?,
+    }
+
+    if report.ok {
+        return Ok(());
+    }
+    Err(CliError::new(
+        "run_verification_failed",
+        GuiErrorCategory::ProcessFailed,
+        None,
+        format!(
+            "{} of {} checks of run '{}' failed",
+            report.failures(),
+            report.digests.len() + report.comparisons.len(),
+            arguments.run.display()
+        ),
+    ))
+}
+
+/// Run the recorded card again with this executable, writing into `output`,
+/// and return the directory holding the repetition's manifest.
+fn rerun_recorded_run(
+    manifest: &RunManifest,
+    output: &Path,
+) -> std::result::Result<PathBuf, CliError> {
+    let rerun_failed = |message: String| {
+        CliError::new(
+            "rerun_failed",
+            GuiErrorCategory::ProcessFailed,
+            None,
+            message,
+        )
+    };
+    if output.exists() {
+        return Err(CliError::new(
+            "output_unwritable",
+            GuiErrorCategory::InvalidInput,
+            Some("rerun-output"),
+            format!(
+                "refusing to rerun into existing path '{}'",
+                output.display()
+            ),
+        ));
+    }
+    let mut card = manifest.inputs.clone();
+    if card.command.is_none() {
+        return Err(CliError::new(
+            "invalid_manifest",
+            GuiErrorCategory::ParseError,
+            Some("run"),
+            "the run card of the manifest names no command".to_string(),
+        ));
+    }
+    card.options.remove("resume");
+    card.set_option("output", output.display().to_string());
+
+    let executable = std::env::current_exe()
+        .map_err(|e| rerun_failed(format!("failed to locate this executable: {e}")))?;
+    let result = std::process::Command::new(executable)
+        .args(card.command_line())
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .output()
+        .map_err(|e| rerun_failed(format!("failed to start the rerun: {e}")))?;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let last_line = stderr.lines().rev().find(|line| !line.trim().is_empty());
+        return Err(rerun_failed(format!(
+            "the rerun exited with {}: {}",
+            result.status,
+            last_line.unwrap_or("no error output")
+        )));
+    }
+
+    // generate-dis-events writes into a new run directory below --output.
+    if output.join(MANIFEST_FILENAME).is_file() {
+        return Ok(output.to_owned());
+    }
+    let mut candidates = std::fs::read_dir(output)
+        .map_err(|e| rerun_failed(format!("failed to read '{}': {e}", output.display())))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.join(MANIFEST_FILENAME).is_file());
+    match (candidates.next(), candidates.next()) {
+        (Some(directory), None) => Ok(directory),
+        _ => Err(rerun_failed(format!(
+            "the rerun wrote no single {MANIFEST_FILENAME} below '{}'",
+            output.display()
+        ))),
+    }
+}
+
+/// Flavours of `pdf table` and `pdf plot` without `--flavours`.
+const DEFAULT_PDF_FLAVOURS: [PdfFlavour; 6] = [
+    PdfFlavour::UpValence,
+    PdfFlavour::DownValence,
+    PdfFlavour::AntiUp,
+    PdfFlavour::AntiDown,
+    PdfFlavour::Strange,
+    PdfFlavour::Gluon,
+];
+
+/// The default `pdf plot` x grid: the x range of the set, kept away from
+/// the grid edges where the valence distributions vanish or blow up.
+const DEFAULT_PDF_PLOT_X_MIN: f64 = 1.0e-5;
+const DEFAULT_PDF_PLOT_X_MAX: f64 = 0.9;
+const DEFAULT_PDF_PLOT_POINTS: usize = 100;
+
+fn parse_pdf_command(args: &[String]) -> std::result::Result<PdfCommand, CliError> {
+    let (action, args) = match args {
+        [flag] if flag == "-h" || flag == "--help" => return Ok(PdfCommand::Help),
+        [] => {
+            return Err(missing_options(format!(
+                "pdf requires an action: list, info, table or plot\n\n{PDF_HELP}"
+            )))
+        }
+        [action, remaining @ ..] => (action.as_str(), remaining),
+    };
+    let allowed: &[&str] = match action {
+        "list" => &[],
+        "info" => &["--pdf-set", "--pdf-member"],
+        "table" => &[
+            "--pdf-set",
+            "--pdf-member",
+            "--flavours",
+            "--x",
+            "--q2",
+            "--points",
+            "--x-grid",
+            "--q2-grid",
+            "--output",
+        ],
+        "plot" => &[
+            "--pdf-set",
+            "--pdf-member",
+            "--flavours",
+            "--q2",
+            "--x-grid",
+            "--output",
+            "--uncertainty",
+        ],
+        _ => {
+            return Err(CliError::new(
+                "unknown_command",
+                GuiErrorCategory::InvalidInput,
+                None,
+                format!("unknown pdf action: {action}; expected list, info, table or plot"),
+            ))
+        }
+    };
+
+    let mut pdf_set = None;
+    let mut pdf_member = None;
+    let mut flavours = None;
+    let mut kinematics = KinematicOptions::default();
+    let mut uncertainty = false;
+    let mut index = 0;
+
+    while index < args.len() {
+        let flag = args[index].as_str();
+        if flag == "-h" || flag == "--help" {
+            return Err(invalid_option(
+                "help",
+                "--help must be used by itself after pdf",
+            ));
+        }
+        if !allowed.contains(&flag) {
+            return Err(unknown_option(
+                flag,
+                format!("unknown pdf {action} option: {flag}"),
+            ));
+        }
+        if flag == "--uncertainty" {
+            if uncertainty {
+                return Err(invalid_option(
+                    "uncertainty",
+                    "duplicate pdf option: --uncertainty",
+                ));
+            }
+            uncertainty = true;
+            index += 1;
+            continue;
+        }
+
+        let value_text = args
+            .get(index + 1)
+            .filter(|value| !value.starts_with("--"))
+            .ok_or_else(|| {
+                invalid_option(option_field(flag), format!("{flag} requires a value"))
+            })?;
+
+        match flag {
+            "--pdf-set" => {
+                let value = value_text.trim();
+                if value.is_empty() {
+                    return Err(invalid_option("pdf-set", "--pdf-set must not be empty"));
+                }
+                set_pdf_option(&mut pdf_set, value.to_owned(), flag)?;
+            }
+            "--pdf-member" => {
+                set_pdf_option(&mut pdf_member, parse_pdf_member(flag, value_text)?, flag)?;
+            }
+            "--flavours" => {
+                set_pdf_option(&mut flavours, parse_pdf_flavours(flag, value_text)?, flag)?
+            }
+            "--x" => set_pdf_option(
+                &mut kinematics.x,
+                parse_finite_cross_number(flag, value_text)?,
+                flag,
+            )?,
+            "--q2" => set_pdf_option(
+                &mut kinematics.q2,
+                parse_finite_cross_number(flag, value_text)?,
+                flag,
+            )?,
+            "--points" => set_pdf_option(&mut kinematics.points, PathBuf::from(value_text), flag)?,
+            "--x-grid" => set_pdf_option(
+                &mut kinematics.x_grid,
+                parse_log_grid(flag, value_text)?,
+                flag,
+            )?,
+            "--q2-grid" => set_pdf_option(
+                &mut kinematics.q2_grid,
+                parse_log_grid(flag, value_text)?,
+                flag,
+            )?,
+            "--output" => set_pdf_option(&mut kinematics.output, PathBuf::from(value_text), flag)?,
+            _ => unreachable!("supported options were checked above"),
+        }
+        index += 2;
+    }
+
+    if action == "list" {
+        return Ok(PdfCommand::List);
+    }
+    let missing = |option: &str| {
+        missing_option(
+            option,
+            format!("missing required pdf {action} option: {option}"),
+        )
+    };
+    let pdf_set = pdf_set.ok_or_else(|| missing("--pdf-set"))?;
+    let flavours = flavours.unwrap_or_else(|| DEFAULT_PDF_FLAVOURS.to_vec());
+    match action {
+        "info" => Ok(PdfCommand::Info(PdfInfoCliArgs {
+            pdf_set,
+            pdf_member: pdf_member.unwrap_or(0),
+        })),
+        "table" => Ok(PdfCommand::Table(PdfTableCliArgs {
+            pdf_set,
+            pdf_member: pdf_member.unwrap_or(0),
+            flavours,
+            kinematics: kinematics.resolve(missing)?,
+        })),
+        _ => {
+            if uncertainty && pdf_member.is_some_and(|member| member != 0) {
+                return Err(invalid_option(
+                    "uncertainty",
+                    "--uncertainty draws its bands around member 0 and cannot be combined with \
+                     another --pdf-member",
+                ));
+            }
+            Ok(PdfCommand::Plot(PdfPlotCliArgs {
+                pdf_set,
+                pdf_member: pdf_member.unwrap_or(0),
+                flavours,
+                q2: kinematics.q2.ok_or_else(|| missing("--q2"))?,
+                x_grid: kinematics.x_grid,
+                uncertainty,
+                output: kinematics.output.ok_or_else(|| missing("--output"))?,
+            }))
+        }
+    }
+}
+
+fn set_pdf_option<T>(
+    slot: &mut Option<T>,
+    value: T,
+    flag: &str,
+) -> std::result::Result<(), CliError> {
+    if slot.replace(value).is_some() {
+        Err(invalid_option(
+            option_field(flag),
+            format!("duplicate pdf option: {flag}"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// A comma-separated list of distinct flavour names or PDG ids.
+fn parse_pdf_flavours(
+    flag: &str,
+    value_text: &str,
+) -> std::result::Result<Vec<PdfFlavour>, CliError> {
+    let mut flavours = Vec::new();
+    for item in value_text.split(',') {
+        let flavour = item.trim().parse::<PdfFlavour>().map_err(|error| {
+            invalid_option(
+                option_field(flag),
+                format!("invalid value for {flag}: {error}"),
+            )
+        })?;
+        if flavours.contains(&flavour) {
+            return Err(invalid_option(
+                option_field(flag),
+                format!("duplicate flavour in {flag}: {flavour}"),
+            ));
+        }
+        flavours.push(flavour);
+    }
+    Ok(flavours)
+}
+
+fn run_pdf(command: PdfCommand, format: OutputFormat) -> std::result::Result<(), CliError> {
+    match command {
+        PdfCommand::List => run_pdf_list(format),
+        PdfCommand::Info(arguments) => run_pdf_info(&arguments, format),
+        PdfCommand::Table(arguments) => run_pdf_table(arguments, format),
+        PdfCommand::Plot(arguments) => run_pdf_plot(&arguments),
+        PdfCommand::Help => {
+            print!("{PDF_HELP}");
+            Ok(())
+        }
+    }
+}
+
+/// Print the LHAPDF data path and the sets installed in it.
+fn run_pdf_list(format: OutputFormat) -> std::result::Result<(), CliError> {
+    let report = LhapdfReport::scan();
+    match format {
+        OutputFormat::Text => {
+            if report.data_paths.is_empty() {
+                println!("data path  (none)");
+            }
+            for data_path in &report.data_paths {
+                let missing = if data_path.exists { "" } else { " (missing)" };
+                println!("data path  {}{missing}", data_path.path.display());
+            }
+            for set in &report.sets {
+                let members = set
+                    .member_count
+                    .map_or_else(|| "unknown".to_owned(), |count| count.to_string());
+                println!("{:<32} NumMembers {members}", set.name);
+            }
+        }
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string(&report).map_err(std::io::Error::other)?
+        ),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(std::io::stdout().lock());
+            writer
+                .write_record(["pdf_set", "num_members"])
+                .map_err(std::io::Error::other)?;
+            for set in &report.sets {
+                let members = set.member_count.map(|count| count.to_string());
+                writer
+                    .write_record([set.name.as_str(), members.as_deref().unwrap_or("")])
+                    .map_err(std::io::Error::other)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the set-level metadata and the grid of one member.
+fn run_pdf_info(
+    arguments: &PdfInfoCliArgs,
+    format: OutputFormat,
+) -> std::result::Result<(), CliError> {
+    let provider = LhapdfProvider::new(arguments.pdf_set.as_str(), arguments.pdf_member)?;
+    let info = PdfSetInfo::load(&arguments.pdf_set)?;
+    let (x_min, x_max) = provider.x_range();
+    let (q2_min, q2_max) = provider.q2_range();
+
+    if format != OutputFormat::Text {
+        print_record(
+            format,
+            &[
+                ("pdf_set", provider.set_name().into()),
+                ("pdf_member", provider.member().into()),
+                ("order_qcd", provider.order_qcd().into()),
+                ("num_members", info.member_count.into()),
+                ("error_type", info.error_type.clone().into()),
+                ("data_version", provider.data_version().into()),
+                ("x_min", x_min.into()),
+                ("x_max", x_max.into()),
+                ("q_min_gev", q2_min.sqrt().into()),
+                ("q_max_gev", q2_max.sqrt().into()),
+                ("q2_min_gev2", q2_min.into()),
+                ("q2_max_gev2", q2_max.into()),
+                ("flavours", provider.available_flavors().into()),
+            ],
+        )?;
+        return Ok(());
+    }
+    let flavours: Vec<String> = provider
+        .available_flavors()
+        .iter()
+        .map(|pdg_id| match pdg_id.to_string().parse::<PdfFlavour>() {
+            Ok(flavour) => format!("{pdg_id} ({flavour})"),
+            Err(_) => pdg_id.to_string(),
+        })
+        .collect();
+    println!(
+        "PDF set/member: {}/{}",
+        provider.set_name(),
+        provider.member()
+    );
+    println!("OrderQCD     {}", provider.order_qcd());
+    println!("NumMembers   {}", info.member_count);
+    println!(
+        "ErrorType    {}",
+        info.error_type.as_deref().unwrap_or("(not declared)")
+    );
+    println!("DataVersion  {}", provider.data_version());
+    println!("x range      [{x_min:e}, {x_max:e}]");
+    println!(
+        "Q range      [{:e}, {:e}] GeV (Q² [{q2_min:e}, {q2_max:e}] GeV²)",
+        q2_min.sqrt(),
+        q2_max.sqrt()
+    );
+    println!("Flavours     {}", flavours.join(", "));
+    Ok(())
+}
+
+/// Tabulate `x f(x, Q²)` of the chosen flavours at one point or a batch.
+fn run_pdf_table(
+    arguments: PdfTableCliArgs,
+    format: OutputFormat,
+) -> std::result::Result<(), CliError> {
+    let provider = LhapdfProvider::new(arguments.pdf_set, arguments.pdf_member)?;
+    let flavours = arguments.flavours;
+    let (x, q2) = match arguments.kinematics {
+        KinematicInput::Point { x, q2 } => (x, q2),
+        KinematicInput::Batch(batch) => {
+            let columns: Vec<&'static str> =
+                flavours.iter().map(|flavour| flavour.column()).collect();
+            return run_batch(&batch, format, &columns, |point| {
+                let densities = provider
+                    .parton_densities(point.x, point.q2)
+                    .map_err(|error| error.to_string())?;
+                Ok(flavours
+                    .iter()
+                    .map(|flavour| Some(flavour.xf(&densities)))
+                    .collect())
+            });
+        }
+    };
+
+    let densities = provider.parton_densities(x, q2)?;
+    if format != OutputFormat::Text {
+        let mut fields = vec![
+            ("pdf_set", provider.set_name().into()),
+            ("pdf_member", provider.member().into()),
+            ("x", x.into()),
+            ("q2_gev2", q2.into()),
+        ];
+        fields.extend(
+            flavours
+                .iter()
+                .map(|flavour| (flavour.column(), flavour.xf(&densities).into())),
+        );
+        print_record(format, &fields)?;
+        return Ok(());
+    }
+    println!(
+        "x f(x, Q²) of PDF set/member {}/{}",
+        provider.set_name(),
+        provider.member()
+    );
+    println!("{:<6} = {x:.12e}", "x");
+    println!("{:<6} = {q2:.12e} GeV²", "Q²");
+    for flavour in &flavours {
+        println!("{:<6} = {:.12e}", flavour.column(), flavour.xf(&densities));
+    }
+    Ok(())
+}
+
+/// Plot `x f(x, Q²)` of the chosen flavours, with uncertainty bands when
+/// asked for, into a new SVG file.
+fn run_pdf_plot(arguments: &PdfPlotCliArgs) -> std::result::Result<(), CliError> {
+    let (members, error_set) = if arguments.uncertainty {
+        let info = PdfSetInfo::load(&arguments.pdf_set)?;
+        let error_set = PdfErrorSet::from_lhapdf(&info).ok_or_else(|| {
+            CliError::new(
+                "pdf_uncertainty_unavailable",
+                GuiErrorCategory::InvalidInput,
+                Some("uncertainty"),
+                format!(
+                    "{} declares no hessian, symmhessian or replicas error members",
+                    info.set_name
+                ),
+            )
+        })?;
+        let members = (0..info.member_count)
+            .map(|member| {
+                let member = i32::try_from(member).unwrap_or(i32::MAX);
+                LhapdfProvider::new(arguments.pdf_set.as_str(), member)
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        (members, Some(error_set))
+    } else {
+        (
+            vec![LhapdfProvider::new(
+                arguments.pdf_set.as_str(),
+                arguments.pdf_member,
+            )?],
+            None,
+        )
+    };
+
+    let central = &members[0];
+    let x_values = match &arguments.x_grid {
+        Some(grid) => grid.values(),
+        None => {
+            let (x_min, x_max) = central.x_range();
+            let min = x_min.max(DEFAULT_PDF_PLOT_X_MIN);
+            LogGrid {
+                min,
+                max: x_max.min(DEFAULT_PDF_PLOT_X_MAX).max(min),
+                points: DEFAULT_PDF_PLOT_POINTS,
+            }
+            .values()
+        }
+    };
+    let providers: Vec<&dyn PdfProvider> = members
+        .iter()
+        .map(|member| member as &dyn PdfProvider)
+        .collect();
+    let curves = pdf_curves(
+        &providers,
+        error_set.as_ref(),
+        &arguments.flavours,
+        &x_values,
+        arguments.q2,
+    )?;
+
+    let band = error_set
+        .as_ref()
+        .map(|error_set| format!(", {} uncertainty", error_set.error_type))
+        .unwrap_or_default();
+    let title = format!(
+        "{}/{} at Q² = {} GeV²{band}",
+        central.set_name(),
+        central.member(),
+        arguments.q2
+    );
+    write_pdf_plot(&arguments.output, &title, &curves)?;
+    println!("Wrote {}", arguments.output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> std::result::Result<Command, String> {
+        parse_command(args.iter().map(|value| (*value).to_string()))
+            .map_err(|error| error.message)
+    }
+
+    #[test]
+    fn help_is_explicit_and_does_not_select_training() {
+        assert_eq!(parse(&["--help"]), Ok(Command::Help));
+        assert_eq!(parse(&["-h"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn parse_errors_carry_the_code_and_option_of_the_rejected_input() {
+        let typed = |args: &[&str]| {
+            let error = parse_command(args.iter().map(|value| (*value).to_string())).unwrap_err();
+            (error.code, error.field)
+        };
+        let field = |name: &str| Some(name.to_owned());
+
+        assert_eq!(
+            typed(&["train-surrogate", "--sampler", "halton"]),
+            ("invalid_option", field("sampler"))
+        );
+        assert_eq!(
+            typed(&["train-surrogate", "--lr-schedule", "linear"]),
+            ("invalid_option", field("lr-schedule"))
+        );
+        assert_eq!(
+            typed(&["unfold", "--method", "svd"]),
+            ("invalid_option", field("method"))
+        );
+        assert_eq!(
+            typed(&["unfold", "--x-bins", "0.1,0.01", "--q2-bins", "10,100"]),
+            ("invalid_option", field("x-bins"))
+        );
+        assert_eq!(
+            typed(&["analyze-events", "--unknown", "1"]),
+            ("unknown_option", field("unknown"))
+        );
+        assert_eq!(
+            typed(&["analyze-events", "--output", "out"]),
+            ("missing_option", field("input"))
+        );
+        assert_eq!(typed(&["pdf", "draw"]), ("unknown_command", None));
+        assert_eq!(typed(&["frobnicate"]), ("unknown_command", None));
+    }
+
+    #[test]
+    fn malformed_commands_are_rejected() {
+        assert!(parse(&["--load"]).is_err());
+        assert!(parse(&["--load-model"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["--help", "extra"]).is_err());
+        assert!(parse(&["dis-kinematics"]).is_err());
+    }
+
+    #[test]
+    fn dis_help_is_explicit_and_side_effect_free() {
+        assert_eq!(
+            parse(&["dis-kinematics", "--help"]),
+            Ok(Command::DisKinematics(DisCommand::Help))
+        );
+    }
+
+    #[test]
+    fn dis_options_are_parsed_in_any_order() {
+        let command = parse(&[
+            "dis-kinematics",
+            "--theta-deg",
+            "20",
+            "--proton-energy",
+            "920",
+            "--electron-energy",
+            "27.5",
+            "--scattered-electron-energy",
+            "15",
+        ]);
+
+        assert_eq!(
+            command,
+            Ok(Command::DisKinematics(DisCommand::Calculate(DisCliArgs {
+                electron_energy: 27.5,
+                proton_energy: 920.0,
+                scattered_electron_energy: 15.0,
+                theta_deg: 20.0,
+            })))
+        );
+    }
+
+    #[test]
+    fn malformed_dis_options_are_rejected() {
+        assert!(parse(&[
+            "dis-kinematics",
+            "--electron-energy",
+            "27.5",
+            "--electron-energy",
+            "30",
+        ])
+        .is_err());
+        assert!(parse(&["dis-kinematics", "--electron-energy", "not-a-number",]).is_err());
+        assert!(parse(&["dis-kinematics", "--unknown", "1"]).is_err());
+        assert!(parse(&[
+            "dis-kinematics",
+            "--electron-energy",
+            "--proton-energy",
+            "920"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn cross_section_help_is_explicit_and_side_effect_free() {
+        assert_eq!(
+            parse(&["dis-cross-section", "--help"]),
+            Ok(Command::DisCrossSection(CrossSectionCommand::Help))
+        );
+    }
+
+    #[test]
+    fn cross_section_options_are_parsed_in_any_order() {
+        let command = parse(&[
+            "dis-cross-section",
+            "--pdf-member",
+            "0",
+            "--q2",
+            "100",
+            "--proton-energy",
+            "920",
+            "--pdf-set",
+            "CT18LO",
+            "--x",
+            "0.01",
+            "--electron-energy",
+            "27.5",
+        ]);
+
+        assert_eq!(
+            command,
+            Ok(Command::DisCrossSection(CrossSectionCommand::Calculate(
+                CrossSectionCliArgs {
+                    kinematics: KinematicInput::Point { x: 0.01, q2: 100.0 },
+                    electron_energy: 27.5,
+                    proton_energy: 920.0,
+                    pdf_set: "CT18LO".to_string(),
+                    pdf_member: 0,
+                }
+            )))
+        );
+    }
+
+    #[test]
+    fn malformed_cross_section_options_are_rejected() {
+        assert!(parse(&["dis-cross-section"]).is_err());
+        assert!(parse(&["dis-cross-section", "--unknown", "1"]).is_err());
+        assert!(parse(&["dis-cross-section", "--x", "NaN"]).is_err());
+        assert!(parse(&["dis-cross-section", "--pdf-member", "-1"]).is_err());
+        assert!(parse(&["dis-cross-section", "--x", "0.01", "--x", "0.02"]).is_err());
+        assert!(parse(&["dis-cross-section", "--q2", "--electron-energy", "27.5"]).is_err());
+    }
+
+    #[test]
+    fn batch_options_replace_the_single_point() {
+        let beams = [
+            "--electron-energy",
+            "27.5",
+            "--proton-energy",
+            "920",
+            "--pdf-set",
+            "CT18LO",
+            "--pdf-member",
+            "0",
+        ];
+        let cross_section = |options: &[&str]| {
+            let mut args = vec!["dis-cross-section"];
+            args.extend_from_slice(options);
+            args.extend_from_slice(&beams);
+            parse(&args).map(|command| match command {
+                Command::DisCrossSection(CrossSectionCommand::Calculate(arguments)) => {
+                    arguments.kinematics
+                }
+                other => panic!("unexpected command {other:?}"),
+            })
+        };
+
+        assert_eq!(
+            cross_section(&["--x-grid", "1e-3:1e-1:3", "--q2-grid", "10:100:2"]),
+            Ok(KinematicInput::Batch(BatchCliArgs {
+                points: BatchPoints::Grid {
+                    x: "1e-3:1e-1:3".parse().unwrap(),
+                    q2: "10:100:2".parse().unwrap(),
+                },
+                output: None,
+            }))
+        );
+        assert_eq!(
+            cross_section(&["--points", "p.csv", "--output", "p.jsonl"]),
+            Ok(KinematicInput::Batch(BatchCliArgs {
+                points: BatchPoints::File(PathBuf::from("p.csv")),
+                output: Some(PathBuf::from("p.jsonl")),
+            }))
+        );
+        assert!(cross_section(&["--points", "p.csv", "--x", "0.01"]).is_err());
+        assert!(cross_section(&["--points", "p.csv", "--x-grid", "0.01:0.1:2"]).is_err());
+        assert!(cross_section(&["--x-grid", "0.01:0.1:2"]).is_err());
+        assert!(cross_section(&["--x", "0.01", "--q2", "100", "--output", "p.csv"]).is_err());
+
+        let structure_functions = parse(&[
+            "structure-functions",
+            "--backend",
+            "lo",
+            "--order",
+            "LO",
+            "--pdf-set",
+            "CT18LO",
+            "--points",
+            "points.json",
+        ]);
+        assert!(matches!(
+            structure_functions,
+            Ok(Command::StructureFunctions(StructureFunctionsCliArgs {
+                kinematics: KinematicInput::Batch(BatchCliArgs {
+                    points: BatchPoints::File(_),
+                    ..
+                }),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn generate_dis_events_selects_the_native_generator() {
+        let base = [
+            "generate-dis-events",
+            "--electron-energy",
+            "27.5",
+            "--proton-energy",
+            "920",
+            "--q2-min",
+            "10",
+            "--events",
+            "100",
+            "--pdf-set",
+            "CT18LO",
+            "--output",
+            "out",
+        ];
+        let Ok(Command::GenerateDisEvents(GenerateDisEventsCommand::Calculate(arguments))) =
+            parse(&base)
+        else {
+            panic!("generate-dis-events should parse");
+        };
+        assert_eq!(arguments.generator, DisEventGenerator::Pythia);
+        assert_eq!(arguments.weighting, EventWeighting::Unweighted);
+
+        let native = [&base[..], &["--generator", "native", "--weighting", "weighted"]].concat();
+        let Ok(Command::GenerateDisEvents(GenerateDisEventsCommand::Calculate(arguments))) =
+            parse(&native)
+        else {
+            panic!("native generate-dis-events should parse");
+        };
+        assert_eq!(arguments.generator, DisEventGenerator::Native);
+        assert_eq!(arguments.weighting, EventWeighting::Weighted);
+        let config = serde_json::to_value(&arguments).unwrap();
+        assert!(config.get("generator").is_none());
+
+        assert!(parse(&[&base[..], &["--generator", "herwig"]].concat()).is_err());
+        assert!(parse(&[&base[..], &["--weighting", "partial"]].concat()).is_err());
+    }
+
+    #[test]
+    fn generate_dis_events_jobs_need_pythia_and_a_non_negative_seed() {
+        let base = [
+            "generate-dis-events",
+            "--electron-energy",
+            "27.5",
+            "--proton-energy",
+            "920",
+            "--q2-min",
+            "10",
+            "--events",
+            "100",
+            "--pdf-set",
+            "CT18LO",
+            "--output",
+            "out",
+        ];
+        let Ok(Command::GenerateDisEvents(GenerateDisEventsCommand::Calculate(arguments))) =
+            parse(&base)
+        else {
+            panic!("generate-dis-events should parse");
+        };
+        assert_eq!(
+            (arguments.jobs, arguments.retries),
+            (1, DEFAULT_CHUNK_RETRIES)
+        );
+
+        let jobs = [&base[..], &["--jobs", "4", "--retries", "0"]].concat();
+        let Ok(Command::GenerateDisEvents(GenerateDisEventsCommand::Calculate(arguments))) =
+            parse(&jobs)
+        else {
+            panic!("generate-dis-events --jobs should parse");
+        };
+        assert_eq!((arguments.jobs, arguments.retries), (4, 0));
+        let config = serde_json::to_value(&arguments).unwrap();
+        assert!(config.get("jobs").is_none());
+
+        assert!(parse(&[&base[..], &["--jobs", "0"]].concat()).is_err());
+        assert_eq!(
+            parse(&[&jobs[..], &["--generator", "native"]].concat()),
+            Err("--jobs requires --generator pythia".to_string())
+        );
+        assert_eq!(
+            parse(&[&jobs[..], &["--seed", "-1"]].concat()),
+            Err("--seed must be non-negative with --jobs".to_string())
+        );
+        assert!(parse(&[&base[..], &["--seed", "-1"]].concat()).is_ok());
+        assert_eq!(
+            parse(&[&base[..], &["--generator", "native", "--seed", "-1"]].concat()),
+            Err("--seed must be non-negative with --generator native".to_string())
+        );
+    }
+
+    #[test]
+    fn generate_dis_events_progress_output_defaults_to_a_bar() {
+        let base = [
+            "generate-dis-events",
+            "--electron-energy",
+            "27.5",
+            "--proton-energy",
+            "920",
+            "--q2-min",
+            "10",
+            "--events",
+            "100",
+            "--pdf-set",
+            "CT18LO",
+            "--output",
+            "out",
+        ];
+        let progress = |extra: &[&str]| match parse(&[&base[..], extra].concat()) {
+            Ok(Command::GenerateDisEvents(GenerateDisEventsCommand::Calculate(arguments))) => {
+                Ok(arguments.progress)
+            }
+            Ok(other) => panic!("unexpected command: {other:?}"),
+            Err(message) => Err(message),
+        };
+        assert_eq!(progress(&[]), Ok(ProgressOutput::Bar));
+        assert_eq!(progress(&["--progress", "json"]), Ok(ProgressOutput::Json));
+        assert_eq!(progress(&["--progress", "none"]), Ok(ProgressOutput::None));
+        assert_eq!(
+            progress(&["--progress", "dots"]),
+            Err("invalid value for --progress: dots (expected bar, json or none)".to_string())
+        );
+    }
+
+    #[test]
+    fn generate_dis_events_passes_process_and_pythia_settings_to_the_backend() {
+        let base = [
+            "generate-dis-events",
+            "--electron-energy",
+            "27.5",
+            "--proton-energy",
+            "920",
+            "--q2-min",
+            "10",
+            "--events",
+            "100",
+            "--pdf-set",
+            "CT18LO",
+            "--output",
+            "out",
+        ];
+        let config = |extra: &[&str]| match parse(&[&base[..], extra].concat()) {
+            Ok(Command::GenerateDisEvents(GenerateDisEventsCommand::Calculate(arguments))) => {
+                Ok(serde_json::to_value(&arguments).unwrap())
+            }
+            Ok(other) => panic!("unexpected command: {other:?}"),
+            Err(message) => Err(message),
+        };
+        let default = config(&[]).unwrap();
+        assert_eq!(default["process"], "neutral_current_dis");
+        assert!(default.get("pythia_settings").is_none());
+
+        let cmnd = std::env::temp_dir().join(format!(
+            "quark_sim_generate_dis_events_{}.cmnd",
+            std::process::id()
+        ));
+        std::fs::write(&cmnd, "! MPI tune\nPartonLevel:MPI = on\n").unwrap();
+        let charged = config(&[
+            "--process",
+            "cc",
+            "--pythia-setting",
+            " MultipartonInteractions:pT0Ref = 2.3 ",
+            "--pythia-cmnd",
+            cmnd.to_str().unwrap(),
+        ]);
+        let _ = std::fs::remove_file(&cmnd);
+        let charged = charged.unwrap();
+        assert_eq!(charged["process"], "charged_current_dis");
+        assert_eq!(
+            charged["pythia_settings"],
+            serde_json::json!([
+                "PartonLevel:MPI = on",
+                "MultipartonInteractions:pT0Ref = 2.3"
+            ])
+        );
+
+        assert_eq!(
+            config(&["--process", "photoproduction"]),
+            Err("invalid value for --process: photoproduction (expected nc or cc)".to_string())
+        );
+        assert_eq!(
+            config(&["--pythia-setting", "Random:seed = 7"]),
+            Err("PYTHIA setting 'Random:seed = 7' is reserved; use --seed instead".to_string())
+        );
+        assert!(config(&["--pythia-cmnd", cmnd.to_str().unwrap()]).is_err());
+        let typed = |extra: &[&str]| {
+            let error = parse_command([&base[..], extra].concat().into_iter().map(str::to_owned))
+                .unwrap_err();
+            (error.code, error.exit_code(), error.field)
+        };
+        let setting = Some("pythia-setting".to_owned());
+        assert_eq!(
+            typed(&["--pythia-setting", "Random:seed = 7"]),
+            ("reserved_pythia_setting", 2, setting.clone())
+        );
+        assert_eq!(
+            typed(&["--pythia-setting", "PartonLevel"]),
+            ("malformed_pythia_setting", 2, setting)
+        );
+        assert_eq!(
+            typed(&["--pythia-cmnd", cmnd.to_str().unwrap()]),
+            ("pythia_cmnd_unreadable", 2, Some("pythia-cmnd".to_owned()))
+        );
+        assert_eq!(
+            typed(&["--seed", "seven"]),
+            ("invalid_option", 2, Some("seed".to_owned()))
+        );
+        assert_eq!(
+            config(&["--generator", "native", "--process", "cc"]),
+            Err("--process cc requires --generator pythia".to_string())
+        );
+        assert_eq!(
+            config(&[
+                "--generator",
+                "native",
+                "--pythia-setting",
+                "PartonLevel:MPI = on"
+            ]),
+            Err("--pythia-setting and --pythia-cmnd require --generator pythia".to_string())
+        );
+    }
+
+    #[test]
+    fn analyze_events_options_select_analyses_and_normalisation() {
+        assert_eq!(
+            parse(&["analyze-events", "--help"]),
+            Ok(Command::AnalyzeEvents(AnalyzeEventsCommand::Help))
+        );
+        assert_eq!(
+            parse(&[
+                "analyze-events",
+                "--input",
+                "run/events.hepmc3.gz",
+                "--output",
+                "plots",
+                "--analysis",
+                "DIS_INCLUSIVE, dis_inclusive",
+                "--cross-section",
+                "1250.5",
+                "--max-events",
+                "1000",
+            ]),
+            Ok(Command::AnalyzeEvents(AnalyzeEventsCommand::Calculate(
+                AnalyzeEventsCliArgs {
+                    input: PathBuf::from("run/events.hepmc3.gz"),
+                    output: PathBuf::from("plots"),
+                    analyses: vec!["DIS_INCLUSIVE".to_owned(), "dis_inclusive".to_owned()],
+                    summary: None,
+                    cross_section_pb: Some(1250.5),
+                    max_events: Some(1000),
+                    detector: None,
+                    detector_seed: None,
+                }
+            )))
+        );
+        let Ok(Command::AnalyzeEvents(AnalyzeEventsCommand::Calculate(arguments))) = parse(&[
+            "analyze-events",
+            "--input",
+            "e",
+            "--output",
+            "o",
+            "--analysis",
+            "DIS_DETECTOR",
+            "--detector",
+            "data/detector/hera_like.json",
+            "--detector-seed",
+            "7",
+        ]) else {
+            panic!("detector options should parse");
+        };
+        assert_eq!(
+            arguments.detector,
+            Some(PathBuf::from("data/detector/hera_like.json"))
+        );
+        assert_eq!(arguments.detector_seed, Some(7));
+        assert!(parse(&["analyze-events", "--input", "e", "--output", "o", "--detector-seed", "-1"]).is_err());
+        assert!(parse(&["analyze-events", "--output", "plots"]).is_err());
+        assert!(parse(&["analyze-events", "--input", "e", "--output", "o", "--analysis", ","]).is_err());
+        assert!(parse(&["analyze-events", "--input", "e", "--output", "o", "--max-events", "0"]).is_err());
+    }
+
+    #[test]
+    fn unfold_options_select_binning_method_and_reconstruction() {
+        use quark_sim::analysis::{KinematicBinning, ReconstructionMethod, UnfoldingMethod};
+
+        assert_eq!(
+            parse(&["unfold", "--help"]),
+            Ok(Command::Unfold(UnfoldCommand::Help))
+        );
+        let required = [
+            "unfold",
+            "--response",
+            "mc/events.hepmc3.gz",
+            "--data",
+            "data/events.hepmc3.gz",
+            "--detector",
+            "hera_like.json",
+            "--output",
+            "unfolded",
+            "--x-bins",
+            "1e-4, 1e-3,1e-2",
+            "--q2-bins",
+            "10,100",
+        ];
+        assert_eq!(
+            parse(&required),
+            Ok(Command::Unfold(UnfoldCommand::Calculate(Box::new(
+                UnfoldCliArgs {
+                    response: PathBuf::from("mc/events.hepmc3.gz"),
+                    data: PathBuf::from("data/events.hepmc3.gz"),
+                    detector: PathBuf::from("hera_like.json"),
+                    output: PathBuf::from("unfolded"),
+                    binning: KinematicBinning::new(vec![1e-4, 1e-3, 1e-2], vec![10.0, 100.0])
+                        .unwrap(),
+                    method: UnfoldingMethod::IterativeBayes { iterations: 4 },
+                    reconstruction: ReconstructionMethod::Electron,
+                    data_seed: None,
+                    data_summary: None,
+                    cross_section_pb: None,
+                    max_events: None,
+                }
+            ))))
+        );
+
+        let with = |extra: &[&str]| parse(&[&required[..], extra].concat());
+        let Ok(Command::Unfold(UnfoldCommand::Calculate(arguments))) = with(&[
+            "--method",
+            "inversion",
+            "--reconstruction",
+            "jb",
+            "--data-seed",
+            "11",
+        ]) else {
+            panic!("unfold options should parse");
+        };
+        assert_eq!(arguments.method, UnfoldingMethod::MatrixInversion);
+        assert_eq!(
+            arguments.reconstruction,
+            ReconstructionMethod::JacquetBlondel
+        );
+        assert_eq!(arguments.data_seed, Some(11));
+        assert!(with(&["--method", "bin-by-bin", "--iterations", "3"]).is_err());
+        assert!(with(&["--method", "svd"]).is_err());
+        assert!(with(&["--reconstruction", "double-angle"]).is_err());
+        assert!(with(&["--iterations", "0"]).is_err());
+        assert!(parse(&["unfold", "--x-bins", "0.1,0.01", "--q2-bins", "1,10"]).is_err());
+    }
+
+    #[test]
+    fn run_cards_supply_options_that_the_command_line_overrides() {
+        let directory =
+            std::env::temp_dir().join(format!("quark_sim_cli_run_card_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("cross_section.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "schema_version": 1,
+                "command": "dis-cross-section",
+                "dis": {"electron_energy_gev": 27.6, "pdf_set": "CT18NLO"},
+                "options": {"x": 0.01, "q2": 100}
+            }"#,
+        )
+        .unwrap();
+        let invoke = |args: &[&str]| {
+            parse_invocation(args.iter().map(|value| (*value).to_string()).collect())
+        };
+        let config = path.to_str().unwrap();
+
+        let (command, card) = invoke(&["--config", config, "--q2", "250"]).unwrap();
+        assert_eq!(
+            command,
+            Command::DisCrossSection(CrossSectionCommand::Calculate(CrossSectionCliArgs {
+                kinematics: KinematicInput::Point { x: 0.01, q2: 250.0 },
+                electron_energy: 27.6,
+                proton_energy: 920.0,
+                pdf_set: "CT18NLO".to_owned(),
+                pdf_member: 0,
+            }))
+        );
+        assert_eq!(card.options["q2"], serde_json::json!(250));
+        assert_eq!(
+            invoke(&["dis-cross-section", "--config", config, "--x", "0.02"])
+                .unwrap()
+                .1
+                .options["x"],
+            serde_json::json!(0.02)
+        );
+        assert!(invoke(&["unfold", "--config", config]).is_err());
+        assert!(invoke(&["dis-cross-section", "--config", config, "--x"]).is_err());
+
+        let (_, plain) = invoke(&[
+            "dis-kinematics",
+            "--electron-energy",
+            "27.5",
+            "--proton-energy",
+            "920",
+            "--scattered-electron-energy",
+            "20",
+            "--theta-deg",
+            "150",
+        ])
+        .unwrap();
+        assert_eq!(plain.command.as_deref(), Some("dis-kinematics"));
+        assert_eq!(plain.options["theta-deg"], serde_json::json!(150));
+        assert_eq!(invoke(&["--help"]).unwrap().1, RunCard::default());
+        let _ = std::fs::remove_dir_all(directory);
+    }
+
+    #[test]
+    fn reweight_events_options_collect_pdf_members() {
+        assert_eq!(
+            parse(&["reweight-events", "--help"]),
+            Ok(Command::ReweightEvents(ReweightEventsCommand::Help))
+        );
+        let selection = |set_name: &str, member| PdfSelection {
+            set_name: set_name.to_owned(),
+            member,
+        };
+        assert_eq!(
+            parse(&[
+                "reweight-events",
+                "--input",
+                "run/events.hepmc3.gz",
+                "--output",
+                "reweighted",
+                "--pdf",
+                "CT18NNLO",
+                "--pdf",
+                "NNPDF40_nnlo_as_01180/3",
+                "--nominal-pdf",
+                "CT18NLO",
+                "--cross-section",
+                "120.5",
+            ]),
+            Ok(Command::ReweightEvents(ReweightEventsCommand::Calculate(
+                ReweightEventsCliArgs {
+                    input: PathBuf::from("run/events.hepmc3.gz"),
+                    output: PathBuf::from("reweighted"),
+                    pdfs: vec![
+                        selection("CT18NNLO", None),
+                        selection("NNPDF40_nnlo_as_01180", Some(3)),
+                    ],
+                    nominal_pdf: Some(selection("CT18NLO", None)),
+                    summary: None,
+                    cross_section_pb: Some(120.5),
+                    max_events: None,
+                }
+            )))
+        );
+
+        let base = ["reweight-events", "--input", "e", "--output", "o"];
+        let with = |extra: &[&str]| parse(&[&base[..], extra].concat());
+        assert!(parse(&base).is_err());
+        assert!(with(&["--pdf", "CT18NNLO/-1"]).is_err());
+        assert!(with(&["--pdf", "/2"]).is_err());
+        assert!(with(&["--pdf", "CT18NNLO", "--pdf", "CT18NNLO"]).is_err());
+        assert!(with(&["--pdf", "CT18NNLO", "--max-events", "0"]).is_err());
+        assert!(with(&["--pdf", "CT18NNLO", "--help"]).is_err());
+    }
+
+    #[test]
+    fn doctor_options_replace_the_default_locations() {
+        assert_eq!(
+            parse(&["doctor", "--help"]),
+            Ok(Command::Doctor(DoctorCommand::Help))
+        );
+        let Ok(Command::Doctor(DoctorCommand::Run(options))) = parse(&[
+            "doctor",
+            "--apfel-backend",
+            "/opt/apfel_cli",
+            "--venv",
+            ".venv",
+        ]) else {
+            panic!("doctor options should parse");
+        };
+        assert_eq!(options.apfel_backend, PathBuf::from("/opt/apfel_cli"));
+        assert_eq!(options.venv, PathBuf::from(".venv"));
+        assert_eq!(options.model_dir, PathBuf::from("models/surrogate_v1"));
+
+        assert!(parse(&["doctor", "--model-dir"]).is_err());
+        assert!(parse(&["doctor", "--pdf-set", "CT18LO"]).is_err());
+    }
+
+    #[test]
+    fn compare_runs_needs_two_runs_and_one_label_per_run() {
+        assert_eq!(
+            parse(&["compare-runs", "--help"]),
+            Ok(Command::CompareRuns(CompareRunsCommand::Help))
+        );
+        let Ok(Command::CompareRuns(CompareRunsCommand::Compare(arguments))) = parse(&[
+            "compare-runs",
+            "--run",
+            "runs/shower/dis_run_1",
+            "--run",
+            "runs/no_shower/dis_run_1/",
+            "--output",
+            "runs/comparison",
+        ]) else {
+            panic!("compare-runs options should parse");
+        };
+        assert_eq!(arguments.bins, DEFAULT_COMPARISON_BINS);
+        assert_eq!(
+            comparison_labels(&arguments),
+            ["dis_run_1_1", "dis_run_1_2"]
+        );
+
+        let Ok(Command::CompareRuns(CompareRunsCommand::Compare(arguments))) = parse(&[
+            "compare-runs",
+            "--run",
+            "a",
+            "--label",
+            "shower",
+            "--run",
+            "b",
+            "--label",
+            "no shower",
+            "--bins",
+            "25",
+            "--output",
+            "out",
+        ]) else {
+            panic!("labelled compare-runs options should parse");
+        };
+        assert_eq!(comparison_labels(&arguments), ["shower", "no shower"]);
+        assert_eq!(arguments.bins, 25);
+
+        assert!(parse(&["compare-runs", "--run", "a", "--output", "out"]).is_err());
+        assert!(parse(&["compare-runs", "--run", "a", "--run", "b"]).is_err());
+        assert!(parse(&[
+            "compare-runs",
+            "--run",
+            "a",
+            "--run",
+            "b",
+            "--label",
+            "x",
+            "--output",
+            "out"
+        ])
+        .is_err());
+        assert!(parse(&[
+            "compare-runs",
+            "--run",
+            "a",
+            "--run",
+            "b",
+            "--bins",
+            "0",
+            "--output",
+            "out"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn verify_run_reruns_next_to_the_run_by_default() {
+        assert_eq!(
+            parse(&["verify-run", "--help"]),
+            Ok(Command::VerifyRun(VerifyRunCommand::Help))
+        );
+        assert_eq!(
+            parse(&["verify-run", "--run", "runs/hera/"]),
+            Ok(Command::VerifyRun(VerifyRunCommand::Verify(
+                VerifyRunCliArgs {
+                    run: PathBuf::from("runs/hera/"),
+                    rerun: None,
+                    tolerances: Tolerances::default(),
+                }
+            )))
+        );
+        let Ok(Command::VerifyRun(VerifyRunCommand::Verify(arguments))) = parse(&[
+            "verify-run",
+            "--rerun",
+            "--run",
+            "runs/hera/",
+            "--rel-tol",
+            "1e-6",
+        ]) else {
+            panic!("verify-run options should parse");
+        };
+        assert_eq!(arguments.rerun, Some(PathBuf::from("runs/hera_rerun")));
+        assert_eq!(arguments.tolerances.relative, 1e-6);
+        assert_eq!(arguments.tolerances.absolute, 1e-12);
+
+        let Ok(Command::VerifyRun(VerifyRunCommand::Verify(arguments))) = parse(&[
+            "verify-run",
+            "--run",
+            "runs/hera",
+            "--rerun-output",
+            "/tmp/repeat",
+        ]) else {
+            panic!("--rerun-output should imply --rerun");
+        };
+        assert_eq!(arguments.rerun, Some(PathBuf::from("/tmp/repeat")));
+
+        assert_eq!(
+            parse(&["verify-run", "--rerun"]),
+            Err("missing required verify-run option: --run".to_owned())
+        );
+        assert!(parse(&["verify-run", "--run", "a", "--abs-tol", "-1"]).is_err());
+        assert!(parse(&["verify-run", "--run", "a", "--run", "b"]).is_err());
+    }
+
+    #[test]
+    fn pdf_actions_accept_only_their_own_options() {
+        assert_eq!(
+            parse(&["pdf", "--help"]),
+            Ok(Command::Pdf(PdfCommand::Help))
+        );
+        assert_eq!(parse(&["pdf", "list"]), Ok(Command::Pdf(PdfCommand::List)));
+        assert_eq!(
+            parse(&["pdf", "info", "--pdf-set", "CT18LO"]),
+            Ok(Command::Pdf(PdfCommand::Info(PdfInfoCliArgs {
+                pdf_set: "CT18LO".to_owned(),
+                pdf_member: 0,
+            })))
+        );
+
+        let Ok(Command::Pdf(PdfCommand::Table(table))) = parse(&[
+            "pdf",
+            "table",
+            "--pdf-set",
+            "CT18LO",
+            "--flavours",
+            "g, 2,ubar",
+            "--x-grid",
+            "1e-3:0.5:4",
+            "--q2-grid",
+            "10:10:1",
+        ]) else {
+            panic!("pdf table options should parse");
+        };
+        assert_eq!(
+            table.flavours,
+            [PdfFlavour::Gluon, PdfFlavour::Up, PdfFlavour::AntiUp]
+        );
+        assert!(matches!(table.kinematics, KinematicInput::Batch(_)));
+
+        let Ok(Command::Pdf(PdfCommand::Plot(plot))) = parse(&[
+            "pdf",
+            "plot",
+            "--pdf-set",
+            "CT18NLO",
+            "--q2",
+            "100",
+            "--output",
+            "pdf.svg",
+            "--uncertainty",
+        ]) else {
+            panic!("pdf plot options should parse");
+        };
+        assert_eq!(plot.flavours, DEFAULT_PDF_FLAVOURS);
+        assert!(plot.uncertainty && plot.x_grid.is_none());
+
+        assert!(parse(&["pdf"]).is_err());
+        assert!(parse(&["pdf", "show"]).is_err());
+        assert!(parse(&["pdf", "list", "--pdf-set", "CT18LO"]).is_err());
+        assert!(parse(&["pdf", "info", "--pdf-set", "CT18LO", "--x", "0.1"]).is_err());
+        assert!(parse(&["pdf", "table", "--pdf-set", "CT18LO", "--flavours", "g,g"]).is_err());
+        assert!(parse(&[
+            "pdf",
+            "table",
+            "--pdf-set",
+            "CT18LO",
+            "--flavours",
+            "t",
+            "--x",
+            "0.1",
+            "--q2",
+            "10"
+        ])
+        .is_err());
+        assert!(parse(&["pdf", "plot", "--pdf-set", "CT18LO", "--q2", "10"]).is_err());
+        assert!(parse(&[
+            "pdf",
+            "plot",
+            "--pdf-set",
+            "CT18NLO",
+            "--pdf-member",
+            "3",
+            "--q2",
+            "100",
+            "--output",
+            "pdf.svg",
+            "--uncertainty",
+        ])
+        .is_err());
+    }
+    #[test]
+    fn train_surrogate_options_select_sampler_and_refinement() {
+        assert_eq!(
+            parse(&["train-surrogate", "--help"]),
+            Ok(Command::TrainSurrogate(TrainSurrogateCommand::Help))
+        );
+
+        let command = parse(&[
+            "train-surrogate",
+            "--pdf-set",
+            "CT18NLO",
+            "--output",
+            "models/surrogate_v2",
+            "--sampler",
+            "sobol",
+            "--points",
+            "4000",
+            "--seed",
+            "9",
+            "--target-rel-error",
+            "0.005",
+            "--ensemble-size",
+            "5",
+            "--max-uncertainty",
+            "0.02",
+            "--resume",
+        ]);
+        assert_eq!(
+            command,
+            Ok(Command::TrainSurrogate(TrainSurrogateCommand::Calculate(
+                Box::new(TrainSurrogateCliArgs {
+                    pdf_set: "CT18NLO".to_owned(),
+                    pdf_member: 0,
+                    order: "NLO".to_owned(),
+                    output: PathBuf::from("models/surrogate_v2"),
+                    sampler: DatasetSampler::Sobol {
+                        points: 4000,
+                        seed: 9,
+                    },
+                    training: SurrogateTrainingConfig {
+                        ensemble_size: 5,
+                        max_relative_uncertainty: Some(0.02),
+                        seed: 9,
+                        ..SurrogateTrainingConfig::default()
+                    },
+                    refinement: Some(RefinementConfig {
+                        target_f2_relative_error: 0.005,
+                        seed: 9,
+                        ..RefinementConfig::default()
+                    }),
+                    resume: true,
+                })
+            )))
+        );
+
+        let Ok(Command::TrainSurrogate(TrainSurrogateCommand::Calculate(grid))) = parse(&[
+            "train-surrogate",
+            "--pdf-set",
+            "CT18NLO",
+            "--output",
+            "models/surrogate_v2",
+            "--x-points",
+            "12",
+        ]) else {
+            panic!("expected a train-surrogate command");
+        };
+        assert_eq!(
+            grid.sampler,
+            DatasetSampler::Grid {
+                x_points: 12,
+                q2_points: 5,
+                scale_points: 3,
+            }
+        );
+        assert_eq!(grid.refinement, None);
+        assert!(parse(&["train-surrogate", "--pdf-set", "CT18NLO", "--output", "m", "--sampler", "halton"]).is_err());
+    }
+
+    #[test]
+    fn train_surrogate_command_line_overrides_training_config_file() {
+        let path = std::env::temp_dir().join(format!(
+            "quark_sim_training_config_{}.json",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            r#"{"architecture": {"hidden_layers": [64, 64], "activation": "tanh"},
+                "epochs": 100, "seed": 5,
+                "learning_rate_schedule": {"kind": "step", "step_epochs": 20, "factor": 0.8}}"#,
+        )
+        .unwrap();
+        let path_text = path.to_string_lossy().into_owned();
+
+        let Ok(Command::TrainSurrogate(TrainSurrogateCommand::Calculate(arguments))) = parse(&[
+            "train-surrogate",
+            "--pdf-set",
+            "CT18NLO",
+            "--output",
+            "models/surrogate_v2",
+            "--training-config",
+            &path_text,
+            "--epochs",
+            "300",
+            "--activation",
+            "gelu",
+            "--lr-decay-factor",
+            "0.5",
+            "--batch-size",
+            "64",
+            "--sampler",
+            "lhs",
+            "--constrain-fl",
+            "--q2-slope-weight",
+            "0.1",
+        ]) else {
+            panic!("expected a train-surrogate command");
+        };
+        std::fs::remove_file(&path).unwrap();
+
+        let training = arguments.training;
+        assert_eq!(training.architecture.hidden_layers, vec![64, 64]);
+        assert_eq!(training.architecture.activation, SurrogateActivation::Gelu);
+        assert_eq!(training.epochs, 300);
+        assert_eq!(training.batch_size, Some(64));
+        assert!(training.constraints.fl_bounded_by_f2);
+        assert!(!training.constraints.zero_xf3);
+        assert_eq!(training.constraints.q2_slope_weight, Some(0.1));
+        assert_eq!(
+            training.learning_rate_schedule,
+            LearningRateSchedule::Step {
+                step_epochs: 20,
+                factor: 0.5,
+            }
+        );
+        assert_eq!(
+            arguments.sampler,
+            DatasetSampler::LatinHypercube {
+                points: 2000,
+                seed: 5,
+            }
+        );
+        assert!(parse(&[
+            "train-surrogate",
+            "--pdf-set",
+            "CT18NLO",
+            "--output",
+            "m",
+            "--hidden-layers",
+            "64,0",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn validate_surrogate_options_override_defaults() {
+        assert_eq!(
+            parse(&["validate-surrogate", "--help"]),
+            Ok(Command::ValidateSurrogate(ValidateSurrogateCommand::Help))
+        );
+
+        let command = parse(&[
+            "validate-surrogate",
+            "--output",
+            "outputs/surrogate",
+            "--samples",
+            "500",
+            "--sampling",
+            "latin-hypercube",
+            "--seed",
+            "7",
+        ]);
+        assert_eq!(
+            command,
+            Ok(Command::ValidateSurrogate(ValidateSurrogateCommand::Calculate(
+                ValidateSurrogateCliArgs {
+                    model_dir: PathBuf::from("models/surrogate_v1"),
+                    output: PathBuf::from("outputs/surrogate"),
+                    validation: SurrogateValidationConfig {
+                        sample_count: 500,
+                        sampling: SamplingMethod::LatinHypercube,
+                        seed: 7,
+                        ..SurrogateValidationConfig::default()
+                    },
+                }
+            )))
+        );
+
+        assert!(parse(&["validate-surrogate"]).is_err());
+        assert!(parse(&["validate-surrogate", "--output", "out", "--samples", "0"]).is_err());
+        assert!(parse(&["validate-surrogate", "--output", "out", "--sampling", "halton"]).is_err());
     }
 
     #[test]
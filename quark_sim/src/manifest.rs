@@ -0,0 +1,897 @@
+//! The reproducibility manifest written into every run directory.
+//!
+//! `manifest.json` records what a run was given and what it produced: the
+//! resolved run card and the digests of the input files it names, the build
+//! provenance, the APFEL++, LHAPDF, PYTHIA and HepMC3 versions reported by
+//! the backends' artifacts and handshakes, a digest of every PDF set used, the random seeds, the wall
+//! time and the SHA-256 of every artifact. `verify-run` rechecks the digests
+//! and can repeat the run and compare the new artifacts within tolerances.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use quark_sim::checksum::{sha256_file, sha256_hex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::doctor::{self, Backend, BuildProvenance, LhapdfReport};
+use crate::run_card::{RunCard, RUN_CARD_FILENAME};
+
+pub const MANIFEST_FILENAME: &str = "manifest.json";
+pub const MANIFEST_SCHEMA_VERSION: i32 = 1;
+
+/// Run-card options that name LHAPDF sets, as `SET` or `SET/MEMBER`.
+const PDF_SET_OPTIONS: [&str; 3] = ["pdf-set", "nominal-pdf", "pdf"];
+
+/// Keys under which the backends report their versions in `metadata.json`
+/// and in structure-function results.
+const VERSION_KEYS: [&str; 4] = [
+    "apfelxx_version",
+    "lhapdf_version",
+    "pythia_version",
+    "hepmc3_version",
+];
+
+/// JSON artifacts larger than this are not searched for backend versions.
+const MAX_VERSION_SOURCE_BYTES: u64 = 16 << 20;
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    UnsupportedSchemaVersion {
+        path: PathBuf,
+        version: i32,
+    },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(
+                formatter,
+                "failed to access run manifest '{}': {source}",
+                path.display()
+            ),
+            Self::Json { path, source } => write!(
+                formatter,
+                "invalid run manifest '{}': {source}",
+                path.display()
+            ),
+            Self::UnsupportedSchemaVersion { path, version } => write!(
+                formatter,
+                "run manifest '{}' has schema version {version}; expected \
+                 {MANIFEST_SCHEMA_VERSION}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for ManifestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            Self::UnsupportedSchemaVersion { .. } => None,
+        }
+    }
+}
+
+/// Size and SHA-256 of one file. Artifact paths are relative to the run
+/// directory and use `/`; input paths are recorded as given.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDigest {
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+/// Versions the backends reported in the run's artifacts or, failing that, in
+/// their handshake.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BackendVersions {
+    pub apfelxx: Option<String>,
+    pub lhapdf: Option<String>,
+    pub pythia: Option<String>,
+    pub hepmc3: Option<String>,
+}
+
+/// An LHAPDF set used by the run. `sha256` digests the `sha256sum`-style
+/// listing of every file in the set directory, so it changes when any grid
+/// or the `.info` file does; both are `None` when the set is not installed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PdfSetDigest {
+    pub name: String,
+    pub directory: Option<PathBuf>,
+    pub files: usize,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub schema_version: i32,
+    /// The resolved run card, as written to `run_card.json`.
+    pub inputs: RunCard,
+    pub input_files: Vec<FileDigest>,
+    pub build: BuildProvenance,
+    pub backends: BackendVersions,
+    pub pdf_sets: Vec<PdfSetDigest>,
+    pub seeds: BTreeMap<String, u64>,
+    pub started_at: String,
+    pub finished_at: String,
+    pub wall_time_seconds: f64,
+    pub artifacts: Vec<FileDigest>,
+}
+
+impl RunManifest {
+    /// Read `manifest.json` from a run directory.
+    pub fn load(directory: &Path) -> Result<Self, ManifestError> {
+        let path = directory.join(MANIFEST_FILENAME);
+        let text = std::fs::read_to_string(&path).map_err(|source| ManifestError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let manifest: Self = serde_json::from_str(&text).map_err(|source| ManifestError::Json {
+            path: path.clone(),
+            source,
+        })?;
+        if manifest.schema_version != MANIFEST_SCHEMA_VERSION {
+            return Err(ManifestError::UnsupportedSchemaVersion {
+                path,
+                version: manifest.schema_version,
+            });
+        }
+        Ok(manifest)
+    }
+}
+
+/// A run in progress. Start it before the run does any work and finish it
+/// once every artifact is written.
+#[derive(Debug)]
+pub struct ManifestRecorder {
+    inputs: RunCard,
+    seeds: BTreeMap<String, u64>,
+    handshake_versions: BTreeMap<String, String>,
+    started_at: DateTime<Utc>,
+    started: Instant,
+}
+
+impl ManifestRecorder {
+    #[must_use]
+    pub fn start(card: &RunCard) -> Self {
+        Self {
+            inputs: card.clone(),
+            seeds: BTreeMap::new(),
+            handshake_versions: BTreeMap::new(),
+            started_at: Utc::now(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Record a seed the run used, including one it chose itself.
+    pub fn record_seed(&mut self, name: &str, seed: u64) {
+        self.seeds.insert(name.to_owned(), seed);
+    }
+
+    /// Record the library versions of a backend the run launches, from its
+    /// handshake. A backend that does not answer leaves them to the artifacts.
+    pub fn record_backend(&mut self, backend: Backend, executable: &Path) {
+        if let Ok(versions) = doctor::handshake(backend, executable) {
+            for (key, version) in versions {
+                self.handshake_versions.entry(key).or_insert(version);
+            }
+        }
+    }
+
+    /// Digest the artifacts in `directory` and write `manifest.json` there.
+    pub fn finish(self, directory: &Path) -> Result<PathBuf, ManifestError> {
+        let artifacts = directory_digests(directory)?;
+        let pdf_sets = pdf_set_names(&self.inputs)
+            .into_iter()
+            .map(|name| pdf_set_digest(&name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut backends = backend_versions(directory, &artifacts, &self.handshake_versions);
+        if backends.lhapdf.is_none() && !pdf_sets.is_empty() {
+            backends.lhapdf = lhapdf_config_version();
+        }
+        let manifest = RunManifest {
+            schema_version: MANIFEST_SCHEMA_VERSION,
+            input_files: input_file_digests(&self.inputs)?,
+            inputs: self.inputs,
+            build: BuildProvenance::current(),
+            backends,
+            pdf_sets,
+            seeds: self.seeds,
+            started_at: self.started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            finished_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            wall_time_seconds: self.started.elapsed().as_secs_f64(),
+            artifacts,
+        };
+
+        let path = directory.join(MANIFEST_FILENAME);
+        let mut json =
+            serde_json::to_string_pretty(&manifest).map_err(|source| ManifestError::Json {
+                path: path.clone(),
+                source,
+            })?;
+        json.push('\n');
+        std::fs::write(&path, json).map_err(|source| ManifestError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        Ok(path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestStatus {
+    Ok,
+    Modified,
+    Missing,
+}
+
+impl DigestStatus {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Modified => "modified",
+            Self::Missing => "missing",
+        }
+    }
+}
+
+/// The recheck of one recorded digest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DigestCheck {
+    /// `artifact`, `input` or `pdf_set`.
+    pub kind: &'static str,
+    pub path: String,
+    pub status: DigestStatus,
+}
+
+/// Recompute every digest of `manifest`. Input files are resolved against
+/// the working directory, as when the run was started.
+pub fn verify_digests(directory: &Path, manifest: &RunManifest) -> Vec<DigestCheck> {
+    let file_check = |kind: &'static str, path: PathBuf, digest: &FileDigest| DigestCheck {
+        kind,
+        path: digest.path.clone(),
+        status: match sha256_file(&path) {
+            Ok((sha256, bytes)) if sha256 == digest.sha256 && bytes == digest.bytes => {
+                DigestStatus::Ok
+            }
+            Ok(_) => DigestStatus::Modified,
+            Err(_) => DigestStatus::Missing,
+        },
+    };
+    let mut checks: Vec<DigestCheck> = manifest
+        .artifacts
+        .iter()
+        .map(|digest| file_check("artifact", directory.join(&digest.path), digest))
+        .chain(
+            manifest
+                .input_files
+                .iter()
+                .map(|digest| file_check("input", PathBuf::from(&digest.path), digest)),
+        )
+        .collect();
+    for recorded in &manifest.pdf_sets {
+        let Some(sha256) = &recorded.sha256 else {
+            continue;
+        };
+        let status = match pdf_set_digest(&recorded.name) {
+            Ok(PdfSetDigest {
+                sha256: Some(current),
+                ..
+            }) if &current == sha256 => DigestStatus::Ok,
+            Ok(PdfSetDigest {
+                sha256: Some(_), ..
+            }) => DigestStatus::Modified,
+            _ => DigestStatus::Missing,
+        };
+        checks.push(DigestCheck {
+            kind: "pdf_set",
+            path: recorded.name.clone(),
+            status,
+        });
+    }
+    checks
+}
+
+/// Relative and absolute tolerances for comparing the numbers of two
+/// artifacts: `|a - b| <= absolute + relative * max(|a|, |b|)`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Tolerances {
+    pub relative: f64,
+    pub absolute: f64,
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Self {
+            relative: 1.0e-9,
+            absolute: 1.0e-12,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonStatus {
+    Identical,
+    WithinTolerance,
+    Different,
+    Missing,
+}
+
+impl ComparisonStatus {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Identical => "identical",
+            Self::WithinTolerance => "within_tolerance",
+            Self::Different => "different",
+            Self::Missing => "missing",
+        }
+    }
+}
+
+/// One artifact of a run compared with the same artifact of its repetition.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArtifactComparison {
+    pub path: String,
+    pub status: ComparisonStatus,
+    /// The largest difference between corresponding numbers, for artifacts
+    /// that are not byte-identical but match token by token.
+    pub max_abs_difference: Option<f64>,
+    pub detail: Option<String>,
+}
+
+/// Compare the artifacts of a run with those of its repetition. The run
+/// card, the manifest and log files are skipped because they record paths,
+/// times and progress rather than results.
+pub fn compare_artifacts(
+    original_directory: &Path,
+    original: &RunManifest,
+    repeat_directory: &Path,
+    repeat: &RunManifest,
+    tolerances: Tolerances,
+) -> Vec<ArtifactComparison> {
+    let repeated: BTreeMap<&str, &FileDigest> = repeat
+        .artifacts
+        .iter()
+        .map(|digest| (digest.path.as_str(), digest))
+        .collect();
+    original
+        .artifacts
+        .iter()
+        .filter(|digest| digest.path != RUN_CARD_FILENAME && !digest.path.ends_with(".log"))
+        .map(|digest| {
+            let comparison =
+                |status, max_abs_difference, detail: Option<String>| ArtifactComparison {
+                    path: digest.path.clone(),
+                    status,
+                    max_abs_difference,
+                    detail,
+                };
+            let Some(repeated) = repeated.get(digest.path.as_str()) else {
+                return comparison(ComparisonStatus::Missing, None, None);
+            };
+            if repeated.sha256 == digest.sha256 {
+                return comparison(ComparisonStatus::Identical, None, None);
+            }
+            let read = |directory: &Path| std::fs::read(directory.join(&digest.path));
+            match (read(original_directory), read(repeat_directory)) {
+                (Ok(first), Ok(second)) => match compare_numbers(&first, &second, tolerances) {
+                    Ok(difference) => {
+                        comparison(ComparisonStatus::WithinTolerance, Some(difference), None)
+                    }
+                    Err(detail) => comparison(ComparisonStatus::Different, None, Some(detail)),
+                },
+                (Err(error), _) | (_, Err(error)) => {
+                    comparison(ComparisonStatus::Different, None, Some(error.to_string()))
+                }
+            }
+        })
+        .collect()
+}
+
+/// Everything `verify-run` prints.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VerifyReport {
+    pub ok: bool,
+    pub run: PathBuf,
+    pub digests: Vec<DigestCheck>,
+    /// The directory of the repeated run, with `--rerun`.
+    pub rerun: Option<PathBuf>,
+    pub comparisons: Vec<ArtifactComparison>,
+}
+
+impl VerifyReport {
+    #[must_use]
+    pub fn new(
+        run: PathBuf,
+        digests: Vec<DigestCheck>,
+        rerun: Option<PathBuf>,
+        comparisons: Vec<ArtifactComparison>,
+    ) -> Self {
+        Self {
+            ok: digests.iter().all(|check| check.status == DigestStatus::Ok)
+                && comparisons.iter().all(|comparison| {
+                    matches!(
+                        comparison.status,
+                        ComparisonStatus::Identical | ComparisonStatus::WithinTolerance
+                    )
+                }),
+            run,
+            digests,
+            rerun,
+            comparisons,
+        }
+    }
+
+    /// Number of digests and comparisons that failed.
+    #[must_use]
+    pub fn failures(&self) -> usize {
+        self.digests
+            .iter()
+            .filter(|check| check.status != DigestStatus::Ok)
+            .count()
+            + self
+                .comparisons
+                .iter()
+                .filter(|comparison| {
+                    matches!(
+                        comparison.status,
+                        ComparisonStatus::Different | ComparisonStatus::Missing
+                    )
+                })
+                .count()
+    }
+
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        let count = |status| {
+            self.digests
+                .iter()
+                .filter(|check| check.status == status)
+                .count()
+        };
+        let mut text = format!("Run      {}\n", self.run.display());
+        let _ = writeln!(
+            text,
+            "Digests  {} ok, {} modified, {} missing",
+            count(DigestStatus::Ok),
+            count(DigestStatus::Modified),
+            count(DigestStatus::Missing)
+        );
+        for check in self
+            .digests
+            .iter()
+            .filter(|check| check.status != DigestStatus::Ok)
+        {
+            let _ = writeln!(
+                text,
+                "  {:<8} {:<8} {}",
+                check.status.as_str(),
+                check.kind,
+                check.path
+            );
+        }
+        if let Some(rerun) = &self.rerun {
+            let _ = writeln!(text, "Rerun    {}", rerun.display());
+            for comparison in &self.comparisons {
+                let _ = write!(
+                    text,
+                    "  {:<16} {}",
+                    comparison.status.as_str(),
+                    comparison.path
+                );
+                if let Some(difference) = comparison.max_abs_difference {
+                    let _ = write!(text, " (max |Δ| {difference:.3e})");
+                }
+                if let Some(detail) = &comparison.detail {
+                    let _ = write!(text, ": {detail}");
+                }
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// One CSV row per digest and per comparison.
+    pub fn write_csv(&self, output: impl Write) -> io::Result<()> {
+        let mut writer = csv::Writer::from_writer(output);
+        writer.write_record(["check", "kind", "path", "status", "detail"])?;
+        for check in &self.digests {
+            writer.write_record(["digest", check.kind, &check.path, check.status.as_str(), ""])?;
+        }
+        for comparison in &self.comparisons {
+            let difference = comparison
+                .max_abs_difference
+                .map(|difference| format!("max |Δ| {difference:e}"));
+            writer.write_record([
+                "rerun",
+                "artifact",
+                &comparison.path,
+                comparison.status.as_str(),
+                comparison
+                    .detail
+                    .as_deref()
+                    .or(difference.as_deref())
+                    .unwrap_or(""),
+            ])?;
+        }
+        writer.flush()
+    }
+}
+
+/// Compare two text artifacts token by token, treating tokens that parse
+/// as numbers as equal within `tolerances`. Returns the largest absolute
+/// difference, or the first mismatch.
+fn compare_numbers(first: &[u8], second: &[u8], tolerances: Tolerances) -> Result<f64, String> {
+    let (Ok(first), Ok(second)) = (std::str::from_utf8(first), std::str::from_utf8(second)) else {
+        return Err("binary contents differ".to_owned());
+    };
+    let split = |text: &str| -> Vec<String> {
+        text.split(|character: char| {
+            character.is_whitespace()
+                || matches!(character, ',' | ':' | '[' | ']' | '{' | '}' | '"')
+        })
+        .filter(|token| !token.is_empty())
+        .map(str::to_owned)
+        .collect()
+    };
+    let (first, second) = (split(first), split(second));
+    if first.len() != second.len() {
+        return Err(format!(
+            "{} tokens instead of {}",
+            second.len(),
+            first.len()
+        ));
+    }
+    let mut max_difference = 0.0_f64;
+    for (index, (a, b)) in first.iter().zip(&second).enumerate() {
+        if a == b {
+            continue;
+        }
+        match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(x), Ok(y)) if x.is_finite() && y.is_finite() => {
+                let difference = (x - y).abs();
+                let allowed = tolerances.absolute + tolerances.relative * x.abs().max(y.abs());
+                if difference > allowed {
+                    return Err(format!(
+                        "token {} differs beyond tolerance: {a} and {b}",
+                        index + 1
+                    ));
+                }
+                max_difference = max_difference.max(difference);
+            }
+            _ => return Err(format!("token {} differs: '{a}' and '{b}'", index + 1)),
+        }
+    }
+    Ok(max_difference)
+}
+
+/// Digest every file below `directory` except the manifest itself, in
+/// path order.
+fn directory_digests(directory: &Path) -> Result<Vec<FileDigest>, ManifestError> {
+    let mut files = Vec::new();
+    collect_files(directory, &mut files)?;
+    files.sort();
+    files
+        .into_iter()
+        .filter(|path| path.as_path() != Path::new(MANIFEST_FILENAME))
+        .map(|relative| {
+            let path = directory.join(&relative);
+            let (sha256, bytes) =
+                sha256_file(&path).map_err(|source| ManifestError::Io { path, source })?;
+            Ok(FileDigest {
+                path: relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                bytes,
+                sha256,
+            })
+        })
+        .collect()
+}
+
+/// Collect the paths of all files below `root`, relative to it.
+fn collect_files(root: &Path, files: &mut Vec<PathBuf>) -> Result<(), ManifestError> {
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let directory = root.join(&relative);
+        let io_error = |source| ManifestError::Io {
+            path: directory.clone(),
+            source,
+        };
+        for entry in std::fs::read_dir(&directory).map_err(io_error)? {
+            let entry = entry.map_err(io_error)?;
+            let file_type = entry.file_type().map_err(io_error)?;
+            let path = relative.join(entry.file_name());
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The first version of each backend found in the JSON artifacts, else the
+/// one from its handshake.
+fn backend_versions(
+    directory: &Path,
+    artifacts: &[FileDigest],
+    handshake_versions: &BTreeMap<String, String>,
+) -> BackendVersions {
+    let mut found: BTreeMap<&'static str, String> = BTreeMap::new();
+    for artifact in artifacts
+        .iter()
+        .filter(|artifact| artifact.path.ends_with(".json"))
+        .filter(|artifact| artifact.bytes <= MAX_VERSION_SOURCE_BYTES)
+    {
+        let Ok(text) = std::fs::read_to_string(directory.join(&artifact.path)) else {
+            continue;
+        };
+        if let Ok(value) = serde_json::from_str::<Value>(&text) {
+            find_versions(&value, &mut found);
+        }
+    }
+    for name in VERSION_KEYS {
+        if let Some(version) = handshake_versions.get(name) {
+            found.entry(name).or_insert_with(|| version.clone());
+        }
+    }
+    BackendVersions {
+        apfelxx: found.remove("apfelxx_version"),
+        lhapdf: found.remove("lhapdf_version"),
+        pythia: found.remove("pythia_version"),
+        hepmc3: found.remove("hepmc3_version"),
+    }
+}
+
+fn find_versions(value: &Value, found: &mut BTreeMap<&'static str, String>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                match (VERSION_KEYS.iter().find(|name| *name == key), field) {
+                    (Some(name), Value::String(version)) if !version.trim().is_empty() => {
+                        found
+                            .entry(name)
+                            .or_insert_with(|| version.trim().to_owned());
+                    }
+                    _ => find_versions(field, found),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                find_versions(value, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn lhapdf_config_version() -> Option<String> {
+    let output = Command::new("lhapdf-config")
+        .arg("--version")
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let version = String::from_utf8(output.stdout).ok()?;
+    Some(version.trim().to_owned()).filter(|version| !version.is_empty())
+}
+
+/// The sets named by the PDF options of `card`, without member suffixes.
+fn pdf_set_names(card: &RunCard) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for option in PDF_SET_OPTIONS {
+        let values = match card.options.get(option) {
+            Some(Value::Array(values)) => values.iter().collect(),
+            Some(value) => vec![value],
+            None => Vec::new(),
+        };
+        for value in values.into_iter().filter_map(Value::as_str) {
+            let name = value.split('/').next().unwrap_or_default().trim();
+            if !name.is_empty() {
+                names.insert(name.to_owned());
+            }
+        }
+    }
+    names
+}
+
+fn pdf_set_digest(name: &str) -> Result<PdfSetDigest, ManifestError> {
+    let directory = LhapdfReport::scan()
+        .data_paths
+        .into_iter()
+        .filter(|data_path| data_path.exists)
+        .map(|data_path| data_path.path.join(name))
+        .find(|directory| directory.join(format!("{name}.info")).is_file());
+    let Some(directory) = directory else {
+        return Ok(PdfSetDigest {
+            name: name.to_owned(),
+            directory: None,
+            files: 0,
+            sha256: None,
+        });
+    };
+    let mut files = Vec::new();
+    collect_files(&directory, &mut files)?;
+    files.sort();
+    let mut listing = String::new();
+    for relative in &files {
+        let path = directory.join(relative);
+        let (sha256, _) =
+            sha256_file(&path).map_err(|source| ManifestError::Io { path, source })?;
+        listing.push_str(&format!("{sha256}  {}\n", relative.display()));
+    }
+    Ok(PdfSetDigest {
+        name: name.to_owned(),
+        directory: Some(directory),
+        files: files.len(),
+        sha256: Some(sha256_hex(listing.as_bytes())),
+    })
+}
+
+/// Digests of the existing files named by the options of `card`, other
+/// than the output location.
+fn input_file_digests(card: &RunCard) -> Result<Vec<FileDigest>, ManifestError> {
+    let mut paths = BTreeSet::new();
+    for (_, value) in card.options.iter().filter(|(name, _)| *name != "output") {
+        let values = match value {
+            Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        paths.extend(
+            values
+                .into_iter()
+                .filter_map(Value::as_str)
+                .filter(|text| Path::new(text).is_file())
+                .map(str::to_owned),
+        );
+    }
+    paths
+        .into_iter()
+        .map(|path| {
+            let (sha256, bytes) =
+                sha256_file(Path::new(&path)).map_err(|source| ManifestError::Io {
+                    path: PathBuf::from(&path),
+                    source,
+                })?;
+            Ok(FileDigest {
+                path,
+                bytes,
+                sha256,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary_directory(test: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("quark_sim_manifest_{test}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(directory.join("plots")).unwrap();
+        directory
+    }
+
+    #[test]
+    fn manifests_record_and_recheck_every_artifact() {
+        let directory = temporary_directory("record");
+        let input = directory.with_extension("points.csv");
+        std::fs::write(&input, "x,q2\n0.01,100\n").unwrap();
+        std::fs::write(
+            directory.join("summary.json"),
+            r#"{"metadata": {"pythia_version": "8.312", "hepmc3_version": "3.3.0"}}"#,
+        )
+        .unwrap();
+        std::fs::write(directory.join("plots/f2.csv"), "x,f2\n0.01,1.25\n").unwrap();
+
+        let mut card = RunCard {
+            schema_version: 1,
+            command: Some("analyze-events".to_owned()),
+            ..RunCard::default()
+        };
+        card.set_option("points", input.display().to_string());
+        card.set_option("output", directory.display().to_string());
+        let mut recorder = ManifestRecorder::start(&card);
+        recorder.record_seed("seed", 7);
+        #[cfg(unix)]
+        let backend = {
+            use std::os::unix::fs::PermissionsExt;
+            // Versions in the artifacts take precedence over the handshake.
+            let backend = directory.with_extension("apfel_cli");
+            std::fs::write(
+                &backend,
+                "#!/bin/sh\ncat > /dev/null\necho '{\"schema_version\":1,\"success\":true,\"type\":\"handshake\",\"apfelxx_version\":\"4.8.0\",\"pythia_version\":\"8.200\"}'\n",
+            )
+            .unwrap();
+            std::fs::set_permissions(&backend, std::fs::Permissions::from_mode(0o755)).unwrap();
+            recorder.record_backend(Backend::Apfel, &backend);
+            backend
+        };
+        let path = recorder.finish(&directory).unwrap();
+        assert_eq!(path, directory.join(MANIFEST_FILENAME));
+
+        let manifest = RunManifest::load(&directory).unwrap();
+        let artifacts: Vec<&str> = manifest
+            .artifacts
+            .iter()
+            .map(|artifact| artifact.path.as_str())
+            .collect();
+        assert_eq!(artifacts, ["plots/f2.csv", "summary.json"]);
+        assert_eq!(
+            manifest.artifacts[0].sha256,
+            sha256_hex(b"x,f2\n0.01,1.25\n")
+        );
+        assert_eq!(manifest.input_files.len(), 1);
+        assert_eq!(manifest.backends.pythia.as_deref(), Some("8.312"));
+        assert_eq!(manifest.backends.hepmc3.as_deref(), Some("3.3.0"));
+        #[cfg(unix)]
+        {
+            assert_eq!(manifest.backends.apfelxx.as_deref(), Some("4.8.0"));
+            std::fs::remove_file(backend).unwrap();
+        }
+        assert_eq!(manifest.seeds["seed"], 7);
+        assert!(manifest.wall_time_seconds >= 0.0);
+        assert!(verify_digests(&directory, &manifest)
+            .iter()
+            .all(|check| check.status == DigestStatus::Ok));
+
+        std::fs::write(directory.join("plots/f2.csv"), "x,f2\n0.01,1.26\n").unwrap();
+        std::fs::remove_file(&input).unwrap();
+        let statuses: Vec<(&str, DigestStatus)> = verify_digests(&directory, &manifest)
+            .iter()
+            .map(|check| (check.kind, check.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("artifact", DigestStatus::Modified),
+                ("artifact", DigestStatus::Ok),
+                ("input", DigestStatus::Missing),
+            ]
+        );
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn repeated_artifacts_compare_within_tolerances() {
+        let tolerances = Tolerances {
+            relative: 1.0e-6,
+            absolute: 0.0,
+        };
+        let difference =
+            compare_numbers(b"x,f2\n0.01,1.25\n", b"x,f2\n0.01,1.2500001\n", tolerances).unwrap();
+        assert!((difference - 1.0e-7).abs() < 1.0e-12);
+        assert!(compare_numbers(b"x,f2\n0.01,1.25\n", b"x,f2\n0.01,1.3\n", tolerances).is_err());
+        assert!(compare_numbers(b"{\"a\": \"b\"}", b"{\"a\": \"c\"}", tolerances).is_err());
+        assert!(compare_numbers(b"1 2", b"1 2 3", tolerances).is_err());
+    }
+}
@@ -0,0 +1,369 @@
+//! Curves and SVG plots of parton densities for the `pdf` command.
+//!
+//! A curve is `x * f(x, Q^2)` of one flavour at fixed `Q^2`, optionally with
+//! the PDF uncertainty of its set drawn as a band. Like the other artifacts,
+//! an existing plot is never overwritten.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use plotters::backend::SVGBackend;
+use plotters::prelude::*;
+use serde::Serialize;
+
+use crate::physics::{PdfError, PdfErrorSet, PdfFlavour, PdfProvider};
+
+/// `x * f` of one flavour at one `x`. Without an error set both
+/// uncertainties are zero.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PdfCurvePoint {
+    pub x: f64,
+    pub central: f64,
+    pub plus: f64,
+    pub minus: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfCurve {
+    pub flavour: PdfFlavour,
+    pub points: Vec<PdfCurvePoint>,
+}
+
+/// Evaluate `flavours` at every `x` and fixed `q2`.
+///
+/// `members[0]` gives the central value. With an error set, `members` holds
+/// the whole set in member order and the spread is combined with its
+/// prescription; otherwise only the first member is evaluated.
+pub fn pdf_curves(
+    members: &[&dyn PdfProvider],
+    error_set: Option<&PdfErrorSet>,
+    flavours: &[PdfFlavour],
+    x_values: &[f64],
+    q2: f64,
+) -> Result<Vec<PdfCurve>, PdfError> {
+    let members = match error_set {
+        Some(_) => members,
+        None => &members[..members.len().min(1)],
+    };
+    let mut curves: Vec<PdfCurve> = flavours
+        .iter()
+        .map(|&flavour| PdfCurve {
+            flavour,
+            points: Vec::with_capacity(x_values.len()),
+        })
+        .collect();
+    for &x in x_values {
+        let densities = members
+            .iter()
+            .map(|member| member.parton_densities(x, q2))
+            .collect::<Result<Vec<_>, _>>()?;
+        for curve in &mut curves {
+            let values: Vec<f64> = densities
+                .iter()
+                .map(|densities| curve.flavour.xf(densities))
+                .collect();
+            let central = values.first().copied().unwrap_or(f64::NAN);
+            let (plus, minus) = error_set
+                .and_then(|error_set| error_set.uncertainty(&values))
+                .map_or((0.0, 0.0), |uncertainty| {
+                    (uncertainty.plus, uncertainty.minus)
+                });
+            curve.points.push(PdfCurvePoint {
+                x,
+                central,
+                plus,
+                minus,
+            });
+        }
+    }
+    Ok(curves)
+}
+
+#[derive(Debug)]
+pub enum PdfArtifactError {
+    NoCurves,
+    ArtifactExists {
+        path: PathBuf,
+    },
+    Io {
+        operation: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    Plot {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl fmt::Display for PdfArtifactError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCurves => write!(formatter, "a PDF plot needs at least one curve with points"),
+            Self::ArtifactExists { path } => write!(
+                formatter,
+                "refusing to overwrite existing PDF plot '{}'",
+                path.display()
+            ),
+            Self::Io {
+                operation,
+                path,
+                source,
+            } => write!(
+                formatter,
+                "failed while {operation} '{}': {source}",
+                path.display()
+            ),
+            Self::Plot { path, message } => write!(
+                formatter,
+                "failed to render SVG '{}': {message}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for PdfArtifactError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Render `curves` against a logarithmic `x` axis and write them to a new
+/// SVG file.
+pub fn write_pdf_plot(
+    path: &Path,
+    title: &str,
+    curves: &[PdfCurve],
+) -> Result<(), PdfArtifactError> {
+    // Render in memory first so a plotting failure leaves no file behind.
+    let svg = render_pdf_plot(path, title, curves)?;
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|source| {
+            if source.kind() == io::ErrorKind::AlreadyExists {
+                PdfArtifactError::ArtifactExists {
+                    path: path.to_owned(),
+                }
+            } else {
+                PdfArtifactError::Io {
+                    operation: "creating",
+                    path: path.to_owned(),
+                    source,
+                }
+            }
+        })?
+        .write_all(svg.as_bytes())
+        .map_err(|source| PdfArtifactError::Io {
+            operation: "writing SVG",
+            path: path.to_owned(),
+            source,
+        })
+}
+
+fn render_pdf_plot(
+    path: &Path,
+    title: &str,
+    curves: &[PdfCurve],
+) -> Result<String, PdfArtifactError> {
+    let points = || curves.iter().flat_map(|curve| &curve.points);
+    let (mut x_min, mut x_max) = (f64::INFINITY, f64::NEG_INFINITY);
+    let (mut y_min, mut y_max) = (0.0_f64, f64::NEG_INFINITY);
+    for point in points().filter(|point| point.x > 0.0 && point.central.is_finite()) {
+        x_min = x_min.min(point.x.log10());
+        x_max = x_max.max(point.x.log10());
+        y_min = y_min.min(point.central - point.minus);
+        y_max = y_max.max(point.central + point.plus);
+    }
+    if !x_min.is_finite() || !y_max.is_finite() {
+        return Err(PdfArtifactError::NoCurves);
+    }
+    if x_max - x_min < 1.0e-9 {
+        x_min -= 0.5;
+        x_max += 0.5;
+    }
+    let y_padding = 0.05 * (y_max - y_min).max(1.0e-9);
+    let y_range = (y_min - if y_min < 0.0 { y_padding } else { 0.0 })..(y_max + y_padding);
+
+    let mut svg = String::new();
+    {
+        let drawing_area = SVGBackend::with_string(&mut svg, (1_000, 700)).into_drawing_area();
+        drawing_area
+            .fill(&WHITE)
+            .map_err(|error| plot_error(path, error))?;
+        let mut chart = ChartBuilder::on(&drawing_area)
+            .caption(title, ("sans-serif", 24))
+            .margin(16)
+            .x_label_area_size(50)
+            .y_label_area_size(70)
+            .build_cartesian_2d(x_min..x_max, y_range)
+            .map_err(|error| plot_error(path, error))?;
+        chart
+            .configure_mesh()
+            .x_desc("x (log scale)")
+            .y_desc("x f(x, Q²)")
+            .x_label_formatter(&|log_x| format!("{:.0e}", 10.0_f64.powf(*log_x)))
+            .draw()
+            .map_err(|error| plot_error(path, error))?;
+
+        for (index, curve) in curves.iter().enumerate() {
+            let colour = Palette99::pick(index).to_rgba();
+            let finite: Vec<&PdfCurvePoint> = curve
+                .points
+                .iter()
+                .filter(|point| point.x > 0.0 && point.central.is_finite())
+                .collect();
+            if finite
+                .iter()
+                .any(|point| point.plus > 0.0 || point.minus > 0.0)
+            {
+                let band = finite
+                    .iter()
+                    .map(|point| (point.x.log10(), point.central + point.plus))
+                    .chain(
+                        finite
+                            .iter()
+                            .rev()
+                            .map(|point| (point.x.log10(), point.central - point.minus)),
+                    );
+                chart
+                    .draw_series(std::iter::once(Polygon::new(
+                        band.collect::<Vec<_>>(),
+                        colour.mix(0.25).filled(),
+                    )))
+                    .map_err(|error| plot_error(path, error))?;
+            }
+            chart
+                .draw_series(LineSeries::new(
+                    finite.iter().map(|point| (point.x.log10(), point.central)),
+                    colour.stroke_width(2),
+                ))
+                .map_err(|error| plot_error(path, error))?
+                .label(curve.flavour.as_str())
+                .legend(move |(x, y)| {
+                    PathElement::new(vec![(x, y), (x + 20, y)], colour.stroke_width(2))
+                });
+        }
+
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()
+            .map_err(|error| plot_error(path, error))?;
+        drawing_area
+            .present()
+            .map_err(|error| plot_error(path, error))?;
+    }
+    Ok(svg)
+}
+
+fn plot_error(path: &Path, error: impl fmt::Display) -> PdfArtifactError {
+    PdfArtifactError::Plot {
+        path: path.to_owned(),
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{PartonDensities, PdfErrorType};
+
+    /// Every flavour `scale * x^power`, with the valence quarks doubled.
+    struct PowerPdf {
+        scale: f64,
+    }
+
+    impl PdfProvider for PowerPdf {
+        fn parton_densities(&self, x: f64, q2: f64) -> Result<PartonDensities, PdfError> {
+            let value = self.scale * x.sqrt();
+            Ok(PartonDensities {
+                x,
+                q2,
+                gluon: 10.0 * value,
+                up: 2.0 * value,
+                anti_up: value,
+                down: 2.0 * value,
+                anti_down: value,
+                strange: value,
+                anti_strange: value,
+                charm: 0.0,
+                anti_charm: 0.0,
+                bottom: 0.0,
+                anti_bottom: 0.0,
+            })
+        }
+    }
+
+    #[test]
+    fn flavours_parse_from_names_and_pdg_ids() {
+        let parse = |text: &str| text.parse::<PdfFlavour>();
+        assert_eq!(parse("g"), Ok(PdfFlavour::Gluon));
+        assert_eq!(parse("21"), Ok(PdfFlavour::Gluon));
+        assert_eq!(parse("Gluon"), Ok(PdfFlavour::Gluon));
+        assert_eq!(parse("-2"), Ok(PdfFlavour::AntiUp));
+        assert_eq!(parse("UV"), Ok(PdfFlavour::UpValence));
+        assert_eq!(PdfFlavour::AntiBottom.column(), "xbbar");
+        assert_eq!(parse("6").unwrap_err().value(), "6");
+        assert!(parse("top").is_err());
+    }
+
+    #[test]
+    fn curves_combine_members_with_the_error_prescription() {
+        let members = [
+            PowerPdf { scale: 1.0 },
+            PowerPdf { scale: 1.1 },
+            PowerPdf { scale: 0.8 },
+        ];
+        let members: Vec<&dyn PdfProvider> = members
+            .iter()
+            .map(|member| member as &dyn PdfProvider)
+            .collect();
+        let error_set = PdfErrorSet {
+            error_type: PdfErrorType::Hessian,
+            error_members: 2,
+        };
+        let flavours = [PdfFlavour::UpValence, PdfFlavour::Gluon];
+
+        let curves =
+            pdf_curves(&members, Some(&error_set), &flavours, &[0.01, 0.25], 10.0).unwrap();
+        let valence = curves[0].points[1];
+        assert_eq!(valence.x, 0.25);
+        assert!((valence.central - 0.5).abs() < 1e-12);
+        assert!((valence.plus - 0.05).abs() < 1e-12);
+        assert!((valence.minus - 0.1).abs() < 1e-12);
+        assert!((curves[1].points[0].central - 1.0).abs() < 1e-12);
+
+        let central = pdf_curves(&members, None, &flavours, &[0.25], 10.0).unwrap();
+        assert_eq!(central[0].points[0].plus, 0.0);
+        assert_eq!(central[0].points[0].minus, 0.0);
+
+        let path =
+            std::env::temp_dir().join(format!("quark_sim_pdf_plot_{}.svg", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        write_pdf_plot(&path, "PowerPdf at Q² = 10 GeV²", &curves).unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.contains("<svg"));
+        let labels: Vec<&str> = svg.lines().map(str::trim).collect();
+        assert!(labels.contains(&"uv") && labels.contains(&"g"));
+        assert!(matches!(
+            write_pdf_plot(&path, "again", &curves),
+            Err(PdfArtifactError::ArtifactExists { .. })
+        ));
+        assert!(matches!(
+            write_pdf_plot(&path, "empty", &[]),
+            Err(PdfArtifactError::NoCurves)
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
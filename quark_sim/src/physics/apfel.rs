@@ -268,6 +268,7 @@ fn interpret_response(
         f2,
         fl,
         xf3,
+        uncertainty: None,
         metadata,
     };
     result.validate_finite()?;
@@ -433,6 +434,13 @@ mod tests {
             mu_r_over_q: request.mu_r_over_q,
             scheme: APFEL_ZM_VFNS_SCHEME.to_owned(),
             electromagnetic_mode: PHOTON_EXCHANGE_MODE.to_owned(),
+            os_arch: None,
+            rust_version: None,
+            git_commit: None,
+            git_dirty: None,
+            pythia_version: None,
+            hepmc_version: None,
+            python_env_hash: None,
         }
     }
 
@@ -0,0 +1,266 @@
+//! The Breit frame of deep-inelastic scattering.
+//!
+//! In the Breit frame the exchanged boson carries no energy,
+//! `q = (0, 0, 0, -Q)`, and the incoming proton travels along `+z`. In the
+//! quark-parton model the struck quark arrives with `p_z = Q/2` and leaves
+//! with `p_z = -Q/2`, so jets with transverse energy in this frame signal
+//! hard QCD radiation. The hemisphere with `p_z < 0` is the current
+//! hemisphere used by event-shape measurements.
+//!
+//! The frame is the rest frame of `2xP + q`, rotated so that the proton
+//! points along `+z`. This follows the H1 and ZEUS convention, independent of
+//! the laboratory convention in which the electron travels along `+z`.
+
+use std::error::Error;
+use std::fmt;
+
+use super::dis_kinematics::DisKinematics;
+use super::four_vector::FourVector;
+
+/// Reasons the Breit frame cannot be built from the given momenta.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BreitFrameError {
+    NonFiniteInput { quantity: &'static str, value: f64 },
+    NonPositiveQ2 { q2: f64 },
+    InvalidBjorkenX { x: f64 },
+}
+
+impl fmt::Display for BreitFrameError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFiniteInput { quantity, value } => {
+                write!(formatter, "{quantity} is not finite: {value}")
+            }
+            Self::NonPositiveQ2 { q2 } => {
+                write!(formatter, "the Breit frame needs Q² > 0, got {q2}")
+            }
+            Self::InvalidBjorkenX { x } => {
+                write!(formatter, "the Breit frame needs 0 < x ≤ 1, got {x}")
+            }
+        }
+    }
+}
+
+impl Error for BreitFrameError {}
+
+/// Lorentz transformation from the laboratory frame to the Breit frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreitFrame {
+    q2: f64,
+    /// Velocity of the Breit frame in the laboratory.
+    beta: [f64; 3],
+    gamma: f64,
+    /// Rotation applied after the boost, as rows of a 3×3 matrix.
+    rotation: [[f64; 3]; 3],
+}
+
+impl BreitFrame {
+    /// Build the frame from the incoming proton and the exchanged boson
+    /// momentum `q = k - k'`, both in the laboratory.
+    pub fn new(proton: FourVector, q: FourVector) -> Result<Self, BreitFrameError> {
+        for (quantity, vector) in [("proton momentum", proton), ("q", q)] {
+            vector
+                .validate()
+                .map_err(|_| BreitFrameError::NonFiniteInput {
+                    quantity,
+                    value: first_non_finite(vector),
+                })?;
+        }
+        let q2 = -q.mass_squared();
+        if q2.is_nan() || q2 <= 0.0 {
+            return Err(BreitFrameError::NonPositiveQ2 { q2 });
+        }
+        let x = q2 / (2.0 * proton.dot(q));
+        if !(x > 0.0 && x <= 1.0) {
+            return Err(BreitFrameError::InvalidBjorkenX { x });
+        }
+
+        let breit = FourVector::new(
+            2.0 * x * proton.e + q.e,
+            2.0 * x * proton.px + q.px,
+            2.0 * x * proton.py + q.py,
+            2.0 * x * proton.pz + q.pz,
+        );
+        let beta = [breit.px / breit.e, breit.py / breit.e, breit.pz / breit.e];
+        let gamma = breit.e / breit.mass_squared().sqrt();
+        let mut frame = Self {
+            q2,
+            beta,
+            gamma,
+            rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        };
+        let boosted = frame.boost(proton, 1.0);
+        frame.rotation = rotation_onto_z([boosted.px, boosted.py, boosted.pz]);
+        Ok(frame)
+    }
+
+    /// Build the frame from already computed DIS invariants.
+    pub fn from_kinematics(
+        proton: FourVector,
+        kinematics: &DisKinematics,
+    ) -> Result<Self, BreitFrameError> {
+        Self::new(proton, kinematics.q)
+    }
+
+    /// Momentum transfer `Q = sqrt(Q²)` in GeV; the exchanged boson is
+    /// `(0, 0, 0, -Q)` in this frame.
+    #[must_use]
+    pub fn q(&self) -> f64 {
+        self.q2.sqrt()
+    }
+
+    /// Transform a laboratory momentum into the Breit frame.
+    #[must_use]
+    pub fn to_breit(&self, momentum: FourVector) -> FourVector {
+        let boosted = self.boost(momentum, 1.0);
+        let [px, py, pz] = rotate(&self.rotation, [boosted.px, boosted.py, boosted.pz]);
+        FourVector::new(boosted.e, px, py, pz)
+    }
+
+    /// Transform a Breit-frame momentum back into the laboratory.
+    #[must_use]
+    pub fn to_lab(&self, momentum: FourVector) -> FourVector {
+        let transposed = [
+            [
+                self.rotation[0][0],
+                self.rotation[1][0],
+                self.rotation[2][0],
+            ],
+            [
+                self.rotation[0][1],
+                self.rotation[1][1],
+                self.rotation[2][1],
+            ],
+            [
+                self.rotation[0][2],
+                self.rotation[1][2],
+                self.rotation[2][2],
+            ],
+        ];
+        let [px, py, pz] = rotate(&transposed, [momentum.px, momentum.py, momentum.pz]);
+        self.boost(FourVector::new(momentum.e, px, py, pz), -1.0)
+    }
+
+    /// Whether a Breit-frame momentum lies in the current hemisphere, `p_z < 0`.
+    #[must_use]
+    pub fn is_current_hemisphere(breit_momentum: FourVector) -> bool {
+        breit_momentum.pz < 0.0
+    }
+
+    /// Boost into the frame moving with `direction * beta`.
+    fn boost(&self, momentum: FourVector, direction: f64) -> FourVector {
+        let beta = self.beta.map(|component| direction * component);
+        let beta2 = beta[0] * beta[0] + beta[1] * beta[1] + beta[2] * beta[2];
+        if beta2 == 0.0 {
+            return momentum;
+        }
+        let beta_dot_p = beta[0] * momentum.px + beta[1] * momentum.py + beta[2] * momentum.pz;
+        let gamma = self.gamma;
+        let factor = (gamma - 1.0) * beta_dot_p / beta2 - gamma * momentum.e;
+        FourVector::new(
+            gamma * (momentum.e - beta_dot_p),
+            momentum.px + factor * beta[0],
+            momentum.py + factor * beta[1],
+            momentum.pz + factor * beta[2],
+        )
+    }
+}
+
+fn first_non_finite(vector: FourVector) -> f64 {
+    [vector.e, vector.px, vector.py, vector.pz]
+        .into_iter()
+        .find(|value| !value.is_finite())
+        .unwrap_or(f64::NAN)
+}
+
+fn rotate(matrix: &[[f64; 3]; 3], vector: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+/// Rotation taking `vector` onto `+z` about the axis `vector × ẑ`.
+fn rotation_onto_z(vector: [f64; 3]) -> [[f64; 3]; 3] {
+    let length = (vector[0] * vector[0] + vector[1] * vector[1] + vector[2] * vector[2]).sqrt();
+    let [nx, ny, nz] = vector.map(|component| component / length);
+    if nz < -1.0 + 1e-15 {
+        // Anti-parallel: turn by π about the x axis.
+        return [[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]];
+    }
+    // Rodrigues' formula with v = n × ẑ = (ny, -nx, 0) and cos θ = nz.
+    let (vx, vy) = (ny, -nx);
+    let k = 1.0 / (1.0 + nz);
+    [
+        [1.0 - k * vy * vy, k * vx * vy, vy],
+        [k * vx * vy, 1.0 - k * vx * vx, -vx],
+        [-vy, vx, 1.0 - k * (vx * vx + vy * vy)],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{collider_beams, compute_dis_kinematics, scattered_electron};
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {expected:.12e}, got {actual:.12e}"
+        );
+    }
+
+    #[test]
+    fn exchanged_boson_is_space_like_along_minus_z() {
+        let beams = collider_beams(27.5, 920.0).unwrap();
+        let electron = scattered_electron(22.0, 150.0).unwrap();
+        let kinematics = compute_dis_kinematics(beams.proton, beams.electron, electron).unwrap();
+        let frame = BreitFrame::from_kinematics(beams.proton, &kinematics).unwrap();
+        let q = frame.q();
+
+        let boson = frame.to_breit(kinematics.q);
+        assert_close(boson.e, 0.0, 1e-9 * q);
+        assert_close(boson.transverse_momentum(), 0.0, 1e-9 * q);
+        assert_close(boson.pz, -q, 1e-9 * q);
+
+        let proton = frame.to_breit(beams.proton);
+        assert_close(proton.transverse_momentum(), 0.0, 1e-9 * proton.e);
+        assert!(proton.pz > 0.0);
+
+        // The quark-parton-model struck quark xP + q is back-scattered into
+        // the current hemisphere with |p_z| = Q/2, up to the proton mass.
+        let x = kinematics.x;
+        let quark = FourVector::new(
+            x * beams.proton.e,
+            x * beams.proton.px,
+            x * beams.proton.py,
+            x * beams.proton.pz,
+        ) + kinematics.q;
+        let quark = frame.to_breit(quark);
+        assert!(BreitFrame::is_current_hemisphere(quark));
+        assert_close(quark.pz, -0.5 * q, 1e-3 * q);
+        assert_close(quark.transverse_momentum(), 0.0, 1e-9 * q);
+    }
+
+    #[test]
+    fn laboratory_round_trip_is_the_identity() {
+        let beams = collider_beams(27.5, 920.0).unwrap();
+        let electron = scattered_electron(30.0, 120.0).unwrap();
+        let kinematics = compute_dis_kinematics(beams.proton, beams.electron, electron).unwrap();
+        let frame = BreitFrame::from_kinematics(beams.proton, &kinematics).unwrap();
+
+        let hadron = FourVector::new(12.0, 3.0, -4.0, 10.0);
+        let breit = frame.to_breit(hadron);
+        assert_close(breit.mass_squared(), hadron.mass_squared(), 1e-9);
+        let back = frame.to_lab(breit);
+        for (actual, expected) in [
+            (back.e, hadron.e),
+            (back.px, hadron.px),
+            (back.py, hadron.py),
+            (back.pz, hadron.pz),
+        ] {
+            assert_close(actual, expected, 1e-9);
+        }
+        assert_eq!(
+            BreitFrame::new(beams.proton, FourVector::new(1.0, 0.0, 0.0, 0.5)),
+            Err(BreitFrameError::NonPositiveQ2 { q2: -0.75 })
+        );
+    }
+}
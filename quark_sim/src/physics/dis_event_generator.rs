@@ -0,0 +1,1427 @@
+//! Self-contained leading-order neutral-current DIS event generator.
+//!
+//! Events are drawn from `d²σ/(dx dQ²)` of [`lo_differential_cross_section`]
+//! with `F₂` supplied by any [`StructureFunctionProvider`]. Sampling is
+//! logarithmic in `x` and `Q²` on a VEGAS-style separable grid that is adapted
+//! to the integrand before generation; the electron azimuth `φ` is uniform.
+//! Every trial is mapped to exact on-shell four-vectors and checked against
+//! [`DisCuts`] through [`compute_dis_kinematics`], so accepted events satisfy
+//! the same cuts as the PYTHIA backend.
+//!
+//! The generator writes the run-directory layout of the PYTHIA backend
+//! (`config.json`, `metadata.json`, `events.hepmc3`,
+//! `inclusive_observables.csv`, `summary.json`) and adds a total
+//! cross-section estimate to the summary. Events contain the beams, the
+//! scattered electron, the virtual photon, the struck quark before and after
+//! absorbing it and a proton remnant carrying `(1 - x) P`. There is no parton
+//! shower or hadronization. When the struck flavour is drawn from a PDF, each
+//! event also records it in a `GenPdfInfo` attribute, so samples can be
+//! reweighted to other PDFs afterwards.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::constants::{ELECTRON_MASS_GEV, PROTON_MASS_GEV};
+use super::cross_section::{exact_inelasticity, lo_differential_cross_section, FixedAlpha};
+use super::dis_kinematics::{
+    collider_beams, compute_dis_kinematics, ColliderBeams, DisCuts, DisError, DisKinematics,
+};
+use super::four_vector::FourVector;
+use super::pdf::PdfProvider;
+use super::pdf_reweighting::{HardProcess, GEN_PDF_INFO_ATTRIBUTE};
+use super::structure_function_provider::{
+    PerturbativeOrder, StructureFunctionMetadata, StructureFunctionProvider,
+    StructureFunctionRequest,
+};
+use super::structure_functions::{DOWN_TYPE_CHARGE_SQUARED, UP_TYPE_CHARGE_SQUARED};
+use crate::hepmc3::{
+    Attribute, GenEvent, GenParticle, GenRunInfo, GenVertex, Hepmc3WriteError, Hepmc3Writer,
+    ToolInfo,
+};
+
+/// Name recorded in `metadata.json` and the HepMC3 tool list.
+pub const NATIVE_GENERATOR_NAME: &str = "quark_sim_lo_dis";
+
+/// Largest allowed four-momentum imbalance of a generated event, in GeV.
+pub const MOMENTUM_CONSERVATION_TOLERANCE_GEV: f64 = 1.0e-6;
+
+/// Trials allowed per requested event before generation gives up.
+const MAX_TRIALS_PER_EVENT: usize = 100_000;
+
+// HepMC3 status codes: beams, the PYTHIA-style incoming hard parton and
+// intermediate photon, and stable final-state particles.
+const BEAM_STATUS: i32 = 4;
+const INCOMING_PARTON_STATUS: i32 = 21;
+const INTERMEDIATE_STATUS: i32 = 13;
+const FINAL_STATE_STATUS: i32 = 1;
+
+const ELECTRON_PDG_ID: i32 = 11;
+const PHOTON_PDG_ID: i32 = 22;
+const PROTON_PDG_ID: i32 = 2212;
+const UP_QUARK_PDG_ID: i32 = 2;
+const UD_DIQUARK_PDG_ID: i32 = 2101;
+const UU_DIQUARK_PDG_ID: i32 = 2203;
+
+/// How generated events are weighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventWeighting {
+    /// Accept/reject against the maximum weight; every event has weight 1.
+    #[default]
+    Unweighted,
+    /// Keep every trial inside the cuts with its weight in pb.
+    Weighted,
+}
+
+impl EventWeighting {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unweighted => "unweighted",
+            Self::Weighted => "weighted",
+        }
+    }
+}
+
+impl fmt::Display for EventWeighting {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EventWeighting {
+    type Err = DisGeneratorError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "unweighted" => Ok(Self::Unweighted),
+            "weighted" => Ok(Self::Weighted),
+            _ => Err(DisGeneratorError::UnknownWeighting {
+                value: value.to_owned(),
+            }),
+        }
+    }
+}
+
+/// Adaptation of the importance-sampling grid before generation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImportanceSamplingConfig {
+    /// Grid bins per sampled dimension.
+    pub bins: usize,
+    /// Number of grid refinements.
+    pub iterations: usize,
+    /// Trials per refinement, also used to estimate the maximum weight.
+    pub points_per_iteration: usize,
+    /// VEGAS damping exponent; smaller values adapt more cautiously.
+    pub damping: f64,
+}
+
+impl Default for ImportanceSamplingConfig {
+    fn default() -> Self {
+        Self {
+            bins: 32,
+            iterations: 5,
+            points_per_iteration: 4000,
+            damping: 1.5,
+        }
+    }
+}
+
+/// Beams, cuts and run settings of one generator run, written as `config.json`.
+///
+/// Field names follow the PYTHIA backend request so run directories from both
+/// generators can be read by the same tools.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisGeneratorConfig {
+    pub schema_version: i32,
+    pub process: String,
+    pub electron_energy_gev: f64,
+    pub proton_energy_gev: f64,
+    pub q2_min_gev2: f64,
+    pub q2_max_gev2: f64,
+    pub x_min: f64,
+    pub x_max: f64,
+    pub y_min: f64,
+    pub y_max: f64,
+    #[serde(default)]
+    pub w2_min_gev2: f64,
+    pub number_of_events: usize,
+    pub random_seed: u64,
+    pub pdf_set: String,
+    pub pdf_member: i32,
+    pub order: PerturbativeOrder,
+    #[serde(default)]
+    pub weighting: EventWeighting,
+    #[serde(default)]
+    pub importance_sampling: ImportanceSamplingConfig,
+}
+
+impl Default for DisGeneratorConfig {
+    fn default() -> Self {
+        Self {
+            schema_version: 1,
+            process: "neutral_current_dis".to_owned(),
+            electron_energy_gev: 27.5,
+            proton_energy_gev: 920.0,
+            q2_min_gev2: 10.0,
+            q2_max_gev2: 10000.0,
+            x_min: 1e-4,
+            x_max: 0.8,
+            y_min: 0.01,
+            y_max: 0.95,
+            w2_min_gev2: 0.0,
+            number_of_events: 1000,
+            random_seed: 0,
+            pdf_set: "CT18LO".to_owned(),
+            pdf_member: 0,
+            order: PerturbativeOrder::Lo,
+            weighting: EventWeighting::Unweighted,
+            importance_sampling: ImportanceSamplingConfig::default(),
+        }
+    }
+}
+
+impl DisGeneratorConfig {
+    #[must_use]
+    pub const fn cuts(&self) -> DisCuts {
+        DisCuts {
+            q2_min: self.q2_min_gev2,
+            q2_max: Some(self.q2_max_gev2),
+            x_min: self.x_min,
+            x_max: self.x_max,
+            y_min: self.y_min,
+            y_max: self.y_max,
+            w2_min: self.w2_min_gev2,
+        }
+    }
+
+    /// Reject settings that leave no sampling domain or no work to do.
+    pub fn validate(&self) -> Result<(), DisGeneratorError> {
+        collider_beams(self.electron_energy_gev, self.proton_energy_gev)?;
+        self.cuts().validate()?;
+        if self.q2_min_gev2 <= 0.0 || self.q2_max_gev2 <= self.q2_min_gev2 {
+            return Err(invalid_config(
+                "Q² cuts must satisfy 0 < q2_min_gev2 < q2_max_gev2",
+            ));
+        }
+        if self.x_min <= 0.0 || self.x_max <= self.x_min || self.x_max >= 1.0 {
+            return Err(invalid_config("x cuts must satisfy 0 < x_min < x_max < 1"));
+        }
+        if self.number_of_events == 0 {
+            return Err(invalid_config("number_of_events must be positive"));
+        }
+        if self.pdf_member < 0 {
+            return Err(invalid_config("pdf_member must be non-negative"));
+        }
+        let sampling = &self.importance_sampling;
+        if sampling.bins == 0 || sampling.points_per_iteration == 0 {
+            return Err(invalid_config(
+                "importance sampling needs at least one bin and one point per iteration",
+            ));
+        }
+        if !sampling.damping.is_finite() || sampling.damping <= 0.0 {
+            return Err(invalid_config(
+                "importance-sampling damping must be finite and positive",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn invalid_config(message: &str) -> DisGeneratorError {
+    DisGeneratorError::InvalidConfig {
+        message: message.to_owned(),
+    }
+}
+
+/// Generation statistics, written as `summary.json`.
+///
+/// The counters match the PYTHIA backend summary. `cross_section_pb` is the
+/// mean trial weight over all generation trials and `sum_of_weights` the sum
+/// of the event weights written, so `cross_section_pb / sum_of_weights`
+/// normalises a histogram filled with event weights to pb.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationSummary {
+    pub success: bool,
+    pub generator: String,
+    pub requested_events: usize,
+    pub attempted_events: usize,
+    pub accepted_events: usize,
+    pub failed_events: usize,
+    pub vetoed_cuts_events: usize,
+    pub vetoed_conservation_events: usize,
+    pub max_momentum_mismatch_gev: f64,
+    pub max_energy_mismatch_gev: f64,
+    pub momentum_conservation_tolerance_gev: f64,
+    pub failure_reasons: BTreeMap<String, usize>,
+    pub weighting: EventWeighting,
+    pub cross_section_pb: f64,
+    pub cross_section_error_pb: f64,
+    pub sum_of_weights: f64,
+    /// Final maximum weight used for unweighting, in pb.
+    pub max_weight_pb: f64,
+    /// Trials whose weight exceeded the maximum estimated during adaptation.
+    pub max_weight_violations: usize,
+}
+
+/// Failures that stop a generator run.
+#[derive(Debug)]
+pub enum DisGeneratorError {
+    InvalidConfig {
+        message: String,
+    },
+    UnknownWeighting {
+        value: String,
+    },
+    Kinematics(DisError),
+    /// No adaptation trial produced a positive weight.
+    EmptyPhaseSpace {
+        trials: usize,
+        last_failure: Option<String>,
+    },
+    LowEfficiency {
+        attempted: usize,
+        accepted: usize,
+        requested: usize,
+    },
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Serialization {
+        path: PathBuf,
+        message: String,
+    },
+    Hepmc3(Hepmc3WriteError),
+}
+
+impl fmt::Display for DisGeneratorError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidConfig { message } => {
+                write!(formatter, "invalid generator configuration: {message}")
+            }
+            Self::UnknownWeighting { value } => write!(
+                formatter,
+                "unknown event weighting '{value}'; expected unweighted or weighted"
+            ),
+            Self::Kinematics(source) => write!(formatter, "invalid beam or cut setup: {source}"),
+            Self::EmptyPhaseSpace {
+                trials,
+                last_failure,
+            } => {
+                write!(
+                    formatter,
+                    "none of {trials} adaptation trials produced a positive cross section inside the cuts"
+                )?;
+                if let Some(failure) = last_failure {
+                    write!(formatter, " (last failure: {failure})")?;
+                }
+                Ok(())
+            }
+            Self::LowEfficiency {
+                attempted,
+                accepted,
+                requested,
+            } => write!(
+                formatter,
+                "accepted only {accepted} of {requested} requested events after {attempted} trials"
+            ),
+            Self::Io { path, source } => write!(formatter, "{}: {source}", path.display()),
+            Self::Serialization { path, message } => {
+                write!(formatter, "failed to write {}: {message}", path.display())
+            }
+            Self::Hepmc3(source) => write!(formatter, "failed to write HepMC3 events: {source}"),
+        }
+    }
+}
+
+impl Error for DisGeneratorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Kinematics(source) => Some(source),
+            Self::Io { source, .. } => Some(source),
+            Self::Hepmc3(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<DisError> for DisGeneratorError {
+    fn from(source: DisError) -> Self {
+        Self::Kinematics(source)
+    }
+}
+
+impl From<Hepmc3WriteError> for DisGeneratorError {
+    fn from(source: Hepmc3WriteError) -> Self {
+        Self::Hepmc3(source)
+    }
+}
+
+/// Generate events into `run_dir`, which is created if needed.
+///
+/// `flavour_pdf` selects the struck-quark flavour with probability
+/// `e_q² x f_q(x, Q²)`; without it every struck quark is recorded as an up
+/// quark. Trials where the structure-function provider or the cross section
+/// fails are counted in `failed_events` rather than aborting the run.
+pub fn generate_dis_events(
+    provider: &dyn StructureFunctionProvider,
+    flavour_pdf: Option<&dyn PdfProvider>,
+    config: &DisGeneratorConfig,
+    run_dir: &Path,
+) -> Result<GenerationSummary, DisGeneratorError> {
+    config.validate()?;
+    fs::create_dir_all(run_dir).map_err(|source| io_error(run_dir, source))?;
+    write_json(&run_dir.join("config.json"), config)?;
+
+    let mut sampler = Sampler::new(provider, config)?;
+    let mut rng = StdRng::seed_from_u64(config.random_seed);
+    let mut max_weight = sampler.adapt(&mut rng)?;
+
+    let events_path = run_dir.join("events.hepmc3");
+    let mut writer = Hepmc3Writer::create(&events_path, run_info())?;
+    let observables_path = run_dir.join("inclusive_observables.csv");
+    let mut observables = csv::Writer::from_path(&observables_path)
+        .map_err(|source| serialization_error(&observables_path, source))?;
+
+    let requested = config.number_of_events;
+    let max_trials = requested.saturating_mul(MAX_TRIALS_PER_EVENT);
+    let mut summary = GenerationSummary {
+        success: false,
+        generator: NATIVE_GENERATOR_NAME.to_owned(),
+        requested_events: requested,
+        attempted_events: 0,
+        accepted_events: 0,
+        failed_events: 0,
+        vetoed_cuts_events: 0,
+        vetoed_conservation_events: 0,
+        max_momentum_mismatch_gev: 0.0,
+        max_energy_mismatch_gev: 0.0,
+        momentum_conservation_tolerance_gev: MOMENTUM_CONSERVATION_TOLERANCE_GEV,
+        failure_reasons: BTreeMap::new(),
+        weighting: config.weighting,
+        cross_section_pb: 0.0,
+        cross_section_error_pb: 0.0,
+        sum_of_weights: 0.0,
+        max_weight_pb: max_weight,
+        max_weight_violations: 0,
+    };
+    let mut weights = WeightSum::default();
+
+    while summary.accepted_events < requested {
+        if summary.attempted_events >= max_trials {
+            return Err(DisGeneratorError::LowEfficiency {
+                attempted: summary.attempted_events,
+                accepted: summary.accepted_events,
+                requested,
+            });
+        }
+        summary.attempted_events += 1;
+
+        let point = match sampler.trial(&mut rng) {
+            Trial::Inside(point) => point,
+            Trial::OutsideCuts => {
+                summary.vetoed_cuts_events += 1;
+                weights.add(0.0);
+                continue;
+            }
+            Trial::Failed(reason) => {
+                summary.failed_events += 1;
+                *summary
+                    .failure_reasons
+                    .entry(reason.to_owned())
+                    .or_default() += 1;
+                weights.add(0.0);
+                continue;
+            }
+        };
+        weights.add(point.weight);
+
+        let event_weight = match config.weighting {
+            EventWeighting::Weighted => point.weight,
+            EventWeighting::Unweighted => {
+                if point.weight > max_weight {
+                    summary.max_weight_violations += 1;
+                    max_weight = point.weight;
+                }
+                if rng.gen::<f64>() * max_weight >= point.weight {
+                    continue;
+                }
+                1.0
+            }
+        };
+
+        let (flavour, pdf_info) = match flavour_pdf {
+            Some(pdf) => match sample_flavour(pdf, point.x, point.q2, &mut rng) {
+                Some((flavour, xf)) => {
+                    let hard = HardProcess {
+                        x: point.x,
+                        q2: point.q2,
+                        flavour,
+                    };
+                    (flavour, Some(hard.gen_pdf_info(ELECTRON_PDG_ID, xf)))
+                }
+                None => {
+                    summary.failed_events += 1;
+                    *summary
+                        .failure_reasons
+                        .entry("flavour_pdf".to_owned())
+                        .or_default() += 1;
+                    continue;
+                }
+            },
+            None => (UP_QUARK_PDG_ID, None),
+        };
+
+        let event_number = summary.accepted_events + 1;
+        let (cross_section, cross_section_error) = weights.estimate();
+        let event = build_event(
+            &sampler.beams,
+            &point,
+            flavour,
+            event_number,
+            event_weight,
+            format!(
+                "{cross_section:e} {cross_section_error:e} {event_number} {}",
+                summary.attempted_events
+            ),
+            pdf_info,
+        );
+        let (momentum_mismatch, energy_mismatch) = conservation_mismatch(&event);
+        summary.max_momentum_mismatch_gev =
+            summary.max_momentum_mismatch_gev.max(momentum_mismatch);
+        summary.max_energy_mismatch_gev = summary.max_energy_mismatch_gev.max(energy_mismatch);
+        if momentum_mismatch > MOMENTUM_CONSERVATION_TOLERANCE_GEV
+            || energy_mismatch > MOMENTUM_CONSERVATION_TOLERANCE_GEV
+        {
+            summary.vetoed_conservation_events += 1;
+            continue;
+        }
+
+        writer.write_event(&event)?;
+        observables
+            .serialize(InclusiveObservables::new(&event, &point))
+            .map_err(|source| serialization_error(&observables_path, source))?;
+        summary.accepted_events += 1;
+        summary.sum_of_weights += event_weight;
+    }
+
+    writer.close()?;
+    observables
+        .flush()
+        .map_err(|source| io_error(&observables_path, source))?;
+
+    let (cross_section, cross_section_error) = weights.estimate();
+    summary.cross_section_pb = cross_section;
+    summary.cross_section_error_pb = cross_section_error;
+    summary.max_weight_pb = max_weight;
+    summary.success = true;
+
+    write_json(
+        &run_dir.join("metadata.json"),
+        &RunMetadata::new(config, &summary, sampler.metadata.as_ref()),
+    )?;
+    write_json(&run_dir.join("summary.json"), &summary)?;
+    Ok(summary)
+}
+
+/// Kinematics and weight of one trial inside the cuts.
+#[derive(Debug, Clone, Copy)]
+struct PhaseSpacePoint {
+    x: f64,
+    q2: f64,
+    y: f64,
+    kinematics: DisKinematics,
+    scattered_electron: FourVector,
+    /// Trial weight in pb: `d²σ/(dx dQ²)` times all sampling Jacobians.
+    weight: f64,
+}
+
+enum Trial {
+    Inside(PhaseSpacePoint),
+    OutsideCuts,
+    Failed(&'static str),
+}
+
+/// Maps grid points to weighted phase-space points.
+struct Sampler<'a> {
+    provider: &'a dyn StructureFunctionProvider,
+    config: &'a DisGeneratorConfig,
+    cuts: DisCuts,
+    beams: ColliderBeams,
+    s: f64,
+    coupling: FixedAlpha,
+    ln_x: (f64, f64),
+    ln_q2: (f64, f64),
+    grid: AdaptiveGrid,
+    metadata: Option<StructureFunctionMetadata>,
+}
+
+impl<'a> Sampler<'a> {
+    fn new(
+        provider: &'a dyn StructureFunctionProvider,
+        config: &'a DisGeneratorConfig,
+    ) -> Result<Self, DisGeneratorError> {
+        let beams = collider_beams(config.electron_energy_gev, config.proton_energy_gev)?;
+        let s = (beams.electron + beams.proton).mass_squared();
+        // Q² = x y (s - M²) can never exceed s, so larger cuts only waste trials.
+        let q2_max = config.q2_max_gev2.min(s);
+        if q2_max <= config.q2_min_gev2 {
+            return Err(invalid_config(
+                "q2_min_gev2 is above the kinematic limit Q² < s",
+            ));
+        }
+        Ok(Self {
+            provider,
+            config,
+            cuts: config.cuts(),
+            beams,
+            s,
+            coupling: FixedAlpha::default(),
+            ln_x: (config.x_min.ln(), config.x_max.ln()),
+            ln_q2: (config.q2_min_gev2.ln(), q2_max.ln()),
+            grid: AdaptiveGrid::uniform(config.importance_sampling.bins),
+            metadata: None,
+        })
+    }
+
+    /// Refine the grid and return the largest weight seen on the final grid.
+    fn adapt(&mut self, rng: &mut StdRng) -> Result<f64, DisGeneratorError> {
+        let settings = self.config.importance_sampling;
+        let mut last_failure = None;
+        for _ in 0..settings.iterations {
+            let mut accumulated = [vec![0.0; settings.bins], vec![0.0; settings.bins]];
+            for _ in 0..settings.points_per_iteration {
+                let sample = self.grid.sample(rng);
+                match self.evaluate(sample.point, sample.jacobian, rng) {
+                    Trial::Inside(point) => {
+                        for (dimension, &bin) in sample.bins.iter().enumerate() {
+                            accumulated[dimension][bin] += point.weight.powi(2);
+                        }
+                    }
+                    Trial::OutsideCuts => {}
+                    Trial::Failed(reason) => last_failure = Some(reason.to_owned()),
+                }
+            }
+            self.grid.refine(&accumulated, settings.damping);
+        }
+
+        let mut max_weight = 0.0_f64;
+        for _ in 0..settings.points_per_iteration {
+            match self.trial(rng) {
+                Trial::Inside(point) => max_weight = max_weight.max(point.weight),
+                Trial::OutsideCuts => {}
+                Trial::Failed(reason) => last_failure = Some(reason.to_owned()),
+            }
+        }
+        if max_weight > 0.0 {
+            Ok(max_weight)
+        } else {
+            Err(DisGeneratorError::EmptyPhaseSpace {
+                trials: settings.points_per_iteration * (settings.iterations + 1),
+                last_failure,
+            })
+        }
+    }
+
+    fn trial(&mut self, rng: &mut StdRng) -> Trial {
+        let sample = self.grid.sample(rng);
+        self.evaluate(sample.point, sample.jacobian, rng)
+    }
+
+    fn evaluate(&mut self, unit: [f64; 2], grid_jacobian: f64, rng: &mut StdRng) -> Trial {
+        let ln_x = self.ln_x.0 + unit[0] * (self.ln_x.1 - self.ln_x.0);
+        let ln_q2 = self.ln_q2.0 + unit[1] * (self.ln_q2.1 - self.ln_q2.0);
+        let (x, q2) = (ln_x.exp(), ln_q2.exp());
+        let Ok(y) = exact_inelasticity(x, q2, self.s) else {
+            return Trial::OutsideCuts;
+        };
+        let phi = rng.gen_range(0.0..2.0 * PI);
+        let Some(scattered_electron) = scattered_electron_from_invariants(&self.beams, q2, y, phi)
+        else {
+            return Trial::OutsideCuts;
+        };
+        let Ok(kinematics) =
+            compute_dis_kinematics(self.beams.proton, self.beams.electron, scattered_electron)
+        else {
+            return Trial::OutsideCuts;
+        };
+        if !matches!(self.cuts.accepts(&kinematics), Ok(true)) {
+            return Trial::OutsideCuts;
+        }
+
+        let request = StructureFunctionRequest::electromagnetic_nc(
+            x,
+            q2,
+            self.config.order,
+            self.config.pdf_set.clone(),
+            self.config.pdf_member,
+        );
+        let Ok(result) = self.provider.evaluate(&request) else {
+            return Trial::Failed("structure_function_provider");
+        };
+        let Ok(cross_section) =
+            lo_differential_cross_section(x, q2, self.s, result.f2, &self.coupling)
+        else {
+            return Trial::Failed("cross_section");
+        };
+        if self.metadata.is_none() {
+            self.metadata = Some(result.metadata);
+        }
+
+        // dx dQ² = x Q² d(ln x) d(ln Q²), and each logarithm spans its range.
+        let mapping_jacobian = x * q2 * (self.ln_x.1 - self.ln_x.0) * (self.ln_q2.1 - self.ln_q2.0);
+        Trial::Inside(PhaseSpacePoint {
+            x,
+            q2,
+            y,
+            kinematics,
+            scattered_electron,
+            weight: cross_section.d2sigma_dx_dq2_pb_per_gev2 * mapping_jacobian * grid_jacobian,
+        })
+    }
+}
+
+/// Solve `k·k' = m_e² + Q²/2` and `P·k' = (1 - y) P·k` for the scattered
+/// electron, with transverse momentum from the mass shell and azimuth `phi`.
+fn scattered_electron_from_invariants(
+    beams: &ColliderBeams,
+    q2: f64,
+    y: f64,
+    phi: f64,
+) -> Option<FourVector> {
+    let (k, p) = (beams.electron, beams.proton);
+    let k_dot_k_prime = ELECTRON_MASS_GEV.powi(2) + 0.5 * q2;
+    let p_dot_k_prime = (1.0 - y) * p.dot(k);
+    // Both beams lie on the z axis, so only E' and p_z' enter the dot products.
+    let determinant = k.pz * p.e - k.e * p.pz;
+    if determinant == 0.0 {
+        return None;
+    }
+    let energy = (k.pz * p_dot_k_prime - p.pz * k_dot_k_prime) / determinant;
+    let pz = (k.e * p_dot_k_prime - p.e * k_dot_k_prime) / determinant;
+    let pt_squared = energy.powi(2) - pz.powi(2) - ELECTRON_MASS_GEV.powi(2);
+    if !(pt_squared >= 0.0 && energy > 0.0) {
+        return None;
+    }
+    let pt = pt_squared.sqrt();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+    FourVector::try_new(energy, pt * cos_phi, pt * sin_phi, pz).ok()
+}
+
+/// Draw the struck flavour with probability `e_q² x f_q(x, Q²)` and return
+/// it with its `x f_q(x, Q²)`.
+fn sample_flavour(pdf: &dyn PdfProvider, x: f64, q2: f64, rng: &mut StdRng) -> Option<(i32, f64)> {
+    let densities = pdf.parton_densities(x, q2).ok()?;
+    let candidates = [1, -1, 2, -2, 3, -3, 4, -4, 5, -5].map(|flavour: i32| {
+        let charge_squared = if flavour.abs() % 2 == 0 {
+            UP_TYPE_CHARGE_SQUARED
+        } else {
+            DOWN_TYPE_CHARGE_SQUARED
+        };
+        let xf = densities.xf(flavour).unwrap_or(0.0);
+        (flavour, xf, charge_squared * xf)
+    });
+    let total: f64 = candidates
+        .iter()
+        .map(|&(_, _, weight)| weight.max(0.0))
+        .sum();
+    if !(total.is_finite() && total > 0.0) {
+        return None;
+    }
+    let mut target = rng.gen::<f64>() * total;
+    for &(flavour, xf, weight) in &candidates {
+        target -= weight.max(0.0);
+        if target < 0.0 {
+            return Some((flavour, xf));
+        }
+    }
+    candidates
+        .iter()
+        .rev()
+        .find(|&&(_, _, weight)| weight > 0.0)
+        .map(|&(flavour, xf, _)| (flavour, xf))
+}
+
+/// Diquark left behind by the struck quark. Only valence up and down quarks
+/// leave the correct flavour; sea quarks are given the `ud` remnant.
+fn remnant_pdg_id(flavour: i32) -> i32 {
+    if flavour == 1 {
+        UU_DIQUARK_PDG_ID
+    } else {
+        UD_DIQUARK_PDG_ID
+    }
+}
+
+fn build_event(
+    beams: &ColliderBeams,
+    point: &PhaseSpacePoint,
+    flavour: i32,
+    event_number: usize,
+    weight: f64,
+    cross_section: String,
+    pdf_info: Option<String>,
+) -> GenEvent {
+    let photon = beams.electron - point.scattered_electron;
+    let incoming_quark = scale(beams.proton, point.x);
+    let remnant = scale(beams.proton, 1.0 - point.x);
+    let outgoing_quark = incoming_quark + photon;
+    let vertex = |id: i32, incoming: Vec<i32>, outgoing: Vec<i32>| GenVertex {
+        id,
+        status: 0,
+        position: None,
+        incoming,
+        outgoing,
+    };
+    let quark_mass = point.x * PROTON_MASS_GEV;
+    // (PDG id, momentum, generated mass, status, production vertex, end vertex)
+    // for particles 1..=7. Spacelike masses are stored negative, as PYTHIA does.
+    let particles = [
+        (
+            ELECTRON_PDG_ID,
+            beams.electron,
+            ELECTRON_MASS_GEV,
+            BEAM_STATUS,
+            None,
+            Some(-1),
+        ),
+        (
+            PROTON_PDG_ID,
+            beams.proton,
+            PROTON_MASS_GEV,
+            BEAM_STATUS,
+            None,
+            Some(-2),
+        ),
+        (
+            ELECTRON_PDG_ID,
+            point.scattered_electron,
+            ELECTRON_MASS_GEV,
+            FINAL_STATE_STATUS,
+            Some(-1),
+            None,
+        ),
+        (
+            PHOTON_PDG_ID,
+            photon,
+            -point.q2.sqrt(),
+            INTERMEDIATE_STATUS,
+            Some(-1),
+            Some(-3),
+        ),
+        (
+            flavour,
+            incoming_quark,
+            quark_mass,
+            INCOMING_PARTON_STATUS,
+            Some(-2),
+            Some(-3),
+        ),
+        (
+            remnant_pdg_id(flavour),
+            remnant,
+            PROTON_MASS_GEV - quark_mass,
+            FINAL_STATE_STATUS,
+            Some(-2),
+            None,
+        ),
+        (
+            flavour,
+            outgoing_quark,
+            quark_mass,
+            FINAL_STATE_STATUS,
+            Some(-3),
+            None,
+        ),
+    ];
+
+    GenEvent {
+        event_number: event_number as i64,
+        weights: vec![weight],
+        particles: particles
+            .into_iter()
+            .zip(1..)
+            .map(
+                |((pdg_id, momentum, mass, status, production_vertex, end_vertex), id)| {
+                    GenParticle {
+                        id,
+                        pdg_id,
+                        momentum,
+                        mass,
+                        status,
+                        production_vertex,
+                        end_vertex,
+                    }
+                },
+            )
+            .collect(),
+        vertices: vec![
+            vertex(-1, vec![1], vec![3, 4]),
+            vertex(-2, vec![2], vec![5, 6]),
+            vertex(-3, vec![4, 5], vec![7]),
+        ],
+        attributes: std::iter::once(("GenCrossSection", cross_section))
+            .chain(pdf_info.map(|value| (GEN_PDF_INFO_ATTRIBUTE, value)))
+            .map(|(name, value)| Attribute {
+                target: 0,
+                name: name.to_owned(),
+                value,
+            })
+            .collect(),
+        ..GenEvent::default()
+    }
+}
+
+fn scale(vector: FourVector, factor: f64) -> FourVector {
+    FourVector::new(
+        factor * vector.e,
+        factor * vector.px,
+        factor * vector.py,
+        factor * vector.pz,
+    )
+}
+
+/// Largest three-momentum component and energy imbalance between the beams
+/// and the final state.
+fn conservation_mismatch(event: &GenEvent) -> (f64, f64) {
+    let sum = |status: i32| {
+        event
+            .particles
+            .iter()
+            .filter(|particle| particle.status == status)
+            .fold(FourVector::new(0.0, 0.0, 0.0, 0.0), |total, particle| {
+                total + particle.momentum
+            })
+    };
+    let difference = sum(BEAM_STATUS) - sum(FINAL_STATE_STATUS);
+    let momentum = difference
+        .px
+        .abs()
+        .max(difference.py.abs())
+        .max(difference.pz.abs());
+    (momentum, difference.e.abs())
+}
+
+fn run_info() -> GenRunInfo {
+    GenRunInfo {
+        weight_names: vec!["Weight".to_owned()],
+        tools: vec![ToolInfo {
+            name: NATIVE_GENERATOR_NAME.to_owned(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            description: "leading-order neutral-current DIS".to_owned(),
+        }],
+        attributes: Vec::new(),
+    }
+}
+
+/// Running mean and standard error of the trial weights.
+#[derive(Debug, Default)]
+struct WeightSum {
+    count: usize,
+    sum: f64,
+    sum_of_squares: f64,
+}
+
+impl WeightSum {
+    fn add(&mut self, weight: f64) {
+        self.count += 1;
+        self.sum += weight;
+        self.sum_of_squares += weight * weight;
+    }
+
+    fn estimate(&self) -> (f64, f64) {
+        if self.count == 0 {
+            return (0.0, 0.0);
+        }
+        let count = self.count as f64;
+        let mean = self.sum / count;
+        let variance = (self.sum_of_squares / count - mean * mean).max(0.0);
+        (mean, (variance / count).sqrt())
+    }
+}
+
+struct GridSample {
+    point: [f64; 2],
+    bins: [usize; 2],
+    jacobian: f64,
+}
+
+/// Separable piecewise-uniform sampling density on the unit square.
+///
+/// Each dimension has `bins` intervals of equal probability whose widths are
+/// adapted so that every interval carries a similar share of `∫ f²`.
+struct AdaptiveGrid {
+    edges: [Vec<f64>; 2],
+}
+
+impl AdaptiveGrid {
+    fn uniform(bins: usize) -> Self {
+        let edges: Vec<f64> = (0..=bins).map(|index| index as f64 / bins as f64).collect();
+        Self {
+            edges: [edges.clone(), edges],
+        }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> GridSample {
+        let mut point = [0.0; 2];
+        let mut bins = [0; 2];
+        let mut jacobian = 1.0;
+        for (dimension, edges) in self.edges.iter().enumerate() {
+            let count = edges.len() - 1;
+            let bin = rng.gen_range(0..count);
+            let width = edges[bin + 1] - edges[bin];
+            point[dimension] = edges[bin] + rng.gen::<f64>() * width;
+            bins[dimension] = bin;
+            jacobian *= count as f64 * width;
+        }
+        GridSample {
+            point,
+            bins,
+            jacobian,
+        }
+    }
+
+    fn refine(&mut self, accumulated: &[Vec<f64>; 2], damping: f64) {
+        for (edges, values) in self.edges.iter_mut().zip(accumulated) {
+            let count = values.len();
+            // Smooth neighbouring bins so isolated fluctuations do not collapse the grid.
+            let smoothed: Vec<f64> = (0..count)
+                .map(|bin| {
+                    let low = bin.saturating_sub(1);
+                    let high = (bin + 1).min(count - 1);
+                    values[low..=high].iter().sum::<f64>() / (high - low + 1) as f64
+                })
+                .collect();
+            let total: f64 = smoothed.iter().sum();
+            if !(total.is_finite() && total > 0.0) {
+                continue;
+            }
+            let importance: Vec<f64> = smoothed
+                .iter()
+                .map(|&value| {
+                    let fraction = value / total;
+                    if fraction <= 0.0 || fraction >= 1.0 {
+                        fraction
+                    } else {
+                        ((fraction - 1.0) / fraction.ln()).powf(damping)
+                    }
+                })
+                .collect();
+            let importance_total: f64 = importance.iter().sum();
+            if !(importance_total.is_finite() && importance_total > 0.0) {
+                continue;
+            }
+
+            let per_bin = importance_total / count as f64;
+            let mut new_edges = Vec::with_capacity(count + 1);
+            new_edges.push(0.0);
+            let mut accumulated_importance = 0.0;
+            let mut bin = 0;
+            for edge in 1..count {
+                let target = edge as f64 * per_bin;
+                while bin < count - 1 && accumulated_importance + importance[bin] < target {
+                    accumulated_importance += importance[bin];
+                    bin += 1;
+                }
+                let fraction = if importance[bin] > 0.0 {
+                    ((target - accumulated_importance) / importance[bin]).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                new_edges.push(edges[bin] + fraction * (edges[bin + 1] - edges[bin]));
+            }
+            new_edges.push(1.0);
+            *edges = new_edges;
+        }
+    }
+}
+
+/// One row of `inclusive_observables.csv`, in the PYTHIA backend's columns.
+///
+/// True values come from the sampled `(x, Q²)`; reconstructed values are
+/// recomputed from the written electron four-vectors.
+#[derive(Debug, Serialize)]
+struct InclusiveObservables {
+    event_number: i64,
+    event_weight: f64,
+    #[serde(rename = "Q2")]
+    q2: f64,
+    x: f64,
+    y: f64,
+    #[serde(rename = "W2")]
+    w2: f64,
+    #[serde(rename = "scattered_electron_E")]
+    scattered_electron_e: f64,
+    scattered_electron_px: f64,
+    scattered_electron_py: f64,
+    scattered_electron_pz: f64,
+    number_of_final_state_particles: usize,
+    number_of_charged_final_state_particles: usize,
+    #[serde(rename = "Q2_reco")]
+    q2_reco: f64,
+    x_reco: f64,
+    y_reco: f64,
+    #[serde(rename = "W2_reco")]
+    w2_reco: f64,
+    #[serde(rename = "Q2_mismatch")]
+    q2_mismatch: f64,
+    x_mismatch: f64,
+    y_mismatch: f64,
+    #[serde(rename = "W2_mismatch")]
+    w2_mismatch: f64,
+}
+
+impl InclusiveObservables {
+    fn new(event: &GenEvent, point: &PhaseSpacePoint) -> Self {
+        let w2 = PROTON_MASS_GEV.powi(2) + point.q2 * (1.0 - point.x) / point.x;
+        let electron = point.scattered_electron;
+        let reco = &point.kinematics;
+        let final_state: Vec<&GenParticle> = event.final_state().collect();
+        Self {
+            event_number: event.event_number,
+            event_weight: event.weight(),
+            q2: point.q2,
+            x: point.x,
+            y: point.y,
+            w2,
+            scattered_electron_e: electron.e,
+            scattered_electron_px: electron.px,
+            scattered_electron_py: electron.py,
+            scattered_electron_pz: electron.pz,
+            number_of_final_state_particles: final_state.len(),
+            number_of_charged_final_state_particles: final_state
+                .iter()
+                .filter(|particle| is_charged(particle.pdg_id))
+                .count(),
+            q2_reco: reco.q2,
+            x_reco: reco.x,
+            y_reco: reco.y,
+            w2_reco: reco.w2,
+            q2_mismatch: (reco.q2 - point.q2).abs(),
+            x_mismatch: (reco.x - point.x).abs(),
+            y_mismatch: (reco.y - point.y).abs(),
+            w2_mismatch: (reco.w2 - w2).abs(),
+        }
+    }
+}
+
+/// Charged leptons, quarks and diquarks; the only stable species produced here.
+fn is_charged(pdg_id: i32) -> bool {
+    let code = pdg_id.abs();
+    matches!(code, 1..=6 | 11 | 13 | 15) || (1000..10000).contains(&code)
+}
+
+/// Run provenance, written as `metadata.json` with the PYTHIA backend keys
+/// where they apply.
+#[derive(Debug, Serialize)]
+struct RunMetadata<'a> {
+    generator: &'static str,
+    generator_version: &'static str,
+    structure_function_backend: Option<String>,
+    structure_function_scheme: Option<&'a str>,
+    lhapdf_version: Option<&'a str>,
+    apfelxx_version: Option<&'a str>,
+    pdf_set: &'a str,
+    pdf_member: i32,
+    order: PerturbativeOrder,
+    electron_energy_gev: f64,
+    proton_energy_gev: f64,
+    cuts: RunCuts,
+    requested_event_count: usize,
+    accepted_event_count: usize,
+    failed_event_count: usize,
+    random_seed: u64,
+    weighting: EventWeighting,
+    importance_sampling: ImportanceSamplingConfig,
+    parton_shower_state: bool,
+    hadronization_state: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct RunCuts {
+    q2_min_gev2: f64,
+    q2_max_gev2: f64,
+    x_min: f64,
+    x_max: f64,
+    y_min: f64,
+    y_max: f64,
+    w2_min_gev2: f64,
+}
+
+impl<'a> RunMetadata<'a> {
+    fn new(
+        config: &'a DisGeneratorConfig,
+        summary: &GenerationSummary,
+        structure_functions: Option<&'a StructureFunctionMetadata>,
+    ) -> Self {
+        Self {
+            generator: NATIVE_GENERATOR_NAME,
+            generator_version: env!("CARGO_PKG_VERSION"),
+            structure_function_backend: structure_functions
+                .map(|metadata| metadata.backend.to_string()),
+            structure_function_scheme: structure_functions.map(|metadata| metadata.scheme.as_str()),
+            lhapdf_version: structure_functions
+                .and_then(|metadata| metadata.lhapdf_version.as_deref()),
+            apfelxx_version: structure_functions
+                .and_then(|metadata| metadata.apfelxx_version.as_deref()),
+            pdf_set: &config.pdf_set,
+            pdf_member: config.pdf_member,
+            order: config.order,
+            electron_energy_gev: config.electron_energy_gev,
+            proton_energy_gev: config.proton_energy_gev,
+            cuts: RunCuts {
+                q2_min_gev2: config.q2_min_gev2,
+                q2_max_gev2: config.q2_max_gev2,
+                x_min: config.x_min,
+                x_max: config.x_max,
+                y_min: config.y_min,
+                y_max: config.y_max,
+                w2_min_gev2: config.w2_min_gev2,
+            },
+            requested_event_count: summary.requested_events,
+            accepted_event_count: summary.accepted_events,
+            failed_event_count: summary.failed_events,
+            random_seed: config.random_seed,
+            weighting: config.weighting,
+            importance_sampling: config.importance_sampling,
+            parton_shower_state: false,
+            hadronization_state: false,
+        }
+    }
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), DisGeneratorError> {
+    let json =
+        serde_json::to_string_pretty(value).map_err(|source| serialization_error(path, source))?;
+    fs::write(path, json).map_err(|source| io_error(path, source))
+}
+
+fn io_error(path: &Path, source: io::Error) -> DisGeneratorError {
+    DisGeneratorError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+fn serialization_error(path: &Path, source: impl fmt::Display) -> DisGeneratorError {
+    DisGeneratorError::Serialization {
+        path: path.to_path_buf(),
+        message: source.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hepmc3::parse_ascii;
+    use crate::physics::pdf::{PartonDensities, PdfError};
+    use crate::physics::structure_function_provider::LoPdfStructureFunctionProvider;
+
+    /// Valence-like densities that make `F₂` fall steeply with `x`.
+    struct ShapePdf;
+
+    impl PdfProvider for ShapePdf {
+        fn parton_densities(&self, x: f64, q2: f64) -> Result<PartonDensities, PdfError> {
+            let valence = x.sqrt() * (1.0 - x).powi(3);
+            let sea = 0.1 * x.powf(-0.2) * (1.0 - x).powi(7);
+            Ok(PartonDensities {
+                x,
+                q2,
+                gluon: 0.0,
+                up: 2.0 * valence + sea,
+                anti_up: sea,
+                down: valence + sea,
+                anti_down: sea,
+                strange: 0.5 * sea,
+                anti_strange: 0.5 * sea,
+                charm: 0.0,
+                anti_charm: 0.0,
+                bottom: 0.0,
+                anti_bottom: 0.0,
+            })
+        }
+    }
+
+    fn provider() -> LoPdfStructureFunctionProvider<ShapePdf> {
+        LoPdfStructureFunctionProvider::new(ShapePdf, "ShapePdf", 0, 0, 1).unwrap()
+    }
+
+    fn config(weighting: EventWeighting, seed: u64) -> DisGeneratorConfig {
+        DisGeneratorConfig {
+            number_of_events: 200,
+            random_seed: seed,
+            pdf_set: "ShapePdf".to_owned(),
+            weighting,
+            importance_sampling: ImportanceSamplingConfig {
+                points_per_iteration: 2000,
+                iterations: 4,
+                ..ImportanceSamplingConfig::default()
+            },
+            ..DisGeneratorConfig::default()
+        }
+    }
+
+    fn run_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("quark_sim_generator_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn generate(config: &DisGeneratorConfig, dir: &Path) -> GenerationSummary {
+        let provider = provider();
+        let pdf = provider.pdf_provider();
+        generate_dis_events(&provider, Some(pdf), config, dir).unwrap()
+    }
+
+    #[test]
+    fn scattered_electron_reproduces_the_sampled_invariants() {
+        let beams = collider_beams(27.5, 920.0).unwrap();
+        let s = (beams.electron + beams.proton).mass_squared();
+        for (x, q2, phi) in [(1e-3, 20.0, 0.3), (0.05, 400.0, 2.0), (0.4, 5000.0, 5.5)] {
+            let y = exact_inelasticity(x, q2, s).unwrap();
+            let electron = scattered_electron_from_invariants(&beams, q2, y, phi).unwrap();
+            let kinematics =
+                compute_dis_kinematics(beams.proton, beams.electron, electron).unwrap();
+
+            assert!(
+                (kinematics.q2 / q2 - 1.0).abs() < 1e-9,
+                "Q² {}",
+                kinematics.q2
+            );
+            assert!((kinematics.x / x - 1.0).abs() < 1e-9, "x {}", kinematics.x);
+            assert!((electron.py.atan2(electron.px).rem_euclid(2.0 * PI) - phi).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn unweighted_run_writes_events_inside_the_cuts() {
+        let dir = run_dir("unweighted");
+        let config = config(EventWeighting::Unweighted, 7);
+        let summary = generate(&config, &dir);
+
+        assert!(summary.success);
+        assert_eq!(summary.accepted_events, 200);
+        assert_eq!(summary.sum_of_weights, 200.0);
+        assert!(summary.cross_section_pb > 0.0);
+        assert!(summary.cross_section_error_pb < 0.1 * summary.cross_section_pb);
+        for file in ["config.json", "metadata.json", "summary.json"] {
+            assert!(dir.join(file).is_file(), "{file}");
+        }
+
+        let file = parse_ascii(&fs::read_to_string(dir.join("events.hepmc3")).unwrap()).unwrap();
+        assert_eq!(file.events.len(), 200);
+        let cuts = config.cuts();
+        for event in &file.events {
+            assert_eq!(event.weight(), 1.0);
+            let electron = event.particle(3).unwrap().momentum;
+            let kinematics = compute_dis_kinematics(
+                event.particle(2).unwrap().momentum,
+                event.particle(1).unwrap().momentum,
+                electron,
+            )
+            .unwrap();
+            assert!(cuts.accepts(&kinematics).unwrap());
+            let (momentum, energy) = conservation_mismatch(event);
+            assert!(momentum <= MOMENTUM_CONSERVATION_TOLERANCE_GEV);
+            assert!(energy <= MOMENTUM_CONSERVATION_TOLERANCE_GEV);
+            assert_eq!(event.final_state().count(), 3);
+            let hard = HardProcess::from_event(event).unwrap();
+            assert_eq!(hard.flavour, event.particle(5).unwrap().pdg_id);
+            assert!((hard.x / kinematics.x - 1.0).abs() < 1e-6);
+        }
+
+        let rows = csv::Reader::from_path(dir.join("inclusive_observables.csv"))
+            .unwrap()
+            .records()
+            .count();
+        assert_eq!(rows, 200);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn the_same_seed_reproduces_the_run() {
+        let first_dir = run_dir("seed_a");
+        let second_dir = run_dir("seed_b");
+        let third_dir = run_dir("seed_c");
+        let first = generate(&config(EventWeighting::Unweighted, 11), &first_dir);
+        let second = generate(&config(EventWeighting::Unweighted, 11), &second_dir);
+        generate(&config(EventWeighting::Unweighted, 12), &third_dir);
+
+        assert_eq!(first, second);
+        let events = |dir: &Path| fs::read_to_string(dir.join("events.hepmc3")).unwrap();
+        assert_eq!(events(&first_dir), events(&second_dir));
+        assert_ne!(events(&first_dir), events(&third_dir));
+        for dir in [first_dir, second_dir, third_dir] {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+
+    #[test]
+    fn weighted_cross_section_matches_direct_integration() {
+        let dir = run_dir("weighted");
+        let mut config = config(EventWeighting::Weighted, 3);
+        config.number_of_events = 20_000;
+        let summary = generate(&config, &dir);
+
+        let provider = provider();
+        let beams = collider_beams(config.electron_energy_gev, config.proton_energy_gev).unwrap();
+        let s = (beams.electron + beams.proton).mass_squared();
+        let (ln_x_min, ln_x_max) = (config.x_min.ln(), config.x_max.ln());
+        let (ln_q2_min, ln_q2_max) = (config.q2_min_gev2.ln(), config.q2_max_gev2.ln());
+        let steps = 400;
+        let (dx, dq) = (
+            (ln_x_max - ln_x_min) / steps as f64,
+            (ln_q2_max - ln_q2_min) / steps as f64,
+        );
+        let mut integral = 0.0;
+        for i in 0..steps {
+            for j in 0..steps {
+                let x = (ln_x_min + (i as f64 + 0.5) * dx).exp();
+                let q2 = (ln_q2_min + (j as f64 + 0.5) * dq).exp();
+                let Ok(y) = exact_inelasticity(x, q2, s) else {
+                    continue;
+                };
+                if y < config.y_min || y > config.y_max {
+                    continue;
+                }
+                let request = StructureFunctionRequest::electromagnetic_nc(
+                    x,
+                    q2,
+                    config.order,
+                    "ShapePdf",
+                    0,
+                );
+                let f2 = provider.evaluate(&request).unwrap().f2;
+                let cross_section =
+                    lo_differential_cross_section(x, q2, s, f2, &FixedAlpha::default()).unwrap();
+                integral += cross_section.d2sigma_dx_dq2_pb_per_gev2 * x * q2 * dx * dq;
+            }
+        }
+
+        let difference = (summary.cross_section_pb - integral).abs();
+        assert!(
+            difference < 0.02 * integral + 3.0 * summary.cross_section_error_pb,
+            "generated {} ± {} pb, integrated {integral} pb",
+            summary.cross_section_pb,
+            summary.cross_section_error_pb
+        );
+        assert!(
+            (summary.sum_of_weights / summary.attempted_events as f64 - summary.cross_section_pb)
+                .abs()
+                < 1e-9 * integral
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+}
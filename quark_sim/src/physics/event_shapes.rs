@@ -0,0 +1,327 @@
+//! DIS event shapes in the current hemisphere of the Breit frame.
+//!
+//! The observables follow the H1 and ZEUS definitions. With `p⃗_h` the
+//! Breit-frame momenta of the hadrons in the current hemisphere (`p_z < 0`),
+//! `n⃗_γ = -ẑ` the boson axis and `Σ|p⃗| = Σ_h |p⃗_h|`:
+//!
+//! - `τ_Q = 1 - T_Q`, `T_Q = 2 Σ_h |p⃗_h · n⃗_γ| / Q` (thrust along the boson
+//!   axis, normalised to `Q/2`),
+//! - `τ_T = 1 - T_T`, `T_T = max_n⃗ Σ_h |p⃗_h · n⃗| / Σ|p⃗|` (thrust along the
+//!   thrust axis),
+//! - `B = Σ_h |p⃗_h × n⃗_γ| / (2 Σ|p⃗|)` (broadening with respect to the
+//!   boson axis),
+//! - `ρ = M² / (2 Σ|p⃗|)²` with `M² = (Σ_h p_h)²` (jet mass),
+//! - `C = 3/2 Σ_h,k |p⃗_h| |p⃗_k| sin² θ_hk / (Σ|p⃗|)²` (C-parameter).
+//!
+//! All vanish for the quark-parton-model configuration of a single massless
+//! quark along `-z` with energy `Q/2`. Events whose current hemisphere holds
+//! less than `Q/10` of energy are rejected, as in the measurements, because
+//! the observables are not infrared safe there.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::breit_frame::BreitFrame;
+use super::four_vector::FourVector;
+
+/// Minimum current-hemisphere energy as a fraction of `Q`.
+pub const CURRENT_HEMISPHERE_ENERGY_FRACTION: f64 = 0.1;
+
+/// Event shapes of one event's Breit current hemisphere.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EventShapes {
+    /// Momentum transfer `Q` in GeV.
+    pub q: f64,
+    /// Summed energy of the current-hemisphere hadrons in GeV.
+    pub current_hemisphere_energy: f64,
+    /// Number of current-hemisphere hadrons.
+    pub multiplicity: usize,
+    pub tau_q: f64,
+    pub tau_t: f64,
+    pub broadening: f64,
+    pub jet_mass: f64,
+    pub c_parameter: f64,
+}
+
+/// Reasons event shapes are not defined for an event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventShapeError {
+    InvalidQ { q: f64 },
+    EmptyCurrentHemisphere,
+    SoftCurrentHemisphere { energy: f64, minimum: f64 },
+}
+
+impl fmt::Display for EventShapeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidQ { q } => write!(formatter, "Q must be finite and positive, got {q}"),
+            Self::EmptyCurrentHemisphere => {
+                write!(formatter, "the Breit current hemisphere has no hadrons")
+            }
+            Self::SoftCurrentHemisphere { energy, minimum } => write!(
+                formatter,
+                "the Breit current hemisphere holds {energy:.4} GeV, below the {minimum:.4} GeV needed for event shapes"
+            ),
+        }
+    }
+}
+
+impl Error for EventShapeError {}
+
+impl EventShapes {
+    /// Event shapes of Breit-frame current-hemisphere momenta.
+    pub fn from_current_hemisphere(
+        particles: &[FourVector],
+        q: f64,
+    ) -> Result<Self, EventShapeError> {
+        if !(q.is_finite() && q > 0.0) {
+            return Err(EventShapeError::InvalidQ { q });
+        }
+        if particles.is_empty() {
+            return Err(EventShapeError::EmptyCurrentHemisphere);
+        }
+        let energy = particles.iter().map(|particle| particle.e).sum::<f64>();
+        let minimum = CURRENT_HEMISPHERE_ENERGY_FRACTION * q;
+        if energy <= minimum {
+            return Err(EventShapeError::SoftCurrentHemisphere { energy, minimum });
+        }
+
+        let momenta = particles
+            .iter()
+            .map(|particle| [particle.px, particle.py, particle.pz])
+            .collect::<Vec<_>>();
+        let momentum_sum = particles
+            .iter()
+            .map(|particle| particle.spatial_momentum())
+            .sum::<f64>();
+        let longitudinal = particles
+            .iter()
+            .map(|particle| particle.pz.abs())
+            .sum::<f64>();
+        let transverse = particles
+            .iter()
+            .map(|particle| particle.transverse_momentum())
+            .sum::<f64>();
+        let total = particles
+            .iter()
+            .fold(FourVector::new(0.0, 0.0, 0.0, 0.0), |sum, &particle| {
+                sum + particle
+            });
+
+        Ok(Self {
+            q,
+            current_hemisphere_energy: energy,
+            multiplicity: particles.len(),
+            tau_q: 1.0 - 2.0 * longitudinal / q,
+            tau_t: 1.0 - thrust(&momenta) / momentum_sum,
+            broadening: transverse / (2.0 * momentum_sum),
+            jet_mass: total.mass_squared() / (4.0 * momentum_sum * momentum_sum),
+            c_parameter: c_parameter(&momenta, momentum_sum),
+        })
+    }
+}
+
+/// Boost laboratory hadron momenta into the Breit frame, keep the current
+/// hemisphere and compute its event shapes.
+pub fn current_hemisphere_event_shapes(
+    hadrons: &[FourVector],
+    frame: &BreitFrame,
+) -> Result<EventShapes, EventShapeError> {
+    let current = hadrons
+        .iter()
+        .map(|&momentum| frame.to_breit(momentum))
+        .filter(|&momentum| BreitFrame::is_current_hemisphere(momentum))
+        .collect::<Vec<_>>();
+    EventShapes::from_current_hemisphere(&current, frame.q())
+}
+
+/// `max_n⃗ Σ_h |p⃗_h · n⃗|`, found exactly.
+///
+/// The optimal axis splits the momenta by a plane through the origin, and
+/// that plane can be turned until it contains two of them. Every pair
+/// therefore defines one candidate partition, with the sides of the pair
+/// itself tried both ways. Single-momentum axes cover collinear inputs.
+/// The cost is `O(N³)`, ample for one hemisphere.
+fn thrust(momenta: &[[f64; 3]]) -> f64 {
+    let signed_sum = |normal: [f64; 3], skip: [usize; 2]| {
+        let mut sum = [0.0; 3];
+        for (index, momentum) in momenta.iter().enumerate() {
+            if skip.contains(&index) {
+                continue;
+            }
+            let sign = if dot(*momentum, normal) >= 0.0 {
+                1.0
+            } else {
+                -1.0
+            };
+            for axis in 0..3 {
+                sum[axis] += sign * momentum[axis];
+            }
+        }
+        sum
+    };
+
+    let mut best = momenta
+        .iter()
+        .map(|&axis| norm(signed_sum(axis, [usize::MAX; 2])))
+        .fold(0.0, f64::max);
+    for (i, &first) in momenta.iter().enumerate() {
+        for (j, &second) in momenta.iter().enumerate().skip(i + 1) {
+            let normal = cross(first, second);
+            if norm(normal) == 0.0 {
+                continue;
+            }
+            let base = signed_sum(normal, [i, j]);
+            for (first_sign, second_sign) in [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)] {
+                let candidate = [0, 1, 2]
+                    .map(|axis| base[axis] + first_sign * first[axis] + second_sign * second[axis]);
+                best = best.max(norm(candidate));
+            }
+        }
+    }
+    best
+}
+
+/// `C = 3/2 [(Σ|p⃗|)² - Σ_ab Θ_ab²] / (Σ|p⃗|)²` with the linearised momentum
+/// tensor `Θ_ab = Σ_h p_h,a p_h,b / |p⃗_h|`, equal to the pairwise definition.
+fn c_parameter(momenta: &[[f64; 3]], momentum_sum: f64) -> f64 {
+    let mut tensor = [[0.0; 3]; 3];
+    for momentum in momenta {
+        let length = norm(*momentum);
+        if length == 0.0 {
+            continue;
+        }
+        for a in 0..3 {
+            for b in 0..3 {
+                tensor[a][b] += momentum[a] * momentum[b] / length;
+            }
+        }
+    }
+    let squared = tensor
+        .iter()
+        .flatten()
+        .map(|value| value * value)
+        .sum::<f64>();
+    1.5 * (momentum_sum * momentum_sum - squared) / (momentum_sum * momentum_sum)
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn norm(vector: [f64; 3]) -> f64 {
+    dot(vector, vector).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {expected:.12e}, got {actual:.12e}"
+        );
+    }
+
+    fn massless(px: f64, py: f64, pz: f64) -> FourVector {
+        FourVector::new(norm([px, py, pz]), px, py, pz)
+    }
+
+    #[test]
+    fn quark_parton_model_and_symmetric_configurations() {
+        let q = 20.0;
+        let born = EventShapes::from_current_hemisphere(&[massless(0.0, 0.0, -10.0)], q).unwrap();
+        assert_close(born.tau_q, 0.0, 1e-15);
+        assert_close(born.tau_t, 0.0, 1e-15);
+        assert_close(born.broadening, 0.0, 1e-15);
+        assert_close(born.jet_mass, 0.0, 1e-15);
+        assert_close(born.c_parameter, 0.0, 1e-15);
+
+        // Two equal massless hadrons at 90° to each other, 45° off the boson
+        // axis: T_T = 1/√2, B = 1/(2√2), M² = 2E²(1 - cos 90°), C = 3/4.
+        let energy = 5.0;
+        let component = energy / 2.0f64.sqrt();
+        let pair = [
+            massless(component, 0.0, -component),
+            massless(-component, 0.0, -component),
+        ];
+        let shapes = EventShapes::from_current_hemisphere(&pair, q).unwrap();
+        assert_close(shapes.tau_t, 1.0 - 1.0 / 2.0f64.sqrt(), 1e-12);
+        assert_close(shapes.tau_q, 1.0 - 4.0 * component / q, 1e-12);
+        assert_close(shapes.broadening, 0.5 / 2.0f64.sqrt(), 1e-12);
+        assert_close(
+            shapes.jet_mass,
+            2.0 * energy * energy / (4.0 * 100.0),
+            1e-12,
+        );
+        assert_close(shapes.c_parameter, 0.75, 1e-12);
+        assert_eq!(shapes.multiplicity, 2);
+    }
+
+    #[test]
+    fn exact_thrust_and_tensor_c_parameter_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for particles in [3, 6, 12] {
+            let momenta = (0..particles)
+                .map(|_| [0, 1, 2].map(|_| rng.gen_range(-5.0..5.0)))
+                .collect::<Vec<[f64; 3]>>();
+            let momentum_sum = momenta.iter().map(|&p| norm(p)).sum::<f64>();
+
+            let mut brute_thrust = 0.0f64;
+            for signs in 0..1u32 << particles {
+                let mut sum = [0.0; 3];
+                for (index, momentum) in momenta.iter().enumerate() {
+                    let sign = if signs & (1 << index) == 0 { 1.0 } else { -1.0 };
+                    for axis in 0..3 {
+                        sum[axis] += sign * momentum[axis];
+                    }
+                }
+                brute_thrust = brute_thrust.max(norm(sum));
+            }
+            assert_close(thrust(&momenta), brute_thrust, 1e-12 * momentum_sum);
+
+            let mut pairwise = 0.0;
+            for &first in &momenta {
+                for &second in &momenta {
+                    let (a, b) = (norm(first), norm(second));
+                    let cos = dot(first, second) / (a * b);
+                    pairwise += a * b * (1.0 - cos * cos);
+                }
+            }
+            assert_close(
+                c_parameter(&momenta, momentum_sum),
+                1.5 * pairwise / (momentum_sum * momentum_sum),
+                1e-12,
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_soft_or_empty_current_hemispheres() {
+        assert_eq!(
+            EventShapes::from_current_hemisphere(&[], 10.0),
+            Err(EventShapeError::EmptyCurrentHemisphere)
+        );
+        assert!(matches!(
+            EventShapes::from_current_hemisphere(&[massless(0.5, 0.0, -0.5)], 10.0),
+            Err(EventShapeError::SoftCurrentHemisphere { .. })
+        ));
+        assert_eq!(
+            EventShapes::from_current_hemisphere(&[massless(0.0, 0.0, -1.0)], 0.0),
+            Err(EventShapeError::InvalidQ { q: 0.0 })
+        );
+    }
+}
@@ -96,6 +96,41 @@ impl FourVector {
     pub fn transverse_momentum(self) -> f64 {
         self.px.hypot(self.py)
     }
+
+    /// Transverse energy `E_T = E p_T / |p⃗|`, zero for a vector at rest.
+    #[must_use]
+    pub fn transverse_energy(self) -> f64 {
+        let momentum = self.spatial_momentum();
+        if momentum == 0.0 {
+            0.0
+        } else {
+            self.e * self.transverse_momentum() / momentum
+        }
+    }
+
+    /// Azimuthal angle in `[0, 2π)`.
+    #[must_use]
+    pub fn phi(self) -> f64 {
+        let phi = self.py.atan2(self.px);
+        if phi < 0.0 {
+            phi + std::f64::consts::TAU
+        } else {
+            phi
+        }
+    }
+
+    /// Rapidity `y = ½ ln((E + p_z) / (E - p_z))`, infinite along the beam axis.
+    #[must_use]
+    pub fn rapidity(self) -> f64 {
+        0.5 * ((self.e + self.pz) / (self.e - self.pz)).ln()
+    }
+
+    /// Pseudorapidity `η = -ln tan(θ/2)`, infinite along the beam axis.
+    #[must_use]
+    pub fn pseudorapidity(self) -> f64 {
+        let momentum = self.spatial_momentum();
+        0.5 * ((momentum + self.pz) / (momentum - self.pz)).ln()
+    }
 }
 
 impl Add for FourVector {
@@ -171,6 +206,21 @@ mod tests {
         assert_close(vector.transverse_momentum(), 5.0, f64::EPSILON);
     }
 
+    #[test]
+    fn angular_coordinates_follow_the_collider_conventions() {
+        let vector = FourVector::new(5.0, 0.0, -3.0, 4.0);
+
+        assert_close(vector.phi(), 1.5 * std::f64::consts::PI, 1e-15);
+        assert_close(vector.pseudorapidity(), 3.0f64.ln(), 1e-15);
+        assert_close(vector.rapidity(), 3.0f64.ln(), 1e-15);
+        assert_close(vector.transverse_energy(), 3.0, 1e-15);
+        assert_close(
+            FourVector::new(5.0, 0.0, 0.0, 3.0).rapidity(),
+            2.0f64.ln(),
+            1e-15,
+        );
+    }
+
     #[test]
     fn non_finite_components_are_rejected() {
         let cases = [
@@ -4,45 +4,84 @@
 //! compatibility. Relativistic DIS calculations live in dedicated modules.
 
 pub mod apfel;
+pub mod breit_frame;
 pub mod constants;
 pub mod cross_section;
+pub mod dis_event_generator;
 pub mod dis_kinematics;
+pub mod event_shapes;
 pub mod four_vector;
+pub mod jets;
 pub mod legacy_cornell;
 pub mod pdf;
+pub mod pdf_reweighting;
+pub mod sampling;
 pub mod structure_function_provider;
 pub mod structure_functions;
 pub mod surrogate;
 pub mod surrogate_training;
+pub mod surrogate_validation;
 
 pub use apfel::{
     ApfelProtocolError, ApfelProtocolResponse, ApfelStructureFunctionProvider,
     APFEL_ZM_VFNS_SCHEME, DEFAULT_APFEL_BACKEND_PATH,
 };
+pub use breit_frame::{BreitFrame, BreitFrameError};
 pub use constants::{ELECTRON_MASS_GEV, PROTON_MASS_GEV};
 pub use cross_section::{
     exact_inelasticity, gev_minus_four_to_pb_per_gev2, leptonic_y_plus,
     lo_differential_cross_section, CouplingError, CrossSectionError, ElectromagneticCoupling,
     FixedAlpha, LoDisCrossSection, DEFAULT_FIXED_ALPHA, GEV_MINUS_2_TO_PB,
 };
+pub use dis_event_generator::{
+    generate_dis_events, DisGeneratorConfig, DisGeneratorError, EventWeighting, GenerationSummary,
+    ImportanceSamplingConfig, MOMENTUM_CONSERVATION_TOLERANCE_GEV, NATIVE_GENERATOR_NAME,
+};
 pub use dis_kinematics::{
     collider_beams, compute_dis_kinematics, incoming_electron, incoming_proton, scattered_electron,
     ColliderBeams, DisCuts, DisError, DisKinematics,
 };
+pub use event_shapes::{
+    current_hemisphere_event_shapes, EventShapeError, EventShapes,
+    CURRENT_HEMISPHERE_ENERGY_FRACTION,
+};
 pub use four_vector::{FourVector, FourVectorError};
+pub use jets::{
+    cluster, cluster_breit_frame, BreitJet, BreitJetCuts, ClusterSequence, ClusterStep,
+    ClusterStrategy, Jet, JetAlgorithm, JetDefinition, JetError, RecombinationScheme,
+};
 pub use legacy_cornell::{cornell_potential, ALPHA_S, HBARC, STRING_TENSION};
-pub use pdf::{LhapdfProvider, PartonDensities, PdfError, PdfProvider};
+pub use pdf::{
+    LhapdfProvider, ParsePdfFlavourError, PartonDensities, PdfError, PdfFlavour, PdfProvider,
+    PdfSetInfo,
+};
+pub use pdf_reweighting::{
+    pdf_weight_name, HardProcess, PdfErrorSet, PdfErrorType, PdfReweighter, PdfReweightingError,
+    PdfUncertainty, GEN_PDF_INFO_ATTRIBUTE,
+};
+pub use sampling::{SamplingError, SamplingMethod, SobolSequence};
 pub use structure_function_provider::{
     DisProjectile, DisTarget, LoPdfStructureFunctionProvider, ParsePerturbativeOrderError,
     PerturbativeOrder, StructureFunctionBackend, StructureFunctionMetadata,
     StructureFunctionProcess, StructureFunctionProvider, StructureFunctionProviderError,
-    StructureFunctionRequest, StructureFunctionResult, LO_PARTON_MODEL_SCHEME,
-    PHOTON_EXCHANGE_MODE, STRUCTURE_FUNCTION_SCHEMA_VERSION,
+    StructureFunctionRequest, StructureFunctionResult, StructureFunctionUncertainty,
+    LO_PARTON_MODEL_SCHEME, PHOTON_EXCHANGE_MODE, STRUCTURE_FUNCTION_SCHEMA_VERSION,
 };
 pub use structure_functions::{
     electromagnetic_f2_from_xf, evaluate_lo_structure_functions, LoStructureFunctions,
     StructureFunctionError, DOWN_TYPE_CHARGE_SQUARED, LO_LONGITUDINAL_STRUCTURE_FUNCTION,
     LO_PARITY_VIOLATING_STRUCTURE_FUNCTION, UP_TYPE_CHARGE_SQUARED,
 };
-pub use surrogate::{SurrogateConfig, SurrogateModel, SurrogateProvider, SURROGATE_SCHEME};
-pub use surrogate_training::{generate_dataset, train_and_save_surrogate, SurrogateDataPoint};
+pub use surrogate::{
+    SurrogateActivation, SurrogateArchitecture, SurrogateConfig, SurrogateModel, SurrogateProvider,
+    SURROGATE_SCHEME,
+};
+pub use surrogate_training::{
+    generate_dataset, refine_surrogate, train_and_save_surrogate, DatasetDomain, DatasetSampler,
+    LearningRateSchedule, RefinementConfig, RefinementRound, SurrogateDataPoint,
+    SurrogateTrainingConfig,
+};
+pub use surrogate_validation::{
+    validate_surrogate, SurrogateValidationConfig, SurrogateValidationError,
+    SurrogateValidationReport,
+};
@@ -8,6 +8,7 @@
 use std::error::Error;
 use std::fmt;
 use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::str::FromStr;
 
 use managed_lhapdf::{Pdf, PdfSet};
 
@@ -39,6 +40,189 @@ pub struct PartonDensities {
     pub anti_bottom: f64,
 }
 
+impl PartonDensities {
+    /// `x * f(x, Q^2)` of the parton with PDG id `pdg_id`, where `21` (or
+    /// `0`) is the gluon. Flavors without a field, such as top, give `None`.
+    #[must_use]
+    pub const fn xf(&self, pdg_id: i32) -> Option<f64> {
+        if pdg_id == 0 || pdg_id == GLUON_ID {
+            return Some(self.gluon);
+        }
+        let (quark, anti_quark) = match pdg_id.abs() {
+            DOWN_ID => (self.down, self.anti_down),
+            UP_ID => (self.up, self.anti_up),
+            STRANGE_ID => (self.strange, self.anti_strange),
+            CHARM_ID => (self.charm, self.anti_charm),
+            BOTTOM_ID => (self.bottom, self.anti_bottom),
+            _ => return None,
+        };
+        Some(if pdg_id > 0 { quark } else { anti_quark })
+    }
+}
+
+/// A parton density or valence combination of [`PartonDensities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PdfFlavour {
+    Gluon,
+    Down,
+    Up,
+    Strange,
+    Charm,
+    Bottom,
+    AntiDown,
+    AntiUp,
+    AntiStrange,
+    AntiCharm,
+    AntiBottom,
+    /// `u - ubar`.
+    UpValence,
+    /// `d - dbar`.
+    DownValence,
+}
+
+impl PdfFlavour {
+    pub const ALL: [Self; 13] = [
+        Self::Gluon,
+        Self::Down,
+        Self::Up,
+        Self::Strange,
+        Self::Charm,
+        Self::Bottom,
+        Self::AntiDown,
+        Self::AntiUp,
+        Self::AntiStrange,
+        Self::AntiCharm,
+        Self::AntiBottom,
+        Self::UpValence,
+        Self::DownValence,
+    ];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Gluon => "g",
+            Self::Down => "d",
+            Self::Up => "u",
+            Self::Strange => "s",
+            Self::Charm => "c",
+            Self::Bottom => "b",
+            Self::AntiDown => "dbar",
+            Self::AntiUp => "ubar",
+            Self::AntiStrange => "sbar",
+            Self::AntiCharm => "cbar",
+            Self::AntiBottom => "bbar",
+            Self::UpValence => "uv",
+            Self::DownValence => "dv",
+        }
+    }
+
+    /// Column name of `x * f` in tables, such as `xg` or `xubar`.
+    #[must_use]
+    pub const fn column(self) -> &'static str {
+        match self {
+            Self::Gluon => "xg",
+            Self::Down => "xd",
+            Self::Up => "xu",
+            Self::Strange => "xs",
+            Self::Charm => "xc",
+            Self::Bottom => "xb",
+            Self::AntiDown => "xdbar",
+            Self::AntiUp => "xubar",
+            Self::AntiStrange => "xsbar",
+            Self::AntiCharm => "xcbar",
+            Self::AntiBottom => "xbbar",
+            Self::UpValence => "xuv",
+            Self::DownValence => "xdv",
+        }
+    }
+
+    /// `x * f(x, Q^2)` of this flavour.
+    #[must_use]
+    pub const fn xf(self, densities: &PartonDensities) -> f64 {
+        match self {
+            Self::Gluon => densities.gluon,
+            Self::Down => densities.down,
+            Self::Up => densities.up,
+            Self::Strange => densities.strange,
+            Self::Charm => densities.charm,
+            Self::Bottom => densities.bottom,
+            Self::AntiDown => densities.anti_down,
+            Self::AntiUp => densities.anti_up,
+            Self::AntiStrange => densities.anti_strange,
+            Self::AntiCharm => densities.anti_charm,
+            Self::AntiBottom => densities.anti_bottom,
+            Self::UpValence => densities.up - densities.anti_up,
+            Self::DownValence => densities.down - densities.anti_down,
+        }
+    }
+}
+
+impl fmt::Display for PdfFlavour {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PdfFlavour {
+    type Err = ParsePdfFlavourError;
+
+    /// Parse a name such as `g`, `ubar` or `uv`, or a PDG id such as `21`
+    /// or `-2`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let name = value.trim().to_ascii_lowercase();
+        let by_pdg_id = name.parse::<i32>().ok().and_then(|pdg_id| match pdg_id {
+            0 | GLUON_ID => Some("g"),
+            DOWN_ID => Some("d"),
+            UP_ID => Some("u"),
+            STRANGE_ID => Some("s"),
+            CHARM_ID => Some("c"),
+            BOTTOM_ID => Some("b"),
+            -1 => Some("dbar"),
+            -2 => Some("ubar"),
+            -3 => Some("sbar"),
+            -4 => Some("cbar"),
+            -5 => Some("bbar"),
+            _ => None,
+        });
+        let name = by_pdg_id.unwrap_or(match name.as_str() {
+            "gluon" => "g",
+            other => other,
+        });
+        Self::ALL
+            .into_iter()
+            .find(|flavour| flavour.as_str() == name)
+            .ok_or_else(|| ParsePdfFlavourError {
+                value: value.to_owned(),
+            })
+    }
+}
+
+/// An unknown flavour name or PDG id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePdfFlavourError {
+    value: String,
+}
+
+impl ParsePdfFlavourError {
+    #[must_use]
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for ParsePdfFlavourError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "unknown PDF flavour '{}'; use g, d, u, s, c, b, dbar, ubar, sbar, cbar, bbar, \
+             uv, dv or a PDG id",
+            self.value
+        )
+    }
+}
+
+impl Error for ParsePdfFlavourError {}
+
 /// A pluggable source of parton densities.
 ///
 /// Implementations must return flavor values using the `x * f(x, Q^2)`
@@ -386,6 +570,38 @@ impl LhapdfProvider {
     }
 }
 
+/// Set-level LHAPDF metadata needed to combine the predictions of all
+/// members of a set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfSetInfo {
+    pub set_name: String,
+    pub member_count: usize,
+    /// LHAPDF `ErrorType`, e.g. `hessian`, `symmhessian` or `replicas`.
+    pub error_type: Option<String>,
+}
+
+impl PdfSetInfo {
+    /// Read the metadata of an installed set without loading any member.
+    pub fn load(set_name: &str) -> Result<Self, PdfError> {
+        let set_name = set_name.trim();
+        if set_name.is_empty() {
+            return Err(PdfError::EmptySetName);
+        }
+        let set = PdfSet::new(set_name).map_err(|error| PdfError::SetUnavailable {
+            set_name: set_name.to_owned(),
+            message: error.to_string(),
+        })?;
+        Ok(Self {
+            set_name: set_name.to_owned(),
+            member_count: parse_usize_metadata(&set, set_name, "NumMembers")?,
+            error_type: set
+                .entry("ErrorType")
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty()),
+        })
+    }
+}
+
 impl PdfProvider for LhapdfProvider {
     fn parton_densities(&self, x: f64, q2: f64) -> Result<PartonDensities, PdfError> {
         self.validate_point(x, q2)?;
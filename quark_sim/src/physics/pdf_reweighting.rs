@@ -0,0 +1,585 @@
+//! Reweighting of generated events to other PDF sets or members.
+//!
+//! At leading order an event depends on the PDF only through the density of
+//! the struck parton, so an event generated with PDF `f` carries the weight
+//! `w f'_q(x, Q²) / f_q(x, Q²)` under another PDF `f'`. The parton flavour,
+//! its momentum fraction and the factorization scale are read from the
+//! event's HepMC3 `GenPdfInfo` attribute, which both the PYTHIA backend and
+//! the native generator write. [`PdfReweighter`] appends one such weight per
+//! target member, and [`PdfErrorSet`] combines the members of a set into a
+//! PDF uncertainty with the set's LHAPDF error prescription.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::pdf::{PdfError, PdfProvider, PdfSetInfo};
+use crate::hepmc3::GenEvent;
+
+/// Name of the HepMC3 event attribute carrying the hard-process PDF
+/// information.
+pub const GEN_PDF_INFO_ATTRIBUTE: &str = "GenPdfInfo";
+
+const GLUON_PDG_ID: i32 = 21;
+
+/// Weight name of one PDF member, in LHAPDF's `SET/MEMBER` notation.
+#[must_use]
+pub fn pdf_weight_name(set_name: &str, member: i32) -> String {
+    format!("{set_name}/{member}")
+}
+
+/// Failure while reweighting an event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PdfReweightingError {
+    DuplicateWeightName {
+        name: String,
+    },
+    MissingPdfInfo {
+        event_number: i64,
+    },
+    InvalidPdfInfo {
+        event_number: i64,
+        value: String,
+        reason: String,
+    },
+    Pdf {
+        event_number: i64,
+        pdf: String,
+        source: PdfError,
+    },
+    VanishingNominalDensity {
+        event_number: i64,
+        flavour: i32,
+        x: f64,
+        q2: f64,
+    },
+}
+
+impl fmt::Display for PdfReweightingError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateWeightName { name } => {
+                write!(formatter, "weight '{name}' is already present")
+            }
+            Self::MissingPdfInfo { event_number } => write!(
+                formatter,
+                "event {event_number} has no {GEN_PDF_INFO_ATTRIBUTE} attribute; \
+                 the hard-process x, Q² and parton flavour are needed for reweighting"
+            ),
+            Self::InvalidPdfInfo {
+                event_number,
+                value,
+                reason,
+            } => write!(
+                formatter,
+                "event {event_number} has an invalid {GEN_PDF_INFO_ATTRIBUTE} '{value}': {reason}"
+            ),
+            Self::Pdf {
+                event_number,
+                pdf,
+                source,
+            } => write!(formatter, "event {event_number}: PDF {pdf}: {source}"),
+            Self::VanishingNominalDensity {
+                event_number,
+                flavour,
+                x,
+                q2,
+            } => write!(
+                formatter,
+                "event {event_number}: the nominal PDF has no density for flavour {flavour} \
+                 at x={x}, Q^2={q2} GeV^2, so the event cannot be reweighted"
+            ),
+        }
+    }
+}
+
+impl Error for PdfReweightingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Pdf { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// The PDF-dependent part of a lepton-hadron hard process.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HardProcess {
+    /// Momentum fraction of the struck parton.
+    pub x: f64,
+    /// Squared factorization scale at which the PDF was evaluated, in GeV².
+    pub q2: f64,
+    /// PDG id of the struck parton; the gluon is `21`.
+    pub flavour: i32,
+}
+
+impl HardProcess {
+    /// Read the hadron-side parton of the event's `GenPdfInfo` attribute.
+    pub fn from_event(event: &GenEvent) -> Result<Self, PdfReweightingError> {
+        let event_number = event.event_number;
+        let value = event
+            .attribute(0, GEN_PDF_INFO_ATTRIBUTE)
+            .ok_or(PdfReweightingError::MissingPdfInfo { event_number })?;
+        Self::parse_gen_pdf_info(value, event.units.momentum.to_gev()).map_err(|reason| {
+            PdfReweightingError::InvalidPdfInfo {
+                event_number,
+                value: value.to_owned(),
+                reason,
+            }
+        })
+    }
+
+    /// Parse `id1 id2 x1 x2 scale [xf1 xf2 pdf_id1 pdf_id2]`, with `scale`
+    /// in the event's momentum unit. Exactly one side must be a quark or
+    /// gluon; the other is the lepton.
+    fn parse_gen_pdf_info(value: &str, to_gev: f64) -> Result<Self, String> {
+        let fields: Vec<&str> = value.split_whitespace().collect();
+        if fields.len() < 5 {
+            return Err(format!(
+                "expected at least 5 fields, found {}",
+                fields.len()
+            ));
+        }
+        let id = |index: usize| {
+            fields[index]
+                .parse::<i32>()
+                .map_err(|_| format!("parton id '{}' is not an integer", fields[index]))
+        };
+        let number = |index: usize| {
+            fields[index]
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| format!("'{}' is not a finite number", fields[index]))
+        };
+        let partons = [(id(0)?, number(2)?), (id(1)?, number(3)?)];
+        let scale = number(4)? * to_gev;
+
+        let is_parton = |pdg_id: i32| (1..=5).contains(&pdg_id.abs()) || pdg_id == GLUON_PDG_ID;
+        let (flavour, x) = match partons.map(|(pdg_id, _)| is_parton(pdg_id)) {
+            [false, true] => partons[1],
+            [true, false] => partons[0],
+            [true, true] => {
+                return Err("both incoming partons are hadronic; only lepton-hadron \
+                            events can be reweighted"
+                    .to_owned())
+            }
+            [false, false] => return Err("neither incoming parton is a quark or gluon".to_owned()),
+        };
+        if !(x > 0.0 && x < 1.0) {
+            return Err(format!("parton momentum fraction {x} is outside (0, 1)"));
+        }
+        if scale <= 0.0 {
+            return Err(format!("factorization scale {scale} GeV is not positive"));
+        }
+        Ok(Self {
+            x,
+            q2: scale * scale,
+            flavour,
+        })
+    }
+
+    /// `GenPdfInfo` value for a lepton `lepton_pdg_id` scattering off this
+    /// parton, with the parton's `x f(x, Q²)` and no LHAPDF ids.
+    #[must_use]
+    pub fn gen_pdf_info(&self, lepton_pdg_id: i32, xf: f64) -> String {
+        format!(
+            "{lepton_pdg_id} {} 1 {:e} {:e} 1 {xf:e} 0 0",
+            self.flavour,
+            self.x,
+            self.q2.sqrt()
+        )
+    }
+}
+
+/// Computes PDF weight ratios relative to the PDF an event sample was
+/// generated with.
+pub struct PdfReweighter {
+    nominal: Box<dyn PdfProvider>,
+    members: Vec<(String, Box<dyn PdfProvider>)>,
+}
+
+impl PdfReweighter {
+    /// `nominal` must be the PDF the events were generated with.
+    #[must_use]
+    pub fn new(nominal: Box<dyn PdfProvider>) -> Self {
+        Self {
+            nominal,
+            members: Vec::new(),
+        }
+    }
+
+    /// Add a target PDF whose weight is stored under `name`.
+    pub fn add_member(
+        &mut self,
+        name: impl Into<String>,
+        provider: Box<dyn PdfProvider>,
+    ) -> Result<(), PdfReweightingError> {
+        let name = name.into();
+        if self.members.iter().any(|(existing, _)| *existing == name) {
+            return Err(PdfReweightingError::DuplicateWeightName { name });
+        }
+        self.members.push((name, provider));
+        Ok(())
+    }
+
+    /// Names of the weights added by [`PdfReweighter::reweight`], in order.
+    pub fn weight_names(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|(name, _)| name.as_str())
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// `f'_q / f_q` of every target member for the hard process of `event`.
+    pub fn ratios(&self, event: &GenEvent) -> Result<Vec<f64>, PdfReweightingError> {
+        let hard = HardProcess::from_event(event)?;
+        let event_number = event.event_number;
+        let density = |pdf: &str, provider: &dyn PdfProvider| {
+            let densities = provider
+                .parton_densities(hard.x, hard.q2)
+                .map_err(|source| PdfReweightingError::Pdf {
+                    event_number,
+                    pdf: pdf.to_owned(),
+                    source,
+                })?;
+            densities
+                .xf(hard.flavour)
+                .ok_or_else(|| PdfReweightingError::InvalidPdfInfo {
+                    event_number,
+                    value: hard.flavour.to_string(),
+                    reason: "the PDF has no density for this flavour".to_owned(),
+                })
+        };
+
+        let nominal = density("nominal", self.nominal.as_ref())?;
+        if !(nominal.is_finite() && nominal > 0.0) {
+            return Err(PdfReweightingError::VanishingNominalDensity {
+                event_number,
+                flavour: hard.flavour,
+                x: hard.x,
+                q2: hard.q2,
+            });
+        }
+        self.members
+            .iter()
+            .map(|(name, provider)| Ok(density(name, provider.as_ref())? / nominal))
+            .collect()
+    }
+
+    /// Append `w f'_q / f_q` for every target member to the event's weights,
+    /// where `w` is its nominal weight.
+    pub fn reweight(&self, event: &mut GenEvent) -> Result<(), PdfReweightingError> {
+        let weight = event.weight();
+        let ratios = self.ratios(event)?;
+        event
+            .weights
+            .extend(ratios.into_iter().map(|ratio| weight * ratio));
+        Ok(())
+    }
+}
+
+/// LHAPDF error prescriptions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PdfErrorType {
+    /// Asymmetric Hessian eigenvector pairs.
+    #[serde(rename = "hessian")]
+    Hessian,
+    /// Symmetric Hessian eigenvectors.
+    #[serde(rename = "symmhessian")]
+    SymmetricHessian,
+    /// Monte Carlo replicas.
+    #[serde(rename = "replicas")]
+    Replicas,
+}
+
+impl PdfErrorType {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Hessian => "hessian",
+            Self::SymmetricHessian => "symmhessian",
+            Self::Replicas => "replicas",
+        }
+    }
+}
+
+impl fmt::Display for PdfErrorType {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+/// Central value with upward and downward PDF uncertainties.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PdfUncertainty {
+    pub central: f64,
+    pub plus: f64,
+    pub minus: f64,
+}
+
+/// Error prescription of a PDF set and the members it applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PdfErrorSet {
+    pub error_type: PdfErrorType,
+    /// Members `1..=error_members` are error members. Any later members are
+    /// parameter variations, such as `αs`, and do not enter the uncertainty.
+    pub error_members: usize,
+}
+
+impl PdfErrorSet {
+    /// Read the prescription from a set's `ErrorType`. Each `+` suffix, as in
+    /// `hessian+as`, marks a pair of parameter-variation members at the end
+    /// of the set. Unknown error types give `None`.
+    #[must_use]
+    pub fn from_lhapdf(info: &PdfSetInfo) -> Option<Self> {
+        let mut parts = info.error_type.as_deref()?.split('+');
+        let error_type = match parts.next()?.trim().to_ascii_lowercase().as_str() {
+            "hessian" => PdfErrorType::Hessian,
+            "symmhessian" => PdfErrorType::SymmetricHessian,
+            "replicas" => PdfErrorType::Replicas,
+            _ => return None,
+        };
+        let parameter_members = 2 * parts.count();
+        let error_members = info
+            .member_count
+            .checked_sub(1 + parameter_members)
+            .filter(|&count| count > 0)?;
+        Some(Self {
+            error_type,
+            error_members,
+        })
+    }
+
+    /// Combine predictions indexed by member number, member 0 being the
+    /// central value. `None` when fewer than `1 + error_members` are given.
+    #[must_use]
+    pub fn uncertainty(&self, values: &[f64]) -> Option<PdfUncertainty> {
+        let central = *values.first()?;
+        let members = values.get(1..=self.error_members)?;
+        let quadrature =
+            |terms: &mut dyn Iterator<Item = f64>| terms.map(|t| t * t).sum::<f64>().sqrt();
+        let (plus, minus) = match self.error_type {
+            PdfErrorType::Hessian => {
+                let pairs = members.chunks_exact(2);
+                let up = quadrature(
+                    &mut pairs
+                        .clone()
+                        .map(|pair| (pair[0] - central).max(pair[1] - central).max(0.0)),
+                );
+                let down = quadrature(
+                    &mut pairs.map(|pair| (central - pair[0]).max(central - pair[1]).max(0.0)),
+                );
+                (up, down)
+            }
+            PdfErrorType::SymmetricHessian => {
+                let error = quadrature(&mut members.iter().map(|value| value - central));
+                (error, error)
+            }
+            PdfErrorType::Replicas => {
+                let count = members.len() as f64;
+                let mean = members.iter().sum::<f64>() / count;
+                let variance = if members.len() > 1 {
+                    members
+                        .iter()
+                        .map(|value| (value - mean).powi(2))
+                        .sum::<f64>()
+                        / (count - 1.0)
+                } else {
+                    0.0
+                };
+                (variance.sqrt(), variance.sqrt())
+            }
+        };
+        Some(PdfUncertainty {
+            central,
+            plus,
+            minus,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hepmc3::Attribute;
+    use crate::physics::pdf::PartonDensities;
+
+    /// Densities `scale * x^power` for every flavour.
+    struct PowerPdf {
+        scale: f64,
+        power: f64,
+    }
+
+    impl PdfProvider for PowerPdf {
+        fn parton_densities(&self, x: f64, q2: f64) -> Result<PartonDensities, PdfError> {
+            let value = self.scale * x.powf(self.power);
+            Ok(PartonDensities {
+                x,
+                q2,
+                gluon: value,
+                up: value,
+                anti_up: value,
+                down: 2.0 * value,
+                anti_down: value,
+                strange: value,
+                anti_strange: value,
+                charm: 0.0,
+                anti_charm: 0.0,
+                bottom: 0.0,
+                anti_bottom: 0.0,
+            })
+        }
+    }
+
+    fn event(pdf_info: &str, weight: f64) -> GenEvent {
+        GenEvent {
+            event_number: 5,
+            weights: vec![weight],
+            attributes: vec![Attribute {
+                target: 0,
+                name: GEN_PDF_INFO_ATTRIBUTE.to_owned(),
+                value: pdf_info.to_owned(),
+            }],
+            ..GenEvent::default()
+        }
+    }
+
+    #[test]
+    fn gen_pdf_info_selects_the_hadronic_parton() {
+        let pythia = event("11 2 1 0.01 10 1 0.5 0 0", 1.0);
+        assert_eq!(
+            HardProcess::from_event(&pythia).unwrap(),
+            HardProcess {
+                x: 0.01,
+                q2: 100.0,
+                flavour: 2
+            }
+        );
+
+        let written = HardProcess {
+            x: 0.25,
+            q2: 400.0,
+            flavour: -1,
+        };
+        let round_trip = event(&written.gen_pdf_info(11, 0.3), 1.0);
+        let parsed = HardProcess::from_event(&round_trip).unwrap();
+        assert_eq!(parsed.flavour, -1);
+        assert!((parsed.x - 0.25).abs() < 1e-15 && (parsed.q2 - 400.0).abs() < 1e-9);
+
+        for invalid in [
+            "11 2 1 0.01",
+            "1 2 0.1 0.01 10",
+            "11 22 1 0.1 10",
+            "11 2 1 1.5 10",
+        ] {
+            assert!(matches!(
+                HardProcess::from_event(&event(invalid, 1.0)),
+                Err(PdfReweightingError::InvalidPdfInfo { .. })
+            ));
+        }
+        assert_eq!(
+            HardProcess::from_event(&GenEvent::default()),
+            Err(PdfReweightingError::MissingPdfInfo { event_number: 0 })
+        );
+    }
+
+    #[test]
+    fn reweighting_appends_flavour_density_ratios() {
+        let mut reweighter = PdfReweighter::new(Box::new(PowerPdf {
+            scale: 1.0,
+            power: 0.0,
+        }));
+        reweighter
+            .add_member(
+                "A/0",
+                Box::new(PowerPdf {
+                    scale: 2.0,
+                    power: 0.0,
+                }),
+            )
+            .unwrap();
+        reweighter
+            .add_member(
+                "B/0",
+                Box::new(PowerPdf {
+                    scale: 1.0,
+                    power: 1.0,
+                }),
+            )
+            .unwrap();
+        assert_eq!(
+            reweighter.add_member(
+                "A/0",
+                Box::new(PowerPdf {
+                    scale: 1.0,
+                    power: 0.0
+                })
+            ),
+            Err(PdfReweightingError::DuplicateWeightName {
+                name: "A/0".to_owned()
+            })
+        );
+        assert_eq!(
+            reweighter.weight_names().collect::<Vec<_>>(),
+            ["A/0", "B/0"]
+        );
+
+        let mut down = event("11 1 1 0.1 10", 3.0);
+        reweighter.reweight(&mut down).unwrap();
+        assert_eq!(down.weights.len(), 3);
+        assert!((down.weights[1] - 6.0).abs() < 1e-12);
+        assert!((down.weights[2] - 0.3).abs() < 1e-12);
+
+        let zero = PdfReweighter::new(Box::new(PowerPdf {
+            scale: 0.0,
+            power: 0.0,
+        }));
+        assert!(matches!(
+            zero.ratios(&down),
+            Err(PdfReweightingError::VanishingNominalDensity { flavour: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn error_sets_follow_the_lhapdf_prescriptions() {
+        let info = |error_type: &str, member_count| PdfSetInfo {
+            set_name: "Test".to_owned(),
+            member_count,
+            error_type: Some(error_type.to_owned()),
+        };
+        let hessian = PdfErrorSet::from_lhapdf(&info("hessian+as", 7)).unwrap();
+        assert_eq!(hessian.error_type, PdfErrorType::Hessian);
+        assert_eq!(hessian.error_members, 4);
+        assert_eq!(PdfErrorSet::from_lhapdf(&info("unknown", 7)), None);
+
+        let values = [10.0, 13.0, 8.0, 9.0, 9.5, 100.0, 100.0];
+        let result = hessian.uncertainty(&values).unwrap();
+        assert_eq!(result.central, 10.0);
+        assert!((result.plus - 3.0).abs() < 1e-12);
+        assert!((result.minus - (4.0_f64 + 1.0).sqrt()).abs() < 1e-12);
+
+        let symmetric = PdfErrorSet {
+            error_type: PdfErrorType::SymmetricHessian,
+            error_members: 2,
+        };
+        let result = symmetric.uncertainty(&[10.0, 13.0, 6.0]).unwrap();
+        assert!((result.plus - 5.0).abs() < 1e-12 && result.plus == result.minus);
+
+        let replicas = PdfErrorSet {
+            error_type: PdfErrorType::Replicas,
+            error_members: 4,
+        };
+        let result = replicas.uncertainty(&[10.0, 9.0, 11.0, 9.0, 11.0]).unwrap();
+        assert!((result.plus - (4.0_f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert_eq!(replicas.uncertainty(&[10.0, 9.0]), None);
+    }
+}
@@ -0,0 +1,292 @@
+//! Space-filling point sets on the unit hypercube.
+//!
+//! Latin-hypercube and Sobol samples are produced in `[0, 1)^d`. Callers map
+//! each coordinate onto their own physical domain, for example logarithmically
+//! in `x` and `Q²`. Both samplers are deterministic for a given seed.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+const SOBOL_BITS: usize = 32;
+
+/// Joe-Kuo primitive polynomials and initial direction numbers for the Sobol
+/// dimensions after the first, as `(degree s, coefficients a, m_1..m_s)`.
+const SOBOL_DIRECTIONS: [(u32, u32, &[u32]); 7] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+];
+
+/// Largest dimension supported by [`SobolSequence`].
+pub const SOBOL_MAX_DIMENSIONS: usize = SOBOL_DIRECTIONS.len() + 1;
+
+/// Point-set construction used to fill a sampling domain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplingMethod {
+    LatinHypercube,
+    Sobol,
+}
+
+impl SamplingMethod {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::LatinHypercube => "latin_hypercube",
+            Self::Sobol => "sobol",
+        }
+    }
+
+    /// Draw `count` points in `[0, 1)^dimensions`.
+    pub fn unit_points(
+        self,
+        count: usize,
+        dimensions: usize,
+        seed: u64,
+    ) -> Result<Vec<Vec<f64>>, SamplingError> {
+        match self {
+            Self::LatinHypercube => latin_hypercube(count, dimensions, seed),
+            Self::Sobol => {
+                if count == 0 {
+                    return Err(SamplingError::EmptySample);
+                }
+                let mut sequence = SobolSequence::new(dimensions, seed)?;
+                Ok((0..count).map(|_| sequence.next_point()).collect())
+            }
+        }
+    }
+}
+
+impl fmt::Display for SamplingMethod {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SamplingMethod {
+    type Err = SamplingError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().replace('-', "_").as_str() {
+            "latin_hypercube" | "lhs" => Ok(Self::LatinHypercube),
+            "sobol" => Ok(Self::Sobol),
+            _ => Err(SamplingError::UnknownMethod {
+                value: value.to_owned(),
+            }),
+        }
+    }
+}
+
+/// Invalid sampler configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SamplingError {
+    EmptySample,
+    InvalidDimensions { dimensions: usize, maximum: usize },
+    UnknownMethod { value: String },
+}
+
+impl fmt::Display for SamplingError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptySample => write!(formatter, "sample size must be positive"),
+            Self::InvalidDimensions {
+                dimensions,
+                maximum,
+            } => write!(
+                formatter,
+                "sampler dimension {dimensions} is unsupported; expected 1..={maximum}"
+            ),
+            Self::UnknownMethod { value } => write!(
+                formatter,
+                "unknown sampling method '{value}'; supported methods are sobol and latin-hypercube"
+            ),
+        }
+    }
+}
+
+impl Error for SamplingError {}
+
+/// Latin-hypercube sample: every coordinate has exactly one point in each of
+/// the `count` equal-width strata, jittered uniformly inside its stratum.
+pub fn latin_hypercube(
+    count: usize,
+    dimensions: usize,
+    seed: u64,
+) -> Result<Vec<Vec<f64>>, SamplingError> {
+    if count == 0 {
+        return Err(SamplingError::EmptySample);
+    }
+    if dimensions == 0 {
+        return Err(SamplingError::InvalidDimensions {
+            dimensions,
+            maximum: usize::MAX,
+        });
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut points = vec![vec![0.0; dimensions]; count];
+    let mut strata: Vec<usize> = (0..count).collect();
+    for dimension in 0..dimensions {
+        strata.shuffle(&mut rng);
+        for (point, &stratum) in points.iter_mut().zip(&strata) {
+            point[dimension] = (stratum as f64 + rng.gen::<f64>()) / count as f64;
+        }
+    }
+    Ok(points)
+}
+
+/// Gray-code Sobol sequence with a seeded random digital shift.
+///
+/// The shift keeps the low-discrepancy structure while making different seeds
+/// produce independent-looking point sets. The all-zero first point of the
+/// unshifted sequence is skipped.
+#[derive(Debug, Clone)]
+pub struct SobolSequence {
+    directions: Vec<[u32; SOBOL_BITS]>,
+    state: Vec<u32>,
+    shift: Vec<u32>,
+    index: u64,
+}
+
+impl SobolSequence {
+    pub fn new(dimensions: usize, seed: u64) -> Result<Self, SamplingError> {
+        if dimensions == 0 || dimensions > SOBOL_MAX_DIMENSIONS {
+            return Err(SamplingError::InvalidDimensions {
+                dimensions,
+                maximum: SOBOL_MAX_DIMENSIONS,
+            });
+        }
+
+        let mut directions = Vec::with_capacity(dimensions);
+        let mut first = [0_u32; SOBOL_BITS];
+        for (bit, direction) in first.iter_mut().enumerate() {
+            *direction = 1 << (SOBOL_BITS - 1 - bit);
+        }
+        directions.push(first);
+
+        for &(degree, coefficients, initial) in SOBOL_DIRECTIONS.iter().take(dimensions - 1) {
+            let degree = degree as usize;
+            let mut values = [0_u32; SOBOL_BITS];
+            for bit in 0..degree {
+                values[bit] = initial[bit] << (SOBOL_BITS - 1 - bit);
+            }
+            for bit in degree..SOBOL_BITS {
+                let mut value = values[bit - degree] ^ (values[bit - degree] >> degree);
+                for k in 1..degree {
+                    if (coefficients >> (degree - 1 - k)) & 1 == 1 {
+                        value ^= values[bit - k];
+                    }
+                }
+                values[bit] = value;
+            }
+            directions.push(values);
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let shift = (0..dimensions).map(|_| rng.gen::<u32>()).collect();
+
+        Ok(Self {
+            directions,
+            state: vec![0; dimensions],
+            shift,
+            index: 0,
+        })
+    }
+
+    #[must_use]
+    pub fn dimensions(&self) -> usize {
+        self.directions.len()
+    }
+
+    /// Advance the sequence and return the next point in `[0, 1)^d`.
+    pub fn next_point(&mut self) -> Vec<f64> {
+        // The bit flipped between consecutive Gray codes is the lowest zero
+        // bit of the previous index.
+        let bit = (self.index.trailing_ones() as usize).min(SOBOL_BITS - 1);
+        self.index += 1;
+        self.state
+            .iter_mut()
+            .zip(&self.directions)
+            .zip(&self.shift)
+            .map(|((state, directions), shift)| {
+                *state ^= directions[bit];
+                f64::from(*state ^ shift) / 2_f64.powi(SOBOL_BITS as i32)
+            })
+            .collect()
+    }
+}
+
+/// Map a unit coordinate onto `[minimum, maximum]` uniformly in `log10`.
+#[must_use]
+pub fn log_uniform(unit: f64, minimum: f64, maximum: f64) -> f64 {
+    let log_minimum = minimum.log10();
+    10_f64.powf(log_minimum + unit * (maximum.log10() - log_minimum))
+}
+
+/// Map a unit coordinate onto `[minimum, maximum]` linearly.
+#[must_use]
+pub fn linear_uniform(unit: f64, minimum: f64, maximum: f64) -> f64 {
+    minimum + unit * (maximum - minimum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latin_hypercube_fills_every_stratum_once() {
+        let points = latin_hypercube(16, 3, 7).unwrap();
+        for dimension in 0..3 {
+            let mut strata: Vec<usize> = points
+                .iter()
+                .map(|point| (point[dimension] * 16.0).floor() as usize)
+                .collect();
+            strata.sort_unstable();
+            assert_eq!(strata, (0..16).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn unshifted_sobol_matches_reference_points() {
+        let mut sequence = SobolSequence::new(3, 0).unwrap();
+        sequence.shift = vec![0; 3];
+        let points: Vec<Vec<f64>> = (0..4).map(|_| sequence.next_point()).collect();
+        assert_eq!(points[0], vec![0.5, 0.5, 0.5]);
+        assert_eq!(points[1], vec![0.75, 0.25, 0.25]);
+        assert_eq!(points[2], vec![0.25, 0.75, 0.75]);
+        assert_eq!(points[3], vec![0.375, 0.375, 0.625]);
+    }
+
+    #[test]
+    fn sobol_points_are_seeded_and_in_unit_cube() {
+        let first = SamplingMethod::Sobol.unit_points(64, 4, 11).unwrap();
+        let second = SamplingMethod::Sobol.unit_points(64, 4, 11).unwrap();
+        assert_eq!(first, second);
+        assert!(first
+            .iter()
+            .flatten()
+            .all(|value| (0.0..1.0).contains(value)));
+    }
+
+    #[test]
+    fn rejects_unsupported_configurations() {
+        assert!(SobolSequence::new(0, 0).is_err());
+        assert!(SobolSequence::new(SOBOL_MAX_DIMENSIONS + 1, 0).is_err());
+        assert!(latin_hypercube(0, 2, 0).is_err());
+        assert!("halton".parse::<SamplingMethod>().is_err());
+        assert_eq!(
+            "latin-hypercube".parse::<SamplingMethod>(),
+            Ok(SamplingMethod::LatinHypercube)
+        );
+    }
+}
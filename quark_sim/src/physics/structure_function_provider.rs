@@ -281,12 +281,26 @@ pub struct StructureFunctionMetadata {
     pub python_env_hash: Option<String>,
 }
 
+/// One-standard-deviation spread of a prediction, reported by backends that
+/// evaluate several models (currently the surrogate ensemble).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StructureFunctionUncertainty {
+    pub f2: f64,
+    pub fl: f64,
+    pub xf3: f64,
+    /// Number of ensemble members the spread was computed from.
+    pub members: usize,
+}
+
 /// Backend-independent inclusive structure functions at one `(x, Q²)` point.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StructureFunctionResult {
     pub f2: f64,
     pub fl: f64,
     pub xf3: f64,
+    /// Absent for deterministic backends such as APFEL++ and the LO model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uncertainty: Option<StructureFunctionUncertainty>,
     pub metadata: StructureFunctionMetadata,
 }
 
@@ -422,6 +436,7 @@ impl<P: PdfProvider> StructureFunctionProvider for LoPdfStructureFunctionProvide
             f2: lo.f2,
             fl: lo.fl,
             xf3: lo.xf3,
+            uncertainty: None,
             metadata: StructureFunctionMetadata {
                 backend: StructureFunctionBackend::LoPdf,
                 apfelxx_version: None,
@@ -540,6 +555,13 @@ pub enum StructureFunctionProviderError {
         q2: f64,
         reason: String,
     },
+    UncertaintyTooLarge {
+        x: f64,
+        q2: f64,
+        quantity: &'static str,
+        relative_spread: f64,
+        threshold: f64,
+    },
     EvaluationFailed(String),
 }
 
@@ -649,6 +671,16 @@ impl fmt::Display for StructureFunctionProviderError {
                 formatter,
                 "surrogate model rejected point x={x}, Q2={q2}: {reason}"
             ),
+            Self::UncertaintyTooLarge {
+                x,
+                q2,
+                quantity,
+                relative_spread,
+                threshold,
+            } => write!(
+                formatter,
+                "surrogate ensemble spread of {quantity} at x={x}, Q2={q2} is {relative_spread:.3e} of F2, above the threshold {threshold:.3e}"
+            ),
             Self::EvaluationFailed(message) => write!(
                 formatter,
                 "provider evaluation failed: {message}"
@@ -719,6 +751,13 @@ mod tests {
             mu_r_over_q: 1.0,
             scheme: "ZM-VFNS".to_owned(),
             electromagnetic_mode: PHOTON_EXCHANGE_MODE.to_owned(),
+            os_arch: None,
+            rust_version: None,
+            git_commit: None,
+            git_dirty: None,
+            pythia_version: None,
+            hepmc_version: None,
+            python_env_hash: None,
         }
     }
 
@@ -833,6 +872,7 @@ mod tests {
                 f2: 0.8,
                 fl: 0.1,
                 xf3: 0.0,
+                uncertainty: None,
                 metadata: mock_metadata(),
             })
         }
@@ -851,6 +891,7 @@ mod tests {
             f2: f64::NAN,
             fl: 0.0,
             xf3: 0.0,
+            uncertainty: None,
             metadata: mock_metadata(),
         };
         assert!(matches!(
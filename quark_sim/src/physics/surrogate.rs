@@ -5,8 +5,10 @@
 //! limits and cannot be used as exact ground truth.
 
 use std::error::Error;
+use std::fmt;
 use std::fs;
 use std::path::Path;
+use std::str::FromStr;
 
 use candle_core::{DType, Device, Result as CandleResult, Tensor};
 use candle_nn::{Linear, Module, VarBuilder, VarMap};
@@ -14,13 +16,29 @@ use serde::{Deserialize, Serialize};
 
 use super::structure_function_provider::{
     PerturbativeOrder, StructureFunctionBackend, StructureFunctionMetadata,
-    StructureFunctionProvider, StructureFunctionProviderError,
-    StructureFunctionRequest, StructureFunctionResult, PHOTON_EXCHANGE_MODE,
+    StructureFunctionProvider, StructureFunctionProviderError, StructureFunctionRequest,
+    StructureFunctionResult, StructureFunctionUncertainty, PHOTON_EXCHANGE_MODE,
 };
 
 /// Name of the scheme used by the surrogate.
 pub const SURROGATE_SCHEME: &str = "surrogate_nlo";
 
+/// Weights file of one ensemble member inside a model directory.
+///
+/// Member 0 keeps the single-model name so older model directories still load.
+#[must_use]
+pub fn member_weights_filename(member: usize) -> String {
+    if member == 0 {
+        "model.safetensors".to_owned()
+    } else {
+        format!("model_member{member}.safetensors")
+    }
+}
+
+fn default_ensemble_size() -> usize {
+    1
+}
+
 /// Model configuration stored with the `.safetensors` file.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SurrogateConfig {
@@ -50,7 +68,8 @@ pub struct SurrogateConfig {
     pub input_std: Vec<f32>,
 
     /// Target normalizations: mean and standard deviation.
-    /// Vector order: [log10(F₂), FL, xF₃]
+    /// Vector order: [log10(F₂), FL, xF₃], with FL replaced by logit(FL/F₂)
+    /// when `constraints.fl_bounded_by_f2` is set.
     pub target_mean: Vec<f32>,
     pub target_std: Vec<f32>,
 
@@ -58,6 +77,22 @@ pub struct SurrogateConfig {
     pub validation_mse: f32,
     /// Last known test max relative error.
     pub test_max_rel_error: f32,
+
+    /// Number of independently trained networks averaged at evaluation.
+    #[serde(default = "default_ensemble_size")]
+    pub ensemble_size: usize,
+    /// Largest accepted ensemble standard deviation of F₂, FL or xF₃,
+    /// relative to the predicted F₂. `None` reports the spread without
+    /// rejecting points.
+    #[serde(default)]
+    pub max_relative_uncertainty: Option<f64>,
+
+    /// Network layout the weights were trained with.
+    #[serde(default)]
+    pub architecture: SurrogateArchitecture,
+    /// Physics constraints the network was trained and is evaluated with.
+    #[serde(default)]
+    pub constraints: SurrogateConstraints,
 }
 
 impl SurrogateConfig {
@@ -77,50 +112,207 @@ impl SurrogateConfig {
     }
 }
 
+/// Optional physics constraints on the surrogate outputs.
+///
+/// The first two hold by construction: they change what the network output
+/// means, so they apply identically in training and evaluation. The slope
+/// penalty only shapes training.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurrogateConstraints {
+    /// Predict FL/F₂ through a logistic function, so `0 ≤ FL ≤ F₂` for any
+    /// input.
+    pub fl_bounded_by_f2: bool,
+    /// Return `xF₃ = 0`. The surrogate models pure photon exchange, where the
+    /// parity-violating structure function vanishes.
+    pub zero_xf3: bool,
+    /// Weight of the penalty on the squared difference between the network's
+    /// `∂ln F₂/∂ln Q²` and the source backend's. `None` disables it.
+    pub q2_slope_weight: Option<f64>,
+}
+
+impl SurrogateConstraints {
+    /// Smallest FL/F₂ ratio distance from 0 and 1 used for the logit target.
+    const FL_RATIO_EPSILON: f64 = 1e-6;
+
+    /// Unnormalised network targets `[log10(F₂), FL or logit(FL/F₂), xF₃]`.
+    #[must_use]
+    pub fn targets(&self, f2: f64, fl: f64, xf3: f64) -> [f64; 3] {
+        let f2 = f2.max(1e-10);
+        let fl_target = if self.fl_bounded_by_f2 {
+            let ratio = (fl / f2).clamp(Self::FL_RATIO_EPSILON, 1.0 - Self::FL_RATIO_EPSILON);
+            (ratio / (1.0 - ratio)).ln()
+        } else {
+            fl
+        };
+        let xf3_target = if self.zero_xf3 { 0.0 } else { xf3 };
+        [f2.log10(), fl_target, xf3_target]
+    }
+
+    /// Physical `[F₂, FL, xF₃]` from unnormalised network outputs; the inverse
+    /// of [`Self::targets`].
+    #[must_use]
+    pub fn structure_functions(&self, outputs: [f64; 3]) -> [f64; 3] {
+        let f2 = 10_f64.powf(outputs[0]);
+        let fl = if self.fl_bounded_by_f2 {
+            f2 / (1.0 + (-outputs[1]).exp())
+        } else {
+            outputs[1]
+        };
+        let xf3 = if self.zero_xf3 { 0.0 } else { outputs[2] };
+        [f2, fl, xf3]
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .q2_slope_weight
+            .is_some_and(|weight| !(weight.is_finite() && weight > 0.0))
+        {
+            return Err("q2_slope_weight must be finite and positive".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Nonlinearity applied after every hidden layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SurrogateActivation {
+    Relu,
+    Gelu,
+    Silu,
+    Tanh,
+}
+
+impl SurrogateActivation {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Relu => "relu",
+            Self::Gelu => "gelu",
+            Self::Silu => "silu",
+            Self::Tanh => "tanh",
+        }
+    }
+
+    fn apply(self, xs: &Tensor) -> CandleResult<Tensor> {
+        match self {
+            Self::Relu => xs.relu(),
+            Self::Gelu => xs.gelu(),
+            Self::Silu => candle_nn::ops::silu(xs),
+            Self::Tanh => xs.tanh(),
+        }
+    }
+}
+
+impl fmt::Display for SurrogateActivation {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SurrogateActivation {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "relu" => Ok(Self::Relu),
+            "gelu" => Ok(Self::Gelu),
+            "silu" | "swish" => Ok(Self::Silu),
+            "tanh" => Ok(Self::Tanh),
+            _ => Err(format!(
+                "unknown activation '{value}'; supported activations are relu, gelu, silu and tanh"
+            )),
+        }
+    }
+}
+
+/// Hidden-layer layout of the surrogate network.
+///
+/// Stored in `model_config.json` so [`SurrogateProvider::load`] rebuilds the
+/// network that produced the weights. Configs written before the layout was
+/// configurable deserialize to the original `128 → 64 → 32` ReLU network.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SurrogateArchitecture {
+    /// Width of each hidden layer, from input to output side.
+    pub hidden_layers: Vec<usize>,
+    pub activation: SurrogateActivation,
+}
+
+impl Default for SurrogateArchitecture {
+    fn default() -> Self {
+        Self {
+            hidden_layers: vec![128, 64, 32],
+            activation: SurrogateActivation::Relu,
+        }
+    }
+}
+
+impl SurrogateArchitecture {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.hidden_layers.is_empty() {
+            return Err("surrogate architecture needs at least one hidden layer".to_owned());
+        }
+        if self.hidden_layers.contains(&0) {
+            return Err("surrogate hidden-layer widths must be positive".to_owned());
+        }
+        Ok(())
+    }
+}
+
 /// A multilayer perceptron mapping 4 inputs to 3 outputs.
+///
+/// Layers are named `layer1`, `layer2`, … in the safetensors file, so the
+/// default architecture matches weights saved by earlier versions.
 pub struct SurrogateModel {
-    layer1: Linear,
-    layer2: Linear,
-    layer3: Linear,
-    layer4: Linear,
+    layers: Vec<Linear>,
+    activation: SurrogateActivation,
 }
 
 impl SurrogateModel {
     pub const INPUT_SIZE: usize = 4;
-    pub const HIDDEN1_SIZE: usize = 128;
-    pub const HIDDEN2_SIZE: usize = 64;
-    pub const HIDDEN3_SIZE: usize = 32;
     pub const OUTPUT_SIZE: usize = 3;
 
-    pub fn new(vs: VarBuilder) -> CandleResult<Self> {
-        let layer1 = candle_nn::linear(Self::INPUT_SIZE, Self::HIDDEN1_SIZE, vs.pp("layer1"))?;
-        let layer2 = candle_nn::linear(Self::HIDDEN1_SIZE, Self::HIDDEN2_SIZE, vs.pp("layer2"))?;
-        let layer3 = candle_nn::linear(Self::HIDDEN2_SIZE, Self::HIDDEN3_SIZE, vs.pp("layer3"))?;
-        let layer4 = candle_nn::linear(Self::HIDDEN3_SIZE, Self::OUTPUT_SIZE, vs.pp("layer4"))?;
+    pub fn new(vs: VarBuilder, architecture: &SurrogateArchitecture) -> CandleResult<Self> {
+        architecture.validate().map_err(candle_core::Error::Msg)?;
+        let widths: Vec<usize> = std::iter::once(Self::INPUT_SIZE)
+            .chain(architecture.hidden_layers.iter().copied())
+            .chain(std::iter::once(Self::OUTPUT_SIZE))
+            .collect();
+        let layers = widths
+            .windows(2)
+            .enumerate()
+            .map(|(index, pair)| {
+                candle_nn::linear(pair[0], pair[1], vs.pp(format!("layer{}", index + 1)))
+            })
+            .collect::<CandleResult<Vec<_>>>()?;
 
         Ok(Self {
-            layer1,
-            layer2,
-            layer3,
-            layer4,
+            layers,
+            activation: architecture.activation,
         })
     }
 
     pub fn forward(&self, xs: &Tensor) -> CandleResult<Tensor> {
-        let xs = self.layer1.forward(xs)?;
-        let xs = xs.relu()?;
-        let xs = self.layer2.forward(&xs)?;
-        let xs = xs.relu()?;
-        let xs = self.layer3.forward(&xs)?;
-        let xs = xs.relu()?;
-        let xs = self.layer4.forward(&xs)?;
-        Ok(xs)
+        let (output, hidden) = self
+            .layers
+            .split_last()
+            .expect("a surrogate model always has an output layer");
+        let mut xs = xs.clone();
+        for layer in hidden {
+            xs = self.activation.apply(&layer.forward(&xs)?)?;
+        }
+        output.forward(&xs)
     }
 }
 
 /// A full structure-function provider relying on the surrogate model.
+///
+/// With more than one ensemble member the result is the member mean, and the
+/// member standard deviation is reported in
+/// [`StructureFunctionResult::uncertainty`].
 pub struct SurrogateProvider {
-    model: SurrogateModel,
+    models: Vec<SurrogateModel>,
     config: SurrogateConfig,
     device: Device,
 }
@@ -129,34 +321,74 @@ impl SurrogateProvider {
     pub fn load(model_dir: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
         let dir = model_dir.as_ref();
         let config_path = dir.join("model_config.json");
-        let weights_path = dir.join("model.safetensors");
 
         if !config_path.exists() {
             return Err(format!("Model config not found at {}", config_path.display()).into());
         }
-        if !weights_path.exists() {
-            return Err(format!("Model weights not found at {}", weights_path.display()).into());
-        }
 
         let config_json = fs::read_to_string(config_path)?;
         let config: SurrogateConfig = serde_json::from_str(&config_json)?;
+        if config.ensemble_size == 0 {
+            return Err("Model config declares an empty ensemble".into());
+        }
 
         let device = Device::Cpu;
-        let mut varmap = VarMap::new();
-        varmap.load(&weights_path)?;
-        let vs = VarBuilder::from_varmap(&varmap, DType::F32, &device);
-        let model = SurrogateModel::new(vs)?;
+        let mut models = Vec::with_capacity(config.ensemble_size);
+        for member in 0..config.ensemble_size {
+            let weights_path = dir.join(member_weights_filename(member));
+            if !weights_path.exists() {
+                return Err(
+                    format!("Model weights not found at {}", weights_path.display()).into(),
+                );
+            }
+            let mut varmap = VarMap::new();
+            let vs = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+            let model = SurrogateModel::new(vs, &config.architecture)?;
+            varmap.load(&weights_path)?;
+            models.push(model);
+        }
 
         Ok(Self {
-            model,
+            models,
             config,
             device,
         })
     }
 
+    /// Override the stored spread threshold; `None` disables rejection.
+    #[must_use]
+    pub fn with_max_relative_uncertainty(mut self, threshold: Option<f64>) -> Self {
+        self.config.max_relative_uncertainty = threshold;
+        self
+    }
+
     pub fn config(&self) -> &SurrogateConfig {
         &self.config
     }
+
+    /// Physical `[F₂, FL, xF₃]` predicted by one ensemble member.
+    fn predict(
+        &self,
+        model: &SurrogateModel,
+        input: &Tensor,
+    ) -> Result<[f64; 3], StructureFunctionProviderError> {
+        let output_tensor = model
+            .forward(input)
+            .map_err(|e| StructureFunctionProviderError::EvaluationFailed(e.to_string()))?;
+
+        let outputs = output_tensor
+            .to_vec2::<f32>()
+            .map_err(|e| StructureFunctionProviderError::EvaluationFailed(e.to_string()))?[0]
+            .clone();
+
+        // Outputs are the normalized targets of SurrogateConstraints::targets;
+        // F2 = 10^output is always positive.
+        let mut denormalized = [0.0; 3];
+        for (i, value) in denormalized.iter_mut().enumerate() {
+            *value = f64::from(outputs[i] * self.config.target_std[i] + self.config.target_mean[i]);
+        }
+        Ok(self.config.constraints.structure_functions(denormalized))
+    }
 }
 
 impl StructureFunctionProvider for SurrogateProvider {
@@ -200,32 +432,61 @@ impl StructureFunctionProvider for SurrogateProvider {
         let input_tensor = Tensor::from_vec(input_norm.to_vec(), (1, 4), &self.device)
             .map_err(|e| StructureFunctionProviderError::EvaluationFailed(e.to_string()))?;
 
-        let output_tensor = self
-            .model
-            .forward(&input_tensor)
-            .map_err(|e| StructureFunctionProviderError::EvaluationFailed(e.to_string()))?;
-
-        let outputs = output_tensor
-            .to_vec2::<f32>()
-            .map_err(|e| StructureFunctionProviderError::EvaluationFailed(e.to_string()))?[0]
-            .clone();
-
-        // Outputs are: [log10(F2), FL, xF3] (normalized)
-        let f2_log_norm = outputs[0];
-        let fl_norm = outputs[1];
-        let xf3_norm = outputs[2];
-
-        let f2_log = f2_log_norm * self.config.target_std[0] + self.config.target_mean[0];
-        let fl = fl_norm * self.config.target_std[1] + self.config.target_mean[1];
-        let xf3 = xf3_norm * self.config.target_std[2] + self.config.target_mean[2];
-
-        // Ensure non-negativity for F2 (since it's 10^f2_log it's always positive)
-        let f2 = 10_f64.powf(f2_log as f64);
+        let predictions = self
+            .models
+            .iter()
+            .map(|model| self.predict(model, &input_tensor))
+            .collect::<Result<Vec<_>, _>>()?;
+        let members = predictions.len() as f64;
+        let mut mean = [0.0; 3];
+        for prediction in &predictions {
+            for (sum, value) in mean.iter_mut().zip(prediction) {
+                *sum += value / members;
+            }
+        }
+        let [f2, fl, xf3] = mean;
+
+        let uncertainty = if predictions.len() > 1 {
+            let mut variance = [0.0; 3];
+            for prediction in &predictions {
+                for ((sum, value), mean) in variance.iter_mut().zip(prediction).zip(mean) {
+                    *sum += (value - mean).powi(2) / (members - 1.0);
+                }
+            }
+            let [f2_spread, fl_spread, xf3_spread] = variance.map(f64::sqrt);
+
+            if let Some(threshold) = self.config.max_relative_uncertainty {
+                for (quantity, spread) in
+                    [("F2", f2_spread), ("FL", fl_spread), ("xF3", xf3_spread)]
+                {
+                    let relative_spread = spread / f2;
+                    if relative_spread > threshold {
+                        return Err(StructureFunctionProviderError::UncertaintyTooLarge {
+                            x: request.x,
+                            q2: request.q2,
+                            quantity,
+                            relative_spread,
+                            threshold,
+                        });
+                    }
+                }
+            }
+
+            Some(StructureFunctionUncertainty {
+                f2: f2_spread,
+                fl: fl_spread,
+                xf3: xf3_spread,
+                members: predictions.len(),
+            })
+        } else {
+            None
+        };
 
         Ok(StructureFunctionResult {
             f2,
-            fl: fl as f64,
-            xf3: xf3 as f64,
+            fl,
+            xf3,
+            uncertainty,
             metadata: StructureFunctionMetadata {
                 backend: StructureFunctionBackend::Surrogate,
                 apfelxx_version: None,
@@ -253,3 +514,117 @@ impl StructureFunctionProvider for SurrogateProvider {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_model(dir: &Path, ensemble_size: usize) -> serde_json::Value {
+        let device = Device::Cpu;
+        for member in 0..ensemble_size {
+            let varmap = VarMap::new();
+            let vs = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+            SurrogateModel::new(vs, &SurrogateArchitecture::default()).unwrap();
+            varmap
+                .save(dir.join(member_weights_filename(member)))
+                .unwrap();
+        }
+        serde_json::json!({
+            "source_backend": "apfel",
+            "pdf_set": "CT18NLO",
+            "pdf_member": 0,
+            "order": "NLO",
+            "x_min": 1e-4,
+            "x_max": 0.5,
+            "q2_min": 4.0,
+            "q2_max": 1000.0,
+            "mu_f_ratio_min": 0.5,
+            "mu_f_ratio_max": 2.0,
+            "mu_r_ratio_min": 0.5,
+            "mu_r_ratio_max": 2.0,
+            "input_mean": [0.0, 0.0, 1.0, 1.0],
+            "input_std": [1.0, 1.0, 1.0, 1.0],
+            "target_mean": [0.0, 0.1, 0.0],
+            "target_std": [0.2, 0.1, 0.1],
+            "validation_mse": 0.0,
+            "test_max_rel_error": 0.0
+        })
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("quark_sim_surrogate_{name}_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn request() -> StructureFunctionRequest {
+        StructureFunctionRequest::electromagnetic_nc(
+            0.01,
+            50.0,
+            PerturbativeOrder::Nlo,
+            "CT18NLO",
+            0,
+        )
+    }
+
+    #[test]
+    fn single_model_config_without_ensemble_fields_still_loads() {
+        let dir = temp_dir("single");
+        let config = write_model(&dir, 1);
+        fs::write(dir.join("model_config.json"), config.to_string()).unwrap();
+
+        let provider = SurrogateProvider::load(&dir).unwrap();
+        assert_eq!(provider.config().ensemble_size, 1);
+        let result = provider.evaluate(&request()).unwrap();
+        assert!(result.f2 > 0.0);
+        assert_eq!(result.uncertainty, None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn ensemble_reports_spread_and_enforces_threshold() {
+        let dir = temp_dir("ensemble");
+        let mut config = write_model(&dir, 3);
+        config["ensemble_size"] = 3.into();
+        fs::write(dir.join("model_config.json"), config.to_string()).unwrap();
+
+        let provider = SurrogateProvider::load(&dir).unwrap();
+        let result = provider.evaluate(&request()).unwrap();
+        let uncertainty = result.uncertainty.unwrap();
+        assert_eq!(uncertainty.members, 3);
+        assert!(uncertainty.f2 > 0.0 && uncertainty.fl > 0.0);
+
+        let strict = provider.with_max_relative_uncertainty(Some(1e-12));
+        assert!(matches!(
+            strict.evaluate(&request()),
+            Err(StructureFunctionProviderError::UncertaintyTooLarge { .. })
+        ));
+
+        fs::remove_file(dir.join(member_weights_filename(2))).unwrap();
+        assert!(SurrogateProvider::load(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fl_constraint_keeps_fl_between_zero_and_f2() {
+        let constraints = SurrogateConstraints {
+            fl_bounded_by_f2: true,
+            zero_xf3: true,
+            q2_slope_weight: None,
+        };
+        let targets = constraints.targets(0.8, 0.2, 0.05);
+        assert_eq!(targets[2], 0.0);
+        let [f2, fl, xf3] = constraints.structure_functions(targets);
+        assert!((f2 - 0.8).abs() < 1e-12 && (fl - 0.2).abs() < 1e-12);
+        assert_eq!(xf3, 0.0);
+
+        for logit in [-50.0, -1.0, 0.0, 3.0, 50.0] {
+            let [f2, fl, _] = constraints.structure_functions([-0.3, logit, 1.0]);
+            assert!((0.0..=f2).contains(&fl));
+        }
+        // Unphysical source values are clamped rather than producing NaN.
+        assert!(constraints.targets(0.8, 1.5, 0.0)[1].is_finite());
+        assert!(constraints.targets(0.8, -0.1, 0.0)[1].is_finite());
+    }
+}
@@ -1,26 +1,43 @@
 //! Training pipeline for the neural network surrogate.
 //!
-//! Generates datasets by querying APFEL++, handles train/validation/test splits,
-//! and trains the `candle_core` surrogate model.
+//! Generates datasets by querying APFEL++ with a configurable sampler, handles
+//! train/validation/test splits, trains the `candle_core` surrogate model, and
+//! optionally refines the dataset where the trained model disagrees with its
+//! source backend. Datasets are persisted as CSV so an interrupted refinement
+//! run can resume without repeating backend evaluations.
 
+use std::cmp::Ordering;
 use std::error::Error;
 use std::fs;
 use std::path::Path;
 
 use candle_core::{DType, Device, Tensor};
 use candle_nn::{optim::AdamW, Optimizer, VarBuilder, VarMap};
-use rand::seq::SliceRandom;
 use rand::rngs::StdRng;
-use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
-use super::apfel::ApfelStructureFunctionProvider;
+use super::sampling::{linear_uniform, log_uniform, SamplingMethod};
 use super::structure_function_provider::{
     PerturbativeOrder, StructureFunctionProvider, StructureFunctionRequest,
 };
-use super::surrogate::{SurrogateConfig, SurrogateModel};
+use super::surrogate::{
+    member_weights_filename, SurrogateArchitecture, SurrogateConfig, SurrogateConstraints,
+    SurrogateModel, SurrogateProvider,
+};
+use super::surrogate_validation::{
+    validate_surrogate, SurrogateValidationConfig, SurrogateValidationRow,
+};
+
+/// File name of the persisted training dataset inside a model directory.
+pub const DATASET_FILENAME: &str = "dataset.csv";
+
+/// File name of the per-round refinement record inside a model directory.
+pub const REFINEMENT_HISTORY_FILENAME: &str = "refinement_history.json";
 
 /// Dataset row representing one kinematic point and its target values.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SurrogateDataPoint {
     // Inputs
     pub x: f64,
@@ -31,68 +48,190 @@ pub struct SurrogateDataPoint {
     pub f2: f64,
     pub fl: f64,
     pub xf3: f64,
+    /// Source-backend `∂F₂/∂ln Q²`, present when the slope penalty is used.
+    #[serde(default)]
+    pub df2_dlnq2: Option<f64>,
+}
+
+/// Kinematic and scale ranges covered by a generated dataset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatasetDomain {
+    pub x_min: f64,
+    pub x_max: f64,
+    /// Range of Q² in GeV².
+    pub q2_min: f64,
+    pub q2_max: f64,
+    pub mu_f_ratio_min: f64,
+    pub mu_f_ratio_max: f64,
+    pub mu_r_ratio_min: f64,
+    pub mu_r_ratio_max: f64,
+}
+
+impl Default for DatasetDomain {
+    fn default() -> Self {
+        Self {
+            x_min: 1e-5,
+            x_max: 0.8,
+            q2_min: 3.5,
+            q2_max: 10000.0,
+            mu_f_ratio_min: 0.5,
+            mu_f_ratio_max: 2.0,
+            mu_r_ratio_min: 0.5,
+            mu_r_ratio_max: 2.0,
+        }
+    }
+}
+
+/// Placement of training points inside a [`DatasetDomain`].
+///
+/// The grid is logarithmic in x, Q² and both scale ratios. Sobol and
+/// Latin-hypercube points are logarithmic in x and Q² and linear in the scale
+/// ratios, matching the surrogate validation sampler.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum DatasetSampler {
+    Grid {
+        x_points: usize,
+        q2_points: usize,
+        scale_points: usize,
+    },
+    Sobol {
+        points: usize,
+        seed: u64,
+    },
+    LatinHypercube {
+        points: usize,
+        seed: u64,
+    },
+}
+
+impl Default for DatasetSampler {
+    fn default() -> Self {
+        Self::Grid {
+            x_points: 5,
+            q2_points: 5,
+            scale_points: 3,
+        }
+    }
+}
+
+impl DatasetSampler {
+    /// Kinematic points as `[x, Q², μ_F/Q, μ_R/Q]`.
+    pub fn kinematic_points(
+        &self,
+        domain: &DatasetDomain,
+    ) -> Result<Vec<[f64; 4]>, Box<dyn Error>> {
+        let (method, count, seed) = match *self {
+            Self::Grid {
+                x_points,
+                q2_points,
+                scale_points,
+            } => {
+                if x_points == 0 || q2_points == 0 || scale_points == 0 {
+                    return Err("dataset grid dimensions must be positive".into());
+                }
+                let x_vals = log_space(domain.x_min, domain.x_max, x_points);
+                let q2_vals = log_space(domain.q2_min, domain.q2_max, q2_points);
+                let mu_f_ratios =
+                    log_space(domain.mu_f_ratio_min, domain.mu_f_ratio_max, scale_points);
+                let mu_r_ratios =
+                    log_space(domain.mu_r_ratio_min, domain.mu_r_ratio_max, scale_points);
+
+                let mut points = Vec::with_capacity(
+                    x_vals.len() * q2_vals.len() * mu_f_ratios.len() * mu_r_ratios.len(),
+                );
+                for &x in &x_vals {
+                    for &q2 in &q2_vals {
+                        for &mu_f in &mu_f_ratios {
+                            for &mu_r in &mu_r_ratios {
+                                points.push([x, q2, mu_f, mu_r]);
+                            }
+                        }
+                    }
+                }
+                return Ok(points);
+            }
+            Self::Sobol { points, seed } => (SamplingMethod::Sobol, points, seed),
+            Self::LatinHypercube { points, seed } => (SamplingMethod::LatinHypercube, points, seed),
+        };
+
+        Ok(method
+            .unit_points(count, 4, seed)?
+            .into_iter()
+            .map(|unit| {
+                [
+                    log_uniform(unit[0], domain.x_min, domain.x_max),
+                    log_uniform(unit[1], domain.q2_min, domain.q2_max),
+                    linear_uniform(unit[2], domain.mu_f_ratio_min, domain.mu_f_ratio_max),
+                    linear_uniform(unit[3], domain.mu_r_ratio_min, domain.mu_r_ratio_max),
+                ]
+            })
+            .collect())
+    }
 }
 
-/// Generate a dataset over a grid using APFEL++.
+/// Generate a dataset by evaluating `provider` (normally APFEL++) at the
+/// points chosen by `sampler`.
 pub fn generate_dataset(
-    provider: &ApfelStructureFunctionProvider,
+    provider: &dyn StructureFunctionProvider,
     pdf_set: &str,
     pdf_member: i32,
     order: PerturbativeOrder,
+    domain: &DatasetDomain,
+    sampler: &DatasetSampler,
 ) -> Result<Vec<SurrogateDataPoint>, Box<dyn Error>> {
     println!("Generating dataset using APFEL++...");
-    let mut data = Vec::new();
-
-    // Logarithmic grid for x and Q²
-    let x_vals = log_space(1e-5, 0.8, 5);
-    let q2_vals = log_space(3.5, 10000.0, 5);
-    let mu_f_ratios = [0.5, 1.0, 2.0];
-    let mu_r_ratios = [0.5, 1.0, 2.0];
-
-    let total = x_vals.len() * q2_vals.len() * mu_f_ratios.len() * mu_r_ratios.len();
-    let mut count = 0;
-
-    for &x in &x_vals {
-        for &q2 in &q2_vals {
-            for &mu_f in &mu_f_ratios {
-                for &mu_r in &mu_r_ratios {
-                    let mut req = StructureFunctionRequest::electromagnetic_nc(
-                        x, q2, order, pdf_set, pdf_member,
-                    );
-                    req.mu_f_over_q = mu_f;
-                    req.mu_r_over_q = mu_r;
-
-                    match provider.evaluate(&req) {
-                        Ok(res) => {
-                            data.push(SurrogateDataPoint {
-                                x,
-                                q2,
-                                mu_f_ratio: mu_f,
-                                mu_r_ratio: mu_r,
-                                f2: res.f2,
-                                fl: res.fl,
-                                xf3: res.xf3,
-                            });
-                        }
-                        Err(e) => {
-                            eprintln!("APFEL++ error at x={x}, Q2={q2}: {e}");
-                        }
-                    }
+    let points = sampler.kinematic_points(domain)?;
+    let data = evaluate_points(provider, &points, pdf_set, pdf_member, order);
+    println!("Dataset generated with {} valid points.", data.len());
+    Ok(data)
+}
 
-                    count += 1;
-                    if count % 1000 == 0 {
-                        println!("  Generated {}/{} points...", count, total);
-                    }
-                }
+fn evaluate_points(
+    provider: &dyn StructureFunctionProvider,
+    points: &[[f64; 4]],
+    pdf_set: &str,
+    pdf_member: i32,
+    order: PerturbativeOrder,
+) -> Vec<SurrogateDataPoint> {
+    let mut data = Vec::with_capacity(points.len());
+    let total = points.len();
+
+    for (count, &[x, q2, mu_f, mu_r]) in points.iter().enumerate() {
+        let mut req =
+            StructureFunctionRequest::electromagnetic_nc(x, q2, order, pdf_set, pdf_member);
+        req.mu_f_over_q = mu_f;
+        req.mu_r_over_q = mu_r;
+
+        match provider.evaluate(&req) {
+            Ok(res) => {
+                data.push(SurrogateDataPoint {
+                    x,
+                    q2,
+                    mu_f_ratio: mu_f,
+                    mu_r_ratio: mu_r,
+                    f2: res.f2,
+                    fl: res.fl,
+                    xf3: res.xf3,
+                    df2_dlnq2: None,
+                });
+            }
+            Err(e) => {
+                eprintln!("APFEL++ error at x={x}, Q2={q2}: {e}");
             }
         }
-    }
 
-    println!("Dataset generated with {} valid points.", data.len());
-    Ok(data)
+        if (count + 1) % 1000 == 0 {
+            println!("  Generated {}/{} points...", count + 1, total);
+        }
+    }
+    data
 }
 
 fn log_space(start: f64, end: f64, n: usize) -> Vec<f64> {
+    if n == 1 {
+        return vec![(start * end).sqrt()];
+    }
     let log_start = start.log10();
     let log_end = end.log10();
     let step = (log_end - log_start) / (n as f64 - 1.0);
@@ -101,6 +240,411 @@ fn log_space(start: f64, end: f64, n: usize) -> Vec<f64> {
         .collect()
 }
 
+/// Step in ln Q² of the central difference behind [`attach_q2_slopes`].
+pub const Q2_SLOPE_STEP: f64 = 0.05;
+
+/// Evaluate the source backend's `∂F₂/∂ln Q²` at every point by a central
+/// difference in ln Q², dropping points where either neighbour fails.
+pub fn attach_q2_slopes(
+    provider: &dyn StructureFunctionProvider,
+    data: Vec<SurrogateDataPoint>,
+    pdf_set: &str,
+    pdf_member: i32,
+    order: PerturbativeOrder,
+) -> Vec<SurrogateDataPoint> {
+    let evaluate_f2 = |point: &SurrogateDataPoint, q2: f64| {
+        let mut req =
+            StructureFunctionRequest::electromagnetic_nc(point.x, q2, order, pdf_set, pdf_member);
+        req.mu_f_over_q = point.mu_f_ratio;
+        req.mu_r_over_q = point.mu_r_ratio;
+        provider.evaluate(&req).map(|result| result.f2)
+    };
+
+    let mut sloped = Vec::with_capacity(data.len());
+    for mut point in data {
+        if point.df2_dlnq2.is_some() {
+            sloped.push(point);
+            continue;
+        }
+        let step = Q2_SLOPE_STEP.exp();
+        match (
+            evaluate_f2(&point, point.q2 * step),
+            evaluate_f2(&point, point.q2 / step),
+        ) {
+            (Ok(upper), Ok(lower)) => {
+                point.df2_dlnq2 = Some((upper - lower) / (2.0 * Q2_SLOPE_STEP));
+                sloped.push(point);
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!("APFEL++ slope error at x={}, Q2={}: {e}", point.x, point.q2);
+            }
+        }
+    }
+    sloped
+}
+
+/// Write a dataset as CSV, replacing any previous file only once the new
+/// content is complete.
+pub fn save_dataset(
+    path: impl AsRef<Path>,
+    data: &[SurrogateDataPoint],
+) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    let temporary = path.with_extension("csv.tmp");
+    let mut writer = csv::Writer::from_path(&temporary)?;
+    for point in data {
+        writer.serialize(point)?;
+    }
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+/// Read a dataset previously written by [`save_dataset`].
+pub fn load_dataset(path: impl AsRef<Path>) -> Result<Vec<SurrogateDataPoint>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_path(path.as_ref())?;
+    let data = reader
+        .deserialize()
+        .collect::<Result<Vec<SurrogateDataPoint>, _>>()?;
+    Ok(data)
+}
+
+/// Stopping rule and budget for [`refine_surrogate`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefinementConfig {
+    /// Stop once the maximum held-out F₂ relative error is at or below this.
+    pub target_f2_relative_error: f64,
+    /// Total number of train-and-validate rounds, including resumed ones.
+    pub max_rounds: usize,
+    /// Held-out points compared with the source backend in every round.
+    pub validation_samples: usize,
+    /// Maximum number of points with the largest F₂ error added to the
+    /// dataset per round.
+    pub points_per_round: usize,
+    pub seed: u64,
+}
+
+impl Default for RefinementConfig {
+    fn default() -> Self {
+        Self {
+            target_f2_relative_error: 0.01,
+            max_rounds: 5,
+            validation_samples: 500,
+            points_per_round: 50,
+            seed: 42,
+        }
+    }
+}
+
+/// Outcome of one refinement round.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefinementRound {
+    pub round: usize,
+    pub dataset_size: usize,
+    pub validation_points: usize,
+    pub f2_p95_relative_error: Option<f64>,
+    pub f2_max_relative_error: Option<f64>,
+    pub added_points: usize,
+    pub converged: bool,
+}
+
+/// Read the refinement history of an earlier run, or an empty history when
+/// the directory has none.
+pub fn load_refinement_history(
+    output_dir: impl AsRef<Path>,
+) -> Result<Vec<RefinementRound>, Box<dyn Error>> {
+    let path = output_dir.as_ref().join(REFINEMENT_HISTORY_FILENAME);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// The held-out points whose F₂ relative error exceeds the target, worst
+/// first and at most `points_per_round` of them.
+///
+/// Points are ranked by the F₂ error the stopping rule tests, not by
+/// [`SurrogateValidationRow::worst_error`]: a point that is off only in F_L
+/// or xF₃ would not bring the next round closer to the target.
+fn refinement_additions(
+    rows: &[SurrogateValidationRow],
+    config: &RefinementConfig,
+) -> Vec<SurrogateDataPoint> {
+    let mut candidates: Vec<(f64, &SurrogateValidationRow)> = rows
+        .iter()
+        .filter_map(|row| Some((row.f2_relative_error?, row)))
+        .filter(|(error, _)| *error > config.target_f2_relative_error)
+        .collect();
+    candidates.sort_by(|left, right| right.0.partial_cmp(&left.0).unwrap_or(Ordering::Equal));
+    candidates
+        .into_iter()
+        .take(config.points_per_round)
+        .map(|(_, row)| SurrogateDataPoint {
+            x: row.x,
+            q2: row.q2,
+            mu_f_ratio: row.mu_f_over_q,
+            mu_r_ratio: row.mu_r_over_q,
+            f2: row.reference_f2,
+            fl: row.reference_fl,
+            xf3: row.reference_xf3,
+            df2_dlnq2: None,
+        })
+        .collect()
+}
+
+/// Train, validate against `reference`, and add the worst held-out points
+/// until the F₂ target is met or the round budget is spent.
+///
+/// Each round uses a fresh Sobol validation sample. Points it adds were
+/// already evaluated by the reference, so refinement costs no extra backend
+/// calls beyond validation. The dataset and history are written to
+/// `output_dir` after every round; passing them back in resumes the run.
+#[allow(clippy::too_many_arguments)]
+pub fn refine_surrogate(
+    reference: &dyn StructureFunctionProvider,
+    mut data: Vec<SurrogateDataPoint>,
+    mut history: Vec<RefinementRound>,
+    output_dir: impl AsRef<Path>,
+    pdf_set: String,
+    pdf_member: i32,
+    order: PerturbativeOrder,
+    training: &SurrogateTrainingConfig,
+    config: &RefinementConfig,
+) -> Result<Vec<RefinementRound>, Box<dyn Error>> {
+    let dir = output_dir.as_ref();
+    fs::create_dir_all(dir)?;
+    if config.target_f2_relative_error.is_nan() || config.target_f2_relative_error <= 0.0 {
+        return Err("refinement target relative error must be positive".into());
+    }
+    if history.last().is_some_and(|round| round.converged) {
+        println!("Refinement already converged; nothing to resume.");
+        return Ok(history);
+    }
+
+    for round in history.len()..config.max_rounds {
+        save_dataset(dir.join(DATASET_FILENAME), &data)?;
+        println!("\nRefinement round {round}: {} training points", data.len());
+        train_and_save_surrogate(
+            data.clone(),
+            dir,
+            pdf_set.clone(),
+            pdf_member,
+            order,
+            training,
+        )?;
+
+        let surrogate = SurrogateProvider::load(dir)?.with_max_relative_uncertainty(None);
+        let validation = SurrogateValidationConfig {
+            sample_count: config.validation_samples,
+            sampling: SamplingMethod::Sobol,
+            seed: config.seed.wrapping_add(round as u64),
+            worst_point_count: config.points_per_round,
+            ..SurrogateValidationConfig::default()
+        };
+        let report = validate_surrogate(&surrogate, reference, surrogate.config(), &validation)?;
+        let f2_errors = &report.statistics.f2_relative_error;
+        let converged = f2_errors
+            .maximum
+            .is_some_and(|maximum| maximum <= config.target_f2_relative_error);
+
+        let mut additions = Vec::new();
+        if !converged {
+            additions = refinement_additions(&report.rows, config);
+        }
+        if training.constraints.q2_slope_weight.is_some() {
+            additions = attach_q2_slopes(reference, additions, &pdf_set, pdf_member, order);
+        }
+        let added_points = additions.len();
+        data.extend(additions);
+
+        println!(
+            "Round {round}: held-out max F2 relative error {:.3e} (p95 {:.3e}), added {added_points} points",
+            f2_errors.maximum.unwrap_or(f64::NAN),
+            f2_errors.p95.unwrap_or(f64::NAN)
+        );
+        history.push(RefinementRound {
+            round,
+            dataset_size: data.len() - added_points,
+            validation_points: report.evaluated_points,
+            f2_p95_relative_error: f2_errors.p95,
+            f2_max_relative_error: f2_errors.maximum,
+            added_points,
+            converged,
+        });
+        // Persist additions too, so a resumed run trains on them first.
+        save_dataset(dir.join(DATASET_FILENAME), &data)?;
+        fs::write(
+            dir.join(REFINEMENT_HISTORY_FILENAME),
+            serde_json::to_string_pretty(&history)?,
+        )?;
+
+        if converged || added_points == 0 {
+            break;
+        }
+    }
+
+    if !history.last().is_some_and(|round| round.converged) {
+        println!(
+            "Target F2 relative error {} not reached after {} rounds.",
+            config.target_f2_relative_error,
+            history.len()
+        );
+    }
+    Ok(history)
+}
+
+/// File name of the training options recorded next to a trained model.
+pub const TRAINING_CONFIG_FILENAME: &str = "training_config.json";
+
+/// Learning rate as a function of the epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LearningRateSchedule {
+    Constant,
+    /// Multiply the rate by `factor` every `step_epochs` epochs.
+    Step {
+        step_epochs: usize,
+        factor: f64,
+    },
+    /// Cosine decay from the initial rate to `min_learning_rate` at the last
+    /// epoch.
+    Cosine {
+        min_learning_rate: f64,
+    },
+}
+
+impl LearningRateSchedule {
+    #[must_use]
+    pub fn learning_rate(&self, initial: f64, epoch: usize, epochs: usize) -> f64 {
+        match *self {
+            Self::Constant => initial,
+            Self::Step {
+                step_epochs,
+                factor,
+            } => initial * factor.powi((epoch / step_epochs.max(1)) as i32),
+            Self::Cosine { min_learning_rate } => {
+                let progress = epoch as f64 / epochs.saturating_sub(1).max(1) as f64;
+                min_learning_rate
+                    + 0.5
+                        * (initial - min_learning_rate)
+                        * (1.0 + (std::f64::consts::PI * progress).cos())
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match *self {
+            Self::Constant => Ok(()),
+            Self::Step {
+                step_epochs,
+                factor,
+            } => {
+                if step_epochs == 0 || !(factor.is_finite() && factor > 0.0) {
+                    return Err(
+                        "step schedule needs positive step_epochs and a positive factor".to_owned(),
+                    );
+                }
+                Ok(())
+            }
+            Self::Cosine { min_learning_rate } => {
+                if !(min_learning_rate.is_finite() && min_learning_rate >= 0.0) {
+                    return Err("cosine schedule needs a non-negative min_learning_rate".to_owned());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Network and optimiser options applied by [`train_and_save_surrogate`].
+///
+/// Every field has a default, so a training config file only needs the
+/// options it changes. The defaults reproduce the original fixed setup:
+/// a `128 → 64 → 32` ReLU network, full-batch AdamW at 10⁻³ for up to 2000
+/// epochs, validated every 50 epochs with a patience of 200.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurrogateTrainingConfig {
+    pub architecture: SurrogateArchitecture,
+    pub learning_rate: f64,
+    pub learning_rate_schedule: LearningRateSchedule,
+    pub epochs: usize,
+    /// Mini-batch size; `None` trains on the whole training split each epoch.
+    pub batch_size: Option<usize>,
+    /// Epochs between validation-loss checks and checkpoints.
+    pub validation_interval: usize,
+    /// Stop once the validation loss has not improved for this many epochs;
+    /// `None` always runs every epoch.
+    pub early_stopping_patience: Option<usize>,
+    /// Seeds the data split, bootstrap resampling, batch order and weight
+    /// initialisation.
+    pub seed: u64,
+    /// Number of independently trained networks. Values above one make the
+    /// provider report the member spread as the prediction uncertainty.
+    pub ensemble_size: usize,
+    /// Stored in the model config; see
+    /// [`SurrogateConfig::max_relative_uncertainty`].
+    pub max_relative_uncertainty: Option<f64>,
+    /// Physics constraints, recorded in the model config.
+    pub constraints: SurrogateConstraints,
+}
+
+impl Default for SurrogateTrainingConfig {
+    fn default() -> Self {
+        Self {
+            architecture: SurrogateArchitecture::default(),
+            learning_rate: 0.001,
+            learning_rate_schedule: LearningRateSchedule::Constant,
+            epochs: 2000,
+            batch_size: None,
+            validation_interval: 50,
+            early_stopping_patience: Some(200),
+            seed: 42,
+            ensemble_size: 1,
+            max_relative_uncertainty: None,
+            constraints: SurrogateConstraints::default(),
+        }
+    }
+}
+
+impl SurrogateTrainingConfig {
+    /// Read a JSON training config; omitted fields keep their defaults.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+        let config: Self = serde_json::from_str(&text)
+            .map_err(|error| format!("invalid training config {}: {error}", path.display()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.architecture.validate()?;
+        self.learning_rate_schedule.validate()?;
+        self.constraints.validate()?;
+        if !(self.learning_rate.is_finite() && self.learning_rate > 0.0) {
+            return Err("learning_rate must be finite and positive".to_owned());
+        }
+        if self.epochs == 0 || self.validation_interval == 0 || self.ensemble_size == 0 {
+            return Err(
+                "epochs, validation_interval and ensemble_size must be positive".to_owned(),
+            );
+        }
+        if self.batch_size == Some(0) || self.early_stopping_patience == Some(0) {
+            return Err("batch_size and early_stopping_patience must be positive".to_owned());
+        }
+        if self
+            .max_relative_uncertainty
+            .is_some_and(|threshold| !(threshold.is_finite() && threshold > 0.0))
+        {
+            return Err("max_relative_uncertainty must be finite and positive".to_owned());
+        }
+        Ok(())
+    }
+}
+
 /// Train the surrogate model and save it to the specified directory.
 pub fn train_and_save_surrogate(
     data: Vec<SurrogateDataPoint>,
@@ -108,29 +652,43 @@ pub fn train_and_save_surrogate(
     pdf_set: String,
     pdf_member: i32,
     order: PerturbativeOrder,
+    training: &SurrogateTrainingConfig,
 ) -> Result<(), Box<dyn Error>> {
     let dir = output_dir.as_ref();
     fs::create_dir_all(dir)?;
+    training.validate()?;
 
     let n_total = data.len();
     if n_total < 100 {
         return Err("Not enough data to train surrogate".into());
     }
+    let constraints = &training.constraints;
+    if constraints.q2_slope_weight.is_some() && data.iter().any(|pt| pt.df2_dlnq2.is_none()) {
+        return Err("the Q2 slope penalty needs df2_dlnq2 for every data point".into());
+    }
 
     // Determine domain bounds
     let mut x_min = f64::MAX;
     let mut x_max = f64::MIN;
     let mut q2_min = f64::MAX;
     let mut q2_max = f64::MIN;
+    let mut mu_f_ratio_min = f64::MAX;
+    let mut mu_f_ratio_max = f64::MIN;
+    let mut mu_r_ratio_min = f64::MAX;
+    let mut mu_r_ratio_max = f64::MIN;
     for pt in &data {
         x_min = x_min.min(pt.x);
         x_max = x_max.max(pt.x);
         q2_min = q2_min.min(pt.q2);
         q2_max = q2_max.max(pt.q2);
+        mu_f_ratio_min = mu_f_ratio_min.min(pt.mu_f_ratio);
+        mu_f_ratio_max = mu_f_ratio_max.max(pt.mu_f_ratio);
+        mu_r_ratio_min = mu_r_ratio_min.min(pt.mu_r_ratio);
+        mu_r_ratio_max = mu_r_ratio_max.max(pt.mu_r_ratio);
     }
 
     // Split data into train (70%), val (15%), test (15%)
-    let mut rng = StdRng::seed_from_u64(42);
+    let mut rng = StdRng::seed_from_u64(training.seed);
     let mut indices: Vec<usize> = (0..n_total).collect();
     indices.shuffle(&mut rng);
 
@@ -149,13 +707,8 @@ pub fn train_and_save_surrogate(
 
     for &idx in train_indices {
         let pt = &data[idx];
-        let inputs = [
-            pt.x.log10(),
-            pt.q2.log10(),
-            pt.mu_f_ratio,
-            pt.mu_r_ratio,
-        ];
-        let targets = [pt.f2.max(1e-10).log10(), pt.fl, pt.xf3];
+        let inputs = [pt.x.log10(), pt.q2.log10(), pt.mu_f_ratio, pt.mu_r_ratio];
+        let targets = constraints.targets(pt.f2, pt.fl, pt.xf3);
 
         for i in 0..4 {
             input_sum[i] += inputs[i];
@@ -186,77 +739,95 @@ pub fn train_and_save_surrogate(
         target_std[i] = var.max(1e-12).sqrt() as f32;
     }
 
-    // Setup device and model
     let device = Device::Cpu;
-    let mut varmap = VarMap::new();
-    let vs = VarBuilder::from_varmap(&varmap, DType::F32, &device);
-    let model = SurrogateModel::new(vs)?;
-
-    let mut optimizer = AdamW::new_lr(varmap.all_vars(), 0.001)?;
-
-    // Prepare tensors
-    let train_x = build_input_tensor(&data, train_indices, &input_mean, &input_std, &device)?;
-    let train_y = build_target_tensor(&data, train_indices, &target_mean, &target_std, &device)?;
     let val_x = build_input_tensor(&data, val_indices, &input_mean, &input_std, &device)?;
-    let val_y = build_target_tensor(&data, val_indices, &target_mean, &target_std, &device)?;
+    let val_y = build_target_tensor(
+        &data,
+        val_indices,
+        constraints,
+        &target_mean,
+        &target_std,
+        &device,
+    )?;
 
     println!("\nTraining surrogate...");
-    println!("Train set: {}, Val set: {}, Test set: {}", train_indices.len(), val_indices.len(), test_indices.len());
-
-    let epochs = 2000;
-    let mut best_val_loss = f32::MAX;
-    let mut best_epoch = 0;
-    let patience = 200;
-    let mut wait = 0;
-
-    for epoch in 0..epochs {
-        // Forward pass
-        let pred = model.forward(&train_x)?;
-        let loss = pred.sub(&train_y)?.sqr()?.mean_all()?;
-        
-        optimizer.backward_step(&loss)?;
+    println!(
+        "Train set: {}, Val set: {}, Test set: {}",
+        train_indices.len(),
+        val_indices.len(),
+        test_indices.len()
+    );
 
-        if epoch % 50 == 0 || epoch == epochs - 1 {
-            let val_pred = model.forward(&val_x)?;
-            let val_loss = val_pred.sub(&val_y)?.sqr()?.mean_all()?.to_vec0::<f32>()?;
-            let train_loss = loss.to_vec0::<f32>()?;
+    let loss_terms = LossTerms::new(constraints, input_std[1], target_std[0], &device)?;
 
-            println!("Epoch {epoch:4}: Train MSE = {train_loss:.4e}, Val MSE = {val_loss:.4e}");
+    let ensemble_size = training.ensemble_size;
+    let mut models = Vec::with_capacity(ensemble_size);
+    let mut validation_losses = Vec::with_capacity(ensemble_size);
+    for member in 0..ensemble_size {
+        // Ensemble members each see a bootstrap resample of the training
+        // split, so their disagreement grows where the data are sparse.
+        let member_indices: Vec<usize> = if ensemble_size == 1 {
+            train_indices.to_vec()
+        } else {
+            println!("\nEnsemble member {}/{ensemble_size}", member + 1);
+            let mut rng = StdRng::seed_from_u64(training.seed.wrapping_add(member as u64));
+            (0..train_indices.len())
+                .map(|_| train_indices[rng.gen_range(0..train_indices.len())])
+                .collect()
+        };
+        let member_data = MemberData {
+            train_x: build_input_tensor(&data, &member_indices, &input_mean, &input_std, &device)?,
+            train_y: build_target_tensor(
+                &data,
+                &member_indices,
+                constraints,
+                &target_mean,
+                &target_std,
+                &device,
+            )?,
+            train_slopes: match constraints.q2_slope_weight {
+                Some(_) => Some(build_slope_tensor(&data, &member_indices, &device)?),
+                None => None,
+            },
+        };
 
-            if val_loss < best_val_loss {
-                best_val_loss = val_loss;
-                best_epoch = epoch;
-                wait = 0;
-                varmap.save(dir.join("model.safetensors"))?;
-            } else {
-                wait += 50;
-                if wait >= patience {
-                    println!("Early stopping at epoch {epoch}. Best epoch was {best_epoch}.");
-                    break;
-                }
-            }
-        }
+        let (model, best_val_loss) = train_member(
+            training,
+            training.seed.wrapping_add(member as u64),
+            &member_data,
+            &loss_terms,
+            &val_x,
+            &val_y,
+            &dir.join(member_weights_filename(member)),
+            &device,
+        )?;
+        models.push(model);
+        validation_losses.push(best_val_loss);
     }
+    let best_val_loss = validation_losses.iter().sum::<f32>() / ensemble_size as f32;
 
-    // Load best model to evaluate test set
-    varmap.load(dir.join("model.safetensors"))?;
+    // Evaluate the ensemble mean on the test set
     let test_x = build_input_tensor(&data, test_indices, &input_mean, &input_std, &device)?;
-    let test_pred = model.forward(&test_x)?;
-    let test_pred_vec = test_pred.to_vec2::<f32>()?;
+    let mut test_f2 = vec![0.0f32; test_indices.len()];
+    for model in &models {
+        let test_pred_vec = model.forward(&test_x)?.to_vec2::<f32>()?;
+        for (sum, prediction) in test_f2.iter_mut().zip(&test_pred_vec) {
+            let f2_log = prediction[0] * target_std[0] + target_mean[0];
+            *sum += 10_f32.powf(f2_log) / ensemble_size as f32;
+        }
+    }
 
     let mut max_rel_error = 0.0f32;
-    for (i, &idx) in test_indices.iter().enumerate() {
-        let pt = &data[idx];
-        let f2_log_norm = test_pred_vec[i][0];
-        let f2_log = f2_log_norm * target_std[0] + target_mean[0];
-        let f2_pred = 10_f32.powf(f2_log);
-        
-        let target_f2 = pt.f2 as f32;
+    for (&f2_pred, &idx) in test_f2.iter().zip(test_indices) {
+        let target_f2 = data[idx].f2 as f32;
         let rel_err = (f2_pred - target_f2).abs() / target_f2.max(1e-12);
         max_rel_error = max_rel_error.max(rel_err);
     }
 
-    println!("Test Set Max Relative Error on F2: {:.2}%", max_rel_error * 100.0);
+    println!(
+        "Test Set Max Relative Error on F2: {:.2}%",
+        max_rel_error * 100.0
+    );
 
     // Save config
     let config = SurrogateConfig {
@@ -268,24 +839,228 @@ pub fn train_and_save_surrogate(
         x_max,
         q2_min,
         q2_max,
-        mu_f_ratio_min: 0.5,
-        mu_f_ratio_max: 2.0,
-        mu_r_ratio_min: 0.5,
-        mu_r_ratio_max: 2.0,
+        mu_f_ratio_min,
+        mu_f_ratio_max,
+        mu_r_ratio_min,
+        mu_r_ratio_max,
         input_mean: input_mean.to_vec(),
         input_std: input_std.to_vec(),
         target_mean: target_mean.to_vec(),
         target_std: target_std.to_vec(),
         validation_mse: best_val_loss,
         test_max_rel_error: max_rel_error,
+        ensemble_size,
+        max_relative_uncertainty: training.max_relative_uncertainty,
+        architecture: training.architecture.clone(),
+        constraints: constraints.clone(),
     };
 
     fs::write(
         dir.join("model_config.json"),
         serde_json::to_string_pretty(&config)?,
     )?;
+    fs::write(
+        dir.join(TRAINING_CONFIG_FILENAME),
+        serde_json::to_string_pretty(training)?,
+    )?;
 
-    println!("Surrogate successfully trained and saved to {}", dir.display());
+    println!(
+        "Surrogate successfully trained and saved to {}",
+        dir.display()
+    );
+    Ok(())
+}
+
+/// Shift in log10 Q² of the network central difference behind the slope
+/// penalty.
+const NETWORK_SLOPE_STEP: f64 = 0.02;
+
+/// Training rows of one ensemble member.
+struct MemberData {
+    train_x: Tensor,
+    train_y: Tensor,
+    /// Source-backend `∂ln F₂/∂ln Q²` per row, when the slope penalty is on.
+    train_slopes: Option<Tensor>,
+}
+
+/// Constraint-dependent parts of the loss, shared by all members.
+struct LossTerms {
+    /// Per-output weights; xF₃ has weight zero when it is fixed to zero.
+    channel_weights: Tensor,
+    /// Normalised input shift of [`NETWORK_SLOPE_STEP`] in log10 Q².
+    slope_shift: Tensor,
+    /// Converts the shifted log10 F₂ output difference to `∂ln F₂/∂ln Q²`.
+    slope_scale: f64,
+    slope_weight: f64,
+}
+
+impl LossTerms {
+    fn new(
+        constraints: &SurrogateConstraints,
+        q2_input_std: f32,
+        f2_target_std: f32,
+        device: &Device,
+    ) -> Result<Self, Box<dyn Error>> {
+        let xf3_weight = if constraints.zero_xf3 { 0.0f32 } else { 1.0 };
+        let shift = (NETWORK_SLOPE_STEP / f64::from(q2_input_std)) as f32;
+        Ok(Self {
+            channel_weights: Tensor::from_vec(vec![1.0f32, 1.0, xf3_weight], (1, 3), device)?,
+            slope_shift: Tensor::from_vec(vec![0.0f32, shift, 0.0, 0.0], (1, 4), device)?,
+            slope_scale: f64::from(f2_target_std) / (2.0 * NETWORK_SLOPE_STEP),
+            slope_weight: constraints.q2_slope_weight.unwrap_or(0.0),
+        })
+    }
+
+    fn loss(
+        &self,
+        model: &SurrogateModel,
+        x: &Tensor,
+        y: &Tensor,
+        slopes: Option<&Tensor>,
+    ) -> candle_core::Result<Tensor> {
+        let data_loss = model
+            .forward(x)?
+            .sub(y)?
+            .sqr()?
+            .broadcast_mul(&self.channel_weights)?
+            .mean_all()?;
+        let Some(slopes) = slopes else {
+            return Ok(data_loss);
+        };
+        let upper = model.forward(&x.broadcast_add(&self.slope_shift)?)?;
+        let lower = model.forward(&x.broadcast_sub(&self.slope_shift)?)?;
+        let slope = (upper.sub(&lower)?.narrow(1, 0, 1)?.squeeze(1)? * self.slope_scale)?;
+        let penalty = slope.sub(slopes)?.sqr()?.mean_all()?;
+        data_loss + (penalty * self.slope_weight)?
+    }
+}
+
+/// Train one network with early stopping, keeping the best weights at
+/// `weights_path`.
+#[allow(clippy::too_many_arguments)]
+fn train_member(
+    training: &SurrogateTrainingConfig,
+    seed: u64,
+    member_data: &MemberData,
+    loss_terms: &LossTerms,
+    val_x: &Tensor,
+    val_y: &Tensor,
+    weights_path: &Path,
+    device: &Device,
+) -> Result<(SurrogateModel, f32), Box<dyn Error>> {
+    let mut varmap = VarMap::new();
+    let vs = VarBuilder::from_varmap(&varmap, DType::F32, device);
+    let model = SurrogateModel::new(vs, &training.architecture)?;
+    seed_parameters(&varmap, seed, device)?;
+
+    let mut optimizer = AdamW::new_lr(varmap.all_vars(), training.learning_rate)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let MemberData {
+        train_x,
+        train_y,
+        train_slopes,
+    } = member_data;
+    let n_train = train_x.dims()[0];
+    let mut order: Vec<u32> = (0..n_train as u32).collect();
+
+    let epochs = training.epochs;
+    let interval = training.validation_interval;
+    let mut best_val_loss = f32::MAX;
+    let mut best_epoch = 0;
+    let mut wait = 0;
+
+    for epoch in 0..epochs {
+        optimizer.set_learning_rate(training.learning_rate_schedule.learning_rate(
+            training.learning_rate,
+            epoch,
+            epochs,
+        ));
+
+        let loss = match training.batch_size {
+            Some(batch_size) if batch_size < n_train => {
+                order.shuffle(&mut rng);
+                let mut total = 0.0f32;
+                for batch in order.chunks(batch_size) {
+                    let batch_indices = Tensor::from_slice(batch, batch.len(), device)?;
+                    let slopes = match train_slopes {
+                        Some(slopes) => Some(slopes.index_select(&batch_indices, 0)?),
+                        None => None,
+                    };
+                    let loss = loss_terms.loss(
+                        &model,
+                        &train_x.index_select(&batch_indices, 0)?,
+                        &train_y.index_select(&batch_indices, 0)?,
+                        slopes.as_ref(),
+                    )?;
+                    optimizer.backward_step(&loss)?;
+                    total += loss.to_vec0::<f32>()? * batch.len() as f32;
+                }
+                total / n_train as f32
+            }
+            _ => {
+                let loss = loss_terms.loss(&model, train_x, train_y, train_slopes.as_ref())?;
+                optimizer.backward_step(&loss)?;
+                loss.to_vec0::<f32>()?
+            }
+        };
+
+        if epoch % interval == 0 || epoch == epochs - 1 {
+            let val_loss = loss_terms
+                .loss(&model, val_x, val_y, None)?
+                .to_vec0::<f32>()?;
+
+            println!("Epoch {epoch:4}: Train loss = {loss:.4e}, Val MSE = {val_loss:.4e}");
+
+            if val_loss < best_val_loss {
+                best_val_loss = val_loss;
+                best_epoch = epoch;
+                wait = 0;
+                varmap.save(weights_path)?;
+            } else if let Some(patience) = training.early_stopping_patience {
+                wait += interval;
+                if wait >= patience {
+                    println!("Early stopping at epoch {epoch}. Best epoch was {best_epoch}.");
+                    break;
+                }
+            }
+        }
+    }
+
+    // Load best weights before the caller evaluates the test set
+    varmap.load(weights_path)?;
+    Ok((model, best_val_loss))
+}
+
+/// Draw every parameter from `seed`, because candle cannot seed its CPU
+/// generator. Weights are Kaiming-uniform and biases uniform in
+/// `±1/√fan_in`, matching the scale of `candle_nn::linear`.
+fn seed_parameters(varmap: &VarMap, seed: u64, device: &Device) -> Result<(), Box<dyn Error>> {
+    let data = varmap
+        .data()
+        .lock()
+        .map_err(|_| "surrogate parameter map lock poisoned")?;
+    let mut names: Vec<&String> = data.keys().collect();
+    names.sort();
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    for name in names {
+        let var = &data[name];
+        let bound = match var.dims() {
+            [_, fan_in] => (6.0 / *fan_in as f64).sqrt(),
+            _ => {
+                let weight_name = name.replace(".bias", ".weight");
+                let fan_in = data
+                    .get(&weight_name)
+                    .and_then(|weight| weight.dims().get(1).copied())
+                    .ok_or_else(|| format!("no weight matrix for surrogate parameter {name}"))?;
+                1.0 / (fan_in as f64).sqrt()
+            }
+        };
+        let values: Vec<f32> = (0..var.elem_count())
+            .map(|_| rng.gen_range(-bound..bound) as f32)
+            .collect();
+        var.set(&Tensor::from_vec(values, var.dims(), device)?)?;
+    }
     Ok(())
 }
 
@@ -315,6 +1090,7 @@ fn build_input_tensor(
 fn build_target_tensor(
     data: &[SurrogateDataPoint],
     indices: &[usize],
+    constraints: &SurrogateConstraints,
     mean: &[f32; 3],
     std: &[f32; 3],
     device: &Device,
@@ -322,14 +1098,351 @@ fn build_target_tensor(
     let mut flat = Vec::with_capacity(indices.len() * 3);
     for &idx in indices {
         let pt = &data[idx];
-        let targets = [
-            pt.f2.max(1e-10).log10() as f32,
-            pt.fl as f32,
-            pt.xf3 as f32,
-        ];
+        let targets = constraints.targets(pt.f2, pt.fl, pt.xf3);
         for i in 0..3 {
-            flat.push((targets[i] - mean[i]) / std[i]);
+            flat.push((targets[i] as f32 - mean[i]) / std[i]);
         }
     }
     Ok(Tensor::from_vec(flat, (indices.len(), 3), device)?)
 }
+
+fn build_slope_tensor(
+    data: &[SurrogateDataPoint],
+    indices: &[usize],
+    device: &Device,
+) -> Result<Tensor, Box<dyn Error>> {
+    let slopes = indices
+        .iter()
+        .map(|&idx| {
+            let pt = &data[idx];
+            let slope = pt.df2_dlnq2.ok_or("missing df2_dlnq2")?;
+            Ok((slope / pt.f2.max(1e-10)) as f32)
+        })
+        .collect::<Result<Vec<f32>, Box<dyn Error>>>()?;
+    Ok(Tensor::from_vec(slopes, indices.len(), device)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::structure_function_provider::{
+        DisProjectile, DisTarget, StructureFunctionBackend, StructureFunctionMetadata,
+        StructureFunctionProcess, StructureFunctionProviderError, StructureFunctionResult,
+        PHOTON_EXCHANGE_MODE,
+    };
+
+    struct LinearProvider;
+
+    impl StructureFunctionProvider for LinearProvider {
+        fn evaluate(
+            &self,
+            request: &StructureFunctionRequest,
+        ) -> Result<StructureFunctionResult, StructureFunctionProviderError> {
+            if request.x > 0.5 {
+                return Err(StructureFunctionProviderError::EvaluationFailed(
+                    "large x".to_owned(),
+                ));
+            }
+            Ok(StructureFunctionResult {
+                f2: 1.0 - request.x,
+                fl: 0.1 * request.mu_f_over_q,
+                xf3: 0.0,
+                uncertainty: None,
+                metadata: StructureFunctionMetadata {
+                    backend: StructureFunctionBackend::Apfel,
+                    apfelxx_version: None,
+                    lhapdf_version: None,
+                    pdf_set: request.pdf_set.clone(),
+                    pdf_member: request.pdf_member,
+                    pdf_order_qcd: 1,
+                    pdf_data_version: 1,
+                    order: request.order,
+                    process: StructureFunctionProcess::NcDis,
+                    projectile: DisProjectile::Electron,
+                    target: DisTarget::Proton,
+                    mu_f_over_q: request.mu_f_over_q,
+                    mu_r_over_q: request.mu_r_over_q,
+                    scheme: "test".to_owned(),
+                    electromagnetic_mode: PHOTON_EXCHANGE_MODE.to_owned(),
+                    os_arch: None,
+                    rust_version: None,
+                    git_commit: None,
+                    git_dirty: None,
+                    pythia_version: None,
+                    hepmc_version: None,
+                    python_env_hash: None,
+                },
+            })
+        }
+    }
+
+    #[test]
+    fn default_grid_keeps_the_original_point_layout() {
+        let points = DatasetSampler::default()
+            .kinematic_points(&DatasetDomain::default())
+            .unwrap();
+        assert_eq!(points.len(), 5 * 5 * 3 * 3);
+        for (value, expected) in points[0].iter().zip([1e-5, 3.5, 0.5, 0.5]) {
+            assert!((value / expected - 1.0).abs() < 1e-12);
+        }
+        assert!((points[2][3] - 2.0).abs() < 1e-12);
+        assert!((points[1][3] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn random_samplers_stay_inside_the_domain() {
+        let domain = DatasetDomain::default();
+        for sampler in [
+            DatasetSampler::Sobol {
+                points: 128,
+                seed: 3,
+            },
+            DatasetSampler::LatinHypercube {
+                points: 128,
+                seed: 3,
+            },
+        ] {
+            let points = sampler.kinematic_points(&domain).unwrap();
+            assert_eq!(points.len(), 128);
+            assert_eq!(points, sampler.kinematic_points(&domain).unwrap());
+            assert!(points.iter().all(|&[x, q2, mu_f, mu_r]| {
+                (domain.x_min..domain.x_max).contains(&x)
+                    && (domain.q2_min..domain.q2_max).contains(&q2)
+                    && (domain.mu_f_ratio_min..domain.mu_f_ratio_max).contains(&mu_f)
+                    && (domain.mu_r_ratio_min..domain.mu_r_ratio_max).contains(&mu_r)
+            }));
+        }
+        assert!(DatasetSampler::Grid {
+            x_points: 0,
+            q2_points: 5,
+            scale_points: 3,
+        }
+        .kinematic_points(&domain)
+        .is_err());
+    }
+
+    #[test]
+    fn generated_dataset_round_trips_through_csv() {
+        let sampler = DatasetSampler::Grid {
+            x_points: 4,
+            q2_points: 2,
+            scale_points: 2,
+        };
+        let data = generate_dataset(
+            &LinearProvider,
+            "CT18NLO",
+            0,
+            PerturbativeOrder::Nlo,
+            &DatasetDomain::default(),
+            &sampler,
+        )
+        .unwrap();
+        // The largest x node is rejected by the provider and skipped.
+        assert_eq!(data.len(), 3 * 2 * 2 * 2);
+        assert!(data.iter().all(|point| point.x <= 0.5));
+
+        let dir = std::env::temp_dir().join(format!(
+            "quark_sim_surrogate_dataset_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(DATASET_FILENAME);
+        save_dataset(&path, &data).unwrap();
+        let loaded = load_dataset(&path).unwrap();
+        assert_eq!(loaded, data);
+        assert!(load_refinement_history(&dir).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn learning_rate_schedules_decay_from_the_initial_rate() {
+        let step = LearningRateSchedule::Step {
+            step_epochs: 10,
+            factor: 0.5,
+        };
+        assert_eq!(step.learning_rate(0.1, 9, 100), 0.1);
+        assert_eq!(step.learning_rate(0.1, 25, 100), 0.025);
+
+        let cosine = LearningRateSchedule::Cosine {
+            min_learning_rate: 0.001,
+        };
+        assert!((cosine.learning_rate(0.1, 0, 101) - 0.1).abs() < 1e-12);
+        assert!((cosine.learning_rate(0.1, 50, 101) - 0.0505).abs() < 1e-12);
+        assert!((cosine.learning_rate(0.1, 100, 101) - 0.001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn seeded_training_is_reproducible_and_stores_the_architecture() {
+        let data = generate_dataset(
+            &LinearProvider,
+            "CT18NLO",
+            0,
+            PerturbativeOrder::Nlo,
+            &DatasetDomain {
+                x_max: 0.5,
+                ..DatasetDomain::default()
+            },
+            &DatasetSampler::Sobol {
+                points: 120,
+                seed: 1,
+            },
+        )
+        .unwrap();
+        let training = SurrogateTrainingConfig {
+            architecture: SurrogateArchitecture {
+                hidden_layers: vec![8, 8],
+                activation: crate::physics::surrogate::SurrogateActivation::Tanh,
+            },
+            epochs: 30,
+            batch_size: Some(16),
+            validation_interval: 10,
+            learning_rate_schedule: LearningRateSchedule::Cosine {
+                min_learning_rate: 1e-4,
+            },
+            ..SurrogateTrainingConfig::default()
+        };
+
+        let mut configs = Vec::new();
+        for run in 0..2 {
+            let dir = std::env::temp_dir().join(format!(
+                "quark_sim_surrogate_training_{run}_{}",
+                std::process::id()
+            ));
+            train_and_save_surrogate(
+                data.clone(),
+                &dir,
+                "CT18NLO".to_owned(),
+                0,
+                PerturbativeOrder::Nlo,
+                &training,
+            )
+            .unwrap();
+            let provider = SurrogateProvider::load(&dir).unwrap();
+            assert_eq!(provider.config().architecture, training.architecture);
+            let stored: SurrogateTrainingConfig = serde_json::from_str(
+                &fs::read_to_string(dir.join(TRAINING_CONFIG_FILENAME)).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(stored, training);
+            configs.push(provider.config().clone());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        assert_eq!(configs[0].validation_mse, configs[1].validation_mse);
+        assert_eq!(configs[0].test_max_rel_error, configs[1].test_max_rel_error);
+    }
+
+    #[test]
+    fn constrained_training_records_and_applies_constraints() {
+        let data = generate_dataset(
+            &LinearProvider,
+            "CT18NLO",
+            0,
+            PerturbativeOrder::Nlo,
+            &DatasetDomain {
+                x_max: 0.4,
+                ..DatasetDomain::default()
+            },
+            &DatasetSampler::LatinHypercube {
+                points: 120,
+                seed: 2,
+            },
+        )
+        .unwrap();
+        let data = attach_q2_slopes(&LinearProvider, data, "CT18NLO", 0, PerturbativeOrder::Nlo);
+        assert_eq!(data.len(), 120);
+        assert!(data.iter().all(|point| point.df2_dlnq2 == Some(0.0)));
+
+        let constraints = SurrogateConstraints {
+            fl_bounded_by_f2: true,
+            zero_xf3: true,
+            q2_slope_weight: Some(0.5),
+        };
+        let training = SurrogateTrainingConfig {
+            architecture: SurrogateArchitecture {
+                hidden_layers: vec![8],
+                ..SurrogateArchitecture::default()
+            },
+            epochs: 20,
+            validation_interval: 5,
+            constraints: constraints.clone(),
+            ..SurrogateTrainingConfig::default()
+        };
+        let dir = std::env::temp_dir().join(format!(
+            "quark_sim_surrogate_constraints_{}",
+            std::process::id()
+        ));
+        train_and_save_surrogate(
+            data.clone(),
+            &dir,
+            "CT18NLO".to_owned(),
+            0,
+            PerturbativeOrder::Nlo,
+            &training,
+        )
+        .unwrap();
+
+        let provider = SurrogateProvider::load(&dir).unwrap();
+        assert_eq!(provider.config().constraints, constraints);
+        for point in &data {
+            let mut request = StructureFunctionRequest::electromagnetic_nc(
+                point.x,
+                point.q2,
+                PerturbativeOrder::Nlo,
+                "CT18NLO",
+                0,
+            );
+            request.mu_f_over_q = point.mu_f_ratio;
+            request.mu_r_over_q = point.mu_r_ratio;
+            let result = provider.evaluate(&request).unwrap();
+            assert!((0.0..=result.f2).contains(&result.fl));
+            assert_eq!(result.xf3, 0.0);
+        }
+
+        let mut missing_slopes = data;
+        missing_slopes[0].df2_dlnq2 = None;
+        assert!(train_and_save_surrogate(
+            missing_slopes,
+            &dir,
+            "CT18NLO".to_owned(),
+            0,
+            PerturbativeOrder::Nlo,
+            &training,
+        )
+        .is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refinement_adds_the_points_with_the_largest_f2_error() {
+        let row = |x: f64, f2_error: f64, fl_error: f64| SurrogateValidationRow {
+            x,
+            q2: 10.0,
+            mu_f_over_q: 1.0,
+            mu_r_over_q: 1.0,
+            surrogate_f2: 1.0,
+            reference_f2: 1.0,
+            f2_relative_error: Some(f2_error),
+            surrogate_fl: 0.1,
+            reference_fl: 0.1,
+            fl_error_over_f2: Some(fl_error),
+            surrogate_xf3: 0.0,
+            reference_xf3: 0.0,
+            xf3_error_over_f2: None,
+        };
+        let rows = [
+            row(0.1, 0.001, 0.5),
+            row(0.2, 0.02, 0.0),
+            row(0.3, 0.05, 0.0),
+            row(0.4, 0.03, 0.0),
+        ];
+        let config = RefinementConfig {
+            points_per_round: 2,
+            ..RefinementConfig::default()
+        };
+        let added: Vec<f64> = refinement_additions(&rows, &config)
+            .iter()
+            .map(|point| point.x)
+            .collect();
+        assert_eq!(added, [0.3, 0.4]);
+    }
+}
@@ -0,0 +1,675 @@
+//! Accuracy report for a trained surrogate against its source backend.
+//!
+//! Points are drawn inside the domain stored in [`SurrogateConfig`] with a
+//! space-filling sampler, so they do not coincide with the training grid. Both
+//! providers are evaluated at every point. `F₂` errors are relative to the
+//! reference `F₂`; `F_L` and `xF₃` errors are divided by the reference `F₂`
+//! because either function may legitimately vanish.
+
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::sampling::{linear_uniform, log_uniform, SamplingError, SamplingMethod};
+use super::structure_function_provider::{
+    PerturbativeOrder, StructureFunctionProvider, StructureFunctionRequest,
+};
+use super::surrogate::SurrogateConfig;
+
+pub const F2_RELATIVE_ERROR_DEFINITION: &str =
+    "abs(surrogate_f2 - reference_f2) / abs(reference_f2)";
+pub const FL_ERROR_DEFINITION: &str = "abs(surrogate_fl - reference_fl) / abs(reference_f2)";
+pub const XF3_ERROR_DEFINITION: &str = "abs(surrogate_xf3 - reference_xf3) / abs(reference_f2)";
+pub const ZERO_REFERENCE_POLICY: &str = "null when reference_f2 == 0; no epsilon substitution";
+
+/// Sampling and binning choices for [`validate_surrogate`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurrogateValidationConfig {
+    pub sample_count: usize,
+    pub sampling: SamplingMethod,
+    pub seed: u64,
+    /// Number of logarithmic heat-map bins in `x`.
+    pub x_bins: usize,
+    /// Number of logarithmic heat-map bins in `Q²`.
+    pub q2_bins: usize,
+    /// Number of worst-case points kept in the report.
+    pub worst_point_count: usize,
+}
+
+impl Default for SurrogateValidationConfig {
+    fn default() -> Self {
+        Self {
+            sample_count: 2_000,
+            sampling: SamplingMethod::Sobol,
+            seed: 42,
+            x_bins: 8,
+            q2_bins: 8,
+            worst_point_count: 20,
+        }
+    }
+}
+
+impl SurrogateValidationConfig {
+    pub fn validate(&self) -> Result<(), SurrogateValidationError> {
+        for (field, value) in [
+            ("sample_count", self.sample_count),
+            ("x_bins", self.x_bins),
+            ("q2_bins", self.q2_bins),
+        ] {
+            if value == 0 {
+                return Err(SurrogateValidationError::InvalidConfig {
+                    field,
+                    value: value.to_string(),
+                    requirement: "a positive integer",
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One held-out point evaluated by both providers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurrogateValidationRow {
+    pub x: f64,
+    pub q2: f64,
+    pub mu_f_over_q: f64,
+    pub mu_r_over_q: f64,
+    pub surrogate_f2: f64,
+    pub reference_f2: f64,
+    pub f2_relative_error: Option<f64>,
+    pub surrogate_fl: f64,
+    pub reference_fl: f64,
+    pub fl_error_over_f2: Option<f64>,
+    pub surrogate_xf3: f64,
+    pub reference_xf3: f64,
+    pub xf3_error_over_f2: Option<f64>,
+}
+
+impl SurrogateValidationRow {
+    /// Largest of the three defined error measures, used to rank points.
+    #[must_use]
+    pub fn worst_error(&self) -> Option<f64> {
+        [
+            self.f2_relative_error,
+            self.fl_error_over_f2,
+            self.xf3_error_over_f2,
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(f64::max)
+    }
+}
+
+/// A sampled point at which one of the providers returned an error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurrogateValidationFailure {
+    pub x: f64,
+    pub q2: f64,
+    pub mu_f_over_q: f64,
+    pub mu_r_over_q: f64,
+    pub provider: String,
+    pub message: String,
+}
+
+/// Summary of one error measure over every point where it is defined.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorQuantiles {
+    pub count: usize,
+    pub mean: Option<f64>,
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p95: Option<f64>,
+    pub p99: Option<f64>,
+    pub maximum: Option<f64>,
+}
+
+impl ErrorQuantiles {
+    #[must_use]
+    pub fn from_values(values: impl IntoIterator<Item = f64>) -> Self {
+        let mut sorted: Vec<f64> = values.into_iter().collect();
+        sorted.sort_by(|left, right| left.partial_cmp(right).unwrap_or(Ordering::Equal));
+        let count = sorted.len();
+        let mean = (count > 0).then(|| sorted.iter().sum::<f64>() / count as f64);
+        Self {
+            count,
+            mean,
+            p50: quantile(&sorted, 0.50),
+            p90: quantile(&sorted, 0.90),
+            p95: quantile(&sorted, 0.95),
+            p99: quantile(&sorted, 0.99),
+            maximum: sorted.last().copied(),
+        }
+    }
+}
+
+/// Linearly interpolated quantile of already sorted values.
+fn quantile(sorted: &[f64], probability: f64) -> Option<f64> {
+    let last = sorted.len().checked_sub(1)?;
+    let position = probability * last as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let weight = position - lower as f64;
+    Some(sorted[lower] + weight * (sorted[upper] - sorted[lower]))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurrogateErrorStatistics {
+    pub f2_relative_error: ErrorQuantiles,
+    pub fl_error_over_f2: ErrorQuantiles,
+    pub xf3_error_over_f2: ErrorQuantiles,
+}
+
+/// One `(x, Q²)` region of the error heat map. Bin edges are inclusive below.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurrogateHeatMapCell {
+    pub x_low: f64,
+    pub x_high: f64,
+    pub q2_low: f64,
+    pub q2_high: f64,
+    pub count: usize,
+    pub f2_mean_relative_error: Option<f64>,
+    pub f2_max_relative_error: Option<f64>,
+    pub fl_max_error_over_f2: Option<f64>,
+    pub xf3_max_error_over_f2: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurrogateErrorDefinitions {
+    pub f2: String,
+    pub fl: String,
+    pub xf3: String,
+    pub zero_reference_policy: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurrogateValidationMetadata {
+    pub source_backend: String,
+    pub pdf_set: String,
+    pub pdf_member: i32,
+    pub order: PerturbativeOrder,
+    pub x_range: [f64; 2],
+    pub q2_range_gev2: [f64; 2],
+    pub mu_f_ratio_range: [f64; 2],
+    pub mu_r_ratio_range: [f64; 2],
+    pub config: SurrogateValidationConfig,
+    /// Values recorded by the training run, for comparison with this report.
+    pub stored_validation_mse: f32,
+    pub stored_test_max_rel_error: f32,
+    pub error_definitions: SurrogateErrorDefinitions,
+}
+
+/// Complete accuracy report. `rows` is written to CSV only; the JSON document
+/// keeps the aggregated statistics, heat map and worst-case points.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurrogateValidationReport {
+    pub metadata: SurrogateValidationMetadata,
+    pub evaluated_points: usize,
+    pub statistics: SurrogateErrorStatistics,
+    pub heat_map: Vec<SurrogateHeatMapCell>,
+    pub worst_points: Vec<SurrogateValidationRow>,
+    pub failures: Vec<SurrogateValidationFailure>,
+    #[serde(skip)]
+    pub rows: Vec<SurrogateValidationRow>,
+}
+
+#[derive(Debug)]
+pub enum SurrogateValidationError {
+    InvalidConfig {
+        field: &'static str,
+        value: String,
+        requirement: &'static str,
+    },
+    InvalidDomain {
+        field: &'static str,
+        minimum: f64,
+        maximum: f64,
+    },
+    Sampling(SamplingError),
+    NoSuccessfulPoints {
+        failures: usize,
+    },
+}
+
+impl fmt::Display for SurrogateValidationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidConfig {
+                field,
+                value,
+                requirement,
+            } => write!(
+                formatter,
+                "invalid surrogate validation {field}='{value}': expected {requirement}"
+            ),
+            Self::InvalidDomain {
+                field,
+                minimum,
+                maximum,
+            } => write!(
+                formatter,
+                "surrogate training domain for {field} is invalid: [{minimum}, {maximum}]"
+            ),
+            Self::Sampling(source) => write!(formatter, "{source}"),
+            Self::NoSuccessfulPoints { failures } => write!(
+                formatter,
+                "no validation point could be evaluated by both providers ({failures} failures)"
+            ),
+        }
+    }
+}
+
+impl Error for SurrogateValidationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Sampling(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<SamplingError> for SurrogateValidationError {
+    fn from(source: SamplingError) -> Self {
+        Self::Sampling(source)
+    }
+}
+
+/// Evaluate `surrogate` and `reference` on held-out points inside the stored
+/// training domain and aggregate the errors.
+///
+/// Per-point provider failures are recorded in the report instead of aborting
+/// the run; only a report without any successful point is an error.
+pub fn validate_surrogate(
+    surrogate: &dyn StructureFunctionProvider,
+    reference: &dyn StructureFunctionProvider,
+    model: &SurrogateConfig,
+    config: &SurrogateValidationConfig,
+) -> Result<SurrogateValidationReport, SurrogateValidationError> {
+    config.validate()?;
+    validate_domain(model)?;
+
+    let points = config
+        .sampling
+        .unit_points(config.sample_count, 4, config.seed)?;
+    let mut rows = Vec::with_capacity(points.len());
+    let mut failures = Vec::new();
+
+    for unit in points {
+        let mut request = StructureFunctionRequest::electromagnetic_nc(
+            log_uniform(unit[0], model.x_min, model.x_max),
+            log_uniform(unit[1], model.q2_min, model.q2_max),
+            model.order,
+            model.pdf_set.clone(),
+            model.pdf_member,
+        );
+        request.mu_f_over_q = linear_uniform(unit[2], model.mu_f_ratio_min, model.mu_f_ratio_max);
+        request.mu_r_over_q = linear_uniform(unit[3], model.mu_r_ratio_min, model.mu_r_ratio_max);
+
+        let failure = |provider: &str, message: String| SurrogateValidationFailure {
+            x: request.x,
+            q2: request.q2,
+            mu_f_over_q: request.mu_f_over_q,
+            mu_r_over_q: request.mu_r_over_q,
+            provider: provider.to_owned(),
+            message,
+        };
+        let predicted = match surrogate.evaluate(&request) {
+            Ok(result) => result,
+            Err(error) => {
+                failures.push(failure("surrogate", error.to_string()));
+                continue;
+            }
+        };
+        let expected = match reference.evaluate(&request) {
+            Ok(result) => result,
+            Err(error) => {
+                failures.push(failure(&model.source_backend, error.to_string()));
+                continue;
+            }
+        };
+
+        let scaled =
+            |difference: f64| (expected.f2 != 0.0).then(|| difference.abs() / expected.f2.abs());
+        rows.push(SurrogateValidationRow {
+            x: request.x,
+            q2: request.q2,
+            mu_f_over_q: request.mu_f_over_q,
+            mu_r_over_q: request.mu_r_over_q,
+            surrogate_f2: predicted.f2,
+            reference_f2: expected.f2,
+            f2_relative_error: scaled(predicted.f2 - expected.f2),
+            surrogate_fl: predicted.fl,
+            reference_fl: expected.fl,
+            fl_error_over_f2: scaled(predicted.fl - expected.fl),
+            surrogate_xf3: predicted.xf3,
+            reference_xf3: expected.xf3,
+            xf3_error_over_f2: scaled(predicted.xf3 - expected.xf3),
+        });
+    }
+
+    if rows.is_empty() {
+        return Err(SurrogateValidationError::NoSuccessfulPoints {
+            failures: failures.len(),
+        });
+    }
+
+    let statistics = SurrogateErrorStatistics {
+        f2_relative_error: ErrorQuantiles::from_values(
+            rows.iter().filter_map(|row| row.f2_relative_error),
+        ),
+        fl_error_over_f2: ErrorQuantiles::from_values(
+            rows.iter().filter_map(|row| row.fl_error_over_f2),
+        ),
+        xf3_error_over_f2: ErrorQuantiles::from_values(
+            rows.iter().filter_map(|row| row.xf3_error_over_f2),
+        ),
+    };
+    let heat_map = build_heat_map(&rows, model, config.x_bins, config.q2_bins);
+
+    let mut worst_points = rows.clone();
+    worst_points.sort_by(|left, right| {
+        right
+            .worst_error()
+            .partial_cmp(&left.worst_error())
+            .unwrap_or(Ordering::Equal)
+    });
+    worst_points.truncate(config.worst_point_count);
+
+    Ok(SurrogateValidationReport {
+        metadata: SurrogateValidationMetadata {
+            source_backend: model.source_backend.clone(),
+            pdf_set: model.pdf_set.clone(),
+            pdf_member: model.pdf_member,
+            order: model.order,
+            x_range: [model.x_min, model.x_max],
+            q2_range_gev2: [model.q2_min, model.q2_max],
+            mu_f_ratio_range: [model.mu_f_ratio_min, model.mu_f_ratio_max],
+            mu_r_ratio_range: [model.mu_r_ratio_min, model.mu_r_ratio_max],
+            config: config.clone(),
+            stored_validation_mse: model.validation_mse,
+            stored_test_max_rel_error: model.test_max_rel_error,
+            error_definitions: SurrogateErrorDefinitions {
+                f2: F2_RELATIVE_ERROR_DEFINITION.to_owned(),
+                fl: FL_ERROR_DEFINITION.to_owned(),
+                xf3: XF3_ERROR_DEFINITION.to_owned(),
+                zero_reference_policy: ZERO_REFERENCE_POLICY.to_owned(),
+            },
+        },
+        evaluated_points: rows.len(),
+        statistics,
+        heat_map,
+        worst_points,
+        failures,
+        rows,
+    })
+}
+
+fn validate_domain(model: &SurrogateConfig) -> Result<(), SurrogateValidationError> {
+    for (field, minimum, maximum, logarithmic) in [
+        ("x", model.x_min, model.x_max, true),
+        ("q2", model.q2_min, model.q2_max, true),
+        (
+            "mu_f_over_q",
+            model.mu_f_ratio_min,
+            model.mu_f_ratio_max,
+            false,
+        ),
+        (
+            "mu_r_over_q",
+            model.mu_r_ratio_min,
+            model.mu_r_ratio_max,
+            false,
+        ),
+    ] {
+        if !minimum.is_finite()
+            || !maximum.is_finite()
+            || maximum < minimum
+            || (logarithmic && minimum <= 0.0)
+        {
+            return Err(SurrogateValidationError::InvalidDomain {
+                field,
+                minimum,
+                maximum,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn build_heat_map(
+    rows: &[SurrogateValidationRow],
+    model: &SurrogateConfig,
+    x_bins: usize,
+    q2_bins: usize,
+) -> Vec<SurrogateHeatMapCell> {
+    let x_edges = log_edges(model.x_min, model.x_max, x_bins);
+    let q2_edges = log_edges(model.q2_min, model.q2_max, q2_bins);
+
+    let mut cells = Vec::with_capacity(x_bins * q2_bins);
+    for q2_index in 0..q2_bins {
+        for x_index in 0..x_bins {
+            let members: Vec<&SurrogateValidationRow> = rows
+                .iter()
+                .filter(|row| {
+                    bin_index(row.x, &x_edges) == x_index
+                        && bin_index(row.q2, &q2_edges) == q2_index
+                })
+                .collect();
+            let f2_errors: Vec<f64> = members
+                .iter()
+                .filter_map(|row| row.f2_relative_error)
+                .collect();
+            cells.push(SurrogateHeatMapCell {
+                x_low: x_edges[x_index],
+                x_high: x_edges[x_index + 1],
+                q2_low: q2_edges[q2_index],
+                q2_high: q2_edges[q2_index + 1],
+                count: members.len(),
+                f2_mean_relative_error: (!f2_errors.is_empty())
+                    .then(|| f2_errors.iter().sum::<f64>() / f2_errors.len() as f64),
+                f2_max_relative_error: f2_errors.iter().copied().reduce(f64::max),
+                fl_max_error_over_f2: members
+                    .iter()
+                    .filter_map(|row| row.fl_error_over_f2)
+                    .reduce(f64::max),
+                xf3_max_error_over_f2: members
+                    .iter()
+                    .filter_map(|row| row.xf3_error_over_f2)
+                    .reduce(f64::max),
+            });
+        }
+    }
+    cells
+}
+
+fn log_edges(minimum: f64, maximum: f64, bins: usize) -> Vec<f64> {
+    (0..=bins)
+        .map(|index| log_uniform(index as f64 / bins as f64, minimum, maximum))
+        .collect()
+}
+
+/// Index of the logarithmic bin containing `value`; the upper domain edge
+/// belongs to the last bin.
+fn bin_index(value: f64, edges: &[f64]) -> usize {
+    let bins = edges.len() - 1;
+    edges[1..bins]
+        .iter()
+        .take_while(|&&edge| value >= edge)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::structure_function_provider::{
+        DisProjectile, DisTarget, StructureFunctionBackend, StructureFunctionMetadata,
+        StructureFunctionProcess, StructureFunctionProviderError, StructureFunctionResult,
+        PHOTON_EXCHANGE_MODE,
+    };
+
+    struct ScaledProvider {
+        f2_scale: f64,
+        fl_offset: f64,
+    }
+
+    impl StructureFunctionProvider for ScaledProvider {
+        fn evaluate(
+            &self,
+            request: &StructureFunctionRequest,
+        ) -> Result<StructureFunctionResult, StructureFunctionProviderError> {
+            if request.x > 0.5 {
+                return Err(StructureFunctionProviderError::EvaluationFailed(
+                    "large x".to_owned(),
+                ));
+            }
+            Ok(StructureFunctionResult {
+                f2: self.f2_scale * (1.0 - request.x),
+                fl: 0.1 + self.fl_offset,
+                xf3: 0.0,
+                uncertainty: None,
+                metadata: StructureFunctionMetadata {
+                    backend: StructureFunctionBackend::Surrogate,
+                    apfelxx_version: None,
+                    lhapdf_version: None,
+                    pdf_set: request.pdf_set.clone(),
+                    pdf_member: request.pdf_member,
+                    pdf_order_qcd: 1,
+                    pdf_data_version: 1,
+                    order: request.order,
+                    process: StructureFunctionProcess::NcDis,
+                    projectile: DisProjectile::Electron,
+                    target: DisTarget::Proton,
+                    mu_f_over_q: request.mu_f_over_q,
+                    mu_r_over_q: request.mu_r_over_q,
+                    scheme: "test".to_owned(),
+                    electromagnetic_mode: PHOTON_EXCHANGE_MODE.to_owned(),
+                    os_arch: None,
+                    rust_version: None,
+                    git_commit: None,
+                    git_dirty: None,
+                    pythia_version: None,
+                    hepmc_version: None,
+                    python_env_hash: None,
+                },
+            })
+        }
+    }
+
+    fn model() -> SurrogateConfig {
+        SurrogateConfig {
+            source_backend: "apfel".to_owned(),
+            pdf_set: "CT18NLO".to_owned(),
+            pdf_member: 0,
+            order: PerturbativeOrder::Nlo,
+            x_min: 1.0e-4,
+            x_max: 0.8,
+            q2_min: 3.5,
+            q2_max: 1.0e4,
+            mu_f_ratio_min: 0.5,
+            mu_f_ratio_max: 2.0,
+            mu_r_ratio_min: 0.5,
+            mu_r_ratio_max: 2.0,
+            input_mean: vec![0.0; 4],
+            input_std: vec![1.0; 4],
+            target_mean: vec![0.0; 3],
+            target_std: vec![1.0; 3],
+            validation_mse: 1.0e-3,
+            test_max_rel_error: 0.05,
+            ensemble_size: 1,
+            max_relative_uncertainty: None,
+            architecture: Default::default(),
+            constraints: Default::default(),
+        }
+    }
+
+    #[test]
+    fn quantiles_interpolate_sorted_values() {
+        let quantiles = ErrorQuantiles::from_values([4.0, 1.0, 3.0, 2.0, 5.0]);
+        assert_eq!(quantiles.count, 5);
+        assert_eq!(quantiles.mean, Some(3.0));
+        assert_eq!(quantiles.p50, Some(3.0));
+        assert!((quantiles.p90.unwrap() - 4.6).abs() < 1.0e-12);
+        assert_eq!(quantiles.maximum, Some(5.0));
+        assert_eq!(ErrorQuantiles::from_values([]).p50, None);
+    }
+
+    #[test]
+    fn bins_include_lower_edges_and_the_upper_domain_edge() {
+        let edges = log_edges(1.0, 100.0, 2);
+        assert_eq!(bin_index(1.0, &edges), 0);
+        assert_eq!(bin_index(10.0, &edges), 1);
+        assert_eq!(bin_index(100.0, &edges), 1);
+    }
+
+    #[test]
+    fn report_aggregates_errors_and_records_failures() {
+        let surrogate = ScaledProvider {
+            f2_scale: 1.02,
+            fl_offset: 0.01,
+        };
+        let reference = ScaledProvider {
+            f2_scale: 1.0,
+            fl_offset: 0.0,
+        };
+        let config = SurrogateValidationConfig {
+            sample_count: 128,
+            x_bins: 4,
+            q2_bins: 3,
+            worst_point_count: 5,
+            ..SurrogateValidationConfig::default()
+        };
+
+        let report = validate_surrogate(&surrogate, &reference, &model(), &config).unwrap();
+
+        assert_eq!(report.evaluated_points + report.failures.len(), 128);
+        assert!(!report.failures.is_empty());
+        assert!(report.failures.iter().all(|failure| failure.x > 0.5));
+        let f2 = &report.statistics.f2_relative_error;
+        assert!((f2.maximum.unwrap() - 0.02).abs() < 1.0e-12);
+        assert!((f2.p50.unwrap() - 0.02).abs() < 1.0e-12);
+        assert_eq!(report.heat_map.len(), 12);
+        assert_eq!(
+            report.heat_map.iter().map(|cell| cell.count).sum::<usize>(),
+            report.evaluated_points
+        );
+        assert_eq!(report.worst_points.len(), 5);
+        let worst = report.worst_points[0].worst_error().unwrap();
+        assert!(report
+            .rows
+            .iter()
+            .all(|row| row.worst_error().unwrap() <= worst + 1.0e-15));
+    }
+
+    #[test]
+    fn rejects_empty_samples_and_invalid_domains() {
+        let provider = ScaledProvider {
+            f2_scale: 1.0,
+            fl_offset: 0.0,
+        };
+        let config = SurrogateValidationConfig {
+            sample_count: 0,
+            ..SurrogateValidationConfig::default()
+        };
+        assert!(matches!(
+            validate_surrogate(&provider, &provider, &model(), &config),
+            Err(SurrogateValidationError::InvalidConfig {
+                field: "sample_count",
+                ..
+            })
+        ));
+
+        let mut invalid = model();
+        invalid.x_min = 0.0;
+        assert!(matches!(
+            validate_surrogate(
+                &provider,
+                &provider,
+                &invalid,
+                &SurrogateValidationConfig::default()
+            ),
+            Err(SurrogateValidationError::InvalidDomain { field: "x", .. })
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+//! PYTHIA 8 settings passed through `generate-dis-events` to the backend.
+//!
+//! Settings are PYTHIA's own `Name = value` lines, such as
+//! `MultipartonInteractions:pT0Ref = 2.3` or `23:onMode = off`, given with
+//! `--pythia-setting` or read from a `.cmnd` file. The backend applies them
+//! after its own defaults, so they take precedence, and rejects names PYTHIA
+//! does not know with the `unknown_pythia_setting` error. Settings that
+//! `generate-dis-events` sets from its own options are reserved and rejected
+//! here, before a run starts.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Setting-name prefixes reserved for `generate-dis-events` options, with the
+/// option that controls them.
+const RESERVED_SETTINGS: &[(&str, &str)] = &[
+    ("Beams:", "--electron-energy and --proton-energy"),
+    ("Random:", "--seed"),
+    ("PDF:pSet", "--pdf-set and --pdf-member"),
+    ("Main:numberOfEvents", "--events"),
+];
+
+#[derive(Debug)]
+pub enum PythiaSettingsError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// Not a `Name = value` line with a `Group:key` name.
+    Malformed {
+        setting: String,
+    },
+    /// Set from a `generate-dis-events` option instead.
+    Reserved {
+        setting: String,
+        option: &'static str,
+    },
+}
+
+impl fmt::Display for PythiaSettingsError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(
+                formatter,
+                "failed to read PYTHIA settings from '{}': {source}",
+                path.display()
+            ),
+            Self::Malformed { setting } => write!(
+                formatter,
+                "malformed PYTHIA setting '{setting}': expected 'Group:key = value'"
+            ),
+            Self::Reserved { setting, option } => write!(
+                formatter,
+                "PYTHIA setting '{setting}' is reserved; use {option} instead"
+            ),
+        }
+    }
+}
+
+impl Error for PythiaSettingsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Check that `setting` is a `Name = value` line (PYTHIA also accepts the
+/// value after a space) that does not set a reserved name.
+pub fn check_setting(setting: &str) -> Result<(), PythiaSettingsError> {
+    let malformed = || PythiaSettingsError::Malformed {
+        setting: setting.to_owned(),
+    };
+    let (name, value) = setting
+        .trim()
+        .split_once(|character: char| character == '=' || character.is_whitespace())
+        .ok_or_else(malformed)?;
+    let (group, key) = name.split_once(':').ok_or_else(malformed)?;
+    let value =
+        value.trim_start_matches(|character: char| character == '=' || character.is_whitespace());
+    if group.is_empty() || key.is_empty() || value.is_empty() {
+        return Err(malformed());
+    }
+    match RESERVED_SETTINGS.iter().find(|(prefix, _)| {
+        name.get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    }) {
+        Some(&(_, option)) => Err(PythiaSettingsError::Reserved {
+            setting: setting.trim().to_owned(),
+            option,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The setting lines of a `.cmnd` file. As in PYTHIA's `readFile`, blank
+/// lines and lines that do not start with a letter or digit are comments.
+#[must_use]
+pub fn command_file_settings(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with(|character: char| character.is_ascii_alphanumeric()))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Read and check the settings of a `.cmnd` file.
+pub fn read_command_file(path: &Path) -> Result<Vec<String>, PythiaSettingsError> {
+    let text = std::fs::read_to_string(path).map_err(|source| PythiaSettingsError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let settings = command_file_settings(&text);
+    for setting in &settings {
+        check_setting(setting)?;
+    }
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_checked_for_form_and_reserved_names() {
+        for setting in [
+            "MultipartonInteractions:pT0Ref = 2.3",
+            "PartonLevel:MPI on",
+            "23:onMode=off",
+            "  TimeShower:alphaSvalue = 0.1365 ! Monash  ",
+        ] {
+            assert!(check_setting(setting).is_ok(), "{setting}");
+        }
+        for setting in [
+            "PartonLevel:MPI",
+            "MPI = on",
+            ":MPI = on",
+            "PartonLevel: = on",
+        ] {
+            assert!(
+                matches!(
+                    check_setting(setting),
+                    Err(PythiaSettingsError::Malformed { .. })
+                ),
+                "{setting}"
+            );
+        }
+        let error = check_setting("random:seed = 3").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "PYTHIA setting 'random:seed = 3' is reserved; use --seed instead"
+        );
+        assert!(matches!(
+            check_setting("Beams:eA = 10"),
+            Err(PythiaSettingsError::Reserved { .. })
+        ));
+        assert!(check_setting("PDF:pSetB = 13").is_err());
+        assert!(check_setting("PDF:lepton = off").is_ok());
+    }
+
+    #[test]
+    fn command_files_keep_setting_lines_only() {
+        let text = "! Monash tune variations\n\n# MPI\nPartonLevel:MPI = on\n  \
+                    MultipartonInteractions:pT0Ref = 2.3\n-- end\n23:onMode = off\n";
+        assert_eq!(
+            command_file_settings(text),
+            [
+                "PartonLevel:MPI = on",
+                "MultipartonInteractions:pT0Ref = 2.3",
+                "23:onMode = off"
+            ]
+        );
+
+        let path = std::env::temp_dir().join(format!(
+            "quark_sim_pythia_settings_{}.cmnd",
+            std::process::id()
+        ));
+        std::fs::write(&path, "Random:setSeed = on\n").unwrap();
+        assert!(matches!(
+            read_command_file(&path),
+            Err(PythiaSettingsError::Reserved { .. })
+        ));
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(
+            read_command_file(&path),
+            Err(PythiaSettingsError::Io { .. })
+        ));
+    }
+}
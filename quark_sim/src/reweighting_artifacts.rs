@@ -0,0 +1,282 @@
+//! Output files of a PDF reweighting run.
+//!
+//! The reweighted event file, a per-member CSV table and the JSON summary
+//! share one output directory. Like the other artifact writers an existing
+//! directory is accepted but no artifact is ever overwritten; all paths are
+//! checked before the event file is started.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::analysis::ReweightingSummary;
+
+pub const REWEIGHTED_EVENTS_FILENAME: &str = "events.hepmc3";
+pub const REWEIGHTING_CSV_FILENAME: &str = "reweighted_cross_sections.csv";
+pub const REWEIGHTING_JSON_FILENAME: &str = "reweighting_summary.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReweightingArtifactPaths {
+    pub events: PathBuf,
+    pub csv: PathBuf,
+    pub json: PathBuf,
+}
+
+impl ReweightingArtifactPaths {
+    /// Paths in `output_directory`; the event file is gzipped when
+    /// `compressed` is set.
+    #[must_use]
+    pub fn in_directory(output_directory: &Path, compressed: bool) -> Self {
+        let events = if compressed {
+            format!("{REWEIGHTED_EVENTS_FILENAME}.gz")
+        } else {
+            REWEIGHTED_EVENTS_FILENAME.to_owned()
+        };
+        Self {
+            events: output_directory.join(events),
+            csv: output_directory.join(REWEIGHTING_CSV_FILENAME),
+            json: output_directory.join(REWEIGHTING_JSON_FILENAME),
+        }
+    }
+
+    fn as_array(&self) -> [&Path; 3] {
+        [&self.events, &self.csv, &self.json]
+    }
+}
+
+#[derive(Debug)]
+pub enum ReweightingArtifactError {
+    EmptyOutputPath,
+    OutputIsNotDirectory {
+        path: PathBuf,
+    },
+    ArtifactExists {
+        path: PathBuf,
+    },
+    Io {
+        operation: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    Csv {
+        path: PathBuf,
+        source: csv::Error,
+    },
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+impl fmt::Display for ReweightingArtifactError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyOutputPath => write!(formatter, "reweighting output path must not be empty"),
+            Self::OutputIsNotDirectory { path } => write!(
+                formatter,
+                "reweighting output path '{}' exists but is not a directory",
+                path.display()
+            ),
+            Self::ArtifactExists { path } => write!(
+                formatter,
+                "refusing to overwrite existing reweighting artifact '{}'",
+                path.display()
+            ),
+            Self::Io {
+                operation,
+                path,
+                source,
+            } => write!(
+                formatter,
+                "failed while {operation} '{}': {source}",
+                path.display()
+            ),
+            Self::Csv { path, source } => write!(
+                formatter,
+                "failed to serialize CSV '{}': {source}",
+                path.display()
+            ),
+            Self::Json { path, source } => write!(
+                formatter,
+                "failed to serialize JSON '{}': {source}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for ReweightingArtifactError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Csv { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MemberRow<'a> {
+    weight_name: &'a str,
+    set_name: &'a str,
+    member: i32,
+    sum_of_weights: f64,
+    ratio: f64,
+    cross_section_pb: Option<f64>,
+}
+
+/// Create `output_directory` if needed and make sure none of the artifacts
+/// exists yet.
+pub fn prepare_reweighting_artifacts(
+    output_directory: &Path,
+    compressed: bool,
+) -> Result<ReweightingArtifactPaths, ReweightingArtifactError> {
+    if output_directory.as_os_str().is_empty() {
+        return Err(ReweightingArtifactError::EmptyOutputPath);
+    }
+    if output_directory.exists() {
+        if !output_directory.is_dir() {
+            return Err(ReweightingArtifactError::OutputIsNotDirectory {
+                path: output_directory.to_owned(),
+            });
+        }
+    } else {
+        fs::create_dir_all(output_directory).map_err(|source| ReweightingArtifactError::Io {
+            operation: "creating reweighting output directory",
+            path: output_directory.to_owned(),
+            source,
+        })?;
+    }
+    let paths = ReweightingArtifactPaths::in_directory(output_directory, compressed);
+    for path in paths.as_array() {
+        if path.exists() {
+            return Err(ReweightingArtifactError::ArtifactExists {
+                path: path.to_owned(),
+            });
+        }
+    }
+    Ok(paths)
+}
+
+/// Write the per-member table and the JSON summary next to the event file.
+pub fn write_reweighting_summary(
+    paths: &ReweightingArtifactPaths,
+    summary: &ReweightingSummary,
+) -> Result<(), ReweightingArtifactError> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(true)
+        .from_writer(create_new_file(&paths.csv)?);
+    for member in &summary.members {
+        writer
+            .serialize(MemberRow {
+                weight_name: &member.weight_name,
+                set_name: &member.set_name,
+                member: member.member,
+                sum_of_weights: member.sum_of_weights,
+                ratio: member.ratio,
+                cross_section_pb: member.cross_section_pb,
+            })
+            .map_err(|source| ReweightingArtifactError::Csv {
+                path: paths.csv.clone(),
+                source,
+            })?;
+    }
+    writer
+        .flush()
+        .map_err(|source| ReweightingArtifactError::Io {
+            operation: "flushing CSV",
+            path: paths.csv.clone(),
+            source,
+        })?;
+
+    let mut json = BufWriter::new(create_new_file(&paths.json)?);
+    serde_json::to_writer_pretty(&mut json, summary).map_err(|source| {
+        ReweightingArtifactError::Json {
+            path: paths.json.clone(),
+            source,
+        }
+    })?;
+    json.write_all(b"\n")
+        .and_then(|()| json.flush())
+        .map_err(|source| ReweightingArtifactError::Io {
+            operation: "writing JSON",
+            path: paths.json.clone(),
+            source,
+        })
+}
+
+fn create_new_file(path: &Path) -> Result<File, ReweightingArtifactError> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|source| {
+            if source.kind() == io::ErrorKind::AlreadyExists {
+                ReweightingArtifactError::ArtifactExists {
+                    path: path.to_owned(),
+                }
+            } else {
+                ReweightingArtifactError::Io {
+                    operation: "creating artifact",
+                    path: path.to_owned(),
+                    source,
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{CrossSection, ReweightedMember};
+
+    #[test]
+    fn summary_is_written_once_next_to_the_event_file() {
+        let directory = std::env::temp_dir().join(format!(
+            "quark_sim_reweighting_artifacts_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+
+        let paths = prepare_reweighting_artifacts(&directory, true).unwrap();
+        assert_eq!(paths.events, directory.join("events.hepmc3.gz"));
+        let summary = ReweightingSummary {
+            nominal_pdf: "Nominal/0".to_owned(),
+            events: 10,
+            nominal_sum_of_weights: 10.0,
+            nominal_cross_section: Some(CrossSection {
+                value_pb: 50.0,
+                error_pb: 0.5,
+            }),
+            members: vec![ReweightedMember {
+                weight_name: "Other/0".to_owned(),
+                set_name: "Other".to_owned(),
+                member: 0,
+                sum_of_weights: 11.0,
+                ratio: 1.1,
+                cross_section_pb: Some(55.0),
+            }],
+            sets: Vec::new(),
+        };
+        write_reweighting_summary(&paths, &summary).unwrap();
+
+        let decoded: ReweightingSummary =
+            serde_json::from_reader(File::open(&paths.json).unwrap()).unwrap();
+        assert_eq!(decoded, summary);
+        assert_eq!(
+            fs::read_to_string(&paths.csv).unwrap(),
+            "weight_name,set_name,member,sum_of_weights,ratio,cross_section_pb\n\
+             Other/0,Other,0,11.0,1.1,55.0\n"
+        );
+        assert!(matches!(
+            prepare_reweighting_artifacts(&directory, true),
+            Err(ReweightingArtifactError::ArtifactExists { .. })
+        ));
+        let _ = fs::remove_dir_all(directory);
+    }
+}
@@ -0,0 +1,947 @@
+//! Declarative run cards for the DIS subcommands.
+//!
+//! A run card names a subcommand and its options in a JSON or TOML file:
+//!
+//! ```toml
+//! schema_version = 1
+//! command = "generate-dis-events"
+//!
+//! [dis]
+//! electron_energy_gev = 27.5
+//! pdf_set = "CT18LO"
+//!
+//! [options]
+//! generator = "native"
+//! weighting = "weighted"
+//! ```
+//!
+//! `dis` is the GUI's [`DisConfig`]; fields it omits take the GUI defaults,
+//! and it is turned into options with the same builders the GUI uses to
+//! launch the CLI. `options` holds any option of the subcommand under its
+//! long name without the leading `--`. Options on the command line replace
+//! those of the card, and `options` replaces what `dis` sets. The resolved
+//! card lists every option in `options` and is written as `run_card.json`
+//! into the output directory of a run, so `--config run_card.json` repeats it.
+//!
+//! The `config.json` of a generator run is read as a card too: its beams,
+//! cuts, seed and generator settings become `generate-dis-events` options,
+//! with `output` set to the directory that holds the run.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::doctor::PYTHIA_SCHEMA_VERSION;
+use crate::gui::state::{build_event_generation_command, DisConfig};
+
+pub const RUN_CARD_FILENAME: &str = "run_card.json";
+pub const RUN_CARD_SCHEMA_VERSION: i32 = 1;
+
+/// `schema_version` of the generator request in a run's `config.json`.
+pub const GENERATOR_CONFIG_SCHEMA_VERSION: i32 = PYTHIA_SCHEMA_VERSION;
+
+/// Subcommands that accept `--config`.
+pub const RUN_CARD_COMMANDS: [&str; 12] = [
+    "dis-kinematics",
+    "dis-cross-section",
+    "generate-dis-events",
+    "validate-hera",
+    "theory-uncertainties",
+    "structure-functions",
+    "train-surrogate",
+    "validate-surrogate",
+    "analyze-events",
+    "unfold",
+    "reweight-events",
+    "compare-runs",
+];
+
+/// Options that take no value; `true` in a card passes the bare flag.
+const SWITCH_OPTIONS: [&str; 5] = [
+    "constrain-fl",
+    "pdf-uncertainty",
+    "resume",
+    "scale-variations",
+    "zero-xf3",
+];
+
+#[derive(Debug)]
+pub enum RunCardError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    Toml {
+        path: PathBuf,
+        message: String,
+    },
+    UnsupportedFormat {
+        path: PathBuf,
+    },
+    UnsupportedSchemaVersion {
+        path: PathBuf,
+        version: i32,
+    },
+    /// A generator `config.json` for a process `generate-dis-events` cannot
+    /// select.
+    UnsupportedProcess {
+        path: PathBuf,
+        process: String,
+    },
+    MissingPath,
+    MissingCommand {
+        path: PathBuf,
+    },
+    UnknownCommand {
+        command: String,
+    },
+    CommandMismatch {
+        path: PathBuf,
+        card: String,
+        command: String,
+    },
+    DisNotApplicable {
+        command: String,
+    },
+    InvalidOption {
+        name: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for RunCardError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(
+                formatter,
+                "failed to access run card '{}': {source}",
+                path.display()
+            ),
+            Self::Json { path, source } => {
+                write!(formatter, "invalid run card '{}': {source}", path.display())
+            }
+            Self::Toml { path, message } => {
+                write!(
+                    formatter,
+                    "invalid run card '{}': {message}",
+                    path.display()
+                )
+            }
+            Self::UnsupportedFormat { path } => write!(
+                formatter,
+                "run card '{}' must end in .json or .toml",
+                path.display()
+            ),
+            Self::UnsupportedSchemaVersion { path, version } => write!(
+                formatter,
+                "run card '{}' has schema_version {version}; this build reads version \
+                 {RUN_CARD_SCHEMA_VERSION}",
+                path.display()
+            ),
+            Self::UnsupportedProcess { path, process } => write!(
+                formatter,
+                "generator configuration '{}' has the unsupported process '{process}'",
+                path.display()
+            ),
+            Self::MissingPath => write!(formatter, "--config requires a file path"),
+            Self::MissingCommand { path } => write!(
+                formatter,
+                "run card '{}' names no command; set \"command\" or put the subcommand \
+                 before --config",
+                path.display()
+            ),
+            Self::UnknownCommand { command } => write!(
+                formatter,
+                "run cards do not support the command '{command}'"
+            ),
+            Self::CommandMismatch {
+                path,
+                card,
+                command,
+            } => write!(
+                formatter,
+                "run card '{}' is for {card}, not {command}",
+                path.display()
+            ),
+            Self::DisNotApplicable { command } => write!(
+                formatter,
+                "the dis section of a run card does not apply to {command}"
+            ),
+            Self::InvalidOption { name, reason } => {
+                write!(formatter, "invalid run card option '{name}': {reason}")
+            }
+        }
+    }
+}
+
+impl Error for RunCardError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// A subcommand with its options, read from `--config` or resolved from a
+/// command line.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunCard {
+    pub schema_version: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dis: Option<DisConfig>,
+    #[serde(default)]
+    pub options: BTreeMap<String, Value>,
+}
+
+/// The generator request written as `config.json` into a
+/// `generate-dis-events` run directory, by either generator.
+#[derive(Debug, Deserialize)]
+struct GeneratorConfig {
+    schema_version: i32,
+    process: String,
+    electron_energy_gev: f64,
+    proton_energy_gev: f64,
+    q2_min_gev2: f64,
+    q2_max_gev2: f64,
+    x_min: f64,
+    x_max: f64,
+    y_min: f64,
+    y_max: f64,
+    number_of_events: usize,
+    #[serde(default)]
+    random_seed: Option<i64>,
+    pdf_set: String,
+    pdf_member: i32,
+    #[serde(default)]
+    parton_shower: Option<bool>,
+    #[serde(default)]
+    hadronization: Option<bool>,
+    #[serde(default)]
+    pythia_settings: Vec<String>,
+    /// Only the native generator records its perturbative order.
+    #[serde(default)]
+    order: Option<Value>,
+    #[serde(default)]
+    weighting: Option<String>,
+}
+
+impl GeneratorConfig {
+    /// The `generate-dis-events` card of the run that wrote `path`.
+    fn into_card(self, path: &Path) -> Result<RunCard, RunCardError> {
+        if self.schema_version != GENERATOR_CONFIG_SCHEMA_VERSION {
+            return Err(RunCardError::UnsupportedSchemaVersion {
+                path: path.to_owned(),
+                version: self.schema_version,
+            });
+        }
+        let process = match self.process.as_str() {
+            "neutral_current_dis" => "nc",
+            "charged_current_dis" => "cc",
+            _ => {
+                return Err(RunCardError::UnsupportedProcess {
+                    path: path.to_owned(),
+                    process: self.process,
+                })
+            }
+        };
+        let mut options = BTreeMap::from([
+            ("process".to_owned(), Value::from(process)),
+            (
+                "electron-energy".to_owned(),
+                Value::from(self.electron_energy_gev),
+            ),
+            (
+                "proton-energy".to_owned(),
+                Value::from(self.proton_energy_gev),
+            ),
+            ("q2-min".to_owned(), Value::from(self.q2_min_gev2)),
+            ("q2-max".to_owned(), Value::from(self.q2_max_gev2)),
+            ("x-min".to_owned(), Value::from(self.x_min)),
+            ("x-max".to_owned(), Value::from(self.x_max)),
+            ("y-min".to_owned(), Value::from(self.y_min)),
+            ("y-max".to_owned(), Value::from(self.y_max)),
+            ("events".to_owned(), Value::from(self.number_of_events)),
+            ("pdf-set".to_owned(), Value::from(self.pdf_set)),
+            ("pdf-member".to_owned(), Value::from(self.pdf_member)),
+        ]);
+        if let Some(seed) = self.random_seed {
+            // The native generator records the command's i32 seed as a u32.
+            options.insert("seed".to_owned(), Value::from(seed as u32 as i32));
+        }
+        for (name, enabled) in [
+            ("parton-shower", self.parton_shower),
+            ("hadronization", self.hadronization),
+        ] {
+            if let Some(enabled) = enabled {
+                options.insert(name.to_owned(), Value::Bool(enabled));
+            }
+        }
+        if !self.pythia_settings.is_empty() {
+            options.insert(
+                "pythia-setting".to_owned(),
+                Value::from(self.pythia_settings),
+            );
+        }
+        if self.order.is_some() {
+            options.insert("generator".to_owned(), Value::from("native"));
+            if let Some(weighting) = self.weighting {
+                options.insert("weighting".to_owned(), Value::from(weighting));
+            }
+        }
+        // `<output>/dis_run_<timestamp>/config.json`
+        if let Some(output) = path
+            .parent()
+            .and_then(Path::parent)
+            .filter(|output| !output.as_os_str().is_empty())
+        {
+            options.insert(
+                "output".to_owned(),
+                Value::from(output.to_string_lossy().into_owned()),
+            );
+        }
+        Ok(RunCard {
+            schema_version: RUN_CARD_SCHEMA_VERSION,
+            command: Some("generate-dis-events".to_owned()),
+            dis: None,
+            options,
+        })
+    }
+}
+
+impl RunCard {
+    /// Read a `.json` or `.toml` run card, or the `config.json` of a
+    /// generator run.
+    pub fn load(path: &Path) -> Result<Self, RunCardError> {
+        let text = std::fs::read_to_string(path).map_err(|source| RunCardError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let document = match extension {
+            Some("json") => serde_json::from_str(&text).map_err(|source| RunCardError::Json {
+                path: path.to_owned(),
+                source,
+            })?,
+            Some("toml") => toml_to_json(&text).map_err(|message| RunCardError::Toml {
+                path: path.to_owned(),
+                message,
+            })?,
+            _ => {
+                return Err(RunCardError::UnsupportedFormat {
+                    path: path.to_owned(),
+                })
+            }
+        };
+        let json_error = |source| RunCardError::Json {
+            path: path.to_owned(),
+            source,
+        };
+        if is_generator_config(&document) {
+            return serde_json::from_value::<GeneratorConfig>(document)
+                .map_err(json_error)?
+                .into_card(path);
+        }
+        let card: Self = serde_json::from_value(document).map_err(json_error)?;
+        if card.schema_version != RUN_CARD_SCHEMA_VERSION {
+            return Err(RunCardError::UnsupportedSchemaVersion {
+                path: path.to_owned(),
+                version: card.schema_version,
+            });
+        }
+        Ok(card)
+    }
+
+    /// Resolve the `--config` card of a command line, either
+    /// `--config CARD [OPTIONS]` or `SUBCOMMAND --config CARD [OPTIONS]`.
+    /// Returns `None` when the command line has no `--config`.
+    pub fn from_config_arguments(args: &[String]) -> Result<Option<Self>, RunCardError> {
+        let (command, arguments) = match args {
+            [flag, ..] if flag == "--config" => (None, args),
+            [command, remaining @ ..] if remaining.iter().any(|arg| arg == "--config") => {
+                (Some(command.as_str()), remaining)
+            }
+            _ => return Ok(None),
+        };
+        if let Some(command) = command.filter(|command| !RUN_CARD_COMMANDS.contains(command)) {
+            return Err(RunCardError::UnknownCommand {
+                command: command.to_owned(),
+            });
+        }
+        if let Some(help) = arguments
+            .iter()
+            .find(|arg| *arg == "-h" || *arg == "--help")
+        {
+            return Err(RunCardError::InvalidOption {
+                name: help.clone(),
+                reason: "--help cannot be combined with --config".to_owned(),
+            });
+        }
+
+        let position = arguments
+            .iter()
+            .position(|arg| arg == "--config")
+            .unwrap_or_default();
+        let path = arguments
+            .get(position + 1)
+            .filter(|path| !path.starts_with("--"))
+            .map(PathBuf::from)
+            .ok_or(RunCardError::MissingPath)?;
+        let overrides: Vec<String> = arguments[..position]
+            .iter()
+            .chain(&arguments[position + 2..])
+            .cloned()
+            .collect();
+
+        let card = Self::load(&path)?;
+        let command = match (command, card.command.as_deref()) {
+            (Some(command), Some(card_command)) if command != card_command => {
+                return Err(RunCardError::CommandMismatch {
+                    path,
+                    card: card_command.to_owned(),
+                    command: command.to_owned(),
+                })
+            }
+            (Some(command), _) | (None, Some(command)) => command.to_owned(),
+            (None, None) => return Err(RunCardError::MissingCommand { path }),
+        };
+        if !RUN_CARD_COMMANDS.contains(&command.as_str()) {
+            return Err(RunCardError::UnknownCommand { command });
+        }
+        card.resolve(command, &overrides).map(Some)
+    }
+
+    /// The card of a plain `SUBCOMMAND [OPTIONS]` command line, or `None`
+    /// for other command lines.
+    #[must_use]
+    pub fn from_command_line(args: &[String]) -> Option<Self> {
+        let (command, arguments) = args.split_first()?;
+        if !RUN_CARD_COMMANDS.contains(&command.as_str()) {
+            return None;
+        }
+        Some(Self {
+            schema_version: RUN_CARD_SCHEMA_VERSION,
+            command: Some(command.clone()),
+            dis: None,
+            options: command_line_options(arguments).ok()?,
+        })
+    }
+
+    /// Merge `dis`, `options` and the command-line `overrides`, in increasing
+    /// precedence, into the options of a card for `command`.
+    fn resolve(self, command: String, overrides: &[String]) -> Result<Self, RunCardError> {
+        let mut options = match &self.dis {
+            Some(dis) => dis_options(&command, dis)?,
+            None => BTreeMap::new(),
+        };
+        for (name, value) in self.options {
+            check_option(&name, &value)?;
+            options.insert(name, value);
+        }
+        options.extend(command_line_options(overrides)?);
+        Ok(Self {
+            schema_version: RUN_CARD_SCHEMA_VERSION,
+            command: Some(command),
+            dis: None,
+            options,
+        })
+    }
+
+    /// Set one option, as when it is given on the command line.
+    pub fn set_option(&mut self, name: &str, value: impl Into<Value>) {
+        self.options.insert(name.to_owned(), value.into());
+    }
+
+    /// The subcommand and its options as command-line arguments.
+    #[must_use]
+    pub fn command_line(&self) -> Vec<String> {
+        let mut args: Vec<String> = self.command.iter().cloned().collect();
+        for (name, value) in &self.options {
+            push_option(&mut args, name, value);
+        }
+        args
+    }
+
+    /// Write the card as `run_card.json` into `directory`.
+    pub fn write(&self, directory: &Path) -> Result<PathBuf, RunCardError> {
+        let path = directory.join(RUN_CARD_FILENAME);
+        let mut json = serde_json::to_string_pretty(self).map_err(|source| RunCardError::Json {
+            path: path.clone(),
+            source,
+        })?;
+        json.push('\n');
+        std::fs::write(&path, json).map_err(|source| RunCardError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        Ok(path)
+    }
+}
+
+/// Whether `document` is a generator `config.json` rather than a run card:
+/// it names a `process` and none of the run-card fields.
+fn is_generator_config(document: &Value) -> bool {
+    document.get("process").is_some()
+        && ["command", "dis", "options"]
+            .iter()
+            .all(|field| document.get(field).is_none())
+}
+
+/// Options set by the GUI configuration for `command`.
+fn dis_options(command: &str, dis: &DisConfig) -> Result<BTreeMap<String, Value>, RunCardError> {
+    let beams = [
+        ("electron-energy", dis.electron_energy_gev.to_string()),
+        ("proton-energy", dis.proton_energy_gev.to_string()),
+    ];
+    let pdf = [
+        ("pdf-set", dis.pdf_set.clone()),
+        ("pdf-member", dis.pdf_member.to_string()),
+    ];
+    let theory = [
+        ("backend", dis.backend.clone()),
+        ("order", dis.perturbative_order.clone()),
+    ];
+    let pairs: Vec<(String, String)> = match command {
+        "dis-kinematics" => to_owned_pairs(&beams),
+        "dis-cross-section" => to_owned_pairs(&[&beams[..], &pdf[..]].concat()),
+        "generate-dis-events" => build_event_generation_command(dis)[1..]
+            .chunks(2)
+            .map(|pair| {
+                let flag = pair[0].trim_start_matches("--").to_owned();
+                (flag, pair.get(1).cloned().unwrap_or_default())
+            })
+            .collect(),
+        "structure-functions" => to_owned_pairs(
+            &[
+                &theory[..],
+                &pdf[..],
+                &[
+                    ("mu-f-over-q", dis.mu_f_over_q.to_string()),
+                    ("mu-r-over-q", dis.mu_r_over_q.to_string()),
+                ],
+            ]
+            .concat(),
+        ),
+        "validate-hera" | "theory-uncertainties" => {
+            to_owned_pairs(&[&theory[..], &pdf[..]].concat())
+        }
+        "train-surrogate" => to_owned_pairs(&[&pdf[..], &theory[1..]].concat()),
+        _ => {
+            return Err(RunCardError::DisNotApplicable {
+                command: command.to_owned(),
+            })
+        }
+    };
+    Ok(pairs
+        .into_iter()
+        .map(|(name, text)| {
+            let value = option_value(&text);
+            (name, value)
+        })
+        .collect())
+}
+
+fn to_owned_pairs(pairs: &[(&str, String)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| ((*name).to_owned(), value.clone()))
+        .collect()
+}
+
+/// Options of `--flag value` arguments; repeated flags collect their values
+/// in an array.
+fn command_line_options(args: &[String]) -> Result<BTreeMap<String, Value>, RunCardError> {
+    let mut options = BTreeMap::new();
+    let mut index = 0;
+    while index < args.len() {
+        let flag = &args[index];
+        let name = flag
+            .strip_prefix("--")
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| RunCardError::InvalidOption {
+                name: flag.clone(),
+                reason: "expected an option starting with --".to_owned(),
+            })?;
+        if name == "config" {
+            return Err(RunCardError::InvalidOption {
+                name: flag.clone(),
+                reason: "--config may be given only once".to_owned(),
+            });
+        }
+        let value = if SWITCH_OPTIONS.contains(&name) {
+            index += 1;
+            Value::Bool(true)
+        } else {
+            let text = args
+                .get(index + 1)
+                .filter(|value| !value.starts_with("--"))
+                .ok_or_else(|| RunCardError::InvalidOption {
+                    name: flag.clone(),
+                    reason: format!("{flag} requires a value"),
+                })?;
+            index += 2;
+            option_value(text)
+        };
+        match options.get_mut(name) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(previous) => *previous = Value::Array(vec![previous.take(), value]),
+            None => {
+                options.insert(name.to_owned(), value);
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// A command-line value as a JSON boolean or number when it reads back as
+/// the same text, and as a string otherwise.
+fn option_value(text: &str) -> Value {
+    match text {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => serde_json::from_str::<serde_json::Number>(text)
+            .ok()
+            .filter(|number| number.to_string() == text)
+            .map_or_else(|| Value::String(text.to_owned()), Value::Number),
+    }
+}
+
+fn check_option(name: &str, value: &Value) -> Result<(), RunCardError> {
+    let invalid = |reason: &str| RunCardError::InvalidOption {
+        name: name.to_owned(),
+        reason: reason.to_owned(),
+    };
+    if name.is_empty() || name.starts_with('-') {
+        return Err(invalid("option names are given without the leading --"));
+    }
+    if name == "config" {
+        return Err(invalid("run cards cannot include other run cards"));
+    }
+    match value {
+        Value::Array(values) if values.iter().all(|value| !value.is_array()) => values
+            .iter()
+            .try_for_each(|value| check_option(name, value)),
+        Value::Array(_) => Err(invalid("arrays must not be nested")),
+        Value::Null | Value::Object(_) => {
+            Err(invalid("expected a string, number, boolean or array"))
+        }
+        Value::Bool(false) | Value::Bool(true) | Value::Number(_) | Value::String(_) => Ok(()),
+    }
+}
+
+fn push_option(args: &mut Vec<String>, name: &str, value: &Value) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                push_option(args, name, value);
+            }
+        }
+        Value::Bool(enabled) if SWITCH_OPTIONS.contains(&name) => {
+            if *enabled {
+                args.push(format!("--{name}"));
+            }
+        }
+        Value::String(text) => args.extend([format!("--{name}"), text.clone()]),
+        value => args.extend([format!("--{name}"), value.to_string()]),
+    }
+}
+
+/// Convert a TOML document to the equivalent JSON value.
+fn toml_to_json(text: &str) -> Result<Value, String> {
+    let document = text
+        .parse::<toml_edit::Document>()
+        .map_err(|error| error.to_string())?;
+    toml_table_to_json(document.as_table())
+}
+
+fn toml_table_to_json(table: &toml_edit::Table) -> Result<Value, String> {
+    table
+        .iter()
+        .map(|(key, item)| {
+            let value = match item {
+                toml_edit::Item::Value(value) => toml_value_to_json(value)?,
+                toml_edit::Item::Table(table) => toml_table_to_json(table)?,
+                toml_edit::Item::ArrayOfTables(tables) => Value::Array(
+                    tables
+                        .iter()
+                        .map(toml_table_to_json)
+                        .collect::<Result<_, _>>()?,
+                ),
+                toml_edit::Item::None => Value::Null,
+            };
+            Ok((key.to_owned(), value))
+        })
+        .collect::<Result<serde_json::Map<_, _>, String>>()
+        .map(Value::Object)
+}
+
+fn toml_value_to_json(value: &toml_edit::Value) -> Result<Value, String> {
+    Ok(match value {
+        toml_edit::Value::String(text) => Value::String(text.value().clone()),
+        toml_edit::Value::Integer(integer) => Value::from(*integer.value()),
+        toml_edit::Value::Float(float) => serde_json::Number::from_f64(*float.value())
+            .map(Value::Number)
+            .ok_or_else(|| format!("unsupported float {}", float.value()))?,
+        toml_edit::Value::Boolean(boolean) => Value::Bool(*boolean.value()),
+        toml_edit::Value::Datetime(datetime) => Value::String(datetime.value().to_string()),
+        toml_edit::Value::Array(array) => Value::Array(
+            array
+                .iter()
+                .map(toml_value_to_json)
+                .collect::<Result<_, _>>()?,
+        ),
+        toml_edit::Value::InlineTable(table) => Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| Ok((key.to_owned(), toml_value_to_json(value)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| (*arg).to_owned()).collect()
+    }
+
+    fn temporary_card(test: &str, name: &str, contents: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("quark_sim_run_card_{test}_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn command_line_options_override_the_card_and_its_dis_section() {
+        let path = temporary_card(
+            "override",
+            "generate.toml",
+            r#"
+schema_version = 1
+command = "generate-dis-events"
+
+[dis]
+electron_energy_gev = 18.0
+pdf_set = "CT18LO"
+random_seed = "7"
+
+[options]
+generator = "native"
+events = 500
+"#,
+        );
+        let card = RunCard::from_config_arguments(&arguments(&[
+            "--config",
+            path.to_str().unwrap(),
+            "--events",
+            "20",
+            "--output",
+            "runs/native",
+        ]))
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(card.command.as_deref(), Some("generate-dis-events"));
+        assert_eq!(card.dis, None);
+        assert_eq!(card.options["electron-energy"], Value::from(18));
+        assert_eq!(card.options["proton-energy"], Value::from(920));
+        assert_eq!(card.options["pdf-set"], Value::from("CT18LO"));
+        assert_eq!(card.options["seed"], Value::from(7));
+        assert_eq!(card.options["generator"], Value::from("native"));
+        assert_eq!(card.options["events"], Value::from(20));
+        assert_eq!(card.options["output"], Value::from("runs/native"));
+
+        let command_line = card.command_line();
+        assert_eq!(command_line[0], "generate-dis-events");
+        let position = command_line
+            .iter()
+            .position(|arg| arg == "--x-min")
+            .unwrap();
+        assert_eq!(command_line[position + 1], "0.0001");
+
+        let directory = path.parent().unwrap().join("resolved");
+        std::fs::create_dir_all(&directory).unwrap();
+        let written = card.write(&directory).unwrap();
+        assert_eq!(RunCard::load(&written).unwrap(), card);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn command_lines_round_trip_through_cards() {
+        let args = arguments(&[
+            "reweight-events",
+            "--pdf",
+            "CT18NNLO",
+            "--pdf",
+            "NNPDF40_nnlo_as_01180/3",
+            "--x-bins",
+            "1e-4,1e-3",
+            "--cross-section",
+            "120.5",
+            "--max-events",
+            "100",
+        ]);
+        let card = RunCard::from_command_line(&args).unwrap();
+        assert_eq!(
+            card.options["pdf"],
+            Value::from(vec!["CT18NNLO", "NNPDF40_nnlo_as_01180/3"])
+        );
+        assert_eq!(card.options["x-bins"], Value::from("1e-4,1e-3"));
+        assert_eq!(card.options["cross-section"], Value::from(120.5));
+
+        let mut reparsed = card.command_line();
+        reparsed.sort();
+        let mut original = args;
+        original.sort();
+        assert_eq!(reparsed, original);
+
+        let switches = RunCard::from_command_line(&arguments(&[
+            "theory-uncertainties",
+            "--pdf-uncertainty",
+            "--dataset",
+            "H1",
+        ]))
+        .unwrap();
+        assert_eq!(switches.options["pdf-uncertainty"], Value::Bool(true));
+        assert_eq!(
+            switches.command_line(),
+            [
+                "theory-uncertainties",
+                "--dataset",
+                "H1",
+                "--pdf-uncertainty"
+            ]
+        );
+        assert!(RunCard::from_command_line(&arguments(&["--load", "session.json"])).is_none());
+    }
+
+    #[test]
+    fn generator_configs_are_read_as_generate_dis_events_cards() {
+        let path = temporary_card(
+            "generator",
+            "config.json",
+            r#"{
+  "schema_version": 1,
+  "process": "charged_current_dis",
+  "electron_energy_gev": 18.0,
+  "proton_energy_gev": 275.0,
+  "q2_min_gev2": 100.0,
+  "q2_max_gev2": 10000.0,
+  "x_min": 0.001,
+  "x_max": 0.8,
+  "y_min": 0.01,
+  "y_max": 0.95,
+  "number_of_events": 500,
+  "random_seed": 42,
+  "pdf_set": "CT18LO",
+  "pdf_member": 0,
+  "parton_shower": true,
+  "hadronization": false,
+  "pythia_settings": ["PartonLevel:MPI = on"]
+}"#,
+        );
+        let card = RunCard::load(&path).unwrap();
+        assert_eq!(card.command.as_deref(), Some("generate-dis-events"));
+        assert_eq!(card.options["process"], Value::from("cc"));
+        assert_eq!(card.options["events"], Value::from(500));
+        assert_eq!(card.options["seed"], Value::from(42));
+        assert_eq!(card.options["hadronization"], Value::Bool(false));
+        assert_eq!(
+            card.options["pythia-setting"],
+            Value::from(vec!["PartonLevel:MPI = on"])
+        );
+        assert_eq!(
+            card.options["output"],
+            Value::from(std::env::temp_dir().to_string_lossy().into_owned())
+        );
+        assert!(!card.options.contains_key("generator"));
+
+        let native = temporary_card(
+            "generator",
+            "native.json",
+            r#"{"schema_version": 1, "process": "neutral_current_dis",
+                "electron_energy_gev": 27.5, "proton_energy_gev": 920.0, "q2_min_gev2": 10.0,
+                "q2_max_gev2": 100.0, "x_min": 0.0001, "x_max": 0.8, "y_min": 0.01,
+                "y_max": 0.95, "w2_min_gev2": 0.0, "number_of_events": 10,
+                "random_seed": 4294967295, "pdf_set": "CT18LO", "pdf_member": 0,
+                "order": "lo", "weighting": "weighted"}"#,
+        );
+        let card = RunCard::load(&native).unwrap();
+        assert_eq!(card.options["generator"], Value::from("native"));
+        assert_eq!(card.options["weighting"], Value::from("weighted"));
+        assert_eq!(card.options["seed"], Value::from(-1));
+
+        let unsupported = temporary_card(
+            "generator",
+            "photoproduction.json",
+            r#"{"schema_version": 1, "process": "photoproduction"}"#,
+        );
+        assert!(RunCard::load(&unsupported).is_err());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn invalid_cards_are_rejected() {
+        let mismatch = temporary_card(
+            "invalid",
+            "unfold.json",
+            r#"{"schema_version": 1, "command": "unfold", "options": {"method": "bayes"}}"#,
+        );
+        let config = mismatch.to_str().unwrap();
+        assert!(matches!(
+            RunCard::from_config_arguments(&arguments(&["analyze-events", "--config", config])),
+            Err(RunCardError::CommandMismatch { .. })
+        ));
+        assert!(matches!(
+            RunCard::from_config_arguments(&arguments(&["unfold", "--config"])),
+            Err(RunCardError::MissingPath)
+        ));
+        assert!(matches!(
+            RunCard::from_config_arguments(&arguments(&["unfold", "--config", config, "--help"])),
+            Err(RunCardError::InvalidOption { .. })
+        ));
+
+        for (name, contents) in [
+            (
+                "version.json",
+                r#"{"schema_version": 2, "command": "unfold"}"#,
+            ),
+            ("field.json", r#"{"schema_version": 1, "comand": "unfold"}"#),
+            (
+                "dis.json",
+                r#"{"schema_version": 1, "command": "unfold", "dis": {"pdf_set": "CT18LO"}}"#,
+            ),
+            (
+                "nested.json",
+                r#"{"schema_version": 1, "command": "unfold", "options": {"x-bins": [[1]]}}"#,
+            ),
+            ("card.yaml", "schema_version: 1"),
+        ] {
+            let path = temporary_card("invalid", name, contents);
+            assert!(
+                RunCard::from_config_arguments(&arguments(&["--config", path.to_str().unwrap()]))
+                    .is_err(),
+                "{name} should be rejected"
+            );
+        }
+        let _ = std::fs::remove_dir_all(mismatch.parent().unwrap());
+    }
+}
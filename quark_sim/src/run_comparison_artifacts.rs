@@ -0,0 +1,610 @@
+//! CSV, JSON and SVG artifacts of a run comparison.
+//!
+//! The bundle holds the full comparison as JSON, the differing configuration
+//! and summary values, the compatibility tests and the normalised
+//! distributions as CSV, and one SVG per observable overlaying every run with
+//! a ratio panel to the reference run. Like the other artifacts, an existing
+//! output directory is accepted but no artifact is ever overwritten.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use plotters::backend::SVGBackend;
+use plotters::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::analysis::{DistributionComparison, RunComparison, ValueDifference};
+
+pub const RUN_COMPARISON_JSON_FILENAME: &str = "run_comparison.json";
+pub const RUN_COMPARISON_DIFF_CSV_FILENAME: &str = "configuration_diff.csv";
+pub const RUN_COMPARISON_TESTS_CSV_FILENAME: &str = "compatibility_tests.csv";
+pub const RUN_COMPARISON_DISTRIBUTIONS_CSV_FILENAME: &str = "distributions.csv";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunComparisonArtifactPaths {
+    pub json: PathBuf,
+    pub diff_csv: PathBuf,
+    pub tests_csv: PathBuf,
+    pub distributions_csv: PathBuf,
+    /// `<observable>.svg` for every compared distribution.
+    pub plots: Vec<PathBuf>,
+}
+
+impl RunComparisonArtifactPaths {
+    #[must_use]
+    pub fn in_directory(output_directory: &Path, comparison: &RunComparison) -> Self {
+        Self {
+            json: output_directory.join(RUN_COMPARISON_JSON_FILENAME),
+            diff_csv: output_directory.join(RUN_COMPARISON_DIFF_CSV_FILENAME),
+            tests_csv: output_directory.join(RUN_COMPARISON_TESTS_CSV_FILENAME),
+            distributions_csv: output_directory.join(RUN_COMPARISON_DISTRIBUTIONS_CSV_FILENAME),
+            plots: comparison
+                .distributions
+                .iter()
+                .map(|distribution| {
+                    output_directory.join(format!("{}.svg", distribution.observable))
+                })
+                .collect(),
+        }
+    }
+
+    /// Every artifact, in the order they are written.
+    pub fn iter(&self) -> impl Iterator<Item = &Path> {
+        [
+            &self.json,
+            &self.diff_csv,
+            &self.tests_csv,
+            &self.distributions_csv,
+        ]
+        .into_iter()
+        .chain(&self.plots)
+        .map(PathBuf::as_path)
+    }
+}
+
+#[derive(Debug)]
+pub enum RunComparisonArtifactError {
+    EmptyOutputPath,
+    OutputIsNotDirectory {
+        path: PathBuf,
+    },
+    ArtifactExists {
+        path: PathBuf,
+    },
+    Io {
+        operation: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    Csv {
+        path: PathBuf,
+        source: csv::Error,
+    },
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    Plot {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl fmt::Display for RunComparisonArtifactError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyOutputPath => {
+                write!(formatter, "run comparison output path must not be empty")
+            }
+            Self::OutputIsNotDirectory { path } => write!(
+                formatter,
+                "run comparison output path '{}' exists but is not a directory",
+                path.display()
+            ),
+            Self::ArtifactExists { path } => write!(
+                formatter,
+                "refusing to overwrite existing run comparison artifact '{}'",
+                path.display()
+            ),
+            Self::Io {
+                operation,
+                path,
+                source,
+            } => write!(
+                formatter,
+                "failed while {operation} '{}': {source}",
+                path.display()
+            ),
+            Self::Csv { path, source } => write!(
+                formatter,
+                "failed to serialize CSV '{}': {source}",
+                path.display()
+            ),
+            Self::Json { path, source } => write!(
+                formatter,
+                "failed to serialize JSON '{}': {source}",
+                path.display()
+            ),
+            Self::Plot { path, message } => write!(
+                formatter,
+                "failed to render SVG '{}': {message}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for RunComparisonArtifactError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Csv { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TestRow<'a> {
+    observable: &'a str,
+    label: &'a str,
+    reference: &'a str,
+    chi_square: Option<f64>,
+    degrees_of_freedom: Option<usize>,
+    chi_square_p_value: Option<f64>,
+    ks_statistic: Option<f64>,
+    ks_effective_entries: Option<f64>,
+    ks_p_value: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct DistributionRow<'a> {
+    observable: &'a str,
+    label: &'a str,
+    bin_low: f64,
+    bin_high: f64,
+    density: f64,
+    error: f64,
+    ratio: Option<f64>,
+    ratio_error: Option<f64>,
+}
+
+/// Write the comparison bundle into `output_directory`, which is created if
+/// needed.
+pub fn write_run_comparison_artifacts(
+    output_directory: &Path,
+    comparison: &RunComparison,
+) -> Result<RunComparisonArtifactPaths, RunComparisonArtifactError> {
+    prepare_output_directory(output_directory)?;
+    let paths = RunComparisonArtifactPaths::in_directory(output_directory, comparison);
+    if let Some(path) = paths.iter().find(|path| path.exists()) {
+        return Err(RunComparisonArtifactError::ArtifactExists {
+            path: path.to_owned(),
+        });
+    }
+
+    let mut json = BufWriter::new(create_new_file(&paths.json)?);
+    serde_json::to_writer_pretty(&mut json, comparison).map_err(|source| {
+        RunComparisonArtifactError::Json {
+            path: paths.json.clone(),
+            source,
+        }
+    })?;
+    json.write_all(b"\n")
+        .and_then(|()| json.flush())
+        .map_err(|source| RunComparisonArtifactError::Io {
+            operation: "writing JSON",
+            path: paths.json.clone(),
+            source,
+        })?;
+
+    write_differences(&paths.diff_csv, comparison)?;
+
+    let test_rows: Vec<TestRow<'_>> = comparison
+        .tests
+        .iter()
+        .map(|test| TestRow {
+            observable: test.observable.as_str(),
+            label: &test.label,
+            reference: &test.reference,
+            chi_square: test.chi_square.map(|chi| chi.chi_square),
+            degrees_of_freedom: test.chi_square.map(|chi| chi.degrees_of_freedom),
+            chi_square_p_value: test.chi_square.map(|chi| chi.p_value),
+            ks_statistic: test.kolmogorov_smirnov.map(|ks| ks.statistic),
+            ks_effective_entries: test.kolmogorov_smirnov.map(|ks| ks.effective_entries),
+            ks_p_value: test.kolmogorov_smirnov.map(|ks| ks.p_value),
+        })
+        .collect();
+    write_csv_rows(&paths.tests_csv, &test_rows)?;
+
+    let distribution_rows: Vec<DistributionRow<'_>> = comparison
+        .distributions
+        .iter()
+        .flat_map(|distribution| {
+            distribution.runs.iter().flat_map(move |run| {
+                (0..run.density.len()).map(move |bin| DistributionRow {
+                    observable: distribution.observable.as_str(),
+                    label: &run.label,
+                    bin_low: distribution.edges[bin],
+                    bin_high: distribution.edges[bin + 1],
+                    density: run.density[bin],
+                    error: run.error[bin],
+                    ratio: run.ratio[bin],
+                    ratio_error: run.ratio_error[bin],
+                })
+            })
+        })
+        .collect();
+    write_csv_rows(&paths.distributions_csv, &distribution_rows)?;
+
+    for (distribution, path) in comparison.distributions.iter().zip(&paths.plots) {
+        let svg = render_distribution(path, distribution)?;
+        create_new_file(path)?
+            .write_all(svg.as_bytes())
+            .map_err(|source| RunComparisonArtifactError::Io {
+                operation: "writing SVG",
+                path: path.clone(),
+                source,
+            })?;
+    }
+
+    Ok(paths)
+}
+
+/// One row per differing key: `source` (`configuration` or `summary`),
+/// `key` and the value of every run, with strings unquoted.
+fn write_differences(
+    path: &Path,
+    comparison: &RunComparison,
+) -> Result<(), RunComparisonArtifactError> {
+    let csv_error = |source| RunComparisonArtifactError::Csv {
+        path: path.to_owned(),
+        source,
+    };
+    let mut writer = csv::Writer::from_writer(create_new_file(path)?);
+    let header = ["source", "key"]
+        .into_iter()
+        .map(str::to_owned)
+        .chain(comparison.runs.iter().map(|run| run.label.clone()));
+    writer.write_record(header).map_err(csv_error)?;
+    let rows = [
+        ("configuration", &comparison.configuration_differences),
+        ("summary", &comparison.summary_differences),
+    ];
+    for (source, differences) in rows {
+        for ValueDifference { key, values } in differences {
+            let values = values.iter().map(|value| match value {
+                None => String::new(),
+                Some(Value::String(text)) => text.clone(),
+                Some(value) => value.to_string(),
+            });
+            let record = [source.to_owned(), key.clone()].into_iter().chain(values);
+            writer.write_record(record).map_err(csv_error)?;
+        }
+    }
+    writer
+        .flush()
+        .map_err(|source| RunComparisonArtifactError::Io {
+            operation: "flushing CSV",
+            path: path.to_owned(),
+            source,
+        })
+}
+
+/// Fractions of events per bin of every run, and their ratios to the
+/// reference run below.
+fn render_distribution(
+    path: &Path,
+    distribution: &DistributionComparison,
+) -> Result<String, RunComparisonArtifactError> {
+    let observable = distribution.observable;
+    let axis = |value: f64| {
+        if observable.logarithmic() {
+            value.log10()
+        } else {
+            value
+        }
+    };
+    let edges: Vec<f64> = distribution.edges.iter().map(|&edge| axis(edge)).collect();
+    let widths: Vec<f64> = distribution
+        .edges
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .collect();
+    let fractions: Vec<Vec<f64>> = distribution
+        .runs
+        .iter()
+        .map(|run| {
+            run.density
+                .iter()
+                .zip(&widths)
+                .map(|(d, w)| d * w)
+                .collect()
+        })
+        .collect();
+    let x_range = edges[0]..edges[edges.len() - 1];
+    let y_max = fractions
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|fraction| fraction.is_finite())
+        .fold(0.0_f64, f64::max)
+        .max(1.0e-12)
+        * 1.1;
+    let (mut ratio_min, mut ratio_max) = (0.8_f64, 1.2_f64);
+    for run in distribution.runs.iter().skip(1) {
+        for (ratio, error) in run.ratio.iter().zip(&run.ratio_error) {
+            if let (Some(ratio), Some(error)) = (ratio, error) {
+                ratio_min = ratio_min.min(ratio - error);
+                ratio_max = ratio_max.max(ratio + error);
+            }
+        }
+    }
+    let ratio_range = ratio_min.max(0.0)..ratio_max.min(3.0);
+    let x_formatter = |value: &f64| {
+        if observable.logarithmic() {
+            format!("{:.0e}", 10.0_f64.powf(*value))
+        } else {
+            format!("{value:.2}")
+        }
+    };
+
+    let mut svg = String::new();
+    {
+        let drawing_area = SVGBackend::with_string(&mut svg, (1_000, 800)).into_drawing_area();
+        drawing_area
+            .fill(&WHITE)
+            .map_err(|error| plot_error(path, error))?;
+        let (upper, lower) = drawing_area.split_vertically(560);
+
+        let mut chart = ChartBuilder::on(&upper)
+            .caption(
+                format!("{} distribution", observable.label()),
+                ("sans-serif", 24),
+            )
+            .margin(16)
+            .x_label_area_size(30)
+            .y_label_area_size(80)
+            .build_cartesian_2d(x_range.clone(), 0.0..y_max)
+            .map_err(|error| plot_error(path, error))?;
+        chart
+            .configure_mesh()
+            .y_desc("fraction of events per bin")
+            .x_label_formatter(&x_formatter)
+            .draw()
+            .map_err(|error| plot_error(path, error))?;
+        for (index, (run, fractions)) in distribution.runs.iter().zip(&fractions).enumerate() {
+            let colour = Palette99::pick(index).to_rgba();
+            let steps = fractions
+                .iter()
+                .enumerate()
+                .flat_map(|(bin, &fraction)| [(edges[bin], fraction), (edges[bin + 1], fraction)]);
+            chart
+                .draw_series(LineSeries::new(steps, colour.stroke_width(2)))
+                .map_err(|error| plot_error(path, error))?
+                .label(run.label.as_str())
+                .legend(move |(x, y)| {
+                    PathElement::new(vec![(x, y), (x + 20, y)], colour.stroke_width(2))
+                });
+        }
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()
+            .map_err(|error| plot_error(path, error))?;
+
+        let mut ratio_chart = ChartBuilder::on(&lower)
+            .margin(16)
+            .x_label_area_size(50)
+            .y_label_area_size(80)
+            .build_cartesian_2d(x_range.clone(), ratio_range)
+            .map_err(|error| plot_error(path, error))?;
+        ratio_chart
+            .configure_mesh()
+            .x_desc(if observable.logarithmic() {
+                format!("{} (log scale)", observable.label())
+            } else {
+                observable.label().to_owned()
+            })
+            .y_desc(format!("ratio to {}", distribution.runs[0].label))
+            .x_label_formatter(&x_formatter)
+            .draw()
+            .map_err(|error| plot_error(path, error))?;
+        ratio_chart
+            .draw_series(LineSeries::new(
+                [(x_range.start, 1.0), (x_range.end, 1.0)],
+                BLACK.mix(0.5),
+            ))
+            .map_err(|error| plot_error(path, error))?;
+        for (index, run) in distribution.runs.iter().enumerate().skip(1) {
+            let colour = Palette99::pick(index).to_rgba();
+            let points = run
+                .ratio
+                .iter()
+                .zip(&run.ratio_error)
+                .enumerate()
+                .filter_map(|(bin, (ratio, error))| {
+                    let centre = 0.5 * (edges[bin] + edges[bin + 1]);
+                    Some((centre, (*ratio)?, (*error)?))
+                });
+            ratio_chart
+                .draw_series(points.map(|(centre, ratio, error)| {
+                    ErrorBar::new_vertical(
+                        centre,
+                        ratio - error,
+                        ratio,
+                        ratio + error,
+                        colour.filled(),
+                        6,
+                    )
+                }))
+                .map_err(|error| plot_error(path, error))?;
+        }
+        drawing_area
+            .present()
+            .map_err(|error| plot_error(path, error))?;
+    }
+    Ok(svg)
+}
+
+fn plot_error(path: &Path, error: impl fmt::Display) -> RunComparisonArtifactError {
+    RunComparisonArtifactError::Plot {
+        path: path.to_owned(),
+        message: error.to_string(),
+    }
+}
+
+fn prepare_output_directory(path: &Path) -> Result<(), RunComparisonArtifactError> {
+    if path.as_os_str().is_empty() {
+        return Err(RunComparisonArtifactError::EmptyOutputPath);
+    }
+    if path.exists() {
+        if !path.is_dir() {
+            return Err(RunComparisonArtifactError::OutputIsNotDirectory {
+                path: path.to_owned(),
+            });
+        }
+        return Ok(());
+    }
+    fs::create_dir_all(path).map_err(|source| RunComparisonArtifactError::Io {
+        operation: "creating run comparison output directory",
+        path: path.to_owned(),
+        source,
+    })
+}
+
+fn create_new_file(path: &Path) -> Result<File, RunComparisonArtifactError> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|source| {
+            if source.kind() == io::ErrorKind::AlreadyExists {
+                RunComparisonArtifactError::ArtifactExists {
+                    path: path.to_owned(),
+                }
+            } else {
+                RunComparisonArtifactError::Io {
+                    operation: "creating artifact",
+                    path: path.to_owned(),
+                    source,
+                }
+            }
+        })
+}
+
+fn write_csv_rows<T: Serialize>(path: &Path, rows: &[T]) -> Result<(), RunComparisonArtifactError> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(true)
+        .from_writer(create_new_file(path)?);
+    for row in rows {
+        writer
+            .serialize(row)
+            .map_err(|source| RunComparisonArtifactError::Csv {
+                path: path.to_owned(),
+                source,
+            })?;
+    }
+    writer
+        .flush()
+        .map_err(|source| RunComparisonArtifactError::Io {
+            operation: "flushing CSV",
+            path: path.to_owned(),
+            source,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{compare_runs, ComparedRun, ObservableSample};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn writes_the_bundle_without_overwriting() {
+        let base = std::env::temp_dir().join(format!(
+            "quark_sim_run_comparison_artifacts_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&base);
+        let run = |label: &str, shift: f64, pdf_set: &str| {
+            let directory = base.join(label);
+            fs::create_dir_all(&directory).unwrap();
+            fs::write(
+                directory.join("config.json"),
+                format!(r#"{{"pdf_set": "{pdf_set}", "hadronization": true}}"#),
+            )
+            .unwrap();
+            let mut csv = String::from("event_weight,Q2,x,y,W2\n");
+            for index in 0..200 {
+                let u = (index as f64 + 0.5) / 200.0;
+                let y = 0.1 + 0.6 * u + shift;
+                csv.push_str(&format!(
+                    "1,{},{},{y},{}\n",
+                    10.0 + 90.0 * u,
+                    0.01 + u / 10.0,
+                    1e3 * u + 5.0
+                ));
+            }
+            fs::write(directory.join("inclusive_observables.csv"), csv).unwrap();
+            ComparedRun::load(&directory, label.to_owned()).unwrap()
+        };
+        let runs = [
+            run("shower", 0.0, "CT18LO"),
+            run("no_shower", 0.1, "NNPDF31_lo"),
+        ];
+        assert!(runs
+            .iter()
+            .all(|run| run.observables.as_ref().map(ObservableSample::events) == Some(200)));
+        let comparison = compare_runs(&runs, 8).unwrap();
+
+        let output = base.join("comparison");
+        let paths = write_run_comparison_artifacts(&output, &comparison).unwrap();
+        assert_eq!(paths.plots.len(), 4);
+        assert!(paths.plots[0].ends_with("q2.svg"));
+        let svg = fs::read_to_string(&paths.plots[2]).unwrap();
+        assert!(svg.contains("<svg"));
+        let labels: Vec<&str> = svg.lines().map(str::trim).collect();
+        assert!(labels.contains(&"no_shower"));
+
+        let diff = fs::read_to_string(&paths.diff_csv).unwrap();
+        let mut lines = diff.lines();
+        assert_eq!(lines.next(), Some("source,key,shower,no_shower"));
+        assert_eq!(
+            lines.next(),
+            Some("configuration,config.pdf_set,CT18LO,NNPDF31_lo")
+        );
+        assert_eq!(lines.next(), None);
+
+        let tests: Vec<BTreeMap<String, String>> = csv::Reader::from_path(&paths.tests_csv)
+            .unwrap()
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(tests.len(), 4);
+        assert_eq!(tests[2]["observable"], "y");
+        assert!(tests[2]["ks_p_value"].parse::<f64>().unwrap() < 0.05);
+        assert_eq!(
+            fs::read_to_string(&paths.distributions_csv)
+                .unwrap()
+                .lines()
+                .count(),
+            1 + 4 * 2 * 8
+        );
+        let decoded: Value = serde_json::from_reader(File::open(&paths.json).unwrap()).unwrap();
+        assert_eq!(decoded["reference"], "shower");
+
+        assert!(matches!(
+            write_run_comparison_artifacts(&output, &comparison),
+            Err(RunComparisonArtifactError::ArtifactExists { .. })
+        ));
+        let _ = fs::remove_dir_all(base);
+    }
+}
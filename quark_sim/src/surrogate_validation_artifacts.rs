@@ -0,0 +1,476 @@
+//! CSV, JSON, and SVG artifacts for the surrogate accuracy report.
+//!
+//! Like the APFEL-vs-LO validation artifacts, an existing output directory is
+//! accepted but no final artifact is ever overwritten.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use plotters::backend::SVGBackend;
+use plotters::prelude::*;
+
+use crate::physics::surrogate_validation::{SurrogateHeatMapCell, SurrogateValidationReport};
+
+pub const SURROGATE_VALIDATION_CSV_FILENAME: &str = "surrogate_points.csv";
+pub const SURROGATE_HEAT_MAP_CSV_FILENAME: &str = "surrogate_heat_map.csv";
+pub const SURROGATE_VALIDATION_JSON_FILENAME: &str = "surrogate_validation.json";
+pub const SURROGATE_VALIDATION_SVG_FILENAME: &str = "surrogate_heat_map.svg";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SurrogateValidationArtifactPaths {
+    pub points_csv: PathBuf,
+    pub heat_map_csv: PathBuf,
+    pub json: PathBuf,
+    pub svg: PathBuf,
+}
+
+impl SurrogateValidationArtifactPaths {
+    #[must_use]
+    pub fn in_directory(output_directory: &Path) -> Self {
+        Self {
+            points_csv: output_directory.join(SURROGATE_VALIDATION_CSV_FILENAME),
+            heat_map_csv: output_directory.join(SURROGATE_HEAT_MAP_CSV_FILENAME),
+            json: output_directory.join(SURROGATE_VALIDATION_JSON_FILENAME),
+            svg: output_directory.join(SURROGATE_VALIDATION_SVG_FILENAME),
+        }
+    }
+
+    fn as_array(&self) -> [&Path; 4] {
+        [&self.points_csv, &self.heat_map_csv, &self.json, &self.svg]
+    }
+}
+
+#[derive(Debug)]
+pub enum SurrogateValidationArtifactError {
+    EmptyOutputPath,
+    OutputIsNotDirectory {
+        path: PathBuf,
+    },
+    ArtifactExists {
+        path: PathBuf,
+    },
+    Io {
+        operation: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    Csv {
+        path: PathBuf,
+        source: csv::Error,
+    },
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    Plot {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl fmt::Display for SurrogateValidationArtifactError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyOutputPath => write!(
+                formatter,
+                "surrogate validation output path must not be empty"
+            ),
+            Self::OutputIsNotDirectory { path } => write!(
+                formatter,
+                "surrogate validation output path '{}' exists but is not a directory",
+                path.display()
+            ),
+            Self::ArtifactExists { path } => write!(
+                formatter,
+                "refusing to overwrite existing surrogate validation artifact '{}'",
+                path.display()
+            ),
+            Self::Io {
+                operation,
+                path,
+                source,
+            } => write!(
+                formatter,
+                "failed while {operation} '{}': {source}",
+                path.display()
+            ),
+            Self::Csv { path, source } => write!(
+                formatter,
+                "failed to serialize CSV '{}': {source}",
+                path.display()
+            ),
+            Self::Json { path, source } => write!(
+                formatter,
+                "failed to serialize JSON '{}': {source}",
+                path.display()
+            ),
+            Self::Plot { path, message } => write!(
+                formatter,
+                "failed to render SVG '{}': {message}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for SurrogateValidationArtifactError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Csv { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Write the per-point CSV, heat-map CSV, JSON summary and SVG heat map.
+pub fn write_surrogate_validation_artifacts(
+    output_directory: &Path,
+    report: &SurrogateValidationReport,
+) -> Result<SurrogateValidationArtifactPaths, SurrogateValidationArtifactError> {
+    prepare_output_directory(output_directory)?;
+    let paths = SurrogateValidationArtifactPaths::in_directory(output_directory);
+    for path in paths.as_array() {
+        if path.exists() {
+            return Err(SurrogateValidationArtifactError::ArtifactExists {
+                path: path.to_owned(),
+            });
+        }
+    }
+
+    // Render the plot in memory first so a plotting failure leaves no
+    // partially written artifact set behind.
+    let svg = render_svg(&paths.svg, report)?;
+
+    write_csv_rows(&paths.points_csv, &report.rows)?;
+    write_csv_rows(&paths.heat_map_csv, &report.heat_map)?;
+
+    let mut json = BufWriter::new(create_new_file(&paths.json)?);
+    serde_json::to_writer_pretty(&mut json, report).map_err(|source| {
+        SurrogateValidationArtifactError::Json {
+            path: paths.json.clone(),
+            source,
+        }
+    })?;
+    json.write_all(b"\n")
+        .and_then(|()| json.flush())
+        .map_err(|source| SurrogateValidationArtifactError::Io {
+            operation: "writing JSON",
+            path: paths.json.clone(),
+            source,
+        })?;
+
+    create_new_file(&paths.svg)?
+        .write_all(svg.as_bytes())
+        .map_err(|source| SurrogateValidationArtifactError::Io {
+            operation: "writing SVG",
+            path: paths.svg.clone(),
+            source,
+        })?;
+
+    Ok(paths)
+}
+
+fn prepare_output_directory(path: &Path) -> Result<(), SurrogateValidationArtifactError> {
+    if path.as_os_str().is_empty() {
+        return Err(SurrogateValidationArtifactError::EmptyOutputPath);
+    }
+    if path.exists() {
+        if !path.is_dir() {
+            return Err(SurrogateValidationArtifactError::OutputIsNotDirectory {
+                path: path.to_owned(),
+            });
+        }
+        return Ok(());
+    }
+    fs::create_dir_all(path).map_err(|source| SurrogateValidationArtifactError::Io {
+        operation: "creating surrogate validation output directory",
+        path: path.to_owned(),
+        source,
+    })
+}
+
+fn create_new_file(path: &Path) -> Result<File, SurrogateValidationArtifactError> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|source| {
+            if source.kind() == io::ErrorKind::AlreadyExists {
+                SurrogateValidationArtifactError::ArtifactExists {
+                    path: path.to_owned(),
+                }
+            } else {
+                SurrogateValidationArtifactError::Io {
+                    operation: "creating artifact",
+                    path: path.to_owned(),
+                    source,
+                }
+            }
+        })
+}
+
+fn write_csv_rows<T: serde::Serialize>(
+    path: &Path,
+    rows: &[T],
+) -> Result<(), SurrogateValidationArtifactError> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(true)
+        .from_writer(create_new_file(path)?);
+    for row in rows {
+        writer
+            .serialize(row)
+            .map_err(|source| SurrogateValidationArtifactError::Csv {
+                path: path.to_owned(),
+                source,
+            })?;
+    }
+    writer
+        .flush()
+        .map_err(|source| SurrogateValidationArtifactError::Io {
+            operation: "flushing CSV",
+            path: path.to_owned(),
+            source,
+        })
+}
+
+type CellValue = fn(&SurrogateHeatMapCell) -> Option<f64>;
+
+fn render_svg(
+    path: &Path,
+    report: &SurrogateValidationReport,
+) -> Result<String, SurrogateValidationArtifactError> {
+    let panels: [(&str, CellValue); 3] = [
+        ("max |ΔF₂| / F₂", |cell| cell.f2_max_relative_error),
+        ("max |ΔF_L| / F₂", |cell| cell.fl_max_error_over_f2),
+        ("max |ΔxF₃| / F₂", |cell| cell.xf3_max_error_over_f2),
+    ];
+    let [x_min, x_max] = report.metadata.x_range.map(f64::log10);
+    let [q2_min, q2_max] = report.metadata.q2_range_gev2.map(f64::log10);
+
+    let mut svg = String::new();
+    {
+        let drawing_area = SVGBackend::with_string(&mut svg, (1_500, 560)).into_drawing_area();
+        drawing_area
+            .fill(&WHITE)
+            .map_err(|error| plot_error(path, error))?;
+        let title = format!(
+            "Surrogate vs {} — {}/{} {} — {} {} points",
+            report.metadata.source_backend,
+            report.metadata.pdf_set,
+            report.metadata.pdf_member,
+            report.metadata.order,
+            report.evaluated_points,
+            report.metadata.config.sampling,
+        );
+        let drawing_area = drawing_area
+            .titled(&title, ("sans-serif", 24))
+            .map_err(|error| plot_error(path, error))?;
+
+        for (area, (label, value)) in drawing_area.split_evenly((1, 3)).iter().zip(panels) {
+            let (minimum, maximum) = log_error_range(report.heat_map.iter().filter_map(value));
+            let caption = format!("{label} (log₁₀ colour, range 1e{minimum:.1}..1e{maximum:.1})");
+            let mut chart = ChartBuilder::on(area)
+                .caption(caption, ("sans-serif", 16).into_font())
+                .margin(12)
+                .x_label_area_size(45)
+                .y_label_area_size(60)
+                .build_cartesian_2d(x_min..x_max, q2_min..q2_max)
+                .map_err(|error| plot_error(path, error))?;
+            chart
+                .configure_mesh()
+                .disable_mesh()
+                .x_desc("Bjorken x (log scale)")
+                .y_desc("Q² [GeV²] (log scale)")
+                .x_label_formatter(&|log_x| format!("{:.0e}", 10.0_f64.powf(*log_x)))
+                .y_label_formatter(&|log_q2| format!("{:.0e}", 10.0_f64.powf(*log_q2)))
+                .draw()
+                .map_err(|error| plot_error(path, error))?;
+            chart
+                .draw_series(report.heat_map.iter().map(|cell| {
+                    let corners = [
+                        (cell.x_low.log10(), cell.q2_low.log10()),
+                        (cell.x_high.log10(), cell.q2_high.log10()),
+                    ];
+                    let style = match value(cell) {
+                        Some(error) if error > 0.0 => {
+                            heat_colour(error.log10(), minimum, maximum).filled()
+                        }
+                        Some(_) => heat_colour(minimum, minimum, maximum).filled(),
+                        None => RGBColor(220, 220, 220).filled(),
+                    };
+                    Rectangle::new(corners, style)
+                }))
+                .map_err(|error| plot_error(path, error))?;
+        }
+
+        drawing_area
+            .present()
+            .map_err(|error| plot_error(path, error))?;
+    }
+    Ok(svg)
+}
+
+/// `log10` range of the positive errors, padded when all values coincide.
+fn log_error_range(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let mut minimum = f64::INFINITY;
+    let mut maximum = f64::NEG_INFINITY;
+    for value in values.filter(|value| *value > 0.0) {
+        minimum = minimum.min(value.log10());
+        maximum = maximum.max(value.log10());
+    }
+    if !minimum.is_finite() || !maximum.is_finite() {
+        return (-6.0, 0.0);
+    }
+    if maximum - minimum < 1.0e-9 {
+        return (minimum - 0.5, maximum + 0.5);
+    }
+    (minimum, maximum)
+}
+
+/// Blue-to-red colour scale over `[minimum, maximum]`.
+fn heat_colour(value: f64, minimum: f64, maximum: f64) -> HSLColor {
+    let fraction = ((value - minimum) / (maximum - minimum)).clamp(0.0, 1.0);
+    HSLColor((1.0 - fraction) * 240.0 / 360.0, 0.75, 0.5)
+}
+
+fn plot_error(path: &Path, error: impl fmt::Display) -> SurrogateValidationArtifactError {
+    SurrogateValidationArtifactError::Plot {
+        path: path.to_owned(),
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use crate::physics::surrogate_validation::{validate_surrogate, SurrogateValidationConfig};
+    use crate::physics::{
+        PerturbativeOrder, StructureFunctionBackend, StructureFunctionMetadata,
+        StructureFunctionProvider, StructureFunctionProviderError, StructureFunctionRequest,
+        StructureFunctionResult, SurrogateConfig, PHOTON_EXCHANGE_MODE,
+    };
+
+    struct MockProvider {
+        f2: f64,
+    }
+
+    impl StructureFunctionProvider for MockProvider {
+        fn evaluate(
+            &self,
+            request: &StructureFunctionRequest,
+        ) -> Result<StructureFunctionResult, StructureFunctionProviderError> {
+            Ok(StructureFunctionResult {
+                f2: self.f2 * (1.0 + request.q2.log10() / 100.0),
+                fl: 0.1,
+                xf3: 0.0,
+                uncertainty: None,
+                metadata: StructureFunctionMetadata {
+                    backend: StructureFunctionBackend::Surrogate,
+                    apfelxx_version: None,
+                    lhapdf_version: None,
+                    pdf_set: request.pdf_set.clone(),
+                    pdf_member: request.pdf_member,
+                    pdf_order_qcd: 1,
+                    pdf_data_version: 1,
+                    order: request.order,
+                    process: request.process,
+                    projectile: request.projectile,
+                    target: request.target,
+                    mu_f_over_q: request.mu_f_over_q,
+                    mu_r_over_q: request.mu_r_over_q,
+                    scheme: "test".to_owned(),
+                    electromagnetic_mode: PHOTON_EXCHANGE_MODE.to_owned(),
+                    os_arch: None,
+                    rust_version: None,
+                    git_commit: None,
+                    git_dirty: None,
+                    pythia_version: None,
+                    hepmc_version: None,
+                    python_env_hash: None,
+                },
+            })
+        }
+    }
+
+    fn report() -> SurrogateValidationReport {
+        let model = SurrogateConfig {
+            source_backend: "apfel".to_owned(),
+            pdf_set: "CT18NLO".to_owned(),
+            pdf_member: 0,
+            order: PerturbativeOrder::Nlo,
+            x_min: 1.0e-4,
+            x_max: 0.8,
+            q2_min: 3.5,
+            q2_max: 1.0e4,
+            mu_f_ratio_min: 0.5,
+            mu_f_ratio_max: 2.0,
+            mu_r_ratio_min: 0.5,
+            mu_r_ratio_max: 2.0,
+            input_mean: vec![0.0; 4],
+            input_std: vec![1.0; 4],
+            target_mean: vec![0.0; 3],
+            target_std: vec![1.0; 3],
+            validation_mse: 1.0e-3,
+            test_max_rel_error: 0.05,
+            ensemble_size: 1,
+            max_relative_uncertainty: None,
+            architecture: Default::default(),
+            constraints: Default::default(),
+        };
+        let config = SurrogateValidationConfig {
+            sample_count: 64,
+            x_bins: 3,
+            q2_bins: 2,
+            ..SurrogateValidationConfig::default()
+        };
+        validate_surrogate(
+            &MockProvider { f2: 1.01 },
+            &MockProvider { f2: 1.0 },
+            &model,
+            &config,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn writes_all_artifacts_once_and_refuses_to_overwrite() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let directory =
+            std::env::temp_dir().join(format!("quark_sim_surrogate_validation_{unique}"));
+        let report = report();
+
+        let paths = write_surrogate_validation_artifacts(&directory, &report).unwrap();
+
+        let points = fs::read_to_string(&paths.points_csv).unwrap();
+        assert_eq!(points.lines().count(), 65);
+        assert!(points.starts_with("x,q2,mu_f_over_q,mu_r_over_q,surrogate_f2"));
+        assert_eq!(
+            fs::read_to_string(&paths.heat_map_csv)
+                .unwrap()
+                .lines()
+                .count(),
+            7
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&paths.json).unwrap()).unwrap();
+        assert_eq!(json["evaluated_points"], 64);
+        assert!(json.get("rows").is_none());
+        assert!(fs::read_to_string(&paths.svg).unwrap().contains("<svg"));
+
+        assert!(matches!(
+            write_surrogate_validation_artifacts(&directory, &report),
+            Err(SurrogateValidationArtifactError::ArtifactExists { .. })
+        ));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,346 @@
+//! CSV and JSON artifacts for an unfolded inclusive spectrum.
+//!
+//! Like the validation artifacts, an existing output directory is accepted
+//! but no final artifact is ever overwritten.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::analysis::UnfoldingReport;
+
+pub const UNFOLDING_BINS_CSV_FILENAME: &str = "unfolded_bins.csv";
+pub const UNFOLDING_RESPONSE_CSV_FILENAME: &str = "response_matrix.csv";
+pub const UNFOLDING_COVARIANCE_CSV_FILENAME: &str = "unfolded_covariance.csv";
+pub const UNFOLDING_JSON_FILENAME: &str = "unfolding.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnfoldingArtifactPaths {
+    pub bins_csv: PathBuf,
+    pub response_csv: PathBuf,
+    pub covariance_csv: PathBuf,
+    pub json: PathBuf,
+}
+
+impl UnfoldingArtifactPaths {
+    #[must_use]
+    pub fn in_directory(output_directory: &Path) -> Self {
+        Self {
+            bins_csv: output_directory.join(UNFOLDING_BINS_CSV_FILENAME),
+            response_csv: output_directory.join(UNFOLDING_RESPONSE_CSV_FILENAME),
+            covariance_csv: output_directory.join(UNFOLDING_COVARIANCE_CSV_FILENAME),
+            json: output_directory.join(UNFOLDING_JSON_FILENAME),
+        }
+    }
+
+    fn as_array(&self) -> [&Path; 4] {
+        [
+            &self.bins_csv,
+            &self.response_csv,
+            &self.covariance_csv,
+            &self.json,
+        ]
+    }
+}
+
+#[derive(Debug)]
+pub enum UnfoldingArtifactError {
+    EmptyOutputPath,
+    OutputIsNotDirectory {
+        path: PathBuf,
+    },
+    ArtifactExists {
+        path: PathBuf,
+    },
+    Io {
+        operation: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    Csv {
+        path: PathBuf,
+        source: csv::Error,
+    },
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+impl fmt::Display for UnfoldingArtifactError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyOutputPath => write!(formatter, "unfolding output path must not be empty"),
+            Self::OutputIsNotDirectory { path } => write!(
+                formatter,
+                "unfolding output path '{}' exists but is not a directory",
+                path.display()
+            ),
+            Self::ArtifactExists { path } => write!(
+                formatter,
+                "refusing to overwrite existing unfolding artifact '{}'",
+                path.display()
+            ),
+            Self::Io {
+                operation,
+                path,
+                source,
+            } => write!(
+                formatter,
+                "failed while {operation} '{}': {source}",
+                path.display()
+            ),
+            Self::Csv { path, source } => write!(
+                formatter,
+                "failed to serialize CSV '{}': {source}",
+                path.display()
+            ),
+            Self::Json { path, source } => write!(
+                formatter,
+                "failed to serialize JSON '{}': {source}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for UnfoldingArtifactError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Csv { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ResponseRow {
+    truth_bin: usize,
+    reconstructed_bin: usize,
+    probability: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct CovarianceRow {
+    bin_i: usize,
+    bin_j: usize,
+    covariance: f64,
+}
+
+/// Write the per-bin table, the response probabilities, the covariance of
+/// the unfolded values and the full JSON report.
+pub fn write_unfolding_artifacts(
+    output_directory: &Path,
+    report: &UnfoldingReport,
+) -> Result<UnfoldingArtifactPaths, UnfoldingArtifactError> {
+    prepare_output_directory(output_directory)?;
+    let paths = UnfoldingArtifactPaths::in_directory(output_directory);
+    for path in paths.as_array() {
+        if path.exists() {
+            return Err(UnfoldingArtifactError::ArtifactExists {
+                path: path.to_owned(),
+            });
+        }
+    }
+
+    write_csv_rows(&paths.bins_csv, &report.bins)?;
+    let response_rows = report
+        .response_probabilities
+        .iter()
+        .enumerate()
+        .flat_map(|(reconstructed_bin, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(truth_bin, &probability)| ResponseRow {
+                    truth_bin,
+                    reconstructed_bin,
+                    probability,
+                })
+        })
+        .collect::<Vec<_>>();
+    write_csv_rows(&paths.response_csv, &response_rows)?;
+    let covariance_rows = report
+        .covariance
+        .iter()
+        .enumerate()
+        .flat_map(|(bin_i, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(bin_j, &covariance)| CovarianceRow {
+                    bin_i,
+                    bin_j,
+                    covariance,
+                })
+        })
+        .collect::<Vec<_>>();
+    write_csv_rows(&paths.covariance_csv, &covariance_rows)?;
+
+    let mut json = BufWriter::new(create_new_file(&paths.json)?);
+    serde_json::to_writer_pretty(&mut json, report).map_err(|source| {
+        UnfoldingArtifactError::Json {
+            path: paths.json.clone(),
+            source,
+        }
+    })?;
+    json.write_all(b"\n")
+        .and_then(|()| json.flush())
+        .map_err(|source| UnfoldingArtifactError::Io {
+            operation: "writing JSON",
+            path: paths.json.clone(),
+            source,
+        })?;
+
+    Ok(paths)
+}
+
+fn prepare_output_directory(path: &Path) -> Result<(), UnfoldingArtifactError> {
+    if path.as_os_str().is_empty() {
+        return Err(UnfoldingArtifactError::EmptyOutputPath);
+    }
+    if path.exists() {
+        if !path.is_dir() {
+            return Err(UnfoldingArtifactError::OutputIsNotDirectory {
+                path: path.to_owned(),
+            });
+        }
+        return Ok(());
+    }
+    fs::create_dir_all(path).map_err(|source| UnfoldingArtifactError::Io {
+        operation: "creating unfolding output directory",
+        path: path.to_owned(),
+        source,
+    })
+}
+
+fn create_new_file(path: &Path) -> Result<File, UnfoldingArtifactError> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|source| {
+            if source.kind() == io::ErrorKind::AlreadyExists {
+                UnfoldingArtifactError::ArtifactExists {
+                    path: path.to_owned(),
+                }
+            } else {
+                UnfoldingArtifactError::Io {
+                    operation: "creating artifact",
+                    path: path.to_owned(),
+                    source,
+                }
+            }
+        })
+}
+
+fn write_csv_rows<T: Serialize>(path: &Path, rows: &[T]) -> Result<(), UnfoldingArtifactError> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(true)
+        .from_writer(create_new_file(path)?);
+    for row in rows {
+        writer
+            .serialize(row)
+            .map_err(|source| UnfoldingArtifactError::Csv {
+                path: path.to_owned(),
+                source,
+            })?;
+    }
+    writer.flush().map_err(|source| UnfoldingArtifactError::Io {
+        operation: "flushing CSV",
+        path: path.to_owned(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{
+        KinematicBinning, ReconstructionMethod, ResponseMatrix, RunStatistics, Spectrum,
+        UnfoldedBin, UnfoldingMetadata, UnfoldingMethod,
+    };
+
+    #[test]
+    fn writes_bins_response_covariance_and_json_without_overwriting() {
+        let directory = std::env::temp_dir().join(format!(
+            "quark_sim_unfolding_artifacts_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+
+        let binning = KinematicBinning::new(vec![1e-3, 1e-2, 1e-1], vec![10.0, 100.0]).unwrap();
+        let mut response = ResponseMatrix::new(binning);
+        response.fill(Some((3e-3, 30.0)), Some((3e-3, 30.0)), 90.0);
+        response.fill(Some((3e-3, 30.0)), Some((3e-2, 30.0)), 10.0);
+        response.fill(Some((3e-2, 30.0)), Some((3e-2, 30.0)), 50.0);
+        response.s_gev2 = Some(4.0 * 27.5 * 920.0);
+        let measured = Spectrum::poisson(vec![180.0, 120.0]);
+        let method = UnfoldingMethod::IterativeBayes { iterations: 4 };
+        let result = response.unfold(&measured, method).unwrap();
+        let data_run = RunStatistics {
+            events: 300,
+            sum_of_weights: 300.0,
+            sum_of_weights_squared: 300.0,
+            cross_section: Some(crate::analysis::CrossSection {
+                value_pb: 600.0,
+                error_pb: 1.0,
+            }),
+        };
+        let report = UnfoldingReport::new(
+            UnfoldingMetadata {
+                method,
+                reconstruction: ReconstructionMethod::Electron,
+                detector: "test".to_owned(),
+                response_seed: 1,
+                data_seed: 2,
+                response_events: 150,
+                data_events: 300,
+                data_cross_section: data_run.cross_section,
+                s_gev2: response.s_gev2,
+            },
+            &response,
+            &measured,
+            &data_run,
+            &result,
+        );
+        assert!((report.bins[0].sigma_pb.unwrap() - 2.0 * result.values[0]).abs() < 1e-9);
+        assert!(report.bins[0].reduced_cross_section.unwrap() > 0.0);
+
+        let paths = write_unfolding_artifacts(&directory, &report).unwrap();
+        let rows: Vec<UnfoldedBin> = csv::Reader::from_path(&paths.bins_csv)
+            .unwrap()
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows, report.bins);
+        assert_eq!(
+            fs::read_to_string(&paths.response_csv)
+                .unwrap()
+                .lines()
+                .count(),
+            5
+        );
+        let decoded: UnfoldingReport =
+            serde_json::from_reader(File::open(&paths.json).unwrap()).unwrap();
+        assert_eq!(decoded.metadata, report.metadata);
+        assert_eq!(decoded.bins.len(), 2);
+        assert_eq!(
+            decoded.response_probabilities,
+            report.response_probabilities
+        );
+        assert!(fs::read_to_string(&paths.covariance_csv)
+            .unwrap()
+            .starts_with("bin_i,bin_j,covariance"));
+
+        assert!(matches!(
+            write_unfolding_artifacts(&directory, &report),
+            Err(UnfoldingArtifactError::ArtifactExists { .. })
+        ));
+        let _ = fs::remove_dir_all(directory);
+    }
+}
@@ -67,3 +67,743 @@ fn unphysical_cli_input_exits_with_an_error() {
     assert!(!output.status.success());
     assert!(stderr.contains("incoming electron energy must be positive"));
 }
+
+#[test]
+fn json_format_reports_structured_errors_and_exit_codes() {
+    let kinematics = |electron_energy: &str| {
+        binary()
+            .args([
+                "dis-kinematics",
+                "--format",
+                "json",
+                "--electron-energy",
+                electron_energy,
+                "--proton-energy",
+                "920.0",
+                "--scattered-electron-energy",
+                "15.0",
+                "--theta-deg",
+                "20.0",
+            ])
+            .output()
+            .expect("DIS command should start")
+    };
+
+    let output = kinematics("27.5");
+    assert!(output.status.success());
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!((result["y"].as_f64().unwrap() - 0.470992917430).abs() < 1e-12);
+
+    let output = kinematics("-1");
+    assert_eq!(output.status.code(), Some(4));
+    assert!(output.stdout.is_empty());
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "non_positive_energy");
+    assert_eq!(error["error"]["category"], "invalid_kinematics");
+    assert_eq!(error["error"]["field"], "electron-energy");
+
+    let output = kinematics("abc");
+    assert_eq!(output.status.code(), Some(2));
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "invalid_option");
+}
+
+#[test]
+fn generate_dis_events_reports_typed_errors_in_json() {
+    let output = std::env::temp_dir().join(format!("quark_sim_typed_{}", std::process::id()));
+    let generate = |extra: &[&str]| {
+        binary()
+            .env("PYTHIA_BACKEND_BIN", output.join("missing_pythia_dis_cli"))
+            .args([
+                "generate-dis-events",
+                "--format",
+                "json",
+                "--electron-energy",
+                "27.5",
+                "--proton-energy",
+                "920",
+                "--q2-min",
+                "10",
+                "--events",
+                "10",
+                "--pdf-set",
+                "CT18LO",
+            ])
+            .args(extra)
+            .arg("--output")
+            .arg(&output)
+            .output()
+            .expect("generate-dis-events command should start")
+    };
+
+    let missing = generate(&[]);
+    assert_eq!(missing.status.code(), Some(3));
+    let error: serde_json::Value = serde_json::from_slice(&missing.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "pythia_backend_missing");
+    assert_eq!(error["error"]["category"], "pythia_backend_missing");
+
+    let cuts = generate(&["--x-min", "0.5", "--x-max", "0.1"]);
+    assert_eq!(cuts.status.code(), Some(2));
+    let error: serde_json::Value = serde_json::from_slice(&cuts.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "invalid_option");
+    assert_eq!(error["error"]["field"], "x-min");
+    let _ = std::fs::remove_dir_all(output);
+}
+
+#[test]
+fn doctor_reports_missing_backends_in_json() {
+    let output = binary()
+        .args([
+            "doctor",
+            "--format",
+            "json",
+            "--apfel-backend",
+            "/definitely/missing/apfel_cli",
+            "--pythia-backend",
+            "/definitely/missing/pythia_dis_cli",
+        ])
+        .output()
+        .expect("doctor command should start");
+
+    assert_eq!(output.status.code(), Some(3));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["ok"], false);
+    assert!(report["build"]["git_hash"].is_string());
+    let checks = report["checks"].as_array().unwrap();
+    let apfel = checks
+        .iter()
+        .find(|check| check["name"] == "apfel_backend")
+        .unwrap();
+    assert_eq!(apfel["status"], "failed");
+    assert_eq!(apfel["category"], "apfel_backend_missing");
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "environment_check_failed");
+}
+
+#[test]
+fn pdf_info_reports_a_missing_set_in_json() {
+    let output = binary()
+        .args([
+            "pdf",
+            "info",
+            "--format",
+            "json",
+            "--pdf-set",
+            "Definitely_Missing_PDF_Set",
+        ])
+        .output()
+        .expect("pdf command should start");
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stdout.is_empty());
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["category"], "lhapdf_set_not_installed");
+    assert_eq!(error["error"]["field"], "pdf-set");
+}
+
+#[test]
+fn verify_run_reports_a_missing_manifest_in_json() {
+    let output = binary()
+        .args([
+            "verify-run",
+            "--format",
+            "json",
+            "--run",
+            "/definitely/missing/run",
+        ])
+        .output()
+        .expect("verify-run command should start");
+
+    assert_eq!(output.status.code(), Some(2));
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "manifest_unreadable");
+    assert_eq!(error["error"]["category"], "file_not_found");
+    assert_eq!(error["error"]["field"], "run");
+}
+
+#[test]
+fn compare_runs_writes_the_comparison_bundle() {
+    let base = std::env::temp_dir().join(format!("quark_sim_compare_runs_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    for (name, pdf_set, shift) in [("shower", "CT18LO", 0.0), ("no_shower", "CT18NLO", 0.05)] {
+        let run = base.join(name);
+        std::fs::create_dir_all(&run).unwrap();
+        std::fs::write(
+            run.join("config.json"),
+            format!(r#"{{"pdf_set": "{pdf_set}", "parton_shower": true}}"#),
+        )
+        .unwrap();
+        std::fs::write(run.join("summary.json"), r#"{"success": true}"#).unwrap();
+        let mut csv = String::from("event_number,event_weight,Q2,x,y,W2\n");
+        for index in 0..100 {
+            let u = (f64::from(index) + 0.5) / 100.0;
+            let x = 0.001 + 0.1 * u;
+            let q2 = 10.0 + 500.0 * u * u;
+            let w2 = q2 * (1.0 - x) / x;
+            csv.push_str(&format!(
+                "{index},1.0,{q2},{x},{},{w2}\n",
+                0.1 + 0.5 * u + shift
+            ));
+        }
+        std::fs::write(run.join("inclusive_observables.csv"), csv).unwrap();
+    }
+
+    let output = base.join("comparison");
+    let result = binary()
+        .args(["compare-runs", "--bins", "10", "--output"])
+        .arg(&output)
+        .arg("--run")
+        .arg(base.join("shower"))
+        .arg("--run")
+        .arg(base.join("no_shower"))
+        .output()
+        .expect("compare-runs command should start");
+    let stdout = String::from_utf8(result.stdout).expect("stdout should be UTF-8");
+
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+    assert!(stdout.contains("config.pdf_set"));
+    for file in [
+        "run_comparison.json",
+        "configuration_diff.csv",
+        "compatibility_tests.csv",
+        "distributions.csv",
+        "q2.svg",
+        "x.svg",
+        "y.svg",
+        "w2.svg",
+        "run_card.json",
+        "manifest.json",
+    ] {
+        assert!(output.join(file).is_file(), "missing {file}");
+    }
+    let comparison: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(output.join("run_comparison.json")).unwrap())
+            .unwrap();
+    assert_eq!(comparison["reference"], "shower");
+    assert_eq!(comparison["tests"].as_array().unwrap().len(), 4);
+
+    let missing = binary()
+        .args(["compare-runs", "--format", "json", "--output"])
+        .arg(base.join("again"))
+        .arg("--run")
+        .arg(base.join("shower"))
+        .arg("--run")
+        .arg(base.join("missing"))
+        .output()
+        .expect("compare-runs command should start");
+    assert_eq!(missing.status.code(), Some(2));
+    let error: serde_json::Value = serde_json::from_slice(&missing.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "run_unreadable");
+    let _ = std::fs::remove_dir_all(base);
+}
+
+#[cfg(unix)]
+#[test]
+fn generate_dis_events_merges_parallel_chunks() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let base = std::env::temp_dir().join(format!("quark_sim_jobs_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    std::fs::create_dir_all(&base).unwrap();
+    // Stands in for pythia_dis_cli: one weightless event per requested event,
+    // and chunk 1 fails on its first launch.
+    let backend = base.join("fake_pythia_dis_cli");
+    std::fs::write(
+        &backend,
+        r#"#!/bin/sh
+request=$(sed '/^}/q')
+events=$(printf '%s\n' "$request" | sed -n 's/.*"number_of_events": \([0-9]*\).*/\1/p')
+seed=$(printf '%s\n' "$request" | sed -n 's/.*"random_seed": \([0-9]*\).*/\1/p')
+case "$1" in
+  *chunk_001) [ -e "$1.failed" ] || { touch "$1.failed"; exit 4; } ;;
+esac
+{
+  printf 'HepMC::Version 3.02.06\nHepMC::Asciiv3-START_EVENT_LISTING\n'
+  i=1; while [ "$i" -le "$events" ]; do printf 'E %s 0 0\nU GEV MM\n' "$i"; i=$((i + 1)); done
+  printf 'HepMC::Asciiv3-END_EVENT_LISTING\n\n'
+} > "$1/events.hepmc3"
+{
+  printf 'event_number,event_weight,Q2\n'
+  i=0; while [ "$i" -lt "$events" ]; do printf '%s,1,%s\n' "$i" "$seed"; i=$((i + 1)); done
+} > "$1/inclusive_observables.csv"
+echo "fake PYTHIA log"
+printf '{"random_seed": %s, "accepted_event_count": %s}\n' "$seed" "$events" > "$1/metadata.json"
+printf '{"success": true, "requested_events": %s, "attempted_events": %s, "accepted_events": %s, "cross_section_pb": 10.0, "cross_section_error_pb": 1.0}\n' "$events" "$((2 * events))" "$events" > "$1/summary.json"
+printf '{"type":"progress","status":"completed","requested_events":%s,"attempted_events":%s,"accepted_events":%s,"failed_events":0,"vetoed_events":%s,"cross_section_pb":10.0,"cross_section_error_pb":1.0,"elapsed_seconds":0.1,"eta_seconds":null}\n' "$events" "$((2 * events))" "$events" "$events" >&2
+"#,
+    )
+    .unwrap();
+    std::fs::set_permissions(&backend, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = base.join("runs");
+    let result = binary()
+        .env("PYTHIA_BACKEND_BIN", &backend)
+        .args([
+            "generate-dis-events",
+            "--electron-energy",
+            "27.5",
+            "--proton-energy",
+            "920",
+            "--q2-min",
+            "10",
+            "--events",
+            "7",
+            "--pdf-set",
+            "CT18LO",
+            "--seed",
+            "42",
+            "--jobs",
+            "3",
+            "--output",
+        ])
+        .arg(&output)
+        .output()
+        .expect("generate-dis-events command should start");
+    let stdout = String::from_utf8(result.stdout).expect("stdout should be UTF-8");
+
+    assert!(
+        result.status.success(),
+        "{stdout}{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+    assert!(stdout.contains("Chunk 1 failed"));
+    assert!(stdout.contains("] 100% 7/7 accepted, 0 failed, 7 vetoed"));
+    assert!(stdout.contains("Cross section: 1.000000e1"));
+    let run = std::fs::read_dir(&output)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    assert!(!run.join("chunks").exists());
+    let log = std::fs::read_to_string(run.join("generator.log")).unwrap();
+    assert_eq!(log.matches("fake PYTHIA log").count(), 3);
+    assert!(!log.contains("\"type\":\"progress\""));
+    let summary: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(run.join("summary.json")).unwrap()).unwrap();
+    assert_eq!(summary["accepted_events"], 7);
+    assert_eq!(summary["jobs"], 3);
+    assert_eq!(summary["chunks"][1]["attempts"], 2);
+    let metadata: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(run.join("metadata.json")).unwrap()).unwrap();
+    assert_eq!(metadata["random_seed"], 42);
+    assert_eq!(metadata["chunk_seeds"].as_array().unwrap().len(), 3);
+
+    let csv = std::fs::read_to_string(run.join("inclusive_observables.csv")).unwrap();
+    let numbers: Vec<&str> = csv
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').next().unwrap())
+        .collect();
+    assert_eq!(numbers, ["0", "1", "2", "3", "4", "5", "6"]);
+    let events = std::fs::read_to_string(run.join("events.hepmc3")).unwrap();
+    assert_eq!(events.matches("\nE ").count(), 7);
+    assert!(events.contains("\nE 7 0 0"));
+    let manifest = std::fs::read_to_string(run.join("manifest.json")).unwrap();
+    assert!(manifest.contains("chunk_002_seed"));
+    let _ = std::fs::remove_dir_all(base);
+}
+
+/// Stands in for pythia_dis_cli: reports three accepted events, then waits
+/// for a control message and writes them as a cancelled run.
+#[cfg(unix)]
+fn write_cancellable_backend(base: &std::path::Path) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let backend = base.join("fake_pythia_dis_cli");
+    std::fs::write(
+        &backend,
+        r#"#!/bin/sh
+sed '/^}/q' > /dev/null
+progress='{"type":"progress","status":"%s","requested_events":1000,"attempted_events":4,"accepted_events":3,"failed_events":0,"vetoed_events":1,"elapsed_seconds":0.5,"eta_seconds":null}\n'
+printf "$progress" running >&2
+read -r message
+case "$message" in *'"cancel"'*) ;; *) exit 3 ;; esac
+printf 'HepMC::Version 3.02.06\nHepMC::Asciiv3-START_EVENT_LISTING\nHepMC::Asciiv3-END_EVENT_LISTING\n\n' > "$1/events.hepmc3"
+printf 'event_number,event_weight,Q2\n' > "$1/inclusive_observables.csv"
+printf '{"accepted_event_count": 3}\n' > "$1/metadata.json"
+printf '{"success": true, "cancelled": true, "requested_events": 1000, "accepted_events": 3}\n' > "$1/summary.json"
+printf "$progress" cancelled >&2
+"#,
+    )
+    .unwrap();
+    std::fs::set_permissions(&backend, std::fs::Permissions::from_mode(0o755)).unwrap();
+    backend
+}
+
+#[cfg(unix)]
+#[test]
+fn generate_dis_events_cancel_message_keeps_a_partial_run() {
+    use std::io::{BufRead, Write};
+
+    let base = std::env::temp_dir().join(format!("quark_sim_cancel_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    std::fs::create_dir_all(&base).unwrap();
+    let backend = write_cancellable_backend(&base);
+
+    let output = base.join("runs");
+    let mut child = binary()
+        .env("PYTHIA_BACKEND_BIN", &backend)
+        .args([
+            "generate-dis-events",
+            "--electron-energy",
+            "27.5",
+            "--proton-energy",
+            "920",
+            "--q2-min",
+            "10",
+            "--events",
+            "1000",
+            "--pdf-set",
+            "CT18LO",
+            "--seed",
+            "42",
+            "--progress",
+            "json",
+            "--output",
+        ])
+        .arg(&output)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("generate-dis-events command should start");
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = std::io::BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    while !line.starts_with(r#"{"type":"progress","status":"running""#) {
+        line.clear();
+        assert!(
+            stdout.read_line(&mut line).unwrap() > 0,
+            "no progress before the end of output"
+        );
+    }
+    writeln!(stdin, r#"{{"type": "cancel"}}"#).unwrap();
+    let mut rest = String::new();
+    std::io::Read::read_to_string(&mut stdout, &mut rest).unwrap();
+    let result = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&result.stderr);
+
+    assert!(!result.status.success());
+    assert!(rest.contains(r#"{"type":"progress","status":"cancelled""#));
+    assert!(rest.contains("DIS events generation was cancelled."));
+    assert!(stderr.contains("generation cancelled after 3 of 1000 events"));
+    let run = std::fs::read_dir(&output)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let summary: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(run.join("summary.json")).unwrap()).unwrap();
+    assert_eq!(summary["cancelled"], true);
+    assert!(run.join("manifest.json").is_file());
+    let _ = std::fs::remove_dir_all(base);
+}
+
+#[cfg(unix)]
+#[test]
+fn generate_dis_events_sigint_merges_the_cancelled_chunks() {
+    use std::io::BufRead;
+
+    let base = std::env::temp_dir().join(format!("quark_sim_sigint_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    std::fs::create_dir_all(&base).unwrap();
+    let backend = write_cancellable_backend(&base);
+
+    let output = base.join("runs");
+    let mut child = binary()
+        .env("PYTHIA_BACKEND_BIN", &backend)
+        .args([
+            "generate-dis-events",
+            "--electron-energy",
+            "27.5",
+            "--proton-energy",
+            "920",
+            "--q2-min",
+            "10",
+            "--events",
+            "1000",
+            "--pdf-set",
+            "CT18LO",
+            "--seed",
+            "42",
+            "--jobs",
+            "2",
+            "--progress",
+            "json",
+            "--output",
+        ])
+        .arg(&output)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("generate-dis-events command should start");
+    let mut stdout = std::io::BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    while !line.starts_with(r#"{"type":"progress","status":"running""#) {
+        line.clear();
+        assert!(
+            stdout.read_line(&mut line).unwrap() > 0,
+            "no progress before the end of output"
+        );
+    }
+    // Only the front end is signalled; it must forward the cancel request.
+    let signalled = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(signalled.success());
+    let mut rest = String::new();
+    std::io::Read::read_to_string(&mut stdout, &mut rest).unwrap();
+    let result = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&result.stderr);
+
+    assert_eq!(result.status.code(), Some(1), "stderr: {stderr}");
+    assert!(rest.contains("Merging 2 chunks..."));
+    assert!(rest.contains("DIS events generation was cancelled."));
+    assert!(stderr.contains("generation cancelled after 6 of 1000 events"));
+    let run = std::fs::read_dir(&output)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let summary: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(run.join("summary.json")).unwrap()).unwrap();
+    assert_eq!(summary["cancelled"], true);
+    assert_eq!(summary["accepted_events"], 6);
+    assert!(!run.join("chunks").exists());
+    assert!(run.join("manifest.json").is_file());
+    let _ = std::fs::remove_dir_all(base);
+}
+
+#[cfg(unix)]
+#[test]
+fn generate_dis_events_reports_a_rejected_pythia_setting_without_retrying() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let base = std::env::temp_dir().join(format!("quark_sim_settings_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    std::fs::create_dir_all(&base).unwrap();
+    // Stands in for pythia_dis_cli: rejects every request the way the backend
+    // rejects a setting PYTHIA does not know, and counts its launches.
+    let backend = base.join("fake_pythia_dis_cli");
+    std::fs::write(
+        &backend,
+        r#"#!/bin/sh
+sed '/^}/q' > /dev/null
+echo launch >> "$1/../launches"
+error='{"success":false,"error":{"code":"unknown_pythia_setting","message":"Unknown PYTHIA setting: PartonLevel:MPX = on","hint":"Check the setting name against the PYTHIA 8 manual."}}'
+printf '%s\n' "$error" > "$1/summary.json"
+printf '%s\n' "$error" >&2
+exit 2
+"#,
+    )
+    .unwrap();
+    std::fs::set_permissions(&backend, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = base.join("runs");
+    let result = binary()
+        .env("PYTHIA_BACKEND_BIN", &backend)
+        .args([
+            "generate-dis-events",
+            "--format",
+            "json",
+            "--electron-energy",
+            "27.5",
+            "--proton-energy",
+            "920",
+            "--q2-min",
+            "10",
+            "--events",
+            "10",
+            "--pdf-set",
+            "CT18LO",
+            "--seed",
+            "42",
+            "--process",
+            "cc",
+            "--pythia-setting",
+            "PartonLevel:MPX = on",
+            "--output",
+        ])
+        .arg(&output)
+        .output()
+        .expect("generate-dis-events command should start");
+    let stderr = String::from_utf8_lossy(&result.stderr);
+
+    assert_eq!(result.status.code(), Some(2), "{stderr}");
+    let error: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(error["error"]["code"], "unknown_pythia_setting");
+    assert_eq!(error["error"]["category"], "invalid_input");
+    assert_eq!(error["error"]["field"], "pythia-setting");
+    assert_eq!(
+        error["error"]["message"],
+        "Unknown PYTHIA setting: PartonLevel:MPX = on"
+    );
+    assert_eq!(
+        std::fs::read_to_string(output.join("launches")).unwrap(),
+        "launch\n"
+    );
+    let run = std::fs::read_dir(&output)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.is_dir())
+        .unwrap();
+    let config: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(run.join("config.json")).unwrap()).unwrap();
+    assert_eq!(config["process"], "charged_current_dis");
+    assert_eq!(
+        config["pythia_settings"],
+        serde_json::json!(["PartonLevel:MPX = on"])
+    );
+    let _ = std::fs::remove_dir_all(base);
+}
+
+#[cfg(unix)]
+#[test]
+fn generate_dis_events_repeats_a_run_from_its_config_json() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let base = std::env::temp_dir().join(format!("quark_sim_replay_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    std::fs::create_dir_all(&base).unwrap();
+    // Stands in for pythia_dis_cli: an empty but complete run.
+    let backend = base.join("fake_pythia_dis_cli");
+    std::fs::write(
+        &backend,
+        r#"#!/bin/sh
+sed '/^}/q' > /dev/null
+printf 'HepMC::Version 3.02.06\nHepMC::Asciiv3-START_EVENT_LISTING\nHepMC::Asciiv3-END_EVENT_LISTING\n\n' > "$1/events.hepmc3"
+printf 'event_number,event_weight,Q2\n' > "$1/inclusive_observables.csv"
+printf '{"accepted_event_count": 0}\n' > "$1/metadata.json"
+printf '{"success": true, "requested_events": 25, "accepted_events": 0}\n' > "$1/summary.json"
+"#,
+    )
+    .unwrap();
+    std::fs::set_permissions(&backend, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let run_once = |args: &[&str], output: &std::path::Path| {
+        let result = binary()
+            .env("PYTHIA_BACKEND_BIN", &backend)
+            .args(args)
+            .args(["--progress", "none", "--output"])
+            .arg(output)
+            .output()
+            .expect("generate-dis-events command should start");
+        assert!(
+            result.status.success(),
+            "{}",
+            String::from_utf8_lossy(&result.stderr)
+        );
+        std::fs::read_dir(output)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path()
+    };
+    let original = run_once(
+        &[
+            "generate-dis-events",
+            "--electron-energy",
+            "18",
+            "--proton-energy",
+            "275",
+            "--q2-min",
+            "25",
+            "--x-max",
+            "0.5",
+            "--events",
+            "25",
+            "--pdf-set",
+            "CT18LO",
+            "--hadronization",
+            "false",
+            "--pythia-setting",
+            "PartonLevel:MPI = on",
+        ],
+        &base.join("original"),
+    );
+    let config = original.join("config.json");
+    let repeated = run_once(
+        &["generate-dis-events", "--config", config.to_str().unwrap()],
+        &base.join("repeated"),
+    );
+
+    assert_eq!(
+        std::fs::read_to_string(repeated.join("config.json")).unwrap(),
+        std::fs::read_to_string(&config).unwrap()
+    );
+    let card: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(repeated.join("run_card.json")).unwrap()).unwrap();
+    assert_eq!(
+        card["options"]["pythia-setting"],
+        serde_json::json!(["PartonLevel:MPI = on"])
+    );
+    let _ = std::fs::remove_dir_all(base);
+}
+
+#[test]
+fn analysis_commands_reject_charged_current_runs() {
+    let base = std::env::temp_dir().join(format!("quark_sim_cc_analysis_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    std::fs::create_dir_all(&base).unwrap();
+    std::fs::write(
+        base.join("metadata.json"),
+        r#"{"process": "charged_current_dis"}"#,
+    )
+    .unwrap();
+    let events = base.join("events.hepmc3");
+    let events = events.to_str().unwrap();
+    let output = base.join("out");
+    let output = output.to_str().unwrap();
+    let commands: [(&[&str], &str); 2] = [
+        (
+            &["analyze-events", "--input", events, "--output", output],
+            "input",
+        ),
+        (
+            &[
+                "unfold",
+                "--response",
+                events,
+                "--data",
+                events,
+                "--detector",
+                events,
+                "--output",
+                output,
+                "--x-bins",
+                "0.01,0.1",
+                "--q2-bins",
+                "10,100",
+            ],
+            "response",
+        ),
+    ];
+
+    for (args, field) in commands {
+        let result = binary()
+            .args(&args[..1])
+            .args(["--format", "json"])
+            .args(&args[1..])
+            .output()
+            .expect("analysis command should start");
+        let stderr = String::from_utf8_lossy(&result.stderr);
+
+        assert_eq!(result.status.code(), Some(2), "{}: {stderr}", args[0]);
+        let error: serde_json::Value =
+            serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+        assert_eq!(error["error"]["code"], "unsupported_process");
+        assert_eq!(error["error"]["category"], "invalid_input");
+        assert_eq!(error["error"]["field"], field);
+    }
+    let _ = std::fs::remove_dir_all(base);
+}